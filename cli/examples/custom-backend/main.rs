@@ -15,12 +15,13 @@
 use std::any::Any;
 use std::io::Read;
 use std::path::Path;
+use std::time::SystemTime;
 
 use jj_cli::cli_util::{CliRunner, CommandError, CommandHelper};
 use jj_cli::ui::Ui;
 use jj_lib::backend::{
     Backend, BackendInitError, BackendLoadError, BackendResult, ChangeId, Commit, CommitId,
-    Conflict, ConflictId, FileId, SymlinkId, Tree, TreeId,
+    Conflict, ConflictId, FileId, SigningFn, SymlinkId, Tree, TreeId,
 };
 use jj_lib::git_backend::GitBackend;
 use jj_lib::repo::StoreFactories;
@@ -151,7 +152,15 @@ impl Backend for JitBackend {
         self.inner.read_commit(id)
     }
 
-    fn write_commit(&self, contents: Commit) -> BackendResult<(CommitId, Commit)> {
-        self.inner.write_commit(contents)
+    fn write_commit(
+        &self,
+        contents: Commit,
+        sign_with: Option<&mut SigningFn>,
+    ) -> BackendResult<(CommitId, Commit)> {
+        self.inner.write_commit(contents, sign_with)
+    }
+
+    fn gc(&self, heads: &[CommitId], keep_newer: SystemTime) -> BackendResult<()> {
+        self.inner.gc(heads, keep_newer)
     }
 }
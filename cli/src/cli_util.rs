@@ -16,6 +16,7 @@ use std::collections::{HashSet, VecDeque};
 use std::env::{self, ArgsOs, VarError};
 use std::ffi::{OsStr, OsString};
 use std::fmt::Debug;
+use std::io::Read as _;
 use std::iter;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
@@ -30,8 +31,11 @@ use clap::{Arg, ArgAction, ArgMatches, Command, FromArgMatches};
 use git2::{Oid, Repository};
 use indexmap::IndexSet;
 use itertools::Itertools;
+use jj_lib::absorb::AbsorbError;
+use jj_lib::annotate::AnnotateError;
 use jj_lib::backend::{BackendError, ChangeId, CommitId, MergedTreeId, ObjectId};
 use jj_lib::commit::Commit;
+use jj_lib::fileset::{self, FilesetExpression, FilesetParseContext, FilesetParseError};
 use jj_lib::git::{
     FailedRefExport, FailedRefExportReason, GitConfigParseError, GitExportError, GitImportError,
     GitRemoteManagementError,
@@ -40,7 +44,7 @@ use jj_lib::git_backend::GitBackend;
 use jj_lib::gitignore::GitIgnoreFile;
 use jj_lib::hex_util::to_reverse_hex;
 use jj_lib::id_prefix::IdPrefixContext;
-use jj_lib::matchers::{EverythingMatcher, Matcher, PrefixMatcher, Visit};
+use jj_lib::matchers::{Matcher, Visit};
 use jj_lib::merged_tree::{MergedTree, MergedTreeBuilder};
 use jj_lib::op_heads_store::{self, OpHeadResolutionError, OpHeadsStore};
 use jj_lib::op_store::{OpStore, OpStoreError, OperationId, RefTarget, WorkspaceId};
@@ -53,14 +57,16 @@ use jj_lib::repo_path::{FsPathParseError, RepoPath};
 use jj_lib::revset::{
     DefaultSymbolResolver, Revset, RevsetAliasesMap, RevsetEvaluationError, RevsetExpression,
     RevsetIteratorExt, RevsetParseContext, RevsetParseError, RevsetParseErrorKind,
-    RevsetResolutionError, RevsetWorkspaceContext,
+    RevsetResolutionError, RevsetWorkspaceContext, StringPatternParseError,
 };
 use jj_lib::settings::{ConfigResultExt as _, UserSettings};
+use jj_lib::sparse::{SparsePattern, SparsePatternParseError};
+use jj_lib::time_util::DatePatternContext;
 use jj_lib::transaction::Transaction;
 use jj_lib::tree::TreeMergeError;
 use jj_lib::working_copy::{
-    CheckoutStats, LockedWorkingCopy, ResetError, SnapshotError, SnapshotOptions, TreeStateError,
-    WorkingCopy,
+    CheckoutOptions, CheckoutStats, LockedWorkingCopy, ResetError, SnapshotError, SnapshotOptions,
+    TreeStateError, WorkingCopy,
 };
 use jj_lib::workspace::{Workspace, WorkspaceInitError, WorkspaceLoadError, WorkspaceLoader};
 use jj_lib::{dag_walk, file_util, git, revset};
@@ -75,7 +81,7 @@ use crate::config::{
     new_config_path, AnnotatedValue, CommandNameAndArgs, ConfigSource, LayeredConfigs,
 };
 use crate::formatter::{FormatRecorder, Formatter, PlainTextFormatter};
-use crate::merge_tools::{ConflictResolveError, DiffEditError, DiffGenerateError};
+use crate::merge_tools::{ConflictResolveError, DiffEditError, DiffGenerateError, HunkSelection};
 use crate::template_parser::{TemplateAliasesMap, TemplateParseError};
 use crate::templater::Template;
 use crate::ui::{ColorChoice, Ui};
@@ -227,6 +233,24 @@ impl From<TreeMergeError> for CommandError {
     }
 }
 
+impl From<AbsorbError> for CommandError {
+    fn from(err: AbsorbError) -> Self {
+        match err {
+            AbsorbError::Backend(err) => err.into(),
+            AbsorbError::TreeMerge(err) => err.into(),
+        }
+    }
+}
+
+impl From<AnnotateError> for CommandError {
+    fn from(err: AnnotateError) -> Self {
+        match err {
+            AnnotateError::Backend(err) => err.into(),
+            AnnotateError::RevsetEvaluation(err) => err.into(),
+        }
+    }
+}
+
 impl From<OpStoreError> for CommandError {
     fn from(err: OpStoreError) -> Self {
         CommandError::InternalError(format!("Failed to load an operation: {err}"))
@@ -352,6 +376,7 @@ impl From<RevsetResolutionError> for CommandError {
             | RevsetResolutionError::WorkspaceMissingWorkingCopy { .. }
             | RevsetResolutionError::AmbiguousCommitIdPrefix(_)
             | RevsetResolutionError::AmbiguousChangeIdPrefix(_)
+            | RevsetResolutionError::InvalidOperation { .. }
             | RevsetResolutionError::StoreError(_) => None,
         };
 
@@ -375,12 +400,33 @@ impl From<FsPathParseError> for CommandError {
     }
 }
 
+impl From<FilesetParseError> for CommandError {
+    fn from(err: FilesetParseError) -> Self {
+        user_error(format!("Failed to parse fileset: {err}"))
+    }
+}
+
+impl From<SparsePatternParseError> for CommandError {
+    fn from(err: SparsePatternParseError) -> Self {
+        user_error(format!("Failed to parse sparse pattern: {err}"))
+    }
+}
+
 impl From<glob::PatternError> for CommandError {
     fn from(err: glob::PatternError) -> Self {
         user_error(format!("Failed to compile glob: {err}"))
     }
 }
 
+impl From<StringPatternParseError> for CommandError {
+    fn from(err: StringPatternParseError) -> Self {
+        match err {
+            StringPatternParseError::GlobPattern(err) => err.into(),
+            err => user_error(format!("Failed to parse string pattern: {err}")),
+        }
+    }
+}
+
 impl From<clap::Error> for CommandError {
     fn from(err: clap::Error) -> Self {
         CommandError::ClapCliError(Arc::new(err))
@@ -704,6 +750,7 @@ pub struct WorkspaceCommandHelper {
     user_repo: ReadonlyUserRepo,
     revset_aliases_map: RevsetAliasesMap,
     template_aliases_map: TemplateAliasesMap,
+    allow_filesets: bool,
     may_update_working_copy: bool,
     working_copy_shared_with_git: bool,
 }
@@ -729,6 +776,7 @@ impl WorkspaceCommandHelper {
             &template_aliases_map,
             &command.settings,
         )?;
+        let allow_filesets = command.settings.config().get_bool("ui.allow-filesets")?;
         let loaded_at_head = command.global_args.at_operation == "@";
         let may_update_working_copy = loaded_at_head && !command.global_args.ignore_working_copy;
         let working_copy_shared_with_git = is_colocated_git_workspace(&workspace, &repo);
@@ -741,6 +789,7 @@ impl WorkspaceCommandHelper {
             user_repo: ReadonlyUserRepo::new(repo),
             revset_aliases_map,
             template_aliases_map,
+            allow_filesets,
             may_update_working_copy,
             working_copy_shared_with_git,
         })
@@ -871,6 +920,10 @@ impl WorkspaceCommandHelper {
         Ok(())
     }
 
+    pub fn settings(&self) -> &UserSettings {
+        &self.settings
+    }
+
     pub fn repo(&self) -> &Arc<ReadonlyRepo> {
         &self.user_repo.repo
     }
@@ -933,19 +986,65 @@ impl WorkspaceCommandHelper {
         RepoPath::parse_fs_path(&self.cwd, self.workspace_root(), input)
     }
 
-    pub fn matcher_from_values(&self, values: &[String]) -> Result<Box<dyn Matcher>, CommandError> {
+    pub fn format_sparse_pattern(&self, pattern: &SparsePattern) -> String {
+        pattern.to_fs_string(&self.cwd, self.workspace_root())
+    }
+
+    /// Parses a sparse pattern, whose leading path components are relative to
+    /// cwd.
+    pub fn parse_sparse_pattern(
+        &self,
+        input: &str,
+    ) -> Result<SparsePattern, SparsePatternParseError> {
+        SparsePattern::parse_fs_pattern(&self.cwd, self.workspace_root(), input)
+    }
+
+    /// Parses the given fileset expressions and concatenates them all.
+    ///
+    /// If `ui.allow-filesets` is disabled, each value is parsed as a plain path
+    /// instead.
+    pub fn parse_file_patterns(
+        &self,
+        values: &[String],
+    ) -> Result<FilesetExpression, CommandError> {
         if values.is_empty() {
-            Ok(Box::new(EverythingMatcher))
+            Ok(FilesetExpression::all())
         } else {
-            // TODO: Add support for globs and other formats
-            let paths: Vec<_> = values
+            let ctx = FilesetParseContext {
+                cwd: &self.cwd,
+                workspace_root: self.workspace_root(),
+            };
+            let expressions: Vec<_> = values
                 .iter()
-                .map(|v| self.parse_file_path(v))
+                .map(|v| {
+                    if self.allow_filesets {
+                        fileset::parse_maybe_bare(v, &ctx)
+                    } else {
+                        fileset::parse_prefix_path(v, &ctx)
+                    }
+                })
                 .try_collect()?;
-            Ok(Box::new(PrefixMatcher::new(&paths)))
+            Ok(FilesetExpression::union_all(expressions))
         }
     }
 
+    pub fn matcher_from_values(&self, values: &[String]) -> Result<Box<dyn Matcher>, CommandError> {
+        Ok(self.parse_file_patterns(values)?.to_matcher())
+    }
+
+    /// Parses the argument of `--hunks`. `-` means that a patch with the hunks
+    /// to select is read from stdin.
+    pub fn parse_hunk_selection(&self, value: &str) -> Result<HunkSelection, CommandError> {
+        let selection = if value == "-" {
+            let mut patch = String::new();
+            std::io::stdin().read_to_string(&mut patch)?;
+            HunkSelection::parse_patch(&patch)
+        } else {
+            HunkSelection::parse_spec(value, &self.cwd, self.workspace_root())
+        };
+        selection.map_err(|err| user_error(err.to_string()))
+    }
+
     pub fn git_config(&self) -> Result<git2::Config, git2::Error> {
         if let Some(git_backend) = self.git_backend() {
             git_backend.git_repo().config()
@@ -1115,6 +1214,14 @@ impl WorkspaceCommandHelper {
                     RevsetExpression::Filter(_) => false,
                     RevsetExpression::AsFilter(expression) => has_legacy_rule(expression),
                     RevsetExpression::Present(expression) => has_legacy_rule(expression),
+                    RevsetExpression::AtOperation {
+                        operation: _,
+                        candidates,
+                    } => has_legacy_rule(candidates),
+                    RevsetExpression::WithinVisibility {
+                        candidates,
+                        visible_heads: _,
+                    } => has_legacy_rule(candidates),
                     RevsetExpression::NotIn(expression) => has_legacy_rule(expression),
                     RevsetExpression::Union(expression1, expression2) => {
                         has_legacy_rule(expression1) || has_legacy_rule(expression2)
@@ -1152,10 +1259,14 @@ impl WorkspaceCommandHelper {
             cwd: &self.cwd,
             workspace_id: self.workspace_id(),
             workspace_root: self.workspace.workspace_root(),
+            allow_filesets: self.allow_filesets,
         };
         RevsetParseContext {
             aliases_map: &self.revset_aliases_map,
             user_email: self.settings.user_email(),
+            date_pattern_context: DatePatternContext::from_timestamp(
+                &self.settings.signature().timestamp,
+            ),
             workspace: Some(workspace_context),
         }
     }
@@ -1166,9 +1277,30 @@ impl WorkspaceCommandHelper {
             Box::new(|repo, prefix| id_prefix_context.resolve_commit_prefix(repo, prefix));
         let change_id_resolver: revset::PrefixResolver<Vec<CommitId>> =
             Box::new(|repo, prefix| id_prefix_context.resolve_change_prefix(repo, prefix));
+        let operation_resolver: revset::OperationResolver = Box::new(|op_str| {
+            let to_resolution_error = |err: CommandError| {
+                let message = match err {
+                    CommandError::UserError { message, .. }
+                    | CommandError::InternalError(message) => message,
+                    err => format!("{err:?}"),
+                };
+                RevsetResolutionError::InvalidOperation {
+                    name: op_str.to_owned(),
+                    message,
+                }
+            };
+            let operation = self
+                .resolve_single_op(op_str)
+                .map_err(to_resolution_error)?;
+            self.repo()
+                .loader()
+                .load_at(&operation)
+                .map_err(|err| to_resolution_error(err.into()))
+        });
         DefaultSymbolResolver::new(self.repo().as_ref())
             .with_commit_id_resolver(commit_id_resolver)
             .with_change_id_resolver(change_id_resolver)
+            .with_operation_resolver(operation_resolver)
     }
 
     pub fn id_prefix_context(&self) -> &IdPrefixContext {
@@ -1230,13 +1362,51 @@ impl WorkspaceCommandHelper {
         template.format(commit, formatter)
     }
 
-    pub fn check_rewritable(&self, commit: &Commit) -> Result<(), CommandError> {
-        if commit.id() == self.repo().store().root_commit_id() {
+    /// Checks that `commits` may be rewritten, i.e. that none of them is the
+    /// root commit or an ancestor of `immutable_heads()`.
+    ///
+    /// Since the set of immutable commits is closed under ancestors, this also
+    /// guarantees that rebasing the descendants of `commits` won't rewrite any
+    /// immutable commit.
+    pub fn check_rewritable<'a>(
+        &self,
+        commits: impl IntoIterator<Item = &'a Commit>,
+    ) -> Result<(), CommandError> {
+        let commit_ids = commits
+            .into_iter()
+            .map(|commit| commit.id().clone())
+            .collect_vec();
+        if commit_ids.contains(self.repo().store().root_commit_id()) {
             return Err(user_error("Cannot rewrite the root commit"));
         }
+        if commit_ids.is_empty() || self.global_args.ignore_immutable {
+            return Ok(());
+        }
+        let immutable_heads = self.immutable_heads_expression()?;
+        let revset = self.evaluate_revset(
+            RevsetExpression::commits(commit_ids).intersection(&immutable_heads.ancestors()),
+        )?;
+        if let Some(commit_id) = revset.iter().next() {
+            return Err(user_error_with_hint(
+                format!("Commit {} is immutable", short_commit_hash(&commit_id)),
+                "Configure the set of immutable commits via \
+                 `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it \
+                 anyway.",
+            ));
+        }
         Ok(())
     }
 
+    /// Parses the `immutable_heads()` revset alias.
+    pub fn immutable_heads_expression(&self) -> Result<Rc<RevsetExpression>, CommandError> {
+        self.parse_revset("immutable_heads()", None).map_err(|err| {
+            user_error(format!(
+                "Invalid `revset-aliases.immutable_heads()`: {}",
+                err
+            ))
+        })
+    }
+
     pub fn check_non_empty(&self, commits: &[Commit]) -> Result<(), CommandError> {
         if commits.is_empty() {
             return Err(user_error("Empty revision set"));
@@ -1287,12 +1457,16 @@ See https://github.com/martinvonz/jj/blob/main/docs/working-copy.md#stale-workin
                 ));
             }
             Err(StaleWorkingCopyError::SiblingOperation) => {
-                return Err(CommandError::InternalError(format!(
-                    "The repo was loaded at operation {}, which seems to be a sibling of the \
-                     working copy's operation {}",
-                    short_operation_hash(repo.op_id()),
-                    short_operation_hash(&old_op_id)
-                )));
+                // This can happen if the working-copy operation was abandoned.
+                return Err(user_error_with_hint(
+                    format!(
+                        "The repo was loaded at operation {}, which seems to be a sibling of the \
+                         working copy's operation {}",
+                        short_operation_hash(repo.op_id()),
+                        short_operation_hash(&old_op_id)
+                    ),
+                    "Run `jj workspace update-stale` to update the working copy.",
+                ));
             }
             Err(StaleWorkingCopyError::UnrelatedOperation) => {
                 return Err(CommandError::InternalError(format!(
@@ -1310,6 +1484,7 @@ See https://github.com/martinvonz/jj/blob/main/docs/working-copy.md#stale-workin
             fsmonitor_kind: self.settings.fsmonitor_kind()?,
             progress: progress.as_ref().map(|x| x as _),
             max_new_file_size: self.settings.max_new_file_size()?,
+            conflict_marker_style: self.settings.conflict_marker_style()?,
         })?;
         drop(progress);
         if new_tree_id != *wc_commit.tree_id() {
@@ -1360,11 +1535,15 @@ See https://github.com/martinvonz/jj/blob/main/docs/working-copy.md#stale-workin
                 return Ok(());
             }
         };
+        let options = CheckoutOptions {
+            conflict_marker_style: self.settings.conflict_marker_style()?,
+        };
         let stats = update_working_copy(
             &self.user_repo.repo,
             self.workspace.working_copy_mut(),
             maybe_old_commit,
             &new_commit,
+            &options,
         )?;
         if Some(&new_commit) != maybe_old_commit {
             ui.write("Working copy now at: ")?;
@@ -1501,6 +1680,17 @@ impl WorkspaceCommandTransaction<'_> {
         )?)
     }
 
+    pub fn select_hunks(
+        &self,
+        left_tree: &MergedTree,
+        right_tree: &MergedTree,
+        selection: &HunkSelection,
+    ) -> Result<MergedTreeId, CommandError> {
+        Ok(crate::merge_tools::select_hunks(
+            left_tree, right_tree, selection,
+        )?)
+    }
+
     pub fn select_diff(
         &self,
         ui: &Ui,
@@ -1940,6 +2130,7 @@ pub fn update_working_copy(
     wc: &mut WorkingCopy,
     old_commit: Option<&Commit>,
     new_commit: &Commit,
+    options: &CheckoutOptions,
 ) -> Result<Option<CheckoutStats>, CommandError> {
     let old_tree_id = old_commit.map(|commit| commit.tree_id().clone());
     let stats = if Some(new_commit.tree_id()) != old_tree_id.as_ref() {
@@ -1947,7 +2138,12 @@ pub fn update_working_copy(
         // warning for most commands (but be an error for the checkout command)
         let new_tree = new_commit.tree()?;
         let stats = wc
-            .check_out(repo.op_id().clone(), old_tree_id.as_ref(), &new_tree)
+            .check_out(
+                repo.op_id().clone(),
+                old_tree_id.as_ref(),
+                &new_tree,
+                options,
+            )
             .map_err(|err| {
                 CommandError::InternalError(format!(
                     "Failed to check out commit {}: {}",
@@ -2236,6 +2432,14 @@ pub struct GlobalArgs {
     /// implies `--ignore-working-copy`.
     #[arg(long, global = true, help_heading = "Global Options")]
     pub ignore_working_copy: bool,
+    /// Allow rewriting immutable commits
+    ///
+    /// By default, Jujutsu refuses to rewrite commits that are ancestors of
+    /// `immutable_heads()` (as configured in `revset-aliases`), such as the
+    /// commits already on the trunk. This flag lifts that restriction. The
+    /// root commit can never be rewritten.
+    #[arg(long, global = true, help_heading = "Global Options")]
+    pub ignore_immutable: bool,
     /// Operation to load the repo at
     ///
     /// Operation to load the repo at. By default, Jujutsu loads the repo at the
@@ -0,0 +1,108 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+
+use jj_lib::absorb::{absorb_hunks, split_hunks_to_trees};
+use jj_lib::backend::ObjectId;
+use jj_lib::revset::RevsetExpression;
+use tracing::instrument;
+
+use crate::cli_util::{CommandError, CommandHelper, RevisionArg};
+use crate::ui::Ui;
+
+/// Move changes from a revision into the ancestors that last modified them
+///
+/// Each hunk in the source revision is compared with the lines it replaces.
+/// If all of those lines were last modified by the same mutable ancestor, the
+/// hunk is squashed into that ancestor. Lines added in the source go where
+/// the lines around them were last modified. Hunks whose destination is
+/// ambiguous, as well as added and removed files, are left in the source.
+///
+/// Only ancestors reachable without passing through a merge commit are
+/// considered. Descendants of the rewritten ancestors, including the source,
+/// are rebased. If the source becomes empty and has no description, it's
+/// abandoned.
+#[derive(clap::Args, Clone, Debug)]
+pub struct AbsorbArgs {
+    /// The revision to move changes from
+    #[arg(long, short, default_value = "@")]
+    from: RevisionArg,
+    /// The revisions the changes may be moved into [default: all mutable
+    /// ancestors]
+    #[arg(long, short = 't', visible_alias = "to", value_name = "REVISIONS")]
+    into: Option<RevisionArg>,
+    /// Move only changes to these paths (instead of all paths)
+    #[arg(value_hint = clap::ValueHint::AnyPath)]
+    paths: Vec<String>,
+}
+
+#[instrument(skip_all)]
+pub fn cmd_absorb(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &AbsorbArgs,
+) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let source = workspace_command.resolve_single_rev(&args.from, ui)?;
+    workspace_command.check_rewritable([&source])?;
+    let matcher = workspace_command.matcher_from_values(&args.paths)?;
+
+    let mut destinations_expression = RevsetExpression::commit(source.id().clone())
+        .parents()
+        .ancestors()
+        .minus(&RevsetExpression::root());
+    if let Some(into) = &args.into {
+        let into_expression = workspace_command.parse_revset(into, Some(ui))?;
+        destinations_expression = destinations_expression.intersection(&into_expression);
+    }
+    if !command.global_args().ignore_immutable {
+        let immutable_heads = workspace_command.immutable_heads_expression()?;
+        destinations_expression = destinations_expression.minus(&immutable_heads.ancestors());
+    }
+    let destinations: HashSet<_> = workspace_command
+        .evaluate_revset(destinations_expression)?
+        .iter()
+        .collect();
+
+    let selected_trees = split_hunks_to_trees(
+        workspace_command.repo().as_ref(),
+        &source,
+        &destinations,
+        matcher.as_ref(),
+    )?;
+    if selected_trees.is_empty() {
+        writeln!(ui, "Nothing changed.")?;
+        return Ok(());
+    }
+
+    let mut tx =
+        workspace_command.start_transaction(&format!("absorb commit {}", source.id().hex()));
+    let stats = absorb_hunks(tx.mut_repo(), command.settings(), &source, selected_trees)?;
+    writeln!(
+        ui,
+        "Absorbed changes into {} revisions:",
+        stats.rewritten_destinations.len()
+    )?;
+    for commit in &stats.rewritten_destinations {
+        ui.write("  ")?;
+        tx.write_commit_summary(ui.stdout_formatter().as_mut(), commit)?;
+        ui.write("\n")?;
+    }
+    if stats.num_rebased > 0 {
+        writeln!(ui, "Rebased {} descendant commits", stats.num_rebased)?;
+    }
+    tx.finish(ui)?;
+    Ok(())
+}
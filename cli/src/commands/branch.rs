@@ -6,8 +6,8 @@ use jj_lib::backend::{CommitId, ObjectId};
 use jj_lib::git;
 use jj_lib::op_store::{BranchTarget, RefTarget};
 use jj_lib::repo::Repo;
-use jj_lib::revset::{self, RevsetExpression};
-use jj_lib::view::View;
+use jj_lib::revset::{self, RevsetExpression, StringPattern};
+use jj_lib::view::{RefName, View};
 
 use crate::cli_util::{user_error, user_error_with_hint, CommandError, CommandHelper, RevisionArg};
 use crate::commands::make_branch_term;
@@ -28,8 +28,13 @@ pub enum BranchSubcommand {
     Forget(BranchForgetArgs),
     #[command(visible_alias("l"))]
     List(BranchListArgs),
+    #[command(visible_alias("r"))]
+    Rename(BranchRenameArgs),
     #[command(visible_alias("s"))]
     Set(BranchSetArgs),
+    #[command(visible_alias("t"))]
+    Track(BranchTrackArgs),
+    Untrack(BranchUntrackArgs),
 }
 
 /// Create a new branch.
@@ -49,8 +54,12 @@ pub struct BranchCreateArgs {
 #[derive(clap::Args, Clone, Debug)]
 pub struct BranchDeleteArgs {
     /// The branches to delete.
-    #[arg(required_unless_present_any(& ["glob"]))]
-    names: Vec<String>,
+    ///
+    /// By default, the specified name matches exactly. Use `glob:` or `regex:`
+    /// prefix to select branches by pattern. For details, see
+    /// https://github.com/martinvonz/jj/blob/main/docs/revsets.md#string-patterns.
+    #[arg(required_unless_present_any(& ["glob"]), value_parser = StringPattern::parse)]
+    names: Vec<StringPattern>,
 
     /// A glob pattern indicating branches to delete.
     #[arg(long)]
@@ -60,12 +69,20 @@ pub struct BranchDeleteArgs {
 /// List branches and their targets
 ///
 /// A remote branch will be included only if its target is different from
-/// the local target. For a conflicted branch (both local and remote), old
+/// the local target, or if it isn't tracked. For a conflicted branch (both local and remote), old
 /// target revisions are preceded by a "-" and new target revisions are
 /// preceded by a "+". For information about branches, see
 /// https://github.com/martinvonz/jj/blob/main/docs/branches.md.
 #[derive(clap::Args, Clone, Debug)]
 pub struct BranchListArgs {
+    /// Show branches whose local name matches
+    ///
+    /// By default, the specified name matches exactly. Use `glob:` or `regex:`
+    /// prefix to select branches by pattern. For details, see
+    /// https://github.com/martinvonz/jj/blob/main/docs/revsets.md#string-patterns.
+    #[arg(value_parser = StringPattern::parse)]
+    names: Vec<StringPattern>,
+
     /// Show branches whose local targets are in the given revisions.
     ///
     /// Note that `-r deleted_branch` will not work since `deleted_branch`
@@ -82,14 +99,83 @@ pub struct BranchListArgs {
 #[derive(clap::Args, Clone, Debug)]
 pub struct BranchForgetArgs {
     /// The branches to forget.
-    #[arg(required_unless_present_any(& ["glob"]))]
-    pub names: Vec<String>,
+    ///
+    /// By default, the specified name matches exactly. Use `glob:` or `regex:`
+    /// prefix to select branches by pattern. For details, see
+    /// https://github.com/martinvonz/jj/blob/main/docs/revsets.md#string-patterns.
+    #[arg(required_unless_present_any(& ["glob"]), value_parser = StringPattern::parse)]
+    pub names: Vec<StringPattern>,
 
     /// A glob pattern indicating branches to forget.
     #[arg(long)]
     pub glob: Vec<String>,
 }
 
+/// Rename a branch.
+///
+/// The new branch takes over the old branch's local target and its tracking
+/// state for remotes where the new branch already exists. The old branch is
+/// deleted, and the deletion is propagated to its tracked remotes on the next
+/// push.
+#[derive(clap::Args, Clone, Debug)]
+pub struct BranchRenameArgs {
+    /// The branch to rename.
+    old: String,
+
+    /// The new name of the branch.
+    #[arg(value_parser=NonEmptyStringValueParser::new())]
+    new: String,
+}
+
+/// Start tracking the given remote branches.
+///
+/// Changes to a tracked remote branch are merged into the local branch of the
+/// same name when fetching, and the local branch is pushed to it. Tracking a
+/// remote branch merges its current target into the local branch.
+#[derive(clap::Args, Clone, Debug)]
+pub struct BranchTrackArgs {
+    /// The remote branches to track, in `NAME@REMOTE` form.
+    #[arg(required = true, value_parser = parse_remote_branch_name)]
+    pub names: Vec<RemoteBranchName>,
+}
+
+/// Stop tracking the given remote branches.
+///
+/// An untracked remote branch is still updated when fetching, but the changes
+/// aren't merged into the local branch, and the local branch isn't pushed to
+/// it.
+#[derive(clap::Args, Clone, Debug)]
+pub struct BranchUntrackArgs {
+    /// The remote branches to untrack, in `NAME@REMOTE` form.
+    #[arg(required = true, value_parser = parse_remote_branch_name)]
+    pub names: Vec<RemoteBranchName>,
+}
+
+/// A remote branch specified as `NAME@REMOTE` on the command line.
+#[derive(Clone, Debug)]
+pub struct RemoteBranchName {
+    pub branch: String,
+    pub remote: String,
+}
+
+impl std::fmt::Display for RemoteBranchName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.branch, self.remote)
+    }
+}
+
+fn parse_remote_branch_name(value: &str) -> Result<RemoteBranchName, String> {
+    match value.rsplit_once('@') {
+        Some((branch, remote)) if !branch.is_empty() && !remote.is_empty() => {
+            Ok(RemoteBranchName {
+                branch: branch.to_owned(),
+                remote: remote.to_owned(),
+            })
+        }
+        _ => Err("remote branch must be specified in branch@remote form".to_owned()),
+    }
+}
+
 /// Update a given branch to point to a certain commit.
 #[derive(clap::Args, Clone, Debug)]
 pub struct BranchSetArgs {
@@ -117,6 +203,9 @@ pub fn cmd_branch(
         BranchSubcommand::Delete(sub_args) => cmd_branch_delete(ui, command, sub_args),
         BranchSubcommand::Forget(sub_args) => cmd_branch_forget(ui, command, sub_args),
         BranchSubcommand::List(sub_args) => cmd_branch_list(ui, command, sub_args),
+        BranchSubcommand::Rename(sub_args) => cmd_branch_rename(ui, command, sub_args),
+        BranchSubcommand::Track(sub_args) => cmd_branch_track(ui, command, sub_args),
+        BranchSubcommand::Untrack(sub_args) => cmd_branch_untrack(ui, command, sub_args),
     }
 }
 
@@ -209,50 +298,177 @@ fn cmd_branch_set(
     Ok(())
 }
 
-/// This function may return the same branch more than once
-fn find_globs(
-    view: &View,
+fn cmd_branch_rename(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &BranchRenameArgs,
+) -> Result<(), CommandError> {
+    let old_branch = &args.old;
+    let new_branch = &args.new;
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let view = workspace_command.repo().view();
+    let old_target = view.get_local_branch(old_branch).clone();
+    if old_target.is_absent() {
+        return Err(user_error(format!("No such branch: {old_branch}")));
+    }
+    if view.get_local_branch(new_branch).is_present() {
+        return Err(user_error(format!("Branch already exists: {new_branch}")));
+    }
+    let old_remotes = view
+        .get_branch(old_branch)
+        .map(|branch_target| branch_target.remote_targets.keys().cloned().collect_vec())
+        .unwrap_or_default();
+
+    let mut tx =
+        workspace_command.start_transaction(&format!("rename branch {old_branch} to {new_branch}"));
+    let mut_repo = tx.mut_repo();
+    mut_repo.set_local_branch_target(new_branch, old_target);
+    for remote in &old_remotes {
+        let tracked = mut_repo.is_remote_branch_tracked(old_branch, remote);
+        mut_repo.set_remote_branch_tracked(new_branch, remote, tracked);
+    }
+    mut_repo.set_local_branch_target(old_branch, RefTarget::absent());
+    tx.finish(ui)?;
+    Ok(())
+}
+
+fn cmd_branch_track(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &BranchTrackArgs,
+) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let view = workspace_command.repo().view();
+    let mut names = Vec::new();
+    for name in &args.names {
+        if view
+            .get_remote_branch(&name.branch, &name.remote)
+            .is_absent()
+        {
+            return Err(user_error(format!("No such remote branch: {name}")));
+        }
+        if view.is_remote_branch_tracked(&name.branch, &name.remote) {
+            writeln!(ui.warning(), "Remote branch already tracked: {name}")?;
+        } else {
+            names.push(name);
+        }
+    }
+    let branch_term = make_branch_term(&names.iter().map(ToString::to_string).collect_vec());
+    let mut tx = workspace_command.start_transaction(&format!("track remote {branch_term}"));
+    for name in &names {
+        let mut_repo = tx.mut_repo();
+        mut_repo.set_remote_branch_tracked(&name.branch, &name.remote, true);
+        let remote_target = mut_repo.get_remote_branch(&name.branch, &name.remote);
+        mut_repo.merge_single_ref(
+            &RefName::LocalBranch(name.branch.clone()),
+            RefTarget::absent_ref(),
+            &remote_target,
+        );
+    }
+    tx.finish(ui)?;
+    Ok(())
+}
+
+fn cmd_branch_untrack(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &BranchUntrackArgs,
+) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let view = workspace_command.repo().view();
+    let mut names = Vec::new();
+    for name in &args.names {
+        if view
+            .get_remote_branch(&name.branch, &name.remote)
+            .is_absent()
+        {
+            return Err(user_error(format!("No such remote branch: {name}")));
+        }
+        if view.is_remote_branch_tracked(&name.branch, &name.remote) {
+            names.push(name);
+        } else {
+            writeln!(ui.warning(), "Remote branch not tracked yet: {name}")?;
+        }
+    }
+    let branch_term = make_branch_term(&names.iter().map(ToString::to_string).collect_vec());
+    let mut tx = workspace_command.start_transaction(&format!("untrack remote {branch_term}"));
+    for name in &names {
+        tx.mut_repo()
+            .set_remote_branch_tracked(&name.branch, &name.remote, false);
+    }
+    tx.finish(ui)?;
+    Ok(())
+}
+
+/// Collects the patterns given as `names` and `--glob` arguments.
+fn make_name_patterns(
+    names: &[StringPattern],
     globs: &[String],
-    allow_deleted: bool,
-) -> Result<Vec<String>, CommandError> {
-    let mut matching_branches: Vec<String> = vec![];
-    let mut failed_globs = vec![];
+) -> Result<Vec<StringPattern>, CommandError> {
+    let mut patterns = names.to_vec();
     for glob_str in globs {
-        let glob = glob::Pattern::new(glob_str)?;
-        let names = view
-            .branches()
-            .iter()
-            .filter_map(|(branch_name, branch_target)| {
-                if glob.matches(branch_name)
-                    && (allow_deleted || branch_target.local_target.is_present())
-                {
-                    Some(branch_name)
-                } else {
-                    None
-                }
-            })
-            .cloned()
-            .collect_vec();
-        if names.is_empty() {
-            failed_globs.push(glob);
+        patterns.push(StringPattern::glob(glob_str)?);
+    }
+    Ok(patterns)
+}
+
+/// Finds the names of the branches matching any of the `name_patterns`.
+///
+/// `find_matches` is called with each pattern and should return the branch
+/// names matching it. Returns an error if any of the patterns doesn't match a
+/// branch.
+fn find_branches_with<I: IntoIterator<Item = String>>(
+    name_patterns: &[StringPattern],
+    mut find_matches: impl FnMut(&StringPattern) -> I,
+) -> Result<BTreeSet<String>, CommandError> {
+    let mut matching_branches = BTreeSet::new();
+    let mut unmatched_patterns = vec![];
+    for pattern in name_patterns {
+        let mut names = find_matches(pattern).into_iter().peekable();
+        if names.peek().is_none() {
+            unmatched_patterns.push(pattern);
         }
         matching_branches.extend(names);
     }
-    match &failed_globs[..] {
-        [] => { /* No problem */ }
-        [glob] => {
-            return Err(user_error(format!(
-                "The provided glob '{glob}' did not match any branches"
-            )))
-        }
-        globs => {
-            return Err(user_error(format!(
-                "The provided globs '{}' did not match any branches",
-                globs.iter().join("', '")
-            )))
-        }
+    if let Some(name) = unmatched_patterns
+        .iter()
+        .find_map(|pattern| pattern.as_exact())
+    {
+        return Err(user_error(format!("No such branch: {name}")));
+    }
+    let kind = if unmatched_patterns
+        .iter()
+        .all(|pattern| matches!(pattern, StringPattern::Glob(_)))
+    {
+        "glob"
+    } else {
+        "pattern"
     };
-    Ok(matching_branches)
+    match &unmatched_patterns[..] {
+        [] => Ok(matching_branches),
+        [pattern] => Err(user_error(format!(
+            "The provided {kind} '{pattern}' did not match any branches"
+        ))),
+        patterns => Err(user_error(format!(
+            "The provided {kind}s '{}' did not match any branches",
+            patterns.iter().join("', '")
+        ))),
+    }
+}
+
+/// Returns the names of the branches in the `view` matching the `pattern`.
+///
+/// Deleted branches, which have remote targets only, are included if
+/// `allow_deleted` is true.
+fn branches_matching(view: &View, pattern: &StringPattern, allow_deleted: bool) -> Vec<String> {
+    view.branches()
+        .iter()
+        .filter(|(branch_name, branch_target)| {
+            pattern.matches(branch_name)
+                && (allow_deleted || branch_target.local_target.is_present())
+        })
+        .map(|(branch_name, _)| branch_name.clone())
+        .collect()
 }
 
 fn cmd_branch_delete(
@@ -262,18 +478,10 @@ fn cmd_branch_delete(
 ) -> Result<(), CommandError> {
     let mut workspace_command = command.workspace_helper(ui)?;
     let view = workspace_command.repo().view();
-    for branch_name in &args.names {
-        if workspace_command
-            .repo()
-            .view()
-            .get_local_branch(branch_name)
-            .is_absent()
-        {
-            return Err(user_error(format!("No such branch: {branch_name}")));
-        }
-    }
-    let globbed_names = find_globs(view, &args.glob, false)?;
-    let names: BTreeSet<String> = args.names.iter().cloned().chain(globbed_names).collect();
+    let name_patterns = make_name_patterns(&args.names, &args.glob)?;
+    let names = find_branches_with(&name_patterns, |pattern| {
+        branches_matching(view, pattern, false)
+    })?;
     let branch_term = make_branch_term(names.iter().collect_vec().as_slice());
     let mut tx = workspace_command.start_transaction(&format!("delete {branch_term}"));
     for branch_name in names.iter() {
@@ -294,13 +502,10 @@ fn cmd_branch_forget(
 ) -> Result<(), CommandError> {
     let mut workspace_command = command.workspace_helper(ui)?;
     let view = workspace_command.repo().view();
-    for branch_name in args.names.iter() {
-        if view.get_branch(branch_name).is_none() {
-            return Err(user_error(format!("No such branch: {branch_name}")));
-        }
-    }
-    let globbed_names = find_globs(view, &args.glob, true)?;
-    let names: BTreeSet<String> = args.names.iter().cloned().chain(globbed_names).collect();
+    let name_patterns = make_name_patterns(&args.names, &args.glob)?;
+    let names = find_branches_with(&name_patterns, |pattern| {
+        branches_matching(view, pattern, true)
+    })?;
     let branch_term = make_branch_term(names.iter().collect_vec().as_slice());
     let mut tx = workspace_command.start_transaction(&format!("forget {branch_term}"));
     for branch_name in names.iter() {
@@ -321,36 +526,38 @@ fn cmd_branch_list(
     let workspace_command = command.workspace_helper(ui)?;
     let repo = workspace_command.repo();
     let mut all_branches = git::build_unified_branches_map(repo.view());
-    if !args.revisions.is_empty() {
+    if !args.names.is_empty() || !args.revisions.is_empty() {
         // Match against local targets only, which is consistent with "jj git push".
         fn local_targets(branch_target: &BranchTarget) -> impl Iterator<Item = &CommitId> {
             branch_target.local_target.added_ids()
         }
 
-        let filter_expressions: Vec<_> = args
-            .revisions
-            .iter()
-            .map(|revision_str| workspace_command.parse_revset(revision_str, Some(ui)))
-            .try_collect()?;
-        let filter_expression = RevsetExpression::union_all(&filter_expressions);
-        // Intersects with the set of all branch targets to minimize the lookup space.
-        let all_targets = all_branches
-            .values()
-            .flat_map(local_targets)
-            .cloned()
-            .collect();
-        let revset_expression =
-            RevsetExpression::commits(all_targets).intersection(&filter_expression);
-        let revset_expression = revset::optimize(revset_expression);
-        let revset = workspace_command.evaluate_revset(revset_expression)?;
-        let filtered_targets: HashSet<CommitId> = revset.iter().collect();
-        // TODO: If we add name-based filter like --glob, this might have to be
-        // rewritten as a positive list or predicate function. Should they
-        // be AND-ed or OR-ed? Maybe OR as "jj git push" would do. Perhaps, we
-        // can consider these options as producers of branch names, not filters
-        // of different kind (which are typically intersected.)
-        all_branches.retain(|_, branch_target| {
-            local_targets(branch_target).any(|id| filtered_targets.contains(id))
+        let filtered_targets: HashSet<CommitId> = if args.revisions.is_empty() {
+            HashSet::new()
+        } else {
+            let filter_expressions: Vec<_> = args
+                .revisions
+                .iter()
+                .map(|revision_str| workspace_command.parse_revset(revision_str, Some(ui)))
+                .try_collect()?;
+            let filter_expression = RevsetExpression::union_all(&filter_expressions);
+            // Intersects with the set of all branch targets to minimize the lookup space.
+            let all_targets = all_branches
+                .values()
+                .flat_map(local_targets)
+                .cloned()
+                .collect();
+            let revset_expression =
+                RevsetExpression::commits(all_targets).intersection(&filter_expression);
+            let revset_expression = revset::optimize(revset_expression);
+            let revset = workspace_command.evaluate_revset(revset_expression)?;
+            revset.iter().collect()
+        };
+        // Branches selected by name or by target are both listed, as "jj git
+        // push" would do.
+        all_branches.retain(|name, branch_target| {
+            args.names.iter().any(|pattern| pattern.matches(name))
+                || local_targets(branch_target).any(|id| filtered_targets.contains(id))
         });
     }
 
@@ -392,29 +599,34 @@ fn cmd_branch_list(
     let formatter = formatter.as_mut();
 
     for (name, branch_target) in all_branches {
-        let found_non_git_remote = {
-            let pseudo_remote_count = branch_target
-                .remote_targets
-                .contains_key(git::REMOTE_NAME_FOR_LOCAL_GIT_REPO)
-                as usize;
-            branch_target.remote_targets.len() - pseudo_remote_count > 0
-        };
+        let found_tracked_remote = branch_target.remote_targets.keys().any(|remote| {
+            remote != git::REMOTE_NAME_FOR_LOCAL_GIT_REPO
+                && !branch_target.untracked_remotes.contains(remote)
+        });
+        let found_untracked_remote = !branch_target.untracked_remotes.is_empty();
 
         write!(formatter.labeled("branch"), "{name}")?;
         if branch_target.local_target.is_present() {
             print_branch_target(formatter, &branch_target.local_target)?;
-        } else if found_non_git_remote {
+        } else if found_tracked_remote {
             writeln!(formatter, " (deleted)")?;
+        } else if found_untracked_remote {
+            writeln!(formatter)?;
         } else {
             writeln!(formatter, " (forgotten)")?;
         }
 
         for (remote, remote_target) in branch_target.remote_targets.iter() {
-            if remote_target == &branch_target.local_target {
+            if remote_target == &branch_target.local_target
+                && !branch_target.untracked_remotes.contains(remote)
+            {
                 continue;
             }
             write!(formatter, "  ")?;
             write!(formatter.labeled("branch"), "@{remote}")?;
+            if branch_target.untracked_remotes.contains(remote) {
+                write!(formatter, " (untracked)")?;
+            }
             let local_target = &branch_target.local_target;
             if local_target.is_present() {
                 let remote_added_ids = remote_target.added_ids().cloned().collect_vec();
@@ -439,13 +651,13 @@ fn cmd_branch_list(
         }
 
         if branch_target.local_target.is_absent() {
-            if found_non_git_remote {
+            if found_tracked_remote {
                 writeln!(
                     formatter,
                     "  (this branch will be *deleted permanently* on the remote on the\n   next \
                      `jj git push`. Use `jj branch forget` to prevent this)"
                 )?;
-            } else {
+            } else if !found_untracked_remote {
                 writeln!(
                     formatter,
                     "  (this branch will be deleted from the underlying Git repo on the next `jj \
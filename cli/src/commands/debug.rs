@@ -62,7 +62,15 @@ pub struct DebugIndexArgs {}
 
 /// Rebuild commit index
 #[derive(clap::Args, Clone, Debug)]
-pub struct DebugReIndexArgs {}
+pub struct DebugReIndexArgs {
+    /// Build changed-path filters to speed up path-limited revsets such as
+    /// `file()`
+    #[arg(long, conflicts_with = "no_changed_paths")]
+    changed_paths: bool,
+    /// Stop building changed-path filters
+    #[arg(long)]
+    no_changed_paths: bool,
+}
 
 /// Show information about an operation and its view
 #[derive(clap::Args, Clone, Debug)]
@@ -128,6 +136,11 @@ pub fn cmd_debug(
                 writeln!(ui, "Max generation number: {}", stats.max_generation_number)?;
                 writeln!(ui, "Number of heads: {}", stats.num_heads)?;
                 writeln!(ui, "Number of changes: {}", stats.num_changes)?;
+                writeln!(
+                    ui,
+                    "Number of changed-path filters: {}",
+                    stats.num_changed_path_filters
+                )?;
                 writeln!(ui, "Stats per level:")?;
                 for (i, level) in stats.levels.iter().enumerate() {
                     writeln!(ui, "  Level {i}:")?;
@@ -141,12 +154,15 @@ pub fn cmd_debug(
                 )));
             }
         }
-        DebugCommands::ReIndex(_reindex_matches) => {
+        DebugCommands::ReIndex(reindex_matches) => {
             let workspace_command = command.workspace_helper(ui)?;
             let repo = workspace_command.repo();
             let default_index_store: Option<&DefaultIndexStore> =
                 repo.index_store().as_any().downcast_ref();
             if let Some(default_index_store) = default_index_store {
+                if reindex_matches.changed_paths || reindex_matches.no_changed_paths {
+                    default_index_store.set_changed_paths_enabled(reindex_matches.changed_paths)?;
+                }
                 default_index_store.reinit();
                 let repo = repo.reload_at(repo.operation())?;
                 let index_impl: &ReadonlyIndexWrapper = repo
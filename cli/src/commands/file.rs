@@ -0,0 +1,96 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::Subcommand;
+use jj_lib::annotate::annotate_file;
+use jj_lib::backend::TreeValue;
+use tracing::instrument;
+
+use crate::cli_util::{user_error, CommandError, CommandHelper, RevisionArg};
+use crate::ui::Ui;
+
+/// Commands for working with files
+#[derive(Subcommand, Clone, Debug)]
+pub enum FileCommands {
+    Annotate(FileAnnotateArgs),
+}
+
+/// Show the source change for each line of a file
+///
+/// Each line is prefixed with the commit that introduced it, formatted with
+/// the `templates.annotate_commit_summary` template, and with the line's
+/// number. Lines are followed across merges and through conflicted files, in
+/// which conflict markers are attributed to the commit that introduced the
+/// conflict.
+#[derive(clap::Args, Clone, Debug)]
+pub struct FileAnnotateArgs {
+    /// The revision to annotate the file in
+    #[arg(long, short, default_value = "@")]
+    revision: RevisionArg,
+    /// The file to annotate
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    path: String,
+}
+
+pub fn cmd_file(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    subcommand: &FileCommands,
+) -> Result<(), CommandError> {
+    match subcommand {
+        FileCommands::Annotate(sub_args) => cmd_file_annotate(ui, command, sub_args),
+    }
+}
+
+#[instrument(skip_all)]
+fn cmd_file_annotate(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &FileAnnotateArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper(ui)?;
+    let commit = workspace_command.resolve_single_rev(&args.revision, ui)?;
+    let path = workspace_command.parse_file_path(&args.path)?;
+    match commit.tree()?.path_value(&path).into_resolved() {
+        Ok(None) => return Err(user_error("No such path")),
+        Ok(Some(TreeValue::File { .. })) | Err(_) => {}
+        Ok(Some(_)) => return Err(user_error("Path exists but is not a file")),
+    }
+    let template = workspace_command.parse_commit_template(
+        &command
+            .settings()
+            .config()
+            .get_string("templates.annotate_commit_summary")?,
+    )?;
+    let repo = workspace_command.repo();
+    let lines = annotate_file(
+        repo.as_ref(),
+        &commit,
+        &path,
+        command.settings().conflict_marker_style()?,
+    )?;
+
+    ui.request_pager();
+    let mut formatter = ui.stdout_formatter();
+    let formatter = formatter.as_mut();
+    for (line_number, line) in lines.iter().enumerate() {
+        template.format(&line.commit, formatter)?;
+        write!(formatter, " {:>4}: ", line_number + 1)?;
+        formatter.write_all(&line.content)?;
+        if !line.content.ends_with(b"\n") {
+            writeln!(formatter)?;
+        }
+    }
+    Ok(())
+}
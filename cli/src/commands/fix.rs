@@ -0,0 +1,304 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{Read as _, Write as _};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use itertools::Itertools;
+use jj_lib::backend::{BackendError, CommitId, FileId, ObjectId, TreeValue};
+use jj_lib::fileset::{self, FilesetExpression, FilesetParseContext};
+use jj_lib::matchers::Matcher;
+use jj_lib::merge::Merge;
+use jj_lib::merged_tree::MergedTreeBuilder;
+use jj_lib::repo::Repo;
+use jj_lib::repo_path::RepoPath;
+use jj_lib::rewrite::merge_commit_trees;
+use jj_lib::settings::{ConfigResultExt as _, UserSettings};
+use jj_lib::store::Store;
+use tracing::instrument;
+
+use crate::cli_util::{
+    resolve_multiple_nonempty_revsets, user_error, CommandError, CommandHelper, RevisionArg,
+};
+use crate::config::CommandNameAndArgs;
+use crate::ui::Ui;
+
+/// Update files with formatting fixes or other changes
+///
+/// The files changed in each of the given revisions are passed through the
+/// tools configured in `fix.tools` whose patterns match the file's path. Each
+/// tool reads the file's content from stdin and writes the fixed content to
+/// stdout. If several tools match a file, they're run in the order of their
+/// names, each reading the output of the previous one.
+///
+/// The revisions are rewritten in place, and their descendants are rebased.
+/// Files in the working copy on disk are only updated if the working-copy
+/// revision itself was fixed. If any tool fails, nothing is rewritten.
+///
+/// The string `$path` in a tool's arguments is replaced by the
+/// workspace-relative path of the file being fixed.
+///
+/// # Example
+///
+/// [fix.tools.rustfmt]
+/// command = ["rustfmt", "--emit", "stdout", "--edition", "2021"]
+/// patterns = ["glob:'**/*.rs'"]
+#[derive(clap::Args, Clone, Debug)]
+#[command(verbatim_doc_comment)]
+pub struct FixArgs {
+    /// The revisions to fix
+    #[arg(long, short, default_value = "@")]
+    source: Vec<RevisionArg>,
+    /// Fix only these paths (instead of all changed paths)
+    #[arg(value_hint = clap::ValueHint::AnyPath)]
+    paths: Vec<String>,
+}
+
+/// Options from `[fix.tools.<name>]`.
+#[derive(Clone, Debug, serde::Deserialize)]
+struct ToolConfig {
+    command: CommandNameAndArgs,
+    patterns: Vec<String>,
+}
+
+struct FixTool {
+    name: String,
+    command: CommandNameAndArgs,
+    matcher: Box<dyn Matcher>,
+}
+
+#[instrument(skip_all)]
+pub fn cmd_fix(ui: &mut Ui, command: &CommandHelper, args: &FixArgs) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let to_fix = resolve_multiple_nonempty_revsets(&args.source, &workspace_command, ui)?;
+    workspace_command.check_rewritable(&to_fix)?;
+    let matcher = workspace_command.matcher_from_values(&args.paths)?;
+    let workspace_root = workspace_command.workspace_root().clone();
+    let tools = get_tools_config(command.settings(), &workspace_root)?;
+    if tools.is_empty() {
+        return Err(user_error(
+            "No fix tools are configured. Add some to the `fix.tools` config table.",
+        ));
+    }
+
+    let mut tx = workspace_command.start_transaction(&format!("fix {} commit(s)", to_fix.len()));
+    let base_repo = tx.base_repo().clone();
+    let store = base_repo.store();
+    // The paths considered in each commit. Descendants of a fixed commit have
+    // to fix the same paths to carry over the fixes.
+    let mut checked_paths: HashMap<CommitId, HashSet<RepoPath>> = HashMap::new();
+    let mut fixed_file_ids: HashMap<(RepoPath, FileId), FileId> = HashMap::new();
+    let mut rewritten: HashMap<CommitId, CommitId> = HashMap::new();
+    let mut fixed_commits = vec![];
+    for commit_id in base_repo
+        .index()
+        .topo_order(&mut to_fix.iter().map(|commit| commit.id()))
+    {
+        let old_commit = store.get_commit(&commit_id)?;
+        let parent_tree = merge_commit_trees(base_repo.as_ref(), &old_commit.parents())?;
+        let tree = old_commit.tree()?;
+        let mut paths: HashSet<RepoPath> = old_commit
+            .parent_ids()
+            .iter()
+            .filter_map(|id| checked_paths.get(id))
+            .flatten()
+            .cloned()
+            .collect();
+        paths.extend(
+            parent_tree
+                .diff(&tree, matcher.as_ref())
+                .map(|(path, _before, _after)| path),
+        );
+
+        let mut tree_builder = MergedTreeBuilder::new(old_commit.tree_id().clone());
+        let mut tree_changed = false;
+        for path in paths.iter().sorted() {
+            let Ok(Some(TreeValue::File { id, executable })) =
+                tree.path_value(path).into_resolved()
+            else {
+                continue;
+            };
+            let key = (path.clone(), id.clone());
+            let new_id = if let Some(new_id) = fixed_file_ids.get(&key) {
+                new_id.clone()
+            } else {
+                let new_id = fix_file(store, &workspace_root, &tools, path, &id)?;
+                fixed_file_ids.insert(key, new_id.clone());
+                new_id
+            };
+            if new_id != id {
+                tree_builder.set_or_remove(
+                    path.clone(),
+                    Merge::normal(TreeValue::File {
+                        id: new_id,
+                        executable,
+                    }),
+                );
+                tree_changed = true;
+            }
+        }
+        checked_paths.insert(commit_id, paths);
+
+        let new_parent_ids = old_commit
+            .parent_ids()
+            .iter()
+            .map(|id| rewritten.get(id).unwrap_or(id).clone())
+            .collect_vec();
+        if !tree_changed && new_parent_ids == old_commit.parent_ids() {
+            continue;
+        }
+        let new_tree_id = tree_builder.write_tree(store)?;
+        let new_commit = tx
+            .mut_repo()
+            .rewrite_commit(command.settings(), &old_commit)
+            .set_parents(new_parent_ids)
+            .set_tree_id(new_tree_id)
+            .write()?;
+        rewritten.insert(old_commit.id().clone(), new_commit.id().clone());
+        if tree_changed {
+            fixed_commits.push(new_commit);
+        }
+    }
+
+    let num_rebased = tx.mut_repo().rebase_descendants(command.settings())?;
+    writeln!(
+        ui,
+        "Fixed {} of {} commits",
+        fixed_commits.len(),
+        to_fix.len()
+    )?;
+    for commit in &fixed_commits {
+        ui.write("  ")?;
+        tx.write_commit_summary(ui.stdout_formatter().as_mut(), commit)?;
+        ui.write("\n")?;
+    }
+    if num_rebased > 0 {
+        writeln!(ui, "Rebased {num_rebased} descendant commits")?;
+    }
+    tx.finish(ui)?;
+    Ok(())
+}
+
+/// Loads the tools from `[fix.tools]`, sorted by name.
+fn get_tools_config(
+    settings: &UserSettings,
+    workspace_root: &Path,
+) -> Result<Vec<FixTool>, CommandError> {
+    const TABLE_KEY: &str = "fix.tools";
+    let Some(table) = settings.config().get_table(TABLE_KEY).optional()? else {
+        return Ok(vec![]);
+    };
+    let ctx = FilesetParseContext {
+        cwd: workspace_root,
+        workspace_root,
+    };
+    table
+        .into_iter()
+        .sorted_by(|a, b| a.0.cmp(&b.0))
+        .map(|(name, value)| {
+            let config: ToolConfig = value
+                .try_deserialize()
+                .map_err(|err| user_error(format!("Invalid `{TABLE_KEY}.{name}`: {err}")))?;
+            let expressions: Vec<_> = config
+                .patterns
+                .iter()
+                .map(|pattern| fileset::parse(pattern, &ctx))
+                .try_collect()
+                .map_err(|err| {
+                    user_error(format!(
+                        "Invalid pattern in `{TABLE_KEY}.{name}.patterns`: {err}"
+                    ))
+                })?;
+            Ok(FixTool {
+                name,
+                command: config.command,
+                matcher: FilesetExpression::union_all(expressions).to_matcher(),
+            })
+        })
+        .try_collect()
+}
+
+/// Runs the matching tools on the file, and returns the id of the fixed file.
+fn fix_file(
+    store: &Store,
+    workspace_root: &Path,
+    tools: &[FixTool],
+    path: &RepoPath,
+    id: &FileId,
+) -> Result<FileId, CommandError> {
+    let mut matching_tools = tools.iter().filter(|tool| tool.matcher.matches(path));
+    let Some(first_tool) = matching_tools.next() else {
+        return Ok(id.clone());
+    };
+    let mut content = vec![];
+    store
+        .read_file(path, id)?
+        .read_to_end(&mut content)
+        .map_err(|err| BackendError::ReadObject {
+            object_type: "file".to_string(),
+            hash: id.hex(),
+            source: err.into(),
+        })?;
+    let old_content = content.clone();
+    for tool in [first_tool].into_iter().chain(matching_tools) {
+        content = run_tool(workspace_root, tool, path, &content)?;
+    }
+    if content == old_content {
+        return Ok(id.clone());
+    }
+    Ok(store.write_file(path, &mut content.as_slice())?)
+}
+
+fn run_tool(
+    workspace_root: &Path,
+    tool: &FixTool,
+    path: &RepoPath,
+    content: &[u8],
+) -> Result<Vec<u8>, CommandError> {
+    let path_str = path.to_internal_file_string();
+    let (name, args) = tool.command.split_name_and_args();
+    let mut cmd = Command::new(name.as_ref());
+    if let CommandNameAndArgs::Structured { env, .. } = &tool.command {
+        cmd.envs(env);
+    }
+    cmd.args(args.iter().map(|arg| arg.replace("$path", &path_str)))
+        .current_dir(workspace_root)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    tracing::info!(?cmd, "running fix tool");
+    let mut child = cmd.spawn().map_err(|err| {
+        user_error(format!(
+            "Failed to run fix tool `{}` ({name}): {err}",
+            tool.name
+        ))
+    })?;
+    // Write from a separate thread so that a tool producing a lot of output
+    // before reading all of its input doesn't deadlock.
+    let mut stdin = child.stdin.take().unwrap();
+    let output = std::thread::scope(|scope| {
+        scope.spawn(move || stdin.write_all(content));
+        child.wait_with_output()
+    })?;
+    if !output.status.success() {
+        return Err(user_error(format!(
+            "Fix tool `{}` failed on {path_str}:\n{}",
+            tool.name,
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        )));
+    }
+    Ok(output.stdout)
+}
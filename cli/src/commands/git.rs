@@ -19,7 +19,7 @@ use jj_lib::repo_path::RepoPath;
 use jj_lib::revset::{self, RevsetExpression, RevsetIteratorExt as _, StringPattern};
 use jj_lib::settings::{ConfigResultExt as _, UserSettings};
 use jj_lib::store::Store;
-use jj_lib::view::View;
+use jj_lib::view::{RefName, View};
 use jj_lib::workspace::Workspace;
 use maplit::hashset;
 
@@ -28,7 +28,7 @@ use crate::cli_util::{
     short_commit_hash, user_error, user_error_with_hint, CommandError, CommandHelper, RevisionArg,
     WorkspaceCommandHelper,
 };
-use crate::commands::make_branch_term;
+use crate::commands::{make_branch_term, make_tag_term};
 use crate::progress::Progress;
 use crate::ui::Ui;
 
@@ -128,9 +128,10 @@ pub struct GitCloneArgs {
 /// By default, pushes any branches pointing to
 /// `remote_branches(remote=<remote>)..@`. Use `--branch` to push specific
 /// branches. Use `--all` to push all branches. Use `--change` to generate
-/// branch names based on the change IDs of specific commits.
+/// branch names based on the change IDs of specific commits. Use `--tag` to
+/// push tags.
 #[derive(clap::Args, Clone, Debug)]
-#[command(group(ArgGroup::new("specific").args(&["branch", "change", "revisions"]).multiple(true)))]
+#[command(group(ArgGroup::new("specific").args(&["branch", "change", "revisions", "tag"]).multiple(true)))]
 #[command(group(ArgGroup::new("what").args(&["all", "deleted"]).conflicts_with("specific")))]
 pub struct GitPushArgs {
     /// The remote to push to (only named remotes are supported)
@@ -152,6 +153,9 @@ pub struct GitPushArgs {
     /// repeated)
     #[arg(long, short)]
     change: Vec<RevisionArg>,
+    /// Push this tag (can be repeated)
+    #[arg(long)]
+    tag: Vec<String>,
     /// Only display what will change on the remote
     #[arg(long)]
     dry_run: bool,
@@ -696,6 +700,7 @@ fn cmd_git_push(
     let mut tx = workspace_command.start_transaction("");
     let tx_description;
     let mut branch_updates = vec![];
+    let mut tag_names = vec![];
     if args.all {
         for (branch_name, branch_target) in repo.view().branches() {
             match classify_branch_update(branch_name, branch_target, &remote) {
@@ -785,8 +790,23 @@ fn cmd_git_push(
             }
         }
 
-        let use_default_revset =
-            args.branch.is_empty() && args.change.is_empty() && args.revisions.is_empty();
+        for tag_name in &args.tag {
+            if tag_names.contains(tag_name) {
+                continue;
+            }
+            let target = repo.view().get_tag(tag_name);
+            if target.is_absent() {
+                return Err(user_error(format!("Tag {tag_name} doesn't exist")));
+            } else if target.has_conflict() {
+                return Err(user_error(format!("Tag {tag_name} is conflicted")));
+            }
+            tag_names.push(tag_name.clone());
+        }
+
+        let use_default_revset = args.branch.is_empty()
+            && args.change.is_empty()
+            && args.revisions.is_empty()
+            && args.tag.is_empty();
         let revision_commit_ids: HashSet<_> = if use_default_revset {
             let Some(wc_commit_id) = wc_commit_id else {
                 return Err(user_error("Nothing checked out in this workspace"));
@@ -828,18 +848,21 @@ fn cmd_git_push(
             )?;
         }
 
-        tx_description = format!(
-            "push {} to git remote {}",
-            make_branch_term(
+        let mut ref_terms = vec![];
+        if !branch_updates.is_empty() || tag_names.is_empty() {
+            ref_terms.push(make_branch_term(
                 &branch_updates
                     .iter()
                     .map(|(branch, _)| branch.as_str())
-                    .collect_vec()
-            ),
-            &remote
-        );
+                    .collect_vec(),
+            ));
+        }
+        if !tag_names.is_empty() {
+            ref_terms.push(make_tag_term(&tag_names));
+        }
+        tx_description = format!("push {} to git remote {}", ref_terms.join(" and "), &remote);
     }
-    if branch_updates.is_empty() {
+    if branch_updates.is_empty() && tag_names.is_empty() {
         writeln!(ui, "Nothing changed.")?;
         return Ok(());
     }
@@ -874,6 +897,10 @@ fn cmd_git_push(
         }
     }
 
+    for tag_name in &tag_names {
+        new_heads.extend(repo.view().get_tag(tag_name).added_ids().cloned());
+    }
+
     // Check if there are conflicts in any commits we're about to push that haven't
     // already been pushed.
     let mut old_heads = vec![];
@@ -917,7 +944,9 @@ fn cmd_git_push(
         }
     }
 
-    writeln!(ui, "Branch changes to push to {}:", &remote)?;
+    if !branch_updates.is_empty() {
+        writeln!(ui, "Branch changes to push to {}:", &remote)?;
+    }
     for (branch_name, update) in &branch_updates {
         match (&update.old_target, &update.new_target) {
             (Some(old_target), Some(new_target)) => {
@@ -957,15 +986,34 @@ fn cmd_git_push(
         }
     }
 
+    if !tag_names.is_empty() {
+        writeln!(ui, "Tags to push to {}:", &remote)?;
+    }
+    for tag_name in &tag_names {
+        let target = repo.view().get_tag(tag_name).as_normal().unwrap();
+        writeln!(ui, "  Push tag {tag_name} to {}", short_commit_hash(target))?;
+    }
+
     if args.dry_run {
         writeln!(ui, "Dry-run requested, not pushing.")?;
         return Ok(());
     }
 
-    with_remote_callbacks(ui, |cb| {
-        git::push_updates(&git_repo, &remote, &ref_updates, cb)
-    })
-    .map_err(|err| match err {
+    // Tags are pushed from the underlying Git repo, so that annotated tags are
+    // pushed along with their tag objects.
+    let failed_tags = git::export_some_refs(
+        tx.mut_repo(),
+        &git_repo,
+        |ref_name| matches!(ref_name, RefName::Tag(tag) if tag_names.contains(tag)),
+    )?;
+    if let Some(failed) = failed_tags.first() {
+        return Err(user_error(format!(
+            "Failed to export tag {} to the underlying Git repo",
+            failed.name
+        )));
+    }
+
+    let map_push_error = |err| match err {
         GitPushError::InternalGitError(err) => map_git_error(err),
         GitPushError::NotFastForward => user_error_with_hint(
             "The push conflicts with changes made on the remote (it is not fast-forwardable).",
@@ -973,9 +1021,29 @@ fn cmd_git_push(
              and push again.",
         ),
         _ => user_error(err.to_string()),
-    })?;
+    };
+    if !ref_updates.is_empty() {
+        with_remote_callbacks(ui, |cb| {
+            git::push_updates(&git_repo, &remote, &ref_updates, cb)
+        })
+        .map_err(map_push_error)?;
+    }
+    if !tag_names.is_empty() {
+        with_remote_callbacks(ui, |cb| {
+            git::push_tags(
+                &git_repo,
+                &remote,
+                &tag_names.iter().map(String::as_str).collect_vec(),
+                cb,
+            )
+        })
+        .map_err(map_push_error)?;
+    }
     git::import_refs(tx.mut_repo(), &git_repo, &command.settings().git_settings())?;
-    tx.finish(ui)?;
+    // Pushing tags that were already exported doesn't change the repo.
+    if tx.mut_repo().has_changes() {
+        tx.finish(ui)?;
+    }
     Ok(())
 }
 
@@ -1009,6 +1077,9 @@ fn classify_branch_update(
         BranchPushAction::RemoteConflicted => {
             Err(format!("Branch {branch_name}@{remote_name} is conflicted"))
         }
+        BranchPushAction::RemoteUntracked => Err(format!(
+            "Non-tracking remote branch {branch_name}@{remote_name} exists"
+        )),
         BranchPushAction::Update(update) => Ok(Some(update)),
     }
 }
@@ -12,19 +12,26 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod absorb;
 #[cfg(feature = "bench")]
 mod bench;
 mod branch;
 mod debug;
+mod file;
+mod fix;
 mod git;
 mod operation;
+mod patch;
+mod run;
+mod tag;
 
 use std::collections::{BTreeMap, HashSet};
 use std::fmt::Debug;
 use std::io::{BufRead, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::{fs, io};
+use std::time::{Duration, SystemTime};
+use std::{fs, io, iter};
 
 use clap::builder::NonEmptyStringValueParser;
 use clap::parser::ValueSource;
@@ -38,7 +45,8 @@ use jj_lib::git_backend::GitBackend;
 use jj_lib::matchers::EverythingMatcher;
 use jj_lib::merge::Merge;
 use jj_lib::merged_tree::{MergedTree, MergedTreeBuilder};
-use jj_lib::op_store::WorkspaceId;
+use jj_lib::op_store::{OperationId, WorkspaceId};
+use jj_lib::operation::{truncate_ancestors, Operation};
 use jj_lib::repo::{ReadonlyRepo, Repo};
 use jj_lib::repo_path::RepoPath;
 use jj_lib::revset::{RevsetExpression, RevsetFilterPredicate, RevsetIteratorExt};
@@ -47,9 +55,10 @@ use jj_lib::revset_graph::{
 };
 use jj_lib::rewrite::{back_out_commit, merge_commit_trees, rebase_commit, DescendantRebaser};
 use jj_lib::settings::UserSettings;
-use jj_lib::working_copy::SnapshotOptions;
+use jj_lib::sparse::SparsePattern;
+use jj_lib::working_copy::{CheckoutOptions, SnapshotOptions};
 use jj_lib::workspace::Workspace;
-use jj_lib::{conflicts, file_util, revset};
+use jj_lib::{conflicts, copies, file_util, revset};
 use maplit::{hashmap, hashset};
 use tracing::instrument;
 
@@ -70,6 +79,7 @@ use crate::ui::Ui;
 #[derive(clap::Parser, Clone, Debug)]
 enum Commands {
     Abandon(AbandonArgs),
+    Absorb(absorb::AbsorbArgs),
     Backout(BackoutArgs),
     #[cfg(feature = "bench")]
     #[command(subcommand)]
@@ -90,7 +100,10 @@ enum Commands {
     Diffedit(DiffeditArgs),
     Duplicate(DuplicateArgs),
     Edit(EditArgs),
+    #[command(subcommand)]
+    File(file::FileCommands),
     Files(FilesArgs),
+    Fix(fix::FixArgs),
     #[command(subcommand)]
     Git(git::GitCommands),
     Init(InitArgs),
@@ -113,13 +126,13 @@ enum Commands {
     #[command(subcommand)]
     #[command(visible_alias = "op")]
     Operation(operation::OperationCommands),
+    #[command(subcommand)]
+    Patch(patch::PatchCommands),
     Prev(PrevArgs),
     Rebase(RebaseArgs),
     Resolve(ResolveArgs),
     Restore(RestoreArgs),
-    #[command(hide = true)]
-    // TODO: Flesh out.
-    Run(RunArgs),
+    Run(run::RunArgs),
     Show(ShowArgs),
     #[command(subcommand)]
     Sparse(SparseArgs),
@@ -127,6 +140,8 @@ enum Commands {
     Squash(SquashArgs),
     Status(StatusArgs),
     #[command(subcommand)]
+    Tag(tag::TagSubcommand),
+    #[command(subcommand)]
     Util(UtilCommands),
     /// Undo an operation (shortcut for `jj op undo`)
     Undo(operation::OperationUndoArgs),
@@ -646,6 +661,9 @@ struct MoveArgs {
     /// Interactively choose which parts to move
     #[arg(long, short)]
     interactive: bool,
+    /// Move only these hunks instead of running the diff editor (see `jj split --help` for the format)
+    #[arg(long, value_name = "HUNKS", conflicts_with_all = ["interactive", "paths"])]
+    hunks: Option<String>,
     /// Move only changes to these paths (instead of all paths)
     #[arg(conflicts_with = "interactive", value_hint = clap::ValueHint::AnyPath)]
     paths: Vec<String>,
@@ -672,6 +690,9 @@ struct SquashArgs {
     /// Interactively choose which parts to squash
     #[arg(long, short)]
     interactive: bool,
+    /// Squash only these hunks instead of running the diff editor (see `jj split --help` for the format)
+    #[arg(long, value_name = "HUNKS", conflicts_with_all = ["interactive", "paths"])]
+    hunks: Option<String>,
     /// Move only changes to these paths (instead of all paths)
     #[arg(conflicts_with = "interactive", value_hint = clap::ValueHint::AnyPath)]
     paths: Vec<String>,
@@ -782,6 +803,9 @@ struct RestoreArgs {
     /// Restore only these paths (instead of all paths)
     #[arg(value_hint = clap::ValueHint::AnyPath)]
     paths: Vec<String>,
+    /// Restore only these hunks of the diff between the revisions (see `jj split --help` for the format)
+    #[arg(long, value_name = "HUNKS", conflicts_with = "paths")]
+    hunks: Option<String>,
     /// Revision to restore from (source)
     #[arg(long)]
     from: Option<RevisionArg>,
@@ -809,29 +833,6 @@ struct RestoreArgs {
     revision: Option<RevisionArg>,
 }
 
-/// Run a command across a set of revisions.
-///
-///
-/// All recorded state will be persisted in the `.jj` directory, so occasionally
-/// a `jj run --clean` is needed to clean up disk space.
-///
-/// # Example
-///
-/// # Run pre-commit on your local work
-/// $ jj run 'pre-commit.py .github/pre-commit.yaml' -r (main..@) -j 4
-///
-/// This allows pre-commit integration and other funny stuff.
-#[derive(clap::Args, Clone, Debug)]
-#[command(verbatim_doc_comment)]
-struct RunArgs {
-    /// The command to run across all selected revisions.
-    #[arg(long, short, alias = "x")]
-    command: String,
-    /// The revisions to change.
-    #[arg(long, short, default_value = "@")]
-    revisions: Vec<RevisionArg>,
-}
-
 /// Touch up the content changes in a revision with a diff editor
 ///
 /// With the `-r` option, which is the default, starts a diff editor (`meld` by
@@ -860,6 +861,10 @@ struct DiffeditArgs {
     /// Edit changes in this revision. Defaults to @ if --from is specified.
     #[arg(long, conflicts_with = "revision")]
     to: Option<RevisionArg>,
+    /// Keep only these hunks of the diff instead of running the diff editor
+    /// (see `jj split --help` for the format)
+    #[arg(long, value_name = "HUNKS")]
+    hunks: Option<String>,
 }
 
 /// Split a revision in two
@@ -882,6 +887,18 @@ struct SplitArgs {
     /// Put these paths in the first commit and don't run the diff editor
     #[arg(value_hint = clap::ValueHint::AnyPath)]
     paths: Vec<String>,
+    /// Put these hunks in the first commit and don't run the diff editor
+    ///
+    /// Hunks are given as a list of files and hunk numbers like `src/a.rs:1,3;
+    /// src/b.rs:*`. The hunks of a file are its runs of changed lines, as well
+    /// as any file mode or binary change, numbered from 1 in the order the
+    /// builtin diff editor shows them. `*` selects all hunks in a file, and
+    /// `2-4` selects a range of hunks.
+    ///
+    /// If `-` is given, a patch containing the hunks to select is read from
+    /// stdin, e.g. an edited version of the output of `jj diff --git`.
+    #[arg(long, value_name = "HUNKS", conflicts_with = "paths")]
+    hunks: Option<String>,
 }
 
 /// Move revisions to different parent(s)
@@ -1060,10 +1077,20 @@ struct WorkspaceUpdateStaleArgs {}
 
 /// Manage which paths from the working-copy commit are present in the working
 /// copy
+///
+/// Each pattern is a path, which includes that path and everything below it,
+/// or a glob such as `services/*/src`, which includes every matching path and
+/// everything below them. In globs, `*` doesn't match `/`, but `**` matches any
+/// number of directories. A pattern prefixed by `!` excludes the paths it
+/// matches instead. A path is present in the working copy if any pattern
+/// includes it and no pattern excludes it, regardless of the order of the
+/// patterns.
 #[derive(Subcommand, Clone, Debug)]
 enum SparseArgs {
     List(SparseListArgs),
     Set(SparseSetArgs),
+    Reset(SparseResetArgs),
+    Edit(SparseEditArgs),
 }
 
 /// List the patterns that are currently present in the working copy
@@ -1079,6 +1106,7 @@ struct SparseListArgs {}
 /// For example, if all you need is the `README.md` and the `lib/`
 /// directory, use `jj sparse set --clear --add README.md --add lib`.
 /// If you no longer need the `lib` directory, use `jj sparse set --remove lib`.
+/// To leave out all test data, use `jj sparse set --add '!**/testdata'`.
 #[derive(clap::Args, Clone, Debug)]
 struct SparseSetArgs {
     /// Patterns to add to the working copy
@@ -1098,12 +1126,23 @@ struct SparseSetArgs {
     reset: bool,
 }
 
+/// Reset the patterns to include all files in the working copy
+#[derive(clap::Args, Clone, Debug)]
+struct SparseResetArgs {}
+
+/// Start an editor to update the patterns that are present in the working copy
+#[derive(clap::Args, Clone, Debug)]
+struct SparseEditArgs {}
+
 /// Infrequently used commands such as for generating shell completions
 #[derive(Subcommand, Clone, Debug)]
 enum UtilCommands {
     Completion(UtilCompletionArgs),
     Mangen(UtilMangenArgs),
     ConfigSchema(UtilConfigSchemaArgs),
+    #[command(subcommand)]
+    Fsmonitor(UtilFsmonitorCommands),
+    Gc(UtilGcArgs),
 }
 
 /// Print a command-line-completion script
@@ -1143,6 +1182,45 @@ struct UtilMangenArgs {}
 #[derive(clap::Args, Clone, Debug)]
 struct UtilConfigSchemaArgs {}
 
+/// Manage the built-in filesystem monitor
+///
+/// The filesystem monitor is a background process that watches the working
+/// copy using inotify. Set `core.fsmonitor = "inotify"` to make snapshots ask
+/// it for the changed files instead of scanning the whole working copy. Only
+/// supported on Linux.
+#[derive(Subcommand, Clone, Debug)]
+enum UtilFsmonitorCommands {
+    /// Start the filesystem monitor in the background
+    Start,
+    /// Stop the filesystem monitor
+    Stop,
+    /// Show whether the filesystem monitor is running
+    Status,
+    /// Run the filesystem monitor in the foreground
+    Run,
+}
+
+/// Run backend-dependent garbage collection.
+///
+/// The operation log is truncated so that operations older than the expiry
+/// time are no longer reachable from the current operation heads. Unreachable
+/// operations (including ones written by interrupted commands) are removed
+/// together with their views. Commits which aren't reachable from any of the
+/// remaining operations are no longer protected from garbage collection of the
+/// backing Git repo, which is then packed.
+///
+/// Repos with multiple workspaces can't be garbage collected, since the other
+/// workspaces could be left pointing to removed operations.
+#[derive(clap::Args, Clone, Debug)]
+struct UtilGcArgs {
+    /// Only keep operations and objects newer than this
+    ///
+    /// The duration is a number followed by a unit (`s`, `m`, `h`, `d`, or
+    /// `w`), such as `30d`, or `now` to remove all unreachable objects.
+    #[arg(long, default_value = "2w", value_parser = parse_expire_duration)]
+    expire: Duration,
+}
+
 #[instrument(skip_all)]
 fn cmd_version(
     ui: &mut Ui,
@@ -1420,6 +1498,7 @@ fn cmd_untrack(
         fsmonitor_kind: command.settings().fsmonitor_kind()?,
         progress: None,
         max_new_file_size: command.settings().max_new_file_size()?,
+        conflict_marker_style: command.settings().conflict_marker_style()?,
     })?;
     if wc_tree_id != new_tree_id {
         let wc_tree = store.get_root_tree(&wc_tree_id)?;
@@ -1492,7 +1571,14 @@ fn cmd_cat(ui: &mut Ui, command: &CommandHelper, args: &CatArgs) -> Result<(), C
         }
         Err(conflict) => {
             let mut contents = vec![];
-            conflicts::materialize(&conflict, repo.store(), &path, &mut contents).unwrap();
+            conflicts::materialize(
+                &conflict,
+                repo.store(),
+                &path,
+                command.settings().conflict_marker_style()?,
+                &mut contents,
+            )
+            .unwrap();
             ui.request_pager();
             ui.stdout_formatter().write_all(&contents)?;
         }
@@ -1583,7 +1669,7 @@ fn cmd_status(
             diff_util::show_diff_summary(
                 formatter,
                 &workspace_command,
-                parent_tree.diff(&tree, &EverythingMatcher),
+                &copies::diff_with_copies(&parent_tree, &tree, &EverythingMatcher)?,
             )?;
         }
 
@@ -1673,13 +1759,9 @@ fn cmd_log(ui: &mut Ui, command: &CommandHelper, args: &LogArgs) -> Result<(), C
             RevsetExpression::union_all(&expressions)
         };
         if !args.paths.is_empty() {
-            let repo_paths: Vec<_> = args
-                .paths
-                .iter()
-                .map(|path_arg| workspace_command.parse_file_path(path_arg))
-                .try_collect()?;
+            let fileset_expression = workspace_command.parse_file_patterns(&args.paths)?;
             expression = expression.intersection(&RevsetExpression::filter(
-                RevsetFilterPredicate::File(Some(repo_paths)),
+                RevsetFilterPredicate::File(fileset_expression),
             ));
         }
         revset::optimize(expression)
@@ -2016,29 +2098,18 @@ fn edit_description(
 fn edit_sparse(
     workspace_root: &Path,
     repo_path: &Path,
-    sparse: &[RepoPath],
+    sparse: &[SparsePattern],
     settings: &UserSettings,
-) -> Result<Vec<RepoPath>, CommandError> {
+) -> Result<Vec<SparsePattern>, CommandError> {
     let file = (|| -> Result<_, io::Error> {
         let mut file = tempfile::Builder::new()
             .prefix("editor-")
             .suffix(".jjsparse")
             .tempfile_in(repo_path)?;
-        for sparse_path in sparse {
-            let workspace_relative_sparse_path =
-                file_util::relative_path(workspace_root, &sparse_path.to_fs_path(workspace_root));
+        for pattern in sparse {
             file.write_all(
-                workspace_relative_sparse_path
-                    .to_str()
-                    .ok_or_else(|| {
-                        io::Error::new(
-                            io::ErrorKind::InvalidData,
-                            format!(
-                                "stored sparse path is not valid utf-8: {}",
-                                workspace_relative_sparse_path.display()
-                            ),
-                        )
-                    })?
+                pattern
+                    .to_fs_string(workspace_root, workspace_root)
                     .as_bytes(),
             )?;
             file.write_all(b"\n")?;
@@ -2071,7 +2142,7 @@ fn edit_sparse(
                     path = file_path.display()
                 ))
             })?;
-            Ok::<_, CommandError>(RepoPath::parse_fs_path(
+            Ok::<_, CommandError>(SparsePattern::parse_fs_pattern(
                 workspace_root,
                 workspace_root,
                 line.trim(),
@@ -2088,7 +2159,7 @@ fn cmd_describe(
 ) -> Result<(), CommandError> {
     let mut workspace_command = command.workspace_helper(ui)?;
     let commit = workspace_command.resolve_single_rev(&args.revision, ui)?;
-    workspace_command.check_rewritable(&commit)?;
+    workspace_command.check_rewritable([&commit])?;
     let description = if args.stdin {
         let mut buffer = String::new();
         io::stdin().read_to_string(&mut buffer).unwrap();
@@ -2173,10 +2244,7 @@ fn cmd_duplicate(
     let mut workspace_command = command.workspace_helper(ui)?;
     let to_duplicate: IndexSet<Commit> =
         resolve_multiple_nonempty_revsets(&args.revisions, &workspace_command, ui)?;
-    to_duplicate
-        .iter()
-        .map(|commit| workspace_command.check_rewritable(commit))
-        .try_collect()?;
+    workspace_command.check_rewritable(&to_duplicate)?;
     let mut duplicated_old_to_new: IndexMap<Commit, Commit> = IndexMap::new();
 
     let mut tx = workspace_command
@@ -2231,10 +2299,7 @@ fn cmd_abandon(
 ) -> Result<(), CommandError> {
     let mut workspace_command = command.workspace_helper(ui)?;
     let to_abandon = resolve_multiple_nonempty_revsets(&args.revisions, &workspace_command, ui)?;
-    to_abandon
-        .iter()
-        .map(|commit| workspace_command.check_rewritable(commit))
-        .try_collect()?;
+    workspace_command.check_rewritable(&to_abandon)?;
     let transaction_description = if to_abandon.len() == 1 {
         format!("abandon commit {}", to_abandon[0].id().hex())
     } else {
@@ -2280,7 +2345,7 @@ fn cmd_abandon(
 fn cmd_edit(ui: &mut Ui, command: &CommandHelper, args: &EditArgs) -> Result<(), CommandError> {
     let mut workspace_command = command.workspace_helper(ui)?;
     let new_commit = workspace_command.resolve_single_rev(&args.revision, ui)?;
-    workspace_command.check_rewritable(&new_commit)?;
+    workspace_command.check_rewritable([&new_commit])?;
     if workspace_command.get_wc_commit_id() == Some(new_commit.id()) {
         ui.write("Already editing that commit\n")?;
     } else {
@@ -2338,6 +2403,8 @@ Please use `jj new 'all:x|y'` instead of `jj new --allow-large-revsets x y`.",
         if target_ids.contains(root_commit.id()) {
             return Err(user_error("Cannot insert a commit before the root commit"));
         }
+        tx.base_workspace_helper()
+            .check_rewritable(&target_commits)?;
         let new_children = RevsetExpression::commits(target_ids.clone());
         let new_parents = new_children.parents();
         if let Some(commit_id) = new_children
@@ -2399,6 +2466,8 @@ Please use `jj new 'all:x|y'` instead of `jj new --allow-large-revsets x y`.",
                 .iter()
                 .commits(tx.base_repo().store())
                 .try_collect()?;
+            tx.base_workspace_helper()
+                .check_rewritable(&commits_to_rebase)?;
             num_rebased = commits_to_rebase.len();
             for child_commit in commits_to_rebase {
                 let commit_parents =
@@ -2480,7 +2549,7 @@ fn cmd_next(ui: &mut Ui, command: &CommandHelper, args: &NextArgs) -> Result<(),
     // We're editing, just move to the target commit.
     if edit {
         // We're editing, the target must be rewritable.
-        workspace_command.check_rewritable(target)?;
+        workspace_command.check_rewritable([target])?;
         let mut tx = workspace_command
             .start_transaction(&format!("next: {current_short} -> editing {target_short}"));
         tx.edit(target)?;
@@ -2545,7 +2614,7 @@ fn cmd_prev(ui: &mut Ui, command: &CommandHelper, args: &PrevArgs) -> Result<(),
     // If we're editing, just move to the revision directly.
     if edit {
         // The target must be rewritable if we're editing.
-        workspace_command.check_rewritable(target)?;
+        workspace_command.check_rewritable([target])?;
         let mut tx = workspace_command
             .start_transaction(&format!("prev: {current_short} -> editing {target_short}"));
         tx.edit(target)?;
@@ -2594,9 +2663,13 @@ fn cmd_move(ui: &mut Ui, command: &CommandHelper, args: &MoveArgs) -> Result<(),
     if source.id() == destination.id() {
         return Err(user_error("Source and destination cannot be the same."));
     }
-    workspace_command.check_rewritable(&source)?;
-    workspace_command.check_rewritable(&destination)?;
+    workspace_command.check_rewritable([&source, &destination])?;
     let matcher = workspace_command.matcher_from_values(&args.paths)?;
+    let hunks = args
+        .hunks
+        .as_deref()
+        .map(|value| workspace_command.parse_hunk_selection(value))
+        .transpose()?;
     let mut tx = workspace_command.start_transaction(&format!(
         "move changes from {} to {}",
         source.id().hex(),
@@ -2620,15 +2693,19 @@ from the source will be moved into the destination.
         tx.format_commit_summary(&source),
         tx.format_commit_summary(&destination)
     );
-    let new_parent_tree_id = tx.select_diff(
-        ui,
-        &parent_tree,
-        &source_tree,
-        &instructions,
-        args.interactive,
-        matcher.as_ref(),
-    )?;
-    if args.interactive && new_parent_tree_id == parent_tree.id() {
+    let new_parent_tree_id = if let Some(hunks) = &hunks {
+        tx.select_hunks(&parent_tree, &source_tree, hunks)?
+    } else {
+        tx.select_diff(
+            ui,
+            &parent_tree,
+            &source_tree,
+            &instructions,
+            args.interactive,
+            matcher.as_ref(),
+        )?
+    };
+    if (args.interactive || hunks.is_some()) && new_parent_tree_id == parent_tree.id() {
         return Err(user_error("No changes to move"));
     }
     let new_parent_tree = tx.repo().store().get_root_tree(&new_parent_tree_id)?;
@@ -2676,14 +2753,19 @@ from the source will be moved into the destination.
 fn cmd_squash(ui: &mut Ui, command: &CommandHelper, args: &SquashArgs) -> Result<(), CommandError> {
     let mut workspace_command = command.workspace_helper(ui)?;
     let commit = workspace_command.resolve_single_rev(&args.revision, ui)?;
-    workspace_command.check_rewritable(&commit)?;
+    workspace_command.check_rewritable([&commit])?;
     let parents = commit.parents();
     if parents.len() != 1 {
         return Err(user_error("Cannot squash merge commits"));
     }
     let parent = &parents[0];
-    workspace_command.check_rewritable(parent)?;
+    workspace_command.check_rewritable([parent])?;
     let matcher = workspace_command.matcher_from_values(&args.paths)?;
+    let hunks = args
+        .hunks
+        .as_deref()
+        .map(|value| workspace_command.parse_hunk_selection(value))
+        .transpose()?;
     let mut tx =
         workspace_command.start_transaction(&format!("squash commit {}", commit.id().hex()));
     let instructions = format!(
@@ -2704,16 +2786,20 @@ from the source will be moved into the parent.
     );
     let parent_tree = parent.tree()?;
     let tree = commit.tree()?;
-    let new_parent_tree_id = tx.select_diff(
-        ui,
-        &parent_tree,
-        &tree,
-        &instructions,
-        args.interactive,
-        matcher.as_ref(),
-    )?;
+    let new_parent_tree_id = if let Some(hunks) = &hunks {
+        tx.select_hunks(&parent_tree, &tree, hunks)?
+    } else {
+        tx.select_diff(
+            ui,
+            &parent_tree,
+            &tree,
+            &instructions,
+            args.interactive,
+            matcher.as_ref(),
+        )?
+    };
     if &new_parent_tree_id == parent.tree_id() {
-        if args.interactive {
+        if args.interactive || hunks.is_some() {
             return Err(user_error("No changes selected"));
         }
 
@@ -2776,13 +2862,13 @@ fn cmd_unsquash(
 ) -> Result<(), CommandError> {
     let mut workspace_command = command.workspace_helper(ui)?;
     let commit = workspace_command.resolve_single_rev(&args.revision, ui)?;
-    workspace_command.check_rewritable(&commit)?;
+    workspace_command.check_rewritable([&commit])?;
     let parents = commit.parents();
     if parents.len() != 1 {
         return Err(user_error("Cannot unsquash merge commits"));
     }
     let parent = &parents[0];
-    workspace_command.check_rewritable(parent)?;
+    workspace_command.check_rewritable([parent])?;
     let mut tx =
         workspace_command.start_transaction(&format!("unsquash commit {}", commit.id().hex()));
     let parent_base_tree = merge_commit_trees(tx.repo(), &parent.parents())?;
@@ -2854,7 +2940,7 @@ fn cmd_chmod(ui: &mut Ui, command: &CommandHelper, args: &ChmodArgs) -> Result<(
         .map(|path| workspace_command.parse_file_path(path))
         .try_collect()?;
     let commit = workspace_command.resolve_single_rev(&args.revision, ui)?;
-    workspace_command.check_rewritable(&commit)?;
+    workspace_command.check_rewritable([&commit])?;
 
     let mut tx = workspace_command.start_transaction(&format!(
         "make paths {} in commit {}",
@@ -2946,7 +3032,7 @@ fn cmd_resolve(
     };
 
     let (repo_path, _) = conflicts.get(0).unwrap();
-    workspace_command.check_rewritable(&commit)?;
+    workspace_command.check_rewritable([&commit])?;
     let mut tx = workspace_command.start_transaction(&format!(
         "Resolve conflicts in commit {}",
         commit.id().hex()
@@ -3090,9 +3176,19 @@ fn cmd_restore(
             workspace_command.resolve_single_rev(args.changes_in.as_deref().unwrap_or("@"), ui)?;
         from_tree = merge_commit_trees(workspace_command.repo().as_ref(), &to_commit.parents())?;
     }
-    workspace_command.check_rewritable(&to_commit)?;
+    workspace_command.check_rewritable([&to_commit])?;
 
-    let new_tree_id = if args.paths.is_empty() {
+    let new_tree_id = if let Some(value) = &args.hunks {
+        let hunks = workspace_command.parse_hunk_selection(value)?;
+        let to_tree = to_commit.tree()?;
+        let selected_tree_id = crate::merge_tools::select_hunks(&from_tree, &to_tree, &hunks)?;
+        let selected_tree = workspace_command
+            .repo()
+            .store()
+            .get_root_tree(&selected_tree_id)?;
+        // Undo the selected changes
+        to_tree.merge(&selected_tree, &from_tree)?.id()
+    } else if args.paths.is_empty() {
         from_tree.id().clone()
     } else {
         let matcher = workspace_command.matcher_from_values(&args.paths)?;
@@ -3145,7 +3241,12 @@ fn cmd_diffedit(
         base_commits = target_commit.parents();
         diff_description = "The diff initially shows the commit's changes.".to_string();
     };
-    workspace_command.check_rewritable(&target_commit)?;
+    workspace_command.check_rewritable([&target_commit])?;
+    let hunks = args
+        .hunks
+        .as_deref()
+        .map(|value| workspace_command.parse_hunk_selection(value))
+        .transpose()?;
 
     let mut tx =
         workspace_command.start_transaction(&format!("edit commit {}", target_commit.id().hex()));
@@ -3161,7 +3262,11 @@ don't make any changes, then the operation will be aborted.",
     );
     let base_tree = merge_commit_trees(tx.repo(), base_commits.as_slice())?;
     let tree = target_commit.tree()?;
-    let tree_id = tx.edit_diff(ui, &base_tree, &tree, &instructions)?;
+    let tree_id = if let Some(hunks) = &hunks {
+        tx.select_hunks(&base_tree, &tree, hunks)?
+    } else {
+        tx.edit_diff(ui, &base_tree, &tree, &instructions)?
+    };
     if tree_id == *target_commit.tree_id() {
         ui.write("Nothing changed.\n")?;
     } else {
@@ -3245,13 +3350,18 @@ fn diff_summary_to_description(bytes: &[u8]) -> String {
 fn cmd_split(ui: &mut Ui, command: &CommandHelper, args: &SplitArgs) -> Result<(), CommandError> {
     let mut workspace_command = command.workspace_helper(ui)?;
     let commit = workspace_command.resolve_single_rev(&args.revision, ui)?;
-    workspace_command.check_rewritable(&commit)?;
+    workspace_command.check_rewritable([&commit])?;
     let matcher = workspace_command.matcher_from_values(&args.paths)?;
+    let hunks = args
+        .hunks
+        .as_deref()
+        .map(|value| workspace_command.parse_hunk_selection(value))
+        .transpose()?;
     let mut tx =
         workspace_command.start_transaction(&format!("split commit {}", commit.id().hex()));
     let end_tree = commit.tree()?;
     let base_tree = merge_commit_trees(tx.repo(), &commit.parents())?;
-    let interactive = args.paths.is_empty();
+    let interactive = args.paths.is_empty() && hunks.is_none();
     let instructions = format!(
         "\
 You are splitting a commit in two: {}
@@ -3264,14 +3374,18 @@ don't make any changes, then the operation will be aborted.
 ",
         tx.format_commit_summary(&commit)
     );
-    let tree_id = tx.select_diff(
-        ui,
-        &base_tree,
-        &end_tree,
-        &instructions,
-        interactive,
-        matcher.as_ref(),
-    )?;
+    let tree_id = if let Some(hunks) = &hunks {
+        tx.select_hunks(&base_tree, &end_tree, hunks)?
+    } else {
+        tx.select_diff(
+            ui,
+            &base_tree,
+            &end_tree,
+            &instructions,
+            interactive,
+            matcher.as_ref(),
+        )?
+    };
     if &tree_id == commit.tree_id() && interactive {
         ui.write("Nothing changed.\n")?;
         return Ok(());
@@ -3354,11 +3468,6 @@ fn cmd_merge(ui: &mut Ui, command: &CommandHelper, args: &NewArgs) -> Result<(),
     cmd_new(ui, command, args)
 }
 
-// TODO: Move to run.rs
-fn cmd_run(_ui: &mut Ui, _command: &CommandHelper, _args: &RunArgs) -> Result<(), CommandError> {
-    Err(user_error("This is a stub, do not use"))
-}
-
 #[instrument(skip_all)]
 fn cmd_rebase(ui: &mut Ui, command: &CommandHelper, args: &RebaseArgs) -> Result<(), CommandError> {
     if args.allow_large_revsets {
@@ -3442,8 +3551,8 @@ fn rebase_descendants(
     new_parents: &[Commit],
     old_commits: &IndexSet<Commit>,
 ) -> Result<(), CommandError> {
+    workspace_command.check_rewritable(old_commits)?;
     for old_commit in old_commits.iter() {
-        workspace_command.check_rewritable(old_commit)?;
         check_rebase_destinations(workspace_command.repo(), new_parents, old_commit)?;
     }
     let tx_message = if old_commits.len() == 1 {
@@ -3474,7 +3583,7 @@ fn rebase_revision(
     rev_str: &str,
 ) -> Result<(), CommandError> {
     let old_commit = workspace_command.resolve_single_rev(rev_str, ui)?;
-    workspace_command.check_rewritable(&old_commit)?;
+    workspace_command.check_rewritable([&old_commit])?;
     check_rebase_destinations(workspace_command.repo(), new_parents, &old_commit)?;
     let children_expression = RevsetExpression::commit(old_commit.id().clone()).children();
     let child_commits: Vec<_> = children_expression
@@ -3599,6 +3708,13 @@ fn make_branch_term(branch_names: &[impl AsRef<str>]) -> String {
     }
 }
 
+fn make_tag_term(tag_names: &[impl AsRef<str>]) -> String {
+    match tag_names {
+        [tag_name] => format!("tag {}", tag_name.as_ref()),
+        tag_names => format!("tags {}", tag_names.iter().map(AsRef::as_ref).join(", ")),
+    }
+}
+
 #[instrument(skip_all)]
 fn cmd_util(
     ui: &mut Ui,
@@ -3630,10 +3746,265 @@ fn cmd_util(
             let buf = include_bytes!("../config-schema.json");
             ui.stdout_formatter().write_all(buf)?;
         }
+        UtilCommands::Fsmonitor(fsmonitor_subcommand) => {
+            cmd_util_fsmonitor(ui, command, fsmonitor_subcommand)?;
+        }
+        UtilCommands::Gc(gc_matches) => cmd_util_gc(ui, command, gc_matches)?,
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[instrument(skip_all)]
+fn cmd_util_fsmonitor(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    subcommand: &UtilFsmonitorCommands,
+) -> Result<(), CommandError> {
+    use std::os::unix::process::CommandExt as _;
+    use std::process::Stdio;
+    use std::time::Instant;
+
+    use jj_lib::fsmonitor::inotify;
+
+    // Watching a large working copy can take a while
+    const START_TIMEOUT: Duration = Duration::from_secs(60);
+
+    let workspace_command = command.workspace_helper_no_snapshot(ui)?;
+    let workspace_root = workspace_command.workspace_root().clone();
+    let state_path = workspace_command.working_copy().state_path();
+    let socket_path = state_path.join(inotify::SOCKET_FILE_NAME);
+    let fsmonitor_error = |err: inotify::Error| user_error(err.to_string());
+    match subcommand {
+        UtilFsmonitorCommands::Start => {
+            match inotify::query_status(&socket_path) {
+                Ok(status) => {
+                    writeln!(
+                        ui,
+                        "The filesystem monitor is already running (pid {})",
+                        status.pid
+                    )?;
+                    return Ok(());
+                }
+                Err(inotify::Error::NotRunning) => {}
+                Err(err) => return Err(fsmonitor_error(err)),
+            }
+            let log_path = state_path.join("fsmonitor.log");
+            let log_file = fs::File::create(&log_path)?;
+            let mut child = std::process::Command::new(std::env::current_exe()?)
+                .args(["util", "fsmonitor", "run", "-R"])
+                .arg(&workspace_root)
+                .current_dir(&workspace_root)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(log_file)
+                .process_group(0)
+                .spawn()?;
+            // Queries are accepted once the whole working copy is being watched
+            let start_time = Instant::now();
+            loop {
+                match inotify::query_status(&socket_path) {
+                    Ok(status) => {
+                        writeln!(ui, "Started the filesystem monitor (pid {})", status.pid)?;
+                        break;
+                    }
+                    Err(inotify::Error::NotRunning) => {}
+                    Err(err) => return Err(fsmonitor_error(err)),
+                }
+                if let Some(exit_status) = child.try_wait()? {
+                    return Err(user_error_with_hint(
+                        format!("The filesystem monitor exited unexpectedly ({exit_status})"),
+                        format!("See {} for details", log_path.display()),
+                    ));
+                }
+                if start_time.elapsed() > START_TIMEOUT {
+                    child.kill().ok();
+                    return Err(user_error_with_hint(
+                        format!(
+                            "The filesystem monitor didn't start within {} seconds",
+                            START_TIMEOUT.as_secs()
+                        ),
+                        format!("See {} for details", log_path.display()),
+                    ));
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+        }
+        UtilFsmonitorCommands::Stop => match inotify::stop(&socket_path) {
+            Ok(()) => writeln!(ui, "Stopped the filesystem monitor")?,
+            Err(inotify::Error::NotRunning) => {
+                writeln!(ui, "The filesystem monitor is not running")?;
+            }
+            Err(err) => return Err(fsmonitor_error(err)),
+        },
+        UtilFsmonitorCommands::Status => match inotify::query_status(&socket_path) {
+            Ok(status) => writeln!(
+                ui,
+                "The filesystem monitor is running (pid {}, watching {} directories)",
+                status.pid, status.num_watches
+            )?,
+            Err(inotify::Error::NotRunning) => {
+                writeln!(ui, "The filesystem monitor is not running")?;
+            }
+            Err(err) => return Err(fsmonitor_error(err)),
+        },
+        UtilFsmonitorCommands::Run => {
+            let daemon =
+                inotify::Daemon::start(&workspace_root, &socket_path).map_err(fsmonitor_error)?;
+            daemon.run().map_err(fsmonitor_error)?;
+        }
     }
     Ok(())
 }
 
+#[cfg(not(target_os = "linux"))]
+fn cmd_util_fsmonitor(
+    _ui: &mut Ui,
+    _command: &CommandHelper,
+    _subcommand: &UtilFsmonitorCommands,
+) -> Result<(), CommandError> {
+    Err(user_error(
+        "The built-in filesystem monitor is only supported on Linux",
+    ))
+}
+
+#[instrument(skip_all)]
+fn cmd_util_gc(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &UtilGcArgs,
+) -> Result<(), CommandError> {
+    if command.global_args().at_operation != "@" {
+        return Err(user_error(
+            "Cannot garbage collect from a non-head operation",
+        ));
+    }
+    let keep_newer = SystemTime::now()
+        .checked_sub(args.expire)
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let repo = workspace_command.repo().clone();
+    // The working copies of other workspaces record the operation they were
+    // last updated at, which may be removed. Their paths aren't known, so
+    // they can't be updated.
+    let workspace_id = workspace_command.workspace_id();
+    if repo
+        .view()
+        .wc_commit_ids()
+        .keys()
+        .any(|id| id != workspace_id)
+    {
+        return Err(user_error_with_hint(
+            "Cannot garbage collect a repo with multiple workspaces",
+            "Other workspaces may point to operations that would be removed. Forget them \
+             with `jj workspace forget` first.",
+        ));
+    }
+    let op_store = repo.op_store();
+    let op_heads_store = repo.op_heads_store();
+    // Cut the operation log at the expiry time under the lock so that no new
+    // operation can be added on top of the old heads in the meantime.
+    let stats = {
+        let _lock = op_heads_store.lock();
+        let old_head_ids = op_heads_store.get_op_heads();
+        let old_head_ops: Vec<_> = old_head_ids
+            .iter()
+            .map(|id| -> Result<_, CommandError> {
+                let data = op_store.read_operation(id)?;
+                Ok(Operation::new(op_store.clone(), id.clone(), data))
+            })
+            .try_collect()?;
+        let stats = truncate_ancestors(op_store.as_ref(), &old_head_ops, keep_newer)?;
+        for new_head_id in &stats.new_head_ids {
+            op_heads_store.add_op_head(new_head_id);
+        }
+        for old_head_id in old_head_ids
+            .iter()
+            .filter(|id| !stats.new_head_ids.contains(id))
+        {
+            op_heads_store.remove_op_head(old_head_id);
+        }
+        stats
+    };
+    // Keep the working copy pointing to a reachable operation.
+    if !stats.rewritten_ids.is_empty() {
+        let (locked_wc, _) = workspace_command.unchecked_start_working_copy_mutation()?;
+        if let Some(new_op_id) = stats.rewritten_ids.get(locked_wc.old_operation_id()) {
+            locked_wc.finish(new_op_id.clone())?;
+        }
+    }
+
+    op_store.gc(&stats.new_head_ids, keep_newer)?;
+    let heads = collect_heads_for_gc(&repo, &stats.new_head_ids)?;
+    repo.store().gc(&heads, keep_newer)?;
+    Ok(())
+}
+
+/// Collects the heads of all commits referenced by the operations reachable
+/// from `op_head_ids`. `op_head_ids` should be the heads of the truncated
+/// operation log so that commits only referenced by the removed operations
+/// aren't included.
+fn collect_heads_for_gc(
+    repo: &ReadonlyRepo,
+    op_head_ids: &[OperationId],
+) -> Result<Vec<CommitId>, CommandError> {
+    let op_store = repo.op_store();
+    let mut commit_ids = HashSet::new();
+    let mut visited_op_ids = HashSet::new();
+    let mut pending_op_ids = op_head_ids.to_vec();
+    while let Some(op_id) = pending_op_ids.pop() {
+        if !visited_op_ids.insert(op_id.clone()) {
+            continue;
+        }
+        let operation = op_store.read_operation(&op_id)?;
+        let view = op_store.read_view(&operation.view_id)?;
+        commit_ids.extend(view.head_ids);
+        commit_ids.extend(view.public_head_ids);
+        commit_ids.extend(view.wc_commit_ids.into_values());
+        let branch_targets = view.branches.into_values().flat_map(|branch_target| {
+            iter::once(branch_target.local_target).chain(branch_target.remote_targets.into_values())
+        });
+        for target in branch_targets
+            .chain(view.tags.into_values())
+            .chain(view.git_refs.into_values())
+            .chain(iter::once(view.git_head))
+        {
+            commit_ids.extend(target.added_ids().cloned());
+            commit_ids.extend(target.removed_ids().cloned());
+        }
+        pending_op_ids.extend(operation.parents);
+    }
+    // All commits referenced by the kept operations should be indexed, but
+    // keep any that aren't rather than losing them.
+    let index = repo.index();
+    let (indexed_ids, mut heads): (Vec<_>, Vec<_>) =
+        commit_ids.into_iter().partition(|id| index.has_id(id));
+    heads.extend(index.heads(&mut indexed_ids.iter()));
+    Ok(heads)
+}
+
+fn parse_expire_duration(s: &str) -> Result<Duration, String> {
+    if s == "now" {
+        return Ok(Duration::ZERO);
+    }
+    let unit_pos = s
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| "missing unit".to_owned())?;
+    let (value, unit) = s.split_at(unit_pos);
+    let value: u64 = value
+        .parse()
+        .map_err(|_| "expected a number followed by a unit".to_owned())?;
+    let unit_secs = match unit.trim_start() {
+        "s" | "sec" | "secs" | "second" | "seconds" => 1,
+        "m" | "min" | "mins" | "minute" | "minutes" => 60,
+        "h" | "hour" | "hours" => 60 * 60,
+        "d" | "day" | "days" => 24 * 60 * 60,
+        "w" | "week" | "weeks" => 7 * 24 * 60 * 60,
+        unit => return Err(format!(r#"invalid unit "{unit}""#)),
+    };
+    Ok(Duration::from_secs(value.saturating_mul(unit_secs)))
+}
+
 #[instrument(skip_all)]
 fn cmd_workspace(
     ui: &mut Ui,
@@ -3814,13 +4185,18 @@ fn cmd_workspace_update_stale(
                 return Err(user_error("Concurrent working copy operation. Try again."));
             }
             let desired_tree = desired_wc_commit.tree()?;
-            let stats = locked_wc.check_out(&desired_tree).map_err(|err| {
-                CommandError::InternalError(format!(
-                    "Failed to check out commit {}: {}",
-                    desired_wc_commit.id().hex(),
-                    err
-                ))
-            })?;
+            let options = CheckoutOptions {
+                conflict_marker_style: command.settings().conflict_marker_style()?,
+            };
+            let stats = locked_wc
+                .check_out(&desired_tree, &options)
+                .map_err(|err| {
+                    CommandError::InternalError(format!(
+                        "Failed to check out commit {}: {}",
+                        desired_wc_commit.id().hex(),
+                        err
+                    ))
+                })?;
             locked_wc.finish(repo.op_id().clone())?;
             ui.write("Working copy now at: ")?;
             ui.stdout_formatter().with_label("working_copy", |fmt| {
@@ -3838,6 +4214,8 @@ fn cmd_sparse(ui: &mut Ui, command: &CommandHelper, args: &SparseArgs) -> Result
     match args {
         SparseArgs::List(sub_args) => cmd_sparse_list(ui, command, sub_args),
         SparseArgs::Set(sub_args) => cmd_sparse_set(ui, command, sub_args),
+        SparseArgs::Reset(sub_args) => cmd_sparse_reset(ui, command, sub_args),
+        SparseArgs::Edit(sub_args) => cmd_sparse_edit(ui, command, sub_args),
     }
 }
 
@@ -3848,9 +4226,9 @@ fn cmd_sparse_list(
     _args: &SparseListArgs,
 ) -> Result<(), CommandError> {
     let workspace_command = command.workspace_helper(ui)?;
-    for path in workspace_command.working_copy().sparse_patterns()? {
-        let ui_path = workspace_command.format_file_path(path);
-        writeln!(ui, "{ui_path}")?;
+    for pattern in workspace_command.working_copy().sparse_patterns()? {
+        let ui_pattern = workspace_command.format_sparse_pattern(pattern);
+        writeln!(ui, "{ui_pattern}")?;
     }
     Ok(())
 }
@@ -3862,15 +4240,15 @@ fn cmd_sparse_set(
     args: &SparseSetArgs,
 ) -> Result<(), CommandError> {
     let mut workspace_command = command.workspace_helper(ui)?;
-    let paths_to_add: Vec<_> = args
+    let patterns_to_add: Vec<_> = args
         .add
         .iter()
-        .map(|v| workspace_command.parse_file_path(v))
+        .map(|v| workspace_command.parse_sparse_pattern(v))
         .try_collect()?;
-    let paths_to_remove: Vec<_> = args
+    let patterns_to_remove: Vec<_> = args
         .remove
         .iter()
-        .map(|v| workspace_command.parse_file_path(v))
+        .map(|v| workspace_command.parse_sparse_pattern(v))
         .try_collect()?;
     // Determine inputs of `edit` operation now, since `workspace_command` is
     // inaccessible while the working copy is locked.
@@ -3880,39 +4258,88 @@ fn cmd_sparse_set(
             workspace_command.workspace_root().clone(),
         )
     });
-    let (mut locked_wc, _wc_commit) = workspace_command.start_working_copy_mutation()?;
-    let mut new_patterns = HashSet::new();
-    if args.reset {
-        new_patterns.insert(RepoPath::root());
-    } else {
-        if !args.clear {
-            new_patterns.extend(locked_wc.sparse_patterns()?.iter().cloned());
-            for path in paths_to_remove {
-                new_patterns.remove(&path);
+    update_sparse_patterns_with(ui, command, &mut workspace_command, |old_patterns| {
+        let mut new_patterns = HashSet::new();
+        if args.reset {
+            new_patterns.insert(SparsePattern::root());
+        } else {
+            if !args.clear {
+                new_patterns.extend(old_patterns.iter().cloned());
+                for pattern in &patterns_to_remove {
+                    new_patterns.remove(pattern);
+                }
             }
+            new_patterns.extend(patterns_to_add);
         }
-        for path in paths_to_add {
-            new_patterns.insert(path);
+        let mut new_patterns = new_patterns.into_iter().collect_vec();
+        new_patterns.sort();
+        if let Some((repo, workspace_root)) = edit_inputs {
+            new_patterns = edit_sparse(
+                &workspace_root,
+                repo.repo_path(),
+                &new_patterns,
+                command.settings(),
+            )?;
+            new_patterns.sort();
         }
-    }
-    let mut new_patterns = new_patterns.into_iter().collect_vec();
-    new_patterns.sort();
-    if let Some((repo, workspace_root)) = edit_inputs {
-        new_patterns = edit_sparse(
+        Ok(new_patterns)
+    })
+}
+
+#[instrument(skip_all)]
+fn cmd_sparse_reset(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    _args: &SparseResetArgs,
+) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    update_sparse_patterns_with(ui, command, &mut workspace_command, |_old_patterns| {
+        Ok(vec![SparsePattern::root()])
+    })
+}
+
+#[instrument(skip_all)]
+fn cmd_sparse_edit(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    _args: &SparseEditArgs,
+) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let repo = workspace_command.repo().clone();
+    let workspace_root = workspace_command.workspace_root().clone();
+    update_sparse_patterns_with(ui, command, &mut workspace_command, |old_patterns| {
+        let mut new_patterns = edit_sparse(
             &workspace_root,
             repo.repo_path(),
-            &new_patterns,
+            old_patterns,
             command.settings(),
         )?;
         new_patterns.sort();
-    }
-    let stats = locked_wc.set_sparse_patterns(new_patterns).map_err(|err| {
-        CommandError::InternalError(format!("Failed to update working copy paths: {err}"))
-    })?;
+        Ok(new_patterns)
+    })
+}
+
+/// Replaces the sparse patterns by the result of `f` and updates the working
+/// copy accordingly.
+fn update_sparse_patterns_with(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    workspace_command: &mut WorkspaceCommandHelper,
+    f: impl FnOnce(&[SparsePattern]) -> Result<Vec<SparsePattern>, CommandError>,
+) -> Result<(), CommandError> {
+    let (mut locked_wc, _wc_commit) = workspace_command.start_working_copy_mutation()?;
+    let new_patterns = f(locked_wc.sparse_patterns()?)?;
+    let options = CheckoutOptions {
+        conflict_marker_style: command.settings().conflict_marker_style()?,
+    };
+    let stats = locked_wc
+        .set_sparse_patterns(new_patterns, &options)
+        .map_err(|err| {
+            CommandError::InternalError(format!("Failed to update working copy paths: {err}"))
+        })?;
     let operation_id = locked_wc.old_operation_id().clone();
     locked_wc.finish(operation_id)?;
     print_checkout_stats(ui, stats)?;
-
     Ok(())
 }
 
@@ -3930,6 +4357,7 @@ pub fn run_command(ui: &mut Ui, command_helper: &CommandHelper) -> Result<(), Co
         Commands::Config(sub_args) => cmd_config(ui, command_helper, sub_args),
         Commands::Checkout(sub_args) => cmd_checkout(ui, command_helper, sub_args),
         Commands::Untrack(sub_args) => cmd_untrack(ui, command_helper, sub_args),
+        Commands::File(sub_args) => file::cmd_file(ui, command_helper, sub_args),
         Commands::Files(sub_args) => cmd_files(ui, command_helper, sub_args),
         Commands::Cat(sub_args) => cmd_cat(ui, command_helper, sub_args),
         Commands::Diff(sub_args) => cmd_diff(ui, command_helper, sub_args),
@@ -3948,9 +4376,11 @@ pub fn run_command(ui: &mut Ui, command_helper: &CommandHelper) -> Result<(), Co
         Commands::New(sub_args) => cmd_new(ui, command_helper, sub_args),
         Commands::Move(sub_args) => cmd_move(ui, command_helper, sub_args),
         Commands::Squash(sub_args) => cmd_squash(ui, command_helper, sub_args),
+        Commands::Absorb(sub_args) => absorb::cmd_absorb(ui, command_helper, sub_args),
         Commands::Unsquash(sub_args) => cmd_unsquash(ui, command_helper, sub_args),
         Commands::Restore(sub_args) => cmd_restore(ui, command_helper, sub_args),
-        Commands::Run(sub_args) => cmd_run(ui, command_helper, sub_args),
+        Commands::Run(sub_args) => run::cmd_run(ui, command_helper, sub_args),
+        Commands::Fix(sub_args) => fix::cmd_fix(ui, command_helper, sub_args),
         Commands::Diffedit(sub_args) => cmd_diffedit(ui, command_helper, sub_args),
         Commands::Split(sub_args) => cmd_split(ui, command_helper, sub_args),
         Commands::Merge(sub_args) => cmd_merge(ui, command_helper, sub_args),
@@ -3958,8 +4388,10 @@ pub fn run_command(ui: &mut Ui, command_helper: &CommandHelper) -> Result<(), Co
         Commands::Backout(sub_args) => cmd_backout(ui, command_helper, sub_args),
         Commands::Resolve(sub_args) => cmd_resolve(ui, command_helper, sub_args),
         Commands::Branch(sub_args) => branch::cmd_branch(ui, command_helper, sub_args),
+        Commands::Tag(sub_args) => tag::cmd_tag(ui, command_helper, sub_args),
         Commands::Undo(sub_args) => operation::cmd_op_undo(ui, command_helper, sub_args),
         Commands::Operation(sub_args) => operation::cmd_operation(ui, command_helper, sub_args),
+        Commands::Patch(sub_args) => patch::cmd_patch(ui, command_helper, sub_args),
         Commands::Workspace(sub_args) => cmd_workspace(ui, command_helper, sub_args),
         Commands::Sparse(sub_args) => cmd_sparse(ui, command_helper, sub_args),
         Commands::Chmod(sub_args) => cmd_chmod(ui, command_helper, sub_args),
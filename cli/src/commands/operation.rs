@@ -1,12 +1,23 @@
 use std::collections::{BTreeMap, BTreeSet};
+use std::{io, slice};
 
 use clap::Subcommand;
-use jj_lib::backend::ObjectId;
+use itertools::Itertools as _;
+use jj_lib::backend::{CommitId, ObjectId};
 use jj_lib::op_store::{BranchTarget, RefTarget};
-use jj_lib::operation;
+use jj_lib::operation::{self, Operation};
+use jj_lib::refs::diff_named_ref_targets;
 use jj_lib::repo::Repo;
+use jj_lib::revset::{RevsetExpression, RevsetIteratorExt as _};
+use jj_lib::settings::UserSettings;
 
-use crate::cli_util::{user_error, CommandError, CommandHelper, LogContentFormat};
+use super::show_predecessor_patch;
+use crate::cli_util::{
+    short_operation_hash, user_error, CommandError, CommandHelper, LogContentFormat,
+    WorkspaceCommandTransaction,
+};
+use crate::diff_util::{self, DiffFormat, DiffFormatArgs};
+use crate::formatter::Formatter;
 use crate::graphlog::{get_graphlog, Edge};
 use crate::operation_templater;
 use crate::templater::Template as _;
@@ -18,7 +29,10 @@ use crate::ui::Ui;
 /// https://github.com/martinvonz/jj/blob/main/docs/operation-log.md.
 #[derive(Subcommand, Clone, Debug)]
 pub enum OperationCommands {
+    Abandon(OperationAbandonArgs),
+    Diff(OperationDiffArgs),
     Log(OperationLogArgs),
+    Show(OperationShowArgs),
     Undo(OperationUndoArgs),
     Restore(OperationRestoreArgs),
 }
@@ -39,6 +53,69 @@ pub struct OperationLogArgs {
     template: Option<String>,
 }
 
+/// Abandon operation history
+///
+/// To discard old operation history, use `jj op abandon ..<operation ID>`. It
+/// will abandon the specified operation and all its ancestors. The descendant
+/// operations will become the new start of the operation log.
+///
+/// To discard a range of operations, use `jj op abandon <from>..<to>`. It will
+/// abandon the operations after `<from>` up to and including `<to>`, and
+/// reparent the descendants onto `<from>`.
+///
+/// The current operation cannot be abandoned. Other workspaces which were last
+/// updated at an abandoned operation can be recovered by `jj workspace
+/// update-stale`. The abandoned operations can later be removed by `jj util
+/// gc`.
+#[derive(clap::Args, Clone, Debug)]
+pub struct OperationAbandonArgs {
+    /// The operation or operation range to abandon
+    operation: String,
+}
+
+/// Show changes to the repository in an operation
+///
+/// This lists the commits which became visible or hidden, and how the working
+/// copies, branches, tags, and git refs moved, compared to the parent
+/// operation(s).
+#[derive(clap::Args, Clone, Debug)]
+pub struct OperationShowArgs {
+    /// The operation to show
+    #[arg(default_value = "@")]
+    operation: String,
+    /// Show patch of the rewritten commits compared to their previous versions
+    ///
+    /// If the previous version has different parents, it will be temporarily
+    /// rebased to the parents of the new version, so the diff is not
+    /// contaminated by unrelated changes.
+    #[arg(long, short = 'p')]
+    patch: bool,
+    #[command(flatten)]
+    diff_format: DiffFormatArgs,
+}
+
+/// Compare changes to the repository between two operations
+#[derive(clap::Args, Clone, Debug)]
+pub struct OperationDiffArgs {
+    /// Show repository changes from this operation
+    ///
+    /// Defaults to the parent(s) of the `--to` operation.
+    #[arg(long)]
+    from: Option<String>,
+    /// Show repository changes to this operation
+    #[arg(long, default_value = "@")]
+    to: String,
+    /// Show patch of the rewritten commits compared to their previous versions
+    ///
+    /// If the previous version has different parents, it will be temporarily
+    /// rebased to the parents of the new version, so the diff is not
+    /// contaminated by unrelated changes.
+    #[arg(long, short = 'p')]
+    patch: bool,
+    #[command(flatten)]
+    diff_format: DiffFormatArgs,
+}
+
 /// Create a new operation that restores the repo to an earlier state
 ///
 /// This restores the repo to the state at the specified operation, effectively
@@ -167,6 +244,410 @@ fn cmd_op_log(
     Ok(())
 }
 
+fn cmd_op_abandon(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &OperationAbandonArgs,
+) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let repo = workspace_command.repo().clone();
+    let (abandon_head_op, dest_ops) =
+        if let Some((root_str, head_str)) = args.operation.split_once("..") {
+            let head_op = if head_str.is_empty() {
+                repo.operation().clone()
+            } else {
+                workspace_command.resolve_single_op(head_str)?
+            };
+            let dest_ops = if root_str.is_empty() {
+                vec![]
+            } else {
+                let root_op = workspace_command.resolve_single_op(root_str)?;
+                if !operation::walk_ancestors(&head_op).any(|op| op.id() == root_op.id()) {
+                    return Err(user_error(format!(
+                        "Operation {} is not an ancestor of {}",
+                        short_operation_hash(root_op.id()),
+                        short_operation_hash(head_op.id())
+                    )));
+                }
+                vec![root_op]
+            };
+            (head_op, dest_ops)
+        } else {
+            let op = workspace_command.resolve_single_op(&args.operation)?;
+            let parent_ops = op.parents();
+            (op, parent_ops)
+        };
+    if abandon_head_op.id() == repo.op_id() {
+        return Err(user_error("Cannot abandon the current operation"));
+    }
+    if dest_ops.iter().any(|op| op.id() == abandon_head_op.id()) {
+        writeln!(ui, "Nothing changed.")?;
+        return Ok(());
+    }
+
+    // Reparent the current op heads under the lock so that no new operation can
+    // be added on top of the old ones in the meantime.
+    let op_store = repo.op_store();
+    let op_heads_store = repo.op_heads_store();
+    let stats = {
+        let _lock = op_heads_store.lock();
+        let old_head_ids = op_heads_store.get_op_heads();
+        let old_head_ops: Vec<_> = old_head_ids
+            .iter()
+            .map(|id| -> Result<_, CommandError> {
+                let data = op_store.read_operation(id)?;
+                Ok(Operation::new(op_store.clone(), id.clone(), data))
+            })
+            .try_collect()?;
+        let stats = operation::reparent_range(
+            op_store.as_ref(),
+            slice::from_ref(&abandon_head_op),
+            &old_head_ops,
+            &dest_ops,
+        )?;
+        for new_head_id in &stats.new_head_ids {
+            op_heads_store.add_op_head(new_head_id);
+        }
+        for old_head_id in old_head_ids
+            .iter()
+            .filter(|id| !stats.new_head_ids.contains(id))
+        {
+            op_heads_store.remove_op_head(old_head_id);
+        }
+        stats
+    };
+    writeln!(
+        ui,
+        "Abandoned {} operations and reparented {} descendant operations.",
+        stats.unreachable_count,
+        stats.rewritten_ids.len(),
+    )?;
+
+    // Keep the working copy pointing to a reachable operation.
+    let (locked_wc, _) = workspace_command.unchecked_start_working_copy_mutation()?;
+    if let Some(new_op_id) = stats.rewritten_ids.get(locked_wc.old_operation_id()) {
+        locked_wc.finish(new_op_id.clone())?;
+    }
+    Ok(())
+}
+
+fn cmd_op_show(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &OperationShowArgs,
+) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let op = workspace_command.resolve_single_op(&args.operation)?;
+    let repo_loader = workspace_command.repo().loader();
+    let from_view = merged_parent_view(&repo_loader, command.settings(), &op)?;
+    let to_view = op.view()?.take_store_view();
+
+    let template_string = command.settings().config().get_string("templates.op_log")?;
+    let template = operation_templater::parse(
+        workspace_command.repo(),
+        &template_string,
+        workspace_command.template_aliases_map(),
+    )?;
+    let with_content_format = LogContentFormat::new(ui, command.settings())?;
+    let diff_formats =
+        diff_util::diff_formats_for_log(command.settings(), &args.diff_format, args.patch)?;
+
+    let mut tx = workspace_command.start_transaction("show operation");
+    ui.request_pager();
+    let mut formatter = ui.stdout_formatter();
+    let formatter = formatter.as_mut();
+    with_content_format.write(formatter, |formatter| {
+        formatter.with_label("op_log", |formatter| template.format(&op, formatter))
+    })?;
+    show_op_diff(
+        ui,
+        formatter,
+        &mut tx,
+        &from_view,
+        &to_view,
+        &diff_formats,
+        true,
+    )
+}
+
+fn cmd_op_diff(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &OperationDiffArgs,
+) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let to_op = workspace_command.resolve_single_op(&args.to)?;
+    let from_view = if let Some(from) = &args.from {
+        workspace_command
+            .resolve_single_op(from)?
+            .view()?
+            .take_store_view()
+    } else {
+        let repo_loader = workspace_command.repo().loader();
+        merged_parent_view(&repo_loader, command.settings(), &to_op)?
+    };
+    let to_view = to_op.view()?.take_store_view();
+    let diff_formats =
+        diff_util::diff_formats_for_log(command.settings(), &args.diff_format, args.patch)?;
+
+    let mut tx = workspace_command.start_transaction("diff operations");
+    ui.request_pager();
+    let mut formatter = ui.stdout_formatter();
+    show_op_diff(
+        ui,
+        formatter.as_mut(),
+        &mut tx,
+        &from_view,
+        &to_view,
+        &diff_formats,
+        false,
+    )
+}
+
+/// Returns the view of the parent operations of `op`, merged if there are
+/// many.
+fn merged_parent_view(
+    repo_loader: &jj_lib::repo::RepoLoader,
+    settings: &UserSettings,
+    op: &Operation,
+) -> Result<jj_lib::op_store::View, CommandError> {
+    let parent_ops = op.parents();
+    let Some((first_parent_op, other_parent_ops)) = parent_ops.split_first() else {
+        // The repo initialization starts from the state with only the root
+        // commit.
+        return Ok(jj_lib::op_store::View {
+            head_ids: [repo_loader.store().root_commit_id().clone()].into(),
+            ..Default::default()
+        });
+    };
+    let base_repo = repo_loader.load_at(first_parent_op)?;
+    let mut tx = base_repo.start_transaction(settings, "merge parent operations");
+    for other_op in other_parent_ops {
+        tx.merge_operation(other_op.clone())?;
+        tx.mut_repo().rebase_descendants(settings)?;
+    }
+    Ok(tx.repo().view().store_view().clone())
+}
+
+/// Shows the changes to the repository between the `from_view` and `to_view`.
+///
+/// The commits referenced by the views are added to the transaction to make
+/// them indexed, but the transaction is never committed. If `has_header` is
+/// set, the first section is separated from the preceding output.
+fn show_op_diff(
+    ui: &Ui,
+    formatter: &mut dyn Formatter,
+    tx: &mut WorkspaceCommandTransaction,
+    from_view: &jj_lib::op_store::View,
+    to_view: &jj_lib::op_store::View,
+    diff_formats: &[DiffFormat],
+    has_header: bool,
+) -> Result<(), CommandError> {
+    let mut needs_separator = has_header;
+    let store = tx.base_repo().store().clone();
+    let head_commits: Vec<_> = from_view
+        .head_ids
+        .iter()
+        .chain(&to_view.head_ids)
+        .unique()
+        .map(|id| store.get_commit(id))
+        .try_collect()?;
+    tx.mut_repo().add_heads(&head_commits);
+
+    let from_heads = RevsetExpression::commits(from_view.head_ids.iter().cloned().collect());
+    let to_heads = RevsetExpression::commits(to_view.head_ids.iter().cloned().collect());
+    let evaluate = |expression: std::rc::Rc<RevsetExpression>| -> Result<Vec<_>, CommandError> {
+        let repo = tx.repo();
+        let commits = expression
+            .resolve(repo)?
+            .evaluate(repo)?
+            .iter()
+            .commits(&store)
+            .try_collect()?;
+        Ok(commits)
+    };
+    let added_commits = evaluate(from_heads.range(&to_heads))?;
+    let removed_commits = evaluate(to_heads.range(&from_heads))?;
+    if !added_commits.is_empty() || !removed_commits.is_empty() {
+        write_section_heading(formatter, &mut needs_separator, "Changed commits:")?;
+        for commit in &added_commits {
+            write!(formatter, "+ ")?;
+            tx.write_commit_summary(formatter, commit)?;
+            writeln!(formatter)?;
+            if !diff_formats.is_empty() {
+                show_predecessor_patch(
+                    ui,
+                    formatter,
+                    tx.base_workspace_helper(),
+                    commit,
+                    diff_formats,
+                )?;
+            }
+        }
+        for commit in &removed_commits {
+            write!(formatter, "- ")?;
+            tx.write_commit_summary(formatter, commit)?;
+            writeln!(formatter)?;
+        }
+    }
+
+    let workspace_ids: BTreeSet<_> = from_view
+        .wc_commit_ids
+        .keys()
+        .chain(to_view.wc_commit_ids.keys())
+        .collect();
+    for workspace_id in workspace_ids {
+        let from_id = from_view.wc_commit_ids.get(workspace_id);
+        let to_id = to_view.wc_commit_ids.get(workspace_id);
+        if from_id == to_id {
+            continue;
+        }
+        write_section_heading(
+            formatter,
+            &mut needs_separator,
+            &format!("Changed working copy {}@:", workspace_id.as_str()),
+        )?;
+        write_commit_id_change(formatter, tx, "+", to_id)?;
+        write_commit_id_change(formatter, tx, "-", from_id)?;
+    }
+
+    let local_branch_diffs = diff_named_ref_targets(
+        from_view
+            .branches
+            .iter()
+            .map(|(name, target)| (name, &target.local_target)),
+        to_view
+            .branches
+            .iter()
+            .map(|(name, target)| (name, &target.local_target)),
+    )
+    .collect_vec();
+    write_ref_target_changes(
+        formatter,
+        tx,
+        &mut needs_separator,
+        "Changed local branches:",
+        local_branch_diffs
+            .into_iter()
+            .map(|(name, targets)| (name.clone(), targets)),
+    )?;
+    let remote_branch_diffs = diff_named_ref_targets(
+        from_view.branches.iter().flat_map(|(name, target)| {
+            target
+                .remote_targets
+                .iter()
+                .map(move |(remote, target)| ((name, remote), target))
+        }),
+        to_view.branches.iter().flat_map(|(name, target)| {
+            target
+                .remote_targets
+                .iter()
+                .map(move |(remote, target)| ((name, remote), target))
+        }),
+    )
+    .collect_vec();
+    write_ref_target_changes(
+        formatter,
+        tx,
+        &mut needs_separator,
+        "Changed remote branches:",
+        remote_branch_diffs
+            .into_iter()
+            .map(|((name, remote), targets)| (format!("{name}@{remote}"), targets)),
+    )?;
+    write_ref_target_changes(
+        formatter,
+        tx,
+        &mut needs_separator,
+        "Changed tags:",
+        diff_named_ref_targets(&from_view.tags, &to_view.tags)
+            .map(|(name, targets)| (name.clone(), targets)),
+    )?;
+    write_ref_target_changes(
+        formatter,
+        tx,
+        &mut needs_separator,
+        "Changed git refs:",
+        diff_named_ref_targets(&from_view.git_refs, &to_view.git_refs)
+            .map(|(name, targets)| (name.clone(), targets))
+            .chain(
+                (from_view.git_head != to_view.git_head)
+                    .then(|| ("HEAD".to_owned(), (&from_view.git_head, &to_view.git_head))),
+            ),
+    )?;
+    Ok(())
+}
+
+fn write_section_heading(
+    formatter: &mut dyn Formatter,
+    needs_separator: &mut bool,
+    heading: &str,
+) -> io::Result<()> {
+    if *needs_separator {
+        writeln!(formatter)?;
+    }
+    *needs_separator = true;
+    writeln!(formatter, "{heading}")
+}
+
+fn write_ref_target_changes<'a>(
+    formatter: &mut dyn Formatter,
+    tx: &WorkspaceCommandTransaction,
+    needs_separator: &mut bool,
+    heading: &str,
+    changes: impl IntoIterator<Item = (String, (&'a RefTarget, &'a RefTarget))>,
+) -> Result<(), CommandError> {
+    let mut changes = changes.into_iter().peekable();
+    if changes.peek().is_none() {
+        return Ok(());
+    }
+    write_section_heading(formatter, needs_separator, heading)?;
+    for (name, (from_target, to_target)) in changes {
+        writeln!(formatter, "{name}:")?;
+        write_ref_target(formatter, tx, "+", to_target)?;
+        write_ref_target(formatter, tx, "-", from_target)?;
+    }
+    Ok(())
+}
+
+fn write_ref_target(
+    formatter: &mut dyn Formatter,
+    tx: &WorkspaceCommandTransaction,
+    prefix: &str,
+    target: &RefTarget,
+) -> Result<(), CommandError> {
+    if target.is_absent() {
+        return write_commit_id_change(formatter, tx, prefix, None);
+    }
+    for id in target.added_ids() {
+        write!(formatter, "{prefix} ")?;
+        if target.has_conflict() {
+            write!(formatter, "(conflicted) ")?;
+        }
+        let commit = tx.repo().store().get_commit(id)?;
+        tx.write_commit_summary(formatter, &commit)?;
+        writeln!(formatter)?;
+    }
+    Ok(())
+}
+
+fn write_commit_id_change(
+    formatter: &mut dyn Formatter,
+    tx: &WorkspaceCommandTransaction,
+    prefix: &str,
+    id: Option<&CommitId>,
+) -> Result<(), CommandError> {
+    write!(formatter, "{prefix} ")?;
+    if let Some(id) = id {
+        let commit = tx.repo().store().get_commit(id)?;
+        tx.write_commit_summary(formatter, &commit)?;
+    } else {
+        write!(formatter, "(absent)")?;
+    }
+    writeln!(formatter)?;
+    Ok(())
+}
+
 /// Restore only the portions of the view specified by the `what` argument
 fn view_with_desired_portions_restored(
     view_being_restored: &jj_lib::op_store::View,
@@ -207,10 +688,10 @@ fn view_with_desired_portions_restored(
             .get(branch_name)
             .map(|br| br.local_target.clone())
             .unwrap_or_else(RefTarget::absent);
-        let remote_targets = branch_source_view
+        let (remote_targets, untracked_remotes) = branch_source_view
             .branches
             .get(branch_name)
-            .map(|br| br.remote_targets.clone())
+            .map(|br| (br.remote_targets.clone(), br.untracked_remotes.clone()))
             .unwrap_or_default();
         if local_target.is_present() || !remote_targets.is_empty() {
             new_branches.insert(
@@ -218,6 +699,7 @@ fn view_with_desired_portions_restored(
                 BranchTarget {
                     local_target,
                     remote_targets,
+                    untracked_remotes,
                 },
             );
         }
@@ -319,7 +801,10 @@ pub fn cmd_operation(
     subcommand: &OperationCommands,
 ) -> Result<(), CommandError> {
     match subcommand {
+        OperationCommands::Abandon(command_matches) => cmd_op_abandon(ui, command, command_matches),
+        OperationCommands::Diff(command_matches) => cmd_op_diff(ui, command, command_matches),
         OperationCommands::Log(command_matches) => cmd_op_log(ui, command, command_matches),
+        OperationCommands::Show(command_matches) => cmd_op_show(ui, command, command_matches),
         OperationCommands::Restore(command_matches) => cmd_op_restore(ui, command, command_matches),
         OperationCommands::Undo(command_matches) => cmd_op_undo(ui, command, command_matches),
     }
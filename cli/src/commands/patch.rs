@@ -0,0 +1,458 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::{Read as _, Write as _};
+use std::path::PathBuf;
+
+use clap::Subcommand;
+use itertools::Itertools as _;
+use jj_lib::backend::{ChangeId, ObjectId as _, Signature, Timestamp};
+use jj_lib::commit::Commit;
+use jj_lib::hex_util::{to_forward_hex, to_reverse_hex};
+use jj_lib::matchers::EverythingMatcher;
+use jj_lib::patch::{apply_patch, parse_patch};
+use jj_lib::repo::Repo as _;
+use jj_lib::revset::{RevsetExpression, RevsetIteratorExt as _};
+use jj_lib::rewrite::merge_commit_trees;
+use tracing::instrument;
+
+use crate::cli_util::{
+    resolve_multiple_nonempty_revsets, user_error, CommandError, CommandHelper, RevisionArg,
+    WorkspaceCommandHelper,
+};
+use crate::diff_util::{self, DiffFormat};
+use crate::formatter::PlainTextFormatter;
+use crate::time_util::{format_absolute_timestamp_with, FormattingItems};
+use crate::ui::Ui;
+
+/// Exchange revisions as patches
+///
+/// Patches use the mbox format of `git format-patch`, so they can be sent with
+/// `git send-email` and applied with `git am`, and patches produced by Git can
+/// be applied with `jj patch apply`.
+#[derive(Subcommand, Clone, Debug)]
+pub enum PatchCommands {
+    Apply(PatchApplyArgs),
+    Export(PatchExportArgs),
+}
+
+/// Create revisions from patch files
+///
+/// A patch file can contain a plain diff, or one or more patches in the mbox
+/// format. The author, description and change id of a revision are taken from
+/// the email headers and the `Change-Id` trailer of the patch, if present.
+///
+/// The patches are applied on top of each other, starting with the given
+/// revision. A new working-copy commit is then created on top of the last
+/// patch. Hunks that don't apply cleanly result in conflicts in the new
+/// revisions.
+#[derive(clap::Args, Clone, Debug)]
+pub struct PatchApplyArgs {
+    /// The patch files to apply, or `-` to read a patch from stdin
+    #[arg(required = true, value_hint = clap::ValueHint::FilePath)]
+    files: Vec<String>,
+    /// The revision to apply the first patch to
+    #[arg(long, short, default_value = "@")]
+    revision: RevisionArg,
+}
+
+/// Export revisions as patches
+///
+/// Each revision is exported with its author, description, a `Change-Id`
+/// trailer containing its change id, and its changes in the Git diff format.
+/// The patches are ordered with parents before their children.
+#[derive(clap::Args, Clone, Debug)]
+pub struct PatchExportArgs {
+    /// The revisions to export
+    #[arg(long, short, default_value = "@")]
+    revisions: Vec<RevisionArg>,
+    /// Write each patch to a file in this directory instead of to stdout
+    #[arg(long, short, value_hint = clap::ValueHint::DirPath)]
+    output_directory: Option<PathBuf>,
+}
+
+const CHANGE_ID_TRAILER: &str = "Change-Id: ";
+
+#[instrument(skip_all)]
+pub fn cmd_patch(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    subcommand: &PatchCommands,
+) -> Result<(), CommandError> {
+    match subcommand {
+        PatchCommands::Apply(command_matches) => cmd_patch_apply(ui, command, command_matches),
+        PatchCommands::Export(command_matches) => cmd_patch_export(ui, command, command_matches),
+    }
+}
+
+fn cmd_patch_export(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &PatchExportArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper(ui)?;
+    let commits = resolve_multiple_nonempty_revsets(&args.revisions, &workspace_command, ui)?;
+    // Order the commits topologically, parents first
+    let expression = RevsetExpression::commits(commits.iter().map(|c| c.id().clone()).collect());
+    let mut commits: Vec<Commit> = workspace_command
+        .evaluate_revset(expression)?
+        .iter()
+        .commits(workspace_command.repo().store())
+        .try_collect()?;
+    commits.reverse();
+    if let Some(output_directory) = &args.output_directory {
+        std::fs::create_dir_all(output_directory).map_err(|err| {
+            user_error(format!(
+                "Failed to create directory {}: {err}",
+                output_directory.display()
+            ))
+        })?;
+    } else {
+        ui.request_pager();
+    }
+    for (index, commit) in commits.iter().enumerate() {
+        let patch = format_patch(ui, &workspace_command, commit, index + 1, commits.len())?;
+        if let Some(output_directory) = &args.output_directory {
+            let path = output_directory.join(patch_file_name(commit, index + 1));
+            std::fs::write(&path, patch)
+                .map_err(|err| user_error(format!("Failed to write {}: {err}", path.display())))?;
+            writeln!(ui, "{}", path.display())?;
+        } else {
+            ui.stdout_formatter().write_all(&patch)?;
+        }
+    }
+    Ok(())
+}
+
+/// Formats a commit like `git format-patch` does.
+fn format_patch(
+    ui: &Ui,
+    workspace_command: &WorkspaceCommandHelper,
+    commit: &Commit,
+    number: usize,
+    total: usize,
+) -> Result<Vec<u8>, CommandError> {
+    let (subject, body) = commit
+        .description()
+        .split_once('\n')
+        .unwrap_or((commit.description(), ""));
+    let subject_prefix = if total == 1 {
+        "[PATCH]".to_owned()
+    } else {
+        format!("[PATCH {number}/{total}]")
+    };
+    let date_format = FormattingItems::parse("%a, %d %b %Y %H:%M:%S %z").unwrap();
+    let author = commit.author();
+    let mut output = vec![];
+    writeln!(
+        output,
+        "From {} Mon Sep 17 00:00:00 2001",
+        commit.id().hex()
+    )?;
+    writeln!(output, "From: {} <{}>", author.name, author.email)?;
+    writeln!(
+        output,
+        "Date: {}",
+        format_absolute_timestamp_with(&author.timestamp, &date_format)
+    )?;
+    writeln!(output, "Subject: {subject_prefix} {subject}")?;
+    writeln!(output, "MIME-Version: 1.0")?;
+    writeln!(output, "Content-Type: text/plain; charset=UTF-8")?;
+    writeln!(output, "Content-Transfer-Encoding: 8bit")?;
+    writeln!(output)?;
+    let body = body.trim();
+    if !body.is_empty() {
+        writeln!(output, "{body}")?;
+        writeln!(output)?;
+    }
+    writeln!(
+        output,
+        "{CHANGE_ID_TRAILER}{}",
+        to_reverse_hex(&commit.change_id().hex()).unwrap()
+    )?;
+    writeln!(output, "---")?;
+    let parent_tree = merge_commit_trees(workspace_command.repo().as_ref(), &commit.parents())?;
+    diff_util::show_diff(
+        ui,
+        &mut PlainTextFormatter::new(&mut output),
+        workspace_command,
+        &parent_tree,
+        &commit.tree()?,
+        &EverythingMatcher,
+        &[DiffFormat::Git],
+    )?;
+    writeln!(output)?;
+    Ok(output)
+}
+
+/// Creates a file name like `0001-Fix-the-thing.patch`, like Git does.
+fn patch_file_name(commit: &Commit, number: usize) -> String {
+    let subject = commit.description().lines().next().unwrap_or_default();
+    let mut slug = subject
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '_')
+        .filter(|word| !word.is_empty())
+        .join("-");
+    slug.truncate(52);
+    let slug = slug.trim_end_matches(['-', '.']);
+    if slug.is_empty() {
+        format!("{number:04}.patch")
+    } else {
+        format!("{number:04}-{slug}.patch")
+    }
+}
+
+fn cmd_patch_apply(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &PatchApplyArgs,
+) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let mut parent = workspace_command.resolve_single_rev(&args.revision, ui)?;
+    let mut messages = vec![];
+    for file in &args.files {
+        let mut content = vec![];
+        if file == "-" {
+            std::io::stdin().read_to_end(&mut content)?;
+        } else {
+            content = std::fs::read(file)
+                .map_err(|err| user_error(format!("Failed to read {file}: {err}")))?;
+        }
+        messages.extend(split_mbox(&content).into_iter().map(PatchMessage::parse));
+    }
+    let mut tx = workspace_command
+        .start_transaction(&format!("apply patches from {}", args.files.join(" ")));
+    for message in messages {
+        let file_patches = parse_patch(&message.patch)
+            .map_err(|err| user_error(format!("Failed to parse patch: {err}")))?;
+        let tree_id = apply_patch(tx.repo().store(), &parent.tree()?, &file_patches)
+            .map_err(|err| user_error(format!("Failed to apply patch: {err}")))?;
+        let mut commit_builder = tx
+            .mut_repo()
+            .new_commit(command.settings(), vec![parent.id().clone()], tree_id)
+            .set_description(message.description);
+        if let Some(author) = message.author {
+            commit_builder = commit_builder.set_author(author);
+        }
+        if let Some(change_id) = message.change_id {
+            commit_builder = commit_builder.set_change_id(change_id);
+        }
+        let commit = commit_builder.write()?;
+        ui.write("Applied ")?;
+        tx.write_commit_summary(ui.stdout_formatter().as_mut(), &commit)?;
+        ui.write("\n")?;
+        if commit.tree()?.has_conflict() {
+            writeln!(
+                ui.warning(),
+                "The patch did not apply cleanly. Resolve the conflicts with `jj resolve`."
+            )?;
+        }
+        parent = commit;
+    }
+    tx.check_out(&parent)?;
+    tx.finish(ui)?;
+    Ok(())
+}
+
+/// Splits an mbox file into messages. Content that doesn't start with a `From `
+/// line is treated as a single message.
+fn split_mbox(content: &[u8]) -> Vec<&[u8]> {
+    let lines = content.split_inclusive(|b| *b == b'\n').collect_vec();
+    let is_separator = |index: usize| {
+        lines[index].starts_with(b"From ")
+            && lines
+                .get(index + 1)
+                .is_some_and(|next| parse_header(next).is_some())
+    };
+    let mut starts = (0..lines.len()).filter(|&i| is_separator(i)).collect_vec();
+    if starts.first() != Some(&0) {
+        starts.insert(0, 0);
+    }
+    let mut offsets = vec![0];
+    for line in &lines {
+        offsets.push(offsets.last().unwrap() + line.len());
+    }
+    starts
+        .iter()
+        .zip(starts.iter().skip(1).chain([&lines.len()]))
+        .map(|(&start, &end)| &content[offsets[start]..offsets[end]])
+        .collect()
+}
+
+/// Parses a `Name: value` header line.
+fn parse_header(line: &[u8]) -> Option<(&str, &str)> {
+    let line = std::str::from_utf8(line).ok()?;
+    let (name, value) = line.split_once(':')?;
+    let is_name = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    is_name.then(|| (name, value.trim()))
+}
+
+#[derive(Debug, Default)]
+struct PatchMessage {
+    author: Option<Signature>,
+    description: String,
+    change_id: Option<ChangeId>,
+    /// The part of the message containing the diff
+    patch: Vec<u8>,
+}
+
+impl PatchMessage {
+    fn parse(content: &[u8]) -> Self {
+        let mut lines = content.split_inclusive(|b| *b == b'\n').peekable();
+        if lines.peek().is_some_and(|line| line.starts_with(b"From ")) {
+            lines.next();
+        }
+        // Headers, with continuation lines unfolded
+        let mut headers: Vec<(String, String)> = vec![];
+        while let Some(line) = lines.peek() {
+            if line.starts_with(b" ") || line.starts_with(b"\t") {
+                let Some((_, value)) = headers.last_mut() else {
+                    break;
+                };
+                value.push(' ');
+                value.push_str(String::from_utf8_lossy(line).trim());
+            } else if let Some((name, value)) = parse_header(line) {
+                headers.push((name.to_ascii_lowercase(), value.to_owned()));
+            } else {
+                break;
+            }
+            lines.next();
+        }
+        if headers.is_empty() {
+            // A plain diff
+            return PatchMessage {
+                patch: content.to_vec(),
+                ..Default::default()
+            };
+        }
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, value)| decode_rfc2047(value))
+        };
+
+        // The body ends at the `---` line, or where the diff starts if there's
+        // no such line.
+        let mut body = String::new();
+        for line in lines.by_ref() {
+            let text = String::from_utf8_lossy(line);
+            if text.trim_end() == "---" {
+                break;
+            }
+            if text.starts_with("diff --git ") || text.starts_with("--- ") {
+                body.push_str(&text);
+                break;
+            }
+            body.push_str(&text);
+        }
+        let patch = lines.flatten().copied().collect_vec();
+        let (body, patch) = match body.find("\ndiff --git ").or(body.find("\n--- ")) {
+            Some(index) => {
+                let mut diff = body.as_bytes()[index + 1..].to_vec();
+                diff.extend(patch);
+                (body[..index + 1].to_owned(), diff)
+            }
+            None => (body, patch),
+        };
+
+        let mut body_lines = body.trim().lines().collect_vec();
+        let mut change_id = None;
+        if let Some(value) = body_lines
+            .last()
+            .and_then(|line| line.strip_prefix(CHANGE_ID_TRAILER))
+        {
+            if let Some(hex) = to_forward_hex(value.trim()).filter(|hex| hex.len() == 32) {
+                change_id = Some(ChangeId::from_hex(&hex));
+                body_lines.pop();
+            }
+        }
+        let subject = header("subject").unwrap_or_default();
+        let subject = match subject.strip_prefix('[').and_then(|s| s.split_once(']')) {
+            Some((_, rest)) => rest.trim().to_owned(),
+            None => subject,
+        };
+        let body = body_lines.join("\n");
+        let description = match (subject.is_empty(), body.trim().is_empty()) {
+            (true, true) => String::new(),
+            (_, true) => format!("{subject}\n"),
+            _ => format!("{subject}\n\n{}\n", body.trim()),
+        };
+        let author = header("from").map(|from| {
+            let (name, email) = match from.rsplit_once('<') {
+                Some((name, email)) => (name.trim(), email.trim_end_matches('>')),
+                None => ("", from.as_str()),
+            };
+            let timestamp = header("date")
+                .and_then(|date| chrono::DateTime::parse_from_rfc2822(&date).ok())
+                .map_or_else(Timestamp::now, Timestamp::from_datetime);
+            Signature {
+                name: name.trim_matches('"').to_owned(),
+                email: email.trim().to_owned(),
+                timestamp,
+            }
+        });
+        PatchMessage {
+            author,
+            description,
+            change_id,
+            patch,
+        }
+    }
+}
+
+/// Decodes the `=?UTF-8?q?...?=` encoded words Git uses for non-ASCII
+/// headers. Other encodings are left as is.
+fn decode_rfc2047(value: &str) -> String {
+    let mut result = String::new();
+    let mut rest = value;
+    let mut last_was_encoded = false;
+    while let Some(start) = rest.find("=?") {
+        let (before, encoded) = rest.split_at(start);
+        let decoded = encoded[2..].splitn(3, '?').collect_vec();
+        let Some(end) = decoded
+            .get(2)
+            .and_then(|text| text.find("?="))
+            .filter(|_| decoded[1].eq_ignore_ascii_case("q"))
+        else {
+            result.push_str(&rest[..start + 2]);
+            rest = &rest[start + 2..];
+            last_was_encoded = false;
+            continue;
+        };
+        // Whitespace between encoded words is ignored
+        if !(last_was_encoded && before.trim().is_empty()) {
+            result.push_str(before);
+        }
+        let text = &decoded[2][..end];
+        let mut bytes = vec![];
+        let mut chars = text.bytes();
+        while let Some(b) = chars.next() {
+            match b {
+                b'_' => bytes.push(b' '),
+                b'=' => {
+                    let hex = [chars.next().unwrap_or(b'0'), chars.next().unwrap_or(b'0')];
+                    let hex = std::str::from_utf8(&hex).unwrap_or("00");
+                    bytes.push(u8::from_str_radix(hex, 16).unwrap_or(b'?'));
+                }
+                _ => bytes.push(b),
+            }
+        }
+        result.push_str(&String::from_utf8_lossy(&bytes));
+        let consumed = 2 + decoded[0].len() + 1 + decoded[1].len() + 1 + end + 2;
+        rest = &encoded[consumed..];
+        last_was_encoded = true;
+    }
+    result.push_str(rest);
+    result
+}
@@ -0,0 +1,216 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use itertools::Itertools;
+use jj_lib::backend::{CommitId, MergedTreeId, ObjectId};
+use jj_lib::commit::Commit;
+use jj_lib::gitignore::GitIgnoreFile;
+use jj_lib::repo::Repo;
+use jj_lib::settings::UserSettings;
+use jj_lib::working_copy::{CheckoutOptions, SnapshotOptions, TreeState};
+use tracing::instrument;
+
+use crate::cli_util::{
+    resolve_multiple_nonempty_revsets, user_error, CommandError, CommandHelper, RevisionArg,
+};
+use crate::config::CommandNameAndArgs;
+use crate::ui::Ui;
+
+/// Run a command across a set of revisions.
+///
+/// Each revision is checked out into a temporary working copy, the command is
+/// run there, and any changes it makes to the files are recorded by rewriting
+/// the revision. Descendants of rewritten revisions are rebased as usual.
+///
+/// The command is run with the root of the temporary working copy as its
+/// current directory. The environment variables `JJ_COMMIT_ID` and
+/// `JJ_CHANGE_ID` are set to the ids of the revision being processed.
+///
+/// If the command fails for any revision, nothing is rewritten. The
+/// repository's own working copy is only touched if the working-copy revision
+/// itself was rewritten.
+///
+/// # Example
+///
+/// # Format all of your local work
+/// $ jj run 'cargo fmt' -r 'main..@'
+#[derive(clap::Args, Clone, Debug)]
+#[command(verbatim_doc_comment)]
+pub struct RunArgs {
+    /// The command to run across all selected revisions.
+    ///
+    /// The command is split on spaces into the program name and its
+    /// arguments.
+    #[arg(value_name = "COMMAND")]
+    command: String,
+    /// The revisions to run the command on.
+    #[arg(long, short, default_value = "@")]
+    revisions: Vec<RevisionArg>,
+}
+
+#[instrument(skip_all)]
+pub fn cmd_run(ui: &mut Ui, command: &CommandHelper, args: &RunArgs) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let to_run = resolve_multiple_nonempty_revsets(&args.revisions, &workspace_command, ui)?;
+    workspace_command.check_rewritable(&to_run)?;
+    let tool = CommandNameAndArgs::from(&args.command);
+    if tool.split_name_and_args().0.is_empty() {
+        return Err(user_error("The command to run must not be empty"));
+    }
+    let base_ignores = workspace_command.base_ignores();
+
+    let mut tx = workspace_command.start_transaction(&format!(
+        "run '{}' on {} commit(s)",
+        args.command,
+        to_run.len()
+    ));
+    let base_repo = tx.base_repo().clone();
+    let store = base_repo.store();
+    let mut rewritten: HashMap<CommitId, CommitId> = HashMap::new();
+    let mut changed_commits = vec![];
+    for commit_id in base_repo
+        .index()
+        .topo_order(&mut to_run.iter().map(|commit| commit.id()))
+    {
+        // Topological order ensures that any parents of `old_commit` that were
+        // also selected have already been rewritten.
+        let old_commit = store.get_commit(&commit_id)?;
+        let new_tree_id = run_in_scratch_working_copy(
+            ui,
+            command.settings(),
+            &old_commit,
+            &tool,
+            base_ignores.clone(),
+        )?;
+        let new_parent_ids = old_commit
+            .parent_ids()
+            .iter()
+            .map(|id| rewritten.get(id).unwrap_or(id).clone())
+            .collect_vec();
+        let tree_changed = new_tree_id != *old_commit.tree_id();
+        if !tree_changed && new_parent_ids == old_commit.parent_ids() {
+            continue;
+        }
+        let new_commit = tx
+            .mut_repo()
+            .rewrite_commit(command.settings(), &old_commit)
+            .set_parents(new_parent_ids)
+            .set_tree_id(new_tree_id)
+            .write()?;
+        rewritten.insert(old_commit.id().clone(), new_commit.id().clone());
+        if tree_changed {
+            changed_commits.push(new_commit);
+        }
+    }
+
+    let num_rebased = tx.mut_repo().rebase_descendants(command.settings())?;
+    writeln!(
+        ui,
+        "Command changed {} of {} commits",
+        changed_commits.len(),
+        to_run.len()
+    )?;
+    for commit in &changed_commits {
+        ui.write("  ")?;
+        tx.write_commit_summary(ui.stdout_formatter().as_mut(), commit)?;
+        ui.write("\n")?;
+    }
+    if num_rebased > 0 {
+        writeln!(ui, "Rebased {num_rebased} descendant commits")?;
+    }
+    tx.finish(ui)?;
+    Ok(())
+}
+
+/// Checks out the tree of `commit` into a temporary directory, runs `tool` in
+/// it, and snapshots the result.
+fn run_in_scratch_working_copy(
+    ui: &mut Ui,
+    settings: &UserSettings,
+    commit: &Commit,
+    tool: &CommandNameAndArgs,
+    base_ignores: Arc<GitIgnoreFile>,
+) -> Result<MergedTreeId, CommandError> {
+    let map_io_err = |err: std::io::Error| {
+        user_error(format!(
+            "Failed to set up working copy for commit {}: {err}",
+            commit.id().hex()
+        ))
+    };
+    let temp_dir = tempfile::Builder::new()
+        .prefix("jj-run-")
+        .tempdir()
+        .map_err(map_io_err)?;
+    let wc_dir = temp_dir.path().join("wc");
+    let state_dir = temp_dir.path().join("state");
+    std::fs::create_dir(&wc_dir).map_err(map_io_err)?;
+    std::fs::create_dir(&state_dir).map_err(map_io_err)?;
+    let mut tree_state = TreeState::init(commit.store().clone(), wc_dir, state_dir)?;
+    let conflict_marker_style = settings.conflict_marker_style()?;
+    tree_state
+        .check_out(
+            &commit.tree()?,
+            &CheckoutOptions {
+                conflict_marker_style,
+            },
+        )
+        .map_err(|err| user_error(format!("Failed to check out commit: {err}")))?;
+
+    run_tool(ui, tool, tree_state.working_copy_path(), commit)?;
+
+    tree_state.snapshot(SnapshotOptions {
+        base_ignores,
+        fsmonitor_kind: None,
+        progress: None,
+        max_new_file_size: settings.max_new_file_size()?,
+        conflict_marker_style,
+    })?;
+    Ok(tree_state.current_tree_id().clone())
+}
+
+fn run_tool(
+    ui: &mut Ui,
+    tool: &CommandNameAndArgs,
+    wc_path: &Path,
+    commit: &Commit,
+) -> Result<(), CommandError> {
+    let mut cmd = tool.to_command();
+    cmd.current_dir(wc_path)
+        .env("JJ_COMMIT_ID", commit.id().hex())
+        .env("JJ_CHANGE_ID", commit.change_id().hex())
+        .stdout(ui.stderr_for_child()?)
+        .stderr(ui.stderr_for_child()?);
+    tracing::info!(?cmd, "Running command on commit {}:", commit.id().hex());
+    let exit_status = cmd.status().map_err(|err| {
+        user_error(format!(
+            "Failed to run '{}': {err}",
+            tool.split_name_and_args().0
+        ))
+    })?;
+    if !exit_status.success() {
+        let code = exit_status
+            .code()
+            .map(|c| c.to_string())
+            .unwrap_or_else(|| "<unknown>".to_string());
+        return Err(user_error(format!(
+            "Command '{tool}' failed on commit {} with exit code {code}",
+            commit.id().hex()
+        )));
+    }
+    Ok(())
+}
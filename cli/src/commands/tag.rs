@@ -0,0 +1,327 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+
+use clap::builder::NonEmptyStringValueParser;
+use itertools::Itertools;
+use jj_lib::backend::ObjectId;
+use jj_lib::commit::Commit;
+use jj_lib::git;
+use jj_lib::op_store::RefTarget;
+use jj_lib::repo::Repo;
+use jj_lib::revset::StringPattern;
+
+use crate::cli_util::{
+    user_error, user_error_with_hint, CommandError, CommandHelper, RevisionArg,
+    WorkspaceCommandHelper,
+};
+use crate::commands::make_tag_term;
+use crate::text_util;
+use crate::ui::Ui;
+
+/// Manage tags.
+///
+/// Tags are exported to the underlying Git repo like branches, and can be
+/// pushed with `jj git push --tag`.
+#[derive(clap::Subcommand, Clone, Debug)]
+pub enum TagSubcommand {
+    #[command(visible_alias("c"))]
+    Create(TagCreateArgs),
+    #[command(visible_alias("d"))]
+    Delete(TagDeleteArgs),
+    #[command(visible_alias("l"))]
+    List(TagListArgs),
+    #[command(visible_alias("s"))]
+    Set(TagSetArgs),
+}
+
+/// Create a new tag.
+#[derive(clap::Args, Clone, Debug)]
+pub struct TagCreateArgs {
+    /// The tag's target revision.
+    #[arg(long, short)]
+    revision: Option<RevisionArg>,
+
+    /// Create an annotated tag with this message (Git backend only).
+    #[arg(long, short)]
+    message: Option<String>,
+
+    /// The tags to create.
+    #[arg(required = true, value_parser=NonEmptyStringValueParser::new())]
+    names: Vec<String>,
+}
+
+/// Delete existing tags.
+#[derive(clap::Args, Clone, Debug)]
+pub struct TagDeleteArgs {
+    /// The tags to delete.
+    ///
+    /// By default, the specified name matches exactly. Use `glob:` or `regex:`
+    /// prefix to select tags by pattern. For details, see
+    /// https://github.com/martinvonz/jj/blob/main/docs/revsets.md#string-patterns.
+    #[arg(required = true, value_parser = StringPattern::parse)]
+    names: Vec<StringPattern>,
+}
+
+/// List tags and their targets
+///
+/// For a conflicted tag, old target revisions are preceded by a "-" and new
+/// target revisions are preceded by a "+".
+#[derive(clap::Args, Clone, Debug)]
+pub struct TagListArgs {
+    /// Show tags whose name matches
+    ///
+    /// By default, the specified name matches exactly. Use `glob:` or `regex:`
+    /// prefix to select tags by pattern. For details, see
+    /// https://github.com/martinvonz/jj/blob/main/docs/revsets.md#string-patterns.
+    #[arg(value_parser = StringPattern::parse)]
+    names: Vec<StringPattern>,
+}
+
+/// Create or update tags to point to a certain commit.
+#[derive(clap::Args, Clone, Debug)]
+pub struct TagSetArgs {
+    /// The tag's target revision.
+    #[arg(long, short)]
+    revision: Option<RevisionArg>,
+
+    /// Make the tags annotated with this message (Git backend only).
+    #[arg(long, short)]
+    message: Option<String>,
+
+    /// The tags to update.
+    #[arg(required = true, value_parser=NonEmptyStringValueParser::new())]
+    names: Vec<String>,
+}
+
+pub fn cmd_tag(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    subcommand: &TagSubcommand,
+) -> Result<(), CommandError> {
+    match subcommand {
+        TagSubcommand::Create(sub_args) => cmd_tag_create(ui, command, sub_args),
+        TagSubcommand::Set(sub_args) => cmd_tag_set(ui, command, sub_args),
+        TagSubcommand::Delete(sub_args) => cmd_tag_delete(ui, command, sub_args),
+        TagSubcommand::List(sub_args) => cmd_tag_list(ui, command, sub_args),
+    }
+}
+
+fn cmd_tag_create(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &TagCreateArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper(ui)?;
+    let view = workspace_command.repo().view();
+    for tag_name in &args.names {
+        if view.get_tag(tag_name).is_present() {
+            return Err(user_error_with_hint(
+                format!("Tag already exists: {tag_name}"),
+                "Use `jj tag set` to update it.",
+            ));
+        }
+    }
+    let target_commit =
+        workspace_command.resolve_single_rev(args.revision.as_deref().unwrap_or("@"), ui)?;
+    let description = format!(
+        "create {} pointing to commit {}",
+        make_tag_term(&args.names),
+        target_commit.id().hex()
+    );
+    set_tags(
+        ui,
+        command,
+        workspace_command,
+        &args.names,
+        &target_commit,
+        args.message.as_deref(),
+        &description,
+    )
+}
+
+fn cmd_tag_set(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &TagSetArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper(ui)?;
+    let target_commit =
+        workspace_command.resolve_single_rev(args.revision.as_deref().unwrap_or("@"), ui)?;
+    let description = format!(
+        "point {} to commit {}",
+        make_tag_term(&args.names),
+        target_commit.id().hex()
+    );
+    set_tags(
+        ui,
+        command,
+        workspace_command,
+        &args.names,
+        &target_commit,
+        args.message.as_deref(),
+        &description,
+    )
+}
+
+/// Points the tags to the `target_commit`, creating annotated tags in the Git
+/// repo if a `message` is given.
+fn set_tags(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    mut workspace_command: WorkspaceCommandHelper,
+    tag_names: &[String],
+    target_commit: &Commit,
+    message: Option<&str>,
+    description: &str,
+) -> Result<(), CommandError> {
+    for tag_name in tag_names {
+        if !git2::Reference::is_valid_name(&format!("refs/tags/{tag_name}")) {
+            return Err(user_error(format!("Invalid tag name: {tag_name}")));
+        }
+    }
+    let git_repo = workspace_command
+        .git_backend()
+        .map(|git_backend| git_backend.git_repo_clone());
+    if message.is_some() {
+        if git_repo.is_none() {
+            return Err(user_error(
+                "Annotated tags are only supported in Git-backed repos",
+            ));
+        }
+        if target_commit.id() == workspace_command.repo().store().root_commit_id() {
+            return Err(user_error(
+                "Cannot create an annotated tag of the root commit",
+            ));
+        }
+    } else if let Some(git_repo) = &git_repo {
+        let view = workspace_command.repo().view();
+        for tag_name in tag_names {
+            let old_target = view.get_tag(tag_name);
+            if old_target.is_present()
+                && old_target.as_normal() != Some(target_commit.id())
+                && git::is_annotated_tag(git_repo, tag_name)
+            {
+                return Err(user_error_with_hint(
+                    format!("Tag {tag_name} is annotated"),
+                    "Pass --message to move it as an annotated tag, or delete it first.",
+                ));
+            }
+        }
+    }
+    let mut tx = workspace_command.start_transaction(description);
+    for tag_name in tag_names {
+        if let (Some(git_repo), Some(message)) = (&git_repo, message) {
+            git::set_annotated_tag(
+                tx.mut_repo(),
+                git_repo,
+                tag_name,
+                target_commit.id(),
+                &text_util::complete_newline(message),
+                &command.settings().signature(),
+            )?;
+        } else {
+            tx.mut_repo()
+                .set_tag_target(tag_name, RefTarget::normal(target_commit.id().clone()));
+        }
+    }
+    tx.finish(ui)?;
+    Ok(())
+}
+
+fn cmd_tag_delete(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &TagDeleteArgs,
+) -> Result<(), CommandError> {
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let view = workspace_command.repo().view();
+    let mut names = BTreeSet::new();
+    for pattern in &args.names {
+        let matching_names = view
+            .tags()
+            .keys()
+            .filter(|name| pattern.matches(name))
+            .cloned()
+            .collect_vec();
+        if matching_names.is_empty() {
+            return Err(match pattern.as_exact() {
+                Some(name) => user_error(format!("No such tag: {name}")),
+                None => user_error(format!(
+                    "The provided pattern '{pattern}' did not match any tags"
+                )),
+            });
+        }
+        names.extend(matching_names);
+    }
+    let tag_term = make_tag_term(names.iter().collect_vec().as_slice());
+    let mut tx = workspace_command.start_transaction(&format!("delete {tag_term}"));
+    for tag_name in &names {
+        tx.mut_repo().set_tag_target(tag_name, RefTarget::absent());
+    }
+    tx.finish(ui)?;
+    if names.len() > 1 {
+        writeln!(ui, "Deleted {} tags.", names.len())?;
+    }
+    Ok(())
+}
+
+fn cmd_tag_list(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &TagListArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper(ui)?;
+    let repo = workspace_command.repo();
+    let template = workspace_command.parse_commit_template(
+        &command
+            .settings()
+            .config()
+            .get_string("templates.commit_summary_no_branches")?,
+    )?;
+
+    ui.request_pager();
+    let mut formatter = ui.stdout_formatter();
+    let formatter = formatter.as_mut();
+    for (name, target) in repo.view().tags() {
+        if !args.names.is_empty() && !args.names.iter().any(|pattern| pattern.matches(name)) {
+            continue;
+        }
+        write!(formatter.labeled("tag"), "{name}")?;
+        if let Some(id) = target.as_normal() {
+            write!(formatter, ": ")?;
+            let commit = repo.store().get_commit(id)?;
+            template.format(&commit, formatter)?;
+            writeln!(formatter)?;
+        } else {
+            write!(formatter, " ")?;
+            write!(formatter.labeled("conflict"), "(conflicted)")?;
+            writeln!(formatter, ":")?;
+            for id in target.removed_ids() {
+                let commit = repo.store().get_commit(id)?;
+                write!(formatter, "  - ")?;
+                template.format(&commit, formatter)?;
+                writeln!(formatter)?;
+            }
+            for id in target.added_ids() {
+                let commit = repo.store().get_commit(id)?;
+                write!(formatter, "  + ")?;
+                template.format(&commit, formatter)?;
+                writeln!(formatter)?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -24,6 +24,7 @@ use jj_lib::hex_util::to_reverse_hex;
 use jj_lib::id_prefix::IdPrefixContext;
 use jj_lib::op_store::{RefTarget, WorkspaceId};
 use jj_lib::repo::Repo;
+use jj_lib::signing::SigStatus;
 use jj_lib::{git, rewrite};
 use once_cell::unsync::OnceCell;
 
@@ -290,6 +291,7 @@ fn build_commit_keyword_opt<'repo>(
             }))
         }
         "git_head" => language.wrap_string(wrap_repo_fn(repo, property, extract_git_head)),
+        "signature" => language.wrap_string(wrap_fn(property, extract_signature_status)),
         "divergent" => language.wrap_boolean(wrap_fn(property, |commit| {
             // The given commit could be hidden in e.g. obslog.
             let maybe_entries = repo.resolve_change_id(commit.change_id());
@@ -314,6 +316,16 @@ fn build_commit_keyword_opt<'repo>(
     Some(property)
 }
 
+// TODO: return Signature type with key and signer information?
+fn extract_signature_status(commit: &Commit) -> String {
+    match commit.verification() {
+        Ok(Some(verification)) => verification.status.to_string(),
+        Ok(None) => "".to_string(),
+        // The signature couldn't be checked, e.g. because gpg isn't installed.
+        Err(_) => SigStatus::Unknown.to_string(),
+    }
+}
+
 // TODO: return Vec<String>
 fn extract_working_copies(repo: &dyn Repo, commit: &Commit) -> String {
     let wc_commit_ids = repo.view().wc_commit_ids();
@@ -21,6 +21,8 @@ use std::sync::Arc;
 use itertools::Itertools;
 use jj_lib::backend::{ObjectId, TreeValue};
 use jj_lib::commit::Commit;
+use jj_lib::conflicts::ConflictMarkerStyle;
+use jj_lib::copies::{self, CopyOperation, TreeDiffEntry};
 use jj_lib::diff::{Diff, DiffHunk};
 use jj_lib::files::DiffLine;
 use jj_lib::matchers::Matcher;
@@ -176,11 +178,22 @@ pub fn show_diff(
     matcher: &dyn Matcher,
     formats: &[DiffFormat],
 ) -> Result<(), CommandError> {
+    // Only the formats that show renames and copies need to detect them, which
+    // requires reading the content of the added and removed files
+    let copies_diff = if formats.iter().any(|format| {
+        matches!(
+            format,
+            DiffFormat::Summary | DiffFormat::Git | DiffFormat::ColorWords
+        )
+    }) {
+        copies::diff_with_copies(from_tree, to_tree, matcher)?
+    } else {
+        vec![]
+    };
     for format in formats {
         match format {
             DiffFormat::Summary => {
-                let tree_diff = from_tree.diff(to_tree, matcher);
-                show_diff_summary(formatter, workspace_command, tree_diff)?;
+                show_diff_summary(formatter, workspace_command, &copies_diff)?;
             }
             DiffFormat::Stat => {
                 let tree_diff = from_tree.diff(to_tree, matcher);
@@ -191,15 +204,21 @@ pub fn show_diff(
                 show_types(formatter, workspace_command, tree_diff)?;
             }
             DiffFormat::Git => {
-                let tree_diff = from_tree.diff(to_tree, matcher);
-                show_git_diff(formatter, workspace_command, tree_diff)?;
+                show_git_diff(formatter, workspace_command, &copies_diff)?;
             }
             DiffFormat::ColorWords => {
-                let tree_diff = from_tree.diff(to_tree, matcher);
-                show_color_words_diff(formatter, workspace_command, tree_diff)?;
+                show_color_words_diff(formatter, workspace_command, &copies_diff)?;
             }
             DiffFormat::Tool(tool) => {
-                merge_tools::generate_diff(ui, formatter.raw(), from_tree, to_tree, matcher, tool)?;
+                merge_tools::generate_diff(
+                    ui,
+                    formatter.raw(),
+                    from_tree,
+                    to_tree,
+                    matcher,
+                    tool,
+                    workspace_command.settings().conflict_marker_style()?,
+                )?;
             }
         }
     }
@@ -345,6 +364,7 @@ fn diff_content(
     repo: &Arc<ReadonlyRepo>,
     path: &RepoPath,
     value: &Merge<Option<TreeValue>>,
+    conflict_marker_style: ConflictMarkerStyle,
 ) -> Result<Vec<u8>, CommandError> {
     match value.as_resolved() {
         Some(None) => Ok(vec![]),
@@ -363,7 +383,14 @@ fn diff_content(
         }
         None => {
             let mut content = vec![];
-            conflicts::materialize(value, repo.store(), path, &mut content).unwrap();
+            conflicts::materialize(
+                value,
+                repo.store(),
+                path,
+                conflict_marker_style,
+                &mut content,
+            )
+            .unwrap();
             Ok(content)
         }
         Some(Some(TreeValue::Tree(_))) | Some(Some(TreeValue::Conflict(_))) => {
@@ -397,14 +424,18 @@ fn basic_diff_file_type(values: &Merge<Option<TreeValue>>) -> String {
 pub fn show_color_words_diff(
     formatter: &mut dyn Formatter,
     workspace_command: &WorkspaceCommandHelper,
-    tree_diff: TreeDiffIterator,
+    tree_diff: &[TreeDiffEntry],
 ) -> Result<(), CommandError> {
     let repo = workspace_command.repo();
+    let conflict_marker_style = workspace_command.settings().conflict_marker_style()?;
     formatter.push_label("diff")?;
-    for (path, left_value, right_value) in tree_diff {
-        let ui_path = workspace_command.format_file_path(&path);
+    for entry in tree_diff {
+        let left_value = entry.before.clone();
+        let right_value = entry.after.clone();
+        let ui_path = format_diff_path(workspace_command, entry);
         if left_value.is_absent() {
-            let right_content = diff_content(repo, &path, &right_value)?;
+            let right_content =
+                diff_content(repo, &entry.target, &right_value, conflict_marker_style)?;
             let description = basic_diff_file_type(&right_value);
             writeln!(
                 formatter.labeled("header"),
@@ -415,9 +446,26 @@ pub fn show_color_words_diff(
             } else {
                 show_color_words_diff_hunks(&[], &right_content, formatter)?;
             }
+        } else if let Some(copy_operation) = entry.copy_operation {
+            let left_content =
+                diff_content(repo, &entry.source, &left_value, conflict_marker_style)?;
+            let right_content =
+                diff_content(repo, &entry.target, &right_value, conflict_marker_style)?;
+            let operation = match copy_operation {
+                CopyOperation::Copy => "Copied",
+                CopyOperation::Rename => "Renamed",
+            };
+            let description = basic_diff_file_type(&right_value);
+            writeln!(
+                formatter.labeled("header"),
+                "{operation} {description} {ui_path}:"
+            )?;
+            show_color_words_diff_hunks(&left_content, &right_content, formatter)?;
         } else if right_value.is_present() {
-            let left_content = diff_content(repo, &path, &left_value)?;
-            let right_content = diff_content(repo, &path, &right_value)?;
+            let left_content =
+                diff_content(repo, &entry.source, &left_value, conflict_marker_style)?;
+            let right_content =
+                diff_content(repo, &entry.target, &right_value, conflict_marker_style)?;
             let description = match (left_value.into_resolved(), right_value.into_resolved()) {
                 (
                     Ok(Some(TreeValue::File {
@@ -460,7 +508,8 @@ pub fn show_color_words_diff(
             writeln!(formatter.labeled("header"), "{description} {ui_path}:")?;
             show_color_words_diff_hunks(&left_content, &right_content, formatter)?;
         } else {
-            let left_content = diff_content(repo, &path, &left_value)?;
+            let left_content =
+                diff_content(repo, &entry.source, &left_value, conflict_marker_style)?;
             let description = basic_diff_file_type(&left_value);
             writeln!(
                 formatter.labeled("header"),
@@ -487,6 +536,7 @@ fn git_diff_part(
     repo: &Arc<ReadonlyRepo>,
     path: &RepoPath,
     value: &Merge<Option<TreeValue>>,
+    conflict_marker_style: ConflictMarkerStyle,
 ) -> Result<GitDiffPart, CommandError> {
     let mode;
     let hash;
@@ -516,7 +566,14 @@ fn git_diff_part(
         None => {
             mode = "100644".to_string();
             hash = "0000000000".to_string();
-            conflicts::materialize(value, repo.store(), path, &mut content).unwrap();
+            conflicts::materialize(
+                value,
+                repo.store(),
+                path,
+                conflict_marker_style,
+                &mut content,
+            )
+            .unwrap();
         }
         Some(Some(TreeValue::Tree(_))) | Some(Some(TreeValue::Conflict(_))) | Some(None) => {
             panic!("Unexpected {value:?} in diff at path {path:?}");
@@ -669,14 +726,21 @@ fn show_unified_diff_hunks(
 pub fn show_git_diff(
     formatter: &mut dyn Formatter,
     workspace_command: &WorkspaceCommandHelper,
-    tree_diff: TreeDiffIterator,
+    tree_diff: &[TreeDiffEntry],
 ) -> Result<(), CommandError> {
     let repo = workspace_command.repo();
+    let conflict_marker_style = workspace_command.settings().conflict_marker_style()?;
     formatter.push_label("diff")?;
-    for (path, left_value, right_value) in tree_diff {
-        let path_string = path.to_internal_file_string();
+    for entry in tree_diff {
+        let left_value = &entry.before;
+        let right_value = &entry.after;
+        let left_path = &entry.source;
+        let right_path = &entry.target;
+        let left_path_string = left_path.to_internal_file_string();
+        let right_path_string = right_path.to_internal_file_string();
         if left_value.is_absent() {
-            let right_part = git_diff_part(repo, &path, &right_value)?;
+            let path_string = right_path_string;
+            let right_part = git_diff_part(repo, right_path, right_value, conflict_marker_style)?;
             formatter.with_label("file_header", |formatter| {
                 writeln!(formatter, "diff --git a/{path_string} b/{path_string}")?;
                 writeln!(formatter, "new file mode {}", &right_part.mode)?;
@@ -686,32 +750,46 @@ pub fn show_git_diff(
             })?;
             show_unified_diff_hunks(formatter, &[], &right_part.content)?;
         } else if right_value.is_present() {
-            let left_part = git_diff_part(repo, &path, &left_value)?;
-            let right_part = git_diff_part(repo, &path, &right_value)?;
+            let left_part = git_diff_part(repo, left_path, left_value, conflict_marker_style)?;
+            let right_part = git_diff_part(repo, right_path, right_value, conflict_marker_style)?;
             formatter.with_label("file_header", |formatter| {
-                writeln!(formatter, "diff --git a/{path_string} b/{path_string}")?;
+                writeln!(
+                    formatter,
+                    "diff --git a/{left_path_string} b/{right_path_string}"
+                )?;
                 if left_part.mode != right_part.mode {
                     writeln!(formatter, "old mode {}", &left_part.mode)?;
                     writeln!(formatter, "new mode {}", &right_part.mode)?;
-                    if left_part.hash != right_part.hash {
+                }
+                if let Some(copy_operation) = entry.copy_operation {
+                    let operation = match copy_operation {
+                        CopyOperation::Copy => "copy",
+                        CopyOperation::Rename => "rename",
+                    };
+                    writeln!(formatter, "{operation} from {left_path_string}")?;
+                    writeln!(formatter, "{operation} to {right_path_string}")?;
+                }
+                if left_part.hash != right_part.hash {
+                    if left_part.mode != right_part.mode {
                         writeln!(formatter, "index {}...{}", &left_part.hash, right_part.hash)?;
+                    } else {
+                        writeln!(
+                            formatter,
+                            "index {}...{} {}",
+                            &left_part.hash, right_part.hash, left_part.mode
+                        )?;
                     }
-                } else if left_part.hash != right_part.hash {
-                    writeln!(
-                        formatter,
-                        "index {}...{} {}",
-                        &left_part.hash, right_part.hash, left_part.mode
-                    )?;
                 }
                 if left_part.content != right_part.content {
-                    writeln!(formatter, "--- a/{path_string}")?;
-                    writeln!(formatter, "+++ b/{path_string}")?;
+                    writeln!(formatter, "--- a/{left_path_string}")?;
+                    writeln!(formatter, "+++ b/{right_path_string}")?;
                 }
                 Ok(())
             })?;
             show_unified_diff_hunks(formatter, &left_part.content, &right_part.content)?;
         } else {
-            let left_part = git_diff_part(repo, &path, &left_value)?;
+            let path_string = left_path_string;
+            let left_part = git_diff_part(repo, left_path, left_value, conflict_marker_style)?;
             formatter.with_label("file_header", |formatter| {
                 writeln!(formatter, "diff --git a/{path_string} b/{path_string}")?;
                 writeln!(formatter, "deleted file mode {}", &left_part.mode)?;
@@ -730,34 +808,39 @@ pub fn show_git_diff(
 pub fn show_diff_summary(
     formatter: &mut dyn Formatter,
     workspace_command: &WorkspaceCommandHelper,
-    tree_diff: TreeDiffIterator,
+    tree_diff: &[TreeDiffEntry],
 ) -> io::Result<()> {
     formatter.with_label("diff", |formatter| {
-        for (repo_path, before, after) in tree_diff {
-            if before.is_present() && after.is_present() {
-                writeln!(
-                    formatter.labeled("modified"),
-                    "M {}",
-                    workspace_command.format_file_path(&repo_path)
-                )?;
-            } else if before.is_absent() {
-                writeln!(
-                    formatter.labeled("added"),
-                    "A {}",
-                    workspace_command.format_file_path(&repo_path)
-                )?;
-            } else {
-                writeln!(
-                    formatter.labeled("removed"),
-                    "R {}",
-                    workspace_command.format_file_path(&repo_path)
-                )?;
-            }
+        for entry in tree_diff {
+            let (label, sigil) = match entry.copy_operation {
+                Some(CopyOperation::Copy) => ("copied", 'C'),
+                Some(CopyOperation::Rename) => ("renamed", 'R'),
+                None if entry.before.is_present() && entry.after.is_present() => ("modified", 'M'),
+                None if entry.before.is_absent() => ("added", 'A'),
+                None => ("removed", 'D'),
+            };
+            writeln!(
+                formatter.labeled(label),
+                "{sigil} {}",
+                format_diff_path(workspace_command, entry)
+            )?;
         }
         Ok(())
     })
 }
 
+/// Formats the path of a diff entry, showing the source path of copies and
+/// renames as `source => target`.
+fn format_diff_path(workspace_command: &WorkspaceCommandHelper, entry: &TreeDiffEntry) -> String {
+    let target = workspace_command.format_file_path(&entry.target);
+    if entry.copy_operation.is_some() {
+        let source = workspace_command.format_file_path(&entry.source);
+        format!("{source} => {target}")
+    } else {
+        target
+    }
+}
+
 struct DiffStat {
     path: String,
     added: usize,
@@ -790,13 +873,24 @@ pub fn show_diff_stat(
     workspace_command: &WorkspaceCommandHelper,
     tree_diff: TreeDiffIterator,
 ) -> Result<(), CommandError> {
+    let conflict_marker_style = workspace_command.settings().conflict_marker_style()?;
     let mut stats: Vec<DiffStat> = vec![];
     let mut max_path_width = 0;
     let mut max_diffs = 0;
     for (repo_path, left, right) in tree_diff {
         let path = workspace_command.format_file_path(&repo_path);
-        let left_content = diff_content(workspace_command.repo(), &repo_path, &left)?;
-        let right_content = diff_content(workspace_command.repo(), &repo_path, &right)?;
+        let left_content = diff_content(
+            workspace_command.repo(),
+            &repo_path,
+            &left,
+            conflict_marker_style,
+        )?;
+        let right_content = diff_content(
+            workspace_command.repo(),
+            &repo_path,
+            &right,
+            conflict_marker_style,
+        )?;
         max_path_width = max(max_path_width, path.width());
         let stat = get_diff_stat(path, &left_content, &right_content);
         max_diffs = max(max_diffs, stat.added + stat.removed);
@@ -13,6 +13,8 @@ use jj_lib::repo_path::RepoPath;
 use jj_lib::store::Store;
 use thiserror::Error;
 
+use super::hunks::{HunkSelection, HunkSelectionError};
+
 #[derive(Debug, Error)]
 pub enum BuiltinToolError {
     #[error("Failed to record changes: {0}")]
@@ -36,6 +38,8 @@ pub enum BuiltinToolError {
     Unimplemented { item: &'static str, id: String },
     #[error("Backend error: {0:?}")]
     BackendError(#[from] jj_lib::backend::BackendError),
+    #[error(transparent)]
+    HunkSelection(#[from] HunkSelectionError),
 }
 
 #[derive(Clone, Debug)]
@@ -425,6 +429,25 @@ pub fn edit_diff_builtin(
     Ok(tree_id)
 }
 
+/// Like `edit_diff_builtin()`, but selects the hunks given by `selection`
+/// instead of asking the user.
+pub fn select_hunks_builtin(
+    left_tree: &MergedTree,
+    right_tree: &MergedTree,
+    selection: &HunkSelection,
+) -> Result<MergedTreeId, BuiltinToolError> {
+    let store = left_tree.store().clone();
+    let changed_files = left_tree
+        .diff(right_tree, &EverythingMatcher)
+        .map(|(path, _left, _right)| path)
+        .collect_vec();
+    let mut files = make_diff_files(&store, left_tree, right_tree, &changed_files)?;
+    selection.apply(&changed_files, &mut files)?;
+    let tree_id = apply_diff_builtin(store, left_tree, right_tree, changed_files, &files)
+        .map_err(BuiltinToolError::BackendError)?;
+    Ok(tree_id)
+}
+
 fn make_merge_sections(
     merge_result: MergeResult,
 ) -> Result<Vec<scm_record::Section<'static>>, BuiltinToolError> {
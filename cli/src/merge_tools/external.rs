@@ -8,15 +8,18 @@ use std::sync::Arc;
 use config::ConfigError;
 use itertools::Itertools;
 use jj_lib::backend::{FileId, MergedTreeId, TreeValue};
-use jj_lib::conflicts::{self, materialize_merge_result};
+use jj_lib::conflicts::{self, materialize_merge_result, ConflictMarkerStyle};
 use jj_lib::gitignore::GitIgnoreFile;
 use jj_lib::matchers::{EverythingMatcher, Matcher};
 use jj_lib::merge::Merge;
 use jj_lib::merged_tree::{MergedTree, MergedTreeBuilder};
 use jj_lib::repo_path::RepoPath;
 use jj_lib::settings::UserSettings;
+use jj_lib::sparse::SparsePattern;
 use jj_lib::store::Store;
-use jj_lib::working_copy::{CheckoutError, SnapshotOptions, TreeState, TreeStateError};
+use jj_lib::working_copy::{
+    CheckoutError, CheckoutOptions, SnapshotOptions, TreeState, TreeStateError,
+};
 use regex::{Captures, Regex};
 use tempfile::TempDir;
 use thiserror::Error;
@@ -186,13 +189,14 @@ fn check_out(
     wc_dir: PathBuf,
     state_dir: PathBuf,
     tree: &MergedTree,
-    sparse_patterns: Vec<RepoPath>,
+    sparse_patterns: Vec<SparsePattern>,
+    options: &CheckoutOptions,
 ) -> Result<TreeState, DiffCheckoutError> {
     std::fs::create_dir(&wc_dir).map_err(DiffCheckoutError::SetUpDir)?;
     std::fs::create_dir(&state_dir).map_err(DiffCheckoutError::SetUpDir)?;
     let mut tree_state = TreeState::init(store, wc_dir, state_dir)?;
-    tree_state.set_sparse_patterns(sparse_patterns)?;
-    tree_state.check_out(tree)?;
+    tree_state.set_sparse_patterns(sparse_patterns, options)?;
+    tree_state.check_out(tree, options)?;
     Ok(tree_state)
 }
 
@@ -235,10 +239,11 @@ fn check_out_trees(
     right_tree: &MergedTree,
     output_is: Option<DiffSide>,
     matcher: &dyn Matcher,
+    options: &CheckoutOptions,
 ) -> Result<DiffWorkingCopies, DiffCheckoutError> {
-    let changed_files = left_tree
+    let sparse_patterns = left_tree
         .diff(right_tree, matcher)
-        .map(|(path, _left, _right)| path)
+        .map(|(path, _left, _right)| SparsePattern::include_prefix(path))
         .collect_vec();
 
     let temp_dir = new_utf8_temp_dir("jj-diff-").map_err(DiffCheckoutError::SetUpDir)?;
@@ -251,14 +256,16 @@ fn check_out_trees(
         left_wc_dir,
         left_state_dir,
         left_tree,
-        changed_files.clone(),
+        sparse_patterns.clone(),
+        options,
     )?;
     let right_tree_state = check_out(
         store.clone(),
         right_wc_dir,
         right_state_dir,
         right_tree,
-        changed_files.clone(),
+        sparse_patterns.clone(),
+        options,
     )?;
     let output_tree_state = output_is
         .map(|output_side| {
@@ -272,7 +279,8 @@ fn check_out_trees(
                     // DiffSide::Left => left_tree,
                     DiffSide::Right => right_tree,
                 },
-                changed_files,
+                sparse_patterns,
+                options,
             )
         })
         .transpose()?;
@@ -291,10 +299,11 @@ pub fn run_mergetool_external(
     repo_path: &RepoPath,
     conflict: Merge<Option<TreeValue>>,
     tree: &MergedTree,
+    conflict_marker_style: ConflictMarkerStyle,
 ) -> Result<MergedTreeId, ConflictResolveError> {
     let initial_output_content: Vec<u8> = if editor.merge_tool_edits_conflict_markers {
         let mut materialized_conflict = vec![];
-        materialize_merge_result(&content, &mut materialized_conflict)
+        materialize_merge_result(&content, conflict_marker_style, &mut materialized_conflict)
             .expect("Writing to an in-memory buffer should never fail");
         materialized_conflict
     } else {
@@ -361,6 +370,7 @@ pub fn run_mergetool_external(
             tree.store(),
             repo_path,
             output_file_contents.as_slice(),
+            conflict_marker_style,
         )?
     } else {
         let new_file_id = tree
@@ -426,12 +436,16 @@ pub fn edit_diff_external(
 ) -> Result<MergedTreeId, DiffEditError> {
     let got_output_field = find_all_variables(&editor.edit_args).contains(&"output");
     let store = left_tree.store();
+    let conflict_marker_style = settings.conflict_marker_style()?;
     let diff_wc = check_out_trees(
         store,
         left_tree,
         right_tree,
         got_output_field.then_some(DiffSide::Right),
         &EverythingMatcher,
+        &CheckoutOptions {
+            conflict_marker_style,
+        },
     )?;
     set_readonly_recursively(diff_wc.left_working_copy_path())
         .map_err(ExternalToolError::SetUpDir)?;
@@ -518,6 +532,7 @@ diff editing in mind and be a little inaccurate.
         fsmonitor_kind: settings.fsmonitor_kind()?,
         progress: None,
         max_new_file_size: settings.max_new_file_size()?,
+        conflict_marker_style,
     })?;
     Ok(output_tree_state.current_tree_id().clone())
 }
@@ -530,9 +545,13 @@ pub fn generate_diff(
     right_tree: &MergedTree,
     matcher: &dyn Matcher,
     tool: &ExternalMergeTool,
+    conflict_marker_style: ConflictMarkerStyle,
 ) -> Result<(), DiffGenerateError> {
     let store = left_tree.store();
-    let diff_wc = check_out_trees(store, left_tree, right_tree, None, matcher)?;
+    let options = CheckoutOptions {
+        conflict_marker_style,
+    };
+    let diff_wc = check_out_trees(store, left_tree, right_tree, None, matcher, &options)?;
     set_readonly_recursively(diff_wc.left_working_copy_path())
         .map_err(ExternalToolError::SetUpDir)?;
     set_readonly_recursively(diff_wc.right_working_copy_path())
@@ -0,0 +1,449 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Non-interactive selection of hunks from the diff presented by the builtin
+//! diff editor, for use by scripts and editor integrations.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use jj_lib::repo_path::{FsPathParseError, RepoPath};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum HunkSelectionError {
+    #[error(transparent)]
+    InvalidPath(#[from] FsPathParseError),
+    #[error("Invalid hunk selection {0:?}: expected `<path>:<hunks>`")]
+    InvalidSpec(String),
+    #[error("Invalid hunk number {0:?}: hunks are numbered from 1, and ranges must be ascending")]
+    InvalidHunkNumber(String),
+    #[error("Invalid patch: {0}")]
+    InvalidPatch(String),
+    #[error("No changes to select in {0}")]
+    NoChanges(String),
+    #[error("Hunk {hunk} doesn't exist in {path}, which has {num_hunks} hunk(s)")]
+    HunkOutOfRange {
+        path: String,
+        hunk: usize,
+        num_hunks: usize,
+    },
+    #[error("The patch doesn't match the changes in {0}")]
+    PatchMismatch(String),
+}
+
+/// Which hunks of a single file to select.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HunkNumbers {
+    /// All hunks, spelled `*`.
+    All,
+    /// Hunks by 1-based number.
+    Some(BTreeSet<usize>),
+}
+
+/// The removed and added lines of a run of changes in a patch, without their
+/// `-`/`+` prefix and line terminator.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PatchBlock {
+    removed: Vec<String>,
+    added: Vec<String>,
+}
+
+/// The changes to a single file in a patch.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PatchFile {
+    path: RepoPath,
+    blocks: Vec<PatchBlock>,
+    mode_changed: bool,
+    binary: bool,
+}
+
+/// A selection of hunks, as given to `--hunks`.
+///
+/// The hunks of a file are its changed sections as shown by the builtin diff
+/// editor, i.e. runs of changed lines, mode changes and binary changes,
+/// numbered from 1 in file order.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HunkSelection {
+    /// Hunk numbers per file, e.g. `src/a.rs:1,3; src/b.rs:*`.
+    Spec(Vec<(RepoPath, HunkNumbers)>),
+    /// The changes contained in a unified diff, e.g. from `jj diff --git`.
+    Patch(Vec<PatchFile>),
+}
+
+impl HunkSelection {
+    /// Parses a list of `<path>:<hunks>` entries separated by `;`, where
+    /// `<hunks>` is `*` or a comma-separated list of hunk numbers and ranges
+    /// like `2-4`. Paths are relative to `cwd`.
+    pub fn parse_spec(
+        spec: &str,
+        cwd: &Path,
+        workspace_root: &Path,
+    ) -> Result<Self, HunkSelectionError> {
+        let mut entries = vec![];
+        for entry in spec.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+            let (path, numbers) = entry
+                .rsplit_once(':')
+                .ok_or_else(|| HunkSelectionError::InvalidSpec(entry.to_owned()))?;
+            let path = RepoPath::parse_fs_path(cwd, workspace_root, path.trim())?;
+            entries.push((path, parse_hunk_numbers(numbers.trim())?));
+        }
+        if entries.is_empty() {
+            return Err(HunkSelectionError::InvalidSpec(spec.to_owned()));
+        }
+        Ok(HunkSelection::Spec(entries))
+    }
+
+    /// Parses a unified diff with paths relative to the workspace root, such
+    /// as the output of `jj diff --git` or `git diff`.
+    pub fn parse_patch(text: &str) -> Result<Self, HunkSelectionError> {
+        let mut files: Vec<PatchFile> = vec![];
+        // Whether the last file was started by a `diff --git` line, which is
+        // followed by its own `---`/`+++` lines.
+        let mut in_git_header = false;
+        let mut lines = text.lines();
+        while let Some(line) = lines.next() {
+            if let Some(rest) = line.strip_prefix("diff --git ") {
+                let path = rest
+                    .split_once(" b/")
+                    .map(|(_, path)| path)
+                    .ok_or_else(|| HunkSelectionError::InvalidPatch(line.to_owned()))?;
+                files.push(PatchFile::new(path));
+                in_git_header = true;
+            } else if let Some(path) = line.strip_prefix("--- ") {
+                let path = strip_patch_path(path, "a/");
+                if !in_git_header {
+                    files.push(PatchFile::new(path.unwrap_or_default()));
+                } else if let (Some(file), Some(path)) = (files.last_mut(), path) {
+                    file.path = RepoPath::from_internal_string(path);
+                }
+                in_git_header = false;
+            } else if let Some(path) = line.strip_prefix("+++ ") {
+                if let (Some(file), Some(path)) = (files.last_mut(), strip_patch_path(path, "b/")) {
+                    file.path = RepoPath::from_internal_string(path);
+                }
+            } else if line.starts_with("new mode ") {
+                last_file(&mut files, line)?.mode_changed = true;
+            } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+                last_file(&mut files, line)?.binary = true;
+            } else if let Some(header) = line.strip_prefix("@@ ") {
+                let (mut old_count, mut new_count) = parse_hunk_header(header)
+                    .ok_or_else(|| HunkSelectionError::InvalidPatch(line.to_owned()))?;
+                in_git_header = false;
+                let file = last_file(&mut files, line)?;
+                let mut block = PatchBlock::default();
+                while old_count > 0 || new_count > 0 {
+                    let line = lines.next().ok_or_else(|| {
+                        HunkSelectionError::InvalidPatch("unexpected end of hunk".to_owned())
+                    })?;
+                    if let Some(removed) = line.strip_prefix('-') {
+                        block.removed.push(removed.to_owned());
+                        old_count = old_count.saturating_sub(1);
+                    } else if let Some(added) = line.strip_prefix('+') {
+                        block.added.push(added.to_owned());
+                        new_count = new_count.saturating_sub(1);
+                    } else if line.starts_with('\\') {
+                        // "\ No newline at end of file"
+                    } else {
+                        // Context lines may have lost their leading space.
+                        if block != PatchBlock::default() {
+                            file.blocks.push(std::mem::take(&mut block));
+                        }
+                        old_count = old_count.saturating_sub(1);
+                        new_count = new_count.saturating_sub(1);
+                    }
+                }
+                if block != PatchBlock::default() {
+                    file.blocks.push(block);
+                }
+            }
+        }
+        if files.is_empty() {
+            return Err(HunkSelectionError::InvalidPatch(
+                "no file changes found".to_owned(),
+            ));
+        }
+        Ok(HunkSelection::Patch(files))
+    }
+
+    /// Marks the selected hunks of `files`, which must have been created by
+    /// `make_diff_files()` for `changed_files`, as checked.
+    pub fn apply(
+        &self,
+        changed_files: &[RepoPath],
+        files: &mut [scm_record::File],
+    ) -> Result<(), HunkSelectionError> {
+        match self {
+            HunkSelection::Spec(entries) => {
+                for (path, numbers) in entries {
+                    let sections = find_sections(changed_files, files, path)?;
+                    let num_hunks = sections.iter().filter(|s| is_hunk(s)).count();
+                    if let HunkNumbers::Some(numbers) = numbers {
+                        if let Some(&hunk) = numbers.iter().find(|&&n| n > num_hunks) {
+                            return Err(HunkSelectionError::HunkOutOfRange {
+                                path: path.to_internal_file_string(),
+                                hunk,
+                                num_hunks,
+                            });
+                        }
+                    }
+                    for (index, section) in sections
+                        .iter_mut()
+                        .filter(|section| is_hunk(section))
+                        .enumerate()
+                    {
+                        let selected = match numbers {
+                            HunkNumbers::All => true,
+                            HunkNumbers::Some(numbers) => numbers.contains(&(index + 1)),
+                        };
+                        if selected {
+                            check_section(section);
+                        }
+                    }
+                }
+            }
+            HunkSelection::Patch(patch_files) => {
+                for patch_file in patch_files {
+                    let sections = find_sections(changed_files, files, &patch_file.path)?;
+                    let mut remaining_blocks = patch_file.blocks.iter().peekable();
+                    for section in sections.iter_mut() {
+                        let selected = match section {
+                            scm_record::Section::Unchanged { .. } => false,
+                            scm_record::Section::Changed { lines } => {
+                                let block = PatchBlock::from_lines(lines);
+                                remaining_blocks.next_if(|&b| *b == block).is_some()
+                            }
+                            scm_record::Section::FileMode { .. } => patch_file.mode_changed,
+                            scm_record::Section::Binary { .. } => patch_file.binary,
+                        };
+                        if selected {
+                            check_section(section);
+                        }
+                    }
+                    if remaining_blocks.next().is_some() {
+                        return Err(HunkSelectionError::PatchMismatch(
+                            patch_file.path.to_internal_file_string(),
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PatchFile {
+    fn new(path: &str) -> Self {
+        PatchFile {
+            path: RepoPath::from_internal_string(path),
+            blocks: vec![],
+            mode_changed: false,
+            binary: false,
+        }
+    }
+}
+
+impl PatchBlock {
+    fn from_lines(lines: &[scm_record::SectionChangedLine]) -> Self {
+        let mut block = PatchBlock::default();
+        for line in lines {
+            let text = line.line.strip_suffix('\n').unwrap_or(&line.line);
+            let text = text.strip_suffix('\r').unwrap_or(text).to_owned();
+            match line.change_type {
+                scm_record::ChangeType::Removed => block.removed.push(text),
+                scm_record::ChangeType::Added => block.added.push(text),
+            }
+        }
+        block
+    }
+}
+
+fn parse_hunk_numbers(text: &str) -> Result<HunkNumbers, HunkSelectionError> {
+    if text == "*" {
+        return Ok(HunkNumbers::All);
+    }
+    let parse_number = |s: &str| match s.trim().parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(HunkSelectionError::InvalidHunkNumber(s.trim().to_owned())),
+    };
+    let mut numbers = BTreeSet::new();
+    for item in text.split(',') {
+        if let Some((start, end)) = item.split_once('-') {
+            let (start, end) = (parse_number(start)?, parse_number(end)?);
+            if start > end {
+                return Err(HunkSelectionError::InvalidHunkNumber(
+                    item.trim().to_owned(),
+                ));
+            }
+            numbers.extend(start..=end);
+        } else {
+            numbers.insert(parse_number(item)?);
+        }
+    }
+    Ok(HunkNumbers::Some(numbers))
+}
+
+/// Parses the `-a,b +c,d @@` part of a hunk header into the old and new line
+/// counts.
+fn parse_hunk_header(header: &str) -> Option<(usize, usize)> {
+    let mut ranges = header.split_whitespace();
+    let old = ranges.next()?.strip_prefix('-')?;
+    let new = ranges.next()?.strip_prefix('+')?;
+    let count = |range: &str| match range.split_once(',') {
+        Some((_, count)) => count.parse().ok(),
+        None => Some(1),
+    };
+    Some((count(old)?, count(new)?))
+}
+
+fn strip_patch_path<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+    // Git appends a tab to paths containing spaces.
+    let path = path.trim_end_matches('\t');
+    (path != "/dev/null").then(|| path.strip_prefix(prefix).unwrap_or(path))
+}
+
+fn last_file<'a>(
+    files: &'a mut [PatchFile],
+    line: &str,
+) -> Result<&'a mut PatchFile, HunkSelectionError> {
+    files.last_mut().ok_or_else(|| {
+        HunkSelectionError::InvalidPatch(format!("{line:?} outside of a file header"))
+    })
+}
+
+fn find_sections<'a, 'b>(
+    changed_files: &[RepoPath],
+    files: &'a mut [scm_record::File<'b>],
+    path: &RepoPath,
+) -> Result<&'a mut Vec<scm_record::Section<'b>>, HunkSelectionError> {
+    changed_files
+        .iter()
+        .position(|changed_path| changed_path == path)
+        .map(|index| &mut files[index].sections)
+        .ok_or_else(|| HunkSelectionError::NoChanges(path.to_internal_file_string()))
+}
+
+fn is_hunk(section: &scm_record::Section) -> bool {
+    !matches!(section, scm_record::Section::Unchanged { .. })
+}
+
+fn check_section(section: &mut scm_record::Section) {
+    match section {
+        scm_record::Section::Unchanged { .. } => {}
+        scm_record::Section::Changed { lines } => {
+            for line in lines {
+                line.is_checked = true;
+            }
+        }
+        scm_record::Section::FileMode { is_checked, .. }
+        | scm_record::Section::Binary { is_checked, .. } => *is_checked = true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spec() {
+        let root = Path::new("/repo");
+        let cwd = Path::new("/repo/src");
+        assert_eq!(
+            HunkSelection::parse_spec("a.rs:1,3-4; ../b.rs:*;", cwd, root).unwrap(),
+            HunkSelection::Spec(vec![
+                (
+                    RepoPath::from_internal_string("src/a.rs"),
+                    HunkNumbers::Some(BTreeSet::from([1, 3, 4]))
+                ),
+                (RepoPath::from_internal_string("b.rs"), HunkNumbers::All),
+            ])
+        );
+        assert_matches::assert_matches!(
+            HunkSelection::parse_spec("a.rs", cwd, root),
+            Err(HunkSelectionError::InvalidSpec(_))
+        );
+        assert_matches::assert_matches!(
+            HunkSelection::parse_spec("a.rs:0", cwd, root),
+            Err(HunkSelectionError::InvalidHunkNumber(_))
+        );
+        assert_matches::assert_matches!(
+            HunkSelection::parse_spec("a.rs:4-2", cwd, root),
+            Err(HunkSelectionError::InvalidHunkNumber(item)) if item == "4-2"
+        );
+        assert_matches::assert_matches!(
+            HunkSelection::parse_spec(" ; ", cwd, root),
+            Err(HunkSelectionError::InvalidSpec(_))
+        );
+    }
+
+    #[test]
+    fn test_parse_patch() {
+        let patch = "\
+diff --git a/file b/file
+index 0000000000..1111111111 100644
+--- a/file
++++ b/file
+@@ -1,4 +1,4 @@
+-a
++A
+ b
+--- c
++++ C
+ d
+--- /dev/null
++++ b/new
+@@ -0,0 +1,1 @@
++new
+\\ No newline at end of file
+diff --git a/exec b/exec
+old mode 100644
+new mode 100755
+";
+        let block = |removed: &[&str], added: &[&str]| PatchBlock {
+            removed: removed.iter().map(|s| s.to_string()).collect(),
+            added: added.iter().map(|s| s.to_string()).collect(),
+        };
+        assert_eq!(
+            HunkSelection::parse_patch(patch).unwrap(),
+            HunkSelection::Patch(vec![
+                PatchFile {
+                    path: RepoPath::from_internal_string("file"),
+                    blocks: vec![block(&["a"], &["A"]), block(&["-- c"], &["++ C"])],
+                    mode_changed: false,
+                    binary: false,
+                },
+                PatchFile {
+                    path: RepoPath::from_internal_string("new"),
+                    blocks: vec![block(&[], &["new"])],
+                    mode_changed: false,
+                    binary: false,
+                },
+                PatchFile {
+                    path: RepoPath::from_internal_string("exec"),
+                    blocks: vec![],
+                    mode_changed: true,
+                    binary: false,
+                },
+            ])
+        );
+        assert_matches::assert_matches!(
+            HunkSelection::parse_patch("@@ -1 +1 @@\n-a\n+b\n"),
+            Err(HunkSelectionError::InvalidPatch(_))
+        );
+        assert_matches::assert_matches!(
+            HunkSelection::parse_patch("not a patch\n"),
+            Err(HunkSelectionError::InvalidPatch(_))
+        );
+    }
+}
@@ -14,6 +14,7 @@
 
 mod builtin;
 mod external;
+mod hunks;
 
 use std::sync::Arc;
 
@@ -27,9 +28,12 @@ use jj_lib::settings::{ConfigResultExt as _, UserSettings};
 use jj_lib::working_copy::SnapshotError;
 use thiserror::Error;
 
-use self::builtin::{edit_diff_builtin, edit_merge_builtin, BuiltinToolError};
+use self::builtin::{
+    edit_diff_builtin, edit_merge_builtin, select_hunks_builtin, BuiltinToolError,
+};
 use self::external::{edit_diff_external, DiffCheckoutError, ExternalToolError};
 pub use self::external::{generate_diff, ExternalMergeTool};
+pub use self::hunks::{HunkSelection, HunkSelectionError};
 use crate::config::CommandNameAndArgs;
 use crate::ui::Ui;
 
@@ -119,9 +123,20 @@ pub fn run_mergetool(
             let tree_id = edit_merge_builtin(tree, repo_path, content).map_err(Box::new)?;
             Ok(tree_id)
         }
-        MergeTool::External(editor) => external::run_mergetool_external(
-            &editor, file_merge, content, repo_path, conflict, tree,
-        ),
+        MergeTool::External(editor) => {
+            let conflict_marker_style = settings
+                .conflict_marker_style()
+                .map_err(ExternalToolError::Config)?;
+            external::run_mergetool_external(
+                &editor,
+                file_merge,
+                content,
+                repo_path,
+                conflict,
+                tree,
+                conflict_marker_style,
+            )
+        }
     }
 }
 
@@ -151,6 +166,17 @@ pub fn edit_diff(
     }
 }
 
+/// Selects the given hunks of the diff between the trees without running a
+/// diff editor.
+pub fn select_hunks(
+    left_tree: &MergedTree,
+    right_tree: &MergedTree,
+    selection: &HunkSelection,
+) -> Result<MergedTreeId, DiffEditError> {
+    let tree_id = select_hunks_builtin(left_tree, right_tree, selection).map_err(Box::new)?;
+    Ok(tree_id)
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum MergeTool {
     Builtin,
@@ -0,0 +1,62 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::{Read, Write};
+use std::process::exit;
+
+use clap::Parser;
+
+/// A fake code formatter, useful for testing
+///
+/// Reads the file content from stdin and writes the formatted content to
+/// stdout.
+#[derive(Parser, Debug)]
+#[clap()]
+struct Args {
+    /// Convert all characters to uppercase
+    #[arg(long)]
+    uppercase: bool,
+
+    /// Convert all characters to lowercase
+    #[arg(long)]
+    lowercase: bool,
+
+    /// Append this text to the end of the content
+    #[arg(long)]
+    append: Option<String>,
+
+    /// Print this message on stderr and exit with a failure
+    #[arg(long)]
+    fail: Option<String>,
+}
+
+fn main() {
+    let args: Args = Args::parse();
+    if let Some(message) = args.fail {
+        eprintln!("{message}");
+        exit(1);
+    }
+    let mut content = String::new();
+    std::io::stdin().read_to_string(&mut content).unwrap();
+    if args.uppercase {
+        content = content.to_uppercase();
+    }
+    if args.lowercase {
+        content = content.to_lowercase();
+    }
+    if let Some(text) = args.append {
+        content.push_str(&text);
+    }
+    std::io::stdout().write_all(content.as_bytes()).unwrap();
+}
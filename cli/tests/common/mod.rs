@@ -311,3 +311,11 @@ pub fn escaped_fake_diff_editor_path() -> String {
     // in it
     diff_editor_path.to_str().unwrap().replace('\\', r"\\")
 }
+
+pub fn escaped_fake_formatter_path() -> String {
+    let formatter_path = assert_cmd::cargo::cargo_bin("fake-formatter");
+    assert!(formatter_path.is_file());
+    // Simplified TOML escaping, hoping that there are no '"' or control characters
+    // in it
+    formatter_path.to_str().unwrap().replace('\\', r"\\")
+}
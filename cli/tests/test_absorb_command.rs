@@ -0,0 +1,144 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+#[test]
+fn test_absorb() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    test_env.jj_cmd_success(&repo_path, &["describe", "-m", "a"]);
+    std::fs::write(repo_path.join("file1"), "a1\na2\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["new", "-m", "b"]);
+    std::fs::write(repo_path.join("file1"), "a1\na2\nb1\nb2\n").unwrap();
+    std::fs::write(repo_path.join("file2"), "b\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["new"]);
+
+    // Hunks go to the commit that last modified the lines they replace. The new
+    // file stays in place.
+    std::fs::write(repo_path.join("file1"), "A1\na2\nB1\nb2\n").unwrap();
+    std::fs::write(repo_path.join("file2"), "B\n").unwrap();
+    std::fs::write(repo_path.join("file3"), "new\n").unwrap();
+    let stdout = test_env.jj_cmd_success(&repo_path, &["absorb"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Absorbed changes into 2 revisions:
+      qpvuntsm de0cbaf8 a
+      kkmpptxz ccc1d2b8 b
+    Rebased 1 descendant commits
+    Working copy now at: zsuskuln c8aeda21 (no description set)
+    Parent commit      : kkmpptxz ccc1d2b8 b
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  c8aeda219a71
+    ◉  ccc1d2b89fab b
+    ◉  de0cbaf804aa a
+    ◉  000000000000
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file3 b/file3
+    new file mode 100644
+    index 0000000000..3e757656cf
+    --- /dev/null
+    +++ b/file3
+    @@ -1,0 +1,1 @@
+    +new
+    "###);
+
+    // Nothing is absorbed into immutable commits
+    std::fs::write(repo_path.join("file1"), "A1\na2\nB1\nB2\n").unwrap();
+    test_env.add_config(r#"revset-aliases."immutable_heads()" = "description(b)""#);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["absorb"]);
+    insta::assert_snapshot!(stdout, @"Nothing changed.");
+
+    // Immutable commits can be rewritten with --ignore-immutable
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "absorb",
+            "--ignore-immutable",
+            "--into",
+            "description(b)",
+            "file1",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    Absorbed changes into 1 revisions:
+      kkmpptxz 505c6c4e b
+    Rebased 1 descendant commits
+    Working copy now at: zsuskuln 0256b3d6 (no description set)
+    Parent commit      : kkmpptxz 505c6c4e b
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  0256b3d6f97d
+    ◉  505c6c4e460a b
+    ◉  de0cbaf804aa a
+    ◉  000000000000
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file3 b/file3
+    new file mode 100644
+    index 0000000000..3e757656cf
+    --- /dev/null
+    +++ b/file3
+    @@ -1,0 +1,1 @@
+    +new
+    "###);
+
+    // Hunks replacing lines from several commits stay in place
+    std::fs::write(repo_path.join("file1"), "A1\nX\nY\nB2\n").unwrap();
+    let stdout = test_env.jj_cmd_success(&repo_path, &["absorb", "--ignore-immutable"]);
+    insta::assert_snapshot!(stdout, @"Nothing changed.");
+}
+
+#[test]
+fn test_absorb_abandons_empty_source() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    test_env.jj_cmd_success(&repo_path, &["describe", "-m", "a"]);
+    std::fs::write(repo_path.join("file1"), "a1\na2\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["new"]);
+    std::fs::write(repo_path.join("file1"), "a1\nA2\n").unwrap();
+    let stdout = test_env.jj_cmd_success(&repo_path, &["absorb"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Absorbed changes into 1 revisions:
+      qpvuntsm 0d669085 a
+    Working copy now at: zsuskuln b11b25ea (empty) (no description set)
+    Parent commit      : qpvuntsm 0d669085 a
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  b11b25eaf36f
+    ◉  0d669085a170 a
+    ◉  000000000000
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["print", "-r", "@-", "file1"]);
+    insta::assert_snapshot!(stdout, @r###"
+    a1
+    A2
+    "###);
+}
+
+fn get_log_output(test_env: &TestEnvironment, repo_path: &Path) -> String {
+    let template = r#"commit_id.short() ++ " " ++ description.first_line()"#;
+    test_env.jj_cmd_success(repo_path, &["log", "-T", template])
+}
@@ -224,6 +224,57 @@ fn test_branch_delete_glob() {
     "###);
 }
 
+#[test]
+fn test_branch_delete_pattern() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    test_env.jj_cmd_success(&repo_path, &["describe", "-m=commit"]);
+    for branch in ["release-1", "release-2", "JIRA-12", "JIRA-x", "main"] {
+        test_env.jj_cmd_success(&repo_path, &["branch", "set", branch]);
+    }
+
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &["branch", "delete", "glob:release-*", "regex:^JIRA-[0-9]+$"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    Deleted 3 branches.
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  JIRA-x main 270721f59bf5
+    ◉   000000000000
+    "###);
+
+    // Exact names and patterns can be mixed
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &[
+            "branch",
+            "delete",
+            "exact:main",
+            "regex:^release",
+            "glob:foo*",
+        ],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Error: The provided patterns '^release', 'foo*' did not match any branches
+    "###);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["branch", "delete", "exact:foo"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: No such branch: foo
+    "###);
+
+    // Unknown pattern kind
+    let stderr = test_env.jj_cmd_cli_error(&repo_path, &["branch", "delete", "bad:main"]);
+    insta::assert_snapshot!(stderr, @r###"
+    error: invalid value 'bad:main' for '[NAMES]...': Invalid string pattern kind "bad"
+
+    For more information, try '--help'.
+    "###);
+}
+
 #[test]
 fn test_branch_forget_export() {
     let test_env = TestEnvironment::default();
@@ -384,6 +435,104 @@ fn test_branch_forget_fetched_branch() {
     "###);
 }
 
+#[test]
+fn test_branch_rename() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    let git_repo = init_git_remote_with_branch(&test_env, &repo_path);
+    test_env.jj_cmd_success(&repo_path, &["git", "fetch", "--remote=origin"]);
+    test_env.jj_cmd_success(&repo_path, &["describe", "-m", "other"]);
+    test_env.jj_cmd_success(&repo_path, &["branch", "create", "other"]);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["branch", "rename", "nope", "new"]);
+    insta::assert_snapshot!(stderr, @"Error: No such branch: nope");
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["branch", "rename", "feature1", "other"]);
+    insta::assert_snapshot!(stderr, @"Error: Branch already exists: other");
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["branch", "rename", "feature1", "feature2"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(get_branch_output(&test_env, &repo_path), @r###"
+    feature1 (deleted)
+      @origin: mzyxwzks 9f01a0e0 message
+      (this branch will be *deleted permanently* on the remote on the
+       next `jj git push`. Use `jj branch forget` to prevent this)
+    feature2: mzyxwzks 9f01a0e0 message
+    other: qpvuntsm 50bf4781 (empty) other
+    "###);
+
+    // The old name is deleted from the remote on push, and the new name is
+    // created
+    let stdout = test_env.jj_cmd_success(&repo_path, &["git", "push", "--all"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Branch changes to push to origin:
+      Delete branch feature1 from 9f01a0e04879
+      Add branch feature2 to 9f01a0e04879
+      Add branch other to 50bf47819695
+    "###);
+    assert!(git_repo.find_reference("refs/heads/feature1").is_err());
+    assert!(git_repo.find_reference("refs/heads/feature2").is_ok());
+}
+
+#[test]
+fn test_branch_track_untrack() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    let git_repo = init_git_remote_with_branch(&test_env, &repo_path);
+    test_env.jj_cmd_success(&repo_path, &["git", "fetch", "--remote=origin"]);
+
+    let stderr = test_env.jj_cmd_cli_error(&repo_path, &["branch", "untrack", "feature1"]);
+    insta::assert_snapshot!(stderr, @r###"
+    error: invalid value 'feature1' for '<NAMES>...': remote branch must be specified in branch@remote form
+
+    For more information, try '--help'.
+    "###);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["branch", "untrack", "feature1@upstream"]);
+    insta::assert_snapshot!(stderr, @"Error: No such remote branch: feature1@upstream");
+    test_env.jj_cmd_success(&repo_path, &["branch", "untrack", "feature1@origin"]);
+    insta::assert_snapshot!(get_branch_output(&test_env, &repo_path), @r###"
+    feature1: mzyxwzks 9f01a0e0 message
+      @origin (untracked): mzyxwzks 9f01a0e0 message
+    "###);
+
+    // Changes to the untracked remote branch aren't merged into the local branch
+    let parent = git_repo
+        .find_reference("refs/heads/feature1")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    let tree = parent.tree().unwrap();
+    let signature =
+        git2::Signature::new("Some One", "some.one@example.com", &git2::Time::new(0, 0)).unwrap();
+    git_repo
+        .commit(
+            Some("refs/heads/feature1"),
+            &signature,
+            &signature,
+            "another message",
+            &tree,
+            &[&parent],
+        )
+        .unwrap();
+    test_env.jj_cmd_success(&repo_path, &["git", "fetch", "--remote=origin"]);
+    insta::assert_snapshot!(get_branch_output(&test_env, &repo_path), @r###"
+    feature1: mzyxwzks 9f01a0e0 message
+      @origin (untracked) (ahead by 1 commits): ooosovrs 38aefb17 (empty) another message
+    "###);
+
+    // The local branch can't be pushed to the untracked remote branch
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["git", "push", "--branch", "feature1"]);
+    insta::assert_snapshot!(stderr, @"Error: Non-tracking remote branch feature1@origin exists");
+
+    // Tracking the remote branch merges it into the local branch
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["branch", "track", "feature1@origin"]);
+    insta::assert_snapshot!(stderr, @"");
+    insta::assert_snapshot!(get_branch_output(&test_env, &repo_path), @"feature1: ooosovrs 38aefb17 (empty) another message");
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["branch", "track", "feature1@origin"]);
+    insta::assert_snapshot!(stderr, @"Remote branch already tracked: feature1@origin");
+}
+
 #[test]
 fn test_branch_forget_deleted_or_nonexistent_branch() {
     // Much of this test is borrowed from `test_git_fetch_remote_only_branch` in
@@ -541,6 +690,63 @@ fn test_branch_list_filtered_by_revset() {
     Error: Revision "remote-delete" doesn't exist
     Hint: Did you mean "remote-delete@origin", "remote-keep", "remote-rewrite", "remote-rewrite@origin"?
     "###);
+
+    // Select branches by name pattern. Branches selected by name or revision
+    // are both listed.
+    insta::assert_snapshot!(
+        test_env.jj_cmd_success(&local_path, &["branch", "list", "glob:remote-*"]), @r###"
+    remote-delete (deleted)
+      @origin: yxusvupt dad5f298 (empty) remote-delete
+      (this branch will be *deleted permanently* on the remote on the
+       next `jj git push`. Use `jj branch forget` to prevent this)
+    remote-keep: nlwprzpn 911e9120 (empty) remote-keep
+    remote-rewrite: xyxluytn e31634b6 (empty) rewritten
+      @origin (ahead by 1 commits, behind by 1 commits): xyxluytn 3e9a5af6 (empty) remote-rewrite
+    "###);
+    insta::assert_snapshot!(
+        test_env.jj_cmd_success(
+            &local_path,
+            &["branch", "list", "regex:keep$", "-r", "branches(rewrite)"],
+        ), @r###"
+    local-keep: kpqxywon c7b4c09c (empty) local-keep
+    remote-keep: nlwprzpn 911e9120 (empty) remote-keep
+    remote-rewrite: xyxluytn e31634b6 (empty) rewritten
+      @origin (ahead by 1 commits, behind by 1 commits): xyxluytn 3e9a5af6 (empty) remote-rewrite
+    "###);
+}
+
+/// Creates a Git repo with a `feature1` branch, and adds it as the `origin`
+/// remote of the repo at `repo_path`.
+fn init_git_remote_with_branch(test_env: &TestEnvironment, repo_path: &Path) -> git2::Repository {
+    let git_repo_path = test_env.env_root().join("git-repo");
+    let git_repo = git2::Repository::init_bare(git_repo_path).unwrap();
+    {
+        let signature =
+            git2::Signature::new("Some One", "some.one@example.com", &git2::Time::new(0, 0))
+                .unwrap();
+        let mut tree_builder = git_repo.treebuilder(None).unwrap();
+        let file_oid = git_repo.blob(b"content").unwrap();
+        tree_builder
+            .insert("file", file_oid, git2::FileMode::Blob.into())
+            .unwrap();
+        let tree_oid = tree_builder.write().unwrap();
+        let tree = git_repo.find_tree(tree_oid).unwrap();
+        git_repo
+            .commit(
+                Some("refs/heads/feature1"),
+                &signature,
+                &signature,
+                "message",
+                &tree,
+                &[],
+            )
+            .unwrap();
+    }
+    test_env.jj_cmd_success(
+        repo_path,
+        &["git", "remote", "add", "origin", "../git-repo"],
+    );
+    git_repo
 }
 
 fn get_log_output(test_env: &TestEnvironment, cwd: &Path) -> String {
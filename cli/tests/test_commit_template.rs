@@ -379,6 +379,55 @@ fn test_log_git_head() {
     "###);
 }
 
+#[test]
+fn test_log_signature() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-m", "unsigned"]);
+    test_env.add_config(
+        r#"
+        signing.backend = "test"
+        signing.key = "impeccable"
+        signing.sign-all = true
+        "#,
+    );
+    test_env.jj_cmd_ok(&repo_path, &["new", "-m", "signed"]);
+
+    let template = r#"separate(" ", description.first_line(), signature) ++ "\n""#;
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "-T", template]);
+    insta::assert_snapshot!(stdout, @r###"
+    @  signed good
+    ◉  unsigned
+    ◉
+    "###);
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "-r", "signed()", "-T", template]);
+    insta::assert_snapshot!(stdout, @r###"
+    @  signed good
+    │
+    ~
+    "###);
+    let stdout =
+        test_env.jj_cmd_success(&repo_path, &["log", "-r", "signed(good)", "-T", template]);
+    insta::assert_snapshot!(stdout, @r###"
+    @  signed good
+    │
+    ~
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "-r", "signed(bad)", "-T", template]);
+    insta::assert_snapshot!(stdout, @"");
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r", "signed(valid)"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Failed to parse revset:  --> 1:8
+      |
+    1 | signed(valid)
+      |        ^---^
+      |
+      = Invalid arguments to revset function "signed": Expected function argument of type signature status
+    "###);
+}
+
 #[test]
 fn test_log_customize_short_id() {
     let test_env = TestEnvironment::default();
@@ -68,6 +68,7 @@ fn test_debug_index() {
     Max generation number: 1
     Number of heads: 1
     Number of changes: 2
+    Number of changed-path filters: 0
     Stats per level:
       Level 0:
         Number of commits: 2
@@ -90,6 +91,7 @@ fn test_debug_reindex() {
     Max generation number: 3
     Number of heads: 1
     Number of changes: 4
+    Number of changed-path filters: 0
     Stats per level:
       Level 0:
         Number of commits: 3
@@ -110,12 +112,42 @@ fn test_debug_reindex() {
     Max generation number: 3
     Number of heads: 1
     Number of changes: 4
+    Number of changed-path filters: 0
     Stats per level:
       Level 0:
         Number of commits: 4
         Name: [hash]
     "###
     );
+
+    // Changed-path filters are persisted across reindexing
+    let stdout = test_env.jj_cmd_success(&workspace_path, &["debug", "reindex", "--changed-paths"]);
+    assert_snapshot!(stdout, @r###"
+    Finished indexing 4 commits.
+    "###);
+    test_env.jj_cmd_success(&workspace_path, &["new"]);
+    test_env.jj_cmd_success(&workspace_path, &["debug", "reindex"]);
+    let stdout = test_env.jj_cmd_success(&workspace_path, &["debug", "index"]);
+    assert_snapshot!(filter_index_stats(&stdout), @r###"
+    Number of commits: 5
+    Number of merges: 0
+    Max generation number: 4
+    Number of heads: 1
+    Number of changes: 5
+    Number of changed-path filters: 5
+    Stats per level:
+      Level 0:
+        Number of commits: 5
+        Name: [hash]
+    "###
+    );
+    let stdout =
+        test_env.jj_cmd_success(&workspace_path, &["debug", "reindex", "--no-changed-paths"]);
+    assert_snapshot!(stdout, @r###"
+    Finished indexing 5 commits.
+    "###);
+    let stdout = test_env.jj_cmd_success(&workspace_path, &["debug", "index"]);
+    assert!(stdout.contains("Number of changed-path filters: 0"));
 }
 
 #[test]
@@ -32,20 +32,16 @@ fn test_diff_basic() {
 
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff"]);
     insta::assert_snapshot!(stdout, @r###"
-    Removed regular file file1:
-       1     : foo
     Modified regular file file2:
        1    1: foo
             2: bar
-    Added regular file file3:
-            1: foo
+    Renamed regular file file1 => file3:
     "###);
 
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
     insta::assert_snapshot!(stdout, @r###"
-    R file1
     M file2
-    A file3
+    R file1 => file3
     "###);
 
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--types"]);
@@ -57,13 +53,6 @@ fn test_diff_basic() {
 
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git"]);
     insta::assert_snapshot!(stdout, @r###"
-    diff --git a/file1 b/file1
-    deleted file mode 100644
-    index 257cc5642c..0000000000
-    --- a/file1
-    +++ /dev/null
-    @@ -1,1 +1,0 @@
-    -foo
     diff --git a/file2 b/file2
     index 257cc5642c...3bd1f0e297 100644
     --- a/file2
@@ -71,27 +60,15 @@ fn test_diff_basic() {
     @@ -1,1 +1,2 @@
      foo
     +bar
-    diff --git a/file3 b/file3
-    new file mode 100644
-    index 0000000000..257cc5642c
-    --- /dev/null
-    +++ b/file3
-    @@ -1,0 +1,1 @@
-    +foo
+    diff --git a/file1 b/file3
+    rename from file1
+    rename to file3
     "###);
 
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s", "--git"]);
     insta::assert_snapshot!(stdout, @r###"
-    R file1
     M file2
-    A file3
-    diff --git a/file1 b/file1
-    deleted file mode 100644
-    index 257cc5642c..0000000000
-    --- a/file1
-    +++ /dev/null
-    @@ -1,1 +1,0 @@
-    -foo
+    R file1 => file3
     diff --git a/file2 b/file2
     index 257cc5642c...3bd1f0e297 100644
     --- a/file2
@@ -99,13 +76,9 @@ fn test_diff_basic() {
     @@ -1,1 +1,2 @@
      foo
     +bar
-    diff --git a/file3 b/file3
-    new file mode 100644
-    index 0000000000..257cc5642c
-    --- /dev/null
-    +++ b/file3
-    @@ -1,0 +1,1 @@
-    +foo
+    diff --git a/file1 b/file3
+    rename from file1
+    rename to file3
     "###);
 
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--stat"]);
@@ -117,6 +90,93 @@ fn test_diff_basic() {
     "###);
 }
 
+#[test]
+fn test_diff_renames() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("exact"), "a\nb\nc\n").unwrap();
+    std::fs::write(repo_path.join("similar"), "1\n2\n3\n4\n").unwrap();
+    std::fs::write(repo_path.join("unrelated"), "foo\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["new"]);
+    std::fs::create_dir(repo_path.join("dir")).unwrap();
+    std::fs::rename(repo_path.join("exact"), repo_path.join("dir").join("exact")).unwrap();
+    std::fs::copy(
+        repo_path.join("dir").join("exact"),
+        repo_path.join("dir").join("copy"),
+    )
+    .unwrap();
+    std::fs::remove_file(repo_path.join("similar")).unwrap();
+    std::fs::write(repo_path.join("similar2"), "1\n2\n3\nfour\n").unwrap();
+    std::fs::remove_file(repo_path.join("unrelated")).unwrap();
+    std::fs::write(repo_path.join("added"), "bar\n").unwrap();
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A added
+    C exact => dir/copy
+    R exact => dir/exact
+    R similar => similar2
+    D unrelated
+    "###);
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Added regular file added:
+            1: bar
+    Copied regular file exact => dir/copy:
+    Renamed regular file exact => dir/exact:
+    Renamed regular file similar => similar2:
+       1    1: 1
+       2    2: 2
+       3    3: 3
+       4    4: 4four
+    Removed regular file unrelated:
+       1     : foo
+    "###);
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/added b/added
+    new file mode 100644
+    index 0000000000..5716ca5987
+    --- /dev/null
+    +++ b/added
+    @@ -1,0 +1,1 @@
+    +bar
+    diff --git a/exact b/dir/copy
+    copy from exact
+    copy to dir/copy
+    diff --git a/exact b/dir/exact
+    rename from exact
+    rename to dir/exact
+    diff --git a/similar b/similar2
+    rename from similar
+    rename to similar2
+    index 94ebaf9001...c33d4989a2 100644
+    --- a/similar
+    +++ b/similar2
+    @@ -1,4 +1,4 @@
+     1
+     2
+     3
+    -4
+    +four
+    diff --git a/unrelated b/unrelated
+    deleted file mode 100644
+    index 257cc5642c..0000000000
+    --- a/unrelated
+    +++ /dev/null
+    @@ -1,1 +1,0 @@
+    -foo
+    "###);
+
+    // The source of a rename is found even if it isn't matched
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s", "similar2"]);
+    insta::assert_snapshot!(stdout, @"R similar => similar2");
+}
+
 #[test]
 fn test_diff_empty() {
     let test_env = TestEnvironment::default();
@@ -372,6 +432,97 @@ fn test_diff_relative_paths() {
     "###);
 }
 
+#[test]
+fn test_diff_filesets() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::create_dir_all(repo_path.join("src").join("proto")).unwrap();
+    std::fs::write(repo_path.join("README"), "foo\n").unwrap();
+    std::fs::write(repo_path.join("src").join("lib.rs"), "foo\n").unwrap();
+    std::fs::write(repo_path.join("src").join("proto").join("foo.rs"), "foo\n").unwrap();
+    std::fs::write(
+        repo_path.join("src").join("proto").join("foo.pb.rs"),
+        "foo\n",
+    )
+    .unwrap();
+    std::fs::write(repo_path.join("file with space"), "foo\n").unwrap();
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s", "src ~ glob:**/*.pb.rs"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A src/lib.rs
+    A src/proto/foo.rs
+    "###);
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s", "~src"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A README
+    A file with space
+    "###);
+
+    let stdout = test_env.jj_cmd_success(
+        &repo_path.join("src"),
+        &["diff", "-s", "glob:*.rs", "root:README"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    A ../README
+    A lib.rs
+    "###);
+
+    // Arguments that aren't valid fileset expressions are paths
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s", "file with space"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A file with space
+    "###);
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["files", "src & glob:src/*/*.rs"]);
+    insta::assert_snapshot!(stdout, @r###"
+    src/proto/foo.pb.rs
+    src/proto/foo.rs
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["diff", "bad:foo"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Failed to parse fileset:  --> 1:1
+      |
+    1 | bad:foo
+      | ^-^
+      |
+      = Invalid file pattern kind "bad:"
+    "###);
+}
+
+#[test]
+fn test_diff_filesets_disabled() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("a"), "foo\n").unwrap();
+    std::fs::write(repo_path.join("a~b"), "foo\n").unwrap();
+    std::fs::write(repo_path.join("glob:*"), "foo\n").unwrap();
+
+    // With ui.allow-filesets disabled, arguments are plain paths
+    test_env.add_config("ui.allow-filesets = false");
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s", "a~b"]);
+    insta::assert_snapshot!(stdout, @"A a~b");
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s", "glob:*"]);
+    insta::assert_snapshot!(stdout, @"A glob:*");
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "log",
+            "--no-graph",
+            "-T",
+            "change_id.short() ++ \"\\n\"",
+            "-r",
+            r#"file("a~b")"#,
+        ],
+    );
+    insta::assert_snapshot!(stdout, @"qpvuntsmwlqt");
+}
+
 #[test]
 fn test_diff_missing_newline() {
     let test_env = TestEnvironment::default();
@@ -44,7 +44,7 @@ fn test_diffedit() {
     "###);
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
     insta::assert_snapshot!(stdout, @r###"
-    R file1
+    D file1
     M file2
     "###);
 
@@ -55,7 +55,7 @@ fn test_diffedit() {
     "###);
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
     insta::assert_snapshot!(stdout, @r###"
-    R file1
+    D file1
     M file2
     "###);
 
@@ -70,7 +70,7 @@ fn test_diffedit() {
     "###);
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
     insta::assert_snapshot!(stdout, @r###"
-    R file1
+    D file1
     "###);
 
     // Changes to a commit are propagated to descendants
@@ -105,8 +105,49 @@ fn test_diffedit() {
     "###);
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
     insta::assert_snapshot!(stdout, @r###"
-    R file1
-    R file2
+    D file1
+    D file2
+    "###);
+}
+
+#[test]
+fn test_diffedit_hunks() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file1"), "a\nb\nc\nd\ne\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["new"]);
+    std::fs::write(repo_path.join("file1"), "A\nb\nc\nd\nE\n").unwrap();
+    std::fs::write(repo_path.join("file2"), "new\n").unwrap();
+
+    // Only the selected hunks are kept
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diffedit", "--hunks", "file1:1"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Created rlvkpnrz ace197fa (no description set)
+    Working copy now at: rlvkpnrz ace197fa (no description set)
+    Parent commit      : qpvuntsm 6e631cf6 (no description set)
+    Added 0 files, modified 1 files, removed 1 files
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file1 b/file1
+    index 9405325339...5909b8479d 100644
+    --- a/file1
+    +++ b/file1
+    @@ -1,5 +1,5 @@
+    -a
+    +A
+     b
+     c
+     d
+     e
+    "###);
+
+    // Selecting everything changes nothing
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diffedit", "--hunks", "file1:*"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Nothing changed.
     "###);
 }
 
@@ -135,7 +176,7 @@ fn test_diffedit_new_file() {
     "###);
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
     insta::assert_snapshot!(stdout, @r###"
-    R file1
+    D file1
     A file2
     "###);
 
@@ -168,7 +209,7 @@ fn test_diffedit_new_file() {
     "###);
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
     insta::assert_snapshot!(stdout, @r###"
-    R file1
+    D file1
     A file2
     "###);
 }
@@ -216,7 +257,7 @@ fn test_diffedit_3pane() {
     "###);
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
     insta::assert_snapshot!(stdout, @r###"
-    R file1
+    D file1
     M file2
     "###);
     // Nothing happens if we make no changes, `config_with_right_as_after` version
@@ -229,7 +270,7 @@ fn test_diffedit_3pane() {
     "###);
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
     insta::assert_snapshot!(stdout, @r###"
-    R file1
+    D file1
     M file2
     "###);
 
@@ -247,7 +288,7 @@ fn test_diffedit_3pane() {
     "###);
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
     insta::assert_snapshot!(stdout, @r###"
-    R file1
+    D file1
     "###);
 
     // Can write something new to `file1`
@@ -281,7 +322,7 @@ fn test_diffedit_3pane() {
     "###);
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
     insta::assert_snapshot!(stdout, @r###"
-    R file1
+    D file1
     M file2
     "###);
 
@@ -335,7 +376,7 @@ fn test_diffedit_merge() {
     "###);
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s", "-r", "@-"]);
     insta::assert_snapshot!(stdout, @r###"
-    R file1
+    D file1
     A file3
     "###);
     assert!(!repo_path.join("file1").exists());
@@ -373,7 +414,7 @@ fn test_diffedit_old_restore_interactive_tests() {
     "###);
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
     insta::assert_snapshot!(stdout, @r###"
-    R file1
+    D file1
     M file2
     A file3
     "###);
@@ -385,7 +426,7 @@ fn test_diffedit_old_restore_interactive_tests() {
     "###);
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
     insta::assert_snapshot!(stdout, @r###"
-    R file1
+    D file1
     M file2
     A file3
     "###);
@@ -401,7 +442,7 @@ fn test_diffedit_old_restore_interactive_tests() {
     "###);
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
     insta::assert_snapshot!(stdout, @r###"
-    R file1
+    D file1
     "###);
 
     // Can make unrelated edits
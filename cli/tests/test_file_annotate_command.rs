@@ -0,0 +1,108 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+#[test]
+fn test_annotate_linear() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file"), "a\nb\nc\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["commit", "-m", "first"]);
+    std::fs::write(repo_path.join("file"), "a\nB\nc\nd").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["commit", "-m", "second"]);
+    std::fs::write(repo_path.join("other"), "other\n").unwrap();
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["file", "annotate", "file"]);
+    insta::assert_snapshot!(stdout, @r###"
+    qpvuntsm cd8727b6 test.user 2001-02-03 04:05:07.000 +07:00    1: a
+    rlvkpnrz fbe78c2f test.user 2001-02-03 04:05:08.000 +07:00    2: B
+    qpvuntsm cd8727b6 test.user 2001-02-03 04:05:07.000 +07:00    3: c
+    rlvkpnrz fbe78c2f test.user 2001-02-03 04:05:08.000 +07:00    4: d
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["file", "annotate", "-r", "@--", "file"]);
+    insta::assert_snapshot!(stdout, @r###"
+    qpvuntsm cd8727b6 test.user 2001-02-03 04:05:07.000 +07:00    1: a
+    qpvuntsm cd8727b6 test.user 2001-02-03 04:05:07.000 +07:00    2: b
+    qpvuntsm cd8727b6 test.user 2001-02-03 04:05:07.000 +07:00    3: c
+    "###);
+
+    // Custom template
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "file",
+            "annotate",
+            "file",
+            "--config-toml",
+            r#"templates.annotate_commit_summary = "description.first_line()""#,
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    first    1: a
+    second    2: B
+    first    3: c
+    second    4: d
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["file", "annotate", "nonexistent"]);
+    insta::assert_snapshot!(stderr, @"Error: No such path");
+    std::fs::create_dir(repo_path.join("dir")).unwrap();
+    std::fs::write(repo_path.join("dir").join("file"), "").unwrap();
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["file", "annotate", "dir"]);
+    insta::assert_snapshot!(stderr, @"Error: Path exists but is not a file");
+}
+
+#[test]
+fn test_annotate_merge_conflict() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file"), "a\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["commit", "-m", "base"]);
+    std::fs::write(repo_path.join("file"), "a\nb\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["describe", "-m", "left"]);
+    test_env.jj_cmd_success(&repo_path, &["new", "description(base)", "-m", "right"]);
+    std::fs::write(repo_path.join("file"), "a\nc\n").unwrap();
+    test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "new",
+            "description(left)",
+            "description(right)",
+            "-m",
+            "merge",
+        ],
+    );
+
+    let template = r#"templates.annotate_commit_summary = "description.first_line()""#;
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &["file", "annotate", "file", "--config-toml", template],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    base    1: a
+    merge    2: <<<<<<<
+    merge    3: %%%%%%%
+    merge    4: +b
+    merge    5: +++++++
+    right    6: c
+    merge    7: >>>>>>>
+    "###);
+}
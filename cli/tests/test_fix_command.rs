@@ -0,0 +1,222 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::{Path, PathBuf};
+
+use itertools::Itertools as _;
+
+use crate::common::{escaped_fake_formatter_path, TestEnvironment};
+
+pub mod common;
+
+fn init_with_fake_formatter(args: &[&str]) -> (TestEnvironment, PathBuf) {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    let formatter_path = escaped_fake_formatter_path();
+    let args = args.iter().map(|arg| format!(r#", "{arg}""#)).join("");
+    test_env.add_config(&format!(
+        r###"
+        [fix.tools.fake]
+        command = ["{formatter_path}"{args}]
+        patterns = ['glob:"**/*.txt"']
+        "###
+    ));
+    (test_env, repo_path)
+}
+
+#[test]
+fn test_fix_no_tools() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["fix"]);
+    insta::assert_snapshot!(stderr, @"Error: No fix tools are configured. Add some to the `fix.tools` config table.");
+}
+
+#[test]
+fn test_fix_changed_files() {
+    let (test_env, repo_path) = init_with_fake_formatter(&["--uppercase"]);
+    std::fs::write(repo_path.join("unchanged.txt"), "unchanged\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["new"]);
+    std::fs::write(repo_path.join("file.txt"), "content\n").unwrap();
+    std::fs::write(repo_path.join("file.md"), "not matched\n").unwrap();
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["fix"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Fixed 1 of 1 commits
+      rlvkpnrz 36f8bd8c (no description set)
+    Working copy now at: rlvkpnrz 36f8bd8c (no description set)
+    Parent commit      : qpvuntsm 5be8812f (no description set)
+    Added 0 files, modified 1 files, removed 0 files
+    "###);
+    // The working copy on disk is updated too
+    let content = std::fs::read_to_string(repo_path.join("file.txt")).unwrap();
+    insta::assert_snapshot!(content, @"CONTENT");
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file.md b/file.md
+    new file mode 100644
+    index 0000000000..714573f05a
+    --- /dev/null
+    +++ b/file.md
+    @@ -1,0 +1,1 @@
+    +not matched
+    diff --git a/file.txt b/file.txt
+    new file mode 100644
+    index 0000000000..57e6630bd0
+    --- /dev/null
+    +++ b/file.txt
+    @@ -1,0 +1,1 @@
+    +CONTENT
+    "###);
+
+    // Already fixed commits aren't rewritten
+    let stdout = test_env.jj_cmd_success(&repo_path, &["fix"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Fixed 0 of 1 commits
+    Nothing changed.
+    "###);
+}
+
+#[test]
+fn test_fix_stack() {
+    let (test_env, repo_path) = init_with_fake_formatter(&["--uppercase"]);
+    test_env.jj_cmd_success(&repo_path, &["describe", "-m", "a"]);
+    std::fs::write(repo_path.join("file.txt"), "a\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["new", "-m", "b"]);
+    std::fs::write(repo_path.join("other.txt"), "b\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["new", "-m", "c"]);
+    std::fs::write(repo_path.join("other.txt"), "b\nc\n").unwrap();
+
+    // Fixing only the bottom commit rebases the others on top
+    let stdout = test_env.jj_cmd_success(&repo_path, &["fix", "-s", "description(a)"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Fixed 1 of 1 commits
+      qpvuntsm b992d327 a
+    Rebased 2 descendant commits
+    Working copy now at: zsuskuln ca8807fc c
+    Parent commit      : kkmpptxz 97a24b88 b
+    Added 0 files, modified 1 files, removed 0 files
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  ca8807fcdd6f c
+    ◉  97a24b885237 b
+    ◉  b992d327e0d7 a
+    ◉  000000000000
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["print", "-r", "@", "other.txt"]);
+    insta::assert_snapshot!(stdout, @r###"
+    b
+    c
+    "###);
+
+    // Fixes in ancestors are carried over to files that descendants didn't
+    // change
+    test_env.jj_cmd_success(&repo_path, &["op", "undo"]);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["fix", "-s", "description(a)::"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Fixed 3 of 3 commits
+      qpvuntsm 316f9bcd a
+      kkmpptxz a379550a b
+      zsuskuln 4612d53a c
+    Working copy now at: zsuskuln 4612d53a c
+    Parent commit      : kkmpptxz a379550a b
+    Added 0 files, modified 2 files, removed 0 files
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["print", "-r", "@", "file.txt"]);
+    insta::assert_snapshot!(stdout, @"A");
+    let stdout = test_env.jj_cmd_success(&repo_path, &["print", "-r", "@", "other.txt"]);
+    insta::assert_snapshot!(stdout, @r###"
+    B
+    C
+    "###);
+}
+
+#[test]
+fn test_fix_paths_and_tool_order() {
+    let (test_env, repo_path) = init_with_fake_formatter(&["--uppercase"]);
+    let formatter_path = escaped_fake_formatter_path();
+    test_env.add_config(&format!(
+        r###"
+        [fix.tools.append-path]
+        command = ["{formatter_path}", "--append", "fixed $path\n"]
+        patterns = ["dir"]
+        "###
+    ));
+    std::fs::create_dir(repo_path.join("dir")).unwrap();
+    std::fs::write(repo_path.join("dir/file.txt"), "content\n").unwrap();
+    std::fs::write(repo_path.join("dir/other"), "content\n").unwrap();
+    std::fs::write(repo_path.join("file.txt"), "content\n").unwrap();
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["fix", "dir"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Fixed 1 of 1 commits
+      qpvuntsm aa9f1caf (no description set)
+    Working copy now at: qpvuntsm aa9f1caf (no description set)
+    Parent commit      : zzzzzzzz 00000000 (empty) (no description set)
+    Added 0 files, modified 2 files, removed 0 files
+    "###);
+    // Tools run in the order of their names
+    let content = std::fs::read_to_string(repo_path.join("dir/file.txt")).unwrap();
+    insta::assert_snapshot!(content, @r###"
+    CONTENT
+    FIXED DIR/FILE.TXT
+    "###);
+    let content = std::fs::read_to_string(repo_path.join("dir/other")).unwrap();
+    insta::assert_snapshot!(content, @r###"
+    content
+    fixed dir/other
+    "###);
+    let content = std::fs::read_to_string(repo_path.join("file.txt")).unwrap();
+    insta::assert_snapshot!(content, @"content");
+}
+
+#[test]
+fn test_fix_tool_failure() {
+    let (test_env, repo_path) = init_with_fake_formatter(&["--fail", "syntax error"]);
+    std::fs::write(repo_path.join("file.txt"), "content\n").unwrap();
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["fix"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Fix tool `fake` failed on file.txt:
+    syntax error
+    "###);
+    let content = std::fs::read_to_string(repo_path.join("file.txt")).unwrap();
+    insta::assert_snapshot!(content, @"content");
+}
+
+#[test]
+fn test_fix_invalid_config() {
+    let (test_env, repo_path) = init_with_fake_formatter(&[]);
+    test_env.add_config(
+        r#"
+        fix.tools.bad.command = ["true"]
+        fix.tools.bad.patterns = ["glob:("]
+        "#,
+    );
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["fix"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Invalid pattern in `fix.tools.bad.patterns`:  --> 1:6
+      |
+    1 | glob:(
+      |      ^---
+      |
+      = expected <bare_string>
+    "###);
+}
+
+fn get_log_output(test_env: &TestEnvironment, repo_path: &Path) -> String {
+    let template = r#"commit_id.short() ++ " " ++ description.first_line()"#;
+    test_env.jj_cmd_success(repo_path, &["log", "-T", template])
+}
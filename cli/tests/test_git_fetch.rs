@@ -1112,9 +1112,14 @@ fn test_git_fetch_remote_only_branch() {
     test_env.jj_cmd_success(&repo_path, &["git", "fetch", "--remote=origin"]);
     insta::assert_snapshot!(get_branch_output(&test_env, &repo_path), @r###"
     feature1: mzyxwzks 9f01a0e0 message
-    feature2 (deleted)
-      @origin: mzyxwzks 9f01a0e0 message
-      (this branch will be *deleted permanently* on the remote on the
-       next `jj git push`. Use `jj branch forget` to prevent this)
+    feature2
+      @origin (untracked): mzyxwzks 9f01a0e0 message
+    "###);
+
+    // Tracking the remote branch creates the local branch
+    test_env.jj_cmd_success(&repo_path, &["branch", "track", "feature2@origin"]);
+    insta::assert_snapshot!(get_branch_output(&test_env, &repo_path), @r###"
+    feature1: mzyxwzks 9f01a0e0 message
+    feature2: mzyxwzks 9f01a0e0 message
     "###);
 }
@@ -575,6 +575,64 @@ fn test_git_push_deleted() {
     "###);
 }
 
+#[test]
+fn test_git_push_tags() {
+    let (test_env, workspace_root) = set_up();
+    let origin_git_repo = git2::Repository::open(
+        test_env
+            .env_root()
+            .join("origin")
+            .join(".jj")
+            .join("repo")
+            .join("store")
+            .join("git"),
+    )
+    .unwrap();
+
+    test_env.jj_cmd_success(&workspace_root, &["new", "branch1", "-m", "release"]);
+    test_env.jj_cmd_success(&workspace_root, &["tag", "create", "v1", "-m", "Version 1"]);
+    let stderr = test_env.jj_cmd_failure(&workspace_root, &["git", "push", "--tag", "v2"]);
+    insta::assert_snapshot!(stderr, @"Error: Tag v2 doesn't exist");
+    let stdout = test_env.jj_cmd_success(
+        &workspace_root,
+        &["git", "push", "--tag", "v1", "--dry-run"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    Tags to push to origin:
+      Push tag v1 to 8fee1ca56720
+    Dry-run requested, not pushing.
+    "###);
+    assert!(origin_git_repo.find_reference("refs/tags/v1").is_err());
+
+    // Only the tag is pushed, not the current branches
+    let stdout = test_env.jj_cmd_success(&workspace_root, &["git", "push", "--tag", "v1"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Tags to push to origin:
+      Push tag v1 to 8fee1ca56720
+    "###);
+    let git_tag = origin_git_repo
+        .find_reference("refs/tags/v1")
+        .unwrap()
+        .peel_to_tag()
+        .unwrap();
+    assert_eq!(git_tag.message(), Some("Version 1\n"));
+    let stdout = test_env.jj_cmd_success(
+        &workspace_root,
+        &[
+            "log",
+            "-r",
+            "tags()",
+            "-T",
+            r#"separate(" ", tags, branches)"#,
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    @  v1
+    │
+    ~
+    "###);
+}
+
 #[test]
 fn test_git_push_conflicting_branches() {
     let (test_env, workspace_root) = set_up();
@@ -0,0 +1,204 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+#[test]
+fn test_rewrite_immutable_generic() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    std::fs::write(repo_path.join("file"), "a").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["describe", "-m=a"]);
+    test_env.jj_cmd_success(&repo_path, &["new", "-m=b"]);
+    std::fs::write(repo_path.join("file"), "b").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["branch", "create", "main"]);
+    test_env.jj_cmd_success(&repo_path, &["new", "main-", "-m=c"]);
+    std::fs::write(repo_path.join("file"), "c").unwrap();
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log"]);
+    insta::assert_snapshot!(stdout, @r###"
+    @  mzvwutvl test.user@example.com 2001-02-03 04:05:12.000 +07:00 78ebd449
+    │  c
+    │ ◉  kkmpptxz test.user@example.com 2001-02-03 04:05:10.000 +07:00 main c8d4c7ca
+    ├─╯  b
+    ◉  qpvuntsm test.user@example.com 2001-02-03 04:05:08.000 +07:00 46a8dc51
+    │  a
+    ◉  zzzzzzzz root() 00000000
+    "###);
+
+    // Cannot rewrite a commit in the configured set
+    test_env.add_config(r#"revset-aliases."immutable_heads()" = "main""#);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["edit", "main"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit c8d4c7ca95d0 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
+    "###);
+    // Cannot rewrite an ancestor of the configured set
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["edit", "main-"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit 46a8dc5175be is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
+    "###);
+    // Cannot rewrite the root commit even with an empty set of immutable commits
+    test_env.add_config(r#"revset-aliases."immutable_heads()" = "none()""#);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["edit", "root()"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Cannot rewrite the root commit
+    "###);
+
+    // Error mutating the repo if immutable_heads() uses a ref that can't be
+    // resolved
+    test_env.add_config(r#"revset-aliases."immutable_heads()" = "branch_that_does_not_exist""#);
+    // Suppress warning in the commit summary template
+    test_env.add_config("template-aliases.'format_short_id(id)' = 'id.short(8)'");
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["describe", "-m=d"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Revision "branch_that_does_not_exist" doesn't exist
+    "###);
+
+    // Can use --ignore-immutable to override
+    test_env.add_config(r#"revset-aliases."immutable_heads()" = "main""#);
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["--ignore-immutable", "edit", "main"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Working copy now at: kkmpptxz c8d4c7ca main | b
+    Parent commit      : qpvuntsm 46a8dc51 a
+    Added 0 files, modified 1 files, removed 0 files
+    "###);
+    insta::assert_snapshot!(stderr, @"");
+    // ... but not the root commit
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["--ignore-immutable", "edit", "root()"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Cannot rewrite the root commit
+    "###);
+}
+
+#[test]
+fn test_rewrite_immutable_commands() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    std::fs::write(repo_path.join("file"), "a").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["describe", "-m=a"]);
+    test_env.jj_cmd_success(&repo_path, &["new", "-m=b"]);
+    std::fs::write(repo_path.join("file"), "b").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["new", "@-", "-m=c"]);
+    std::fs::write(repo_path.join("file"), "c").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["new", "all:visible_heads()", "-m=merge"]);
+    test_env.jj_cmd_success(&repo_path, &["branch", "create", "main"]);
+    test_env.jj_cmd_success(&repo_path, &["new", "main", "-m=d"]);
+    std::fs::write(repo_path.join("file"), "d").unwrap();
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log"]);
+    insta::assert_snapshot!(stdout, @r###"
+    @  yqosqzyt test.user@example.com 2001-02-03 04:05:14.000 +07:00 d4182782
+    │  d
+    ◉    mzvwutvl test.user@example.com 2001-02-03 04:05:11.000 +07:00 main d809c5d9 conflict
+    ├─╮  (empty) merge
+    │ ◉  kkmpptxz test.user@example.com 2001-02-03 04:05:10.000 +07:00 c8d4c7ca
+    │ │  b
+    ◉ │  zsuskuln test.user@example.com 2001-02-03 04:05:11.000 +07:00 6e11f430
+    ├─╯  c
+    ◉  qpvuntsm test.user@example.com 2001-02-03 04:05:08.000 +07:00 46a8dc51
+    │  a
+    ◉  zzzzzzzz root() 00000000
+    "###);
+    test_env.add_config(r#"revset-aliases."immutable_heads()" = "main""#);
+
+    // abandon
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["abandon", "main"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit d809c5d93710 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
+    "###);
+    // chmod
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["chmod", "-r=main", "x", "file"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit d809c5d93710 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
+    "###);
+    // describe
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["describe", "main"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit d809c5d93710 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
+    "###);
+    // diffedit
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["diffedit", "-r=main"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit d809c5d93710 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
+    "###);
+    // edit
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["edit", "main"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit d809c5d93710 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
+    "###);
+    // new --insert-before
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["new", "--insert-before", "main"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit d809c5d93710 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
+    "###);
+    // new --insert-after a parent of main
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["new", "--insert-after", "description(b)"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit d809c5d93710 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
+    "###);
+    // rebase -s
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["rebase", "-s=main", "-d=root()"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit d809c5d93710 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
+    "###);
+    // rebase -b
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["rebase", "-b=main", "-d=root()"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit 46a8dc5175be is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
+    "###);
+    // rebase -r
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["rebase", "-r=main", "-d=root()"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit d809c5d93710 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
+    "###);
+    // resolve
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["resolve", "-r=main", "file"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit d809c5d93710 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
+    "###);
+    // split
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["split", "-r=main"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit d809c5d93710 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
+    "###);
+    // squash
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["squash"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Commit d809c5d93710 is immutable
+    Hint: Configure the set of immutable commits via `revset-aliases.immutable_heads()`, or pass `--ignore-immutable` to rewrite it anyway.
+    "###);
+
+    // The working-copy commit on top of main is still mutable
+    let stdout = test_env.jj_cmd_success(&repo_path, &["describe", "-m=d2"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Working copy now at: yqosqzyt 5bbd6045 d2
+    Parent commit      : mzvwutvl d809c5d9 main | (conflict) (empty) merge
+    "###);
+}
@@ -303,6 +303,317 @@ fn test_op_log_configurable() {
     assert!(stdout.contains("my-username@my-hostname"));
 }
 
+#[test]
+fn test_op_show() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    // The initial operation has nothing to compare to
+    let stdout = test_env.jj_cmd_success(&repo_path, &["op", "show", "@-"]);
+    insta::assert_snapshot!(&stdout, @r###"
+    f1c462c494be test-username@host.example.com 2001-02-03 04:05:07.000 +07:00 - 2001-02-03 04:05:07.000 +07:00
+    initialize repo
+    "###);
+
+    test_env.jj_cmd_success(&repo_path, &["describe", "-m", "description 0"]);
+    test_env.jj_cmd_success(&repo_path, &["branch", "create", "foo"]);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["op", "show", "@-"]);
+    insta::assert_snapshot!(&stdout, @r###"
+    a862b0d19e68 test-username@host.example.com 2001-02-03 04:05:09.000 +07:00 - 2001-02-03 04:05:09.000 +07:00
+    describe commit 230dd059e1b059aefc0da06a2e5a7dbf22362f22
+    args: jj describe -m 'description 0'
+
+    Changed commits:
+    + qpvuntsm bc041dfc foo | (empty) description 0
+    - qpvuntsm 230dd059 (empty) (no description set)
+
+    Changed working copy default@:
+    + qpvuntsm bc041dfc foo | (empty) description 0
+    - qpvuntsm 230dd059 (empty) (no description set)
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["op", "show"]);
+    insta::assert_snapshot!(&stdout, @r###"
+    4a5361134c34 test-username@host.example.com 2001-02-03 04:05:10.000 +07:00 - 2001-02-03 04:05:10.000 +07:00
+    create branch foo pointing to commit bc041dfcf4a1fc5d86e132f1d1d3c26db5987b3d
+    args: jj branch create foo
+
+    Changed local branches:
+    foo:
+    + qpvuntsm bc041dfc foo | (empty) description 0
+    - (absent)
+    "###);
+
+    // Show patch of the rewritten commit
+    std::fs::write(repo_path.join("file"), "a\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["new", "-m", "description 1"]);
+    std::fs::write(repo_path.join("file"), "b\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["status"]);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["op", "show", "-p", "--git"]);
+    insta::assert_snapshot!(&stdout, @r###"
+    60c336f70773 test-username@host.example.com 2001-02-03 04:05:14.000 +07:00 - 2001-02-03 04:05:14.000 +07:00
+    snapshot working copy
+    args: jj status
+
+    Changed commits:
+    + yqosqzyt 7add6bb7 description 1
+    diff --git a/file b/file
+    index 7898192261...6178079822 100644
+    --- a/file
+    +++ b/file
+    @@ -1,1 +1,1 @@
+    -a
+    +b
+    - yqosqzyt 3f251678 (empty) description 1
+
+    Changed working copy default@:
+    + yqosqzyt 7add6bb7 description 1
+    - yqosqzyt 3f251678 (empty) description 1
+    "###);
+}
+
+#[test]
+fn test_op_diff() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_success(&repo_path, &["describe", "-m", "description 0"]);
+    test_env.jj_cmd_ok(&repo_path, &["branch", "create", "foo", "bar"]);
+    test_env.jj_cmd_success(&repo_path, &["new", "-m", "description 1"]);
+    test_env.jj_cmd_success(&repo_path, &["branch", "set", "foo", "-r", "@"]);
+    test_env.jj_cmd_success(&repo_path, &["branch", "delete", "bar"]);
+
+    // Defaults to the last operation
+    let stdout = test_env.jj_cmd_success(&repo_path, &["op", "diff"]);
+    insta::assert_snapshot!(&stdout, @r###"
+    Changed local branches:
+    bar:
+    + (absent)
+    - qpvuntsm bc8f18aa (empty) description 0
+    "###);
+
+    let stdout =
+        test_env.jj_cmd_success(&repo_path, &["op", "diff", "--from", "@---", "--to", "@"]);
+    insta::assert_snapshot!(&stdout, @r###"
+    Changed commits:
+    + zsuskuln b02f0de6 foo | (empty) description 1
+
+    Changed working copy default@:
+    + zsuskuln b02f0de6 foo | (empty) description 1
+    - qpvuntsm bc8f18aa (empty) description 0
+
+    Changed local branches:
+    bar:
+    + (absent)
+    - qpvuntsm bc8f18aa (empty) description 0
+    foo:
+    + zsuskuln b02f0de6 foo | (empty) description 1
+    - qpvuntsm bc8f18aa (empty) description 0
+    "###);
+
+    // Reverse order shows the opposite changes
+    let stdout =
+        test_env.jj_cmd_success(&repo_path, &["op", "diff", "--from", "@", "--to", "@---"]);
+    insta::assert_snapshot!(&stdout, @r###"
+    Changed commits:
+    - zsuskuln b02f0de6 foo | (empty) description 1
+
+    Changed working copy default@:
+    + qpvuntsm bc8f18aa (empty) description 0
+    - zsuskuln b02f0de6 foo | (empty) description 1
+
+    Changed local branches:
+    bar:
+    + qpvuntsm bc8f18aa (empty) description 0
+    - (absent)
+    foo:
+    + qpvuntsm bc8f18aa (empty) description 0
+    - zsuskuln b02f0de6 foo | (empty) description 1
+    "###);
+
+    // Nothing changed
+    let stdout = test_env.jj_cmd_success(&repo_path, &["op", "diff", "--from", "@", "--to", "@"]);
+    insta::assert_snapshot!(&stdout, @"");
+}
+
+#[test]
+fn test_op_diff_concurrent() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_success(&repo_path, &["commit", "-m", "base"]);
+    let base_op_id = test_env.jj_cmd_success(
+        &repo_path,
+        &["op", "log", "--no-graph", "--limit=1", "-T", "id.short()"],
+    );
+    test_env.jj_cmd_success(
+        &repo_path,
+        &["branch", "create", "-r@-", "left", "--at-op=@"],
+    );
+    test_env.jj_cmd_success(&repo_path, &["describe", "-m", "right", "--at-op=@-"]);
+
+    // The merge operation is compared to the merged parent operations, so it
+    // has no changes of its own
+    let stdout = test_env.jj_cmd_success(&repo_path, &["op", "diff"]);
+    insta::assert_snapshot!(&stdout, @r###"
+    Concurrent modification detected, resolving automatically.
+    "###);
+
+    // Changes from both sides of the merge are shown
+    let stdout = test_env.jj_cmd_success(&repo_path, &["op", "diff", "--from", &base_op_id]);
+    insta::assert_snapshot!(&stdout, @r###"
+    Changed commits:
+    + rlvkpnrz fdd09279 (empty) right
+    - rlvkpnrz 2d9062b7 (empty) (no description set)
+
+    Changed working copy default@:
+    + rlvkpnrz fdd09279 (empty) right
+    - rlvkpnrz 2d9062b7 (empty) (no description set)
+
+    Changed local branches:
+    left:
+    + qpvuntsm de9c740f left | (empty) base
+    - (absent)
+    "###);
+}
+
+#[test]
+fn test_op_abandon_ancestors() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_success(&repo_path, &["commit", "-m", "commit 1"]);
+    test_env.jj_cmd_success(&repo_path, &["commit", "-m", "commit 2"]);
+    insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["op", "log"]), @r###"
+    @  bacc8030a969 test-username@host.example.com 2001-02-03 04:05:09.000 +07:00 - 2001-02-03 04:05:09.000 +07:00
+    │  commit a8ac27b29a157ae7dabc0deb524df68823505730
+    │  args: jj commit -m 'commit 2'
+    ◉  bb26fe31d66f test-username@host.example.com 2001-02-03 04:05:08.000 +07:00 - 2001-02-03 04:05:08.000 +07:00
+    │  commit 230dd059e1b059aefc0da06a2e5a7dbf22362f22
+    │  args: jj commit -m 'commit 1'
+    ◉  19b8089fc78b test-username@host.example.com 2001-02-03 04:05:07.000 +07:00 - 2001-02-03 04:05:07.000 +07:00
+    │  add workspace 'default'
+    ◉  f1c462c494be test-username@host.example.com 2001-02-03 04:05:07.000 +07:00 - 2001-02-03 04:05:07.000 +07:00
+       initialize repo
+    "###);
+
+    // Abandon old operations. The working-copy operation id should be updated.
+    let stdout = test_env.jj_cmd_success(&repo_path, &["op", "abandon", "..@-"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Abandoned 3 operations and reparented 1 descendant operations.
+    "###);
+    insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["debug", "workingcopy"]), @r###"
+    Current operation: OperationId("4a4fd47ed05e44d35cec42e575b27be64f58d42682c05121ac3204d70de49da2e6f93003289d868a843022ef5b82474c75d1a81a79d249aac053fc72fafaf778")
+    Current tree: Legacy(TreeId("4b825dc642cb6eb9a060e54bf8d69288fbee4904"))
+    "###);
+    insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["op", "log"]), @r###"
+    @  4a4fd47ed05e test-username@host.example.com 2001-02-03 04:05:09.000 +07:00 - 2001-02-03 04:05:09.000 +07:00
+       commit a8ac27b29a157ae7dabc0deb524df68823505730
+       args: jj commit -m 'commit 2'
+    "###);
+    insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["log"]), @r###"
+    @  kkmpptxz test.user@example.com 2001-02-03 04:05:09.000 +07:00 268f5f16
+    │  (empty) (no description set)
+    ◉  rlvkpnrz test.user@example.com 2001-02-03 04:05:09.000 +07:00 ff30ac88
+    │  (empty) commit 2
+    ◉  qpvuntsm test.user@example.com 2001-02-03 04:05:08.000 +07:00 6534408e
+    │  (empty) commit 1
+    ◉  zzzzzzzz root() 00000000
+    "###);
+
+    // Abandon the current operation
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["op", "abandon", "..@"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Cannot abandon the current operation
+    "###);
+}
+
+#[test]
+fn test_op_abandon_range() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_success(&repo_path, &["commit", "-m", "commit 1"]);
+    test_env.jj_cmd_success(&repo_path, &["commit", "-m", "commit 2"]);
+    test_env.jj_cmd_success(&repo_path, &["describe", "-m", "commit 3"]);
+
+    // Abandon a single operation
+    let stdout = test_env.jj_cmd_success(&repo_path, &["op", "abandon", "@-"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Abandoned 1 operations and reparented 1 descendant operations.
+    "###);
+    // Abandon a range of operations
+    let stdout = test_env.jj_cmd_success(&repo_path, &["op", "abandon", "@---..@--"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Abandoned 1 operations and reparented 2 descendant operations.
+    "###);
+    insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["op", "log"]), @r###"
+    @  50367c26890e test-username@host.example.com 2001-02-03 04:05:10.000 +07:00 - 2001-02-03 04:05:10.000 +07:00
+    │  describe commit 268f5f16139313ff25bef31280b2ec2e675200f3
+    │  args: jj describe -m 'commit 3'
+    ◉  b2d580ce9ffb test-username@host.example.com 2001-02-03 04:05:08.000 +07:00 - 2001-02-03 04:05:08.000 +07:00
+    │  commit 230dd059e1b059aefc0da06a2e5a7dbf22362f22
+    │  args: jj commit -m 'commit 1'
+    ◉  f1c462c494be test-username@host.example.com 2001-02-03 04:05:07.000 +07:00 - 2001-02-03 04:05:07.000 +07:00
+       initialize repo
+    "###);
+    // The repo state is unchanged
+    insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["log"]), @r###"
+    @  kkmpptxz test.user@example.com 2001-02-03 04:05:10.000 +07:00 c931727b
+    │  (empty) commit 3
+    ◉  rlvkpnrz test.user@example.com 2001-02-03 04:05:09.000 +07:00 ff30ac88
+    │  (empty) commit 2
+    ◉  qpvuntsm test.user@example.com 2001-02-03 04:05:08.000 +07:00 6534408e
+    │  (empty) commit 1
+    ◉  zzzzzzzz root() 00000000
+    "###);
+
+    // Empty range
+    let stdout = test_env.jj_cmd_success(&repo_path, &["op", "abandon", "@-..@-"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Nothing changed.
+    "###);
+    // Root of the range must be an ancestor of the head
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["op", "abandon", "@-..@--"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Operation b2d580ce9ffb is not an ancestor of f1c462c494be
+    "###);
+}
+
+#[test]
+fn test_op_abandon_stale_workspace() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "main", "--git"]);
+    let main_path = test_env.env_root().join("main");
+    let secondary_path = test_env.env_root().join("secondary");
+    std::fs::write(main_path.join("file"), "contents\n").unwrap();
+    test_env.jj_cmd_success(&main_path, &["commit", "-m", "initial"]);
+    test_env.jj_cmd_success(&main_path, &["workspace", "add", "../secondary"]);
+
+    // Rewrite the secondary working-copy commit, then abandon the operation the
+    // secondary workspace was last updated at
+    test_env.jj_cmd_success(&main_path, &["rebase", "-r", "secondary@", "-d", "root()"]);
+    test_env.jj_cmd_success(&main_path, &["op", "abandon", "@-"]);
+
+    // The secondary workspace can be recovered
+    let stderr = test_env.jj_cmd_failure(&secondary_path, &["status"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: The repo was loaded at operation 1acf6712d8de, which seems to be a sibling of the working copy's operation 384ccd5cc109
+    Hint: Run `jj workspace update-stale` to update the working copy.
+    "###);
+    let stdout = test_env.jj_cmd_success(&secondary_path, &["workspace", "update-stale"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Working copy now at: pmmvwywv 0d8d5860 (empty) (no description set)
+    Added 0 files, modified 0 files, removed 1 files
+    "###);
+    let stdout = test_env.jj_cmd_success(&secondary_path, &["status"]);
+    insta::assert_snapshot!(stdout, @r###"
+    The working copy is clean
+    Working copy : pmmvwywv 0d8d5860 (empty) (no description set)
+    Parent commit: zzzzzzzz 00000000 (empty) (no description set)
+    "###);
+}
+
 fn get_log_output(test_env: &TestEnvironment, repo_path: &Path, op_id: &str) -> String {
     test_env.jj_cmd_success(
         repo_path,
@@ -0,0 +1,259 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::{get_stderr_string, get_stdout_string, TestEnvironment};
+
+pub mod common;
+
+#[test]
+fn test_patch_export() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file"), "a\nb\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["commit", "-m", "add file"]);
+    std::fs::write(repo_path.join("file"), "a\nc\n").unwrap();
+    test_env.jj_cmd_success(
+        &repo_path,
+        &["describe", "-m", "change file\n\nMore details"],
+    );
+
+    // Exports the working-copy commit by default
+    let stdout = test_env.jj_cmd_success(&repo_path, &["patch", "export"]);
+    insta::assert_snapshot!(stdout, @r###"
+    From a5c4907513c65321247652b348b4a5e04d732cee Mon Sep 17 00:00:00 2001
+    From: Test User <test.user@example.com>
+    Date: Sat, 03 Feb 2001 04:05:08 +0700
+    Subject: [PATCH] change file
+    MIME-Version: 1.0
+    Content-Type: text/plain; charset=UTF-8
+    Content-Transfer-Encoding: 8bit
+
+    More details
+
+    Change-Id: rlvkpnrzqnoowoytxnquwvuryrwnrmlp
+    ---
+    diff --git a/file b/file
+    index 422c2b7ab3...0f7bc76605 100644
+    --- a/file
+    +++ b/file
+    @@ -1,2 +1,2 @@
+     a
+    -b
+    +c
+    "###);
+
+    // Exports several revisions, parents first
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &["patch", "export", "-r", "@", "-r", "@-", "-o", "patches"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    patches/0001-add-file.patch
+    patches/0002-change-file.patch
+    "###);
+    let patch = std::fs::read_to_string(repo_path.join("patches/0001-add-file.patch")).unwrap();
+    insta::assert_snapshot!(patch, @r###"
+    From 08a5f172f44cb4d0a0a4ff66110dafa4cd5ee05a Mon Sep 17 00:00:00 2001
+    From: Test User <test.user@example.com>
+    Date: Sat, 03 Feb 2001 04:05:07 +0700
+    Subject: [PATCH 1/2] add file
+    MIME-Version: 1.0
+    Content-Type: text/plain; charset=UTF-8
+    Content-Transfer-Encoding: 8bit
+
+    Change-Id: qpvuntsmwlqtpsluzzsnyyzlmlwvmlnu
+    ---
+    diff --git a/file b/file
+    new file mode 100644
+    index 0000000000..422c2b7ab3
+    --- /dev/null
+    +++ b/file
+    @@ -1,0 +1,2 @@
+    +a
+    +b
+    "###);
+}
+
+#[test]
+fn test_patch_roundtrip() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo1", "--git"]);
+    let repo1_path = test_env.env_root().join("repo1");
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo2", "--git"]);
+    let repo2_path = test_env.env_root().join("repo2");
+
+    std::fs::write(repo1_path.join("file1"), "a\n").unwrap();
+    std::fs::write(repo1_path.join("file2"), "b\n").unwrap();
+    test_env.jj_cmd_success(&repo1_path, &["commit", "-m", "first"]);
+    std::fs::write(repo1_path.join("file1"), "a\na\n").unwrap();
+    std::fs::remove_file(repo1_path.join("file2")).unwrap();
+    std::fs::write(repo1_path.join("file3"), "c").unwrap();
+    test_env.jj_cmd_success(&repo1_path, &["commit", "-m", "second\n\nWith a body"]);
+    test_env.jj_cmd_success(
+        &repo1_path,
+        &["patch", "export", "-r", "root()..@-", "-o", "../patches"],
+    );
+
+    let stdout = test_env.jj_cmd_success(
+        &repo2_path,
+        &[
+            "patch",
+            "apply",
+            "-r",
+            "root()",
+            "../patches/0001-first.patch",
+            "../patches/0002-second.patch",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    Applied qpvuntsm a58954f9 first
+    Applied kkmpptxz bcbf4206 second
+    Working copy now at: xotyzkmz 17f46c9c (empty) (no description set)
+    Parent commit      : kkmpptxz bcbf4206 second
+    Added 2 files, modified 0 files, removed 0 files
+    "###);
+
+    // The descriptions, authors, change ids and contents are preserved
+    let template = r#"change_id ++ " " ++ author ++ " " ++ description"#;
+    let log1 = test_env.jj_cmd_success(&repo1_path, &["log", "-r", "root()..@-", "-T", template]);
+    let log2 = test_env.jj_cmd_success(&repo2_path, &["log", "-r", "root()..@-", "-T", template]);
+    assert_eq!(log1, log2);
+    insta::assert_snapshot!(log2, @r###"
+    ◉  kkmpptxzrspxrzommnulwmwkkqwworpl Test User <test.user@example.com> second
+    │
+    │  With a body
+    ◉  qpvuntsmwlqtpsluzzsnyyzlmlwvmlnu Test User <test.user@example.com> first
+    │
+    ~
+    "###);
+    let diff1 = test_env.jj_cmd_success(
+        &repo1_path,
+        &["diff", "--git", "--from", "root()", "--to", "@-"],
+    );
+    let diff2 = test_env.jj_cmd_success(
+        &repo2_path,
+        &["diff", "--git", "--from", "root()", "--to", "@-"],
+    );
+    assert_eq!(diff1, diff2);
+}
+
+#[test]
+fn test_patch_apply_plain_diff() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file"), "x\na\nb\nc\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["commit", "-m", "add file"]);
+
+    // A diff without headers is read from stdin, and hunks can apply at an
+    // offset
+    let patch = "\
+--- file.orig\t2023-01-01 00:00:00
++++ file\t2023-01-01 00:00:00
+@@ -1,3 +1,3 @@
+ a
+-b
++B
+ c
+";
+    let assert = test_env
+        .jj_cmd(&repo_path, &["patch", "apply", "-"])
+        .write_stdin(patch)
+        .assert()
+        .success();
+    insta::assert_snapshot!(get_stdout_string(&assert), @r###"
+    Applied kkmpptxz e565956b (no description set)
+    Working copy now at: pmmvwywv d611e758 (empty) (no description set)
+    Parent commit      : kkmpptxz e565956b (no description set)
+    Added 0 files, modified 1 files, removed 0 files
+    "###);
+    insta::assert_snapshot!(get_stderr_string(&assert), @"");
+    let stdout = test_env.jj_cmd_success(&repo_path, &["cat", "-r", "@-", "file"]);
+    insta::assert_snapshot!(stdout, @r###"
+    x
+    a
+    B
+    c
+    "###);
+}
+
+#[test]
+fn test_patch_apply_conflict() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file"), "a\nb\nc\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["commit", "-m", "add file"]);
+
+    // A hunk that doesn't apply results in a conflict
+    let patch = "\
+diff --git a/file b/file
+--- a/file
++++ b/file
+@@ -1,3 +1,3 @@
+ a
+-x
++y
+ c
+diff --git a/missing b/missing
+--- a/missing
++++ b/missing
+@@ -1 +1 @@
+-old
++new
+";
+    std::fs::write(test_env.env_root().join("conflict.patch"), patch).unwrap();
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["patch", "apply", "../conflict.patch"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Applied kkmpptxz 784f77f1 (conflict) (no description set)
+    Working copy now at: pmmvwywv 3017e0dd (conflict) (empty) (no description set)
+    Parent commit      : kkmpptxz 784f77f1 (conflict) (no description set)
+    Added 1 files, modified 1 files, removed 0 files
+    "###);
+    insta::assert_snapshot!(stderr, @r###"
+    The patch did not apply cleanly. Resolve the conflicts with `jj resolve`.
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["resolve", "--list", "-r", "@-"]);
+    insta::assert_snapshot!(stdout, @r###"
+    file       2-sided conflict
+    missing    2-sided conflict including 1 deletion
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["cat", "-r", "@-", "file"]);
+    insta::assert_snapshot!(stdout, @r###"
+    a
+    <<<<<<<
+    %%%%%%%
+    -x
+    +b
+    +++++++
+    y
+    >>>>>>>
+    c
+    "###);
+
+    // Invalid patches are rejected
+    std::fs::write(
+        test_env.env_root().join("invalid.patch"),
+        "--- a/file\n+++ b/file\n@@ -1,2 +1,2 @@\n-a\n",
+    )
+    .unwrap();
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["patch", "apply", "../invalid.patch"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Failed to parse patch: Unexpected end of hunk in file
+    "###);
+}
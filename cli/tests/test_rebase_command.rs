@@ -521,6 +521,43 @@ fn test_rebase_with_descendants() {
     "###);
 }
 
+#[test]
+fn test_rebase_over_rename() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file"), "a\nb\nc\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["commit", "-m", "base"]);
+    std::fs::write(repo_path.join("file"), "a\nB\nc\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["describe", "-m", "modify"]);
+    test_env.jj_cmd_success(&repo_path, &["new", "description(base)", "-m", "rename"]);
+    std::fs::rename(repo_path.join("file"), repo_path.join("renamed")).unwrap();
+    std::fs::write(repo_path.join("renamed"), "a\nb\nc\nd\n").unwrap();
+
+    // The modification is applied to the renamed file
+    test_env.jj_cmd_success(
+        &repo_path,
+        &["rebase", "-r", "description(modify)", "-d", "@"],
+    );
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-r", "description(modify)"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Modified regular file renamed:
+       1    1: a
+       2    2: bB
+       3    3: c
+       4    4: d
+    "###);
+    let stdout =
+        test_env.jj_cmd_success(&repo_path, &["cat", "-r", "description(modify)", "renamed"]);
+    insta::assert_snapshot!(stdout, @r###"
+    a
+    B
+    c
+    d
+    "###);
+}
+
 fn get_log_output(test_env: &TestEnvironment, repo_path: &Path) -> String {
     test_env.jj_cmd_success(repo_path, &["log", "-T", "branches"])
 }
@@ -728,3 +728,81 @@ fn test_multiple_conflicts() {
     Error: No conflicts found at this revision
     "###);
 }
+
+#[test]
+fn test_conflict_marker_style() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.add_config(r#"ui.conflict-marker-style = "diff3""#);
+
+    create_commit(&test_env, &repo_path, "base", &[], &[("file", "a\nb\nc\n")]);
+    create_commit(
+        &test_env,
+        &repo_path,
+        "a",
+        &["base"],
+        &[("file", "a\nb1\nc\n")],
+    );
+    create_commit(
+        &test_env,
+        &repo_path,
+        "b",
+        &["base"],
+        &[("file", "a\nb2\nc\n")],
+    );
+    create_commit(&test_env, &repo_path, "conflict", &["a", "b"], &[]);
+    insta::assert_snapshot!(std::fs::read_to_string(repo_path.join("file")).unwrap(), @r###"
+    a
+    <<<<<<< Side #1
+    b1
+    ||||||| Base
+    b
+    =======
+    b2
+    >>>>>>> Side #2
+    c
+    "###);
+    insta::assert_snapshot!(
+        test_env.jj_cmd_success(&repo_path, &["cat", "file"]), @r###"
+    a
+    <<<<<<< Side #1
+    b1
+    ||||||| Base
+    b
+    =======
+    b2
+    >>>>>>> Side #2
+    c
+    "###);
+
+    // Editing one side of the conflict updates the conflict
+    std::fs::write(
+        repo_path.join("file"),
+        "a\n<<<<<<< Side #1\nb1 edited\n||||||| Base\nb\n=======\nb2\n>>>>>>> Side #2\nc\n",
+    )
+    .unwrap();
+    insta::assert_snapshot!(test_env.jj_cmd_success(&repo_path, &["diff", "--git"]), @r###"
+    diff --git a/file b/file
+    --- a/file
+    +++ b/file
+    @@ -1,9 +1,9 @@
+     a
+     <<<<<<< Side #1
+    -b1
+    +b1 edited
+     ||||||| Base
+     b
+     =======
+     b2
+     >>>>>>> Side #2
+     c
+    "###);
+
+    // Resolving the conflict with the markers removed
+    std::fs::write(repo_path.join("file"), "a\nb3\nc\n").unwrap();
+    insta::assert_snapshot!(
+        test_env.jj_cmd_cli_error(&repo_path, &["resolve", "--list"]), @r###"
+        Error: No conflicts found at this revision
+        "###);
+}
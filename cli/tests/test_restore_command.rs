@@ -85,7 +85,7 @@ fn test_restore() {
     "###);
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
     insta::assert_snapshot!(stdout, @r###"
-    R file2
+    D file2
     "###);
 
     // Can restore into other revision
@@ -101,7 +101,7 @@ fn test_restore() {
     insta::assert_snapshot!(stdout, @"");
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s", "-r", "@-"]);
     insta::assert_snapshot!(stdout, @r###"
-    R file1
+    D file1
     A file2
     A file3
     "###);
@@ -119,7 +119,7 @@ fn test_restore() {
     insta::assert_snapshot!(stdout, @"");
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s", "-r", "@-"]);
     insta::assert_snapshot!(stdout, @r###"
-    R file1
+    D file1
     A file2
     A file3
     "###);
@@ -135,7 +135,60 @@ fn test_restore() {
     "###);
     let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s"]);
     insta::assert_snapshot!(stdout, @r###"
-    R file1
+    D file1
+    "###);
+}
+
+#[test]
+fn test_restore_hunks() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file1"), "a\nb\nc\nd\ne\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["new"]);
+    std::fs::write(repo_path.join("file1"), "A\nb\nc\nd\nE\n").unwrap();
+    std::fs::write(repo_path.join("file2"), "new\n").unwrap();
+
+    // The selected hunks are undone
+    let stdout = test_env.jj_cmd_success(&repo_path, &["restore", "--hunks", "file1:2; file2:*"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Created rlvkpnrz ace197fa (no description set)
+    Working copy now at: rlvkpnrz ace197fa (no description set)
+    Parent commit      : qpvuntsm 6e631cf6 (no description set)
+    Added 0 files, modified 1 files, removed 1 files
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file1 b/file1
+    index 9405325339...5909b8479d 100644
+    --- a/file1
+    +++ b/file1
+    @@ -1,5 +1,5 @@
+    -a
+    +A
+     b
+     c
+     d
+     e
+    "###);
+
+    // The hunks are numbered in the diff from --from to --to
+    test_env.jj_cmd_success(&repo_path, &["undo"]);
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &["restore", "--from", "@", "--to", "@-", "--hunks", "file2:1"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    Created qpvuntsm 65d403f5 (no description set)
+    Rebased 1 descendant commits
+    Working copy now at: rlvkpnrz 8191896a (no description set)
+    Parent commit      : qpvuntsm 65d403f5 (no description set)
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s", "-r", "@-"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A file1
+    A file2
     "###);
 }
 
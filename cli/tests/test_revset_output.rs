@@ -241,7 +241,7 @@ fn test_function_name_hint() {
       | ^-----^
       |
       = Revset function "author_" doesn't exist
-    Hint: Did you mean "author", "my_author"?
+    Hint: Did you mean "author", "author_date", "my_author"?
     "###);
 }
 
@@ -410,3 +410,114 @@ fn test_bad_alias_decl() {
       = Redefinition of function parameter
     "###);
 }
+
+#[test]
+fn test_date_pattern() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_success(&repo_path, &["describe", "-m=old"]);
+    test_env.jj_cmd_success(&repo_path, &["new", "-m=new"]);
+
+    // Relative dates are resolved against the (pinned) current time
+    let template = r#"description.first_line() ++ " " ++ committer.timestamp() ++ "\n""#;
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "log",
+            "--no-graph",
+            "-T",
+            template,
+            "-r",
+            r#"committer_date(after:"1 second ago")"#,
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    new 2001-02-03 04:05:09.000 +07:00
+    "###);
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "log",
+            "--no-graph",
+            "-T",
+            template,
+            "-r",
+            r#"author_date(after:"2001-02-03", before:"2001-02-03 04:05:09 +07:00")"#,
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    old 2001-02-03 04:05:08.000 +07:00
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &[
+            "log",
+            "-r",
+            r#"author_date(after:"the day before yesterday")"#,
+        ],
+    );
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Failed to parse revset:  --> 1:13
+      |
+    1 | author_date(after:"the day before yesterday")
+      |             ^------------------------------^
+      |
+      = Invalid arguments to revset function "author_date": Invalid date "the day before yesterday"
+    "###);
+}
+
+#[test]
+fn test_at_operation() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_success(&repo_path, &["describe", "-m=a"]);
+    test_env.jj_cmd_success(&repo_path, &["branch", "create", "main"]);
+    test_env.jj_cmd_success(&repo_path, &["new", "-m=b"]);
+    test_env.jj_cmd_success(&repo_path, &["new", "-m=c"]);
+    test_env.jj_cmd_success(&repo_path, &["branch", "set", "main", "-r=@"]);
+
+    let template = r#"description.first_line() ++ "\n""#;
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "log",
+            "--no-graph",
+            "-T",
+            template,
+            "-r",
+            "at_operation(@-, main)..main",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    c
+    b
+    "###);
+    // `all()` is resolved at the operation, before "b" and "c" were created
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "log",
+            "--no-graph",
+            "-T",
+            template,
+            "-r",
+            "at_operation(@---, all()) ~ root()",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    a
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "-r", "at_operation(zzz, main)"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Cannot resolve operation "zzz": Operation ID "zzz" is not a valid hexadecimal prefix
+    "###);
+    let stderr =
+        test_env.jj_cmd_failure(&repo_path, &["log", "-r", "at_operation(@, nonexistent)"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Revision "nonexistent" doesn't exist
+    "###);
+}
@@ -0,0 +1,175 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+fn create_commit(
+    test_env: &TestEnvironment,
+    repo_path: &Path,
+    name: &str,
+    parents: &[&str],
+    files: &[(&str, &str)],
+) {
+    if parents.is_empty() {
+        test_env.jj_cmd_success(repo_path, &["new", "root()", "-m", name]);
+    } else {
+        let mut args = vec!["new", "-m", name];
+        args.extend(parents);
+        test_env.jj_cmd_success(repo_path, &args);
+    }
+    for (name, content) in files {
+        std::fs::write(repo_path.join(name), content).unwrap();
+    }
+    test_env.jj_cmd_success(repo_path, &["branch", "create", name]);
+}
+
+fn get_log_output(test_env: &TestEnvironment, repo_path: &Path) -> String {
+    test_env.jj_cmd_success(repo_path, &["log", "-T", "branches"])
+}
+
+fn fake_editor_command(file: &str) -> String {
+    let editor_path = assert_cmd::cargo::cargo_bin("fake-editor");
+    format!("{} {file}", editor_path.to_str().unwrap())
+}
+
+#[test]
+fn test_run_rewrites_selected_commits() {
+    let mut test_env = TestEnvironment::default();
+    let edit_script = test_env.set_up_fake_editor();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    create_commit(&test_env, &repo_path, "a", &[], &[("file", "a\n")]);
+    create_commit(&test_env, &repo_path, "b", &["a"], &[("other", "b\n")]);
+    create_commit(&test_env, &repo_path, "c", &["b"], &[("other", "c\n")]);
+
+    std::fs::write(&edit_script, "write\nformatted\n").unwrap();
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &["run", &fake_editor_command("file"), "-r", "a"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    Command changed 1 of 1 commits
+      rlvkpnrz 0410053f a | a
+    Rebased 2 descendant commits
+    Working copy now at: royxmykx f114060f c | c
+    Parent commit      : zsuskuln 59d1505a b | b
+    Added 0 files, modified 1 files, removed 0 files
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  c
+    ◉  b
+    ◉  a
+    ◉
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["cat", "-r", "a", "file"]);
+    insta::assert_snapshot!(stdout, @r###"
+    formatted
+    "###);
+    // Descendants were rebased on top of the rewritten commit
+    let stdout = test_env.jj_cmd_success(&repo_path, &["cat", "-r", "c", "file"]);
+    insta::assert_snapshot!(stdout, @r###"
+    formatted
+    "###);
+}
+
+#[test]
+fn test_run_on_stack() {
+    let mut test_env = TestEnvironment::default();
+    let edit_script = test_env.set_up_fake_editor();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    create_commit(&test_env, &repo_path, "a", &[], &[("file", "a\n")]);
+    create_commit(&test_env, &repo_path, "b", &["a"], &[("file", "b\n")]);
+    create_commit(&test_env, &repo_path, "c", &["b"], &[("other", "c\n")]);
+
+    // Each commit is processed with its own contents
+    std::fs::write(&edit_script, "write\nformatted\n").unwrap();
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &["run", &fake_editor_command("file"), "-r", "a::"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    Command changed 3 of 3 commits
+      rlvkpnrz 0410053f a | a
+      zsuskuln ecba9008 b | (empty) b
+      royxmykx 7f839598 c | c
+    Working copy now at: royxmykx 7f839598 c | c
+    Parent commit      : zsuskuln ecba9008 b | (empty) b
+    Added 0 files, modified 1 files, removed 0 files
+    "###);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  c
+    ◉  b
+    ◉  a
+    ◉
+    "###);
+    for rev in ["a", "b", "c"] {
+        let stdout = test_env.jj_cmd_success(&repo_path, &["cat", "-r", rev, "file"]);
+        assert_eq!(stdout, "formatted\n");
+    }
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-r", "b", "-s"]);
+    insta::assert_snapshot!(stdout, @"");
+    // The working copy on disk was updated
+    insta::assert_snapshot!(std::fs::read_to_string(repo_path.join("file")).unwrap(), @r###"
+    formatted
+    "###);
+}
+
+#[test]
+fn test_run_failure_changes_nothing() {
+    let mut test_env = TestEnvironment::default();
+    let edit_script = test_env.set_up_fake_editor();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    create_commit(&test_env, &repo_path, "a", &[], &[("file", "a\n")]);
+    create_commit(&test_env, &repo_path, "b", &["a"], &[("file", "b\n")]);
+    let op_id_before = test_env.current_operation_id(&repo_path);
+
+    std::fs::write(&edit_script, "write\nformatted\0next invocation\n\0fail").unwrap();
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &["run", &fake_editor_command("file"), "-r", "a::"],
+    );
+    assert!(
+        stderr.starts_with("Error: Command '") && stderr.contains("with exit code 1"),
+        "{stderr}"
+    );
+    assert_eq!(test_env.current_operation_id(&repo_path), op_id_before);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["cat", "-r", "a", "file"]);
+    insta::assert_snapshot!(stdout, @r###"
+    a
+    "###);
+    insta::assert_snapshot!(std::fs::read_to_string(repo_path.join("file")).unwrap(), @r###"
+    b
+    "###);
+}
+
+#[test]
+fn test_run_root_commit() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["run", "true", "-r", "root()"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Cannot rewrite the root commit
+    "###);
+}
@@ -169,3 +169,128 @@ fn test_sparse_manage_patterns() {
     file3
     "###);
 }
+
+#[test]
+fn test_sparse_globs() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    for path in [
+        "README",
+        "services/foo/src/main.rs",
+        "services/foo/src/testdata/input",
+        "services/foo/Cargo.toml",
+        "services/bar/src/main.rs",
+        "lib/src/lib.rs",
+    ] {
+        let path = repo_path.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "contents").unwrap();
+    }
+
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "sparse",
+            "set",
+            "--clear",
+            "--add",
+            "README",
+            "--add",
+            "services/*/src",
+            "--add",
+            "!**/testdata",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    Added 0 files, modified 0 files, removed 3 files
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["sparse", "list"]);
+    insta::assert_snapshot!(stdout, @r###"
+    README
+    services/*/src
+    !**/testdata
+    "###);
+    assert!(repo_path.join("services/foo/src/main.rs").exists());
+    assert!(repo_path.join("services/bar/src/main.rs").exists());
+    assert!(!repo_path.join("services/foo/src/testdata/input").exists());
+    assert!(!repo_path.join("services/foo/Cargo.toml").exists());
+    assert!(!repo_path.join("lib/src/lib.rs").exists());
+
+    // Patterns are relative to the current directory
+    let stdout = test_env.jj_cmd_success(&repo_path.join("services"), &["sparse", "list"]);
+    insta::assert_snapshot!(stdout, @r###"
+    ../README
+    */src
+    !../**/testdata
+    "###);
+    let stdout = test_env.jj_cmd_success(
+        &repo_path.join("services"),
+        &["sparse", "set", "--remove", "!../**/testdata"],
+    );
+    insta::assert_snapshot!(stdout, @r###"
+    Added 1 files, modified 0 files, removed 0 files
+    "###);
+    assert!(repo_path.join("services/foo/src/testdata/input").exists());
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["sparse", "set", "--add", "src/["]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Failed to parse sparse pattern: Failed to compile glob: Pattern syntax error near position 0: invalid range pattern
+    "###);
+
+    // Can reset back to all files
+    let stdout = test_env.jj_cmd_success(&repo_path, &["sparse", "reset"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Added 2 files, modified 0 files, removed 0 files
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["sparse", "list"]);
+    insta::assert_snapshot!(stdout, @r###"
+    .
+    "###);
+}
+
+#[test]
+fn test_sparse_edit() {
+    let mut test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    let edit_script = test_env.set_up_fake_editor();
+
+    std::fs::create_dir_all(repo_path.join("dir/testdata")).unwrap();
+    std::fs::write(repo_path.join("file1"), "contents").unwrap();
+    std::fs::write(repo_path.join("dir/file2"), "contents").unwrap();
+    std::fs::write(repo_path.join("dir/testdata/file3"), "contents").unwrap();
+
+    std::fs::write(
+        &edit_script,
+        "dump patterns0\0write\ndir\n!**/testdata\n\nJJ: ignored\n",
+    )
+    .unwrap();
+    let stdout = test_env.jj_cmd_success(&repo_path, &["sparse", "edit"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Added 0 files, modified 0 files, removed 2 files
+    "###);
+    insta::assert_snapshot!(
+        std::fs::read_to_string(test_env.env_root().join("patterns0")).unwrap(), @r###"
+    .
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["sparse", "list"]);
+    insta::assert_snapshot!(stdout, @r###"
+    dir
+    !**/testdata
+    "###);
+    assert!(!repo_path.join("file1").exists());
+    assert!(repo_path.join("dir/file2").exists());
+    assert!(!repo_path.join("dir/testdata/file3").exists());
+
+    // The editor starts with the current patterns
+    std::fs::write(&edit_script, "dump patterns1").unwrap();
+    let stdout = test_env.jj_cmd_success(&repo_path, &["sparse", "edit"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(
+        std::fs::read_to_string(test_env.env_root().join("patterns1")).unwrap(), @r###"
+    dir
+    !**/testdata
+    "###);
+}
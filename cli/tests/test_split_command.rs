@@ -14,7 +14,7 @@
 
 use std::path::Path;
 
-use crate::common::TestEnvironment;
+use crate::common::{get_stderr_string, get_stdout_string, TestEnvironment};
 
 pub mod common;
 
@@ -217,6 +217,102 @@ JJ: Lines starting with "JJ: " (like this one) will be removed.
     "###);
 }
 
+#[test]
+fn test_split_by_hunks() {
+    let mut test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file1"), "a\nb\nc\nd\ne\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["new"]);
+    std::fs::write(repo_path.join("file1"), "A\nb\nc\nd\nE\n").unwrap();
+    std::fs::write(repo_path.join("file2"), "new\n").unwrap();
+    test_env.set_up_fake_editor();
+
+    // Hunks are numbered per file
+    let stdout = test_env.jj_cmd_success(&repo_path, &["split", "--hunks", "file1:2; file2:*"]);
+    insta::assert_snapshot!(stdout, @r###"
+    First part: rlvkpnrz 1d217485 (no description set)
+    Second part: kkmpptxz 7493328b (no description set)
+    Working copy now at: kkmpptxz 7493328b (no description set)
+    Parent commit      : rlvkpnrz 1d217485 (no description set)
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git", "-r", "@-"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file1 b/file1
+    index 9405325339...c2f2e5e7ad 100644
+    --- a/file1
+    +++ b/file1
+    @@ -2,4 +2,4 @@
+     b
+     c
+     d
+    -e
+    +E
+    diff --git a/file2 b/file2
+    new file mode 100644
+    index 0000000000..3e757656cf
+    --- /dev/null
+    +++ b/file2
+    @@ -1,0 +1,1 @@
+    +new
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file1 b/file1
+    index c2f2e5e7ad...084d8ddca4 100644
+    --- a/file1
+    +++ b/file1
+    @@ -1,5 +1,5 @@
+    -a
+    +A
+     b
+     c
+     d
+     E
+    "###);
+
+    // Hunks can be selected by a patch read from stdin
+    test_env.jj_cmd_success(&repo_path, &["undo"]);
+    let patch = test_env.jj_cmd_success(&repo_path, &["diff", "--git", "file2"]);
+    let assert = test_env
+        .jj_cmd(&repo_path, &["split", "--hunks", "-"])
+        .write_stdin(patch)
+        .assert()
+        .success();
+    insta::assert_snapshot!(get_stdout_string(&assert), @r###"
+    First part: rlvkpnrz fbd367c8 (no description set)
+    Second part: vruxwmqv d346d5fa (no description set)
+    Working copy now at: vruxwmqv d346d5fa (no description set)
+    Parent commit      : rlvkpnrz fbd367c8 (no description set)
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "-s", "-r", "@-"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A file2
+    "###);
+
+    // Errors
+    test_env.jj_cmd_success(&repo_path, &["undo"]);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["split", "--hunks", "file1:3"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Failed to edit diff: Hunk 3 doesn't exist in file1, which has 2 hunk(s)
+    "###);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["split", "--hunks", "file3:*"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Failed to edit diff: No changes to select in file3
+    "###);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["split", "--hunks", "file1"]);
+    insta::assert_snapshot!(stderr, @r#"Error: Invalid hunk selection "file1": expected `<path>:<hunks>`"#);
+    let assert = test_env
+        .jj_cmd(&repo_path, &["split", "--hunks", "-"])
+        .write_stdin("--- a/file1\n+++ b/file1\n@@ -1 +1 @@\n-a\n+x\n")
+        .assert()
+        .code(1);
+    insta::assert_snapshot!(get_stderr_string(&assert), @r###"
+    Error: Failed to edit diff: The patch doesn't match the changes in file1
+    "###);
+}
+
 fn get_log_output(test_env: &TestEnvironment, cwd: &Path) -> String {
     let template = r#"separate(" ", change_id.short(), empty, description)"#;
     test_env.jj_cmd_success(cwd, &["log", "-T", template])
@@ -259,6 +259,54 @@ fn test_squash_partial() {
     "###);
 }
 
+#[test]
+fn test_squash_hunks() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file1"), "a\nb\nc\nd\ne\n").unwrap();
+    test_env.jj_cmd_success(&repo_path, &["new"]);
+    std::fs::write(repo_path.join("file1"), "A\nb\nc\nd\nE\n").unwrap();
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["squash", "--hunks", "file1:1"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Working copy now at: rlvkpnrz a90de866 (no description set)
+    Parent commit      : qpvuntsm 06dd1e10 (no description set)
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["print", "file1", "-r", "@-"]);
+    insta::assert_snapshot!(stdout, @r###"
+    A
+    b
+    c
+    d
+    e
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--git"]);
+    insta::assert_snapshot!(stdout, @r###"
+    diff --git a/file1 b/file1
+    index 5909b8479d...084d8ddca4 100644
+    --- a/file1
+    +++ b/file1
+    @@ -2,4 +2,4 @@
+     b
+     c
+     d
+    -e
+    +E
+    "###);
+
+    // Can't be combined with the diff editor
+    let stderr = test_env.jj_cmd_cli_error(&repo_path, &["squash", "-i", "--hunks", "file1:*"]);
+    insta::assert_snapshot!(stderr, @r###"
+    error: the argument '--interactive' cannot be used with '--hunks <HUNKS>'
+
+    Usage: jj squash --interactive [PATHS]...
+
+    For more information, try '--help'.
+    "###);
+}
+
 fn get_log_output(test_env: &TestEnvironment, repo_path: &Path) -> String {
     let template = r#"commit_id.short() ++ " " ++ branches"#;
     test_env.jj_cmd_success(repo_path, &["log", "-T", template])
@@ -0,0 +1,123 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use crate::common::TestEnvironment;
+
+pub mod common;
+
+#[test]
+fn test_tag_create_set_delete() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    test_env.jj_cmd_success(&repo_path, &["describe", "-m", "a"]);
+    test_env.jj_cmd_success(&repo_path, &["new", "-m", "b"]);
+    test_env.jj_cmd_success(&repo_path, &["tag", "create", "v1", "-r", "@-"]);
+    test_env.jj_cmd_success(&repo_path, &["tag", "create", "v2"]);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  v2 b
+    ◉  v1 a
+    ◉
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["tag", "list"]);
+    insta::assert_snapshot!(stdout, @r###"
+    v1: qpvuntsm 4c5b3042 (empty) a
+    v2: kkmpptxz 5f2e17b3 (empty) b
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["tag", "create", "v1"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Tag already exists: v1
+    Hint: Use `jj tag set` to update it.
+    "###);
+
+    test_env.jj_cmd_success(&repo_path, &["tag", "set", "v1", "-r", "@"]);
+    insta::assert_snapshot!(get_log_output(&test_env, &repo_path), @r###"
+    @  v1 v2 b
+    ◉  a
+    ◉
+    "###);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["tag", "delete", "v3"]);
+    insta::assert_snapshot!(stderr, @"Error: No such tag: v3");
+    let stdout = test_env.jj_cmd_success(&repo_path, &["tag", "delete", "glob:v*"]);
+    insta::assert_snapshot!(stdout, @"Deleted 2 tags.");
+    let stdout = test_env.jj_cmd_success(&repo_path, &["tag", "list"]);
+    insta::assert_snapshot!(stdout, @"");
+}
+
+#[test]
+fn test_tag_export() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_success(&repo_path, &["describe", "-m", "a"]);
+    test_env.jj_cmd_success(&repo_path, &["tag", "create", "light"]);
+    test_env.jj_cmd_success(
+        &repo_path,
+        &["tag", "create", "annotated", "-m", "Release notes"],
+    );
+    test_env.jj_cmd_success(&repo_path, &["git", "export"]);
+
+    let git_repo = git2::Repository::open(repo_path.join(".jj/repo/store/git")).unwrap();
+    let light = git_repo.find_reference("refs/tags/light").unwrap();
+    assert!(light.peel_to_tag().is_err());
+    let annotated = git_repo
+        .find_reference("refs/tags/annotated")
+        .unwrap()
+        .peel_to_tag()
+        .unwrap();
+    assert_eq!(annotated.message(), Some("Release notes\n"));
+    assert_eq!(annotated.target_id(), light.target().unwrap());
+    insta::assert_snapshot!(annotated.tagger().unwrap().to_string(), @"Test User <test.user@example.com>");
+
+    // Moving an annotated tag needs a new message
+    test_env.jj_cmd_success(&repo_path, &["new"]);
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["tag", "set", "annotated"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Tag annotated is annotated
+    Hint: Pass --message to move it as an annotated tag, or delete it first.
+    "###);
+    test_env.jj_cmd_success(&repo_path, &["tag", "set", "annotated", "-m", "Fixed"]);
+    test_env.jj_cmd_success(&repo_path, &["git", "export"]);
+    let annotated = git_repo
+        .find_reference("refs/tags/annotated")
+        .unwrap()
+        .peel_to_tag()
+        .unwrap();
+    assert_eq!(annotated.message(), Some("Fixed\n"));
+    assert_ne!(annotated.target_id(), light.target().unwrap());
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["tag", "create", "bad..name"]);
+    insta::assert_snapshot!(stderr, @"Error: Invalid tag name: bad..name");
+
+    test_env.jj_cmd_success(&repo_path, &["tag", "delete", "light"]);
+    test_env.jj_cmd_success(&repo_path, &["git", "export"]);
+    assert!(git_repo.find_reference("refs/tags/light").is_err());
+
+    // Annotated tags can't point to the root commit
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &["tag", "create", "root", "-r", "root()", "-m", "message"],
+    );
+    insta::assert_snapshot!(stderr, @"Error: Cannot create an annotated tag of the root commit");
+}
+
+fn get_log_output(test_env: &TestEnvironment, repo_path: &Path) -> String {
+    let template = r#"separate(" ", tags, description.first_line())"#;
+    test_env.jj_cmd_success(repo_path, &["log", "-r", "all()", "-T", template])
+}
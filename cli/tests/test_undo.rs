@@ -56,6 +56,8 @@ fn test_git_push_undo() {
     test_env.jj_cmd_success(test_env.env_root(), &["git", "clone", "git-repo", "repo"]);
     let repo_path = test_env.env_root().join("repo");
 
+    // This test rewrites `main` after pushing it
+    test_env.add_config(r#"revset-aliases."immutable_heads()" = "none()""#);
     test_env.advance_test_rng_seed_to_multiple_of(100_000);
     test_env.jj_cmd_success(&repo_path, &["branch", "create", "main"]);
     test_env.jj_cmd_success(&repo_path, &["describe", "-m", "AA"]);
@@ -127,6 +129,8 @@ fn test_git_push_undo_with_import() {
     test_env.jj_cmd_success(test_env.env_root(), &["git", "clone", "git-repo", "repo"]);
     let repo_path = test_env.env_root().join("repo");
 
+    // This test rewrites `main` after pushing it
+    test_env.add_config(r#"revset-aliases."immutable_heads()" = "none()""#);
     test_env.advance_test_rng_seed_to_multiple_of(100_000);
     test_env.jj_cmd_success(&repo_path, &["branch", "create", "main"]);
     test_env.jj_cmd_success(&repo_path, &["describe", "-m", "AA"]);
@@ -204,6 +208,8 @@ fn test_git_push_undo_colocated() {
     git2::Repository::clone(git_repo_path.to_str().unwrap(), &repo_path).unwrap();
     test_env.jj_cmd_success(&repo_path, &["init", "--git-repo=."]);
 
+    // This test rewrites `main` after pushing it
+    test_env.add_config(r#"revset-aliases."immutable_heads()" = "none()""#);
     test_env.advance_test_rng_seed_to_multiple_of(100_000);
     test_env.jj_cmd_success(&repo_path, &["branch", "create", "main"]);
     test_env.jj_cmd_success(&repo_path, &["describe", "-m", "AA"]);
@@ -279,6 +285,8 @@ fn test_git_push_undo_repo_only() {
     test_env.jj_cmd_success(test_env.env_root(), &["git", "clone", "git-repo", "repo"]);
     let repo_path = test_env.env_root().join("repo");
 
+    // This test rewrites `main` after pushing it
+    test_env.add_config(r#"revset-aliases."immutable_heads()" = "none()""#);
     test_env.advance_test_rng_seed_to_multiple_of(100_000);
     test_env.jj_cmd_success(&repo_path, &["branch", "create", "main"]);
     test_env.jj_cmd_success(&repo_path, &["describe", "-m", "AA"]);
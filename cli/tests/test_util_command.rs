@@ -37,3 +37,142 @@ fn test_util_config_schema() {
         "###)
     });
 }
+
+#[test]
+fn test_gc_args() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["util", "gc"]);
+    insta::assert_snapshot!(stdout, @"");
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["util", "gc", "--at-op=@-"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Cannot garbage collect from a non-head operation
+    "###);
+
+    let stderr = test_env.jj_cmd_cli_error(&repo_path, &["util", "gc", "--expire=foobar"]);
+    insta::assert_snapshot!(stderr, @r###"
+    error: invalid value 'foobar' for '--expire <EXPIRE>': expected a number followed by a unit
+
+    For more information, try '--help'.
+    "###);
+}
+
+#[test]
+fn test_gc_operation_log() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    let git_repo = git2::Repository::open(repo_path.join(".jj/repo/store/git")).unwrap();
+    let count_keep_refs = || git_repo.references_glob("refs/jj/keep/*").unwrap().count();
+
+    // Create a chain of commits, each of which gets its own no-gc ref
+    test_env.jj_cmd_success(&repo_path, &["new"]);
+    test_env.jj_cmd_success(&repo_path, &["new"]);
+    let predecessor_id =
+        test_env.jj_cmd_success(&repo_path, &["log", "-r@", "-T=commit_id", "--no-graph"]);
+    test_env.jj_cmd_success(&repo_path, &["describe", "-m", "head"]);
+    // Commit which is only referenced by old operations
+    test_env.jj_cmd_success(&repo_path, &["new", "-m", "abandoned"]);
+    let abandoned_id =
+        test_env.jj_cmd_success(&repo_path, &["log", "-r@", "-T=commit_id", "--no-graph"]);
+    test_env.jj_cmd_success(&repo_path, &["abandon"]);
+    let has_keep_ref = |commit_id: &str| {
+        git_repo
+            .references_glob("refs/jj/keep/*")
+            .unwrap()
+            .any(|git_ref| git_ref.unwrap().target().unwrap().to_string() == commit_id)
+    };
+    assert!(has_keep_ref(&abandoned_id));
+    assert!(has_keep_ref(&predecessor_id));
+
+    test_env.jj_cmd_success(&repo_path, &["util", "gc", "--expire=now"]);
+    // The operation log is truncated, so only the heads referenced by the
+    // current operation are kept
+    assert!(!has_keep_ref(&abandoned_id));
+    assert!(!has_keep_ref(&predecessor_id));
+    assert_eq!(count_keep_refs(), 1);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["op", "log", "-T", "description"]);
+    insta::assert_snapshot!(stdout, @"@  abandon commit 9e83d2f159b5cb8e71edde85220af3e0c8be4ff3");
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "-r", "all()", "-T", "description"]);
+    insta::assert_snapshot!(stdout, @r###"
+    @
+    ◉  head
+    ◉
+    ◉
+    ◉
+    "###);
+    // Predecessors removed from the backing Git repo are skipped
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &["obslog", "-r", "description(head)", "-T", "description"],
+    );
+    insta::assert_snapshot!(stdout, @"◉  head");
+}
+
+#[test]
+fn test_gc_multiple_workspaces() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_success(&repo_path, &["workspace", "add", "../secondary"]);
+
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["util", "gc"]);
+    insta::assert_snapshot!(stderr, @r###"
+    Error: Cannot garbage collect a repo with multiple workspaces
+    Hint: Other workspaces may point to operations that would be removed. Forget them with `jj workspace forget` first.
+    "###);
+
+    test_env.jj_cmd_success(&repo_path, &["workspace", "forget", "secondary"]);
+    test_env.jj_cmd_success(&repo_path, &["util", "gc"]);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_util_fsmonitor() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_success(test_env.env_root(), &["init", "repo", "--git"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.add_config(r#"core.fsmonitor = "inotify""#);
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["util", "fsmonitor", "status"]);
+    insta::assert_snapshot!(stdout, @r###"
+    The filesystem monitor is not running
+    "###);
+
+    insta::with_settings!({filters => vec![(r"pid \d+", "pid [PID]")]}, {
+        let stdout = test_env.jj_cmd_success(&repo_path, &["util", "fsmonitor", "start"]);
+        insta::assert_snapshot!(stdout, @r###"
+        Started the filesystem monitor (pid [PID])
+        "###);
+        let stdout = test_env.jj_cmd_success(&repo_path, &["util", "fsmonitor", "start"]);
+        insta::assert_snapshot!(stdout, @r###"
+        The filesystem monitor is already running (pid [PID])
+        "###);
+        let stdout = test_env.jj_cmd_success(&repo_path, &["util", "fsmonitor", "status"]);
+        insta::assert_snapshot!(stdout, @r###"
+        The filesystem monitor is running (pid [PID], watching 1 directories)
+        "###);
+    });
+
+    std::fs::create_dir(repo_path.join("dir")).unwrap();
+    std::fs::write(repo_path.join("dir").join("file"), "contents\n").unwrap();
+    let stdout = test_env.jj_cmd_success(&repo_path, &["status"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Working copy changes:
+    A dir/file
+    Working copy : qpvuntsm edd1ae28 (no description set)
+    Parent commit: zzzzzzzz 00000000 (empty) (no description set)
+    "###);
+
+    let stdout = test_env.jj_cmd_success(&repo_path, &["util", "fsmonitor", "stop"]);
+    insta::assert_snapshot!(stdout, @r###"
+    Stopped the filesystem monitor
+    "###);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["util", "fsmonitor", "stop"]);
+    insta::assert_snapshot!(stdout, @r###"
+    The filesystem monitor is not running
+    "###);
+}
@@ -0,0 +1,390 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Moves hunks of a commit into the ancestors that last modified the lines
+//! they touch.
+
+#![allow(missing_docs)]
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Read;
+use std::ops::Range;
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::backend::{BackendError, CommitId, MergedTreeId, ObjectId, TreeValue};
+use crate::commit::Commit;
+use crate::diff::{changed_line_ranges, find_line_ranges, map_lines};
+use crate::matchers::Matcher;
+use crate::merge::Merge;
+use crate::merged_tree::MergedTreeBuilder;
+use crate::repo::{MutableRepo, Repo};
+use crate::repo_path::RepoPath;
+use crate::settings::UserSettings;
+use crate::store::Store;
+use crate::tree::TreeMergeError;
+
+#[derive(Debug, Error)]
+pub enum AbsorbError {
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+    #[error(transparent)]
+    TreeMerge(#[from] TreeMergeError),
+}
+
+/// Hunks of the source commit selected to be moved into a destination commit.
+#[derive(Clone, Debug)]
+pub struct SelectedTree {
+    /// The destination commit as it was before absorbing.
+    pub commit: Commit,
+    /// The destination's tree with the selected hunks applied.
+    pub tree_id: MergedTreeId,
+}
+
+/// Result of `absorb_hunks()`.
+#[derive(Clone, Debug, Default)]
+pub struct AbsorbStats {
+    /// The destination commits after the hunks were moved into them, in
+    /// ancestors-first order.
+    pub rewritten_destinations: Vec<Commit>,
+    /// Number of descendant commits which were rebased.
+    pub num_rebased: usize,
+    /// Whether the source commit was abandoned because it became empty.
+    pub source_abandoned: bool,
+}
+
+/// Line ranges to replace in each file of a commit, and their replacements.
+type FileEdits = BTreeMap<RepoPath, Vec<(Range<usize>, Vec<u8>)>>;
+
+/// Content of a path that absorbing can work with.
+enum FileContent {
+    Absent,
+    File { content: Vec<u8>, executable: bool },
+    Unsupported,
+}
+
+/// Finds the destination for each hunk in `source` touching a path matched by
+/// `matcher`.
+///
+/// Lines are attributed to the commit that last modified them by walking the
+/// ancestors of `source` as long as they're in `destinations` and aren't
+/// merges. A hunk is selected if all the lines it replaces were last modified
+/// by the same commit and are adjacent there. Pure additions are selected if
+/// the lines around them were last modified by the same commit. Other hunks,
+/// as well as added, removed, and non-file paths, are left in the source.
+///
+/// The returned trees are sorted with ancestors first.
+pub fn split_hunks_to_trees(
+    repo: &dyn Repo,
+    source: &Commit,
+    destinations: &HashSet<CommitId>,
+    matcher: &dyn Matcher,
+) -> Result<Vec<SelectedTree>, AbsorbError> {
+    let store = repo.store();
+    let [parent_id] = source.parent_ids() else {
+        return Ok(vec![]);
+    };
+    let mut chain = vec![];
+    let mut next_id = parent_id.clone();
+    while destinations.contains(&next_id) {
+        let commit = store.get_commit(&next_id)?;
+        match commit.parent_ids() {
+            [parent_id] => next_id = parent_id.clone(),
+            _ => break,
+        }
+        chain.push(commit);
+    }
+    let Some(parent) = chain.first() else {
+        return Ok(vec![]);
+    };
+
+    let parent_tree = parent.tree()?;
+    let source_tree = source.tree()?;
+    let mut edits: Vec<FileEdits> = vec![BTreeMap::new(); chain.len()];
+    for (path, before, after) in parent_tree.diff(&source_tree, matcher) {
+        let (
+            FileContent::File {
+                content: parent_text,
+                ..
+            },
+            FileContent::File {
+                content: source_text,
+                ..
+            },
+        ) = (
+            read_file(store, &path, before)?,
+            read_file(store, &path, after)?,
+        )
+        else {
+            continue;
+        };
+        let owners = attribute_lines(store, &chain, &path, &parent_text)?;
+        let source_lines = find_line_ranges(&source_text);
+        for (left, right) in changed_line_ranges(&parent_text, &source_text) {
+            let Some((index, range)) = find_destination(&owners, &left) else {
+                continue;
+            };
+            let replacement = if right.is_empty() {
+                vec![]
+            } else {
+                source_text[source_lines[right.start].start..source_lines[right.end - 1].end]
+                    .to_vec()
+            };
+            edits[index]
+                .entry(path.clone())
+                .or_default()
+                .push((range, replacement));
+        }
+    }
+
+    let mut selected_trees = vec![];
+    for (commit, edits) in chain.into_iter().zip(edits).rev() {
+        if edits.is_empty() {
+            continue;
+        }
+        let tree = commit.tree()?;
+        let mut tree_builder = MergedTreeBuilder::new(commit.tree_id().clone());
+        for (path, edits) in edits {
+            let FileContent::File {
+                content,
+                executable,
+            } = read_file(store, &path, tree.path_value(&path))?
+            else {
+                unreachable!("lines can only be attributed to files");
+            };
+            let new_content = apply_edits(&content, edits);
+            let id = store.write_file(&path, &mut new_content.as_slice())?;
+            tree_builder.set_or_remove(path, Merge::normal(TreeValue::File { id, executable }));
+        }
+        let tree_id = tree_builder.write_tree(store)?;
+        selected_trees.push(SelectedTree { commit, tree_id });
+    }
+    Ok(selected_trees)
+}
+
+/// Moves the selected hunks into their destination commits and rebases the
+/// descendants, including `source`. If `source` becomes empty and has no
+/// description, it's abandoned.
+pub fn absorb_hunks(
+    mut_repo: &mut MutableRepo,
+    settings: &UserSettings,
+    source: &Commit,
+    selected_trees: Vec<SelectedTree>,
+) -> Result<AbsorbStats, AbsorbError> {
+    let store = mut_repo.store().clone();
+    let mut stats = AbsorbStats::default();
+    // Commit ids change as ancestors get rewritten, so they're looked up after
+    // each rebase.
+    let mut source_id = source.id().clone();
+    let mut destination_ids = selected_trees
+        .iter()
+        .map(|selected| selected.commit.id().clone())
+        .collect::<Vec<_>>();
+    // Maps the current id of each rebased commit to its id before absorbing.
+    let mut original_ids = HashMap::new();
+    for (i, selected) in selected_trees.iter().enumerate() {
+        let destination = store.get_commit(&destination_ids[i])?;
+        let selected_tree = store.get_root_tree(&selected.tree_id)?;
+        let new_tree = destination
+            .tree()?
+            .merge(&selected.commit.tree()?, &selected_tree)?;
+        let new_commit = mut_repo
+            .rewrite_commit(settings, &destination)
+            .set_tree_id(new_tree.id())
+            .write()?;
+        stats.rewritten_destinations.push(new_commit);
+        let rebased = rebase_descendants(mut_repo, settings, &mut original_ids)?;
+        for id in destination_ids.iter_mut().chain([&mut source_id]) {
+            if let Some(new_id) = rebased.get(id) {
+                *id = new_id.clone();
+            }
+        }
+    }
+
+    if !stats.rewritten_destinations.is_empty() && store.get_commit(&source_id)?.is_discardable() {
+        mut_repo.record_abandoned_commit(source_id);
+        rebase_descendants(mut_repo, settings, &mut original_ids)?;
+        stats.source_abandoned = true;
+    }
+    let mut not_rebased: HashSet<_> = selected_trees
+        .iter()
+        .map(|selected| selected.commit.id())
+        .collect();
+    if stats.source_abandoned {
+        not_rebased.insert(source.id());
+    }
+    stats.num_rebased = original_ids
+        .values()
+        .filter(|id| !not_rebased.contains(id))
+        .collect::<HashSet<_>>()
+        .len();
+    Ok(stats)
+}
+
+/// Rebases descendants of the recorded rewrites, and returns the commits that
+/// were rebased.
+fn rebase_descendants(
+    mut_repo: &mut MutableRepo,
+    settings: &UserSettings,
+    original_ids: &mut HashMap<CommitId, CommitId>,
+) -> Result<HashMap<CommitId, CommitId>, TreeMergeError> {
+    let mut rebaser = mut_repo.create_descendant_rebaser(settings);
+    rebaser.rebase_all()?;
+    let rebased = rebaser.rebased().clone();
+    for (old_id, new_id) in &rebased {
+        let original_id = original_ids
+            .remove(old_id)
+            .unwrap_or_else(|| old_id.clone());
+        original_ids.insert(new_id.clone(), original_id);
+    }
+    Ok(rebased)
+}
+
+fn read_file(
+    store: &Store,
+    path: &RepoPath,
+    value: Merge<Option<TreeValue>>,
+) -> Result<FileContent, BackendError> {
+    match value.into_resolved() {
+        Ok(None) => Ok(FileContent::Absent),
+        Ok(Some(TreeValue::File { id, executable })) => {
+            let mut content = vec![];
+            store
+                .read_file(path, &id)?
+                .read_to_end(&mut content)
+                .map_err(|err| BackendError::ReadObject {
+                    object_type: "file".to_string(),
+                    hash: id.hex(),
+                    source: err.into(),
+                })?;
+            Ok(FileContent::File {
+                content,
+                executable,
+            })
+        }
+        Ok(Some(_)) | Err(_) => Ok(FileContent::Unsupported),
+    }
+}
+
+/// For each line in `text`, which is the content of `path` in the first
+/// commit of the `chain`, finds the index of the commit in the chain that
+/// last modified it, and the line number in that commit.
+fn attribute_lines(
+    store: &Arc<Store>,
+    chain: &[Commit],
+    path: &RepoPath,
+    text: &[u8],
+) -> Result<Vec<Option<(usize, usize)>>, AbsorbError> {
+    let num_lines = find_line_ranges(text).len();
+    let mut owners = vec![None; num_lines];
+    // Pairs of line number in `text` and in the commit being visited
+    let mut pending = (0..num_lines).map(|line| (line, line)).collect::<Vec<_>>();
+    let mut text = text.to_vec();
+    for (index, commit) in chain.iter().enumerate() {
+        if pending.is_empty() {
+            break;
+        }
+        let parent_tree = store.get_commit(&commit.parent_ids()[0])?.tree()?;
+        let parent_text = match read_file(store, path, parent_tree.path_value(path))? {
+            FileContent::Absent => vec![],
+            FileContent::File { content, .. } => content,
+            FileContent::Unsupported => break,
+        };
+        let mapping = map_lines(&text, &parent_text);
+        pending.retain_mut(|(line, current)| match mapping[*current] {
+            Some(parent_line) => {
+                *current = parent_line;
+                true
+            }
+            None => {
+                owners[*line] = Some((index, *current));
+                false
+            }
+        });
+        text = parent_text;
+    }
+    Ok(owners)
+}
+
+/// Finds the commit and line range to apply a hunk to, given the range of
+/// lines it replaces in the parent.
+fn find_destination(
+    owners: &[Option<(usize, usize)>],
+    left: &Range<usize>,
+) -> Option<(usize, Range<usize>)> {
+    if left.is_empty() {
+        let before = owners.get(left.start.checked_sub(1)?)?.as_ref()?;
+        let after = owners.get(left.start)?.as_ref()?;
+        return (before.0 == after.0 && before.1 + 1 == after.1)
+            .then_some((after.0, after.1..after.1));
+    }
+    let (index, first) = owners[left.start]?;
+    for (offset, owner) in owners[left.clone()].iter().enumerate() {
+        if *owner != Some((index, first + offset)) {
+            return None;
+        }
+    }
+    Some((index, first..first + left.len()))
+}
+
+/// Replaces the given line ranges in `text`.
+fn apply_edits(text: &[u8], mut edits: Vec<(Range<usize>, Vec<u8>)>) -> Vec<u8> {
+    edits.sort_by_key(|(range, _)| range.start);
+    let lines = find_line_ranges(text);
+    let line_start = |line: usize| lines.get(line).map_or(text.len(), |range| range.start);
+    let mut new_text = vec![];
+    let mut pos = 0;
+    for (range, replacement) in edits {
+        new_text.extend_from_slice(&text[pos..line_start(range.start)]);
+        new_text.extend_from_slice(&replacement);
+        pos = line_start(range.end);
+    }
+    new_text.extend_from_slice(&text[pos..]);
+    new_text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_destination() {
+        let owners = [Some((0, 3)), Some((0, 4)), None, Some((1, 0)), Some((0, 5))];
+        assert_eq!(find_destination(&owners, &(0..2)), Some((0, 3..5)));
+        assert_eq!(find_destination(&owners, &(1..3)), None);
+        assert_eq!(find_destination(&owners, &(3..5)), None);
+        // Insertions need both surrounding lines to be adjacent in the same
+        // commit
+        assert_eq!(find_destination(&owners, &(1..1)), Some((0, 4..4)));
+        assert_eq!(find_destination(&owners, &(0..0)), None);
+        assert_eq!(find_destination(&owners, &(4..4)), None);
+        assert_eq!(find_destination(&owners, &(5..5)), None);
+    }
+
+    #[test]
+    fn test_apply_edits() {
+        assert_eq!(
+            apply_edits(
+                b"a\nb\nc\n",
+                vec![(2..3, b"C\n".to_vec()), (0..0, b"0\n".to_vec())]
+            ),
+            b"0\na\nb\nC\n"
+        );
+        assert_eq!(
+            apply_edits(b"a\nb\nc\n", vec![(0..2, vec![]), (3..3, b"d\n".to_vec())]),
+            b"c\nd\n"
+        );
+    }
+}
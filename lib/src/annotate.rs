@@ -0,0 +1,196 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Attributes each line of a file to the commit that introduced it.
+
+#![allow(missing_docs)]
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::rc::Rc;
+
+use thiserror::Error;
+
+use crate::backend::{BackendError, CommitId, ObjectId, TreeValue};
+use crate::commit::Commit;
+use crate::conflicts::{self, ConflictMarkerStyle};
+use crate::diff::{find_line_ranges, map_lines};
+use crate::merge::Merge;
+use crate::repo::Repo;
+use crate::repo_path::RepoPath;
+use crate::revset::{self, RevsetEvaluationError};
+use crate::store::Store;
+
+#[derive(Debug, Error)]
+pub enum AnnotateError {
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+    #[error(transparent)]
+    RevsetEvaluation(#[from] RevsetEvaluationError),
+}
+
+/// A line of the annotated file.
+#[derive(Clone, Debug)]
+pub struct AnnotatedLine {
+    /// The commit that introduced the line.
+    pub commit: Commit,
+    /// The 0-based number of the line in the file as of `commit`.
+    pub original_line: usize,
+    /// The content of the line, including the newline if any.
+    pub content: Vec<u8>,
+}
+
+/// The content of a file in a commit that's being annotated, and the lines
+/// that are yet to be attributed.
+struct PendingFile {
+    value: Merge<Option<TreeValue>>,
+    content: Rc<Vec<u8>>,
+    /// Pairs of line number in the annotated file and in this content
+    lines: Vec<(usize, usize)>,
+}
+
+/// Attributes each line of the file at `path` in `starting_commit` to the
+/// commit that introduced it.
+///
+/// Ancestors are visited in reverse topological order, and the lines are
+/// carried over to the parents by diffing the file's content. A line that's
+/// present in several parents of a merge commit is attributed through the
+/// first of them. A conflicted file is annotated as its materialized content,
+/// so conflict markers are attributed to the commit that introduced the
+/// conflict. A path that isn't a file or a conflict is considered empty.
+pub fn annotate_file(
+    repo: &dyn Repo,
+    starting_commit: &Commit,
+    path: &RepoPath,
+    conflict_marker_style: ConflictMarkerStyle,
+) -> Result<Vec<AnnotatedLine>, AnnotateError> {
+    let store = repo.store();
+    let value = starting_commit.tree()?.path_value(path);
+    let content = read_content(store, path, &value, conflict_marker_style)?;
+    let line_ranges = find_line_ranges(&content);
+    let mut owners: Vec<Option<(CommitId, usize)>> = vec![None; line_ranges.len()];
+    let mut pending = HashMap::new();
+    pending.insert(
+        starting_commit.id().clone(),
+        PendingFile {
+            value,
+            content: Rc::new(content.clone()),
+            lines: (0..line_ranges.len()).map(|line| (line, line)).collect(),
+        },
+    );
+
+    let ancestors = revset::walk_revs(repo, &[starting_commit.id().clone()], &[])?;
+    for commit_id in ancestors.iter() {
+        let Some(file) = pending.remove(&commit_id) else {
+            continue;
+        };
+        let commit = store.get_commit(&commit_id)?;
+        let mut lines = file.lines;
+        for parent_id in commit.parent_ids() {
+            if lines.is_empty() {
+                break;
+            }
+            let mut parent_file = match pending.remove(parent_id) {
+                Some(parent_file) => parent_file,
+                None => {
+                    let value = store.get_commit(parent_id)?.tree()?.path_value(path);
+                    let content = if value == file.value {
+                        file.content.clone()
+                    } else {
+                        Rc::new(read_content(store, path, &value, conflict_marker_style)?)
+                    };
+                    PendingFile {
+                        value,
+                        content,
+                        lines: vec![],
+                    }
+                }
+            };
+            if parent_file.value == file.value {
+                parent_file.lines.append(&mut lines);
+            } else {
+                let mapping = map_lines(&file.content, &parent_file.content);
+                lines.retain(|&(line, current)| match mapping[current] {
+                    Some(parent_line) => {
+                        parent_file.lines.push((line, parent_line));
+                        false
+                    }
+                    None => true,
+                });
+            }
+            if !parent_file.lines.is_empty() {
+                pending.insert(parent_id.clone(), parent_file);
+            }
+        }
+        for (line, current) in lines {
+            owners[line] = Some((commit_id.clone(), current));
+        }
+        if pending.is_empty() {
+            break;
+        }
+    }
+
+    let mut commits: HashMap<CommitId, Commit> = HashMap::new();
+    let mut annotated_lines = Vec::with_capacity(line_ranges.len());
+    for (range, owner) in line_ranges.into_iter().zip(owners) {
+        let (commit_id, original_line) = owner.expect("every line should have been attributed");
+        let commit = match commits.get(&commit_id) {
+            Some(commit) => commit.clone(),
+            None => {
+                let commit = store.get_commit(&commit_id)?;
+                commits.insert(commit_id, commit.clone());
+                commit
+            }
+        };
+        annotated_lines.push(AnnotatedLine {
+            commit,
+            original_line,
+            content: content[range].to_vec(),
+        });
+    }
+    Ok(annotated_lines)
+}
+
+/// Reads the content of a file, materializing conflicts. Absent paths and
+/// non-file values are read as empty.
+fn read_content(
+    store: &Store,
+    path: &RepoPath,
+    value: &Merge<Option<TreeValue>>,
+    conflict_marker_style: ConflictMarkerStyle,
+) -> Result<Vec<u8>, BackendError> {
+    let mut content = vec![];
+    match value.as_resolved() {
+        Some(Some(TreeValue::File { id, .. })) => {
+            store
+                .read_file(path, id)?
+                .read_to_end(&mut content)
+                .map_err(|err| BackendError::ReadObject {
+                    object_type: "file".to_string(),
+                    hash: id.hex(),
+                    source: err.into(),
+                })?;
+        }
+        Some(_) => {}
+        None => {
+            if value.to_file_merge().is_some() {
+                // The conflict has no single object id to report, so the
+                // error can't be a ReadObject error like above.
+                conflicts::materialize(value, store, path, conflict_marker_style, &mut content)
+                    .map_err(|err| BackendError::Other(err.into()))?;
+            }
+        }
+    }
+    Ok(content)
+}
@@ -19,6 +19,7 @@ use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::io::Read;
 use std::result::Result;
+use std::time::SystemTime;
 use std::vec::Vec;
 
 use thiserror::Error;
@@ -26,6 +27,7 @@ use thiserror::Error;
 use crate::content_hash::ContentHash;
 use crate::merge::Merge;
 use crate::repo_path::{RepoPath, RepoPathComponent};
+use crate::signing::SignResult;
 
 pub trait ObjectId {
     fn new(value: Vec<u8>) -> Self;
@@ -196,16 +198,41 @@ impl MergedTreeId {
     }
 }
 
-content_hash! {
-    #[derive(Debug, PartialEq, Eq, Clone)]
-    pub struct Commit {
-        pub parents: Vec<CommitId>,
-        pub predecessors: Vec<CommitId>,
-        pub root_tree: MergedTreeId,
-        pub change_id: ChangeId,
-        pub description: String,
-        pub author: Signature,
-        pub committer: Signature,
+/// A cryptographic signature of a commit, together with the exact data that
+/// was signed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SecureSig {
+    pub data: Vec<u8>,
+    pub sig: Vec<u8>,
+}
+
+/// Creates a signature for the given data. Passed to `Backend::write_commit()`
+/// by callers that want the commit to be signed.
+pub type SigningFn<'a> = dyn FnMut(&[u8]) -> SignResult<Vec<u8>> + 'a;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Commit {
+    pub parents: Vec<CommitId>,
+    pub predecessors: Vec<CommitId>,
+    pub root_tree: MergedTreeId,
+    pub change_id: ChangeId,
+    pub description: String,
+    pub author: Signature,
+    pub committer: Signature,
+    pub secure_sig: Option<SecureSig>,
+}
+
+// The signature is not part of the content hash so that signing a commit
+// doesn't change its id in backends that use the content hash as the id.
+impl ContentHash for Commit {
+    fn hash(&self, state: &mut impl digest::Update) {
+        self.parents.hash(state);
+        self.predecessors.hash(state);
+        self.root_tree.hash(state);
+        self.change_id.hash(state);
+        self.description.hash(state);
+        self.author.hash(state);
+        self.committer.hash(state);
     }
 }
 
@@ -462,6 +489,7 @@ pub fn make_root_commit(root_change_id: ChangeId, empty_tree_id: TreeId) -> Comm
         description: String::new(),
         author: signature.clone(),
         committer: signature,
+        secure_sig: None,
     }
 }
 
@@ -508,5 +536,24 @@ pub trait Backend: Send + Sync + Debug {
     /// committer name to an authenticated user's name, or the backend's
     /// timestamps may have less precision than the millisecond precision in
     /// `Commit`.
-    fn write_commit(&self, contents: Commit) -> BackendResult<(CommitId, Commit)>;
+    ///
+    /// If `sign_with` is given, the backend should sign the commit with it if
+    /// it supports signing. An existing `secure_sig` should only be kept if
+    /// the signed data didn't change.
+    fn write_commit(
+        &self,
+        contents: Commit,
+        sign_with: Option<&mut SigningFn>,
+    ) -> BackendResult<(CommitId, Commit)>;
+
+    /// Removes objects which are no longer needed.
+    ///
+    /// Commits reachable from the `heads` (and their trees) must be preserved.
+    /// Unreachable objects newer than `keep_newer` should be preserved as well
+    /// since they might have been written by a concurrent process.
+    ///
+    /// The default implementation doesn't remove anything.
+    fn gc(&self, _heads: &[CommitId], _keep_newer: SystemTime) -> BackendResult<()> {
+        Ok(())
+    }
 }
@@ -0,0 +1,165 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bloom filters of the paths changed by a commit.
+//!
+//! A filter contains every path changed by the commit relative to its
+//! (merged) parents, along with all ancestor directories of those paths. A
+//! negative lookup therefore proves that the commit doesn't touch anything
+//! under the looked-up path. Filters are stored per commit in the index, so
+//! path-limited revsets can skip most commits without diffing their trees.
+
+use std::collections::HashSet;
+
+use blake2::Blake2b512;
+use digest::Digest;
+
+use crate::repo_path::RepoPath;
+
+/// Number of bits set per path.
+const NUM_HASHES: u64 = 7;
+/// Number of filter bits allocated per path.
+const BITS_PER_PATH: usize = 10;
+/// Commits changing more paths than this don't get a filter, since it would
+/// be large and rarely negative anyway.
+pub const MAX_CHANGED_PATHS: usize = 512;
+
+/// Precomputed hash of a path to look up in [`ChangedPathFilter`]s.
+#[derive(Clone, Debug)]
+pub struct ChangedPathKey {
+    hash1: u64,
+    hash2: u64,
+}
+
+impl ChangedPathKey {
+    /// Hashes the `path` to look up.
+    pub fn new(path: &RepoPath) -> Self {
+        let mut hasher = Blake2b512::new();
+        hasher.update(path.to_internal_file_string().as_bytes());
+        let digest = hasher.finalize();
+        let hash1 = u64::from_le_bytes(digest[0..8].try_into().unwrap());
+        // Make sure the second hash is odd so the probes don't collapse onto
+        // a single bit.
+        let hash2 = u64::from_le_bytes(digest[8..16].try_into().unwrap()) | 1;
+        ChangedPathKey { hash1, hash2 }
+    }
+
+    fn bit_positions(&self, num_bits: u64) -> impl Iterator<Item = u64> + '_ {
+        (0..NUM_HASHES).map(move |i| self.hash1.wrapping_add(i.wrapping_mul(self.hash2)) % num_bits)
+    }
+}
+
+/// Serialized Bloom filter of the paths changed by a commit.
+#[derive(Clone, Copy, Debug)]
+pub struct ChangedPathFilter<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ChangedPathFilter<'a> {
+    /// Wraps serialized filter `data`. Returns `None` if the data is empty,
+    /// which means that the changed paths are unknown.
+    pub fn from_bytes(data: &'a [u8]) -> Option<Self> {
+        (!data.is_empty()).then_some(ChangedPathFilter { data })
+    }
+
+    /// Returns false if the commit definitely doesn't change anything at or
+    /// under the `key` path.
+    pub fn might_contain(&self, key: &ChangedPathKey) -> bool {
+        let num_bits = self.data.len() as u64 * 8;
+        key.bit_positions(num_bits)
+            .all(|bit| self.data[(bit / 8) as usize] & (1 << (bit % 8)) != 0)
+    }
+}
+
+/// Builds a serialized filter from the files changed by a commit.
+///
+/// Returns an empty buffer (meaning "unknown") if there are more than
+/// [`MAX_CHANGED_PATHS`] paths including their ancestor directories.
+pub fn build_changed_path_filter<'a>(
+    changed_files: impl IntoIterator<Item = &'a RepoPath>,
+) -> Vec<u8> {
+    let mut paths = HashSet::new();
+    for file in changed_files {
+        let mut path = Some(file.clone());
+        while let Some(current) = path.filter(|path| !path.is_root()) {
+            path = current.parent();
+            if !paths.insert(current) {
+                // The ancestors have been inserted already
+                break;
+            }
+        }
+        if paths.len() > MAX_CHANGED_PATHS {
+            return vec![];
+        }
+    }
+    // Even a commit that changes nothing gets a non-empty filter
+    let num_bytes = ((paths.len() * BITS_PER_PATH + 7) / 8).max(1);
+    let mut data = vec![0; num_bytes];
+    let num_bits = num_bytes as u64 * 8;
+    for path in &paths {
+        for bit in ChangedPathKey::new(path).bit_positions(num_bits) {
+            data[(bit / 8) as usize] |= 1 << (bit % 8);
+        }
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(value: &str) -> ChangedPathKey {
+        ChangedPathKey::new(&RepoPath::from_internal_string(value))
+    }
+
+    #[test]
+    fn test_changed_path_filter() {
+        let changed_files = [
+            RepoPath::from_internal_string("dir1/dir2/file1"),
+            RepoPath::from_internal_string("dir1/file2"),
+            RepoPath::from_internal_string("file3"),
+        ];
+        let data = build_changed_path_filter(&changed_files);
+        let filter = ChangedPathFilter::from_bytes(&data).unwrap();
+        for path in [
+            "dir1/dir2/file1",
+            "dir1/dir2",
+            "dir1",
+            "dir1/file2",
+            "file3",
+        ] {
+            assert!(filter.might_contain(&key(path)), "{path}");
+        }
+        let num_false_positives = (0..100)
+            .filter(|i| filter.might_contain(&key(&format!("other/file{i}"))))
+            .count();
+        assert!(num_false_positives < 10);
+    }
+
+    #[test]
+    fn test_changed_path_filter_empty() {
+        let data = build_changed_path_filter(&[]);
+        let filter = ChangedPathFilter::from_bytes(&data).unwrap();
+        assert!(!filter.might_contain(&key("file")));
+        assert!(ChangedPathFilter::from_bytes(&[]).is_none());
+    }
+
+    #[test]
+    fn test_changed_path_filter_too_many_paths() {
+        let changed_files = (0..=MAX_CHANGED_PATHS)
+            .map(|i| RepoPath::from_internal_string(&format!("file{i}")))
+            .collect::<Vec<_>>();
+        assert!(build_changed_path_filter(&changed_files).is_empty());
+    }
+}
@@ -22,6 +22,7 @@ use std::sync::Arc;
 use crate::backend;
 use crate::backend::{BackendError, ChangeId, CommitId, MergedTreeId, Signature};
 use crate::merged_tree::MergedTree;
+use crate::signing::{SignResult, Verification};
 use crate::store::Store;
 
 #[derive(Clone)]
@@ -92,11 +93,16 @@ impl Commit {
         &self.data.predecessors
     }
 
+    /// Returns the predecessors of this commit, skipping the ones that were
+    /// removed by garbage collection.
     pub fn predecessors(&self) -> Vec<Commit> {
         self.data
             .predecessors
             .iter()
-            .map(|id| self.store.get_commit(id).unwrap())
+            .filter_map(|id| match self.store.get_commit(id) {
+                Err(BackendError::ObjectNotFound { .. }) => None,
+                result => Some(result.unwrap()),
+            })
             .collect()
     }
 
@@ -128,6 +134,19 @@ impl Commit {
         &self.data.committer
     }
 
+    pub fn is_signed(&self) -> bool {
+        self.data.secure_sig.is_some()
+    }
+
+    /// Verifies the commit's signature, returning `None` if it isn't signed.
+    pub fn verification(&self) -> SignResult<Option<Verification>> {
+        self.data
+            .secure_sig
+            .as_ref()
+            .map(|sig| self.store.signer().verify(&self.id, &sig.data, &sig.sig))
+            .transpose()
+    }
+
     /// A commit is discardable if it has one parent, no change from its
     /// parent, and an empty description.
     pub fn is_discardable(&self) -> bool {
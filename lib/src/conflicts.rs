@@ -16,6 +16,7 @@
 
 use std::io::Write;
 use std::iter::zip;
+use std::str::FromStr;
 
 use itertools::Itertools;
 
@@ -27,11 +28,108 @@ use crate::merge::{Merge, MergeBuilder};
 use crate::repo_path::RepoPath;
 use crate::store::Store;
 
-const CONFLICT_START_LINE: &[u8] = b"<<<<<<<\n";
-const CONFLICT_END_LINE: &[u8] = b">>>>>>>\n";
-const CONFLICT_DIFF_LINE: &[u8] = b"%%%%%%%\n";
-const CONFLICT_MINUS_LINE: &[u8] = b"-------\n";
-const CONFLICT_PLUS_LINE: &[u8] = b"+++++++\n";
+/// The length of conflict markers, unless the conflicting contents contain
+/// lines that could be mistaken for markers of that length.
+pub const MIN_CONFLICT_MARKER_LEN: usize = 7;
+
+const CONFLICT_START_CHAR: u8 = b'<';
+const CONFLICT_END_CHAR: u8 = b'>';
+const CONFLICT_DIFF_CHAR: u8 = b'%';
+const CONFLICT_MINUS_CHAR: u8 = b'-';
+const CONFLICT_PLUS_CHAR: u8 = b'+';
+const CONFLICT_BASE_CHAR: u8 = b'|';
+const CONFLICT_SEPARATOR_CHAR: u8 = b'=';
+const CONFLICT_MARKER_CHARS: &[u8] = &[
+    CONFLICT_START_CHAR,
+    CONFLICT_END_CHAR,
+    CONFLICT_DIFF_CHAR,
+    CONFLICT_MINUS_CHAR,
+    CONFLICT_PLUS_CHAR,
+    CONFLICT_BASE_CHAR,
+    CONFLICT_SEPARATOR_CHAR,
+];
+
+/// How conflicts are written to files.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ConflictMarkerStyle {
+    /// A snapshot of one side and a diff from the base to each other side.
+    #[default]
+    Diff,
+    /// Git's `merge` style, with a snapshot of each side but not of the base.
+    Merge,
+    /// Git's `diff3` style, with a snapshot of each side and of the base.
+    Diff3,
+    /// Git's `zdiff3` style. Like `diff3`, but lines that both sides have in
+    /// common at the start and end of the conflict are moved out of it.
+    Zdiff3,
+}
+
+impl FromStr for ConflictMarkerStyle {
+    type Err = config::ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "diff" => Ok(Self::Diff),
+            "merge" => Ok(Self::Merge),
+            "diff3" => Ok(Self::Diff3),
+            "zdiff3" => Ok(Self::Zdiff3),
+            other => Err(config::ConfigError::Message(format!(
+                "unknown conflict marker style: {other}"
+            ))),
+        }
+    }
+}
+
+fn write_marker(
+    marker_char: u8,
+    marker_len: usize,
+    label: Option<&str>,
+    file: &mut dyn Write,
+) -> std::io::Result<()> {
+    file.write_all(&vec![marker_char; marker_len])?;
+    if let Some(label) = label {
+        write!(file, " {label}")?;
+    }
+    file.write_all(b"\n")
+}
+
+/// Returns the marker character if `line` is a conflict marker of the given
+/// length, optionally followed by a label.
+fn parse_marker(line: &[u8], marker_len: usize) -> Option<u8> {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    let marker_char = *line.first()?;
+    if !CONFLICT_MARKER_CHARS.contains(&marker_char)
+        || line.len() < marker_len
+        || line[..marker_len].iter().any(|b| *b != marker_char)
+    {
+        return None;
+    }
+    match line.get(marker_len) {
+        None | Some(b' ') => Some(marker_char),
+        Some(_) => None,
+    }
+}
+
+/// Returns the length of the conflict markers to use for `single_hunk`. The
+/// markers are made longer than any line in the contents that could otherwise
+/// be mistaken for one, including lines prefixed by a diff.
+pub fn choose_conflict_marker_len(single_hunk: &Merge<ContentHunk>) -> usize {
+    let max_marker_like_len = single_hunk
+        .iter()
+        .flat_map(|content| content.0.split_inclusive(|b| *b == b'\n'))
+        .filter_map(|line| {
+            let first = *line.first()?;
+            CONFLICT_MARKER_CHARS
+                .contains(&first)
+                .then(|| line.iter().take_while(|b| **b == first).count())
+        })
+        .max()
+        .unwrap_or(0);
+    // A line in a diff gets a one-character prefix, which may be another marker
+    // character.
+    (max_marker_like_len + 2).max(MIN_CONFLICT_MARKER_LEN)
+}
 
 fn write_diff_hunks(hunks: &[DiffHunk], file: &mut dyn Write) -> std::io::Result<()> {
     for hunk in hunks {
@@ -86,11 +184,12 @@ pub fn materialize(
     conflict: &Merge<Option<TreeValue>>,
     store: &Store,
     path: &RepoPath,
+    conflict_marker_style: ConflictMarkerStyle,
     output: &mut dyn Write,
 ) -> std::io::Result<()> {
     if let Some(file_merge) = conflict.to_file_merge() {
         let content = extract_as_single_hunk(&file_merge, store, path);
-        materialize_merge_result(&content, output)
+        materialize_merge_result(&content, conflict_marker_style, output)
     } else {
         // Unless all terms are regular files, we can't do much better than to try to
         // describe the merge.
@@ -100,6 +199,7 @@ pub fn materialize(
 
 pub fn materialize_merge_result(
     single_hunk: &Merge<ContentHunk>,
+    conflict_marker_style: ConflictMarkerStyle,
     output: &mut dyn Write,
 ) -> std::io::Result<()> {
     let slices = single_hunk.map(|content| content.0.as_slice());
@@ -109,57 +209,22 @@ pub fn materialize_merge_result(
             output.write_all(&content.0)?;
         }
         MergeResult::Conflict(hunks) => {
+            let marker_len = choose_conflict_marker_len(single_hunk);
             for hunk in hunks {
                 if let Some(content) = hunk.as_resolved() {
                     output.write_all(&content.0)?;
+                } else if conflict_marker_style == ConflictMarkerStyle::Diff
+                    || hunk.num_sides() != 2
+                {
+                    // Git's styles can only represent conflicts with two sides
+                    materialize_diff_conflict_hunk(&hunk, marker_len, output)?;
                 } else {
-                    output.write_all(CONFLICT_START_LINE)?;
-                    let mut add_index = 0;
-                    for left in hunk.removes() {
-                        let right1 = if let Some(right1) = hunk.adds().get(add_index) {
-                            right1
-                        } else {
-                            // If we have no more positive terms, emit the remaining negative
-                            // terms as snapshots.
-                            output.write_all(CONFLICT_MINUS_LINE)?;
-                            output.write_all(&left.0)?;
-                            continue;
-                        };
-                        let diff1 = Diff::for_tokenizer(&[&left.0, &right1.0], &find_line_ranges)
-                            .hunks()
-                            .collect_vec();
-                        // Check if the diff against the next positive term is better. Since
-                        // we want to preserve the order of the terms, we don't match against
-                        // any later positive terms.
-                        if let Some(right2) = hunk.adds().get(add_index + 1) {
-                            let diff2 =
-                                Diff::for_tokenizer(&[&left.0, &right2.0], &find_line_ranges)
-                                    .hunks()
-                                    .collect_vec();
-                            if diff_size(&diff2) < diff_size(&diff1) {
-                                // If the next positive term is a better match, emit
-                                // the current positive term as a snapshot and the next
-                                // positive term as a diff.
-                                output.write_all(CONFLICT_PLUS_LINE)?;
-                                output.write_all(&right1.0)?;
-                                output.write_all(CONFLICT_DIFF_LINE)?;
-                                write_diff_hunks(&diff2, output)?;
-                                add_index += 2;
-                                continue;
-                            }
-                        }
-
-                        output.write_all(CONFLICT_DIFF_LINE)?;
-                        write_diff_hunks(&diff1, output)?;
-                        add_index += 1;
-                    }
-
-                    //  Emit the remaining positive terms as snapshots.
-                    for slice in &hunk.adds()[add_index..] {
-                        output.write_all(CONFLICT_PLUS_LINE)?;
-                        output.write_all(&slice.0)?;
-                    }
-                    output.write_all(CONFLICT_END_LINE)?;
+                    materialize_git_conflict_hunk(
+                        &hunk,
+                        conflict_marker_style,
+                        marker_len,
+                        output,
+                    )?;
                 }
             }
         }
@@ -167,6 +232,113 @@ pub fn materialize_merge_result(
     Ok(())
 }
 
+fn materialize_diff_conflict_hunk(
+    hunk: &Merge<ContentHunk>,
+    marker_len: usize,
+    output: &mut dyn Write,
+) -> std::io::Result<()> {
+    write_marker(CONFLICT_START_CHAR, marker_len, None, output)?;
+    let mut add_index = 0;
+    for left in hunk.removes() {
+        let right1 = if let Some(right1) = hunk.adds().get(add_index) {
+            right1
+        } else {
+            // If we have no more positive terms, emit the remaining negative
+            // terms as snapshots.
+            write_marker(CONFLICT_MINUS_CHAR, marker_len, None, output)?;
+            output.write_all(&left.0)?;
+            continue;
+        };
+        let diff1 = Diff::for_tokenizer(&[&left.0, &right1.0], &find_line_ranges)
+            .hunks()
+            .collect_vec();
+        // Check if the diff against the next positive term is better. Since
+        // we want to preserve the order of the terms, we don't match against
+        // any later positive terms.
+        if let Some(right2) = hunk.adds().get(add_index + 1) {
+            let diff2 = Diff::for_tokenizer(&[&left.0, &right2.0], &find_line_ranges)
+                .hunks()
+                .collect_vec();
+            if diff_size(&diff2) < diff_size(&diff1) {
+                // If the next positive term is a better match, emit
+                // the current positive term as a snapshot and the next
+                // positive term as a diff.
+                write_marker(CONFLICT_PLUS_CHAR, marker_len, None, output)?;
+                output.write_all(&right1.0)?;
+                write_marker(CONFLICT_DIFF_CHAR, marker_len, None, output)?;
+                write_diff_hunks(&diff2, output)?;
+                add_index += 2;
+                continue;
+            }
+        }
+
+        write_marker(CONFLICT_DIFF_CHAR, marker_len, None, output)?;
+        write_diff_hunks(&diff1, output)?;
+        add_index += 1;
+    }
+
+    //  Emit the remaining positive terms as snapshots.
+    for slice in &hunk.adds()[add_index..] {
+        write_marker(CONFLICT_PLUS_CHAR, marker_len, None, output)?;
+        output.write_all(&slice.0)?;
+    }
+    write_marker(CONFLICT_END_CHAR, marker_len, None, output)?;
+    Ok(())
+}
+
+fn materialize_git_conflict_hunk(
+    hunk: &Merge<ContentHunk>,
+    conflict_marker_style: ConflictMarkerStyle,
+    marker_len: usize,
+    output: &mut dyn Write,
+) -> std::io::Result<()> {
+    let left = hunk.adds()[0].0.as_slice();
+    let right = hunk.adds()[1].0.as_slice();
+    let base = hunk.removes()[0].0.as_slice();
+    let (prefix_len, suffix_len) = if conflict_marker_style == ConflictMarkerStyle::Zdiff3 {
+        common_line_affixes(left, right)
+    } else {
+        (0, 0)
+    };
+    output.write_all(&left[..prefix_len])?;
+    write_marker(CONFLICT_START_CHAR, marker_len, Some("Side #1"), output)?;
+    output.write_all(&left[prefix_len..left.len() - suffix_len])?;
+    if conflict_marker_style != ConflictMarkerStyle::Merge {
+        write_marker(CONFLICT_BASE_CHAR, marker_len, Some("Base"), output)?;
+        output.write_all(base)?;
+    }
+    write_marker(CONFLICT_SEPARATOR_CHAR, marker_len, None, output)?;
+    output.write_all(&right[prefix_len..right.len() - suffix_len])?;
+    write_marker(CONFLICT_END_CHAR, marker_len, Some("Side #2"), output)?;
+    output.write_all(&left[left.len() - suffix_len..])?;
+    Ok(())
+}
+
+/// Returns the lengths in bytes of the lines that `left` and `right` have in
+/// common at their start and at their end. The two don't overlap.
+fn common_line_affixes(left: &[u8], right: &[u8]) -> (usize, usize) {
+    let left_lines = left.split_inclusive(|b| *b == b'\n').collect_vec();
+    let right_lines = right.split_inclusive(|b| *b == b'\n').collect_vec();
+    let num_prefix_lines = zip(&left_lines, &right_lines)
+        .take_while(|(l, r)| l == r)
+        .count();
+    let num_suffix_lines = zip(
+        left_lines[num_prefix_lines..].iter().rev(),
+        right_lines[num_prefix_lines..].iter().rev(),
+    )
+    .take_while(|(l, r)| l == r)
+    .count();
+    let prefix_len = left_lines[..num_prefix_lines]
+        .iter()
+        .map(|line| line.len())
+        .sum();
+    let suffix_len = left_lines[left_lines.len() - num_suffix_lines..]
+        .iter()
+        .map(|line| line.len())
+        .sum();
+    (prefix_len, suffix_len)
+}
+
 fn diff_size(hunks: &[DiffHunk]) -> usize {
     hunks
         .iter()
@@ -179,11 +351,60 @@ fn diff_size(hunks: &[DiffHunk]) -> usize {
 
 /// Parses conflict markers from a slice. Returns None if there were no valid
 /// conflict markers. The caller has to provide the expected number of merge
-/// sides (adds). Conflict markers that are otherwise valid will be considered
-/// invalid if they don't have the expected arity.
+/// sides (adds) and the length of the markers, as returned by
+/// [`choose_conflict_marker_len()`]. Conflict markers that are otherwise valid
+/// will be considered invalid if they don't have the expected arity.
+///
+/// All conflict marker styles are accepted. Since the `merge` style doesn't
+/// include the base, the base of such conflicts is empty.
 // TODO: "parse" is not usually the opposite of "materialize", so maybe we
 // should rename them to "serialize" and "deserialize"?
-pub fn parse_conflict(input: &[u8], num_sides: usize) -> Option<Vec<Merge<ContentHunk>>> {
+pub fn parse_conflict(
+    input: &[u8],
+    num_sides: usize,
+    marker_len: usize,
+) -> Option<Vec<Merge<ContentHunk>>> {
+    let hunks = parse_conflict_hunks(input, num_sides, marker_len)?;
+    Some(
+        hunks
+            .into_iter()
+            .map(|hunk| match hunk {
+                ParsedHunk::Merge(merge) => merge,
+                ParsedHunk::WithoutBase(adds) => Merge::new(vec![ContentHunk(vec![])], adds),
+            })
+            .collect(),
+    )
+}
+
+/// A hunk of content with conflict markers.
+enum ParsedHunk {
+    Merge(Merge<ContentHunk>),
+    /// The sides of a conflict in the `merge` style, which doesn't include the
+    /// base.
+    WithoutBase(Vec<ContentHunk>),
+}
+
+impl ParsedHunk {
+    fn num_sides(&self) -> usize {
+        match self {
+            ParsedHunk::Merge(merge) => merge.num_sides(),
+            ParsedHunk::WithoutBase(adds) => adds.len(),
+        }
+    }
+
+    fn is_resolved(&self) -> bool {
+        match self {
+            ParsedHunk::Merge(merge) => merge.is_resolved(),
+            ParsedHunk::WithoutBase(_) => false,
+        }
+    }
+}
+
+fn parse_conflict_hunks(
+    input: &[u8],
+    num_sides: usize,
+    marker_len: usize,
+) -> Option<Vec<ParsedHunk>> {
     if input.is_empty() {
         return None;
     }
@@ -192,15 +413,20 @@ pub fn parse_conflict(input: &[u8], num_sides: usize) -> Option<Vec<Merge<Conten
     let mut resolved_start = 0;
     let mut conflict_start = None;
     for line in input.split_inclusive(|b| *b == b'\n') {
-        if line == CONFLICT_START_LINE {
-            conflict_start = Some(pos);
-        } else if conflict_start.is_some() && line == CONFLICT_END_LINE {
-            let conflict_body = &input[conflict_start.unwrap() + CONFLICT_START_LINE.len()..pos];
-            let hunk = parse_conflict_hunk(conflict_body);
+        let marker = parse_marker(line, marker_len);
+        if marker == Some(CONFLICT_START_CHAR) {
+            conflict_start = Some((pos, line.len()));
+        } else if let (Some((start, start_line_len)), Some(CONFLICT_END_CHAR)) =
+            (conflict_start, marker)
+        {
+            let conflict_body = &input[start + start_line_len..pos];
+            let hunk = parse_conflict_hunk(conflict_body, marker_len);
             if hunk.num_sides() == num_sides {
-                let resolved_slice = &input[resolved_start..conflict_start.unwrap()];
+                let resolved_slice = &input[resolved_start..start];
                 if !resolved_slice.is_empty() {
-                    hunks.push(Merge::resolved(ContentHunk(resolved_slice.to_vec())));
+                    hunks.push(ParsedHunk::Merge(Merge::resolved(ContentHunk(
+                        resolved_slice.to_vec(),
+                    ))));
                 }
                 hunks.push(hunk);
                 resolved_start = pos + line.len();
@@ -214,15 +440,26 @@ pub fn parse_conflict(input: &[u8], num_sides: usize) -> Option<Vec<Merge<Conten
         None
     } else {
         if resolved_start < input.len() {
-            hunks.push(Merge::resolved(ContentHunk(
+            hunks.push(ParsedHunk::Merge(Merge::resolved(ContentHunk(
                 input[resolved_start..].to_vec(),
-            )));
+            ))));
         }
         Some(hunks)
     }
 }
 
-fn parse_conflict_hunk(input: &[u8]) -> Merge<ContentHunk> {
+fn parse_conflict_hunk(input: &[u8], marker_len: usize) -> ParsedHunk {
+    let is_git_style = input
+        .split_inclusive(|b| *b == b'\n')
+        .any(|line| parse_marker(line, marker_len) == Some(CONFLICT_SEPARATOR_CHAR));
+    if is_git_style {
+        parse_git_conflict_hunk(input, marker_len)
+    } else {
+        ParsedHunk::Merge(parse_diff_conflict_hunk(input, marker_len))
+    }
+}
+
+fn parse_diff_conflict_hunk(input: &[u8], marker_len: usize) -> Merge<ContentHunk> {
     enum State {
         Diff,
         Minus,
@@ -233,19 +470,19 @@ fn parse_conflict_hunk(input: &[u8]) -> Merge<ContentHunk> {
     let mut removes = vec![];
     let mut adds = vec![];
     for line in input.split_inclusive(|b| *b == b'\n') {
-        match line {
-            CONFLICT_DIFF_LINE => {
+        match parse_marker(line, marker_len) {
+            Some(CONFLICT_DIFF_CHAR) => {
                 state = State::Diff;
                 removes.push(ContentHunk(vec![]));
                 adds.push(ContentHunk(vec![]));
                 continue;
             }
-            CONFLICT_MINUS_LINE => {
+            Some(CONFLICT_MINUS_CHAR) => {
                 state = State::Minus;
                 removes.push(ContentHunk(vec![]));
                 continue;
             }
-            CONFLICT_PLUS_LINE => {
+            Some(CONFLICT_PLUS_CHAR) => {
                 state = State::Plus;
                 adds.push(ContentHunk(vec![]));
                 continue;
@@ -282,6 +519,44 @@ fn parse_conflict_hunk(input: &[u8]) -> Merge<ContentHunk> {
     Merge::new(removes, adds)
 }
 
+/// Parses a conflict in Git's `merge`, `diff3` or `zdiff3` style.
+fn parse_git_conflict_hunk(input: &[u8], marker_len: usize) -> ParsedHunk {
+    #[derive(PartialEq)]
+    enum State {
+        Left,
+        Base,
+        Right,
+    }
+    let mut state = State::Left;
+    let mut left = vec![];
+    let mut base = None;
+    let mut right = vec![];
+    for line in input.split_inclusive(|b| *b == b'\n') {
+        match parse_marker(line, marker_len) {
+            Some(CONFLICT_BASE_CHAR) if state == State::Left => {
+                state = State::Base;
+                base = Some(vec![]);
+                continue;
+            }
+            Some(CONFLICT_SEPARATOR_CHAR) if state != State::Right => {
+                state = State::Right;
+                continue;
+            }
+            _ => {}
+        }
+        match state {
+            State::Left => left.extend_from_slice(line),
+            State::Base => base.as_mut().unwrap().extend_from_slice(line),
+            State::Right => right.extend_from_slice(line),
+        }
+    }
+    let adds = vec![ContentHunk(left), ContentHunk(right)];
+    match base {
+        Some(base) => ParsedHunk::Merge(Merge::new(vec![ContentHunk(base)], adds)),
+        None => ParsedHunk::WithoutBase(adds),
+    }
+}
+
 /// Parses conflict markers in `content` and returns an updated version of
 /// `file_ids` with the new contents. If no (valid) conflict markers remain, a
 /// single resolves `FileId` will be returned.
@@ -290,6 +565,7 @@ pub fn update_from_content(
     store: &Store,
     path: &RepoPath,
     content: &[u8],
+    conflict_marker_style: ConflictMarkerStyle,
 ) -> BackendResult<Merge<Option<FileId>>> {
     // First check if the new content is unchanged compared to the old content. If
     // it is, we don't need parse the content or write any new objects to the
@@ -298,16 +574,21 @@ pub fn update_from_content(
     // copy.
     let mut old_content = Vec::with_capacity(content.len());
     let merge_hunk = extract_as_single_hunk(file_ids, store, path);
-    materialize_merge_result(&merge_hunk, &mut old_content).unwrap();
+    materialize_merge_result(&merge_hunk, conflict_marker_style, &mut old_content).unwrap();
     if content == old_content {
         return Ok(file_ids.clone());
     }
 
-    let Some(hunks) = parse_conflict(content, file_ids.num_sides()) else {
+    let marker_len = choose_conflict_marker_len(&merge_hunk);
+    let Some(hunks) = parse_conflict_hunks(content, file_ids.num_sides(), marker_len) else {
         // Either there are no self markers of they don't have the expected arity
         let file_id = store.write_file(path, &mut &content[..])?;
         return Ok(Merge::normal(file_id));
     };
+    let mut hunks = restore_missing_bases(&merge_hunk, hunks);
+    if conflict_marker_style == ConflictMarkerStyle::Zdiff3 {
+        restore_zdiff3_affixes(&merge_hunk, &mut hunks);
+    }
     let mut contents = file_ids.map(|_| vec![]);
     for hunk in hunks {
         if let Some(slice) = hunk.as_resolved() {
@@ -350,3 +631,120 @@ pub fn update_from_content(
             .collect();
     Ok(builder?.build())
 }
+
+/// Returns the conflicting hunks of `old_hunk`, which are the ones that were
+/// materialized with conflict markers.
+fn old_conflict_hunks(old_hunk: &Merge<ContentHunk>) -> Vec<Merge<ContentHunk>> {
+    let slices = old_hunk.map(|content| content.0.as_slice());
+    match files::merge(slices.removes(), slices.adds()) {
+        MergeResult::Resolved(_) => vec![],
+        MergeResult::Conflict(old_hunks) => old_hunks
+            .into_iter()
+            .filter(|hunk| !hunk.is_resolved())
+            .collect_vec(),
+    }
+}
+
+/// Fills in the base of conflicts written in the `merge` style from the
+/// corresponding conflicts in `old_hunk`. If the user resolved some of the
+/// conflicts, they are matched by their sides instead of by their position.
+fn restore_missing_bases(
+    old_hunk: &Merge<ContentHunk>,
+    hunks: Vec<ParsedHunk>,
+) -> Vec<Merge<ContentHunk>> {
+    let old_conflicts = old_conflict_hunks(old_hunk);
+    let num_conflicts = hunks.iter().filter(|hunk| !hunk.is_resolved()).count();
+    let match_by_position = num_conflicts == old_conflicts.len();
+    let mut conflict_index = 0;
+    hunks
+        .into_iter()
+        .map(|hunk| {
+            if !hunk.is_resolved() {
+                conflict_index += 1;
+            }
+            match hunk {
+                ParsedHunk::Merge(merge) => merge,
+                ParsedHunk::WithoutBase(adds) => {
+                    let old_conflict = if match_by_position {
+                        old_conflicts.get(conflict_index - 1)
+                    } else {
+                        old_conflicts.iter().find(|old| old.adds() == adds)
+                    };
+                    let base = old_conflict
+                        .and_then(|old| old.removes().first().cloned())
+                        .unwrap_or(ContentHunk(vec![]));
+                    Merge::new(vec![base], adds)
+                }
+            }
+        })
+        .collect()
+}
+
+/// Moves the lines which the `zdiff3` style wrote outside the conflict markers
+/// back into the sides of the corresponding conflicts in `hunks`.
+///
+/// When parsed, these lines are part of the resolved hunks around the
+/// conflict, so they would otherwise be added to the base as well. Conflicts
+/// are matched with the ones in `old_hunk` like in `restore_missing_bases()`.
+/// Lines the user edited are left alone.
+fn restore_zdiff3_affixes(old_hunk: &Merge<ContentHunk>, hunks: &mut [Merge<ContentHunk>]) {
+    let old_conflicts = old_conflict_hunks(old_hunk)
+        .into_iter()
+        .filter(|hunk| hunk.num_sides() == 2)
+        .map(|hunk| {
+            let [left, right] = [&hunk.adds()[0].0, &hunk.adds()[1].0];
+            let (prefix_len, suffix_len) = common_line_affixes(left, right);
+            let prefix = left[..prefix_len].to_vec();
+            let suffix = left[left.len() - suffix_len..].to_vec();
+            let inner_adds = hunk
+                .adds()
+                .iter()
+                .map(|add| ContentHunk(add.0[prefix_len..add.0.len() - suffix_len].to_vec()))
+                .collect_vec();
+            (inner_adds, prefix, suffix)
+        })
+        .collect_vec();
+    let num_conflicts = hunks.iter().filter(|hunk| !hunk.is_resolved()).count();
+    let match_by_position = num_conflicts == old_conflicts.len();
+    let mut conflict_index = 0;
+    for i in 0..hunks.len() {
+        if hunks[i].is_resolved() {
+            continue;
+        }
+        conflict_index += 1;
+        let old_conflict = if match_by_position {
+            old_conflicts.get(conflict_index - 1)
+        } else {
+            old_conflicts
+                .iter()
+                .find(|(inner_adds, _, _)| inner_adds.as_slice() == hunks[i].adds())
+        };
+        let Some((_, prefix, suffix)) = old_conflict else {
+            continue;
+        };
+        let mut adds = hunks[i].adds().to_vec();
+        if let Some(ContentHunk(before)) = i
+            .checked_sub(1)
+            .and_then(|j| hunks[j].as_resolved())
+            .filter(|content| !prefix.is_empty() && content.0.ends_with(prefix))
+        {
+            let before = before[..before.len() - prefix.len()].to_vec();
+            hunks[i - 1] = Merge::resolved(ContentHunk(before));
+            for add in &mut adds {
+                add.0.splice(0..0, prefix.iter().copied());
+            }
+        }
+        if let Some(ContentHunk(after)) = hunks
+            .get(i + 1)
+            .and_then(|hunk| hunk.as_resolved())
+            .filter(|content| !suffix.is_empty() && content.0.starts_with(suffix))
+        {
+            let after = after[suffix.len()..].to_vec();
+            hunks[i + 1] = Merge::resolved(ContentHunk(after));
+            for add in &mut adds {
+                add.0.extend_from_slice(suffix);
+            }
+        }
+        hunks[i] = Merge::new(hunks[i].removes().to_vec(), adds);
+    }
+}
@@ -113,6 +113,18 @@ where
     }
 }
 
+impl<K> ContentHash for std::collections::BTreeSet<K>
+where
+    K: ContentHash,
+{
+    fn hash(&self, state: &mut impl digest::Update) {
+        state.update(&(self.len() as u64).to_le_bytes());
+        for k in self.iter() {
+            k.hash(state);
+        }
+    }
+}
+
 impl<K, V> ContentHash for std::collections::BTreeMap<K, V>
 where
     K: ContentHash,
@@ -0,0 +1,392 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Detection of copied and renamed files.
+//!
+//! Commits don't record copies, so they're detected by comparing the content
+//! of the files removed and added between two trees.
+
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+
+use itertools::Itertools;
+
+use crate::backend::{BackendError, BackendResult, FileId, ObjectId, TreeValue};
+use crate::diff::find_line_ranges;
+use crate::matchers::{EverythingMatcher, Matcher};
+use crate::merge::Merge;
+use crate::merged_tree::{MergedTree, MergedTreeBuilder};
+use crate::repo_path::RepoPath;
+use crate::store::Store;
+
+/// Minimum similarity, in percent, for a file to be considered a copy of
+/// another.
+const SIMILARITY_THRESHOLD: usize = 50;
+
+/// Maximum number of source and target pairs to compare by content. Beyond
+/// that, only files with identical content are matched.
+const MAX_SIMILARITY_COMPARISONS: usize = 100 * 100;
+
+/// How a file was derived from another one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CopyOperation {
+    /// The file was copied from a removed file, which was renamed to another
+    /// path.
+    Copy,
+    /// The file was moved from another path.
+    Rename,
+}
+
+/// A difference between two trees, where the path may have been copied or
+/// renamed from another path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeDiffEntry {
+    /// The path in the tree before. Same as `target` unless the file was copied
+    /// or renamed.
+    pub source: RepoPath,
+    /// The path in the tree after.
+    pub target: RepoPath,
+    /// Whether the file at `target` was copied or renamed from `source`.
+    pub copy_operation: Option<CopyOperation>,
+    /// The value at `source` in the tree before.
+    pub before: Merge<Option<TreeValue>>,
+    /// The value at `target` in the tree after.
+    pub after: Merge<Option<TreeValue>>,
+}
+
+/// Collects the differences between `from_tree` and `to_tree`, detecting
+/// copies and renames.
+///
+/// An added file is considered to be renamed from a removed file if their
+/// contents are similar enough, in which case the removal isn't reported. If
+/// several added files match the same removed file, the one with the same file
+/// name, or else the first one, is reported as a rename and the others as
+/// copies. The removed file doesn't have to match `matcher`.
+pub fn diff_with_copies(
+    from_tree: &MergedTree,
+    to_tree: &MergedTree,
+    matcher: &dyn Matcher,
+) -> BackendResult<Vec<TreeDiffEntry>> {
+    let entries: Vec<_> = from_tree.diff(to_tree, matcher).collect();
+    let targets: Vec<_> = entries
+        .iter()
+        .filter(|(_, before, _)| before.is_absent())
+        .filter_map(|(path, _, after)| Some((path, as_file_id(after)?)))
+        .collect();
+    let mut sources = vec![];
+    if !targets.is_empty() {
+        for (path, before, after) in from_tree.diff(to_tree, &EverythingMatcher) {
+            if let (Some(id), true) = (as_file_id(&before), after.is_absent()) {
+                sources.push((path, id.clone()));
+            }
+        }
+    }
+    let matches = find_copy_sources(from_tree.store(), &sources, &targets)?;
+
+    let mut targets_by_source: HashMap<usize, Vec<&RepoPath>> = HashMap::new();
+    for ((target, _), source_index) in targets.iter().zip(matches) {
+        if let Some(source_index) = source_index {
+            targets_by_source
+                .entry(source_index)
+                .or_default()
+                .push(target);
+        }
+    }
+    let mut copies = HashMap::new();
+    let mut renamed_sources = HashSet::new();
+    for (source_index, source_targets) in targets_by_source {
+        let source = &sources[source_index].0;
+        // Prefer the target that kept the file name as the rename
+        let renamed_index = source_targets
+            .iter()
+            .position(|target| target.components().last() == source.components().last())
+            .unwrap_or(0);
+        for (index, target) in source_targets.into_iter().enumerate() {
+            let copy_operation = if index == renamed_index {
+                CopyOperation::Rename
+            } else {
+                CopyOperation::Copy
+            };
+            copies.insert(target.clone(), (source.clone(), copy_operation));
+        }
+        renamed_sources.insert(source.clone());
+    }
+
+    let mut diff_entries = Vec::with_capacity(entries.len());
+    for (path, before, after) in entries {
+        if after.is_absent() && renamed_sources.contains(&path) {
+            continue;
+        }
+        let entry = match copies.remove(&path) {
+            Some((source, copy_operation)) => TreeDiffEntry {
+                before: from_tree.path_value(&source),
+                source,
+                target: path,
+                copy_operation: Some(copy_operation),
+                after,
+            },
+            None => TreeDiffEntry {
+                source: path.clone(),
+                target: path,
+                copy_operation: None,
+                before,
+                after,
+            },
+        };
+        diff_entries.push(entry);
+    }
+    Ok(diff_entries)
+}
+
+/// Moves files that were renamed on one side of a merge to their new path in
+/// the base and on the other side, if the other side modified them. Without
+/// that, the modification would conflict with the removal of the file.
+pub(crate) fn follow_renames(
+    base: &MergedTree,
+    side1: &MergedTree,
+    side2: &MergedTree,
+) -> BackendResult<(MergedTree, MergedTree, MergedTree)> {
+    let unchanged = || Ok((base.clone(), side1.clone(), side2.clone()));
+    if base.id() == side1.id() || base.id() == side2.id() || side1.id() == side2.id() {
+        return unchanged();
+    }
+    let changes1 = FileChanges::new(base, side1);
+    let changes2 = FileChanges::new(base, side2);
+    let renames1 = changes1.find_renames_modified_by(&changes2, side2)?;
+    let renames2 = changes2.find_renames_modified_by(&changes1, side1)?;
+    if renames1.is_empty() && renames2.is_empty() {
+        return unchanged();
+    }
+    let base_renames = renames1.iter().chain(&renames2).cloned().collect_vec();
+    Ok((
+        move_files(base, &base_renames)?,
+        move_files(side1, &renames2)?,
+        move_files(side2, &renames1)?,
+    ))
+}
+
+/// The regular files that were changed on one side of a merge.
+struct FileChanges<'a> {
+    tree: &'a MergedTree,
+    removed: Vec<(RepoPath, FileId)>,
+    added: Vec<(RepoPath, FileId)>,
+    modified: HashSet<RepoPath>,
+}
+
+impl<'a> FileChanges<'a> {
+    fn new(base: &MergedTree, tree: &'a MergedTree) -> Self {
+        let mut removed = vec![];
+        let mut added = vec![];
+        let mut modified = HashSet::new();
+        for (path, before, after) in base.diff(tree, &EverythingMatcher) {
+            if before.is_absent() {
+                if let Some(id) = as_file_id(&after) {
+                    added.push((path, id.clone()));
+                }
+            } else if after.is_absent() {
+                if let Some(id) = as_file_id(&before) {
+                    removed.push((path, id.clone()));
+                }
+            } else if as_file_id(&after).is_some() {
+                modified.insert(path);
+            }
+        }
+        FileChanges {
+            tree,
+            removed,
+            added,
+            modified,
+        }
+    }
+
+    /// Finds the files renamed on this side that were modified on the other
+    /// side, and whose new path is free there. Returns pairs of old and new
+    /// paths.
+    fn find_renames_modified_by(
+        &self,
+        other: &FileChanges,
+        other_tree: &MergedTree,
+    ) -> BackendResult<Vec<(RepoPath, RepoPath)>> {
+        let sources = self
+            .removed
+            .iter()
+            .filter(|(path, _)| other.modified.contains(path))
+            .cloned()
+            .collect_vec();
+        if sources.is_empty() {
+            return Ok(vec![]);
+        }
+        let targets = self
+            .added
+            .iter()
+            .filter(|(path, _)| other_tree.path_value(path).is_absent())
+            .map(|(path, id)| (path, id))
+            .collect_vec();
+        let matches = find_copy_sources(self.tree.store(), &sources, &targets)?;
+        let mut renamed_sources = HashSet::new();
+        let mut renames = vec![];
+        for ((target, _), source_index) in targets.into_iter().zip(matches) {
+            if let Some(source_index) = source_index {
+                if renamed_sources.insert(source_index) {
+                    renames.push((sources[source_index].0.clone(), target.clone()));
+                }
+            }
+        }
+        Ok(renames)
+    }
+}
+
+fn move_files(tree: &MergedTree, renames: &[(RepoPath, RepoPath)]) -> BackendResult<MergedTree> {
+    if renames.is_empty() {
+        return Ok(tree.clone());
+    }
+    let store = tree.store();
+    let mut tree_builder = MergedTreeBuilder::new(tree.id());
+    for (source, target) in renames {
+        let Ok(Some(TreeValue::File { id, executable })) = tree.path_value(source).into_resolved()
+        else {
+            return Err(BackendError::Other(
+                format!("Only regular files can be renamed, but {source:?} isn't one").into(),
+            ));
+        };
+        // Backends may store files by path, so write the file at its new path
+        let id = store.write_file(target, &mut store.read_file(source, &id)?)?;
+        tree_builder.set_or_remove(
+            target.clone(),
+            Merge::normal(TreeValue::File { id, executable }),
+        );
+        tree_builder.set_or_remove(source.clone(), Merge::absent());
+    }
+    store.get_root_tree(&tree_builder.write_tree(store)?)
+}
+
+/// Returns the value as a file ID if it's a resolved regular file.
+pub(crate) fn as_file_id(value: &Merge<Option<TreeValue>>) -> Option<&FileId> {
+    match value.as_resolved() {
+        Some(Some(TreeValue::File { id, .. })) => Some(id),
+        _ => None,
+    }
+}
+
+/// Finds the index of the file in `sources` that each file in `targets` was
+/// most likely copied from, if any.
+///
+/// Files with identical content are matched first. The remaining files are
+/// matched by the similarity of their lines, unless there are too many of them.
+/// Empty files are never matched.
+pub(crate) fn find_copy_sources(
+    store: &Store,
+    sources: &[(RepoPath, FileId)],
+    targets: &[(&RepoPath, &FileId)],
+) -> BackendResult<Vec<Option<usize>>> {
+    let mut contents: HashMap<&FileId, Vec<u8>> = HashMap::new();
+    let mut matches = vec![None; targets.len()];
+    let mut unmatched = vec![];
+    for (target_index, &(target_path, target_id)) in targets.iter().enumerate() {
+        match sources.iter().position(|(_, id)| id == target_id) {
+            Some(source_index) => {
+                load_file(store, &mut contents, target_path, target_id)?;
+                if !contents[target_id].is_empty() {
+                    matches[target_index] = Some(source_index);
+                }
+            }
+            None => unmatched.push(target_index),
+        }
+    }
+    if unmatched.is_empty() || unmatched.len() * sources.len() > MAX_SIMILARITY_COMPARISONS {
+        return Ok(matches);
+    }
+
+    for (source_path, source_id) in sources {
+        load_file(store, &mut contents, source_path, source_id)?;
+    }
+    for &target_index in &unmatched {
+        let (target_path, target_id) = targets[target_index];
+        load_file(store, &mut contents, target_path, target_id)?;
+    }
+    for target_index in unmatched {
+        let target_content = &contents[targets[target_index].1];
+        let mut best_match = None;
+        for (source_index, (_, source_id)) in sources.iter().enumerate() {
+            let score = similarity(&contents[source_id], target_content);
+            if score >= SIMILARITY_THRESHOLD
+                && best_match.map_or(true, |(_, best_score)| score > best_score)
+            {
+                best_match = Some((source_index, score));
+            }
+        }
+        matches[target_index] = best_match.map(|(source_index, _)| source_index);
+    }
+    Ok(matches)
+}
+
+fn load_file<'a>(
+    store: &Store,
+    contents: &mut HashMap<&'a FileId, Vec<u8>>,
+    path: &RepoPath,
+    id: &'a FileId,
+) -> BackendResult<()> {
+    if contents.contains_key(id) {
+        return Ok(());
+    }
+    let mut content = vec![];
+    store
+        .read_file(path, id)?
+        .read_to_end(&mut content)
+        .map_err(|err| BackendError::ReadObject {
+            object_type: "file".to_string(),
+            hash: id.hex(),
+            source: err.into(),
+        })?;
+    contents.insert(id, content);
+    Ok(())
+}
+
+/// Returns the percentage of the content of the larger of the two files that
+/// consists of lines found in the other one.
+fn similarity(left: &[u8], right: &[u8]) -> usize {
+    let total = left.len().max(right.len());
+    if total == 0 {
+        return 0;
+    }
+    let mut left_lines: HashMap<&[u8], usize> = HashMap::new();
+    for range in find_line_ranges(left) {
+        *left_lines.entry(&left[range]).or_default() += 1;
+    }
+    let mut common = 0;
+    for range in find_line_ranges(right) {
+        let line = &right[range];
+        if let Some(count) = left_lines.get_mut(line).filter(|count| **count > 0) {
+            *count -= 1;
+            common += line.len();
+        }
+    }
+    common * 100 / total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity(b"", b""), 0);
+        assert_eq!(similarity(b"a\nb\n", b"a\nb\n"), 100);
+        assert_eq!(similarity(b"a\nb\n", b"b\na\n"), 100);
+        assert_eq!(similarity(b"a\nb\nc\nd\n", b"a\nb\nx\ny\n"), 50);
+        assert_eq!(similarity(b"a\nb\n", b"a\nb\nc\nd\n"), 50);
+        assert_eq!(similarity(b"a\na\n", b"a\nb\n"), 50);
+        assert_eq!(similarity(b"a\n", b"b\n"), 0);
+    }
+}
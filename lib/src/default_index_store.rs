@@ -23,7 +23,7 @@ use std::hash::{Hash, Hasher};
 use std::io::{Read, Write};
 use std::ops::Range;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::{fs, io, iter};
 
 use blake2::Blake2b512;
@@ -34,23 +34,27 @@ use smallvec::SmallVec;
 use tempfile::NamedTempFile;
 use thiserror::Error;
 
-use crate::backend::{ChangeId, CommitId, ObjectId};
+use crate::backend::{BackendError, ChangeId, CommitId, ObjectId};
+use crate::changed_path_filter::{self, ChangedPathFilter};
 use crate::commit::{Commit, CommitByCommitterTimestamp};
 use crate::file_util::persist_content_addressed_temp_file;
 use crate::index::{
     HexPrefix, Index, IndexStore, IndexWriteError, MutableIndex, PrefixResolution, ReadonlyIndex,
 };
+use crate::matchers::EverythingMatcher;
 use crate::op_store::{OpStoreError, OperationId};
 use crate::operation::Operation;
-use crate::revset::{ResolvedExpression, Revset, RevsetEvaluationError};
+use crate::revset::{ChangeIdIndex, ResolvedExpression, Revset, RevsetEvaluationError};
 use crate::store::Store;
-use crate::{backend, dag_walk, default_revset_engine};
+use crate::{backend, dag_walk, default_revset_engine, rewrite};
 
 #[derive(Debug, Error)]
 pub enum DefaultIndexStoreError {
     #[error(transparent)]
     Io(#[from] io::Error),
     #[error(transparent)]
+    LoadIndex(#[from] IndexLoadError),
+    #[error(transparent)]
     OpStore(#[from] OpStoreError),
 }
 
@@ -79,6 +83,26 @@ impl DefaultIndexStore {
         std::fs::create_dir(op_dir).unwrap();
     }
 
+    /// Whether changed-path filters are built for commits when the index is
+    /// built from scratch. Incremental index files inherit the setting from
+    /// their parent file.
+    pub fn changed_paths_enabled(&self) -> bool {
+        self.dir.join("changed_paths").exists()
+    }
+
+    /// Enables or disables changed-path filters. Takes effect once the index
+    /// is rebuilt by `reinit()`.
+    pub fn set_changed_paths_enabled(&self, enabled: bool) -> io::Result<()> {
+        let path = self.dir.join("changed_paths");
+        if enabled {
+            fs::write(path, b"")
+        } else if path.exists() {
+            fs::remove_file(path)
+        } else {
+            Ok(())
+        }
+    }
+
     fn load_index_at_operation(
         &self,
         commit_id_length: usize,
@@ -99,6 +123,19 @@ impl DefaultIndexStore {
         )
     }
 
+    /// Discards all index files associated with operations and builds the index
+    /// at `operation` from scratch.
+    fn reindex_at_operation(
+        &self,
+        store: &Arc<Store>,
+        operation: &Operation,
+    ) -> Result<Arc<ReadonlyIndexImpl>, DefaultIndexStoreError> {
+        let operations_dir = self.dir.join("operations");
+        fs::remove_dir_all(&operations_dir)?;
+        fs::create_dir(&operations_dir)?;
+        self.index_at_operation(store, operation)
+    }
+
     #[tracing::instrument(skip(self, store))]
     fn index_at_operation(
         &self,
@@ -131,12 +168,15 @@ impl DefaultIndexStore {
         match parent_op_id {
             None => {
                 maybe_parent_file = None;
-                data = MutableIndexImpl::full(commit_id_length, change_id_length);
+                data = MutableIndexImpl::full(commit_id_length, change_id_length)
+                    .with_changed_paths(self.changed_paths_enabled());
             }
             Some(parent_op_id) => {
-                let parent_file = self
-                    .load_index_at_operation(commit_id_length, change_id_length, &parent_op_id)
-                    .unwrap();
+                let parent_file = self.load_index_at_operation(
+                    commit_id_length,
+                    change_id_length,
+                    &parent_op_id,
+                )?;
                 maybe_parent_file = Some(parent_file.clone());
                 data = MutableIndexImpl::incremental(parent_file)
             }
@@ -162,9 +202,22 @@ impl DefaultIndexStore {
                 .map(CommitByCommitterTimestamp),
             |CommitByCommitterTimestamp(commit)| commit.id().clone(),
             |CommitByCommitterTimestamp(commit)| {
-                itertools::chain(commit.parent_ids(), commit.predecessor_ids())
+                let parents = commit
+                    .parent_ids()
+                    .iter()
                     .filter(|&id| !parent_file_has_id(id))
-                    .map(|id| store.get_commit(id).unwrap())
+                    .map(|id| store.get_commit(id).unwrap());
+                // Predecessors may have been removed by garbage collection
+                let predecessors = commit
+                    .predecessor_ids()
+                    .iter()
+                    .filter(|&id| !parent_file_has_id(id))
+                    .filter_map(|id| match store.get_commit(id) {
+                        Err(BackendError::ObjectNotFound { .. }) => None,
+                        result => Some(result.unwrap()),
+                    });
+                parents
+                    .chain(predecessors)
                     .map(CommitByCommitterTimestamp)
                     .collect_vec()
             },
@@ -213,25 +266,32 @@ impl IndexStore for DefaultIndexStore {
     fn get_index_at_op(&self, op: &Operation, store: &Arc<Store>) -> Box<dyn ReadonlyIndex> {
         let op_id_hex = op.id().hex();
         let op_id_file = self.dir.join("operations").join(op_id_hex);
-        let index_impl = if op_id_file.exists() {
-            match self.load_index_at_operation(
+        let result = if op_id_file.exists() {
+            self.load_index_at_operation(
                 store.commit_id_length(),
                 store.change_id_length(),
                 op.id(),
-            ) {
-                Err(IndexLoadError::IndexCorrupt(_)) => {
-                    // If the index was corrupt (maybe it was written in a different format),
-                    // we just reindex.
-                    // TODO: Move this message to a callback or something.
-                    println!("The index was corrupt (maybe the format has changed). Reindexing...");
-                    std::fs::remove_dir_all(self.dir.join("operations")).unwrap();
-                    std::fs::create_dir(self.dir.join("operations")).unwrap();
-                    self.index_at_operation(store, op).unwrap()
-                }
-                result => result.unwrap(),
-            }
+            )
+            .map_err(DefaultIndexStoreError::from)
         } else {
-            self.index_at_operation(store, op).unwrap()
+            self.index_at_operation(store, op)
+        };
+        let index_impl = match result {
+            Err(DefaultIndexStoreError::LoadIndex(
+                err @ IndexLoadError::UnsupportedVersion { .. },
+            )) => {
+                // The index was written by an incompatible version of jj. It can
+                // be rebuilt from the operation log.
+                tracing::info!(%err, "reindexing due to index format change");
+                self.reindex_at_operation(store, op).unwrap()
+            }
+            Err(DefaultIndexStoreError::LoadIndex(IndexLoadError::IndexCorrupt(_))) => {
+                // If the index was corrupt, we just reindex.
+                // TODO: Move this message to a callback or something.
+                println!("The index was corrupt. Reindexing...");
+                self.reindex_at_operation(store, op).unwrap()
+            }
+            result => result.unwrap(),
         };
         Box::new(ReadonlyIndexWrapper(index_impl))
     }
@@ -298,12 +358,10 @@ impl CommitGraphEntry<'_> {
         (&self.data[16..]).read_u32::<LittleEndian>().unwrap()
     }
 
-    // TODO: Consider storing the change ids in a separate table. That table could
-    // be sorted by change id and have the end index into a list as value. That list
-    // would be the concatenation of all index positions associated with the change.
-    // Possible advantages: avoids duplicating change ids; smaller main graph leads
-    // to better cache locality when walking it; ability to quickly find all
-    // commits associated with a change id.
+    // TODO: Consider storing the change ids only in the change id lookup table,
+    // with the end index into a list of positions as value. Possible advantages:
+    // avoids duplicating change ids; smaller main graph leads to better cache
+    // locality when walking it.
     fn change_id(&self) -> ChangeId {
         ChangeId::new(self.data[20..][..self.change_id_length].to_vec())
     }
@@ -341,15 +399,50 @@ impl CommitLookupEntry<'_> {
     }
 }
 
+struct ChangeLookupEntry<'a> {
+    data: &'a [u8],
+    change_id_length: usize,
+}
+
+impl ChangeLookupEntry<'_> {
+    fn size(change_id_length: usize) -> usize {
+        change_id_length + 4
+    }
+
+    fn change_id(&self) -> ChangeId {
+        ChangeId::from_bytes(self.change_id_bytes())
+    }
+
+    fn change_id_bytes(&self) -> &[u8] {
+        &self.data[0..self.change_id_length]
+    }
+
+    fn pos(&self) -> IndexPosition {
+        IndexPosition(
+            (&self.data[self.change_id_length..][..4])
+                .read_u32::<LittleEndian>()
+                .unwrap(),
+        )
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum IndexLoadError {
     #[error("Index file '{0}' is corrupt.")]
     IndexCorrupt(String),
+    #[error("Index file '{name}' has unsupported format version {version}.")]
+    UnsupportedVersion { name: String, version: u32 },
     #[error("I/O error while loading index file: {0}")]
     IoError(#[from] io::Error),
 }
 
+/// Current format version of the index segment file.
+const INDEX_SEGMENT_FILE_FORMAT_VERSION: u32 = 1;
+
 // File format:
+// u32: file format version
+// u32: parent segment file name length (0 means root)
+// <length number of bytes>: parent segment file name
 // u32: number of entries
 // u32: number of parent overflow entries
 // for each entry, in some topological order with parents first:
@@ -361,7 +454,18 @@ pub enum IndexLoadError {
 // for each entry, sorted by commit id:
 //   <hash length number of bytes>: commit id
 //    u32: position in the entry table above
-// TODO: add a version number
+// for each entry, sorted by change id and then by position:
+//   <change id length number of bytes>: change id
+//    u32: position in the entry table above
+// for each parent overflow entry:
+//   u32: position in the entry table above
+// optionally, if the index has changed-path filters:
+//   u32: number of entries
+//   u32: size of the filter data
+//   for each entry, in the same order as the entry table:
+//     u32: end offset of the entry's filter in the filter data
+//   <size of the filter data number of bytes>: filter data (an empty filter
+//     means that the changed paths are unknown)
 // TODO: replace the table by a trie so we don't have to repeat the full commit
 //       ids
 // TODO: add a fanout table like git's commit graph has?
@@ -373,11 +477,16 @@ pub(crate) struct ReadonlyIndexImpl {
     change_id_length: usize,
     commit_graph_entry_size: usize,
     commit_lookup_entry_size: usize,
+    change_lookup_entry_size: usize,
     // Number of commits not counting the parent file
     num_local_commits: u32,
     graph: Vec<u8>,
     lookup: Vec<u8>,
+    change_lookup: Vec<u8>,
     overflow_parent: Vec<u8>,
+    // Empty if the index doesn't have changed-path filters
+    changed_path_ends: Vec<u8>,
+    changed_path_data: Vec<u8>,
 }
 
 pub struct ReadonlyIndexWrapper(Arc<ReadonlyIndexImpl>);
@@ -417,6 +526,7 @@ struct MutableGraphEntry {
     change_id: ChangeId,
     generation_number: u32,
     parent_positions: SmallIndexPositionsVec,
+    changed_path_filter: Option<Vec<u8>>,
 }
 
 pub struct MutableIndexImpl {
@@ -426,6 +536,8 @@ pub struct MutableIndexImpl {
     change_id_length: usize,
     graph: Vec<MutableGraphEntry>,
     lookup: BTreeMap<CommitId, IndexPosition>,
+    change_lookup: BTreeSet<(ChangeId, IndexPosition)>,
+    changed_paths: bool,
 }
 
 impl MutableIndexImpl {
@@ -437,6 +549,8 @@ impl MutableIndexImpl {
             change_id_length,
             graph: vec![],
             lookup: BTreeMap::new(),
+            change_lookup: BTreeSet::new(),
+            changed_paths: false,
         }
     }
 
@@ -444,6 +558,7 @@ impl MutableIndexImpl {
         let num_parent_commits = parent_file.num_parent_commits + parent_file.num_local_commits;
         let commit_id_length = parent_file.commit_id_length;
         let change_id_length = parent_file.change_id_length;
+        let changed_paths = parent_file.has_changed_paths();
         Self {
             parent_file: Some(parent_file),
             num_parent_commits,
@@ -451,18 +566,37 @@ impl MutableIndexImpl {
             change_id_length,
             graph: vec![],
             lookup: BTreeMap::new(),
+            change_lookup: BTreeSet::new(),
+            changed_paths,
         }
     }
 
+    /// Builds changed-path filters for commits added to this index.
+    pub(crate) fn with_changed_paths(mut self, enabled: bool) -> Self {
+        self.changed_paths = enabled;
+        self
+    }
+
     pub fn as_composite(&self) -> CompositeIndex {
         CompositeIndex(self)
     }
 
+    #[cfg(test)]
     pub(crate) fn add_commit_data(
         &mut self,
         commit_id: CommitId,
         change_id: ChangeId,
         parent_ids: &[CommitId],
+    ) {
+        self.add_commit_data_with_filter(commit_id, change_id, parent_ids, None);
+    }
+
+    fn add_commit_data_with_filter(
+        &mut self,
+        commit_id: CommitId,
+        change_id: ChangeId,
+        parent_ids: &[CommitId],
+        changed_path_filter: Option<Vec<u8>>,
     ) {
         if self.has_id(&commit_id) {
             return;
@@ -472,6 +606,7 @@ impl MutableIndexImpl {
             change_id,
             generation_number: 0,
             parent_positions: SmallVec::new(),
+            changed_path_filter,
         };
         for parent_id in parent_ids {
             let parent_entry = CompositeIndex(self)
@@ -483,10 +618,9 @@ impl MutableIndexImpl {
             );
             entry.parent_positions.push(parent_entry.pos);
         }
-        self.lookup.insert(
-            entry.commit_id.clone(),
-            IndexPosition(self.graph.len() as u32 + self.num_parent_commits),
-        );
+        let pos = IndexPosition(self.graph.len() as u32 + self.num_parent_commits);
+        self.lookup.insert(entry.commit_id.clone(), pos);
+        self.change_lookup.insert((entry.change_id.clone(), pos));
         self.graph.push(entry);
     }
 
@@ -499,16 +633,48 @@ impl MutableIndexImpl {
                 .iter()
                 .map(|entry| entry.commit_id())
                 .collect_vec();
-            self.add_commit_data(entry.commit_id(), entry.change_id(), &parent_ids);
+            let changed_path_filter = entry.changed_path_filter_data().map(|data| data.to_vec());
+            self.add_commit_data_with_filter(
+                entry.commit_id(),
+                entry.change_id(),
+                &parent_ids,
+                changed_path_filter,
+            );
         }
     }
 
+    /// Computes the changed-path filter of a commit whose parents are already
+    /// indexed.
+    fn compute_changed_path_filter(&self, commit: &Commit) -> Vec<u8> {
+        let parents = commit.parents();
+        if let [parent] = parents.as_slice() {
+            if parent.tree_id() == commit.tree_id() {
+                return changed_path_filter::build_changed_path_filter([]);
+            }
+        }
+        let from_tree =
+            rewrite::merge_commit_trees_without_repo(commit.store(), self, &parents).unwrap();
+        let to_tree = commit.tree().unwrap();
+        // There can't be fewer paths than changed files, so there's no need to
+        // look further once the filter is known to be too large.
+        let changed_files = from_tree
+            .diff(&to_tree, &EverythingMatcher)
+            .map(|(path, _, _)| path)
+            .take(changed_path_filter::MAX_CHANGED_PATHS + 1)
+            .collect_vec();
+        changed_path_filter::build_changed_path_filter(&changed_files)
+    }
+
     fn serialize(self) -> Vec<u8> {
         assert_eq!(self.graph.len(), self.lookup.len());
+        assert_eq!(self.graph.len(), self.change_lookup.len());
 
         let num_commits = self.graph.len() as u32;
+        let changed_paths = self.changed_paths;
 
         let mut buf = vec![];
+        buf.write_u32::<LittleEndian>(INDEX_SEGMENT_FILE_FORMAT_VERSION)
+            .unwrap();
 
         if let Some(parent_file) = &self.parent_file {
             buf.write_u32::<LittleEndian>(parent_file.name.len() as u32)
@@ -524,7 +690,14 @@ impl MutableIndexImpl {
         buf.write_u32::<LittleEndian>(0_u32).unwrap();
 
         let mut parent_overflow = vec![];
+        let mut changed_path_ends = vec![];
+        let mut changed_path_data = vec![];
         for entry in self.graph {
+            if let Some(filter) = &entry.changed_path_filter {
+                changed_path_data.extend_from_slice(filter);
+            }
+            changed_path_ends.push(changed_path_data.len() as u32);
+
             let flags = 0;
             buf.write_u32::<LittleEndian>(flags).unwrap();
 
@@ -557,6 +730,11 @@ impl MutableIndexImpl {
             buf.write_u32::<LittleEndian>(pos.0).unwrap();
         }
 
+        for (change_id, pos) in self.change_lookup {
+            buf.write_all(change_id.as_bytes()).unwrap();
+            buf.write_u32::<LittleEndian>(pos.0).unwrap();
+        }
+
         (&mut buf[parent_overflow_offset..][..4])
             .write_u32::<LittleEndian>(parent_overflow.len() as u32)
             .unwrap();
@@ -564,6 +742,16 @@ impl MutableIndexImpl {
             buf.write_u32::<LittleEndian>(parent_pos.0).unwrap();
         }
 
+        if changed_paths {
+            buf.write_u32::<LittleEndian>(num_commits).unwrap();
+            buf.write_u32::<LittleEndian>(changed_path_data.len() as u32)
+                .unwrap();
+            for end in changed_path_ends {
+                buf.write_u32::<LittleEndian>(end).unwrap();
+            }
+            buf.write_all(&changed_path_data).unwrap();
+        }
+
         buf
     }
 
@@ -594,6 +782,7 @@ impl MutableIndexImpl {
                 }
             }
         }
+        squashed.changed_paths = self.changed_paths;
 
         if files_to_squash.is_empty() {
             return self;
@@ -633,8 +822,8 @@ impl MutableIndexImpl {
             change_id_length,
         )
         .map_err(|err| match err {
-            IndexLoadError::IndexCorrupt(err) => {
-                panic!("Just-created index file is corrupt: {err}")
+            err @ (IndexLoadError::IndexCorrupt(_) | IndexLoadError::UnsupportedVersion { .. }) => {
+                panic!("Just-created index file is unreadable: {err}")
             }
             IndexLoadError::IoError(err) => err,
         })
@@ -677,6 +866,13 @@ impl Index for MutableIndexImpl {
     ) -> Result<Box<dyn Revset<'index> + 'index>, RevsetEvaluationError> {
         CompositeIndex(self).evaluate_revset(expression, store)
     }
+
+    fn change_id_index(
+        &self,
+        heads: &mut dyn Iterator<Item = &CommitId>,
+    ) -> Box<dyn ChangeIdIndex + '_> {
+        CompositeIndex(self).change_id_index(heads)
+    }
 }
 
 impl MutableIndex for MutableIndexImpl {
@@ -693,10 +889,17 @@ impl MutableIndex for MutableIndexImpl {
     }
 
     fn add_commit(&mut self, commit: &Commit) {
-        self.add_commit_data(
+        if self.has_id(commit.id()) {
+            return;
+        }
+        let changed_path_filter = self
+            .changed_paths
+            .then(|| self.compute_changed_path_filter(commit));
+        self.add_commit_data_with_filter(
             commit.id().clone(),
             commit.change_id().clone(),
             commit.parent_ids(),
+            changed_path_filter,
         );
     }
 
@@ -759,6 +962,22 @@ trait IndexSegment: Send + Sync {
 
     fn segment_resolve_prefix(&self, prefix: &HexPrefix) -> PrefixResolution<CommitId>;
 
+    /// Iterates over the change ids and positions of the commits in this
+    /// segment, sorted by change id, starting at the first change id that
+    /// isn't less than the given `change_id`.
+    fn segment_change_id_entries_from(
+        &self,
+        change_id: &ChangeId,
+    ) -> Box<dyn Iterator<Item = (ChangeId, IndexPosition)> + '_>;
+
+    /// Iterates backwards over the change ids and positions of the commits in
+    /// this segment, starting at the last change id that is less than the
+    /// given `change_id`.
+    fn segment_change_id_entries_before(
+        &self,
+        change_id: &ChangeId,
+    ) -> Box<dyn Iterator<Item = (ChangeId, IndexPosition)> + '_>;
+
     fn segment_generation_number(&self, local_pos: u32) -> u32;
 
     fn segment_commit_id(&self, local_pos: u32) -> CommitId;
@@ -769,6 +988,10 @@ trait IndexSegment: Send + Sync {
 
     fn segment_parent_positions(&self, local_pos: u32) -> SmallIndexPositionsVec;
 
+    /// Returns the serialized changed-path filter of the entry, or `None` if
+    /// this segment doesn't have changed-path filters.
+    fn segment_changed_path_filter(&self, local_pos: u32) -> Option<&[u8]>;
+
     fn segment_entry_by_pos(&self, pos: IndexPosition, local_pos: u32) -> IndexEntry;
 }
 
@@ -798,8 +1021,12 @@ impl<'a> CompositeIndex<'a> {
         let mut max_generation_number = 0;
         let mut is_head = vec![true; num_commits as usize];
         let mut change_ids = HashSet::new();
+        let mut num_changed_path_filters = 0;
         for pos in 0..num_commits {
             let entry = self.entry_by_pos(IndexPosition(pos));
+            if entry.changed_path_filter().is_some() {
+                num_changed_path_filters += 1;
+            }
             max_generation_number = max(max_generation_number, entry.generation_number());
             if entry.num_parents() > 1 {
                 num_merges += 1;
@@ -826,6 +1053,7 @@ impl<'a> CompositeIndex<'a> {
             max_generation_number,
             num_heads,
             num_changes: change_ids.len() as u32,
+            num_changed_path_filters,
             levels,
         }
     }
@@ -1001,6 +1229,20 @@ impl<'a> CompositeIndex<'a> {
         let revset_impl = default_revset_engine::evaluate(expression, store, *self)?;
         Ok(Box::new(revset_impl))
     }
+
+    fn change_id_index(
+        &self,
+        heads: &mut dyn Iterator<Item = &CommitId>,
+    ) -> Box<dyn ChangeIdIndex + 'a> {
+        let mut visible_set = AncestorsBitSet::with_capacity(self.num_commits());
+        for id in heads {
+            visible_set.add(self.commit_id_to_pos(id).unwrap());
+        }
+        Box::new(ChangeIdIndexImpl {
+            index: *self,
+            visible_set: Mutex::new(visible_set),
+        })
+    }
 }
 
 impl Index for CompositeIndex<'_> {
@@ -1080,6 +1322,124 @@ impl Index for CompositeIndex<'_> {
     ) -> Result<Box<dyn Revset<'index> + 'index>, RevsetEvaluationError> {
         CompositeIndex::evaluate_revset(self, expression, store)
     }
+
+    fn change_id_index(
+        &self,
+        heads: &mut dyn Iterator<Item = &CommitId>,
+    ) -> Box<dyn ChangeIdIndex + '_> {
+        CompositeIndex::change_id_index(self, heads)
+    }
+}
+
+/// Set of the ancestors of some heads, which is populated lazily in order of
+/// descending position.
+#[derive(Clone, Debug)]
+struct AncestorsBitSet {
+    bitset: Vec<u64>,
+    // Positions at or above this one have been visited
+    next_pos_to_visit: u32,
+}
+
+impl AncestorsBitSet {
+    fn with_capacity(num_commits: u32) -> Self {
+        AncestorsBitSet {
+            bitset: vec![0; (num_commits as usize + 63) / 64],
+            next_pos_to_visit: num_commits,
+        }
+    }
+
+    fn add(&mut self, pos: IndexPosition) {
+        let (word, bit) = (pos.0 as usize / 64, pos.0 % 64);
+        self.bitset[word] |= 1 << bit;
+    }
+
+    fn contains(&self, pos: IndexPosition) -> bool {
+        let (word, bit) = (pos.0 as usize / 64, pos.0 % 64);
+        self.bitset[word] & (1 << bit) != 0
+    }
+
+    /// Adds the ancestors of the visited entries until `to_pos` is visited.
+    fn visit_until(&mut self, index: CompositeIndex, to_pos: IndexPosition) {
+        while self.next_pos_to_visit > to_pos.0 {
+            self.next_pos_to_visit -= 1;
+            let pos = IndexPosition(self.next_pos_to_visit);
+            if self.contains(pos) {
+                for parent_pos in index.entry_by_pos(pos).parent_positions() {
+                    self.add(parent_pos);
+                }
+            }
+        }
+    }
+}
+
+/// Change id lookup backed by the sorted change id tables in the index
+/// segments. Only the commits reachable from the given heads are visible.
+struct ChangeIdIndexImpl<'a> {
+    index: CompositeIndex<'a>,
+    visible_set: Mutex<AncestorsBitSet>,
+}
+
+impl ChangeIdIndexImpl<'_> {
+    fn is_visible(&self, pos: IndexPosition) -> bool {
+        let mut visible_set = self.visible_set.lock().unwrap();
+        visible_set.visit_until(self.index, pos);
+        visible_set.contains(pos)
+    }
+}
+
+impl ChangeIdIndex for ChangeIdIndexImpl<'_> {
+    fn resolve_prefix(&self, prefix: &HexPrefix) -> PrefixResolution<Vec<CommitId>> {
+        let min_bytes_prefix = ChangeId::from_bytes(prefix.min_prefix_bytes());
+        let mut matched_change_id: Option<ChangeId> = None;
+        let mut positions = vec![];
+        for segment in self.index.ancestor_index_segments() {
+            let entries = segment
+                .segment_change_id_entries_from(&min_bytes_prefix)
+                .take_while(|(change_id, _)| prefix.matches(change_id));
+            for (change_id, pos) in entries {
+                if !self.is_visible(pos) {
+                    continue;
+                }
+                match &matched_change_id {
+                    None => matched_change_id = Some(change_id),
+                    Some(matched) if *matched == change_id => {}
+                    Some(_) => return PrefixResolution::AmbiguousMatch,
+                }
+                positions.push(pos);
+            }
+        }
+        if positions.is_empty() {
+            return PrefixResolution::NoMatch;
+        }
+        // Newest commits first, like in revsets
+        positions.sort_unstable_by_key(|&pos| Reverse(pos));
+        let commit_ids = positions
+            .into_iter()
+            .map(|pos| self.index.entry_by_pos(pos).commit_id())
+            .collect();
+        PrefixResolution::SingleMatch(commit_ids)
+    }
+
+    fn shortest_unique_prefix_len(&self, change_id: &ChangeId) -> usize {
+        let is_visible_neighbor =
+            |(id, pos): &(ChangeId, IndexPosition)| id != change_id && self.is_visible(*pos);
+        self.index
+            .ancestor_index_segments()
+            .flat_map(|segment| {
+                let prev = segment
+                    .segment_change_id_entries_before(change_id)
+                    .find(is_visible_neighbor);
+                let next = segment
+                    .segment_change_id_entries_from(change_id)
+                    .find(is_visible_neighbor);
+                itertools::chain(prev, next)
+            })
+            .map(|(id, _)| backend::common_hex_len(change_id.as_bytes(), id.as_bytes()) + 1)
+            .max()
+            // Even if the change id is the only one in the index, we require at
+            // least one digit.
+            .unwrap_or(1)
+    }
 }
 
 pub struct IndexLevelStats {
@@ -1093,6 +1453,7 @@ pub struct IndexStats {
     pub max_generation_number: u32,
     pub num_heads: u32,
     pub num_changes: u32,
+    pub num_changed_path_filters: u32,
     pub levels: Vec<IndexLevelStats>,
 }
 
@@ -1635,6 +1996,28 @@ impl IndexSegment for ReadonlyIndexImpl {
         }
     }
 
+    fn segment_change_id_entries_from(
+        &self,
+        change_id: &ChangeId,
+    ) -> Box<dyn Iterator<Item = (ChangeId, IndexPosition)> + '_> {
+        let lookup_pos = self.change_id_to_change_lookup_pos(change_id);
+        Box::new((lookup_pos..self.num_local_commits).map(|pos| {
+            let entry = self.change_lookup_entry(pos);
+            (entry.change_id(), entry.pos())
+        }))
+    }
+
+    fn segment_change_id_entries_before(
+        &self,
+        change_id: &ChangeId,
+    ) -> Box<dyn Iterator<Item = (ChangeId, IndexPosition)> + '_> {
+        let lookup_pos = self.change_id_to_change_lookup_pos(change_id);
+        Box::new((0..lookup_pos).rev().map(|pos| {
+            let entry = self.change_lookup_entry(pos);
+            (entry.change_id(), entry.pos())
+        }))
+    }
+
     fn segment_generation_number(&self, local_pos: u32) -> u32 {
         self.graph_entry(local_pos).generation_number()
     }
@@ -1667,6 +2050,21 @@ impl IndexSegment for ReadonlyIndexImpl {
         parent_entries
     }
 
+    fn segment_changed_path_filter(&self, local_pos: u32) -> Option<&[u8]> {
+        if !self.has_changed_paths() {
+            return None;
+        }
+        let read_end = |local_pos: u32| {
+            let offset = (local_pos as usize) * 4;
+            (&self.changed_path_ends[offset..][..4])
+                .read_u32::<LittleEndian>()
+                .unwrap() as usize
+        };
+        let start = local_pos.checked_sub(1).map_or(0, read_end);
+        let end = read_end(local_pos);
+        Some(&self.changed_path_data[start..end])
+    }
+
     fn segment_entry_by_pos(&self, pos: IndexPosition, local_pos: u32) -> IndexEntry {
         IndexEntry {
             source: self,
@@ -1729,6 +2127,22 @@ impl IndexSegment for MutableIndexImpl {
         }
     }
 
+    fn segment_change_id_entries_from(
+        &self,
+        change_id: &ChangeId,
+    ) -> Box<dyn Iterator<Item = (ChangeId, IndexPosition)> + '_> {
+        let start = (change_id.clone(), IndexPosition(0));
+        Box::new(self.change_lookup.range(start..).cloned())
+    }
+
+    fn segment_change_id_entries_before(
+        &self,
+        change_id: &ChangeId,
+    ) -> Box<dyn Iterator<Item = (ChangeId, IndexPosition)> + '_> {
+        let end = (change_id.clone(), IndexPosition(0));
+        Box::new(self.change_lookup.range(..end).rev().cloned())
+    }
+
     fn segment_generation_number(&self, local_pos: u32) -> u32 {
         self.graph[local_pos as usize].generation_number
     }
@@ -1749,6 +2163,12 @@ impl IndexSegment for MutableIndexImpl {
         self.graph[local_pos as usize].parent_positions.clone()
     }
 
+    fn segment_changed_path_filter(&self, local_pos: u32) -> Option<&[u8]> {
+        self.graph[local_pos as usize]
+            .changed_path_filter
+            .as_deref()
+    }
+
     fn segment_entry_by_pos(&self, pos: IndexPosition, local_pos: u32) -> IndexEntry {
         IndexEntry {
             source: self,
@@ -1822,6 +2242,17 @@ impl<'a> IndexEntry<'a> {
             .map(|pos| composite.entry_by_pos(pos))
             .collect()
     }
+
+    /// Returns the filter of paths changed by this commit, or `None` if the
+    /// changed paths aren't known.
+    pub fn changed_path_filter(&self) -> Option<ChangedPathFilter<'a>> {
+        self.changed_path_filter_data()
+            .and_then(ChangedPathFilter::from_bytes)
+    }
+
+    fn changed_path_filter_data(&self) -> Option<&'a [u8]> {
+        self.source.segment_changed_path_filter(self.local_pos)
+    }
 }
 
 impl ReadonlyIndexImpl {
@@ -1832,6 +2263,10 @@ impl ReadonlyIndexImpl {
         commit_id_length: usize,
         change_id_length: usize,
     ) -> Result<Arc<ReadonlyIndexImpl>, IndexLoadError> {
+        let version = file.read_u32::<LittleEndian>()?;
+        if version != INDEX_SEGMENT_FILE_FORMAT_VERSION {
+            return Err(IndexLoadError::UnsupportedVersion { name, version });
+        }
         let parent_filename_len = file.read_u32::<LittleEndian>()?;
         let num_parent_commits;
         let maybe_parent_file;
@@ -1862,12 +2297,28 @@ impl ReadonlyIndexImpl {
         let graph_size = (num_commits as usize) * commit_graph_entry_size;
         let commit_lookup_entry_size = CommitLookupEntry::size(commit_id_length);
         let lookup_size = (num_commits as usize) * commit_lookup_entry_size;
+        let change_lookup_entry_size = ChangeLookupEntry::size(change_id_length);
+        let change_lookup_size = (num_commits as usize) * change_lookup_entry_size;
         let parent_overflow_size = (num_parent_overflow_entries as usize) * 4;
-        let expected_size = graph_size + lookup_size + parent_overflow_size;
-        if data.len() != expected_size {
+        let expected_size = graph_size + lookup_size + change_lookup_size + parent_overflow_size;
+        if data.len() < expected_size {
             return Err(IndexLoadError::IndexCorrupt(name));
         }
-        let overflow_parent = data.split_off(graph_size + lookup_size);
+        let mut changed_path_ends = data.split_off(expected_size);
+        let mut changed_path_data = vec![];
+        if !changed_path_ends.is_empty() {
+            let mut header = changed_path_ends.as_slice();
+            let num_entries = header.read_u32::<LittleEndian>()?;
+            let data_size = header.read_u32::<LittleEndian>()?;
+            let ends_size = (num_entries as usize) * 4;
+            if num_entries != num_commits || header.len() != ends_size + data_size as usize {
+                return Err(IndexLoadError::IndexCorrupt(name));
+            }
+            changed_path_data = changed_path_ends.split_off(8 + ends_size);
+            changed_path_ends.drain(..8);
+        }
+        let overflow_parent = data.split_off(graph_size + lookup_size + change_lookup_size);
+        let change_lookup = data.split_off(graph_size + lookup_size);
         let lookup = data.split_off(graph_size);
         let graph = data;
         Ok(Arc::new(ReadonlyIndexImpl {
@@ -1878,13 +2329,21 @@ impl ReadonlyIndexImpl {
             change_id_length,
             commit_graph_entry_size,
             commit_lookup_entry_size,
+            change_lookup_entry_size,
             num_local_commits: num_commits,
             graph,
             lookup,
+            change_lookup,
             overflow_parent,
+            changed_path_ends,
+            changed_path_data,
         }))
     }
 
+    fn has_changed_paths(&self) -> bool {
+        !self.changed_path_ends.is_empty()
+    }
+
     pub fn as_composite(&self) -> CompositeIndex {
         CompositeIndex(self)
     }
@@ -1910,6 +2369,14 @@ impl ReadonlyIndexImpl {
         }
     }
 
+    fn change_lookup_entry(&self, lookup_pos: u32) -> ChangeLookupEntry<'_> {
+        let offset = (lookup_pos as usize) * self.change_lookup_entry_size;
+        ChangeLookupEntry {
+            data: &self.change_lookup[offset..][..self.change_lookup_entry_size],
+            change_id_length: self.change_id_length,
+        }
+    }
+
     fn overflow_parent(&self, overflow_pos: u32) -> IndexPosition {
         let offset = (overflow_pos as usize) * 4;
         IndexPosition(
@@ -1941,6 +2408,24 @@ impl ReadonlyIndexImpl {
             }
         }
     }
+
+    /// Returns the position of the first entry in the change id lookup table
+    /// whose change id isn't less than the given (possibly partial)
+    /// `change_id`. May be `num_local_commits`.
+    fn change_id_to_change_lookup_pos(&self, change_id: &ChangeId) -> u32 {
+        let mut low = 0;
+        let mut high = self.num_local_commits;
+        while low < high {
+            let mid = (low + high) / 2;
+            let entry = self.change_lookup_entry(mid);
+            if entry.change_id_bytes() < change_id.as_bytes() {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
 }
 
 impl Index for ReadonlyIndexImpl {
@@ -1979,10 +2464,19 @@ impl Index for ReadonlyIndexImpl {
     ) -> Result<Box<dyn Revset<'index> + 'index>, RevsetEvaluationError> {
         CompositeIndex(self).evaluate_revset(expression, store)
     }
+
+    fn change_id_index(
+        &self,
+        heads: &mut dyn Iterator<Item = &CommitId>,
+    ) -> Box<dyn ChangeIdIndex + '_> {
+        CompositeIndex(self).change_id_index(heads)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::slice;
+
     use smallvec::smallvec_inline;
     use test_case::test_case;
 
@@ -2509,6 +3003,104 @@ mod tests {
         );
     }
 
+    #[test]
+    fn change_id_index() {
+        let temp_dir = testutils::new_temp_dir();
+        let mut index = MutableIndexImpl::full(3, 2);
+
+        // 3 4 5
+        // | |/
+        // 1 | 2
+        //  \|/
+        //   0
+        let id_0 = CommitId::from_hex("000000");
+        let id_1 = CommitId::from_hex("111111");
+        let id_2 = CommitId::from_hex("222222");
+        let id_3 = CommitId::from_hex("333333");
+        let id_4 = CommitId::from_hex("444444");
+        let id_5 = CommitId::from_hex("555555");
+        index.add_commit_data(id_0.clone(), ChangeId::from_hex("0000"), &[]);
+        index.add_commit_data(
+            id_1.clone(),
+            ChangeId::from_hex("aaaa"),
+            slice::from_ref(&id_0),
+        );
+        index.add_commit_data(
+            id_2.clone(),
+            ChangeId::from_hex("aabb"),
+            slice::from_ref(&id_0),
+        );
+
+        // Write the first three commits to one file and build the remainder on top.
+        let initial_file = index.save_in(temp_dir.path().to_owned()).unwrap();
+        index = MutableIndexImpl::incremental(initial_file);
+
+        index.add_commit_data(
+            id_3.clone(),
+            ChangeId::from_hex("aaaa"),
+            slice::from_ref(&id_1),
+        );
+        index.add_commit_data(
+            id_4.clone(),
+            ChangeId::from_hex("ab00"),
+            slice::from_ref(&id_0),
+        );
+        index.add_commit_data(
+            id_5.clone(),
+            ChangeId::from_hex("bbbb"),
+            slice::from_ref(&id_0),
+        );
+
+        // Commit 2 is hidden
+        let change_id_index = index.change_id_index(&mut [&id_3, &id_4, &id_5].into_iter());
+        let resolve_prefix =
+            |prefix: &str| change_id_index.resolve_prefix(&HexPrefix::new(prefix).unwrap());
+        assert_eq!(
+            resolve_prefix("0"),
+            PrefixResolution::SingleMatch(vec![id_0.clone()])
+        );
+        assert_eq!(resolve_prefix("a"), PrefixResolution::AmbiguousMatch);
+        // Divergent commits are returned newest first
+        assert_eq!(
+            resolve_prefix("aa"),
+            PrefixResolution::SingleMatch(vec![id_3.clone(), id_1.clone()])
+        );
+        assert_eq!(resolve_prefix("aab"), PrefixResolution::NoMatch);
+        assert_eq!(
+            resolve_prefix("ab"),
+            PrefixResolution::SingleMatch(vec![id_4.clone()])
+        );
+        assert_eq!(
+            resolve_prefix("bbbb"),
+            PrefixResolution::SingleMatch(vec![id_5.clone()])
+        );
+        assert_eq!(resolve_prefix("c"), PrefixResolution::NoMatch);
+        let prefix_len = |change_id: &str| {
+            change_id_index.shortest_unique_prefix_len(&ChangeId::from_hex(change_id))
+        };
+        assert_eq!(prefix_len("0000"), 1);
+        assert_eq!(prefix_len("aaaa"), 2);
+        assert_eq!(prefix_len("aabb"), 3);
+        assert_eq!(prefix_len("ab00"), 2);
+        assert_eq!(prefix_len("bbbb"), 1);
+        assert_eq!(prefix_len("ffff"), 1);
+
+        // All commits are visible
+        let change_id_index = index.change_id_index(&mut [&id_2, &id_3, &id_4, &id_5].into_iter());
+        let resolve_prefix =
+            |prefix: &str| change_id_index.resolve_prefix(&HexPrefix::new(prefix).unwrap());
+        assert_eq!(resolve_prefix("aa"), PrefixResolution::AmbiguousMatch);
+        assert_eq!(
+            resolve_prefix("aab"),
+            PrefixResolution::SingleMatch(vec![id_2.clone()])
+        );
+        let prefix_len = |change_id: &str| {
+            change_id_index.shortest_unique_prefix_len(&ChangeId::from_hex(change_id))
+        };
+        assert_eq!(prefix_len("aaaa"), 3);
+        assert_eq!(prefix_len("aabb"), 3);
+    }
+
     #[test]
     fn test_is_ancestor() {
         let mut new_change_id = change_id_generator();
@@ -17,24 +17,32 @@
 use std::cmp::{Ordering, Reverse};
 use std::collections::{BTreeSet, BinaryHeap, HashSet};
 use std::fmt;
+use std::io::Read as _;
 use std::iter::Peekable;
 use std::ops::Range;
 use std::sync::Arc;
 
 use itertools::Itertools;
 
-use crate::backend::{ChangeId, CommitId, MillisSinceEpoch};
+use crate::backend::{
+    BackendError, BackendResult, ChangeId, CommitId, MillisSinceEpoch, ObjectId as _, TreeValue,
+};
+use crate::changed_path_filter::ChangedPathKey;
+use crate::conflicts::{self, ConflictMarkerStyle};
 use crate::default_index_store::{
     CompositeIndex, IndexEntry, IndexEntryByPosition, IndexPosition, RevWalk,
 };
 use crate::default_revset_graph_iterator::RevsetGraphIterator;
+use crate::diff::{self, Diff, DiffHunk};
+use crate::fileset::{FilePattern, FilesetExpression};
 use crate::id_prefix::{IdIndex, IdIndexSource, IdIndexSourceEntry};
 use crate::index::{HexPrefix, PrefixResolution};
-use crate::matchers::{EverythingMatcher, Matcher, PrefixMatcher, Visit};
+use crate::matchers::{Matcher, Visit};
+use crate::merge::Merge;
 use crate::repo_path::RepoPath;
 use crate::revset::{
     ChangeIdIndex, ResolvedExpression, ResolvedPredicateExpression, Revset, RevsetEvaluationError,
-    RevsetFilterPredicate, GENERATION_RANGE_FULL,
+    RevsetFilterPredicate, StringPattern, GENERATION_RANGE_FULL,
 };
 use crate::revset_graph::RevsetGraphEdge;
 use crate::rewrite;
@@ -534,7 +542,7 @@ impl<'index> EvaluationContext<'index> {
     ) -> Result<Box<dyn InternalRevset<'index> + 'index>, RevsetEvaluationError> {
         match expression {
             ResolvedExpression::Commits(commit_ids) => {
-                Ok(Box::new(self.revset_for_commit_ids(commit_ids)))
+                Ok(Box::new(self.revset_for_commit_ids(commit_ids)?))
             }
             ResolvedExpression::Ancestors { heads, generation } => {
                 let head_set = self.evaluate(heads)?;
@@ -746,14 +754,22 @@ impl<'index> EvaluationContext<'index> {
         (EagerRevset { index_entries }, reachable_positions)
     }
 
-    fn revset_for_commit_ids(&self, commit_ids: &[CommitId]) -> EagerRevset<'index> {
+    fn revset_for_commit_ids(
+        &self,
+        commit_ids: &[CommitId],
+    ) -> Result<EagerRevset<'index>, RevsetEvaluationError> {
         let mut index_entries = vec![];
         for id in commit_ids {
-            index_entries.push(self.index.entry_by_id(id).unwrap());
+            // Commits resolved at another operation, e.g. by at_operation(),
+            // may have been removed from the index since.
+            let entry = self.index.entry_by_id(id).ok_or_else(|| {
+                RevsetEvaluationError::Other(format!("Commit {} is not in the index", id.hex()))
+            })?;
+            index_entries.push(entry);
         }
         index_entries.sort_unstable_by_key(|b| Reverse(b.position()));
         index_entries.dedup();
-        EagerRevset { index_entries }
+        Ok(EagerRevset { index_entries })
     }
 
     fn take_latest_revset(
@@ -856,21 +872,110 @@ fn build_predicate_fn<'index>(
                     || pattern.matches(&commit.committer().email)
             })
         }
-        RevsetFilterPredicate::File(paths) => {
-            // TODO: Add support for globs and other formats
-            let matcher: Box<dyn Matcher> = if let Some(paths) = paths {
-                Box::new(PrefixMatcher::new(paths))
-            } else {
-                Box::new(EverythingMatcher)
-            };
+        RevsetFilterPredicate::AuthorDate(pattern) => {
+            let pattern = pattern.clone();
             pure_predicate_fn(move |entry| {
-                has_diff_from_parent(&store, index, entry, matcher.as_ref())
+                let commit = store.get_commit(&entry.commit_id()).unwrap();
+                pattern.matches(&commit.author().timestamp)
+            })
+        }
+        RevsetFilterPredicate::CommitterDate(pattern) => {
+            let pattern = pattern.clone();
+            pure_predicate_fn(move |entry| {
+                let commit = store.get_commit(&entry.commit_id()).unwrap();
+                pattern.matches(&commit.committer().timestamp)
+            })
+        }
+        RevsetFilterPredicate::File(expr) => {
+            let matcher = expr.to_matcher();
+            let changed_path_keys = to_changed_path_keys(expr);
+            pure_predicate_fn(move |entry| {
+                might_change_paths(entry, changed_path_keys.as_deref())
+                    && has_diff_from_parent(&store, index, entry, matcher.as_ref())
+            })
+        }
+        RevsetFilterPredicate::DiffContains { text, files } => {
+            let text_pattern = text.clone();
+            let files_matcher = files.to_matcher();
+            let changed_path_keys = to_changed_path_keys(files);
+            // TODO: Propagate the error once revset iteration can fail. Like the
+            // other predicates, this still panics if the store can't be read.
+            pure_predicate_fn(move |entry| {
+                might_change_paths(entry, changed_path_keys.as_deref())
+                    && matches_diff_from_parent(
+                        &store,
+                        index,
+                        entry,
+                        &text_pattern,
+                        files_matcher.as_ref(),
+                    )
+                    .unwrap()
             })
         }
         RevsetFilterPredicate::HasConflict => pure_predicate_fn(move |entry| {
             let commit = store.get_commit(&entry.commit_id()).unwrap();
             commit.tree().unwrap().has_conflict()
         }),
+        RevsetFilterPredicate::Signed(status) => {
+            let status = *status;
+            pure_predicate_fn(move |entry| {
+                let commit = store.get_commit(&entry.commit_id()).unwrap();
+                match status {
+                    None => commit.is_signed(),
+                    // Signatures that fail to verify don't match any status.
+                    Some(status) => commit
+                        .verification()
+                        .ok()
+                        .flatten()
+                        .is_some_and(|verification| verification.status == status),
+                }
+            })
+        }
+    }
+}
+
+/// Collects the paths which contain all files matched by the `expression`, to
+/// be looked up in changed-path filters. Returns `None` if the matched files
+/// can't be narrowed down.
+fn to_changed_path_keys(expression: &FilesetExpression) -> Option<Vec<ChangedPathKey>> {
+    fn collect_paths(expression: &FilesetExpression) -> Option<Vec<RepoPath>> {
+        match expression {
+            FilesetExpression::None => Some(vec![]),
+            FilesetExpression::All => None,
+            FilesetExpression::Pattern(pattern) => match pattern {
+                FilePattern::FilePath(path) | FilePattern::PrefixPath(path) => {
+                    Some(vec![path.clone()])
+                }
+                FilePattern::FileGlob { dir, .. } => Some(vec![dir.clone()]),
+            },
+            FilesetExpression::UnionAll(expressions) => {
+                let paths: Option<Vec<_>> = expressions.iter().map(collect_paths).collect();
+                Some(paths?.concat())
+            }
+            FilesetExpression::Intersection(expression1, expression2) => {
+                collect_paths(expression1).or_else(|| collect_paths(expression2))
+            }
+            FilesetExpression::Difference(expression1, _) => collect_paths(expression1),
+        }
+    }
+
+    let paths = collect_paths(expression)?;
+    // Changes to the root directory aren't recorded in the filters
+    if paths.iter().any(|path| path.is_root()) {
+        return None;
+    }
+    Some(paths.iter().map(ChangedPathKey::new).collect())
+}
+
+/// Returns false if the changed-path filter of the `entry` proves that the
+/// commit doesn't touch any of the `changed_path_keys`.
+fn might_change_paths(
+    entry: &IndexEntry<'_>,
+    changed_path_keys: Option<&[ChangedPathKey]>,
+) -> bool {
+    match (changed_path_keys, entry.changed_path_filter()) {
+        (Some(keys), Some(filter)) => keys.iter().any(|key| filter.might_contain(key)),
+        _ => true,
     }
 }
 
@@ -896,6 +1001,88 @@ fn has_diff_from_parent(
     from_tree.diff(&to_tree, matcher).next().is_some()
 }
 
+fn matches_diff_from_parent(
+    store: &Arc<Store>,
+    index: CompositeIndex<'_>,
+    entry: &IndexEntry<'_>,
+    text_pattern: &StringPattern,
+    files_matcher: &dyn Matcher,
+) -> BackendResult<bool> {
+    let commit = store.get_commit(&entry.commit_id())?;
+    let parents = commit.parents();
+    if let [parent] = parents.as_slice() {
+        if commit.tree_id() == parent.tree_id() {
+            return Ok(false);
+        }
+    }
+    let from_tree = rewrite::merge_commit_trees_without_repo(store, &index, &parents)
+        .map_err(|err| BackendError::Other(err.into()))?;
+    let to_tree = commit.tree()?;
+    let matches_any_line = |text: &[u8]| {
+        diff::find_line_ranges(text).into_iter().any(|range| {
+            let line = &text[range];
+            let line = line.strip_suffix(b"\n").unwrap_or(line);
+            text_pattern.matches(&String::from_utf8_lossy(line))
+        })
+    };
+    for (path, left_value, right_value) in from_tree.diff(&to_tree, files_matcher) {
+        let left_content = to_file_content(store, &path, &left_value)?;
+        let right_content = to_file_content(store, &path, &right_value)?;
+        // Most files won't contain the text at all, so check that before
+        // computing the diff.
+        if !matches_any_line(&left_content) && !matches_any_line(&right_content) {
+            continue;
+        }
+        let diff = Diff::for_tokenizer(&[&left_content, &right_content], &diff::find_line_ranges);
+        if diff.hunks().any(|hunk| match hunk {
+            DiffHunk::Matching(_) => false,
+            DiffHunk::Different(contents) => contents.iter().any(|text| matches_any_line(text)),
+        }) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn to_file_content(
+    store: &Store,
+    path: &RepoPath,
+    value: &Merge<Option<TreeValue>>,
+) -> BackendResult<Vec<u8>> {
+    match value.as_resolved() {
+        Some(None) => Ok(vec![]),
+        Some(Some(TreeValue::File { id, .. })) => {
+            let mut content = vec![];
+            let mut file_reader = store.read_file(path, id)?;
+            file_reader
+                .read_to_end(&mut content)
+                .map_err(|err| BackendError::ReadObject {
+                    object_type: id.object_type(),
+                    hash: id.hex(),
+                    source: err.into(),
+                })?;
+            Ok(content)
+        }
+        Some(Some(TreeValue::Symlink(id))) => Ok(store.read_symlink(path, id)?.into_bytes()),
+        Some(Some(TreeValue::GitSubmodule(_))) => Ok(vec![]),
+        Some(Some(TreeValue::Tree(_))) | Some(Some(TreeValue::Conflict(_))) => Err(
+            BackendError::Other(format!("Unexpected {value:?} in diff at path {path:?}").into()),
+        ),
+        None => {
+            let mut content = vec![];
+            conflicts::materialize(
+                value,
+                store,
+                path,
+                ConflictMarkerStyle::default(),
+                &mut content,
+            )
+            .map_err(|err| BackendError::Other(err.into()))?;
+            Ok(content)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -635,6 +635,53 @@ pub fn diff<'a>(left: &'a [u8], right: &'a [u8]) -> Vec<DiffHunk<'a>> {
         .collect_vec()
 }
 
+/// Returns the ranges of lines that differ between `left` and `right`, as
+/// pairs of line ranges in `left` and in `right`.
+pub fn changed_line_ranges(left: &[u8], right: &[u8]) -> Vec<(Range<usize>, Range<usize>)> {
+    let diff = Diff::for_tokenizer(&[left, right], &find_line_ranges);
+    let mut left_line = 0;
+    let mut right_line = 0;
+    let mut ranges = vec![];
+    for hunk in diff.hunks() {
+        match hunk {
+            DiffHunk::Matching(content) => {
+                let num_lines = find_line_ranges(content).len();
+                left_line += num_lines;
+                right_line += num_lines;
+            }
+            DiffHunk::Different(contents) => {
+                let left_end = left_line + find_line_ranges(contents[0]).len();
+                let right_end = right_line + find_line_ranges(contents[1]).len();
+                ranges.push((left_line..left_end, right_line..right_end));
+                left_line = left_end;
+                right_line = right_end;
+            }
+        }
+    }
+    ranges
+}
+
+/// Maps each line in `text` to the same line in `parent_text`, or to `None` if
+/// it was modified.
+pub fn map_lines(text: &[u8], parent_text: &[u8]) -> Vec<Option<usize>> {
+    let mut mapping = vec![];
+    let mut parent_line = 0;
+    for (parent_range, range) in changed_line_ranges(parent_text, text) {
+        while mapping.len() < range.start {
+            mapping.push(Some(parent_line));
+            parent_line += 1;
+        }
+        mapping.resize(range.end, None);
+        parent_line = parent_range.end;
+    }
+    let num_lines = find_line_ranges(text).len();
+    while mapping.len() < num_lines {
+        mapping.push(Some(parent_line));
+        parent_line += 1;
+    }
+    mapping
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1188,4 +1235,27 @@ int main(int argc, char **argv)
             ]
         );
     }
+
+    #[test]
+    fn test_changed_line_ranges() {
+        assert_eq!(changed_line_ranges(b"a\nb\nc\n", b"a\nb\nc\n"), vec![]);
+        assert_eq!(
+            changed_line_ranges(b"a\nb\nc\n", b"a\nB\nc\nd\n"),
+            vec![(1..2, 1..2), (3..3, 3..4)]
+        );
+        assert_eq!(
+            changed_line_ranges(b"a\nb\nc\n", b"c\n"),
+            vec![(0..2, 0..0)]
+        );
+    }
+
+    #[test]
+    fn test_map_lines() {
+        assert_eq!(
+            map_lines(b"a\nB\nc\nd\n", b"a\nb\nc\n"),
+            vec![Some(0), None, Some(2), None]
+        );
+        assert_eq!(map_lines(b"c\n", b"a\nb\nc\n"), vec![Some(2)]);
+        assert_eq!(map_lines(b"a\nb\n", b""), vec![None, None]);
+    }
 }
@@ -0,0 +1,656 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Functional language for selecting a set of paths.
+
+#![allow(missing_docs)]
+
+use std::path::{self, Path};
+use std::{error, fmt};
+
+use itertools::Itertools as _;
+use once_cell::sync::Lazy;
+use pest::iterators::{Pair, Pairs};
+use pest::pratt_parser::{Assoc, Op, PrattParser};
+use pest::Parser;
+use pest_derive::Parser;
+use thiserror::Error;
+
+use crate::matchers::{
+    DifferenceMatcher, EverythingMatcher, FileGlobsMatcher, FilesMatcher, IntersectionMatcher,
+    Matcher, NothingMatcher, PrefixMatcher, UnionMatcher,
+};
+use crate::repo_path::{FsPathParseError, RepoPath};
+
+#[derive(Parser)]
+#[grammar = "fileset.pest"]
+struct FilesetParser;
+
+impl Rule {
+    fn to_symbol(self) -> Option<&'static str> {
+        match self {
+            Rule::EOI => None,
+            Rule::whitespace => None,
+            Rule::identifier => None,
+            Rule::strict_identifier_part => None,
+            Rule::strict_identifier => None,
+            Rule::literal_string => None,
+            Rule::bare_string => None,
+            Rule::pattern_kind_op => Some(":"),
+            Rule::negate_op => Some("~"),
+            Rule::union_op => Some("|"),
+            Rule::intersection_op => Some("&"),
+            Rule::difference_op => Some("~"),
+            Rule::infix_op => None,
+            Rule::function_name => None,
+            Rule::function => None,
+            Rule::string_pattern => None,
+            Rule::primary => None,
+            Rule::expression => None,
+            Rule::program => None,
+        }
+    }
+}
+
+/// Workspace information needed to resolve paths in a fileset expression.
+#[derive(Clone, Copy, Debug)]
+pub struct FilesetParseContext<'a> {
+    pub cwd: &'a Path,
+    pub workspace_root: &'a Path,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct FilesetParseError {
+    kind: FilesetParseErrorKind,
+    pest_error: Box<pest::error::Error<Rule>>,
+}
+
+#[derive(Debug, Eq, Error, PartialEq)]
+pub enum FilesetParseErrorKind {
+    #[error("Syntax error")]
+    SyntaxError,
+    #[error(r#"Invalid file pattern kind "{0}:""#)]
+    InvalidKind(String),
+    #[error(r#"Function "{0}" doesn't exist"#)]
+    NoSuchFunction(String),
+    #[error(transparent)]
+    FsPath(#[from] FsPathParseError),
+    #[error("Failed to compile glob: {0}")]
+    GlobPattern(String),
+}
+
+impl FilesetParseError {
+    fn with_span(kind: FilesetParseErrorKind, span: pest::Span<'_>) -> Self {
+        let err = pest::error::Error::new_from_span(
+            pest::error::ErrorVariant::CustomError {
+                message: kind.to_string(),
+            },
+            span,
+        );
+        FilesetParseError {
+            kind,
+            pest_error: Box::new(err),
+        }
+    }
+
+    pub fn kind(&self) -> &FilesetParseErrorKind {
+        &self.kind
+    }
+}
+
+impl From<pest::error::Error<Rule>> for FilesetParseError {
+    fn from(err: pest::error::Error<Rule>) -> Self {
+        FilesetParseError {
+            kind: FilesetParseErrorKind::SyntaxError,
+            pest_error: Box::new(rename_rules_in_pest_error(err)),
+        }
+    }
+}
+
+impl fmt::Display for FilesetParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.pest_error.fmt(f)
+    }
+}
+
+impl error::Error for FilesetParseError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match &self.kind {
+            // SyntaxError is a wrapper for pest::error::Error.
+            FilesetParseErrorKind::SyntaxError => Some(&self.pest_error as &dyn error::Error),
+            // Otherwise the kind represents this error.
+            e => e.source(),
+        }
+    }
+}
+
+fn rename_rules_in_pest_error(err: pest::error::Error<Rule>) -> pest::error::Error<Rule> {
+    err.renamed_rules(|rule| {
+        rule.to_symbol()
+            .map(|sym| format!("`{sym}`"))
+            .unwrap_or_else(|| format!("<{rule:?}>"))
+    })
+}
+
+/// Basic pattern to match `RepoPath`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FilePattern {
+    /// Matches file (or exact) path.
+    FilePath(RepoPath),
+    /// Matches path prefix.
+    PrefixPath(RepoPath),
+    /// Matches file paths under the directory by glob pattern.
+    FileGlob {
+        dir: RepoPath,
+        pattern: glob::Pattern,
+    },
+}
+
+/// AST-level representation of the fileset expression.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FilesetExpression {
+    /// Matches nothing.
+    None,
+    /// Matches everything.
+    All,
+    /// Matches basic pattern.
+    Pattern(FilePattern),
+    /// Matches any of the expressions.
+    UnionAll(Vec<FilesetExpression>),
+    /// Matches both expressions.
+    Intersection(Box<FilesetExpression>, Box<FilesetExpression>),
+    /// Matches the first expression, but not the second expression.
+    Difference(Box<FilesetExpression>, Box<FilesetExpression>),
+}
+
+impl FilesetExpression {
+    pub fn none() -> Self {
+        FilesetExpression::None
+    }
+
+    pub fn all() -> Self {
+        FilesetExpression::All
+    }
+
+    pub fn pattern(pattern: FilePattern) -> Self {
+        FilesetExpression::Pattern(pattern)
+    }
+
+    pub fn file_path(path: RepoPath) -> Self {
+        FilesetExpression::Pattern(FilePattern::FilePath(path))
+    }
+
+    pub fn prefix_path(path: RepoPath) -> Self {
+        FilesetExpression::Pattern(FilePattern::PrefixPath(path))
+    }
+
+    /// Expression that matches any of the given `expressions`.
+    pub fn union_all(expressions: Vec<FilesetExpression>) -> Self {
+        match expressions.len() {
+            0 => FilesetExpression::none(),
+            1 => expressions.into_iter().next().unwrap(),
+            _ => FilesetExpression::UnionAll(expressions),
+        }
+    }
+
+    pub fn intersection(self, other: Self) -> Self {
+        FilesetExpression::Intersection(Box::new(self), Box::new(other))
+    }
+
+    pub fn difference(self, other: Self) -> Self {
+        FilesetExpression::Difference(Box::new(self), Box::new(other))
+    }
+
+    /// Transforms the expression tree to `Matcher` object.
+    pub fn to_matcher(&self) -> Box<dyn Matcher> {
+        build_union_matcher(self.as_union_all())
+    }
+
+    /// Flattens union expression at most one level.
+    fn as_union_all(&self) -> &[Self] {
+        match self {
+            FilesetExpression::UnionAll(exprs) => exprs,
+            _ => std::slice::from_ref(self),
+        }
+    }
+}
+
+fn build_union_matcher(expressions: &[FilesetExpression]) -> Box<dyn Matcher> {
+    let mut file_paths = Vec::new();
+    let mut prefix_paths = Vec::new();
+    let mut file_globs = Vec::new();
+    let mut matchers: Vec<Option<Box<dyn Matcher>>> = Vec::new();
+    for expr in expressions {
+        let matcher: Box<dyn Matcher> = match expr {
+            // None and All are supposed to be simplified by caller.
+            FilesetExpression::None => Box::new(NothingMatcher),
+            FilesetExpression::All => Box::new(EverythingMatcher),
+            FilesetExpression::Pattern(pattern) => {
+                match pattern {
+                    FilePattern::FilePath(path) => file_paths.push(path.clone()),
+                    FilePattern::PrefixPath(path) => prefix_paths.push(path.clone()),
+                    FilePattern::FileGlob { dir, pattern } => {
+                        file_globs.push((dir.clone(), pattern.clone()));
+                    }
+                }
+                continue;
+            }
+            // UnionAll is supposed to be flattened by caller.
+            FilesetExpression::UnionAll(exprs) => build_union_matcher(exprs),
+            FilesetExpression::Intersection(expr1, expr2) => {
+                let m1 = expr1.to_matcher();
+                let m2 = expr2.to_matcher();
+                Box::new(IntersectionMatcher::new(m1, m2))
+            }
+            FilesetExpression::Difference(expr1, expr2) => {
+                let m1 = expr1.to_matcher();
+                let m2 = expr2.to_matcher();
+                Box::new(DifferenceMatcher::new(m1, m2))
+            }
+        };
+        matchers.push(Some(matcher));
+    }
+
+    if !file_paths.is_empty() {
+        let matcher = FilesMatcher::new(&file_paths);
+        matchers.insert(0, Some(Box::new(matcher)));
+    }
+    if !prefix_paths.is_empty() {
+        let matcher = PrefixMatcher::new(&prefix_paths);
+        matchers.insert(0, Some(Box::new(matcher)));
+    }
+    if !file_globs.is_empty() {
+        let matcher = FileGlobsMatcher::new(&file_globs);
+        matchers.insert(0, Some(Box::new(matcher)));
+    }
+
+    union_all_matchers(&mut matchers)
+}
+
+/// Concatenates all `matchers` as union.
+///
+/// Each matcher element must be wrapped in `Some` so the matchers can be moved
+/// in arbitrary order.
+fn union_all_matchers(matchers: &mut [Option<Box<dyn Matcher>>]) -> Box<dyn Matcher> {
+    match matchers {
+        [] => Box::new(NothingMatcher),
+        [matcher] => matcher.take().expect("matcher should still be available"),
+        _ => {
+            // Build balanced tree to minimize the recursion depth.
+            let (left, right) = matchers.split_at_mut(matchers.len() / 2);
+            let m1 = union_all_matchers(left);
+            let m2 = union_all_matchers(right);
+            Box::new(UnionMatcher::new(m1, m2))
+        }
+    }
+}
+
+fn parse_expression_rule(
+    pairs: Pairs<Rule>,
+    ctx: &FilesetParseContext,
+) -> Result<FilesetExpression, FilesetParseError> {
+    static PRATT: Lazy<PrattParser<Rule>> = Lazy::new(|| {
+        PrattParser::new()
+            .op(Op::infix(Rule::union_op, Assoc::Left))
+            .op(Op::infix(Rule::intersection_op, Assoc::Left)
+                | Op::infix(Rule::difference_op, Assoc::Left))
+            .op(Op::prefix(Rule::negate_op))
+    });
+    PRATT
+        .map_primary(|primary| parse_primary_rule(primary, ctx))
+        .map_prefix(|op, rhs| match op.as_rule() {
+            Rule::negate_op => Ok(FilesetExpression::all().difference(rhs?)),
+            r => panic!("unexpected prefix operator rule {r:?}"),
+        })
+        .map_infix(|lhs, op, rhs| match op.as_rule() {
+            Rule::union_op => {
+                let mut exprs = match lhs? {
+                    FilesetExpression::UnionAll(exprs) => exprs,
+                    expr => vec![expr],
+                };
+                exprs.push(rhs?);
+                Ok(FilesetExpression::union_all(exprs))
+            }
+            Rule::intersection_op => Ok(lhs?.intersection(rhs?)),
+            Rule::difference_op => Ok(lhs?.difference(rhs?)),
+            r => panic!("unexpected infix operator rule {r:?}"),
+        })
+        .parse(pairs)
+}
+
+fn parse_primary_rule(
+    pair: Pair<Rule>,
+    ctx: &FilesetParseContext,
+) -> Result<FilesetExpression, FilesetParseError> {
+    let first = pair.into_inner().next().unwrap();
+    match first.as_rule() {
+        Rule::expression => parse_expression_rule(first.into_inner(), ctx),
+        Rule::function => {
+            let name_pair = first.into_inner().next().unwrap();
+            match name_pair.as_str() {
+                "all" => Ok(FilesetExpression::all()),
+                "none" => Ok(FilesetExpression::none()),
+                name => Err(FilesetParseError::with_span(
+                    FilesetParseErrorKind::NoSuchFunction(name.to_owned()),
+                    name_pair.as_span(),
+                )),
+            }
+        }
+        Rule::string_pattern => {
+            let (kind_pair, _op, value_pair) = first.into_inner().collect_tuple().unwrap();
+            let kind = kind_pair.as_str();
+            let value = parse_bare_string(value_pair.clone());
+            let pattern = parse_file_pattern(kind, &value, ctx).map_err(|kind_err| {
+                let span = match &kind_err {
+                    FilesetParseErrorKind::InvalidKind(_) => kind_pair.as_span(),
+                    _ => value_pair.as_span(),
+                };
+                FilesetParseError::with_span(kind_err, span)
+            })?;
+            Ok(FilesetExpression::pattern(pattern))
+        }
+        Rule::bare_string => {
+            let span = first.as_span();
+            let value = parse_bare_string(first);
+            let path = RepoPath::parse_fs_path(ctx.cwd, ctx.workspace_root, value)
+                .map_err(|err| FilesetParseError::with_span(err.into(), span))?;
+            Ok(FilesetExpression::prefix_path(path))
+        }
+        r => panic!("unexpected primary rule {r:?}"),
+    }
+}
+
+fn parse_bare_string(pair: Pair<Rule>) -> String {
+    let content = pair.into_inner().next().unwrap();
+    match content.as_rule() {
+        Rule::identifier => content.as_str().to_owned(),
+        Rule::literal_string => {
+            let s = content.as_str();
+            s[1..s.len() - 1].to_owned()
+        }
+        r => panic!("unexpected string rule {r:?}"),
+    }
+}
+
+/// Parses `value` of the given pattern `kind`.
+fn parse_file_pattern(
+    kind: &str,
+    value: &str,
+    ctx: &FilesetParseContext,
+) -> Result<FilePattern, FilesetParseErrorKind> {
+    let root = ctx.workspace_root;
+    let pattern = match kind {
+        "cwd" => FilePattern::PrefixPath(RepoPath::parse_fs_path(ctx.cwd, root, value)?),
+        "file" => FilePattern::FilePath(RepoPath::parse_fs_path(ctx.cwd, root, value)?),
+        "glob" => parse_file_glob(ctx.cwd, root, value)?,
+        "root" => FilePattern::PrefixPath(RepoPath::parse_fs_path(root, root, value)?),
+        "root-file" => FilePattern::FilePath(RepoPath::parse_fs_path(root, root, value)?),
+        "root-glob" => parse_file_glob(root, root, value)?,
+        _ => return Err(FilesetParseErrorKind::InvalidKind(kind.to_owned())),
+    };
+    Ok(pattern)
+}
+
+/// Splits the leading literal directory components off the glob `input`, and
+/// resolves them relative to `cwd`.
+fn parse_file_glob(
+    cwd: &Path,
+    workspace_root: &Path,
+    input: &str,
+) -> Result<FilePattern, FilesetParseErrorKind> {
+    let Some(meta_pos) = input.find(['*', '?', '[']) else {
+        // No need to match by glob
+        let path = RepoPath::parse_fs_path(cwd, workspace_root, input)?;
+        return Ok(FilePattern::FilePath(path));
+    };
+    let (dir_str, pattern_str) = match input[..meta_pos].rfind(path::is_separator) {
+        Some(pos) => (&input[..pos], &input[pos + 1..]),
+        None => ("", input),
+    };
+    let dir = RepoPath::parse_fs_path(cwd, workspace_root, dir_str)?;
+    let pattern = glob::Pattern::new(pattern_str)
+        .map_err(|err| FilesetParseErrorKind::GlobPattern(err.to_string()))?;
+    Ok(FilePattern::FileGlob { dir, pattern })
+}
+
+/// Parses text into fileset expression.
+pub fn parse(
+    text: &str,
+    ctx: &FilesetParseContext,
+) -> Result<FilesetExpression, FilesetParseError> {
+    let mut pairs = FilesetParser::parse(Rule::program, text)?;
+    let first = pairs.next().unwrap();
+    parse_expression_rule(first.into_inner(), ctx)
+}
+
+/// Parses text into fileset expression, falling back to a plain path.
+///
+/// If the text can't be parsed as a fileset expression, it is resolved as a
+/// path prefix relative to the current directory. This keeps file names
+/// containing whitespace or other special characters usable without quoting.
+pub fn parse_maybe_bare(
+    text: &str,
+    ctx: &FilesetParseContext,
+) -> Result<FilesetExpression, FilesetParseError> {
+    match parse(text, ctx) {
+        Ok(expression) => Ok(expression),
+        Err(err) if matches!(err.kind(), FilesetParseErrorKind::SyntaxError) => {
+            parse_prefix_path(text, ctx)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Parses text as a plain path, which matches the file or directory and its
+/// descendants.
+///
+/// This is how file arguments were interpreted before fileset expressions were
+/// introduced, and is still used if `ui.allow-filesets` is disabled.
+pub fn parse_prefix_path(
+    text: &str,
+    ctx: &FilesetParseContext,
+) -> Result<FilesetExpression, FilesetParseError> {
+    let path = RepoPath::parse_fs_path(ctx.cwd, ctx.workspace_root, text).map_err(|err| {
+        let span = pest::Span::new(text, 0, text.len()).unwrap();
+        FilesetParseError::with_span(err.into(), span)
+    })?;
+    Ok(FilesetExpression::prefix_path(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    fn repo_path(value: &str) -> RepoPath {
+        RepoPath::from_internal_string(value)
+    }
+
+    fn glob(dir: &str, pattern: &str) -> FilesetExpression {
+        FilesetExpression::pattern(FilePattern::FileGlob {
+            dir: repo_path(dir),
+            pattern: glob::Pattern::new(pattern).unwrap(),
+        })
+    }
+
+    fn with_context<T>(cwd: &str, f: impl FnOnce(&FilesetParseContext) -> T) -> T {
+        let workspace_root = PathBuf::from(if cfg!(windows) { r"C:\ws" } else { "/ws" });
+        let cwd = workspace_root.join(cwd);
+        f(&FilesetParseContext {
+            cwd: &cwd,
+            workspace_root: &workspace_root,
+        })
+    }
+
+    #[test]
+    fn test_parse_file_pattern() {
+        with_context("sub", |ctx| {
+            let parse = |text| parse(text, ctx);
+            assert_eq!(
+                parse("foo").unwrap(),
+                FilesetExpression::prefix_path(repo_path("sub/foo"))
+            );
+            assert_eq!(
+                parse("cwd:foo").unwrap(),
+                FilesetExpression::prefix_path(repo_path("sub/foo"))
+            );
+            assert_eq!(
+                parse(r#""foo bar""#).unwrap(),
+                FilesetExpression::prefix_path(repo_path("sub/foo bar"))
+            );
+            assert_eq!(
+                parse("../foo").unwrap(),
+                FilesetExpression::prefix_path(repo_path("foo"))
+            );
+            assert_eq!(
+                parse("root:foo").unwrap(),
+                FilesetExpression::prefix_path(repo_path("foo"))
+            );
+            assert_eq!(
+                parse("file:foo").unwrap(),
+                FilesetExpression::file_path(repo_path("sub/foo"))
+            );
+            assert_eq!(
+                parse("root-file:foo").unwrap(),
+                FilesetExpression::file_path(repo_path("foo"))
+            );
+            assert_eq!(parse(r#"glob:"*.rs""#).unwrap(), glob("sub", "*.rs"));
+            assert_eq!(
+                parse("glob:../src/**/*.rs").unwrap(),
+                glob("src", "**/*.rs")
+            );
+            assert_eq!(parse("root-glob:*.rs").unwrap(), glob("", "*.rs"));
+            assert_eq!(
+                parse("glob:foo").unwrap(),
+                FilesetExpression::file_path(repo_path("sub/foo"))
+            );
+
+            assert_matches!(
+                parse("bad:foo").unwrap_err().kind(),
+                FilesetParseErrorKind::InvalidKind(kind) if kind == "bad"
+            );
+            assert_matches!(
+                parse("root:../foo").unwrap_err().kind(),
+                FilesetParseErrorKind::FsPath(_)
+            );
+            assert_matches!(
+                parse("glob:[").unwrap_err().kind(),
+                FilesetParseErrorKind::GlobPattern(_)
+            );
+        });
+    }
+
+    #[test]
+    fn test_parse_operators() {
+        with_context("", |ctx| {
+            let parse = |text| parse(text, ctx);
+            let foo = || FilesetExpression::prefix_path(repo_path("foo"));
+            let bar = || FilesetExpression::prefix_path(repo_path("bar"));
+            let baz = || FilesetExpression::prefix_path(repo_path("baz"));
+            assert_eq!(
+                parse("foo | bar|baz").unwrap(),
+                FilesetExpression::union_all(vec![foo(), bar(), baz()])
+            );
+            assert_eq!(parse("foo ~ bar").unwrap(), foo().difference(bar()));
+            assert_eq!(
+                parse("~foo").unwrap(),
+                FilesetExpression::all().difference(foo())
+            );
+            assert_eq!(
+                parse("foo | bar & baz").unwrap(),
+                FilesetExpression::union_all(vec![foo(), bar().intersection(baz())])
+            );
+            assert_eq!(
+                parse("(foo | bar) & baz").unwrap(),
+                FilesetExpression::union_all(vec![foo(), bar()]).intersection(baz())
+            );
+            assert_eq!(parse("all()").unwrap(), FilesetExpression::all());
+            assert_eq!(parse("none()").unwrap(), FilesetExpression::none());
+            assert_matches!(
+                parse("bad()").unwrap_err().kind(),
+                FilesetParseErrorKind::NoSuchFunction(name) if name == "bad"
+            );
+            assert_matches!(
+                parse("foo |").unwrap_err().kind(),
+                FilesetParseErrorKind::SyntaxError
+            );
+        });
+    }
+
+    #[test]
+    fn test_parse_maybe_bare() {
+        with_context("", |ctx| {
+            let parse = |text| parse_maybe_bare(text, ctx);
+            assert_eq!(
+                parse("foo bar").unwrap(),
+                FilesetExpression::prefix_path(repo_path("foo bar"))
+            );
+            assert_eq!(
+                parse("foo (bar)").unwrap(),
+                FilesetExpression::prefix_path(repo_path("foo (bar)"))
+            );
+            assert_eq!(
+                parse("foo ~ bar").unwrap(),
+                FilesetExpression::prefix_path(repo_path("foo"))
+                    .difference(FilesetExpression::prefix_path(repo_path("bar")))
+            );
+            assert_matches!(
+                parse("../foo bar").unwrap_err().kind(),
+                FilesetParseErrorKind::FsPath(_)
+            );
+        });
+    }
+
+    #[test]
+    fn test_parse_prefix_path() {
+        with_context("", |ctx| {
+            let parse = |text| parse_prefix_path(text, ctx);
+            assert_eq!(
+                parse("foo ~ bar").unwrap(),
+                FilesetExpression::prefix_path(repo_path("foo ~ bar"))
+            );
+            assert_eq!(
+                parse("glob:*.rs").unwrap(),
+                FilesetExpression::prefix_path(repo_path("glob:*.rs"))
+            );
+            assert_matches!(
+                parse("../foo").unwrap_err().kind(),
+                FilesetParseErrorKind::FsPath(_)
+            );
+        });
+    }
+
+    #[test]
+    fn test_to_matcher() {
+        with_context("", |ctx| {
+            let matcher = parse("src ~ glob:src/**/*.pb.rs | file:README", ctx)
+                .unwrap()
+                .to_matcher();
+            assert!(matcher.matches(&repo_path("src/lib.rs")));
+            assert!(matcher.matches(&repo_path("src/proto/foo.rs")));
+            assert!(!matcher.matches(&repo_path("src/proto/foo.pb.rs")));
+            assert!(!matcher.matches(&repo_path("src/foo.pb.rs")));
+            assert!(matcher.matches(&repo_path("README")));
+            assert!(!matcher.matches(&repo_path("README/foo")));
+            assert!(!matcher.matches(&repo_path("docs/index.md")));
+
+            let matcher = parse("~src", ctx).unwrap().to_matcher();
+            assert!(!matcher.matches(&repo_path("src/lib.rs")));
+            assert!(matcher.matches(&repo_path("docs/index.md")));
+        });
+    }
+}
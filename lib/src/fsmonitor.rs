@@ -28,6 +28,9 @@ pub enum FsmonitorKind {
     /// The Watchman filesystem monitor (https://facebook.github.io/watchman/).
     Watchman,
 
+    /// The built-in inotify-based filesystem monitor. Only supported on Linux.
+    Inotify,
+
     /// Only used in tests.
     Test {
         /// The set of changed files to pretend that the filesystem monitor is
@@ -42,6 +45,7 @@ impl FromStr for FsmonitorKind {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "watchman" => Ok(Self::Watchman),
+            "inotify" => Ok(Self::Inotify),
             "test" => Err(config::ConfigError::Message(
                 "cannot use test fsmonitor in real repository".to_string(),
             )),
@@ -215,3 +219,567 @@ pub mod watchman {
         }
     }
 }
+
+/// Built-in filesystem monitor based on inotify. Only available on Linux.
+///
+/// A long-lived daemon watches every directory of the working copy and
+/// remembers which paths changed. Clients connect to it over a Unix socket and
+/// ask for the paths that changed since a clock previously returned by the
+/// daemon.
+#[cfg(target_os = "linux")]
+pub mod inotify {
+    use std::collections::HashMap;
+    use std::ffi::OsStr;
+    use std::fs;
+    use std::io::{self, BufRead, BufReader, Read, Write};
+    use std::os::fd::{AsFd, OwnedFd};
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, SystemTime};
+
+    use itertools::Itertools;
+    use rustix::event::{poll, PollFd, PollFlags};
+    use rustix::fs::inotify::{self, CreateFlags, WatchFlags};
+    use rustix::io::Errno;
+    use thiserror::Error;
+    use tracing::{info, instrument, warn};
+
+    /// Name of the daemon's socket file in the working copy state directory.
+    pub const SOCKET_FILE_NAME: &str = "fsmonitor.sock";
+
+    // Event bits that are only ever reported, never requested
+    const IN_Q_OVERFLOW: u32 = 0x0000_4000;
+    const IN_IGNORED: u32 = 0x0000_8000;
+    const IN_ISDIR: u32 = 0x4000_0000;
+
+    const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Maximum number of changed paths remembered by the daemon. Once it's
+    /// reached, the recorded changes are forgotten and clients fall back to
+    /// crawling the working copy.
+    const MAX_CHANGED_PATHS: usize = 100_000;
+
+    /// Size of `sun_path` in `struct sockaddr_un`, including the terminating
+    /// NUL.
+    const SUN_PATH_LEN: usize = 108;
+
+    #[allow(missing_docs)]
+    #[derive(Debug, Error)]
+    pub enum Error {
+        #[error("The filesystem monitor is not running")]
+        NotRunning,
+
+        #[error("The filesystem monitor is already running")]
+        AlreadyRunning,
+
+        #[error("Failed to communicate with the filesystem monitor: {0}")]
+        Io(#[source] io::Error),
+
+        #[error("Unexpected response from the filesystem monitor: {0:?}")]
+        InvalidResponse(String),
+
+        #[error("Failed to watch {path}: {source}")]
+        Watch {
+            path: PathBuf,
+            #[source]
+            source: io::Error,
+        },
+
+        #[error(
+            "Ran out of inotify watches while watching {0} (consider raising \
+             fs.inotify.max_user_watches)"
+        )]
+        TooManyWatches(PathBuf),
+
+        #[error("Socket path {0} is too long (must be shorter than {SUN_PATH_LEN} bytes)")]
+        SocketPathTooLong(PathBuf),
+
+        #[error("Failed to listen on {path}: {source}")]
+        Listen {
+            path: PathBuf,
+            #[source]
+            source: io::Error,
+        },
+    }
+
+    /// Information about a running filesystem monitor.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Status {
+        /// Process id of the daemon.
+        pub pid: u32,
+        /// Number of directories the daemon is watching.
+        pub num_watches: usize,
+    }
+
+    /// Query for changed files since the previous point in time.
+    ///
+    /// Returns the clock to pass to the next query along with the changed
+    /// paths, relative to the working copy root. If the paths are `None`, then
+    /// the caller must crawl the entire working copy themselves.
+    #[instrument]
+    pub fn query_changed_files(
+        socket_path: &Path,
+        previous_clock: Option<&str>,
+    ) -> Result<(String, Option<Vec<PathBuf>>), Error> {
+        info!("Querying inotify filesystem monitor for changed files...");
+        let request = match previous_clock {
+            None => "query".to_string(),
+            Some(clock) => format!("query {clock}"),
+        };
+        let response = send_request(socket_path, &request)?;
+        let (header, paths) = match response.iter().position(|&b| b == b'\n') {
+            Some(pos) => (&response[..pos], &response[pos + 1..]),
+            None => return Err(invalid_response(&response)),
+        };
+        let header = std::str::from_utf8(header).map_err(|_| invalid_response(&response))?;
+        match header.split_once(' ') {
+            Some((clock, "fresh")) => Ok((clock.to_string(), None)),
+            Some((clock, "changed")) => {
+                let paths = paths
+                    .split(|&b| b == 0)
+                    .filter(|path| !path.is_empty())
+                    .map(|path| PathBuf::from(OsStr::from_bytes(path)))
+                    .collect_vec();
+                Ok((clock.to_string(), Some(paths)))
+            }
+            _ => Err(invalid_response(&response)),
+        }
+    }
+
+    /// Asks the filesystem monitor listening on `socket_path` about its state.
+    pub fn query_status(socket_path: &Path) -> Result<Status, Error> {
+        let response = send_request(socket_path, "status")?;
+        let text = String::from_utf8_lossy(&response);
+        let mut pid = None;
+        let mut num_watches = None;
+        for line in text.lines() {
+            match line.split_once(' ') {
+                Some(("pid", value)) => pid = value.parse().ok(),
+                Some(("watches", value)) => num_watches = value.parse().ok(),
+                _ => {}
+            }
+        }
+        match (pid, num_watches) {
+            (Some(pid), Some(num_watches)) => Ok(Status { pid, num_watches }),
+            _ => Err(invalid_response(&response)),
+        }
+    }
+
+    /// Asks the filesystem monitor listening on `socket_path` to exit.
+    pub fn stop(socket_path: &Path) -> Result<(), Error> {
+        let response = send_request(socket_path, "stop")?;
+        if response == b"ok\n" {
+            Ok(())
+        } else {
+            Err(invalid_response(&response))
+        }
+    }
+
+    fn check_socket_path(socket_path: &Path) -> Result<(), Error> {
+        if socket_path.as_os_str().len() >= SUN_PATH_LEN {
+            Err(Error::SocketPathTooLong(socket_path.to_path_buf()))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn send_request(socket_path: &Path, request: &str) -> Result<Vec<u8>, Error> {
+        check_socket_path(socket_path)?;
+        let mut stream = match UnixStream::connect(socket_path) {
+            Ok(stream) => stream,
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+                ) =>
+            {
+                return Err(Error::NotRunning);
+            }
+            Err(err) => return Err(Error::Io(err)),
+        };
+        stream
+            .set_read_timeout(Some(IO_TIMEOUT))
+            .map_err(Error::Io)?;
+        stream
+            .set_write_timeout(Some(IO_TIMEOUT))
+            .map_err(Error::Io)?;
+        stream
+            .write_all(format!("{request}\n").as_bytes())
+            .map_err(Error::Io)?;
+        let mut response = vec![];
+        stream.read_to_end(&mut response).map_err(Error::Io)?;
+        Ok(response)
+    }
+
+    fn invalid_response(response: &[u8]) -> Error {
+        Error::InvalidResponse(String::from_utf8_lossy(response).into_owned())
+    }
+
+    /// The filesystem monitor daemon for a single working copy.
+    pub struct Daemon {
+        watcher: Watcher,
+        listener: UnixListener,
+        socket_path: PathBuf,
+    }
+
+    impl Daemon {
+        /// Starts watching the working copy at `root`. Queries are accepted on
+        /// `socket_path` once all directories are being watched.
+        #[instrument]
+        pub fn start(root: &Path, socket_path: &Path) -> Result<Self, Error> {
+            info!("Starting inotify filesystem monitor...");
+            check_socket_path(socket_path)?;
+            match UnixStream::connect(socket_path) {
+                Ok(_) => return Err(Error::AlreadyRunning),
+                // Left behind by a daemon that didn't exit cleanly
+                Err(err) if err.kind() == io::ErrorKind::ConnectionRefused => {
+                    fs::remove_file(socket_path).ok();
+                }
+                Err(_) => {}
+            }
+            let mut watcher = Watcher::new(root)?;
+            watcher.watch_recursively(Path::new(""), false)?;
+            let listener = UnixListener::bind(socket_path).map_err(|source| Error::Listen {
+                path: socket_path.to_path_buf(),
+                source,
+            })?;
+            info!(
+                num_watches = watcher.dirs_by_wd.len(),
+                "Watching working copy"
+            );
+            Ok(Daemon {
+                watcher,
+                listener,
+                socket_path: socket_path.to_path_buf(),
+            })
+        }
+
+        /// Serves queries until asked to stop or until the working copy root
+        /// is deleted or moved.
+        pub fn run(mut self) -> Result<(), Error> {
+            loop {
+                let (has_events, has_connection) = {
+                    let mut fds = [
+                        PollFd::new(&self.watcher.inotify, PollFlags::IN),
+                        PollFd::new(&self.listener, PollFlags::IN),
+                    ];
+                    match poll(&mut fds, -1) {
+                        Ok(_) => {}
+                        Err(Errno::INTR) => continue,
+                        Err(err) => return Err(Error::Io(err.into())),
+                    }
+                    (!fds[0].revents().is_empty(), !fds[1].revents().is_empty())
+                };
+                if has_events {
+                    self.watcher.process_events();
+                }
+                if !self.watcher.is_root_watched() {
+                    info!("Working copy root was removed, exiting");
+                    break;
+                }
+                if has_connection {
+                    match self.listener.accept() {
+                        Ok((stream, _)) => {
+                            if self.handle_connection(stream) {
+                                info!("Asked to stop, exiting");
+                                break;
+                            }
+                        }
+                        Err(err) => warn!(?err, "Failed to accept connection"),
+                    }
+                }
+            }
+            fs::remove_file(&self.socket_path).ok();
+            Ok(())
+        }
+
+        /// Answers a single request. Returns true if the daemon should exit.
+        fn handle_connection(&mut self, stream: UnixStream) -> bool {
+            let mut stop = false;
+            let result = (|| -> io::Result<()> {
+                stream.set_read_timeout(Some(IO_TIMEOUT))?;
+                stream.set_write_timeout(Some(IO_TIMEOUT))?;
+                let mut request = String::new();
+                BufReader::new(&stream).read_line(&mut request)?;
+                let response = match request.trim_end().split_once(' ') {
+                    None if request.trim_end() == "query" => self.query(None),
+                    Some(("query", clock)) => self.query(Some(clock)),
+                    None if request.trim_end() == "status" => format!(
+                        "pid {}\nwatches {}\n",
+                        std::process::id(),
+                        self.watcher.dirs_by_wd.len()
+                    )
+                    .into_bytes(),
+                    None if request.trim_end() == "stop" => {
+                        stop = true;
+                        b"ok\n".to_vec()
+                    }
+                    _ => b"error unknown request\n".to_vec(),
+                };
+                (&stream).write_all(&response)
+            })();
+            if let Err(err) = result {
+                warn!(?err, "Failed to answer request");
+            }
+            stop
+        }
+
+        fn query(&mut self, previous_clock: Option<&str>) -> Vec<u8> {
+            // Events for changes made before the request was sent are already
+            // queued, so the answer can't miss them.
+            self.watcher.process_events();
+            let watcher = &self.watcher;
+            let since = previous_clock
+                .and_then(|clock| clock.rsplit_once(':'))
+                .filter(|(instance, _)| *instance == watcher.instance)
+                .and_then(|(_, seq)| seq.parse::<u64>().ok())
+                .filter(|seq| *seq <= watcher.seq);
+            let mut response = format!("{}:{} ", watcher.instance, watcher.seq).into_bytes();
+            match since {
+                None => response.extend_from_slice(b"fresh\n"),
+                Some(since) => {
+                    response.extend_from_slice(b"changed\n");
+                    for (path, _) in watcher
+                        .changed_paths
+                        .iter()
+                        .filter(|(_, seq)| **seq > since)
+                    {
+                        response.extend_from_slice(path.as_os_str().as_bytes());
+                        response.push(0);
+                    }
+                }
+            }
+            response
+        }
+    }
+
+    struct Watcher {
+        root: PathBuf,
+        inotify: OwnedFd,
+        /// Directories relative to the root, by watch descriptor.
+        dirs_by_wd: HashMap<i32, PathBuf>,
+        /// Identifies the current set of recorded changes. Clocks from another
+        /// instance are answered with a fresh instance.
+        instance: String,
+        seq: u64,
+        /// The sequence number of the last change to each path.
+        changed_paths: HashMap<PathBuf, u64>,
+        max_changed_paths: usize,
+    }
+
+    impl Watcher {
+        fn new(root: &Path) -> Result<Self, Error> {
+            let inotify = inotify::inotify_init(CreateFlags::CLOEXEC | CreateFlags::NONBLOCK)
+                .map_err(|err| Error::Io(err.into()))?;
+            Ok(Watcher {
+                root: root.to_path_buf(),
+                inotify,
+                dirs_by_wd: HashMap::new(),
+                instance: new_instance_id(),
+                seq: 0,
+                changed_paths: HashMap::new(),
+                max_changed_paths: MAX_CHANGED_PATHS,
+            })
+        }
+
+        fn is_root_watched(&self) -> bool {
+            self.dirs_by_wd
+                .values()
+                .any(|dir| dir.as_os_str().is_empty())
+        }
+
+        fn watch_recursively(&mut self, dir: &Path, record_contents: bool) -> Result<(), Error> {
+            let watch_flags = WatchFlags::CREATE
+                | WatchFlags::DELETE
+                | WatchFlags::MODIFY
+                | WatchFlags::ATTRIB
+                | WatchFlags::MOVED_FROM
+                | WatchFlags::MOVED_TO
+                | WatchFlags::DELETE_SELF
+                | WatchFlags::MOVE_SELF
+                | WatchFlags::ONLYDIR
+                | WatchFlags::DONT_FOLLOW
+                | WatchFlags::EXCL_UNLINK;
+            let mut pending = vec![dir.to_path_buf()];
+            while let Some(dir) = pending.pop() {
+                let disk_dir = self.root.join(&dir);
+                let wd = match inotify::inotify_add_watch(
+                    self.inotify.as_fd(),
+                    disk_dir.as_path(),
+                    watch_flags,
+                ) {
+                    Ok(wd) => wd,
+                    // Removed (or replaced by a file) since we found it
+                    Err(Errno::NOENT | Errno::NOTDIR) => continue,
+                    Err(Errno::NOSPC) => return Err(Error::TooManyWatches(disk_dir)),
+                    Err(err) => {
+                        return Err(Error::Watch {
+                            path: disk_dir,
+                            source: err.into(),
+                        })
+                    }
+                };
+                self.dirs_by_wd.insert(wd, dir.clone());
+                let Ok(entries) = fs::read_dir(&disk_dir) else {
+                    continue;
+                };
+                for entry in entries.flatten() {
+                    let name = entry.file_name();
+                    if is_ignored_name(&name) {
+                        continue;
+                    }
+                    let path = dir.join(&name);
+                    if record_contents {
+                        self.record_change(path.clone());
+                    }
+                    if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                        pending.push(path);
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        fn unwatch_recursively(&mut self, dir: &Path) {
+            let wds = self
+                .dirs_by_wd
+                .iter()
+                .filter(|(_, path)| path.starts_with(dir))
+                .map(|(wd, _)| *wd)
+                .collect_vec();
+            for wd in wds {
+                self.dirs_by_wd.remove(&wd);
+                inotify::inotify_remove_watch(self.inotify.as_fd(), wd).ok();
+            }
+        }
+
+        fn record_change(&mut self, path: PathBuf) {
+            if self.changed_paths.len() >= self.max_changed_paths
+                && !self.changed_paths.contains_key(&path)
+            {
+                info!(
+                    max_changed_paths = self.max_changed_paths,
+                    "Too many changed paths, forgetting recorded changes"
+                );
+                self.reset();
+            }
+            self.seq += 1;
+            self.changed_paths.insert(path, self.seq);
+        }
+
+        /// Forgets all recorded changes, so the next query of every client is
+        /// answered with a fresh instance.
+        fn reset(&mut self) {
+            self.instance = new_instance_id();
+            self.changed_paths.clear();
+        }
+
+        fn process_events(&mut self) {
+            let mut buf = vec![0; 64 * 1024];
+            loop {
+                let len = match rustix::io::read(&self.inotify, &mut buf) {
+                    Ok(0) | Err(Errno::AGAIN) => break,
+                    Ok(len) => len,
+                    Err(Errno::INTR) => continue,
+                    Err(err) => {
+                        warn!(?err, "Failed to read inotify events");
+                        self.reset();
+                        break;
+                    }
+                };
+                // Each event is a `struct inotify_event` followed by the
+                // NUL-padded name of the affected directory entry.
+                let mut offset = 0;
+                while offset + 16 <= len {
+                    let field = |index: usize| {
+                        let start = offset + index * 4;
+                        u32::from_ne_bytes(buf[start..start + 4].try_into().unwrap())
+                    };
+                    let wd = field(0) as i32;
+                    let mask = field(1);
+                    let name_len = field(3) as usize;
+                    let name = &buf[offset + 16..offset + 16 + name_len];
+                    let name = name.split(|&b| b == 0).next().unwrap();
+                    offset += 16 + name_len;
+                    self.handle_event(wd, mask, OsStr::from_bytes(name));
+                }
+            }
+        }
+
+        fn handle_event(&mut self, wd: i32, mask: u32, name: &OsStr) {
+            if mask & IN_Q_OVERFLOW != 0 {
+                warn!("inotify event queue overflowed");
+                self.reset();
+                return;
+            }
+            if mask & IN_IGNORED != 0 {
+                self.dirs_by_wd.remove(&wd);
+                return;
+            }
+            let Some(dir) = self.dirs_by_wd.get(&wd) else {
+                return;
+            };
+            if name.is_empty() {
+                // Events about a watched directory itself are also reported as
+                // events about an entry in its parent, except for the root.
+                if dir.as_os_str().is_empty() && mask & WatchFlags::MOVE_SELF.bits() != 0 {
+                    self.dirs_by_wd.remove(&wd);
+                }
+                return;
+            }
+            if is_ignored_name(name) {
+                return;
+            }
+            let path = dir.join(name);
+            self.record_change(path.clone());
+            if mask & IN_ISDIR != 0 {
+                if mask & (WatchFlags::CREATE | WatchFlags::MOVED_TO).bits() != 0 {
+                    if let Err(err) = self.watch_recursively(&path, true) {
+                        warn!(?err, "Failed to watch new directory");
+                        self.reset();
+                    }
+                } else if mask & WatchFlags::MOVED_FROM.bits() != 0 {
+                    self.unwatch_recursively(&path);
+                }
+            }
+        }
+    }
+
+    fn is_ignored_name(name: &OsStr) -> bool {
+        name == ".jj" || name == ".git"
+    }
+
+    fn new_instance_id() -> String {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        format!("{}-{}", std::process::id(), now.as_nanos())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_record_change_over_limit() {
+            let temp_dir = testutils::new_temp_dir();
+            let mut watcher = Watcher::new(temp_dir.path()).unwrap();
+            watcher.max_changed_paths = 2;
+            watcher.record_change(PathBuf::from("a"));
+            watcher.record_change(PathBuf::from("b"));
+            // Paths that are already recorded don't count against the limit
+            watcher.record_change(PathBuf::from("a"));
+            let instance = watcher.instance.clone();
+            assert_eq!(watcher.changed_paths.len(), 2);
+
+            // Clients have to crawl the working copy once the limit is exceeded
+            watcher.record_change(PathBuf::from("c"));
+            assert_ne!(watcher.instance, instance);
+            assert_eq!(
+                watcher.changed_paths.keys().collect_vec(),
+                vec![Path::new("c")]
+            );
+        }
+    }
+}
@@ -25,8 +25,8 @@ use itertools::Itertools;
 use tempfile::NamedTempFile;
 use thiserror::Error;
 
-use crate::backend::{BackendError, CommitId, ObjectId};
-use crate::git_backend::GitBackend;
+use crate::backend::{BackendError, CommitId, ObjectId, Signature};
+use crate::git_backend::{signature_to_git, GitBackend};
 use crate::op_store::{BranchTarget, RefTarget, RefTargetOptionExt};
 use crate::repo::{MutableRepo, Repo};
 use crate::revset;
@@ -35,6 +35,9 @@ use crate::view::{RefName, View};
 
 /// Reserved remote name for the backing Git repo.
 pub const REMOTE_NAME_FOR_LOCAL_GIT_REPO: &str = "git";
+/// Namespace of refs to annotated tag objects that haven't been exported to
+/// `refs/tags/` yet.
+const PENDING_ANNOTATED_TAG_NAMESPACE: &str = "refs/jj/tags/";
 
 #[derive(Error, Debug)]
 pub enum GitImportError {
@@ -266,10 +269,17 @@ pub fn import_some_refs(
         if let RefName::RemoteBranch { branch, remote } = ref_name {
             // Remote-tracking branch is the last known state of the branch in the remote.
             // It shouldn't diverge even if we had inconsistent view.
+            // A newly-seen remote branch is tracked only if configured to do so.
+            let tracked = if mut_repo.get_remote_branch(branch, remote).is_absent() {
+                git_settings.auto_local_branch
+            } else {
+                mut_repo.is_remote_branch_tracked(branch, remote)
+            };
             mut_repo.set_remote_branch_target(branch, remote, new_git_target.clone());
-            // If a git remote-tracking branch changed, apply the change to the local branch
-            // as well.
-            if git_settings.auto_local_branch {
+            mut_repo.set_remote_branch_tracked(branch, remote, tracked);
+            // If a tracked git remote-tracking branch changed, apply the change to the local
+            // branch as well.
+            if tracked {
                 let local_ref_name = RefName::LocalBranch(branch.clone());
                 mut_repo.merge_single_ref(&local_ref_name, old_git_target, new_git_target);
             }
@@ -423,9 +433,9 @@ struct RefsToExport {
 /// repo compared to our last remembered view of the Git repo). These will be
 /// marked conflicted by the next `jj git import`.
 ///
-/// We do not export tags and other refs at the moment, since these aren't
-/// supposed to be modified by JJ. For them, the Git state is considered
-/// authoritative.
+/// Tags are exported the same way as branches. We do not export other refs
+/// (such as notes) since these aren't supposed to be modified by JJ. For them,
+/// the Git state is considered authoritative.
 pub fn export_refs(
     mut_repo: &mut MutableRepo,
     git_repo: &git2::Repository,
@@ -438,8 +448,9 @@ pub fn export_some_refs(
     git_repo: &git2::Repository,
     git_ref_filter: impl Fn(&RefName) -> bool,
 ) -> Result<Vec<FailedRefExport>, GitExportError> {
+    let annotated_tags = pending_annotated_tags(mut_repo.view(), git_repo, &git_ref_filter)?;
     let RefsToExport {
-        branches_to_update,
+        mut branches_to_update,
         branches_to_delete,
         mut failed_branches,
     } = diff_refs_to_export(
@@ -447,6 +458,18 @@ pub fn export_some_refs(
         mut_repo.store().root_commit_id(),
         git_ref_filter,
     );
+    // Annotating a tag without moving it doesn't change the view
+    for tag_name in annotated_tags.keys() {
+        let ref_name = RefName::Tag(tag_name.clone());
+        if branches_to_update.contains_key(&ref_name) {
+            continue;
+        }
+        let git_ref_name = to_git_ref_name(&ref_name).unwrap();
+        if let Some(id) = mut_repo.view().get_git_ref(&git_ref_name).as_normal() {
+            let oid = Oid::from_bytes(id.as_bytes()).unwrap();
+            branches_to_update.insert(ref_name, (Some(oid), oid));
+        }
+    }
 
     // TODO: Also check other worktrees' HEAD.
     if let Ok(head_ref) = git_repo.find_reference("HEAD") {
@@ -479,12 +502,24 @@ pub fn export_some_refs(
     }
     for (parsed_ref_name, (old_oid, new_oid)) in branches_to_update {
         let git_ref_name = to_git_ref_name(&parsed_ref_name).unwrap();
-        if let Err(reason) = update_git_ref(git_repo, &git_ref_name, old_oid, new_oid) {
+        let annotated_tag_oid = match &parsed_ref_name {
+            RefName::Tag(tag_name) => annotated_tags.get(tag_name).copied(),
+            _ => None,
+        };
+        let new_git_oid = annotated_tag_oid.unwrap_or(new_oid);
+        if let Err(reason) = update_git_ref(git_repo, &git_ref_name, old_oid, new_git_oid) {
             failed_branches.push(FailedRefExport {
                 name: parsed_ref_name,
                 reason,
             });
         } else {
+            if let (RefName::Tag(tag_name), Some(_)) = (&parsed_ref_name, annotated_tag_oid) {
+                // The tag object is now referenced from `refs/tags/`
+                let pending_ref_name = format!("{PENDING_ANNOTATED_TAG_NAMESPACE}{tag_name}");
+                if let Ok(mut pending_ref) = git_repo.find_reference(&pending_ref_name) {
+                    pending_ref.delete()?;
+                }
+            }
             mut_repo.set_git_ref_target(
                 &git_ref_name,
                 RefTarget::normal(CommitId::from_bytes(new_oid.as_bytes())),
@@ -520,11 +555,13 @@ fn diff_refs_to_export(
                 }),
         )
     });
+    let jj_repo_iter_all_tags = view.tags().keys().map(|tag| RefName::Tag(tag.to_owned()));
     let all_ref_names_passing_filter: HashSet<_> = view
         .git_refs()
         .keys()
         .filter_map(|name| parse_git_ref(name))
         .chain(jj_repo_iter_all_branches)
+        .chain(jj_repo_iter_all_tags)
         .filter(git_ref_filter)
         .collect();
     for ref_name in all_ref_names_passing_filter {
@@ -537,7 +574,8 @@ fn diff_refs_to_export(
                 // `new_target == None`.
                 view.get_remote_branch(branch, remote)
             }
-            _ => continue,
+            RefName::Tag(tag) => view.get_tag(tag),
+            RefName::GitRef(_) => continue,
         };
         let old_target = if let Some(name) = to_git_ref_name(&ref_name) {
             view.get_git_ref(&name)
@@ -599,6 +637,7 @@ fn delete_git_ref(
     old_oid: Oid,
 ) -> Result<(), FailedRefExportReason> {
     if let Ok(mut git_repo_ref) = git_repo.find_reference(git_ref_name) {
+        let old_oid = annotated_tag_oid(&git_repo_ref, old_oid).unwrap_or(old_oid);
         if git_repo_ref.target() == Some(old_oid) {
             // The branch has not been updated by git, so go ahead and delete it
             git_repo_ref
@@ -636,6 +675,11 @@ fn update_git_ref(
             }
         }
         Some(old_oid) => {
+            let old_oid = git_repo
+                .find_reference(git_ref_name)
+                .ok()
+                .and_then(|git_repo_ref| annotated_tag_oid(&git_repo_ref, old_oid))
+                .unwrap_or(old_oid);
             // The branch was modified in jj. We can use libgit2's API for updating under a
             // lock.
             if let Err(err) =
@@ -660,6 +704,101 @@ fn update_git_ref(
     Ok(())
 }
 
+/// Returns the id of the tag object if `git_ref` is an annotated tag of the
+/// `commit_oid`.
+///
+/// The view records the tagged commit, whereas the Git ref of an annotated tag
+/// points to the tag object.
+fn annotated_tag_oid(git_ref: &git2::Reference<'_>, commit_oid: Oid) -> Option<Oid> {
+    if !git_ref.is_tag() {
+        return None;
+    }
+    let tag = git_ref.peel_to_tag().ok()?;
+    (tag.target_id() == commit_oid).then(|| tag.id())
+}
+
+/// Creates an annotated tag object for the `target` commit, and points the tag
+/// in `mut_repo` to the commit.
+///
+/// The `refs/tags/<name>` ref in the Git repo is pointed to the tag object by
+/// the next export. Until then, the tag object is referenced from the
+/// `refs/jj/tags/` namespace, since the message can't be stored in the view.
+pub fn set_annotated_tag(
+    mut_repo: &mut MutableRepo,
+    git_repo: &git2::Repository,
+    name: &str,
+    target: &CommitId,
+    message: &str,
+    tagger: &Signature,
+) -> Result<(), GitExportError> {
+    let oid = Oid::from_bytes(target.as_bytes())?;
+    let git_commit = git_repo.find_object(oid, Some(git2::ObjectType::Commit))?;
+    let tagger = signature_to_git(tagger);
+    let tag_oid = git_repo.tag_annotation_create(name, &git_commit, &tagger, message)?;
+    git_repo.reference(
+        &format!("{PENDING_ANNOTATED_TAG_NAMESPACE}{name}"),
+        tag_oid,
+        true,
+        "annotated tag created by jj",
+    )?;
+    mut_repo.set_tag_target(name, RefTarget::normal(target.clone()));
+    Ok(())
+}
+
+/// Returns true if the tag `name` is annotated in the Git repo, or will be once
+/// exported.
+pub fn is_annotated_tag(git_repo: &git2::Repository, name: &str) -> bool {
+    [
+        format!("{PENDING_ANNOTATED_TAG_NAMESPACE}{name}"),
+        format!("refs/tags/{name}"),
+    ]
+    .iter()
+    .any(|git_ref_name| {
+        git_repo
+            .find_reference(git_ref_name)
+            .is_ok_and(|git_ref| git_ref.peel_to_tag().is_ok())
+    })
+}
+
+/// Returns the ids of the tag objects created by `set_annotated_tag()` that
+/// are yet to be exported, by tag name.
+///
+/// Tag objects that no longer match the tag in the `view` are forgotten.
+fn pending_annotated_tags(
+    view: &View,
+    git_repo: &git2::Repository,
+    git_ref_filter: impl Fn(&RefName) -> bool,
+) -> Result<HashMap<String, Oid>, git2::Error> {
+    let mut annotated_tags = HashMap::new();
+    for git_ref in git_repo.references_glob(&format!("{PENDING_ANNOTATED_TAG_NAMESPACE}*"))? {
+        let mut git_ref = git_ref?;
+        let Some(name) = git_ref
+            .name()
+            .and_then(|name| name.strip_prefix(PENDING_ANNOTATED_TAG_NAMESPACE))
+            .map(str::to_owned)
+        else {
+            continue;
+        };
+        if !git_ref_filter(&RefName::Tag(name.clone())) {
+            continue;
+        }
+        let tag_target = git_ref
+            .peel_to_tag()
+            .ok()
+            .map(|tag| (tag.id(), tag.target_id()));
+        match tag_target {
+            Some((tag_oid, target_oid))
+                if view.get_tag(&name).as_normal().map(|id| id.as_bytes())
+                    == Some(target_oid.as_bytes()) =>
+            {
+                annotated_tags.insert(name, tag_oid);
+            }
+            _ => git_ref.delete()?,
+        }
+    }
+    Ok(annotated_tags)
+}
+
 #[derive(Debug, Error)]
 pub enum GitRemoteManagementError {
     #[error("No git remote named '{0}'")]
@@ -1001,6 +1140,36 @@ pub fn push_updates(
     result
 }
 
+/// Pushes the `refs/tags/<name>` refs of the Git repo to the remote.
+///
+/// The tags should have been exported to the Git repo first, so annotated tags
+/// are pushed along with their tag objects.
+pub fn push_tags(
+    git_repo: &git2::Repository,
+    remote_name: &str,
+    tag_names: &[&str],
+    callbacks: RemoteCallbacks<'_>,
+) -> Result<(), GitPushError> {
+    let qualified_remote_refs = tag_names
+        .iter()
+        .map(|name| format!("refs/tags/{name}"))
+        .collect_vec();
+    let refspecs = qualified_remote_refs
+        .iter()
+        .map(|qualified_name| format!("{qualified_name}:{qualified_name}"))
+        .collect_vec();
+    push_refs(
+        git_repo,
+        remote_name,
+        &qualified_remote_refs
+            .iter()
+            .map(String::as_str)
+            .collect_vec(),
+        &refspecs,
+        callbacks,
+    )
+}
+
 fn push_refs(
     git_repo: &git2::Repository,
     remote_name: &str,
@@ -15,12 +15,15 @@
 #![allow(missing_docs)]
 
 use std::any::Any;
+use std::collections::HashSet;
 use std::fmt::{Debug, Error, Formatter};
 use std::fs;
-use std::io::{Cursor, Read};
+use std::io::{Cursor, Read, Write as _};
 use std::ops::Deref;
 use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
 use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use git2::Oid;
 use itertools::Itertools;
@@ -30,7 +33,8 @@ use thiserror::Error;
 use crate::backend::{
     make_root_commit, Backend, BackendError, BackendInitError, BackendLoadError, BackendResult,
     ChangeId, Commit, CommitId, Conflict, ConflictId, ConflictTerm, FileId, MergedTreeId,
-    MillisSinceEpoch, ObjectId, Signature, SymlinkId, Timestamp, Tree, TreeId, TreeValue,
+    MillisSinceEpoch, ObjectId, SecureSig, Signature, SigningFn, SymlinkId, Timestamp, Tree,
+    TreeId, TreeValue,
 };
 use crate::file_util::{IoResultExt as _, PathError};
 use crate::lock::FileLock;
@@ -91,6 +95,21 @@ impl From<GitBackendError> for BackendError {
     }
 }
 
+/// Error that may occur while running `git` to clean up the backing repo.
+#[derive(Debug, Error)]
+pub enum GitGcError {
+    #[error("Failed to run git: {0}")]
+    GitCommand(#[source] std::io::Error),
+    #[error("git exited with an error: {status}: {stderr}")]
+    GitCommandErrorStatus { status: ExitStatus, stderr: String },
+}
+
+impl From<GitGcError> for BackendError {
+    fn from(err: GitGcError) -> Self {
+        BackendError::Other(err.into())
+    }
+}
+
 pub struct GitBackend {
     repo: Mutex<git2::Repository>,
     root_commit_id: CommitId,
@@ -284,6 +303,8 @@ fn commit_from_git_without_root_parent(
         description,
         author,
         committer,
+        // The signature is extracted separately since it needs the repo.
+        secure_sig: None,
     }
 }
 
@@ -314,7 +335,7 @@ fn signature_from_git(signature: git2::Signature) -> Signature {
     }
 }
 
-fn signature_to_git(signature: &Signature) -> git2::Signature<'static> {
+pub(crate) fn signature_to_git(signature: &Signature) -> git2::Signature<'static> {
     // git does not support empty names or emails
     let name = if !signature.name.is_empty() {
         &signature.name
@@ -376,6 +397,35 @@ fn deserialize_extras(commit: &mut Commit, bytes: &[u8]) {
     }
 }
 
+/// Reads the signature of the given commit and the signed commit data, or
+/// `None` if the commit isn't signed.
+fn extract_signature(
+    git_repo: &git2::Repository,
+    id: &CommitId,
+) -> BackendResult<Option<SecureSig>> {
+    let git_commit_id = Oid::from_bytes(id.as_bytes()).unwrap();
+    match git_repo.extract_signature(&git_commit_id, None) {
+        Ok((sig, data)) => Ok(Some(SecureSig {
+            data: data.to_vec(),
+            sig: sig.to_vec(),
+        })),
+        // The commit isn't signed.
+        Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
+        Err(err) => Err(BackendError::ReadObject {
+            object_type: id.object_type(),
+            hash: id.hex(),
+            source: Box::new(err),
+        }),
+    }
+}
+
+fn to_commit_write_err(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> BackendError {
+    BackendError::WriteObject {
+        object_type: "commit",
+        source: err.into(),
+    }
+}
+
 /// Creates a random ref in refs/jj/. Used for preventing GC of commits we
 /// create.
 fn create_no_gc_ref() -> String {
@@ -395,6 +445,100 @@ fn prevent_gc(git_repo: &git2::Repository, id: &CommitId) -> Result<(), BackendE
     Ok(())
 }
 
+/// Replaces the refs used for preventing GC with ones pointing to the `heads`.
+///
+/// Refs pointing to commits newer than `keep_newer` are preserved since they
+/// might have been created by a concurrent process.
+fn recreate_no_gc_refs<'a>(
+    git_repo: &git2::Repository,
+    heads: impl IntoIterator<Item = &'a CommitId>,
+    keep_newer: SystemTime,
+) -> Result<(), BackendError> {
+    let to_backend_err = |err: git2::Error| BackendError::Other(Box::new(err));
+    let keep_newer_secs = to_unix_seconds(keep_newer);
+    let mut missing_heads: HashSet<&CommitId> = heads.into_iter().collect();
+    let mut stale_ref_names = vec![];
+    for git_ref in git_repo
+        .references_glob(&format!("{NO_GC_REF_NAMESPACE}*"))
+        .map_err(to_backend_err)?
+    {
+        let git_ref = git_ref.map_err(to_backend_err)?;
+        let Some(name) = git_ref.name() else {
+            continue;
+        };
+        let keep = match git_ref.target() {
+            Some(oid) => {
+                let id = CommitId::from_bytes(oid.as_bytes());
+                missing_heads.remove(&id)
+                    || git_repo
+                        .find_commit(oid)
+                        .is_ok_and(|commit| commit.committer().when().seconds() > keep_newer_secs)
+            }
+            None => false,
+        };
+        if !keep {
+            stale_ref_names.push(name.to_owned());
+        }
+    }
+    // Protect the heads before removing the other refs so the commits are never
+    // left unprotected.
+    for id in missing_heads {
+        prevent_gc(git_repo, id)?;
+    }
+    if !stale_ref_names.is_empty() {
+        tracing::info!(count = stale_ref_names.len(), "removing no-gc refs");
+        // Deleting refs one by one through libgit2 would rewrite packed-refs
+        // for each of them, so let git process them in one transaction.
+        let commands = stale_ref_names
+            .iter()
+            .map(|name| format!("delete {name}\n"))
+            .join("");
+        run_git_command(
+            git_repo.path(),
+            &["update-ref", "--stdin"],
+            commands.as_bytes(),
+        )?;
+    }
+    Ok(())
+}
+
+/// Packs the backing Git repository, pruning unreachable objects older than
+/// `keep_newer`.
+fn run_git_gc(git_dir: &Path, keep_newer: SystemTime) -> Result<(), GitGcError> {
+    let prune_arg = format!("--prune=@{} +0000", to_unix_seconds(keep_newer));
+    run_git_command(git_dir, &["gc", "--quiet", &prune_arg], b"")
+}
+
+fn run_git_command(git_dir: &Path, args: &[&str], input: &[u8]) -> Result<(), GitGcError> {
+    let mut git = Command::new("git");
+    git.arg("--git-dir").arg(git_dir).args(args);
+    git.stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    tracing::info!(?git, "running git command");
+    let mut child = git.spawn().map_err(GitGcError::GitCommand)?;
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input)
+        .map_err(GitGcError::GitCommand)?;
+    let output = child.wait_with_output().map_err(GitGcError::GitCommand)?;
+    if !output.status.success() {
+        return Err(GitGcError::GitCommandErrorStatus {
+            status: output.status,
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        });
+    }
+    Ok(())
+}
+
+fn to_unix_seconds(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |duration| {
+        duration.as_secs().try_into().unwrap_or(i64::MAX)
+    })
+}
+
 fn validate_git_object_id(id: &impl ObjectId) -> Result<git2::Oid, BackendError> {
     if id.as_bytes().len() != HASH_LENGTH {
         return Err(BackendError::InvalidHashLength {
@@ -690,6 +834,7 @@ impl Backend for GitBackend {
         if commit.parents.is_empty() {
             commit.parents.push(self.root_commit_id.clone());
         };
+        commit.secure_sig = extract_signature(&locked_repo, id)?;
 
         let table = self.cached_extra_metadata_table()?;
         let extras =
@@ -704,7 +849,11 @@ impl Backend for GitBackend {
         Ok(commit)
     }
 
-    fn write_commit(&self, mut contents: Commit) -> BackendResult<(CommitId, Commit)> {
+    fn write_commit(
+        &self,
+        mut contents: Commit,
+        mut sign_with: Option<&mut SigningFn>,
+    ) -> BackendResult<(CommitId, Commit)> {
         let locked_repo = self.repo.lock().unwrap();
         let git_tree_id = match &contents.root_tree {
             MergedTreeId::Legacy(tree_id) => validate_git_object_id(tree_id)?,
@@ -755,20 +904,35 @@ impl Backend for GitBackend {
         // table lock. This is still racy if multiple machines are involved and the
         // repository is rsync-ed.
         let (table, table_lock) = self.read_extra_metadata_table_locked()?;
-        let id = loop {
-            let git_id = locked_repo
-                .commit(
-                    Some(&create_no_gc_ref()),
-                    &author,
-                    &committer,
-                    message,
-                    &git_tree,
-                    &parent_refs,
-                )
-                .map_err(|err| BackendError::WriteObject {
-                    object_type: "commit",
-                    source: Box::new(err),
-                })?;
+        let (id, secure_sig) = loop {
+            let commit_buf = locked_repo
+                .commit_create_buffer(&author, &committer, message, &git_tree, &parent_refs)
+                .map_err(to_commit_write_err)?;
+            let commit_data = commit_buf.to_vec();
+            let sig = match &contents.secure_sig {
+                // Keep the existing signature only if the signed data is unchanged.
+                Some(secure_sig) if secure_sig.data == commit_data => Some(secure_sig.sig.clone()),
+                _ => match &mut sign_with {
+                    Some(sign) => Some(sign(&commit_data).map_err(to_commit_write_err)?),
+                    None => None,
+                },
+            };
+            let git_id = match &sig {
+                Some(sig) => {
+                    let commit_str = commit_buf
+                        .as_str()
+                        .ok_or_else(|| to_commit_write_err("commit is not valid UTF-8"))?;
+                    let sig_str = std::str::from_utf8(sig).map_err(to_commit_write_err)?;
+                    locked_repo.commit_signed(commit_str, sig_str, None)
+                }
+                None => locked_repo
+                    .odb()
+                    .and_then(|odb| odb.write(git2::ObjectType::Commit, &commit_data)),
+            }
+            .map_err(to_commit_write_err)?;
+            locked_repo
+                .reference(&create_no_gc_ref(), git_id, false, "used by jj")
+                .map_err(to_commit_write_err)?;
             let id = CommitId::from_bytes(git_id.as_bytes());
             match table.get_value(id.as_bytes()) {
                 Some(existing_extras) if existing_extras != extras => {
@@ -786,7 +950,11 @@ impl Backend for GitBackend {
                     .unwrap();
                 }
                 _ => {
-                    break id;
+                    let secure_sig = sig.map(|sig| SecureSig {
+                        data: commit_data,
+                        sig,
+                    });
+                    break (id, secure_sig);
                 }
             }
         };
@@ -795,11 +963,21 @@ impl Backend for GitBackend {
         contents.author.timestamp.timestamp = MillisSinceEpoch(author.when().seconds() * 1000);
         contents.committer.timestamp.timestamp =
             MillisSinceEpoch(committer.when().seconds() * 1000);
+        contents.secure_sig = secure_sig;
         let mut mut_table = table.start_mutation();
         mut_table.add_entry(id.to_bytes(), extras);
         self.save_extra_metadata_table(mut_table, &table_lock)?;
         Ok((id, contents))
     }
+
+    #[tracing::instrument(skip(self, heads))]
+    fn gc(&self, heads: &[CommitId], keep_newer: SystemTime) -> BackendResult<()> {
+        let locked_repo = self.repo.lock().unwrap();
+        let heads = heads.iter().filter(|&id| *id != self.root_commit_id);
+        recreate_no_gc_refs(&locked_repo, heads, keep_newer)?;
+        run_git_gc(locked_repo.path(), keep_newer)?;
+        Ok(())
+    }
 }
 
 /// Write a tree conflict as a special tree with `.jjconflict-base-N` and
@@ -901,6 +1079,8 @@ mod tests {
     use assert_matches::assert_matches;
     use test_case::test_case;
 
+    use std::time::Duration;
+
     use super::*;
     use crate::backend::{FileId, MillisSinceEpoch};
 
@@ -1128,18 +1308,19 @@ mod tests {
             description: "".to_string(),
             author: create_signature(),
             committer: create_signature(),
+            secure_sig: None,
         };
 
         // No parents
         commit.parents = vec![];
         assert_matches!(
-            backend.write_commit(commit.clone()),
+            backend.write_commit(commit.clone(), None),
             Err(BackendError::Other(err)) if err.to_string().contains("no parents")
         );
 
         // Only root commit as parent
         commit.parents = vec![backend.root_commit_id().clone()];
-        let first_id = backend.write_commit(commit.clone()).unwrap().0;
+        let first_id = backend.write_commit(commit.clone(), None).unwrap().0;
         let first_commit = backend.read_commit(&first_id).unwrap();
         assert_eq!(first_commit, commit);
         let first_git_commit = git_repo.find_commit(git_id(&first_id)).unwrap();
@@ -1147,7 +1328,7 @@ mod tests {
 
         // Only non-root commit as parent
         commit.parents = vec![first_id.clone()];
-        let second_id = backend.write_commit(commit.clone()).unwrap().0;
+        let second_id = backend.write_commit(commit.clone(), None).unwrap().0;
         let second_commit = backend.read_commit(&second_id).unwrap();
         assert_eq!(second_commit, commit);
         let second_git_commit = git_repo.find_commit(git_id(&second_id)).unwrap();
@@ -1158,7 +1339,7 @@ mod tests {
 
         // Merge commit
         commit.parents = vec![first_id.clone(), second_id.clone()];
-        let merge_id = backend.write_commit(commit.clone()).unwrap().0;
+        let merge_id = backend.write_commit(commit.clone(), None).unwrap().0;
         let merge_commit = backend.read_commit(&merge_id).unwrap();
         assert_eq!(merge_commit, commit);
         let merge_git_commit = git_repo.find_commit(git_id(&merge_id)).unwrap();
@@ -1170,7 +1351,7 @@ mod tests {
         // Merge commit with root as one parent
         commit.parents = vec![first_id, backend.root_commit_id().clone()];
         assert_matches!(
-            backend.write_commit(commit),
+            backend.write_commit(commit, None),
             Err(BackendError::Other(err)) if err.to_string().contains("root commit")
         );
     }
@@ -1204,11 +1385,12 @@ mod tests {
             description: "".to_string(),
             author: create_signature(),
             committer: create_signature(),
+            secure_sig: None,
         };
 
         // When writing a tree-level conflict, the root tree on the git side has the
         // individual trees as subtrees.
-        let read_commit_id = backend.write_commit(commit.clone()).unwrap().0;
+        let read_commit_id = backend.write_commit(commit.clone(), None).unwrap().0;
         let read_commit = backend.read_commit(&read_commit_id).unwrap();
         assert_eq!(read_commit, commit);
         let git_commit = git_repo
@@ -1237,7 +1419,7 @@ mod tests {
         // When writing a single tree using the new format, it's represented by a
         // regular git tree.
         commit.root_tree = MergedTreeId::resolved(create_tree(5));
-        let read_commit_id = backend.write_commit(commit.clone()).unwrap().0;
+        let read_commit_id = backend.write_commit(commit.clone(), None).unwrap().0;
         let read_commit = backend.read_commit(&read_commit_id).unwrap();
         assert_eq!(read_commit, commit);
         let git_commit = git_repo
@@ -1269,8 +1451,9 @@ mod tests {
             description: "initial".to_string(),
             author: signature.clone(),
             committer: signature,
+            secure_sig: None,
         };
-        let commit_id = store.write_commit(commit).unwrap().0;
+        let commit_id = store.write_commit(commit, None).unwrap().0;
         let git_refs = store
             .git_repo()
             .references_glob("refs/jj/keep/*")
@@ -1292,16 +1475,17 @@ mod tests {
             description: "initial".to_string(),
             author: create_signature(),
             committer: create_signature(),
+            secure_sig: None,
         };
         // libgit2 doesn't seem to preserve negative timestamps, so set it to at least 1
         // second after the epoch, so the timestamp adjustment can remove 1
         // second and it will still be nonnegative
         commit1.committer.timestamp.timestamp = MillisSinceEpoch(1000);
-        let (commit_id1, mut commit2) = store.write_commit(commit1).unwrap();
+        let (commit_id1, mut commit2) = store.write_commit(commit1, None).unwrap();
         commit2.predecessors.push(commit_id1.clone());
         // `write_commit` should prevent the ids from being the same by changing the
         // committer timestamp of the commit it actually writes.
-        let (commit_id2, mut actual_commit2) = store.write_commit(commit2.clone()).unwrap();
+        let (commit_id2, mut actual_commit2) = store.write_commit(commit2.clone(), None).unwrap();
         // The returned matches the ID
         assert_eq!(store.read_commit(&commit_id2).unwrap(), actual_commit2);
         assert_ne!(commit_id2, commit_id1);
@@ -1316,6 +1500,61 @@ mod tests {
         assert_eq!(actual_commit2, commit2);
     }
 
+    #[test]
+    fn gc_recreates_no_gc_refs() {
+        let temp_dir = testutils::new_temp_dir();
+        let store = GitBackend::init_internal(temp_dir.path()).unwrap();
+        let write_commit = |parent_id: &CommitId, description: &str, timestamp_secs: i64| {
+            let mut signature = create_signature();
+            signature.timestamp.timestamp = MillisSinceEpoch(timestamp_secs * 1000);
+            let commit = Commit {
+                parents: vec![parent_id.clone()],
+                predecessors: vec![],
+                root_tree: MergedTreeId::Legacy(store.empty_tree_id().clone()),
+                change_id: ChangeId::new(vec![]),
+                description: description.to_string(),
+                author: signature.clone(),
+                committer: signature,
+                secure_sig: None,
+            };
+            store.write_commit(commit, None).unwrap().0
+        };
+        let git_refs = || {
+            store
+                .git_repo()
+                .references_glob("refs/jj/keep/*")
+                .unwrap()
+                .map(|git_ref| git_ref.unwrap().target().unwrap())
+                .sorted()
+                .collect_vec()
+        };
+        let root_commit_id = store.root_commit_id().clone();
+        let commit_id1 = write_commit(&root_commit_id, "1", 10);
+        let commit_id2 = write_commit(&commit_id1, "2", 10);
+        write_commit(&root_commit_id, "unreachable old", 10);
+        let commit_id4 = write_commit(&root_commit_id, "unreachable new", 1000);
+        assert_eq!(git_refs().len(), 4);
+
+        // Only the head and the commit newer than the threshold should be kept
+        let keep_newer = UNIX_EPOCH + Duration::from_secs(100);
+        store
+            .gc(&[root_commit_id, commit_id2.clone()], keep_newer)
+            .unwrap();
+        assert_eq!(
+            git_refs(),
+            [git_id(&commit_id2), git_id(&commit_id4)]
+                .into_iter()
+                .sorted()
+                .collect_vec()
+        );
+        // The ancestors of the head should still be readable. The object of
+        // the old unreachable commit is pruned by git based on its file mtime,
+        // so it isn't checked here.
+        assert!(store.read_commit(&commit_id1).is_ok());
+        assert!(store.read_commit(&commit_id2).is_ok());
+        assert!(store.read_commit(&commit_id4).is_ok());
+    }
+
     fn git_id(commit_id: &CommitId) -> Oid {
         Oid::from_bytes(commit_id.as_bytes()).unwrap()
     }
@@ -0,0 +1,180 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Signing backend which shells out to `gpg`.
+
+use std::ffi::OsString;
+use std::io::Write as _;
+use std::process::{Command, Output, Stdio};
+
+use crate::signing::{SigStatus, SignError, SignResult, SigningBackend, Verification};
+
+const SIGNATURE_PREFIX: &[u8] = b"-----BEGIN PGP SIGNATURE-----";
+
+/// Signs and verifies data using the `gpg` program.
+#[derive(Debug)]
+pub struct GpgBackend {
+    program: OsString,
+}
+
+impl GpgBackend {
+    /// Creates a backend running the given `gpg` program.
+    pub fn new(program: OsString) -> Self {
+        GpgBackend { program }
+    }
+
+    /// Creates a backend configured by `signing.backends.gpg.program`.
+    pub fn from_config(config: &config::Config) -> Self {
+        let program = config
+            .get_string("signing.backends.gpg.program")
+            .unwrap_or_else(|_| "gpg".to_string());
+        GpgBackend::new(program.into())
+    }
+
+    fn run(&self, args: &[&str], input: &[u8]) -> SignResult<Output> {
+        let mut command = Command::new(&self.program);
+        command
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        tracing::info!(?command, "running gpg command");
+        let mut child = command.spawn().map_err(backend_error)?;
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(input)
+            .map_err(backend_error)?;
+        child.wait_with_output().map_err(backend_error)
+    }
+}
+
+impl SigningBackend for GpgBackend {
+    fn name(&self) -> &str {
+        "gpg"
+    }
+
+    fn can_read(&self, signature: &[u8]) -> bool {
+        signature.starts_with(SIGNATURE_PREFIX)
+    }
+
+    fn sign(&self, data: &[u8], key: Option<&str>) -> SignResult<Vec<u8>> {
+        let mut args = vec!["--detach-sign", "--armor"];
+        if let Some(key) = key {
+            args.extend(["--local-user", key]);
+        }
+        let output = self.run(&args, data)?;
+        if !output.status.success() {
+            return Err(SignError::Backend(
+                format!(
+                    "gpg failed to sign the data: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )
+                .into(),
+            ));
+        }
+        Ok(output.stdout)
+    }
+
+    fn verify(&self, data: &[u8], signature: &[u8]) -> SignResult<Verification> {
+        let mut signature_file = tempfile::NamedTempFile::new().map_err(backend_error)?;
+        signature_file.write_all(signature).map_err(backend_error)?;
+        signature_file.flush().map_err(backend_error)?;
+        let signature_path = signature_file
+            .path()
+            .to_str()
+            .ok_or_else(|| SignError::Backend("temporary file path is not valid UTF-8".into()))?;
+        // gpg exits with an error status for bad signatures, so we only look at
+        // the machine-readable status lines.
+        let output = self.run(
+            &[
+                "--keyid-format=long",
+                "--status-fd=1",
+                "--verify",
+                signature_path,
+                "-",
+            ],
+            data,
+        )?;
+        parse_gpg_status(&output.stdout).ok_or(SignError::InvalidSignatureFormat)
+    }
+}
+
+fn backend_error(err: std::io::Error) -> SignError {
+    SignError::Backend(Box::new(err))
+}
+
+/// Parses the output of `gpg --status-fd`. See `doc/DETAILS` in the GnuPG
+/// sources for the format.
+fn parse_gpg_status(output: &[u8]) -> Option<Verification> {
+    let output = String::from_utf8_lossy(output);
+    output.lines().find_map(|line| {
+        let mut parts = line.strip_prefix("[GNUPG:] ")?.splitn(3, ' ');
+        let status = match parts.next()? {
+            "GOODSIG" => SigStatus::Good,
+            "EXPSIG" | "EXPKEYSIG" | "REVKEYSIG" | "BADSIG" => SigStatus::Bad,
+            "ERRSIG" => SigStatus::Unknown,
+            _ => return None,
+        };
+        let key = parts.next().map(|key| key.to_owned());
+        // ERRSIG is followed by algorithm ids rather than a user id.
+        let display = parts
+            .next()
+            .filter(|_| status != SigStatus::Unknown)
+            .map(|display| display.to_owned());
+        Some(Verification {
+            status,
+            key,
+            display,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gpg_status() {
+        assert_eq!(
+            parse_gpg_status(
+                b"[GNUPG:] NEWSIG\n[GNUPG:] GOODSIG 123ABC Test User <test@example.com>\n"
+            ),
+            Some(Verification {
+                status: SigStatus::Good,
+                key: Some("123ABC".to_owned()),
+                display: Some("Test User <test@example.com>".to_owned()),
+            })
+        );
+        assert_eq!(
+            parse_gpg_status(b"[GNUPG:] BADSIG 123ABC Test User <test@example.com>\n"),
+            Some(Verification {
+                status: SigStatus::Bad,
+                key: Some("123ABC".to_owned()),
+                display: Some("Test User <test@example.com>".to_owned()),
+            })
+        );
+        assert_eq!(
+            parse_gpg_status(b"[GNUPG:] ERRSIG 123ABC 1 8 00 1700000000 9 -\n"),
+            Some(Verification {
+                status: SigStatus::Unknown,
+                key: Some("123ABC".to_owned()),
+                display: None,
+            })
+        );
+        assert_eq!(parse_gpg_status(b"[GNUPG:] NEWSIG\n"), None);
+        assert_eq!(parse_gpg_status(b""), None);
+    }
+}
@@ -24,7 +24,7 @@ use crate::backend::{CommitId, ObjectId};
 use crate::commit::Commit;
 use crate::op_store::OperationId;
 use crate::operation::Operation;
-use crate::revset::{ResolvedExpression, Revset, RevsetEvaluationError};
+use crate::revset::{ChangeIdIndex, ResolvedExpression, Revset, RevsetEvaluationError};
 use crate::store::Store;
 
 #[derive(Debug, Error)]
@@ -68,6 +68,13 @@ pub trait Index: Send + Sync {
         expression: &ResolvedExpression,
         store: &Arc<Store>,
     ) -> Result<Box<dyn Revset<'index> + 'index>, RevsetEvaluationError>;
+
+    /// Returns an index of the change ids of the commits reachable from
+    /// `heads`.
+    fn change_id_index(
+        &self,
+        heads: &mut dyn Iterator<Item = &CommitId>,
+    ) -> Box<dyn ChangeIdIndex + '_>;
 }
 
 pub trait ReadonlyIndex: Send + Sync {
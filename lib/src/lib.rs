@@ -20,10 +20,14 @@
 #[macro_use]
 pub mod content_hash;
 
+pub mod absorb;
+pub mod annotate;
 pub mod backend;
+pub mod changed_path_filter;
 pub mod commit;
 pub mod commit_builder;
 pub mod conflicts;
+pub mod copies;
 pub mod dag_walk;
 pub mod default_index_store;
 pub mod default_revset_engine;
@@ -32,11 +36,13 @@ pub mod default_submodule_store;
 pub mod diff;
 pub mod file_util;
 pub mod files;
+pub mod fileset;
 pub mod fmt_util;
 pub mod fsmonitor;
 pub mod git;
 pub mod git_backend;
 pub mod gitignore;
+pub mod gpg_signing;
 pub mod hex_util;
 pub mod id_prefix;
 pub mod index;
@@ -48,6 +54,7 @@ pub mod merged_tree;
 pub mod op_heads_store;
 pub mod op_store;
 pub mod operation;
+pub mod patch;
 #[allow(missing_docs)]
 pub mod protos;
 pub mod refs;
@@ -57,11 +64,17 @@ pub mod revset;
 pub mod revset_graph;
 pub mod rewrite;
 pub mod settings;
+pub mod signing;
 pub mod simple_op_heads_store;
 pub mod simple_op_store;
+pub mod sparse;
+pub mod ssh_signing;
 pub mod stacked_table;
 pub mod store;
 pub mod submodule_store;
+#[cfg(feature = "testing")]
+pub mod test_signing_backend;
+pub mod time_util;
 pub mod transaction;
 pub mod tree;
 pub mod tree_builder;
@@ -17,6 +17,8 @@
 use std::collections::{HashMap, HashSet};
 use std::iter;
 
+use itertools::Itertools as _;
+
 use tracing::instrument;
 
 use crate::repo_path::{RepoPath, RepoPathComponent};
@@ -72,6 +74,26 @@ pub trait Matcher: Sync {
     fn visit(&self, dir: &RepoPath) -> Visit;
 }
 
+impl<T: Matcher + ?Sized> Matcher for &T {
+    fn matches(&self, file: &RepoPath) -> bool {
+        <T as Matcher>::matches(self, file)
+    }
+
+    fn visit(&self, dir: &RepoPath) -> Visit {
+        <T as Matcher>::visit(self, dir)
+    }
+}
+
+impl<T: Matcher + ?Sized> Matcher for Box<T> {
+    fn matches(&self, file: &RepoPath) -> bool {
+        <T as Matcher>::matches(self, file)
+    }
+
+    fn visit(&self, dir: &RepoPath) -> Visit {
+        <T as Matcher>::visit(self, dir)
+    }
+}
+
 #[derive(PartialEq, Eq, Debug)]
 pub struct NothingMatcher;
 
@@ -160,22 +182,172 @@ impl Matcher for PrefixMatcher {
     }
 }
 
+const GLOB_MATCH_OPTIONS: glob::MatchOptions = glob::MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Matches file paths with glob patterns.
+///
+/// Each pattern is anchored at a base directory, and matched against the
+/// remainder of the path. A `*` doesn't match path separators, but `**` does.
+#[derive(Clone, Debug)]
+pub struct FileGlobsMatcher {
+    tree: RepoPathTree,
+    globs: Vec<(RepoPath, glob::Pattern)>,
+}
+
+impl FileGlobsMatcher {
+    pub fn new(globs: &[(RepoPath, glob::Pattern)]) -> Self {
+        let mut tree = RepoPathTree::new();
+        for (dir, _) in globs {
+            // 'is_file' means that there are patterns to be matched in the
+            // directory
+            tree.add(dir).is_file = true;
+        }
+        FileGlobsMatcher {
+            tree,
+            globs: globs.to_vec(),
+        }
+    }
+}
+
+impl Matcher for FileGlobsMatcher {
+    fn matches(&self, file: &RepoPath) -> bool {
+        self.globs.iter().any(|(dir, pattern)| {
+            file.components()
+                .strip_prefix(dir.components().as_slice())
+                .filter(|tail| !tail.is_empty())
+                .is_some_and(|tail| {
+                    let tail = tail.iter().map(|c| c.as_str()).join("/");
+                    pattern.matches_with(&tail, GLOB_MATCH_OPTIONS)
+                })
+        })
+    }
+
+    fn visit(&self, dir: &RepoPath) -> Visit {
+        self.tree.get_glob_visit_sets(dir)
+    }
+}
+
+/// Matches paths that are at or below a path matching one of the glob
+/// patterns.
+///
+/// Patterns are anchored the same way as in `FileGlobsMatcher`. For example,
+/// the pattern `*/src` in the root directory matches `foo/src` and
+/// `foo/src/main.rs`, but not `foo/bar/src`.
+#[derive(Clone, Debug)]
+pub struct PrefixGlobsMatcher {
+    tree: RepoPathTree,
+    globs: Vec<(RepoPath, glob::Pattern)>,
+}
+
+impl PrefixGlobsMatcher {
+    pub fn new(globs: &[(RepoPath, glob::Pattern)]) -> Self {
+        let mut tree = RepoPathTree::new();
+        for (dir, _) in globs {
+            tree.add(dir).is_file = true;
+        }
+        PrefixGlobsMatcher {
+            tree,
+            globs: globs.to_vec(),
+        }
+    }
+}
+
+impl Matcher for PrefixGlobsMatcher {
+    fn matches(&self, file: &RepoPath) -> bool {
+        self.globs.iter().any(|(dir, pattern)| {
+            file.components()
+                .strip_prefix(dir.components().as_slice())
+                .is_some_and(|tail| {
+                    (1..=tail.len()).any(|len| {
+                        let prefix = tail[..len].iter().map(|c| c.as_str()).join("/");
+                        pattern.matches_with(&prefix, GLOB_MATCH_OPTIONS)
+                    })
+                })
+        })
+    }
+
+    fn visit(&self, dir: &RepoPath) -> Visit {
+        if self.matches(dir) {
+            Visit::AllRecursively
+        } else {
+            self.tree.get_glob_visit_sets(dir)
+        }
+    }
+}
+
+/// Matches paths that are matched by any of the input matchers.
+pub struct UnionMatcher<M1, M2> {
+    input1: M1,
+    input2: M2,
+}
+
+impl<M1: Matcher, M2: Matcher> UnionMatcher<M1, M2> {
+    pub fn new(input1: M1, input2: M2) -> Self {
+        Self { input1, input2 }
+    }
+}
+
+impl<M1: Matcher, M2: Matcher> Matcher for UnionMatcher<M1, M2> {
+    fn matches(&self, file: &RepoPath) -> bool {
+        self.input1.matches(file) || self.input2.matches(file)
+    }
+
+    fn visit(&self, dir: &RepoPath) -> Visit {
+        match self.input1.visit(dir) {
+            Visit::AllRecursively => Visit::AllRecursively,
+            Visit::Nothing => self.input2.visit(dir),
+            Visit::Specific {
+                dirs: dirs1,
+                files: files1,
+            } => match self.input2.visit(dir) {
+                Visit::AllRecursively => Visit::AllRecursively,
+                Visit::Nothing => Visit::Specific {
+                    dirs: dirs1,
+                    files: files1,
+                },
+                Visit::Specific {
+                    dirs: dirs2,
+                    files: files2,
+                } => {
+                    let dirs = match (dirs1, dirs2) {
+                        (VisitDirs::All, _) | (_, VisitDirs::All) => VisitDirs::All,
+                        (VisitDirs::Set(dirs1), VisitDirs::Set(dirs2)) => {
+                            VisitDirs::Set(dirs1.iter().chain(&dirs2).cloned().collect())
+                        }
+                    };
+                    let files = match (files1, files2) {
+                        (VisitFiles::All, _) | (_, VisitFiles::All) => VisitFiles::All,
+                        (VisitFiles::Set(files1), VisitFiles::Set(files2)) => {
+                            VisitFiles::Set(files1.iter().chain(&files2).cloned().collect())
+                        }
+                    };
+                    Visit::Specific { dirs, files }
+                }
+            },
+        }
+    }
+}
+
 /// Matches paths that are matched by the first input matcher but not by the
 /// second.
-pub struct DifferenceMatcher<'input> {
+pub struct DifferenceMatcher<M1, M2> {
     /// The minuend
-    wanted: &'input dyn Matcher,
+    wanted: M1,
     /// The subtrahend
-    unwanted: &'input dyn Matcher,
+    unwanted: M2,
 }
 
-impl<'input> DifferenceMatcher<'input> {
-    pub fn new(wanted: &'input dyn Matcher, unwanted: &'input dyn Matcher) -> Self {
+impl<M1: Matcher, M2: Matcher> DifferenceMatcher<M1, M2> {
+    pub fn new(wanted: M1, unwanted: M2) -> Self {
         Self { wanted, unwanted }
     }
 }
 
-impl Matcher for DifferenceMatcher<'_> {
+impl<M1: Matcher, M2: Matcher> Matcher for DifferenceMatcher<M1, M2> {
     fn matches(&self, file: &RepoPath) -> bool {
         self.wanted.matches(file) && !self.unwanted.matches(file)
     }
@@ -196,18 +368,18 @@ impl Matcher for DifferenceMatcher<'_> {
 }
 
 /// Matches paths that are matched by both input matchers.
-pub struct IntersectionMatcher<'input> {
-    input1: &'input dyn Matcher,
-    input2: &'input dyn Matcher,
+pub struct IntersectionMatcher<M1, M2> {
+    input1: M1,
+    input2: M2,
 }
 
-impl<'input> IntersectionMatcher<'input> {
-    pub fn new(input1: &'input dyn Matcher, input2: &'input dyn Matcher) -> Self {
+impl<M1: Matcher, M2: Matcher> IntersectionMatcher<M1, M2> {
+    pub fn new(input1: M1, input2: M2) -> Self {
         Self { input1, input2 }
     }
 }
 
-impl Matcher for IntersectionMatcher<'_> {
+impl<M1: Matcher, M2: Matcher> Matcher for IntersectionMatcher<M1, M2> {
     fn matches(&self, file: &RepoPath) -> bool {
         self.input1.matches(file) && self.input2.matches(file)
     }
@@ -261,7 +433,7 @@ impl Matcher for IntersectionMatcher<'_> {
 
 /// Keeps track of which subdirectories and files of each directory need to be
 /// visited.
-#[derive(PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 struct RepoPathTree {
     entries: HashMap<RepoPathComponent, RepoPathTree>,
     // is_dir/is_file aren't exclusive, both can be set to true. If entries is not empty,
@@ -323,6 +495,32 @@ impl RepoPathTree {
         )
     }
 
+    /// Returns the directories to visit in order to match glob patterns, where
+    /// `is_file` is set on the directories the patterns are anchored at.
+    fn get_glob_visit_sets(&self, dir: &RepoPath) -> Visit {
+        for (sub, tail_components) in self.walk_to(dir) {
+            // Patterns may match anything in the directory
+            if sub.is_file {
+                return Visit::Specific {
+                    dirs: VisitDirs::All,
+                    files: VisitFiles::All,
+                };
+            }
+            // 'dir' found, and is an ancestor of pattern directories
+            if tail_components.is_empty() {
+                return Visit::sets(
+                    sub.entries
+                        .iter()
+                        .filter(|(_, sub)| sub.is_dir || sub.is_file)
+                        .map(|(name, _)| name.clone())
+                        .collect(),
+                    HashSet::new(),
+                );
+            }
+        }
+        Visit::Nothing
+    }
+
     fn to_visit_sets(&self) -> Visit {
         let mut dirs = HashSet::new();
         let mut files = HashSet::new();
@@ -715,4 +913,142 @@ mod tests {
             Visit::AllRecursively
         );
     }
+
+    #[test]
+    fn test_fileglobsmatcher() {
+        let m = FileGlobsMatcher::new(&[
+            (
+                RepoPath::from_internal_string("foo"),
+                glob::Pattern::new("*.rs").unwrap(),
+            ),
+            (
+                RepoPath::from_internal_string("bar"),
+                glob::Pattern::new("**/*.md").unwrap(),
+            ),
+        ]);
+
+        assert!(m.matches(&RepoPath::from_internal_string("foo/lib.rs")));
+        assert!(!m.matches(&RepoPath::from_internal_string("foo/sub/lib.rs")));
+        assert!(!m.matches(&RepoPath::from_internal_string("foo/lib.c")));
+        assert!(!m.matches(&RepoPath::from_internal_string("lib.rs")));
+        assert!(m.matches(&RepoPath::from_internal_string("bar/README.md")));
+        assert!(m.matches(&RepoPath::from_internal_string("bar/sub/README.md")));
+        assert!(!m.matches(&RepoPath::from_internal_string("baz/README.md")));
+
+        assert_eq!(
+            m.visit(&RepoPath::root()),
+            Visit::sets(
+                hashset! {RepoPathComponent::from("foo"), RepoPathComponent::from("bar")},
+                hashset! {}
+            )
+        );
+        assert_eq!(
+            m.visit(&RepoPath::from_internal_string("foo")),
+            Visit::Specific {
+                dirs: VisitDirs::All,
+                files: VisitFiles::All,
+            }
+        );
+        assert_eq!(
+            m.visit(&RepoPath::from_internal_string("bar/sub")),
+            Visit::Specific {
+                dirs: VisitDirs::All,
+                files: VisitFiles::All,
+            }
+        );
+        assert_eq!(
+            m.visit(&RepoPath::from_internal_string("baz")),
+            Visit::Nothing
+        );
+    }
+
+    #[test]
+    fn test_prefixglobsmatcher() {
+        let m = PrefixGlobsMatcher::new(&[
+            (
+                RepoPath::from_internal_string("services"),
+                glob::Pattern::new("*/src").unwrap(),
+            ),
+            (RepoPath::root(), glob::Pattern::new("**/testdata").unwrap()),
+        ]);
+
+        assert!(m.matches(&RepoPath::from_internal_string("services/foo/src")));
+        assert!(m.matches(&RepoPath::from_internal_string("services/foo/src/main.rs")));
+        assert!(!m.matches(&RepoPath::from_internal_string("services/foo/README")));
+        assert!(!m.matches(&RepoPath::from_internal_string(
+            "services/foo/bar/src/main.rs"
+        )));
+        assert!(m.matches(&RepoPath::from_internal_string("testdata/file")));
+        assert!(m.matches(&RepoPath::from_internal_string("foo/testdata/file")));
+        assert!(!m.matches(&RepoPath::from_internal_string("foo/testdata2/file")));
+
+        assert_eq!(
+            m.visit(&RepoPath::root()),
+            Visit::Specific {
+                dirs: VisitDirs::All,
+                files: VisitFiles::All,
+            }
+        );
+        assert_eq!(
+            m.visit(&RepoPath::from_internal_string("services/foo")),
+            Visit::Specific {
+                dirs: VisitDirs::All,
+                files: VisitFiles::All,
+            }
+        );
+        assert_eq!(
+            m.visit(&RepoPath::from_internal_string("services/foo/src")),
+            Visit::AllRecursively
+        );
+        assert_eq!(
+            m.visit(&RepoPath::from_internal_string("foo/testdata")),
+            Visit::AllRecursively
+        );
+
+        let m = PrefixGlobsMatcher::new(&[(
+            RepoPath::from_internal_string("services"),
+            glob::Pattern::new("*/src").unwrap(),
+        )]);
+        assert_eq!(
+            m.visit(&RepoPath::root()),
+            Visit::sets(hashset! {RepoPathComponent::from("services")}, hashset! {})
+        );
+        assert_eq!(
+            m.visit(&RepoPath::from_internal_string("lib")),
+            Visit::Nothing
+        );
+    }
+
+    #[test]
+    fn test_unionmatcher_concatenate_roots() {
+        let m1 = PrefixMatcher::new(&[RepoPath::from_internal_string("foo")]);
+        let m2 = FilesMatcher::new(&[RepoPath::from_internal_string("bar/baz")]);
+        let m = UnionMatcher::new(&m1, &m2);
+
+        assert!(m.matches(&RepoPath::from_internal_string("foo")));
+        assert!(m.matches(&RepoPath::from_internal_string("foo/bar")));
+        assert!(!m.matches(&RepoPath::from_internal_string("bar")));
+        assert!(m.matches(&RepoPath::from_internal_string("bar/baz")));
+        assert!(!m.matches(&RepoPath::from_internal_string("bar/qux")));
+
+        assert_eq!(
+            m.visit(&RepoPath::root()),
+            Visit::sets(
+                hashset! {RepoPathComponent::from("foo"), RepoPathComponent::from("bar")},
+                hashset! {RepoPathComponent::from("foo")}
+            )
+        );
+        assert_eq!(
+            m.visit(&RepoPath::from_internal_string("foo")),
+            Visit::AllRecursively
+        );
+        assert_eq!(
+            m.visit(&RepoPath::from_internal_string("bar")),
+            Visit::sets(hashset! {}, hashset! {RepoPathComponent::from("baz")})
+        );
+        assert_eq!(
+            m.visit(&RepoPath::from_internal_string("qux")),
+            Visit::Nothing
+        );
+    }
 }
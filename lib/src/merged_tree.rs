@@ -29,7 +29,7 @@ use crate::repo_path::{RepoPath, RepoPathComponent, RepoPathJoin};
 use crate::store::Store;
 use crate::tree::{try_resolve_file_conflict, Tree, TreeMergeError};
 use crate::tree_builder::TreeBuilder;
-use crate::{backend, tree};
+use crate::{backend, copies, tree};
 
 /// Presents a view of a merged set of trees.
 #[derive(PartialEq, Eq, Clone, Debug)]
@@ -358,6 +358,21 @@ impl MergedTree {
         }
     }
 
+    /// Merges this tree with `other`, using `base` as base, like `merge()`,
+    /// except that a file that was renamed on one side and modified on the
+    /// other is merged at its new path.
+    ///
+    /// Renames are detected by comparing the content of removed and added
+    /// files, so this reads those files from the store.
+    pub fn merge_following_renames(
+        &self,
+        base: &MergedTree,
+        other: &MergedTree,
+    ) -> Result<MergedTree, TreeMergeError> {
+        let (base, this, other) = copies::follow_renames(base, self, other)?;
+        this.merge(&base, &other)
+    }
+
     /// Merges this tree with `other`, using `base` as base.
     pub fn merge(
         &self,
@@ -14,8 +14,9 @@
 
 #![allow(missing_docs)]
 
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt::{Debug, Error, Formatter};
+use std::time::SystemTime;
 
 use once_cell::sync::Lazy;
 use thiserror::Error;
@@ -166,6 +167,10 @@ content_hash! {
         // whether the branch is known to have existed on the remote. We may not want to resurrect
         // the branch if the branch's state on the remote was just not known.
         pub remote_targets: BTreeMap<String, RefTarget>,
+        /// The remotes in `remote_targets` whose branch isn't tracked by the
+        /// local branch. Changes to an untracked remote branch aren't merged
+        /// into the local branch, and aren't pushed.
+        pub untracked_remotes: BTreeSet<String>,
     }
 }
 
@@ -257,4 +262,17 @@ pub trait OpStore: Send + Sync + Debug {
     fn read_operation(&self, id: &OperationId) -> OpStoreResult<Operation>;
 
     fn write_operation(&self, contents: &Operation) -> OpStoreResult<OperationId>;
+
+    /// Removes operations and views which are unreachable from the
+    /// `head_ids`.
+    ///
+    /// Objects newer than `keep_newer` are preserved even if they are
+    /// unreachable, so that operations written by concurrent processes aren't
+    /// lost. Expired operations which are still reachable are kept; use
+    /// `operation::truncate_ancestors()` to make them unreachable first.
+    ///
+    /// The default implementation doesn't remove anything.
+    fn gc(&self, _head_ids: &[OperationId], _keep_newer: SystemTime) -> OpStoreResult<()> {
+        Ok(())
+    }
 }
@@ -15,10 +15,13 @@
 #![allow(missing_docs)]
 
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Error, Formatter};
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use itertools::Itertools as _;
 
 use crate::backend::CommitId;
 use crate::op_store::{OpStore, OpStoreResult, OperationId, ViewId};
@@ -199,3 +202,125 @@ pub fn walk_ancestors(head_op: &Operation) -> impl Iterator<Item = Operation> {
     )
     .map(|OperationByEndTime(op)| op)
 }
+
+fn collect_ancestor_ids(ops: &[Operation]) -> HashSet<OperationId> {
+    dag_walk::dfs(
+        ops.to_vec(),
+        |op: &Operation| op.id().clone(),
+        |op: &Operation| op.parents(),
+    )
+    .map(|op| op.id().clone())
+    .collect()
+}
+
+/// Result of [`reparent_range()`].
+#[derive(Clone, Debug)]
+pub struct ReparentStats {
+    /// New ids of the `head_ops`, in the same order.
+    pub new_head_ids: Vec<OperationId>,
+    /// Map of the reparented operation ids to their new ids.
+    pub rewritten_ids: HashMap<OperationId, OperationId>,
+    /// Number of operations which are no longer reachable from the new heads.
+    pub unreachable_count: usize,
+}
+
+/// Reparents the operations in `root_ops..head_ops` onto `dest_ops`.
+///
+/// The operations in `dest_ops..root_ops`, including the `root_ops`
+/// themselves, become unreachable from the new heads. If `dest_ops` is empty,
+/// the reparented operations whose parents were dropped have no parents.
+///
+/// The new operations are written to the `op_store`, but it's up to the caller
+/// to update the op heads.
+pub fn reparent_range(
+    op_store: &dyn OpStore,
+    root_ops: &[Operation],
+    head_ops: &[Operation],
+    dest_ops: &[Operation],
+) -> OpStoreResult<ReparentStats> {
+    let root_ancestor_ids = collect_ancestor_ids(root_ops);
+    let dest_ancestor_ids = collect_ancestor_ids(dest_ops);
+    let ops_to_reparent = dag_walk::topo_order_reverse(
+        head_ops
+            .iter()
+            .filter(|op| !root_ancestor_ids.contains(op.id()))
+            .cloned(),
+        |op: &Operation| op.id().clone(),
+        |op: &Operation| {
+            op.parents()
+                .into_iter()
+                .filter(|parent| !root_ancestor_ids.contains(parent.id()))
+                .collect_vec()
+        },
+    );
+
+    let dest_ids = dest_ops.iter().map(|op| op.id().clone()).collect_vec();
+    let mut rewritten_ids: HashMap<OperationId, OperationId> = HashMap::new();
+    for old_op in ops_to_reparent.into_iter().rev() {
+        let mut data = old_op.store_operation().clone();
+        data.parents = data
+            .parents
+            .iter()
+            .flat_map(|id| match rewritten_ids.get(id) {
+                Some(new_id) => vec![new_id.clone()],
+                None => dest_ids.clone(),
+            })
+            .unique()
+            .collect();
+        let new_id = op_store.write_operation(&data)?;
+        rewritten_ids.insert(old_op.id().clone(), new_id);
+    }
+
+    let new_head_ids = head_ops
+        .iter()
+        .map(|op| rewritten_ids.get(op.id()).unwrap_or(op.id()).clone())
+        .collect();
+    let unreachable_count = root_ancestor_ids.difference(&dest_ancestor_ids).count();
+    Ok(ReparentStats {
+        new_head_ids,
+        rewritten_ids,
+        unreachable_count,
+    })
+}
+
+/// Drops the ancestors of `head_ops` which ended before `keep_newer`.
+///
+/// The `head_ops` themselves are kept regardless of their age. Descendants of
+/// the dropped operations are reparented so that the dropped operations become
+/// unreachable, as in [`reparent_range()`]. It's up to the caller to update
+/// the op heads.
+pub fn truncate_ancestors(
+    op_store: &dyn OpStore,
+    head_ops: &[Operation],
+    keep_newer: SystemTime,
+) -> OpStoreResult<ReparentStats> {
+    let keep_newer_millis = keep_newer.duration_since(UNIX_EPOCH).map_or(0, |duration| {
+        i64::try_from(duration.as_millis()).unwrap_or(i64::MAX)
+    });
+    let is_expired =
+        |op: &Operation| op.store_operation().metadata.end_time.timestamp.0 < keep_newer_millis;
+    let head_ids: HashSet<_> = head_ops.iter().map(|op| op.id()).collect();
+    // Stop at the newest expired operations. Their ancestors will become
+    // unreachable with them.
+    let mut expired_ops = vec![];
+    let mut visited_ids = HashSet::new();
+    let mut pending_ops = head_ops.to_vec();
+    while let Some(op) = pending_ops.pop() {
+        if !visited_ids.insert(op.id().clone()) {
+            continue;
+        }
+        if !head_ids.contains(op.id()) && is_expired(&op) {
+            expired_ops.push(op);
+        } else {
+            pending_ops.extend(op.parents());
+        }
+    }
+    if expired_ops.is_empty() {
+        return Ok(ReparentStats {
+            new_head_ids: head_ops.iter().map(|op| op.id().clone()).collect(),
+            rewritten_ids: HashMap::new(),
+            unreachable_count: 0,
+        });
+    }
+    reparent_range(op_store, &expired_ops, head_ops, &[])
+}
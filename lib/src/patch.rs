@@ -0,0 +1,759 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing of unified and Git-style diffs, and applying them to trees.
+
+#![allow(missing_docs)]
+
+use std::io::Read;
+use std::sync::Arc;
+
+use itertools::Itertools as _;
+use thiserror::Error;
+
+use crate::backend::{BackendError, MergedTreeId, ObjectId, TreeValue};
+use crate::merge::Merge;
+use crate::merged_tree::{MergedTree, MergedTreeBuilder};
+use crate::repo_path::RepoPath;
+use crate::store::Store;
+
+#[derive(Debug, Error)]
+pub enum PatchParseError {
+    #[error("Invalid hunk header: {0}")]
+    InvalidHunkHeader(String),
+    #[error("Unexpected end of hunk in {0}")]
+    TruncatedHunk(String),
+    #[error("Unsupported file mode {mode} for {path}")]
+    UnsupportedMode { path: String, mode: String },
+    #[error("Binary patches are not supported ({0})")]
+    Binary(String),
+    #[error("Patch has neither an old nor a new path")]
+    MissingPath,
+    #[error("Invalid path in patch: {0}")]
+    InvalidPath(String),
+}
+
+#[derive(Debug, Error)]
+pub enum PatchApplyError {
+    #[error("Cannot apply a patch to {0}, which has unresolved conflicts")]
+    Conflicted(String),
+    #[error("Cannot apply a patch to {0}, which is not a file")]
+    NotAFile(String),
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+}
+
+/// The mode of a file as given in a Git patch.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PatchFileMode {
+    Normal,
+    Executable,
+    Symlink,
+}
+
+impl PatchFileMode {
+    fn parse(mode: &str, path: &str) -> Result<Self, PatchParseError> {
+        match mode {
+            "100644" => Ok(PatchFileMode::Normal),
+            "100755" => Ok(PatchFileMode::Executable),
+            "120000" => Ok(PatchFileMode::Symlink),
+            _ => Err(PatchParseError::UnsupportedMode {
+                path: path.to_owned(),
+                mode: mode.to_owned(),
+            }),
+        }
+    }
+}
+
+/// A hunk of a patch. Lines include their line terminator, if any.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PatchHunk {
+    /// The 1-based line number of the first old line, as given in the header.
+    pub old_start: usize,
+    /// The context and removed lines.
+    pub old_lines: Vec<Vec<u8>>,
+    /// The context and added lines.
+    pub new_lines: Vec<Vec<u8>>,
+}
+
+/// The changes to a single file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FilePatch {
+    /// The path before the change, or `None` if the file is added.
+    pub old_path: Option<RepoPath>,
+    /// The path after the change, or `None` if the file is deleted.
+    pub new_path: Option<RepoPath>,
+    /// Whether the file at `old_path` is kept when it's renamed.
+    pub is_copy: bool,
+    pub old_mode: Option<PatchFileMode>,
+    pub new_mode: Option<PatchFileMode>,
+    pub hunks: Vec<PatchHunk>,
+}
+
+impl FilePatch {
+    fn new() -> Self {
+        FilePatch {
+            old_path: None,
+            new_path: None,
+            is_copy: false,
+            old_mode: None,
+            new_mode: None,
+            hunks: vec![],
+        }
+    }
+
+    /// The path for messages.
+    pub fn display_path(&self) -> String {
+        self.new_path
+            .as_ref()
+            .or(self.old_path.as_ref())
+            .map(|path| path.to_internal_file_string())
+            .unwrap_or_default()
+    }
+}
+
+/// Parses the file patches in `text`. Lines that aren't part of a patch, such
+/// as a commit message or a diffstat, are ignored.
+pub fn parse_patch(text: &[u8]) -> Result<Vec<FilePatch>, PatchParseError> {
+    let mut files: Vec<FilePatch> = vec![];
+    // Whether each file was started by a `diff --git` line
+    let mut is_git = vec![];
+    // Whether the last file was started by a `diff --git` line, in which case
+    // `---`/`+++` lines don't start a new file.
+    let mut in_git_header = false;
+    let mut lines = text.split_inclusive(|b| *b == b'\n');
+    while let Some(raw_line) = lines.next() {
+        let line = String::from_utf8_lossy(raw_line);
+        let line = line.trim_end_matches(['\n', '\r']);
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            let mut file = FilePatch::new();
+            if let Some((old, new)) = rest.split_once(" b/") {
+                file.old_path = Some(parse_path(old, "a/")?);
+                file.new_path = Some(parse_path(new, "")?);
+            }
+            files.push(file);
+            is_git.push(true);
+            in_git_header = true;
+        } else if let Some(path) = line.strip_prefix("--- ") {
+            if !in_git_header {
+                files.push(FilePatch::new());
+                is_git.push(false);
+            }
+            in_git_header = false;
+            let file = files.last_mut().unwrap();
+            file.old_path = parse_header_path(path, "a/")?;
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            if let Some(file) = files.last_mut() {
+                file.new_path = parse_header_path(path, "b/")?;
+            }
+        } else if line.starts_with("@@ ") {
+            let Some(file) = files.last_mut() else {
+                continue;
+            };
+            in_git_header = false;
+            let (old_start, mut old_count, mut new_count) = parse_hunk_header(line)
+                .ok_or_else(|| PatchParseError::InvalidHunkHeader(line.to_owned()))?;
+            let mut hunk = PatchHunk {
+                old_start,
+                ..Default::default()
+            };
+            // Which sides the last line was added to, for "\ No newline".
+            let mut last_sides = (false, false);
+            while old_count > 0 || new_count > 0 || next_is_no_newline(&lines) {
+                let line = lines
+                    .next()
+                    .ok_or_else(|| PatchParseError::TruncatedHunk(file.display_path()))?;
+                let (first, content) = match line.split_first() {
+                    Some((first, content)) => (*first, content),
+                    None => (b' ', line),
+                };
+                last_sides = match first {
+                    b' ' => (true, true),
+                    // Some tools strip the space from empty context lines.
+                    b'\n' | b'\r' => (true, true),
+                    b'-' => (true, false),
+                    b'+' => (false, true),
+                    b'\\' => {
+                        if last_sides.0 {
+                            strip_newline(hunk.old_lines.last_mut());
+                        }
+                        if last_sides.1 {
+                            strip_newline(hunk.new_lines.last_mut());
+                        }
+                        continue;
+                    }
+                    _ => return Err(PatchParseError::TruncatedHunk(file.display_path())),
+                };
+                let content = if first == b'\n' || first == b'\r' {
+                    line
+                } else {
+                    content
+                };
+                if last_sides.0 {
+                    hunk.old_lines.push(content.to_vec());
+                    old_count = old_count.saturating_sub(1);
+                }
+                if last_sides.1 {
+                    hunk.new_lines.push(content.to_vec());
+                    new_count = new_count.saturating_sub(1);
+                }
+            }
+            file.hunks.push(hunk);
+        } else if let Some(file) = files.last_mut().filter(|_| in_git_header) {
+            let path = file.display_path();
+            if let Some(mode) = line.strip_prefix("new file mode ") {
+                file.old_path = None;
+                file.new_mode = Some(PatchFileMode::parse(mode, &path)?);
+            } else if let Some(mode) = line.strip_prefix("deleted file mode ") {
+                file.new_path = None;
+                file.old_mode = Some(PatchFileMode::parse(mode, &path)?);
+            } else if let Some(mode) = line.strip_prefix("old mode ") {
+                file.old_mode = Some(PatchFileMode::parse(mode, &path)?);
+            } else if let Some(mode) = line.strip_prefix("new mode ") {
+                file.new_mode = Some(PatchFileMode::parse(mode, &path)?);
+            } else if let Some(index) = line.strip_prefix("index ") {
+                if let Some((_, mode)) = index.split_once(' ') {
+                    let mode = PatchFileMode::parse(mode, &path)?;
+                    file.old_mode.get_or_insert(mode);
+                    file.new_mode.get_or_insert(mode);
+                }
+            } else if let Some(old) = line
+                .strip_prefix("rename from ")
+                .or_else(|| line.strip_prefix("copy from "))
+            {
+                file.old_path = Some(parse_path(old, "")?);
+                file.is_copy = line.starts_with("copy");
+            } else if let Some(new) = line
+                .strip_prefix("rename to ")
+                .or_else(|| line.strip_prefix("copy to "))
+            {
+                file.new_path = Some(parse_path(new, "")?);
+            } else if line == "GIT binary patch" || line.starts_with("Binary files ") {
+                return Err(PatchParseError::Binary(path));
+            }
+        }
+    }
+    // `---` lines without hunks are probably part of a commit message.
+    let files = files
+        .into_iter()
+        .zip(is_git)
+        .filter(|(file, is_git)| *is_git || !file.hunks.is_empty())
+        .map(|(mut file, is_git)| {
+            // Traditional diffs don't have renames. The old path is usually a
+            // backup of the file, like `file.orig`.
+            if !is_git && file.old_path.is_some() && file.new_path.is_some() {
+                file.old_path = file.new_path.clone();
+            }
+            file
+        })
+        .collect_vec();
+    if files
+        .iter()
+        .any(|file| file.old_path.is_none() && file.new_path.is_none())
+    {
+        return Err(PatchParseError::MissingPath);
+    }
+    Ok(files)
+}
+
+fn next_is_no_newline<'a>(lines: &(impl Iterator<Item = &'a [u8]> + Clone)) -> bool {
+    lines
+        .clone()
+        .next()
+        .is_some_and(|line| line.starts_with(b"\\"))
+}
+
+fn strip_newline(line: Option<&mut Vec<u8>>) {
+    if let Some(line) = line {
+        if line.ends_with(b"\n") {
+            line.pop();
+            if line.ends_with(b"\r") {
+                line.pop();
+            }
+        }
+    }
+}
+
+/// Parses a path in the patch, stripping the `prefix`. Like `git apply`,
+/// rejects paths which could escape the working copy or touch the `.jj` and
+/// `.git` directories.
+fn parse_path(path: &str, prefix: &str) -> Result<RepoPath, PatchParseError> {
+    let path = path.trim_matches('"');
+    let path = path.strip_prefix(prefix).unwrap_or(path);
+    let is_valid_component = |component: &str| {
+        !matches!(component, "" | "." | "..")
+            && !component.eq_ignore_ascii_case(".git")
+            && !component.eq_ignore_ascii_case(".jj")
+    };
+    if !path.split('/').all(is_valid_component) {
+        return Err(PatchParseError::InvalidPath(path.to_owned()));
+    }
+    Ok(RepoPath::from_internal_string(path))
+}
+
+/// Parses the path of a `---` or `+++` line. `diff -u` appends a timestamp
+/// separated by a tab.
+fn parse_header_path(path: &str, prefix: &str) -> Result<Option<RepoPath>, PatchParseError> {
+    let path = path.split('\t').next().unwrap();
+    if path == "/dev/null" {
+        Ok(None)
+    } else {
+        parse_path(path, prefix).map(Some)
+    }
+}
+
+/// Parses `@@ -a,b +c,d @@` into the old start and the old and new line counts.
+fn parse_hunk_header(line: &str) -> Option<(usize, usize, usize)> {
+    let mut ranges = line.strip_prefix("@@ ")?.split_whitespace();
+    let old = ranges.next()?.strip_prefix('-')?;
+    let new = ranges.next()?.strip_prefix('+')?;
+    let parse_range = |range: &str| -> Option<(usize, usize)> {
+        match range.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((range.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_count) = parse_range(old)?;
+    let (_, new_count) = parse_range(new)?;
+    Some((old_start, old_count, new_count))
+}
+
+/// The result of applying hunks to some content. If some hunks didn't apply,
+/// `conflict` holds the base and the patched side of a 3-way conflict whose
+/// other side is `content`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ApplyResult {
+    pub content: Vec<u8>,
+    pub conflict: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+/// Applies hunks to `content`. A hunk applies if its old lines are found in
+/// the content, preferably near where the hunk says they should be.
+///
+/// Hunks that don't apply become a conflict: the region of `content` where
+/// the hunk was expected is one side, the hunk's old lines are the base, and
+/// its new lines are the other side.
+pub fn apply_hunks(content: &[u8], hunks: &[PatchHunk]) -> ApplyResult {
+    let lines: Vec<&[u8]> = content.split_inclusive(|b| *b == b'\n').collect();
+    let mut current = vec![];
+    let mut base = vec![];
+    let mut patched = vec![];
+    let mut has_conflict = false;
+    // Index of the first line of `lines` that hasn't been copied yet
+    let mut pos = 0;
+    // How far the hunks have been found from where they say they apply
+    let mut offset: isize = 0;
+    for hunk in hunks {
+        let start = if hunk.old_lines.is_empty() {
+            hunk.old_start
+        } else {
+            hunk.old_start.saturating_sub(1)
+        };
+        let expected = (start as isize + offset).clamp(pos as isize, lines.len() as isize) as usize;
+        let found = find_lines(&lines, &hunk.old_lines, pos, expected);
+        let (index, end) = match found {
+            Some(index) => (index, index + hunk.old_lines.len()),
+            None => (expected, (expected + hunk.old_lines.len()).min(lines.len())),
+        };
+        for line in &lines[pos..index] {
+            current.extend_from_slice(line);
+            base.extend_from_slice(line);
+            patched.extend_from_slice(line);
+        }
+        if found.is_some() {
+            offset = index as isize - start as isize;
+            for line in &hunk.new_lines {
+                current.extend_from_slice(line);
+                base.extend_from_slice(line);
+                patched.extend_from_slice(line);
+            }
+        } else {
+            has_conflict = true;
+            for line in &lines[index..end] {
+                current.extend_from_slice(line);
+            }
+            base.extend(hunk.old_lines.concat());
+            patched.extend(hunk.new_lines.concat());
+        }
+        pos = end;
+    }
+    for line in &lines[pos..] {
+        current.extend_from_slice(line);
+        base.extend_from_slice(line);
+        patched.extend_from_slice(line);
+    }
+    ApplyResult {
+        content: current,
+        conflict: has_conflict.then_some((base, patched)),
+    }
+}
+
+/// Finds `needle` in `lines` at or after `min_index`, preferring the match
+/// closest to `expected`.
+fn find_lines(
+    lines: &[&[u8]],
+    needle: &[Vec<u8>],
+    min_index: usize,
+    expected: usize,
+) -> Option<usize> {
+    let matches_at = |index: usize| {
+        index + needle.len() <= lines.len()
+            && needle
+                .iter()
+                .zip(&lines[index..])
+                .all(|(a, b)| a.as_slice() == *b)
+    };
+    (0..=lines.len()).find_map(|distance| {
+        let after = expected + distance;
+        let before = expected.checked_sub(distance).filter(|&i| i >= min_index);
+        if matches_at(after) {
+            Some(after)
+        } else {
+            before.filter(|&i| matches_at(i))
+        }
+    })
+}
+
+/// Applies the patches to `tree`. Hunks that don't apply result in conflicts
+/// in the returned tree rather than in an error.
+pub fn apply_patch(
+    store: &Arc<Store>,
+    tree: &MergedTree,
+    patches: &[FilePatch],
+) -> Result<MergedTreeId, PatchApplyError> {
+    let mut tree_builder = MergedTreeBuilder::new(tree.id());
+    for patch in patches {
+        let current = match &patch.old_path {
+            Some(old_path) => read_file(store, tree, old_path)?,
+            None => None,
+        };
+        let mode = patch
+            .new_mode
+            .or(current.as_ref().map(|(mode, _)| *mode))
+            .unwrap_or(PatchFileMode::Normal);
+        let old_content = patch
+            .hunks
+            .iter()
+            .flat_map(|hunk| &hunk.old_lines)
+            .flatten()
+            .copied()
+            .collect::<Vec<u8>>();
+        let new_content = patch
+            .hunks
+            .iter()
+            .flat_map(|hunk| &hunk.new_lines)
+            .flatten()
+            .copied()
+            .collect::<Vec<u8>>();
+        let value = match (&patch.old_path, &patch.new_path, current) {
+            (None, Some(new_path), _) => {
+                let new_value = write_file(store, new_path, mode, &new_content)?;
+                match read_file(store, tree, new_path)? {
+                    // The file was added on both sides
+                    Some((current_mode, current)) => Merge::new(
+                        vec![None],
+                        vec![
+                            write_file(store, new_path, current_mode, &current)?,
+                            new_value,
+                        ],
+                    ),
+                    None => Merge::resolved(new_value),
+                }
+            }
+            // The file to delete doesn't exist
+            (Some(_), None, None) => Merge::absent(),
+            (Some(old_path), new_path, None) => {
+                // The file to modify doesn't exist
+                let path = new_path.as_ref().unwrap_or(old_path);
+                let new_value = match new_path {
+                    Some(_) => write_file(store, path, mode, &new_content)?,
+                    None => None,
+                };
+                Merge::new(
+                    vec![write_file(store, path, mode, &old_content)?],
+                    vec![None, new_value],
+                )
+            }
+            (Some(old_path), new_path, Some((current_mode, current))) => {
+                let path = new_path.as_ref().unwrap_or(old_path);
+                let result = apply_hunks(&current, &patch.hunks);
+                match (new_path, result.conflict) {
+                    (Some(_), None) => {
+                        Merge::resolved(write_file(store, path, mode, &result.content)?)
+                    }
+                    (None, None) if result.content.is_empty() => Merge::absent(),
+                    (None, _) => Merge::new(
+                        vec![write_file(store, path, current_mode, &old_content)?],
+                        vec![write_file(store, path, current_mode, &current)?, None],
+                    ),
+                    (Some(_), Some((base, patched))) => Merge::new(
+                        vec![write_file(store, path, mode, &base)?],
+                        vec![
+                            write_file(store, path, current_mode, &result.content)?,
+                            write_file(store, path, mode, &patched)?,
+                        ],
+                    ),
+                }
+            }
+            (None, None, _) => unreachable!("patch without paths"),
+        };
+        if let (Some(old_path), Some(new_path)) = (&patch.old_path, &patch.new_path) {
+            if old_path != new_path && !patch.is_copy {
+                tree_builder.set_or_remove(old_path.clone(), Merge::absent());
+            }
+        }
+        let path = patch.new_path.as_ref().or(patch.old_path.as_ref()).unwrap();
+        tree_builder.set_or_remove(path.clone(), value);
+    }
+    Ok(tree_builder.write_tree(store)?)
+}
+
+/// Reads the file at `path`, or returns `None` if there's no file.
+fn read_file(
+    store: &Store,
+    tree: &MergedTree,
+    path: &RepoPath,
+) -> Result<Option<(PatchFileMode, Vec<u8>)>, PatchApplyError> {
+    let value = tree
+        .path_value(path)
+        .into_resolved()
+        .map_err(|_| PatchApplyError::Conflicted(path.to_internal_file_string()))?;
+    match value {
+        None => Ok(None),
+        Some(TreeValue::File { id, executable }) => {
+            let mut content = vec![];
+            store
+                .read_file(path, &id)?
+                .read_to_end(&mut content)
+                .map_err(|err| BackendError::ReadObject {
+                    object_type: "file".to_string(),
+                    hash: id.hex(),
+                    source: err.into(),
+                })?;
+            let mode = if executable {
+                PatchFileMode::Executable
+            } else {
+                PatchFileMode::Normal
+            };
+            Ok(Some((mode, content)))
+        }
+        Some(TreeValue::Symlink(id)) => {
+            let target = store.read_symlink(path, &id)?;
+            Ok(Some((PatchFileMode::Symlink, target.into_bytes())))
+        }
+        Some(_) => Err(PatchApplyError::NotAFile(path.to_internal_file_string())),
+    }
+}
+
+fn write_file(
+    store: &Store,
+    path: &RepoPath,
+    mode: PatchFileMode,
+    content: &[u8],
+) -> Result<Option<TreeValue>, PatchApplyError> {
+    let value = match mode {
+        PatchFileMode::Normal | PatchFileMode::Executable => TreeValue::File {
+            id: store.write_file(path, &mut &content[..])?,
+            executable: mode == PatchFileMode::Executable,
+        },
+        PatchFileMode::Symlink => {
+            TreeValue::Symlink(store.write_symlink(path, &String::from_utf8_lossy(content))?)
+        }
+    };
+    Ok(Some(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<Vec<u8>> {
+        text.split_inclusive('\n')
+            .map(|line| line.as_bytes().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_patch() {
+        let patch = b"\
+From: Some One <some.one@example.com>
+Subject: [PATCH] Change things
+
+diff --git a/file b/file
+index 1111111111...2222222222 100644
+--- a/file
++++ b/file
+@@ -1,3 +1,3 @@
+-a
++A
+ b
+--- c
++++ C
+diff --git a/old b/new
+similarity index 90%
+rename from old
+rename to new
+diff --git a/exec b/exec
+new file mode 100755
+index 0000000000..3333333333
+--- /dev/null
++++ b/exec
+@@ -1,0 +1,1 @@
++#!/bin/sh
+\\ No newline at end of file
+--
+2.40.0
+";
+        assert_eq!(
+            parse_patch(patch).unwrap(),
+            vec![
+                FilePatch {
+                    old_path: Some(RepoPath::from_internal_string("file")),
+                    new_path: Some(RepoPath::from_internal_string("file")),
+                    is_copy: false,
+                    old_mode: Some(PatchFileMode::Normal),
+                    new_mode: Some(PatchFileMode::Normal),
+                    hunks: vec![PatchHunk {
+                        old_start: 1,
+                        old_lines: lines("a\nb\n-- c\n"),
+                        new_lines: lines("A\nb\n++ C\n"),
+                    }],
+                },
+                FilePatch {
+                    old_path: Some(RepoPath::from_internal_string("old")),
+                    new_path: Some(RepoPath::from_internal_string("new")),
+                    is_copy: false,
+                    old_mode: None,
+                    new_mode: None,
+                    hunks: vec![],
+                },
+                FilePatch {
+                    old_path: None,
+                    new_path: Some(RepoPath::from_internal_string("exec")),
+                    is_copy: false,
+                    old_mode: None,
+                    new_mode: Some(PatchFileMode::Executable),
+                    hunks: vec![PatchHunk {
+                        old_start: 1,
+                        old_lines: vec![],
+                        new_lines: lines("#!/bin/sh"),
+                    }],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_patch_errors() {
+        assert_matches::assert_matches!(
+            parse_patch(b"--- a/file\n+++ b/file\n@@ -1 +x @@\n"),
+            Err(PatchParseError::InvalidHunkHeader(_))
+        );
+        assert_matches::assert_matches!(
+            parse_patch(b"--- a/file\n+++ b/file\n@@ -1,2 +1,2 @@\n-a\n+b\n"),
+            Err(PatchParseError::TruncatedHunk(_))
+        );
+        assert_matches::assert_matches!(
+            parse_patch(b"diff --git a/f b/f\nBinary files a/f and b/f differ\n"),
+            Err(PatchParseError::Binary(_))
+        );
+        assert_matches::assert_matches!(
+            parse_patch(b"diff --git a/f b/f\nnew file mode 160000\n"),
+            Err(PatchParseError::UnsupportedMode { .. })
+        );
+    }
+
+    #[test]
+    fn test_parse_patch_invalid_paths() {
+        let parse_header = |old: &str, new: &str| {
+            let patch = format!("--- {old}\n+++ {new}\n@@ -1 +1 @@\n-a\n+b\n");
+            parse_patch(patch.as_bytes())
+        };
+        assert!(parse_header("a/dir/file", "b/dir/file").is_ok());
+        assert!(parse_header("/dev/null", "b/dir/file").is_ok());
+        assert!(parse_header("a/.gitignore", "b/.gitignore").is_ok());
+        for path in [
+            "../x",
+            "b/../x",
+            "b/dir/../../x",
+            "/etc/passwd",
+            "b//x",
+            "b/./x",
+            "b/.jj/repo/store",
+            "b/.git/config",
+            "b/dir/.GIT/config",
+        ] {
+            assert_matches::assert_matches!(
+                parse_header("a/file", path),
+                Err(PatchParseError::InvalidPath(_)),
+                "{path}"
+            );
+        }
+        assert_matches::assert_matches!(
+            parse_patch(b"diff --git a/../x b/../x\n"),
+            Err(PatchParseError::InvalidPath(_))
+        );
+        assert_matches::assert_matches!(
+            parse_patch(b"diff --git a/f b/g\nrename from f\nrename to .jj/g\n"),
+            Err(PatchParseError::InvalidPath(_))
+        );
+    }
+
+    #[test]
+    fn test_apply_hunks() {
+        let hunk = |old_start, old: &str, new: &str| PatchHunk {
+            old_start,
+            old_lines: lines(old),
+            new_lines: lines(new),
+        };
+        // Applies at the given position
+        assert_eq!(
+            apply_hunks(b"a\nb\nc\n", &[hunk(2, "b\n", "B\n")]),
+            ApplyResult {
+                content: b"a\nB\nc\n".to_vec(),
+                conflict: None,
+            }
+        );
+        // Applies at an offset, and later hunks take the offset into account
+        assert_eq!(
+            apply_hunks(
+                b"x\nx\na\nb\nc\nd\n",
+                &[hunk(1, "a\n", "A\n"), hunk(3, "c\n", "C\n")]
+            ),
+            ApplyResult {
+                content: b"x\nx\nA\nb\nC\nd\n".to_vec(),
+                conflict: None,
+            }
+        );
+        // Prefers the closest match
+        assert_eq!(
+            apply_hunks(b"a\nb\na\nb\n", &[hunk(3, "a\n", "A\n")]),
+            ApplyResult {
+                content: b"a\nb\nA\nb\n".to_vec(),
+                conflict: None,
+            }
+        );
+        // A hunk that doesn't apply becomes a conflict
+        assert_eq!(
+            apply_hunks(
+                b"a\nb\nc\n",
+                &[hunk(1, "a\n", "A\n"), hunk(2, "x\n", "y\n")]
+            ),
+            ApplyResult {
+                content: b"A\nb\nc\n".to_vec(),
+                conflict: Some((b"A\nx\nc\n".to_vec(), b"A\ny\nc\n".to_vec())),
+            }
+        );
+    }
+}
@@ -53,6 +53,10 @@ pub struct RemoteBranch {
     pub remote_name: ::prost::alloc::string::String,
     #[prost(message, optional, tag = "2")]
     pub target: ::core::option::Option<RefTarget>,
+    /// Set if the local branch doesn't track this remote branch. Unset in views
+    /// written by older versions.
+    #[prost(bool, optional, tag = "3")]
+    pub untracked: ::core::option::Option<bool>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -15,8 +15,26 @@ pub struct FileState {
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SparsePatterns {
+    /// Paths included along with everything below them. If `patterns` is
+    /// non-empty, these are only read by older versions, which ignore `patterns`,
+    /// and contain the directories of all included patterns.
     #[prost(string, repeated, tag = "1")]
     pub prefixes: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// All patterns if any of them is a glob or an exclusion, otherwise empty
+    #[prost(message, repeated, tag = "2")]
+    pub patterns: ::prost::alloc::vec::Vec<SparsePattern>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SparsePattern {
+    #[prost(bool, tag = "1")]
+    pub exclude: bool,
+    /// The path prefix, or the directory the glob is anchored at
+    #[prost(string, tag = "2")]
+    pub dir: ::prost::alloc::string::String,
+    /// Empty if the pattern is a plain path prefix
+    #[prost(string, tag = "3")]
+    pub glob: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -36,6 +54,9 @@ pub struct TreeState {
     pub sparse_patterns: ::core::option::Option<SparsePatterns>,
     #[prost(message, optional, tag = "4")]
     pub watchman_clock: ::core::option::Option<WatchmanClock>,
+    /// Clock returned by the built-in inotify filesystem monitor, if used
+    #[prost(string, tag = "6")]
+    pub inotify_clock: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
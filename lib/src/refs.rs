@@ -14,6 +14,8 @@
 
 #![allow(missing_docs)]
 
+use itertools::EitherOrBoth;
+
 use crate::backend::CommitId;
 use crate::index::Index;
 use crate::merge::{trivial_merge, Merge};
@@ -85,6 +87,26 @@ fn find_pair_to_remove(
     None
 }
 
+/// Compares `refs1` and `refs2` targets, yields entries that differ.
+///
+/// Both `refs1` and `refs2` must be sorted by name. A ref missing on one side
+/// is paired with an absent target.
+pub fn diff_named_ref_targets<'a, K: Ord>(
+    refs1: impl IntoIterator<Item = (K, &'a RefTarget)>,
+    refs2: impl IntoIterator<Item = (K, &'a RefTarget)>,
+) -> impl Iterator<Item = (K, (&'a RefTarget, &'a RefTarget))> {
+    itertools::merge_join_by(refs1, refs2, |(name1, _), (name2, _)| name1.cmp(name2)).filter_map(
+        |entry| {
+            let (name, target1, target2) = match entry {
+                EitherOrBoth::Both((name, target1), (_, target2)) => (name, target1, target2),
+                EitherOrBoth::Left((name, target1)) => (name, target1, RefTarget::absent_ref()),
+                EitherOrBoth::Right((name, target2)) => (name, RefTarget::absent_ref(), target2),
+            };
+            (target1 != target2).then_some((name, (target1, target2)))
+        },
+    )
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct BranchPushUpdate {
     pub old_target: Option<CommitId>,
@@ -97,6 +119,7 @@ pub enum BranchPushAction {
     AlreadyMatches,
     LocalConflicted,
     RemoteConflicted,
+    RemoteUntracked,
 }
 
 /// Figure out what changes (if any) need to be made to the remote when pushing
@@ -113,6 +136,8 @@ pub fn classify_branch_push_action(
         BranchPushAction::LocalConflicted
     } else if remote_target.has_conflict() {
         BranchPushAction::RemoteConflicted
+    } else if branch_target.untracked_remotes.contains(remote_name) {
+        BranchPushAction::RemoteUntracked
     } else {
         BranchPushAction::Update(BranchPushUpdate {
             old_target: remote_target.as_normal().cloned(),
@@ -128,6 +153,35 @@ mod tests {
     use super::*;
     use crate::backend::ObjectId;
 
+    #[test]
+    fn test_diff_named_ref_targets() {
+        let target1 = RefTarget::normal(CommitId::from_hex("11"));
+        let target2 = RefTarget::normal(CommitId::from_hex("22"));
+        let refs1 = btreemap! {
+            "added" => RefTarget::absent(),
+            "changed" => target1.clone(),
+            "removed" => target1.clone(),
+            "unchanged" => target1.clone(),
+        };
+        let refs2 = btreemap! {
+            "added" => target2.clone(),
+            "changed" => target2.clone(),
+            "unchanged" => target1.clone(),
+        };
+        assert_eq!(
+            diff_named_ref_targets(
+                refs1.iter().map(|(&name, target)| (name, target)),
+                refs2.iter().map(|(&name, target)| (name, target)),
+            )
+            .collect::<Vec<_>>(),
+            vec![
+                ("added", (RefTarget::absent_ref(), &target2)),
+                ("changed", (&target1, &target2)),
+                ("removed", (&target1, RefTarget::absent_ref())),
+            ]
+        );
+    }
+
     #[test]
     fn test_classify_branch_push_action_unchanged() {
         let commit_id1 = CommitId::from_hex("11");
@@ -136,6 +190,7 @@ mod tests {
             remote_targets: btreemap! {
                 "origin".to_string() => RefTarget::normal(commit_id1),
             },
+            untracked_remotes: Default::default(),
         };
         assert_eq!(
             classify_branch_push_action(&branch, "origin"),
@@ -149,6 +204,7 @@ mod tests {
         let branch = BranchTarget {
             local_target: RefTarget::normal(commit_id1.clone()),
             remote_targets: btreemap! {},
+            untracked_remotes: Default::default(),
         };
         assert_eq!(
             classify_branch_push_action(&branch, "origin"),
@@ -167,6 +223,7 @@ mod tests {
             remote_targets: btreemap! {
                 "origin".to_string() => RefTarget::normal(commit_id1.clone()),
             },
+            untracked_remotes: Default::default(),
         };
         assert_eq!(
             classify_branch_push_action(&branch, "origin"),
@@ -186,6 +243,7 @@ mod tests {
             remote_targets: btreemap! {
                 "origin".to_string() => RefTarget::normal(commit_id1.clone()),
             },
+            untracked_remotes: Default::default(),
         };
         assert_eq!(
             classify_branch_push_action(&branch, "origin"),
@@ -205,6 +263,7 @@ mod tests {
             remote_targets: btreemap! {
                 "origin".to_string() => RefTarget::normal(commit_id1),
             },
+            untracked_remotes: Default::default(),
         };
         assert_eq!(
             classify_branch_push_action(&branch, "origin"),
@@ -224,6 +283,7 @@ mod tests {
                     [commit_id1, commit_id2],
                 ),
             },
+            untracked_remotes: Default::default(),
         };
         assert_eq!(
             classify_branch_push_action(&branch, "origin"),
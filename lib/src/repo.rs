@@ -31,7 +31,7 @@ use tracing::instrument;
 use self::dirty_cell::DirtyCell;
 use crate::backend::{
     Backend, BackendError, BackendInitError, BackendLoadError, BackendResult, ChangeId, CommitId,
-    MergedTreeId, ObjectId,
+    MergedTreeId, ObjectId, SigningFn,
 };
 use crate::commit::{Commit, CommitByCommitterTimestamp};
 use crate::commit_builder::CommitBuilder;
@@ -45,9 +45,10 @@ use crate::op_heads_store::{self, OpHeadResolutionError, OpHeadsStore};
 use crate::op_store::{BranchTarget, OpStore, OpStoreError, OperationId, RefTarget, WorkspaceId};
 use crate::operation::Operation;
 use crate::refs::merge_ref_targets;
-use crate::revset::{self, ChangeIdIndex, Revset, RevsetExpression};
+use crate::revset::{self, ChangeIdIndex};
 use crate::rewrite::DescendantRebaser;
 use crate::settings::{RepoSettings, UserSettings};
+use crate::signing::Signer;
 use crate::simple_op_heads_store::SimpleOpHeadsStore;
 use crate::simple_op_store::SimpleOpStore;
 use crate::store::Store;
@@ -152,7 +153,8 @@ impl ReadonlyRepo {
         let backend = backend_factory(&store_path)?;
         let backend_path = store_path.join("type");
         fs::write(&backend_path, backend.name()).context(&backend_path)?;
-        let store = Store::new(backend, user_settings.use_tree_conflict_format());
+        let signer = Signer::from_settings(user_settings);
+        let store = Store::new(backend, signer, user_settings.use_tree_conflict_format());
         let repo_settings = user_settings.with_repo(&repo_path).unwrap();
 
         let op_store_path = repo_path.join("op_store");
@@ -260,13 +262,11 @@ impl ReadonlyRepo {
         let change_id_index: &'a (dyn ChangeIdIndex + 'a) = self
             .change_id_index
             .get_or_init(|| {
-                let revset: Box<dyn Revset<'a>> = RevsetExpression::all()
-                    .resolve(self)
-                    .unwrap()
-                    .evaluate(self)
-                    .unwrap();
-                let change_id_index: Box<dyn ChangeIdIndex + 'a> = revset.change_id_index();
-                // evaluate() above only borrows the index, not the whole repo
+                let change_id_index: Box<dyn ChangeIdIndex + 'a> = self
+                    .readonly_index()
+                    .as_index()
+                    .change_id_index(&mut self.view().heads().iter());
+                // The change id index only borrows the index, not the whole repo
                 let change_id_index: Box<dyn ChangeIdIndex> =
                     unsafe { std::mem::transmute(change_id_index) };
                 change_id_index
@@ -587,6 +587,7 @@ impl RepoLoader {
     ) -> Result<Self, StoreLoadError> {
         let store = Store::new(
             store_factories.load_backend(&repo_path.join("store"))?,
+            Signer::from_settings(user_settings),
             user_settings.use_tree_conflict_format(),
         );
         let repo_settings = user_settings.with_repo(repo_path).unwrap();
@@ -766,8 +767,12 @@ impl MutableRepo {
         CommitBuilder::for_rewrite_from(self, settings, predecessor)
     }
 
-    pub fn write_commit(&mut self, commit: backend::Commit) -> BackendResult<Commit> {
-        let commit = self.store().write_commit(commit)?;
+    pub fn write_commit(
+        &mut self,
+        commit: backend::Commit,
+        sign_with: Option<&mut SigningFn>,
+    ) -> BackendResult<Commit> {
+        let commit = self.store().write_commit(commit, sign_with)?;
         self.add_head(&commit);
         Ok(commit)
     }
@@ -1010,6 +1015,16 @@ impl MutableRepo {
             .set_remote_branch_target(name, remote_name, target);
     }
 
+    pub fn is_remote_branch_tracked(&self, name: &str, remote_name: &str) -> bool {
+        self.view
+            .with_ref(|v| v.is_remote_branch_tracked(name, remote_name))
+    }
+
+    pub fn set_remote_branch_tracked(&mut self, name: &str, remote_name: &str, tracked: bool) {
+        self.view_mut()
+            .set_remote_branch_tracked(name, remote_name, tracked);
+    }
+
     pub fn rename_remote(&mut self, old: &str, new: &str) {
         self.view_mut().rename_remote(old, new);
     }
@@ -1159,6 +1174,18 @@ impl MutableRepo {
             );
         }
 
+        // Apply the other side's changes to the tracking state of remote
+        // branches. If both sides changed it, the other side wins.
+        for (branch_name, other_branch) in other.branches() {
+            for remote in other_branch.remote_targets.keys() {
+                let other_tracked = !other_branch.untracked_remotes.contains(remote);
+                if other_tracked != base.is_remote_branch_tracked(branch_name, remote) {
+                    self.view_mut()
+                        .set_remote_branch_tracked(branch_name, remote, other_tracked);
+                }
+            }
+        }
+
         let new_git_head_target = merge_ref_targets(
             self.index(),
             self.view().git_head(),
@@ -1254,22 +1281,16 @@ impl Repo for MutableRepo {
     }
 
     fn resolve_change_id_prefix(&self, prefix: &HexPrefix) -> PrefixResolution<Vec<CommitId>> {
-        let revset = RevsetExpression::all()
-            .resolve(self)
-            .unwrap()
-            .evaluate(self)
-            .unwrap();
-        let change_id_index = revset.change_id_index();
+        let change_id_index = self
+            .index()
+            .change_id_index(&mut self.view().heads().iter());
         change_id_index.resolve_prefix(prefix)
     }
 
     fn shortest_unique_change_id_prefix_len(&self, target_id: &ChangeId) -> usize {
-        let revset = RevsetExpression::all()
-            .resolve(self)
-            .unwrap()
-            .evaluate(self)
-            .unwrap();
-        let change_id_index = revset.change_id_index();
+        let change_id_index = self
+            .index()
+            .change_id_index(&mut self.view().heads().iter());
         change_id_index.shortest_unique_prefix_len(target_id)
     }
 }
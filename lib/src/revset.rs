@@ -34,14 +34,16 @@ use thiserror::Error;
 
 use crate::backend::{BackendError, BackendResult, ChangeId, CommitId, ObjectId};
 use crate::commit::Commit;
+use crate::fileset::{self, FilesetExpression, FilesetParseContext, FilesetParseError};
 use crate::git::{self, get_local_git_tracking_branch};
 use crate::hex_util::to_forward_hex;
 use crate::index::{HexPrefix, PrefixResolution};
 use crate::op_store::WorkspaceId;
-use crate::repo::Repo;
-use crate::repo_path::{FsPathParseError, RepoPath};
+use crate::repo::{ReadonlyRepo, Repo};
 use crate::revset_graph::RevsetGraphEdge;
+use crate::signing::SigStatus;
 use crate::store::Store;
+use crate::time_util::{DatePattern, DatePatternContext};
 
 /// Error occurred during symbol resolution.
 #[derive(Debug, Error)]
@@ -59,6 +61,8 @@ pub enum RevsetResolutionError {
     AmbiguousCommitIdPrefix(String),
     #[error("Change ID prefix \"{0}\" is ambiguous")]
     AmbiguousChangeIdPrefix(String),
+    #[error("Cannot resolve operation \"{name}\": {message}")]
+    InvalidOperation { name: String, message: String },
     #[error("Unexpected error from store: {0}")]
     StoreError(#[source] BackendError),
 }
@@ -174,8 +178,8 @@ pub enum RevsetParseErrorKind {
     },
     #[error("Invalid arguments to revset function \"{name}\": {message}")]
     InvalidFunctionArguments { name: String, message: String },
-    #[error("Invalid file pattern: {0}")]
-    FsPathParseError(#[source] FsPathParseError),
+    #[error("Invalid file pattern: {}", .0.kind())]
+    FilesetParseError(#[source] FilesetParseError),
     #[error("Cannot resolve file pattern without workspace")]
     FsPathWithoutWorkspace,
     #[error(r#"Cannot resolve "@" without workspace"#)]
@@ -305,14 +309,33 @@ fn rename_rules_in_pest_error(mut err: pest::error::Error<Rule>) -> pest::error:
 pub const GENERATION_RANGE_FULL: Range<u64> = 0..u64::MAX;
 pub const GENERATION_RANGE_EMPTY: Range<u64> = 0..0;
 
+/// Error occurred during parsing of a [`StringPattern`].
+#[derive(Debug, Error)]
+pub enum StringPatternParseError {
+    /// Unknown pattern kind is specified.
+    #[error(r#"Invalid string pattern kind "{0}""#)]
+    InvalidKind(String),
+    /// Failed to parse glob pattern.
+    #[error(transparent)]
+    GlobPattern(#[from] glob::PatternError),
+    /// Failed to parse regular expression.
+    #[error(transparent)]
+    Regex(#[from] regex::Error),
+}
+
 /// Pattern to be tested against string property like commit description or
 /// branch name.
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug)]
 pub enum StringPattern {
     /// Matches strings exactly equal to `string`.
     Exact(String),
     /// Matches strings that contain `substring`.
     Substring(String),
+    /// Matches strings with Unix-style shell wildcard pattern.
+    Glob(glob::Pattern),
+    /// Matches strings against the regular expression. The pattern is not
+    /// anchored, so `regex:"foo"` matches any string containing `foo`.
+    Regex(regex::Regex),
 }
 
 impl StringPattern {
@@ -321,25 +344,102 @@ impl StringPattern {
         StringPattern::Substring(String::new())
     }
 
-    /// Returns true if this pattern matches the `haystack`.
-    pub fn matches(&self, haystack: &str) -> bool {
-        match self {
-            StringPattern::Exact(literal) => haystack == literal,
-            StringPattern::Substring(needle) => haystack.contains(needle),
+    /// Parses the given string as a `StringPattern`. Everything before the
+    /// first ":" is considered the string's prefix. If the prefix is
+    /// "exact:", "substring:", "glob:", or "regex:", a pattern of the
+    /// specified kind is returned. Returns an error if the string has an
+    /// unrecognized prefix. Otherwise, a `StringPattern::Exact` is returned.
+    pub fn parse(src: &str) -> Result<StringPattern, StringPatternParseError> {
+        if let Some((kind, pat)) = src.split_once(':') {
+            StringPattern::from_str_kind(pat, kind)
+        } else {
+            Ok(StringPattern::Exact(src.to_owned()))
         }
     }
 
+    /// Creates a pattern of the given `kind` from the `src` string.
+    pub fn from_str_kind(src: &str, kind: &str) -> Result<Self, StringPatternParseError> {
+        match kind {
+            "exact" => Ok(StringPattern::Exact(src.to_owned())),
+            "substring" => Ok(StringPattern::Substring(src.to_owned())),
+            "glob" => StringPattern::glob(src),
+            "regex" => StringPattern::regex(src),
+            _ => Err(StringPatternParseError::InvalidKind(kind.to_owned())),
+        }
+    }
+
+    /// Parses the given string as glob pattern.
+    pub fn glob(src: &str) -> Result<Self, StringPatternParseError> {
+        // TODO: might be better to do parsing and compilation separately since
+        // not all backends would use the compiled pattern object.
+        // TODO: if no meta character found, it can be mapped to Exact.
+        let pattern = glob::Pattern::new(src)?;
+        Ok(StringPattern::Glob(pattern))
+    }
+
+    /// Parses the given string as regular expression.
+    pub fn regex(src: &str) -> Result<Self, StringPatternParseError> {
+        let regex = regex::Regex::new(src)?;
+        Ok(StringPattern::Regex(regex))
+    }
+
+    /// Returns true if this pattern matches input strings exactly.
+    pub fn is_exact(&self) -> bool {
+        self.as_exact().is_some()
+    }
+
     /// Returns a literal pattern if this should match input strings exactly.
     ///
     /// This can be used to optimize map lookup by exact key.
     pub fn as_exact(&self) -> Option<&str> {
         match self {
             StringPattern::Exact(literal) => Some(literal),
-            StringPattern::Substring(_) => None,
+            StringPattern::Substring(_) | StringPattern::Glob(_) | StringPattern::Regex(_) => None,
+        }
+    }
+
+    /// Returns the original string of this pattern.
+    pub fn as_str(&self) -> &str {
+        match self {
+            StringPattern::Exact(literal) => literal,
+            StringPattern::Substring(needle) => needle,
+            StringPattern::Glob(pattern) => pattern.as_str(),
+            StringPattern::Regex(regex) => regex.as_str(),
+        }
+    }
+
+    /// Returns true if this pattern matches the `haystack`.
+    pub fn matches(&self, haystack: &str) -> bool {
+        match self {
+            StringPattern::Exact(literal) => haystack == literal,
+            StringPattern::Substring(needle) => haystack.contains(needle),
+            StringPattern::Glob(pattern) => pattern.matches(haystack),
+            StringPattern::Regex(regex) => regex.is_match(haystack),
+        }
+    }
+}
+
+// `regex::Regex` doesn't implement `Eq`, so compare the patterns by source.
+impl PartialEq for StringPattern {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (StringPattern::Exact(a), StringPattern::Exact(b)) => a == b,
+            (StringPattern::Substring(a), StringPattern::Substring(b)) => a == b,
+            (StringPattern::Glob(a), StringPattern::Glob(b)) => a == b,
+            (StringPattern::Regex(a), StringPattern::Regex(b)) => a.as_str() == b.as_str(),
+            _ => false,
         }
     }
 }
 
+impl Eq for StringPattern {}
+
+impl fmt::Display for StringPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
 /// Symbol or function to be resolved to `CommitId`s.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum RevsetCommitRef {
@@ -355,6 +455,9 @@ pub enum RevsetCommitRef {
     RemoteBranches {
         branch_pattern: StringPattern,
         remote_pattern: StringPattern,
+        /// Only include remote branches without a local branch of the same
+        /// name.
+        untracked_only: bool,
     },
     Tags,
     GitRefs,
@@ -371,10 +474,22 @@ pub enum RevsetFilterPredicate {
     Author(StringPattern),
     /// Commits with committer's name or email containing the needle.
     Committer(StringPattern),
-    /// Commits modifying the paths specified by the pattern.
-    File(Option<Vec<RepoPath>>), // TODO: embed matcher expression?
+    /// Commits with author date matching the pattern.
+    AuthorDate(DatePattern),
+    /// Commits with committer date matching the pattern.
+    CommitterDate(DatePattern),
+    /// Commits modifying the paths specified by the fileset.
+    File(FilesetExpression),
+    /// Commits containing diffs matching the `text` pattern within the `files`.
+    DiffContains {
+        text: StringPattern,
+        files: FilesetExpression,
+    },
     /// Commits with conflicts
     HasConflict,
+    /// Commits with a cryptographic signature, optionally only those whose
+    /// signature has the given status.
+    Signed(Option<SigStatus>),
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -416,6 +531,17 @@ pub enum RevsetExpression {
     /// Marker for subtree that should be intersected as filter.
     AsFilter(Rc<RevsetExpression>),
     Present(Rc<RevsetExpression>),
+    /// Resolves symbols in `candidates` at the given operation.
+    AtOperation {
+        operation: String,
+        candidates: Rc<RevsetExpression>,
+    },
+    /// Resolves `all()` and `visible_heads()` within `candidates` against the
+    /// given heads instead of the current ones.
+    WithinVisibility {
+        candidates: Rc<RevsetExpression>,
+        visible_heads: Vec<CommitId>,
+    },
     NotIn(Rc<RevsetExpression>),
     Union(Rc<RevsetExpression>, Rc<RevsetExpression>),
     Intersection(Rc<RevsetExpression>, Rc<RevsetExpression>),
@@ -476,6 +602,20 @@ impl RevsetExpression {
             RevsetCommitRef::RemoteBranches {
                 branch_pattern,
                 remote_pattern,
+                untracked_only: false,
+            },
+        ))
+    }
+
+    pub fn untracked_remote_branches(
+        branch_pattern: StringPattern,
+        remote_pattern: StringPattern,
+    ) -> Rc<RevsetExpression> {
+        Rc::new(RevsetExpression::CommitRef(
+            RevsetCommitRef::RemoteBranches {
+                branch_pattern,
+                remote_pattern,
+                untracked_only: true,
             },
         ))
     }
@@ -862,6 +1002,7 @@ struct ParseState<'a> {
     aliases_expanding: &'a [RevsetAliasId<'a>],
     locals: &'a HashMap<&'a str, Rc<RevsetExpression>>,
     user_email: &'a str,
+    date_pattern_context: DatePatternContext,
     workspace_ctx: &'a Option<RevsetWorkspaceContext<'a>>,
 }
 
@@ -887,6 +1028,7 @@ impl ParseState<'_> {
             aliases_expanding: &aliases_expanding,
             locals,
             user_email: self.user_email,
+            date_pattern_context: self.date_pattern_context,
             workspace_ctx: self.workspace_ctx,
         };
         f(expanding_state).map_err(|e| {
@@ -1227,23 +1369,24 @@ static BUILTIN_FUNCTION_MAP: Lazy<HashMap<&'static str, RevsetFunction>> = Lazy:
         Ok(RevsetExpression::branches(pattern))
     });
     map.insert("remote_branches", |name, arguments_pair, state| {
-        let ([], [branch_opt_arg, remote_opt_arg]) =
-            expect_named_arguments(name, &["", "remote"], arguments_pair)?;
-        let branch_pattern = if let Some(branch_arg) = branch_opt_arg {
-            parse_function_argument_to_string_pattern(name, branch_arg, state)?
-        } else {
-            StringPattern::everything()
-        };
-        let remote_pattern = if let Some(remote_arg) = remote_opt_arg {
-            parse_function_argument_to_string_pattern(name, remote_arg, state)?
-        } else {
-            StringPattern::everything()
-        };
+        let (branch_pattern, remote_pattern) =
+            parse_remote_branches_arguments(name, arguments_pair, state)?;
         Ok(RevsetExpression::remote_branches(
             branch_pattern,
             remote_pattern,
         ))
     });
+    map.insert(
+        "untracked_remote_branches",
+        |name, arguments_pair, state| {
+            let (branch_pattern, remote_pattern) =
+                parse_remote_branches_arguments(name, arguments_pair, state)?;
+            Ok(RevsetExpression::untracked_remote_branches(
+                branch_pattern,
+                remote_pattern,
+            ))
+        },
+    );
     map.insert("tags", |name, arguments_pair, _state| {
         expect_no_arguments(name, arguments_pair)?;
         Ok(RevsetExpression::tags())
@@ -1299,29 +1442,46 @@ static BUILTIN_FUNCTION_MAP: Lazy<HashMap<&'static str, RevsetFunction>> = Lazy:
             pattern,
         )))
     });
+    map.insert("author_date", |name, arguments_pair, state| {
+        let patterns = parse_date_pattern_arguments(name, arguments_pair, state)?;
+        Ok(patterns
+            .into_iter()
+            .map(|pattern| RevsetExpression::filter(RevsetFilterPredicate::AuthorDate(pattern)))
+            .reduce(|acc, expression| acc.intersection(&expression))
+            .unwrap())
+    });
+    map.insert("committer_date", |name, arguments_pair, state| {
+        let patterns = parse_date_pattern_arguments(name, arguments_pair, state)?;
+        Ok(patterns
+            .into_iter()
+            .map(|pattern| RevsetExpression::filter(RevsetFilterPredicate::CommitterDate(pattern)))
+            .reduce(|acc, expression| acc.intersection(&expression))
+            .unwrap())
+    });
     map.insert("empty", |name, arguments_pair, _state| {
         expect_no_arguments(name, arguments_pair)?;
-        Ok(RevsetExpression::filter(RevsetFilterPredicate::File(None)).negated())
+        Ok(
+            RevsetExpression::filter(RevsetFilterPredicate::File(FilesetExpression::all()))
+                .negated(),
+        )
     });
     map.insert("file", |name, arguments_pair, state| {
         if let Some(ctx) = state.workspace_ctx {
             let arguments_span = arguments_pair.as_span();
-            let paths: Vec<_> = arguments_pair
+            let expressions: Vec<_> = arguments_pair
                 .into_inner()
                 .map(|arg| -> Result<_, RevsetParseError> {
                     let span = arg.as_span();
-                    let needle = parse_function_argument_to_string(name, arg, state)?;
-                    let path = RepoPath::parse_fs_path(ctx.cwd, ctx.workspace_root, needle)
-                        .map_err(|e| {
-                            RevsetParseError::with_span(
-                                RevsetParseErrorKind::FsPathParseError(e),
-                                span,
-                            )
-                        })?;
-                    Ok(path)
+                    let text = parse_function_argument_to_string(name, arg, state)?;
+                    parse_file_argument(&text, ctx).map_err(|e| {
+                        RevsetParseError::with_span(
+                            RevsetParseErrorKind::FilesetParseError(e),
+                            span,
+                        )
+                    })
                 })
                 .try_collect()?;
-            if paths.is_empty() {
+            if expressions.is_empty() {
                 Err(RevsetParseError::with_span(
                     RevsetParseErrorKind::InvalidFunctionArguments {
                         name: name.to_owned(),
@@ -1330,9 +1490,9 @@ static BUILTIN_FUNCTION_MAP: Lazy<HashMap<&'static str, RevsetFunction>> = Lazy:
                     arguments_span,
                 ))
             } else {
-                Ok(RevsetExpression::filter(RevsetFilterPredicate::File(Some(
-                    paths,
-                ))))
+                Ok(RevsetExpression::filter(RevsetFilterPredicate::File(
+                    FilesetExpression::union_all(expressions),
+                )))
             }
         } else {
             Err(RevsetParseError::new(
@@ -1340,15 +1500,62 @@ static BUILTIN_FUNCTION_MAP: Lazy<HashMap<&'static str, RevsetFunction>> = Lazy:
             ))
         }
     });
+    map.insert("diff_contains", |name, arguments_pair, state| {
+        let ([text_arg], [files_opt_arg]) = expect_arguments(name, arguments_pair)?;
+        let text = parse_function_argument_to_string_pattern(name, text_arg, state)?;
+        let files = if let Some(files_arg) = files_opt_arg {
+            let Some(ctx) = state.workspace_ctx else {
+                return Err(RevsetParseError::new(
+                    RevsetParseErrorKind::FsPathWithoutWorkspace,
+                ));
+            };
+            let span = files_arg.as_span();
+            let text = parse_function_argument_to_string(name, files_arg, state)?;
+            parse_file_argument(&text, ctx).map_err(|e| {
+                RevsetParseError::with_span(RevsetParseErrorKind::FilesetParseError(e), span)
+            })?
+        } else {
+            FilesetExpression::all()
+        };
+        Ok(RevsetExpression::filter(
+            RevsetFilterPredicate::DiffContains { text, files },
+        ))
+    });
     map.insert("conflict", |name, arguments_pair, _state| {
         expect_no_arguments(name, arguments_pair)?;
         Ok(RevsetExpression::filter(RevsetFilterPredicate::HasConflict))
     });
+    map.insert("signed", |name, arguments_pair, state| {
+        let ([], [status_opt_arg]) = expect_arguments(name, arguments_pair)?;
+        let status = if let Some(status_arg) = status_opt_arg {
+            Some(parse_function_argument_as_literal(
+                "signature status",
+                name,
+                status_arg,
+                state,
+            )?)
+        } else {
+            None
+        };
+        Ok(RevsetExpression::filter(RevsetFilterPredicate::Signed(
+            status,
+        )))
+    });
     map.insert("present", |name, arguments_pair, state| {
         let arg = expect_one_argument(name, arguments_pair)?;
         let expression = parse_expression_rule(arg.into_inner(), state)?;
         Ok(Rc::new(RevsetExpression::Present(expression)))
     });
+    map.insert("at_operation", |name, arguments_pair, state| {
+        let ([operation_arg, candidates_arg], []) = expect_arguments(name, arguments_pair)?;
+        // The operation is an operation expression such as "@-", not a revset.
+        let operation = operation_arg.as_str().trim().to_owned();
+        let candidates = parse_expression_rule(candidates_arg.into_inner(), state)?;
+        Ok(Rc::new(RevsetExpression::AtOperation {
+            operation,
+            candidates,
+        }))
+    });
     map
 });
 
@@ -1474,6 +1681,41 @@ fn expect_named_arguments_vec<'i>(
     Ok((required, optional))
 }
 
+fn parse_remote_branches_arguments(
+    name: &str,
+    arguments_pair: Pair<Rule>,
+    state: ParseState,
+) -> Result<(StringPattern, StringPattern), RevsetParseError> {
+    let ([], [branch_opt_arg, remote_opt_arg]) =
+        expect_named_arguments(name, &["", "remote"], arguments_pair)?;
+    let branch_pattern = if let Some(branch_arg) = branch_opt_arg {
+        parse_function_argument_to_string_pattern(name, branch_arg, state)?
+    } else {
+        StringPattern::everything()
+    };
+    let remote_pattern = if let Some(remote_arg) = remote_opt_arg {
+        parse_function_argument_to_string_pattern(name, remote_arg, state)?
+    } else {
+        StringPattern::everything()
+    };
+    Ok((branch_pattern, remote_pattern))
+}
+
+fn parse_file_argument(
+    text: &str,
+    ctx: &RevsetWorkspaceContext,
+) -> Result<FilesetExpression, FilesetParseError> {
+    let fileset_ctx = FilesetParseContext {
+        cwd: ctx.cwd,
+        workspace_root: ctx.workspace_root,
+    };
+    if ctx.allow_filesets {
+        fileset::parse_maybe_bare(text, &fileset_ctx)
+    } else {
+        fileset::parse_prefix_path(text, &fileset_ctx)
+    }
+}
+
 fn parse_function_argument_to_string(
     name: &str,
     pair: Pair<Rule>,
@@ -1487,6 +1729,46 @@ fn parse_function_argument_to_string_pattern(
     pair: Pair<Rule>,
     state: ParseState,
 ) -> Result<StringPattern, RevsetParseError> {
+    parse_function_argument_as_pattern("string pattern", name, pair, state, |needle, kind| {
+        if let Some(kind) = kind {
+            StringPattern::from_str_kind(needle, kind).map_err(|err| err.to_string())
+        } else {
+            Ok(StringPattern::Substring(needle.to_owned()))
+        }
+    })
+}
+
+/// Parses one or two date pattern arguments, which are all to be matched.
+fn parse_date_pattern_arguments(
+    name: &str,
+    arguments_pair: Pair<Rule>,
+    state: ParseState,
+) -> Result<Vec<DatePattern>, RevsetParseError> {
+    let ([arg], [opt_arg]) = expect_arguments(name, arguments_pair)?;
+    [Some(arg), opt_arg]
+        .into_iter()
+        .flatten()
+        .map(|arg| {
+            parse_function_argument_as_pattern("date pattern", name, arg, state, |needle, kind| {
+                let kind = kind.ok_or_else(|| {
+                    "Expected date pattern of kind `after:` or `before:`".to_owned()
+                })?;
+                DatePattern::from_str_kind(needle, kind, &state.date_pattern_context)
+                    .map_err(|err| err.to_string())
+            })
+        })
+        .try_collect()
+}
+
+/// Parses `kind:needle` or bare `needle` argument by `parse_pattern`, which
+/// returns an error message on failure.
+fn parse_function_argument_as_pattern<T>(
+    type_name: &str,
+    name: &str,
+    pair: Pair<Rule>,
+    state: ParseState,
+    parse_pattern: impl FnOnce(&str, Option<&str>) -> Result<T, String>,
+) -> Result<T, RevsetParseError> {
     let span = pair.as_span();
     let make_error = |message| {
         RevsetParseError::with_span(
@@ -1497,12 +1779,11 @@ fn parse_function_argument_to_string_pattern(
             span,
         )
     };
-    let make_type_error = || make_error("Expected function argument of string pattern".to_owned());
+    let make_type_error = || make_error(format!("Expected function argument of {type_name}"));
     let expression = parse_expression_rule(pair.into_inner(), state)?;
-    let pattern = match expression.as_ref() {
-        RevsetExpression::CommitRef(RevsetCommitRef::Symbol(symbol)) => {
-            let needle = symbol.to_owned();
-            StringPattern::Substring(needle)
+    match expression.as_ref() {
+        RevsetExpression::CommitRef(RevsetCommitRef::Symbol(needle)) => {
+            parse_pattern(needle, None).map_err(make_error)
         }
         // TODO: Add proper parsed node if we drop support for legacy x:y range
         RevsetExpression::DagRange {
@@ -1518,20 +1799,11 @@ fn parse_function_argument_to_string_pattern(
             else {
                 return Err(make_type_error());
             };
-            match kind.as_ref() {
-                "exact" => StringPattern::Exact(needle.clone()),
-                "substring" => StringPattern::Substring(needle.clone()),
-                _ => {
-                    // TODO: error span can be narrowed to the lhs node
-                    return Err(make_error(format!(
-                        r#"Invalid string pattern kind "{kind}""#
-                    )));
-                }
-            }
+            // TODO: error span can be narrowed to the lhs node
+            parse_pattern(needle, Some(kind)).map_err(make_error)
         }
-        _ => return Err(make_type_error()),
-    };
-    Ok(pattern)
+        _ => Err(make_type_error()),
+    }
 }
 
 fn parse_function_argument_as_literal<T: FromStr>(
@@ -1568,6 +1840,7 @@ pub fn parse(
         aliases_expanding: &[],
         locals: &HashMap::new(),
         user_email: &context.user_email,
+        date_pattern_context: context.date_pattern_context,
         workspace_ctx: &context.workspace,
     };
     parse_program(revset_str, state)
@@ -1664,6 +1937,24 @@ fn try_transform_expression<E>(
             RevsetExpression::Present(candidates) => {
                 transform_rec(candidates, pre, post)?.map(RevsetExpression::Present)
             }
+            RevsetExpression::AtOperation {
+                operation,
+                candidates,
+            } => transform_rec(candidates, pre, post)?.map(|candidates| {
+                RevsetExpression::AtOperation {
+                    operation: operation.clone(),
+                    candidates,
+                }
+            }),
+            RevsetExpression::WithinVisibility {
+                candidates,
+                visible_heads,
+            } => transform_rec(candidates, pre, post)?.map(|candidates| {
+                RevsetExpression::WithinVisibility {
+                    candidates,
+                    visible_heads: visible_heads.clone(),
+                }
+            }),
             RevsetExpression::NotIn(complement) => {
                 transform_rec(complement, pre, post)?.map(RevsetExpression::NotIn)
             }
@@ -2084,6 +2375,15 @@ fn resolve_full_commit_id(
 
 pub trait SymbolResolver {
     fn resolve_symbol(&self, symbol: &str) -> Result<Vec<CommitId>, RevsetResolutionError>;
+
+    /// Loads the repo at the operation `op_str` to resolve symbols in
+    /// `at_operation()` against.
+    fn resolve_operation(&self, op_str: &str) -> Result<Arc<ReadonlyRepo>, RevsetResolutionError> {
+        Err(RevsetResolutionError::InvalidOperation {
+            name: op_str.to_owned(),
+            message: "Operations cannot be resolved in this context".to_owned(),
+        })
+    }
 }
 
 /// Fails on any attempt to resolve a symbol.
@@ -2103,12 +2403,16 @@ impl SymbolResolver for FailingSymbolResolver {
 
 pub type PrefixResolver<'a, T> = Box<dyn Fn(&dyn Repo, &HexPrefix) -> PrefixResolution<T> + 'a>;
 
+pub type OperationResolver<'a> =
+    Box<dyn Fn(&str) -> Result<Arc<ReadonlyRepo>, RevsetResolutionError> + 'a>;
+
 /// Resolves branches, remote branches, tags, git refs, and full and abbreviated
 /// commit and change ids.
 pub struct DefaultSymbolResolver<'a> {
     repo: &'a dyn Repo,
     commit_id_resolver: PrefixResolver<'a, CommitId>,
     change_id_resolver: PrefixResolver<'a, Vec<CommitId>>,
+    operation_resolver: Option<OperationResolver<'a>>,
 }
 
 impl<'a> DefaultSymbolResolver<'a> {
@@ -2117,6 +2421,7 @@ impl<'a> DefaultSymbolResolver<'a> {
             repo,
             commit_id_resolver: Box::new(|repo, prefix| repo.index().resolve_prefix(prefix)),
             change_id_resolver: Box::new(|repo, prefix| repo.resolve_change_id_prefix(prefix)),
+            operation_resolver: None,
         }
     }
 
@@ -2135,6 +2440,11 @@ impl<'a> DefaultSymbolResolver<'a> {
         self.change_id_resolver = change_id_resolver;
         self
     }
+
+    pub fn with_operation_resolver(mut self, operation_resolver: OperationResolver<'a>) -> Self {
+        self.operation_resolver = Some(operation_resolver);
+        self
+    }
 }
 
 impl SymbolResolver for DefaultSymbolResolver<'_> {
@@ -2200,6 +2510,34 @@ impl SymbolResolver for DefaultSymbolResolver<'_> {
 
         Err(make_no_such_symbol_error(self.repo, symbol))
     }
+
+    fn resolve_operation(&self, op_str: &str) -> Result<Arc<ReadonlyRepo>, RevsetResolutionError> {
+        if let Some(operation_resolver) = &self.operation_resolver {
+            operation_resolver(op_str)
+        } else {
+            Err(RevsetResolutionError::InvalidOperation {
+                name: op_str.to_owned(),
+                message: "Operations cannot be resolved in this context".to_owned(),
+            })
+        }
+    }
+}
+
+/// Resolves symbols against the repo loaded by `at_operation()`. Nested
+/// operations are resolved by the `parent` resolver.
+struct AtOperationSymbolResolver<'a> {
+    inner: DefaultSymbolResolver<'a>,
+    parent: &'a dyn SymbolResolver,
+}
+
+impl SymbolResolver for AtOperationSymbolResolver<'_> {
+    fn resolve_symbol(&self, symbol: &str) -> Result<Vec<CommitId>, RevsetResolutionError> {
+        self.inner.resolve_symbol(symbol)
+    }
+
+    fn resolve_operation(&self, op_str: &str) -> Result<Arc<ReadonlyRepo>, RevsetResolutionError> {
+        self.parent.resolve_operation(op_str)
+    }
 }
 
 fn resolve_commit_ref(
@@ -2233,11 +2571,21 @@ fn resolve_commit_ref(
         RevsetCommitRef::RemoteBranches {
             branch_pattern,
             remote_pattern,
+            untracked_only,
         } => {
             let view = repo.view();
             let commit_ids = filter_map_values_by_key_pattern(view.branches(), branch_pattern)
                 .flat_map(|branch_target| {
-                    filter_map_values_by_key_pattern(&branch_target.remote_targets, remote_pattern)
+                    branch_target
+                        .remote_targets
+                        .iter()
+                        .filter(|(remote, _)| remote_pattern.matches(remote))
+                        .filter(|(remote, _)| {
+                            !*untracked_only
+                                || branch_target.local_target.is_absent()
+                                || branch_target.untracked_remotes.contains(*remote)
+                        })
+                        .map(|(_, remote_target)| remote_target)
                 })
                 .flat_map(|remote_target| remote_target.added_ids())
                 .cloned()
@@ -2281,10 +2629,31 @@ fn resolve_symbols(
                         | RevsetResolutionError::EmptyString
                         | RevsetResolutionError::AmbiguousCommitIdPrefix(_)
                         | RevsetResolutionError::AmbiguousChangeIdPrefix(_)
+                        | RevsetResolutionError::InvalidOperation { .. }
                         | RevsetResolutionError::StoreError(_) => Err(err),
                     })
                     .map(Some) // Always rewrite subtree
             }
+            // 'at_operation(op, x)' resolves symbols in 'x' against the view
+            // at 'op', but the resolved commits are evaluated in the current
+            // index.
+            RevsetExpression::AtOperation {
+                operation,
+                candidates,
+            } => {
+                let op_repo = symbol_resolver.resolve_operation(operation)?;
+                let op_symbol_resolver = AtOperationSymbolResolver {
+                    inner: DefaultSymbolResolver::new(op_repo.as_ref()),
+                    parent: symbol_resolver,
+                };
+                let candidates =
+                    resolve_symbols(op_repo.as_ref(), candidates.clone(), &op_symbol_resolver)?;
+                let visible_heads = op_repo.view().heads().iter().cloned().collect();
+                Ok(Some(Rc::new(RevsetExpression::WithinVisibility {
+                    candidates,
+                    visible_heads,
+                })))
+            }
             // Otherwise resolve symbols recursively.
             _ => Ok(None),
         },
@@ -2309,9 +2678,8 @@ fn resolve_symbols(
 /// return type `ResolvedExpression` is stricter than `RevsetExpression`,
 /// and isn't designed for such transformation.
 fn resolve_visibility(repo: &dyn Repo, expression: &RevsetExpression) -> ResolvedExpression {
-    // If we add "operation" scope (#1283), visible_heads might be translated to
-    // `RevsetExpression::WithinOperation(visible_heads, expression)` node to
-    // evaluate filter predicates and "all()" against that scope.
+    // Subtrees resolved by 'at_operation()' are wrapped in
+    // `RevsetExpression::WithinVisibility` nodes, which replace these heads.
     let context = VisibilityResolutionContext {
         visible_heads: &repo.view().heads().iter().cloned().collect_vec(),
     };
@@ -2380,9 +2748,16 @@ impl VisibilityResolutionContext<'_> {
                     predicate: self.resolve_predicate(expression),
                 }
             }
-            RevsetExpression::Present(_) => {
+            RevsetExpression::Present(_) | RevsetExpression::AtOperation { .. } => {
                 panic!("Expression '{expression:?}' should have been resolved by caller");
             }
+            RevsetExpression::WithinVisibility {
+                candidates,
+                visible_heads,
+            } => {
+                let context = VisibilityResolutionContext { visible_heads };
+                context.resolve(candidates)
+            }
             RevsetExpression::NotIn(complement) => ResolvedExpression::Difference(
                 self.resolve_all().into(),
                 self.resolve(complement).into(),
@@ -2448,14 +2823,15 @@ impl VisibilityResolutionContext<'_> {
             | RevsetExpression::DagRange { .. }
             | RevsetExpression::Heads(_)
             | RevsetExpression::Roots(_)
-            | RevsetExpression::Latest { .. } => {
+            | RevsetExpression::Latest { .. }
+            | RevsetExpression::WithinVisibility { .. } => {
                 ResolvedPredicateExpression::Set(self.resolve(expression).into())
             }
             RevsetExpression::Filter(predicate) => {
                 ResolvedPredicateExpression::Filter(predicate.clone())
             }
             RevsetExpression::AsFilter(candidates) => self.resolve_predicate(candidates),
-            RevsetExpression::Present(_) => {
+            RevsetExpression::Present(_) | RevsetExpression::AtOperation { .. } => {
                 panic!("Expression '{expression:?}' should have been resolved by caller")
             }
             RevsetExpression::NotIn(complement) => {
@@ -2564,6 +2940,7 @@ impl Iterator for ReverseRevsetIterator {
 pub struct RevsetParseContext<'a> {
     pub aliases_map: &'a RevsetAliasesMap,
     pub user_email: String,
+    pub date_pattern_context: DatePatternContext,
     pub workspace: Option<RevsetWorkspaceContext<'a>>,
 }
 
@@ -2573,11 +2950,18 @@ pub struct RevsetWorkspaceContext<'a> {
     pub cwd: &'a Path,
     pub workspace_id: &'a WorkspaceId,
     pub workspace_root: &'a Path,
+    /// Whether file arguments are parsed as fileset expressions. If false, they
+    /// are plain paths.
+    pub allow_filesets: bool,
 }
 
 #[cfg(test)]
 mod tests {
+    use assert_matches::assert_matches;
+
     use super::*;
+    use crate::fileset::FilePattern;
+    use crate::repo_path::RepoPath;
 
     fn parse(revset_str: &str) -> Result<Rc<RevsetExpression>, RevsetParseErrorKind> {
         parse_with_aliases(revset_str, [] as [(&str, &str); 0])
@@ -2601,6 +2985,7 @@ mod tests {
         let context = RevsetParseContext {
             aliases_map: &aliases_map,
             user_email: "test.user@example.com".to_string(),
+            date_pattern_context: DatePatternContext::from_current_time(),
             workspace: None,
         };
         // Map error to comparable object
@@ -2617,6 +3002,7 @@ mod tests {
             cwd: Path::new("/"),
             workspace_id,
             workspace_root: Path::new("/"),
+            allow_filesets: true,
         };
         let mut aliases_map = RevsetAliasesMap::new();
         for (decl, defn) in aliases {
@@ -2625,6 +3011,7 @@ mod tests {
         let context = RevsetParseContext {
             aliases_map: &aliases_map,
             user_email: "test.user@example.com".to_string(),
+            date_pattern_context: DatePatternContext::from_current_time(),
             workspace: Some(workspace_ctx),
         };
         // Map error to comparable object
@@ -2942,9 +3329,9 @@ mod tests {
                 StringPattern::Substring("arg1".to_string())
             ))
             .minus(&RevsetExpression::filter(RevsetFilterPredicate::File(
-                Some(vec![
-                    RepoPath::from_internal_string("arg1"),
-                    RepoPath::from_internal_string("arg2"),
+                FilesetExpression::union_all(vec![
+                    FilesetExpression::prefix_path(RepoPath::from_internal_string("arg1")),
+                    FilesetExpression::prefix_path(RepoPath::from_internal_string("arg2")),
                 ])
             )))
             .minus(&RevsetExpression::visible_heads()))
@@ -2980,6 +3367,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_string_pattern_matches() {
+        let pattern = StringPattern::parse("exact:foo").unwrap();
+        assert!(pattern.matches("foo"));
+        assert!(!pattern.matches("foobar"));
+
+        let pattern = StringPattern::parse("substring:oba").unwrap();
+        assert!(pattern.matches("foobar"));
+        assert!(!pattern.matches("foo"));
+
+        let pattern = StringPattern::parse("glob:release-*").unwrap();
+        assert!(pattern.matches("release-1.0"));
+        assert!(!pattern.matches("pre-release-1.0"));
+
+        let pattern = StringPattern::parse("regex:^JIRA-[0-9]+").unwrap();
+        assert!(pattern.matches("JIRA-123: fix"));
+        assert!(!pattern.matches("See JIRA-123"));
+
+        // Without a kind, the pattern matches exactly
+        let pattern = StringPattern::parse("foo").unwrap();
+        assert_eq!(pattern, StringPattern::Exact("foo".to_owned()));
+        assert_eq!(pattern.to_string(), "foo");
+
+        assert_matches!(
+            StringPattern::parse("bad:foo"),
+            Err(StringPatternParseError::InvalidKind(kind)) if kind == "bad"
+        );
+        assert_matches!(
+            StringPattern::parse("glob:foo-[1-3"),
+            Err(StringPatternParseError::GlobPattern(_))
+        );
+        assert_matches!(
+            StringPattern::parse("regex:(foo"),
+            Err(StringPatternParseError::Regex(_))
+        );
+    }
+
     #[test]
     fn test_parse_string_pattern() {
         assert_eq!(
@@ -3000,6 +3424,29 @@ mod tests {
                 "foo".to_owned()
             )))
         );
+        assert_eq!(
+            parse(r#"branches(glob:"release-*")"#),
+            Ok(RevsetExpression::branches(
+                StringPattern::glob("release-*").unwrap()
+            ))
+        );
+        assert_eq!(
+            parse(r#"description(regex:"^JIRA-[0-9]+")"#),
+            Ok(RevsetExpression::filter(
+                RevsetFilterPredicate::Description(StringPattern::regex("^JIRA-[0-9]+").unwrap())
+            ))
+        );
+        assert_eq!(
+            parse(r#"branches(glob:"foo-[1-3")"#),
+            Err(RevsetParseErrorKind::InvalidFunctionArguments {
+                name: "branches".to_owned(),
+                message: "Pattern syntax error near position 4: invalid range pattern".to_owned()
+            })
+        );
+        assert_matches!(
+            parse(r#"author(regex:"(foo")"#),
+            Err(RevsetParseErrorKind::InvalidFunctionArguments { name, .. }) if name == "author"
+        );
         assert_eq!(
             parse(r#"branches("exact:foo")"#),
             Ok(RevsetExpression::branches(StringPattern::Substring(
@@ -3022,6 +3469,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_date_pattern() {
+        assert_matches!(
+            parse(r#"author_date(after:"2023-01-01")"#).as_deref(),
+            Ok(RevsetExpression::Filter(RevsetFilterPredicate::AuthorDate(
+                DatePattern::AtOrAfter(_)
+            )))
+        );
+        assert_matches!(
+            parse(r#"committer_date(after:"2023-01-01", before:"3 days ago")"#).as_deref(),
+            Ok(RevsetExpression::Intersection(_, _))
+        );
+        assert_eq!(
+            parse(r#"author_date("2023-01-01")"#),
+            Err(RevsetParseErrorKind::InvalidFunctionArguments {
+                name: "author_date".to_owned(),
+                message: "Expected date pattern of kind `after:` or `before:`".to_owned()
+            })
+        );
+        assert_eq!(
+            parse(r#"author_date(on:"2023-01-01")"#),
+            Err(RevsetParseErrorKind::InvalidFunctionArguments {
+                name: "author_date".to_owned(),
+                message: r#"Invalid date pattern kind "on""#.to_owned()
+            })
+        );
+        assert_eq!(
+            parse(r#"committer_date(before:"next week")"#),
+            Err(RevsetParseErrorKind::InvalidFunctionArguments {
+                name: "committer_date".to_owned(),
+                message: r#"Invalid date "next week""#.to_owned()
+            })
+        );
+    }
+
     #[test]
     fn test_parse_revset_alias_symbol_decl() {
         let mut aliases_map = RevsetAliasesMap::new();
@@ -3192,25 +3674,90 @@ mod tests {
         );
         assert_eq!(
             parse_with_workspace("empty()", &WorkspaceId::default()),
-            Ok(RevsetExpression::filter(RevsetFilterPredicate::File(None)).negated())
+            Ok(
+                RevsetExpression::filter(RevsetFilterPredicate::File(FilesetExpression::all()))
+                    .negated()
+            )
         );
         assert!(parse_with_workspace("empty(foo)", &WorkspaceId::default()).is_err());
         assert!(parse_with_workspace("file()", &WorkspaceId::default()).is_err());
         assert_eq!(
             parse_with_workspace("file(foo)", &WorkspaceId::default()),
-            Ok(RevsetExpression::filter(RevsetFilterPredicate::File(Some(
-                vec![RepoPath::from_internal_string("foo")]
-            ))))
+            Ok(RevsetExpression::filter(RevsetFilterPredicate::File(
+                FilesetExpression::prefix_path(RepoPath::from_internal_string("foo"))
+            )))
         );
         assert_eq!(
             parse_with_workspace("file(foo, bar, baz)", &WorkspaceId::default()),
-            Ok(RevsetExpression::filter(RevsetFilterPredicate::File(Some(
-                vec![
-                    RepoPath::from_internal_string("foo"),
-                    RepoPath::from_internal_string("bar"),
-                    RepoPath::from_internal_string("baz"),
-                ]
-            ))))
+            Ok(RevsetExpression::filter(RevsetFilterPredicate::File(
+                FilesetExpression::union_all(vec![
+                    FilesetExpression::prefix_path(RepoPath::from_internal_string("foo")),
+                    FilesetExpression::prefix_path(RepoPath::from_internal_string("bar")),
+                    FilesetExpression::prefix_path(RepoPath::from_internal_string("baz")),
+                ])
+            )))
+        );
+        assert_eq!(
+            parse_with_workspace(r#"file("foo ~ glob:*.rs")"#, &WorkspaceId::default()),
+            Ok(RevsetExpression::filter(RevsetFilterPredicate::File(
+                FilesetExpression::prefix_path(RepoPath::from_internal_string("foo")).difference(
+                    FilesetExpression::pattern(FilePattern::FileGlob {
+                        dir: RepoPath::root(),
+                        pattern: glob::Pattern::new("*.rs").unwrap(),
+                    })
+                )
+            )))
+        );
+        assert_matches!(
+            parse_with_workspace(r#"file("bad:foo")"#, &WorkspaceId::default()),
+            Err(RevsetParseErrorKind::FilesetParseError(_))
+        );
+        assert_eq!(
+            parse("at_operation(@-, foo-)"),
+            Ok(Rc::new(RevsetExpression::AtOperation {
+                operation: "@-".to_owned(),
+                candidates: RevsetExpression::symbol("foo".to_owned()).parents(),
+            }))
+        );
+        assert_eq!(
+            parse("at_operation( abc123 , foo)"),
+            Ok(Rc::new(RevsetExpression::AtOperation {
+                operation: "abc123".to_owned(),
+                candidates: RevsetExpression::symbol("foo".to_owned()),
+            }))
+        );
+        assert!(parse("at_operation(@-)").is_err());
+    }
+
+    #[test]
+    fn test_parse_file_without_filesets() {
+        let workspace_id = WorkspaceId::default();
+        let context = RevsetParseContext {
+            aliases_map: &RevsetAliasesMap::new(),
+            user_email: "test.user@example.com".to_string(),
+            date_pattern_context: DatePatternContext::from_current_time(),
+            workspace: Some(RevsetWorkspaceContext {
+                cwd: Path::new("/"),
+                workspace_id: &workspace_id,
+                workspace_root: Path::new("/"),
+                allow_filesets: false,
+            }),
+        };
+        let parse = |revset_str| super::parse(revset_str, &context).map_err(|e| e.kind);
+        assert_eq!(
+            parse(r#"file("foo ~ glob:*.rs")"#),
+            Ok(RevsetExpression::filter(RevsetFilterPredicate::File(
+                FilesetExpression::prefix_path(RepoPath::from_internal_string("foo ~ glob:*.rs"))
+            )))
+        );
+        assert_eq!(
+            parse(r#"diff_contains("bar", "a~b")"#),
+            Ok(RevsetExpression::filter(
+                RevsetFilterPredicate::DiffContains {
+                    text: StringPattern::Substring("bar".to_owned()),
+                    files: FilesetExpression::prefix_path(RepoPath::from_internal_string("a~b")),
+                }
+            ))
         );
     }
 
@@ -3808,7 +4355,7 @@ mod tests {
         insta::assert_debug_snapshot!(optimize(parse("~empty()").unwrap()), @r###"
         Filter(
             File(
-                None,
+                All,
             ),
         )
         "###);
@@ -4027,7 +4574,7 @@ mod tests {
         )
         "###);
         insta::assert_debug_snapshot!(
-            optimize(parse_with_workspace("committer(foo) & file(bar) & baz", &WorkspaceId::default()).unwrap()), @r###"
+            optimize(parse_with_workspace("committer(foo) & file(bar) & baz", &WorkspaceId::default()).unwrap()), @r#"
         Intersection(
             Intersection(
                 CommitRef(
@@ -4045,17 +4592,17 @@ mod tests {
             ),
             Filter(
                 File(
-                    Some(
-                        [
+                    Pattern(
+                        PrefixPath(
                             "bar",
-                        ],
+                        ),
                     ),
                 ),
             ),
         )
-        "###);
+        "#);
         insta::assert_debug_snapshot!(
-            optimize(parse_with_workspace("committer(foo) & file(bar) & author(baz)", &WorkspaceId::default()).unwrap()), @r###"
+            optimize(parse_with_workspace("committer(foo) & file(bar) & author(baz)", &WorkspaceId::default()).unwrap()), @r#"
         Intersection(
             Intersection(
                 Filter(
@@ -4067,10 +4614,10 @@ mod tests {
                 ),
                 Filter(
                     File(
-                        Some(
-                            [
+                        Pattern(
+                            PrefixPath(
                                 "bar",
-                            ],
+                            ),
                         ),
                     ),
                 ),
@@ -4083,8 +4630,8 @@ mod tests {
                 ),
             ),
         )
-        "###);
-        insta::assert_debug_snapshot!(optimize(parse_with_workspace("foo & file(bar) & baz", &WorkspaceId::default()).unwrap()), @r###"
+        "#);
+        insta::assert_debug_snapshot!(optimize(parse_with_workspace("foo & file(bar) & baz", &WorkspaceId::default()).unwrap()), @r#"
         Intersection(
             Intersection(
                 CommitRef(
@@ -4100,15 +4647,15 @@ mod tests {
             ),
             Filter(
                 File(
-                    Some(
-                        [
+                    Pattern(
+                        PrefixPath(
                             "bar",
-                        ],
+                        ),
                     ),
                 ),
             ),
         )
-        "###);
+        "#);
 
         insta::assert_debug_snapshot!(
             optimize(parse("foo & description(bar) & author(baz) & qux").unwrap()), @r###"
@@ -91,7 +91,11 @@ pub fn rebase_commit(
         let old_base_tree = merge_commit_trees(mut_repo, &old_parents)?;
         let new_base_tree = merge_commit_trees(mut_repo, new_parents)?;
         let old_tree = old_commit.tree()?;
-        let merged_tree = new_base_tree.merge(&old_base_tree, &old_tree)?;
+        let merged_tree = if settings.follow_renames() {
+            new_base_tree.merge_following_renames(&old_base_tree, &old_tree)?
+        } else {
+            new_base_tree.merge(&old_base_tree, &old_tree)?
+        };
         merged_tree.id()
     };
     let new_parent_ids = new_parents
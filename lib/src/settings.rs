@@ -22,6 +22,7 @@ use rand::prelude::*;
 use rand_chacha::ChaCha20Rng;
 
 use crate::backend::{ChangeId, ObjectId, Signature, Timestamp};
+use crate::conflicts::ConflictMarkerStyle;
 use crate::fmt_util::binary_prefix;
 use crate::fsmonitor::FsmonitorKind;
 
@@ -58,6 +59,25 @@ impl Default for GitSettings {
     }
 }
 
+/// Settings for signing new and rewritten commits.
+#[derive(Debug, Clone, Default)]
+pub struct SignSettings {
+    /// Whether commits written by jj should be signed.
+    pub sign_all: bool,
+    /// The key passed to the signing backend, e.g. a GPG key id or the path
+    /// to an SSH key.
+    pub key: Option<String>,
+}
+
+impl SignSettings {
+    pub fn from_config(config: &config::Config) -> Self {
+        SignSettings {
+            sign_all: config.get_bool("signing.sign-all").unwrap_or(false),
+            key: config.get_string("signing.key").ok(),
+        }
+    }
+}
+
 fn get_timestamp_config(config: &config::Config, key: &str) -> Option<Timestamp> {
     match config.get_string(key) {
         Ok(timestamp_str) => match DateTime::parse_from_rfc3339(&timestamp_str) {
@@ -103,6 +123,12 @@ impl UserSettings {
             .unwrap_or(false)
     }
 
+    pub fn follow_renames(&self) -> bool {
+        self.config
+            .get_bool("merge.follow-renames")
+            .unwrap_or(true)
+    }
+
     pub fn user_name(&self) -> String {
         self.config.get_string("user.name").unwrap_or_default()
     }
@@ -122,6 +148,14 @@ impl UserSettings {
         }
     }
 
+    pub fn conflict_marker_style(&self) -> Result<ConflictMarkerStyle, config::ConfigError> {
+        match self.config.get_string("ui.conflict-marker-style") {
+            Ok(conflict_marker_style) => conflict_marker_style.parse(),
+            Err(config::ConfigError::NotFound(_)) => Ok(ConflictMarkerStyle::default()),
+            Err(err) => Err(err),
+        }
+    }
+
     // Must not be changed to avoid git pushing older commits with no set email
     // address
     pub const USER_EMAIL_PLACEHOLDER: &'static str = "(no email configured)";
@@ -190,6 +224,21 @@ impl UserSettings {
         GitSettings::from_config(&self.config)
     }
 
+    pub fn sign_settings(&self) -> SignSettings {
+        SignSettings::from_config(&self.config)
+    }
+
+    /// The name of the backend used for signing commits, or `None` if
+    /// signing is disabled.
+    pub fn signing_backend(&self) -> Result<Option<String>, config::ConfigError> {
+        match self.config.get_string("signing.backend") {
+            Ok(backend) if backend == "none" => Ok(None),
+            Ok(backend) => Ok(Some(backend)),
+            Err(config::ConfigError::NotFound(_)) => Ok(Some("gpg".to_string())),
+            Err(err) => Err(err),
+        }
+    }
+
     pub fn graph_style(&self) -> String {
         self.config
             .get_string("ui.graph.style")
@@ -0,0 +1,247 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Generic APIs to work with cryptographic signatures created and verified by
+//! various backends.
+
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+use thiserror::Error;
+
+use crate::backend::CommitId;
+use crate::gpg_signing::GpgBackend;
+use crate::settings::UserSettings;
+use crate::ssh_signing::SshBackend;
+
+/// The status of a verified signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SigStatus {
+    /// The signature matches the data and was made by a trusted key.
+    Good,
+    /// The signature could not be checked against a trusted key, e.g. because
+    /// the key is not known to the backend.
+    Unknown,
+    /// The signature does not match the signed data.
+    Bad,
+}
+
+impl SigStatus {
+    /// The lowercase name of the status, as used in templates and revsets.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SigStatus::Good => "good",
+            SigStatus::Unknown => "unknown",
+            SigStatus::Bad => "bad",
+        }
+    }
+}
+
+impl Display for SigStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SigStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "good" => Ok(SigStatus::Good),
+            "unknown" => Ok(SigStatus::Unknown),
+            "bad" => Ok(SigStatus::Bad),
+            _ => Err(format!("Invalid signature status: {s}")),
+        }
+    }
+}
+
+/// The result of a signature verification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verification {
+    /// The status of the signature.
+    pub status: SigStatus,
+    /// The key id, if the backend could determine it. For GPG, this is the
+    /// long key id. For SSH, this is the key fingerprint.
+    pub key: Option<String>,
+    /// A human-readable description of the signer, e.g. the primary user id
+    /// of a GPG key.
+    pub display: Option<String>,
+}
+
+impl Verification {
+    /// A verification result for a signature no backend could make sense of.
+    pub fn unknown() -> Self {
+        Verification {
+            status: SigStatus::Unknown,
+            key: None,
+            display: None,
+        }
+    }
+}
+
+/// An error that occurred while creating or verifying a signature.
+#[derive(Debug, Error)]
+pub enum SignError {
+    /// The signature could not be parsed by the backend.
+    #[error("Invalid signature")]
+    InvalidSignatureFormat,
+    /// No signing backend is configured.
+    #[error("No signing backend is configured")]
+    NoBackend,
+    /// The signing backend couldn't be set up from the config.
+    #[error(transparent)]
+    Init(Arc<SignInitError>),
+    /// The backend failed, e.g. because the external program is missing.
+    #[error("Signing error: {0}")]
+    Backend(Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// A result type for signing and verification.
+pub type SignResult<T> = Result<T, SignError>;
+
+/// An error that occurred while setting up signing from the config.
+#[derive(Debug, Error)]
+pub enum SignInitError {
+    /// The configured backend does not exist.
+    #[error("Unknown signing backend configured: {0}")]
+    UnknownBackend(String),
+    /// The signing config could not be read.
+    #[error("Failed to read signing config: {0}")]
+    Config(#[from] config::ConfigError),
+}
+
+/// A backend that can create and verify signatures for arbitrary data.
+pub trait SigningBackend: Debug + Send + Sync {
+    /// The name of the backend, as used in the `signing.backend` config.
+    fn name(&self) -> &str;
+
+    /// Whether the signature was (probably) created by this backend.
+    fn can_read(&self, signature: &[u8]) -> bool;
+
+    /// Creates a detached signature for `data` with the given key, or with
+    /// the backend's default key if `key` is `None`.
+    fn sign(&self, data: &[u8], key: Option<&str>) -> SignResult<Vec<u8>>;
+
+    /// Verifies a signature created by `sign()`.
+    fn verify(&self, data: &[u8], signature: &[u8]) -> SignResult<Verification>;
+}
+
+/// Wraps the signing backends and caches verification results, similar to
+/// what `Store` does for the commit backend.
+#[derive(Debug, Default)]
+pub struct Signer {
+    /// The backend used for creating signatures, if any.
+    main_backend: Option<Box<dyn SigningBackend>>,
+    /// Why the configured backend couldn't be set up. It's only reported when
+    /// signing so that a bad config doesn't break commands that don't sign.
+    main_backend_error: Option<Arc<SignInitError>>,
+    /// The other backends, which are only used for verification.
+    backends: Vec<Box<dyn SigningBackend>>,
+    cache: RwLock<HashMap<CommitId, Verification>>,
+}
+
+impl Signer {
+    /// Creates a signer with all built-in backends, using the one configured
+    /// in `signing.backend` for creating signatures.
+    ///
+    /// If the configured backend is invalid, the error is returned when
+    /// signing, and all backends can still be used for verification.
+    pub fn from_settings(settings: &UserSettings) -> Self {
+        let config = settings.config();
+        let mut backends: Vec<Box<dyn SigningBackend>> = vec![
+            Box::new(GpgBackend::from_config(config)),
+            Box::new(SshBackend::from_config(config)),
+            #[cfg(feature = "testing")]
+            Box::new(crate::test_signing_backend::TestSigningBackend),
+        ];
+        let main_backend = settings
+            .signing_backend()
+            .map_err(SignInitError::from)
+            .and_then(|name| {
+                name.map(|name| {
+                    let index = backends
+                        .iter()
+                        .position(|backend| backend.name() == name)
+                        .ok_or(SignInitError::UnknownBackend(name))?;
+                    Ok(backends.remove(index))
+                })
+                .transpose()
+            });
+        match main_backend {
+            Ok(main_backend) => Signer::new(main_backend, backends),
+            Err(err) => Signer {
+                main_backend_error: Some(Arc::new(err)),
+                ..Signer::new(None, backends)
+            },
+        }
+    }
+
+    /// Creates a signer from the given backends. `main_backend` is used for
+    /// signing, and all backends are used for verification.
+    pub fn new(
+        main_backend: Option<Box<dyn SigningBackend>>,
+        other_backends: Vec<Box<dyn SigningBackend>>,
+    ) -> Self {
+        Signer {
+            main_backend,
+            main_backend_error: None,
+            backends: other_backends,
+            cache: Default::default(),
+        }
+    }
+
+    /// Whether a backend for creating signatures is configured.
+    pub fn can_sign(&self) -> bool {
+        self.main_backend.is_some()
+    }
+
+    /// Signs `data` with the main backend.
+    pub fn sign(&self, data: &[u8], key: Option<&str>) -> SignResult<Vec<u8>> {
+        if let Some(err) = &self.main_backend_error {
+            return Err(SignError::Init(err.clone()));
+        }
+        let backend = self.main_backend.as_ref().ok_or(SignError::NoBackend)?;
+        backend.sign(data, key)
+    }
+
+    /// Verifies the signature of the given commit with the first backend that
+    /// can read it. The result is cached by commit id.
+    pub fn verify(
+        &self,
+        commit_id: &CommitId,
+        data: &[u8],
+        signature: &[u8],
+    ) -> SignResult<Verification> {
+        if let Some(verification) = self.cache.read().unwrap().get(commit_id) {
+            return Ok(verification.clone());
+        }
+        let backend = self
+            .main_backend
+            .iter()
+            .chain(&self.backends)
+            .find(|backend| backend.can_read(signature));
+        let verification = match backend {
+            Some(backend) => backend.verify(data, signature)?,
+            None => Verification::unknown(),
+        };
+        self.cache
+            .write()
+            .unwrap()
+            .insert(commit_id.clone(), verification.clone());
+        Ok(verification)
+    }
+}
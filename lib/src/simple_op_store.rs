@@ -14,11 +14,12 @@
 
 #![allow(missing_docs)]
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fmt::Debug;
 use std::fs;
 use std::io::{ErrorKind, Write};
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 use prost::Message;
 use tempfile::{NamedTempFile, PersistError};
@@ -27,6 +28,7 @@ use thiserror::Error;
 use crate::backend::{CommitId, MillisSinceEpoch, ObjectId, Timestamp};
 use crate::content_hash::blake2b_hash;
 use crate::file_util::persist_content_addressed_temp_file;
+use crate::git::REMOTE_NAME_FOR_LOCAL_GIT_REPO;
 use crate::merge::Merge;
 use crate::op_store::{
     BranchTarget, OpStore, OpStoreError, OpStoreResult, Operation, OperationId, OperationMetadata,
@@ -146,6 +148,74 @@ impl OpStore for SimpleOpStore {
         persist_content_addressed_temp_file(temp_file, self.operation_path(&id))?;
         Ok(id)
     }
+
+    #[tracing::instrument(skip(self))]
+    fn gc(&self, head_ids: &[OperationId], keep_newer: SystemTime) -> OpStoreResult<()> {
+        let to_io_err = |err: std::io::Error| OpStoreError::Other(err.into());
+        let is_expired = |entry: &fs::DirEntry| -> OpStoreResult<bool> {
+            let modified = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .map_err(to_io_err)?;
+            Ok(modified < keep_newer)
+        };
+
+        // Reachable operations must be kept regardless of their age.
+        let mut reachable_op_ids = HashSet::new();
+        let mut kept_view_ids = HashSet::new();
+        let mut pending_op_ids = head_ids.to_vec();
+        while let Some(op_id) = pending_op_ids.pop() {
+            if !reachable_op_ids.insert(op_id.clone()) {
+                continue;
+            }
+            let operation = self.read_operation(&op_id)?;
+            kept_view_ids.insert(operation.view_id);
+            pending_op_ids.extend(operation.parents);
+        }
+
+        let mut num_removed_ops = 0;
+        for entry in fs::read_dir(self.path.join("operations")).map_err(to_io_err)? {
+            let entry = entry.map_err(to_io_err)?;
+            let Some(op_id) = parse_id_file_name(&entry).map(OperationId::new) else {
+                continue;
+            };
+            if reachable_op_ids.contains(&op_id) {
+                continue;
+            }
+            if is_expired(&entry)? {
+                fs::remove_file(entry.path()).map_err(to_io_err)?;
+                num_removed_ops += 1;
+            } else {
+                // Unreachable but recent operation. Its view must be kept too.
+                let operation = self.read_operation(&op_id)?;
+                kept_view_ids.insert(operation.view_id);
+            }
+        }
+
+        let mut num_removed_views = 0;
+        for entry in fs::read_dir(self.path.join("views")).map_err(to_io_err)? {
+            let entry = entry.map_err(to_io_err)?;
+            let Some(view_id) = parse_id_file_name(&entry).map(ViewId::new) else {
+                continue;
+            };
+            if !kept_view_ids.contains(&view_id) && is_expired(&entry)? {
+                fs::remove_file(entry.path()).map_err(to_io_err)?;
+                num_removed_views += 1;
+            }
+        }
+        tracing::info!(
+            num_removed_ops,
+            num_removed_views,
+            "removed unreachable objects"
+        );
+        Ok(())
+    }
+}
+
+/// Parses the hex file name of a stored object, ignoring other files.
+fn parse_id_file_name(entry: &fs::DirEntry) -> Option<Vec<u8>> {
+    let name = entry.file_name();
+    hex::decode(name.to_str()?).ok()
 }
 
 fn not_found_to_store_error(err: std::io::Error, id: &impl ObjectId) -> OpStoreError {
@@ -254,19 +324,20 @@ fn view_to_proto(view: &View) -> crate::protos::op_store::View {
         proto.public_head_ids.push(head_id.to_bytes());
     }
 
-    for (name, target) in &view.branches {
+    for (name, branch_target) in &view.branches {
         let mut branch_proto = crate::protos::op_store::Branch {
             name: name.clone(),
             ..Default::default()
         };
         branch_proto.name = name.clone();
-        branch_proto.local_target = ref_target_to_proto(&target.local_target);
-        for (remote_name, target) in &target.remote_targets {
+        branch_proto.local_target = ref_target_to_proto(&branch_target.local_target);
+        for (remote_name, target) in &branch_target.remote_targets {
             branch_proto
                 .remote_branches
                 .push(crate::protos::op_store::RemoteBranch {
                     remote_name: remote_name.clone(),
                     target: ref_target_to_proto(target),
+                    untracked: Some(branch_target.untracked_remotes.contains(remote_name)),
                 });
         }
         proto.branches.push(branch_proto);
@@ -312,11 +383,38 @@ fn view_from_proto(proto: crate::protos::op_store::View) -> View {
         view.public_head_ids.insert(CommitId::new(head_id_bytes));
     }
 
+    // Local branches that still exist in the underlying Git repo. A branch
+    // deleted by older versions keeps its Git ref until the deletion is exported.
+    let git_local_branches: HashSet<&str> = proto
+        .git_refs
+        .iter()
+        .filter_map(|git_ref| git_ref.name.strip_prefix("refs/heads/"))
+        .collect();
     for branch_proto in proto.branches {
         let local_target = ref_target_from_proto(branch_proto.local_target);
+        let is_pending_deletion = local_target.is_absent()
+            && (git_local_branches.contains(branch_proto.name.as_str())
+                || branch_proto.remote_branches.iter().any(|remote_branch| {
+                    remote_branch.remote_name == REMOTE_NAME_FOR_LOCAL_GIT_REPO
+                }));
 
         let mut remote_targets = BTreeMap::new();
+        let mut untracked_remotes = BTreeSet::new();
         for remote_branch in branch_proto.remote_branches {
+            // Older versions merged remote branches into the local branches only
+            // if git.auto-local-branch was enabled, which can't be checked here.
+            // Since it usually creates the local branch, assume that remote
+            // branches without a local branch aren't tracked, unless the local
+            // branch was deleted and the deletion hasn't been pushed yet. The
+            // pseudo "git" remote is always tracked.
+            let untracked = remote_branch.untracked.unwrap_or_else(|| {
+                remote_branch.remote_name != REMOTE_NAME_FOR_LOCAL_GIT_REPO
+                    && local_target.is_absent()
+                    && !is_pending_deletion
+            });
+            if untracked {
+                untracked_remotes.insert(remote_branch.remote_name.clone());
+            }
             remote_targets.insert(
                 remote_branch.remote_name,
                 ref_target_from_proto(remote_branch.target),
@@ -328,6 +426,7 @@ fn view_from_proto(proto: crate::protos::op_store::View) -> View {
             BranchTarget {
                 local_target,
                 remote_targets,
+                untracked_remotes,
             },
         );
     }
@@ -434,7 +533,7 @@ fn ref_target_from_proto(maybe_proto: Option<crate::protos::op_store::RefTarget>
 #[cfg(test)]
 mod tests {
     use insta::assert_snapshot;
-    use maplit::{btreemap, hashmap, hashset};
+    use maplit::{btreemap, btreeset, hashmap, hashset};
 
     use super::*;
     use crate::backend::{CommitId, MillisSinceEpoch, ObjectId, Timestamp};
@@ -466,12 +565,14 @@ mod tests {
                     remote_targets: btreemap! {
                         "origin".to_string() => branch_main_origin_target,
                     },
+                    untracked_remotes: btreeset! {},
                 },
                 "deleted".to_string() => BranchTarget {
                     local_target: RefTarget::absent(),
                     remote_targets: btreemap! {
                         "origin".to_string() => branch_deleted_origin_target,
                     },
+                    untracked_remotes: btreeset! {"origin".to_string()},
                 },
             },
             tags: btreemap! {
@@ -521,7 +622,7 @@ mod tests {
         // Test exact output so we detect regressions in compatibility
         assert_snapshot!(
             ViewId::new(blake2b_hash(&create_view()).to_vec()).hex(),
-            @"3c1c6efecfc0809130a5bf139aec77e6299cd7d5985b95c01a29318d40a5e2defc9bd12329e91511e545fbad065f60ce5da91f5f0368c9bf549ca761bb047f7e"
+            @"a15d9f830b24316c58210da0cf7fc140b378952a2ef7b6f768ba9795585bd8800db1d41d00b7aaccbcb80792a45d3bebf29e404f31f6b2653e4bbd95289eaff1"
         );
     }
 
@@ -602,4 +703,64 @@ mod tests {
         let maybe_proto = ref_target_to_proto_legacy(&target);
         assert_eq!(ref_target_from_proto(maybe_proto), target);
     }
+
+    #[test]
+    fn test_remote_branch_legacy_tracking_state() {
+        let target = RefTarget::normal(CommitId::from_hex("111111"));
+        let remote_branches = |remote_names: &[&str]| {
+            remote_names
+                .iter()
+                .map(|remote_name| crate::protos::op_store::RemoteBranch {
+                    remote_name: remote_name.to_string(),
+                    target: ref_target_to_proto(&target),
+                    untracked: None,
+                })
+                .collect()
+        };
+        let proto = crate::protos::op_store::View {
+            branches: vec![
+                crate::protos::op_store::Branch {
+                    name: "local".to_string(),
+                    local_target: ref_target_to_proto(&target),
+                    remote_branches: remote_branches(&["origin"]),
+                },
+                crate::protos::op_store::Branch {
+                    name: "remote-only".to_string(),
+                    local_target: None,
+                    remote_branches: remote_branches(&["origin"]),
+                },
+                crate::protos::op_store::Branch {
+                    name: "deleted".to_string(),
+                    local_target: None,
+                    remote_branches: remote_branches(&["origin"]),
+                },
+                crate::protos::op_store::Branch {
+                    name: "deleted-git-remote".to_string(),
+                    local_target: None,
+                    remote_branches: remote_branches(&["git", "origin"]),
+                },
+            ],
+            git_refs: vec![crate::protos::op_store::GitRef {
+                name: "refs/heads/deleted".to_string(),
+                target: ref_target_to_proto(&target),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let view = view_from_proto(proto);
+        assert_eq!(view.branches["local"].untracked_remotes, btreeset! {});
+        // Remote branches without a local branch weren't merged by older
+        // versions, so they are considered untracked
+        assert_eq!(
+            view.branches["remote-only"].untracked_remotes,
+            btreeset! {"origin".to_string()}
+        );
+        // Unless the local branch has been deleted, but the deletion hasn't
+        // been exported to Git nor pushed
+        assert_eq!(view.branches["deleted"].untracked_remotes, btreeset! {});
+        assert_eq!(
+            view.branches["deleted-git-remote"].untracked_remotes,
+            btreeset! {}
+        );
+    }
 }
@@ -0,0 +1,260 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Patterns selecting which paths are present in a sparse working copy.
+
+#![allow(missing_docs)]
+
+use std::path::{self, Path};
+
+use itertools::{Either, Itertools as _};
+use thiserror::Error;
+
+use crate::file_util;
+use crate::matchers::{
+    DifferenceMatcher, Matcher, PrefixGlobsMatcher, PrefixMatcher, UnionMatcher,
+};
+use crate::repo_path::{FsPathParseError, RepoPath};
+
+/// Selects a path and everything below it.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum SparsePath {
+    /// Matches the path itself and everything below it.
+    Prefix(RepoPath),
+    /// Matches paths under the directory which have a prefix matching the glob
+    /// pattern.
+    Glob {
+        dir: RepoPath,
+        pattern: glob::Pattern,
+    },
+}
+
+/// Rule in the list of sparse patterns.
+///
+/// A path is present in the working copy if it's selected by at least one
+/// `Include` rule and by no `Exclude` rule. The order of the rules doesn't
+/// matter.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum SparsePattern {
+    Include(SparsePath),
+    Exclude(SparsePath),
+}
+
+#[derive(Debug, Error)]
+pub enum SparsePatternParseError {
+    #[error(transparent)]
+    FsPath(#[from] FsPathParseError),
+    #[error("Failed to compile glob: {0}")]
+    GlobPattern(#[from] glob::PatternError),
+}
+
+impl SparsePattern {
+    /// Pattern that includes every file in the working copy.
+    pub fn root() -> Self {
+        SparsePattern::Include(SparsePath::Prefix(RepoPath::root()))
+    }
+
+    /// Pattern that includes `path` and everything below it.
+    pub fn include_prefix(path: RepoPath) -> Self {
+        SparsePattern::Include(SparsePath::Prefix(path))
+    }
+
+    pub fn path(&self) -> &SparsePath {
+        match self {
+            SparsePattern::Include(path) | SparsePattern::Exclude(path) => path,
+        }
+    }
+
+    pub fn is_exclude(&self) -> bool {
+        matches!(self, SparsePattern::Exclude(_))
+    }
+
+    /// Parses a pattern as entered by the user.
+    ///
+    /// A leading `!` turns the pattern into an exclusion. If the rest contains
+    /// glob meta characters, it's parsed as a glob, and its leading literal
+    /// directory components are resolved relative to `cwd`. Otherwise it's a
+    /// path relative to `cwd`.
+    pub fn parse_fs_pattern(
+        cwd: &Path,
+        workspace_root: &Path,
+        input: &str,
+    ) -> Result<Self, SparsePatternParseError> {
+        let (exclude, input) = match input.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, input),
+        };
+        let path = match input.find(['*', '?', '[']) {
+            None => SparsePath::Prefix(RepoPath::parse_fs_path(cwd, workspace_root, input)?),
+            Some(meta_pos) => {
+                let (dir_str, pattern_str) = match input[..meta_pos].rfind(path::is_separator) {
+                    Some(pos) => (&input[..pos], &input[pos + 1..]),
+                    None => ("", input),
+                };
+                let dir = RepoPath::parse_fs_path(cwd, workspace_root, dir_str)?;
+                let pattern = glob::Pattern::new(pattern_str)?;
+                SparsePath::Glob { dir, pattern }
+            }
+        };
+        if exclude {
+            Ok(SparsePattern::Exclude(path))
+        } else {
+            Ok(SparsePattern::Include(path))
+        }
+    }
+
+    /// Formats the pattern relative to `cwd` so that it can be parsed back by
+    /// `parse_fs_pattern()`.
+    pub fn to_fs_string(&self, cwd: &Path, workspace_root: &Path) -> String {
+        let prefix = if self.is_exclude() { "!" } else { "" };
+        let path = match self.path() {
+            SparsePath::Prefix(path) => {
+                file_util::relative_path(cwd, &path.to_fs_path(workspace_root))
+            }
+            SparsePath::Glob { dir, pattern } => {
+                let dir = file_util::relative_path(cwd, &dir.to_fs_path(workspace_root));
+                if dir == Path::new(".") {
+                    pattern.as_str().into()
+                } else {
+                    dir.join(pattern.as_str())
+                }
+            }
+        };
+        format!("{prefix}{}", path.display())
+    }
+}
+
+/// Builds a matcher for the paths that are present in the working copy
+/// according to the `patterns`.
+pub fn build_matcher(patterns: &[SparsePattern]) -> Box<dyn Matcher> {
+    let (includes, excludes): (Vec<_>, Vec<_>) =
+        patterns.iter().partition_map(|pattern| match pattern {
+            SparsePattern::Include(path) => Either::Left(path),
+            SparsePattern::Exclude(path) => Either::Right(path),
+        });
+    let included = build_paths_matcher(&includes);
+    if excludes.is_empty() {
+        included
+    } else {
+        let excluded = build_paths_matcher(&excludes);
+        Box::new(DifferenceMatcher::new(included, excluded))
+    }
+}
+
+fn build_paths_matcher(paths: &[&SparsePath]) -> Box<dyn Matcher> {
+    let mut prefixes = vec![];
+    let mut globs = vec![];
+    for path in paths {
+        match path {
+            SparsePath::Prefix(path) => prefixes.push(path.clone()),
+            SparsePath::Glob { dir, pattern } => globs.push((dir.clone(), pattern.clone())),
+        }
+    }
+    if globs.is_empty() {
+        Box::new(PrefixMatcher::new(&prefixes))
+    } else if prefixes.is_empty() {
+        Box::new(PrefixGlobsMatcher::new(&globs))
+    } else {
+        Box::new(UnionMatcher::new(
+            PrefixMatcher::new(&prefixes),
+            PrefixGlobsMatcher::new(&globs),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob(dir: &str, pattern: &str) -> SparsePath {
+        SparsePath::Glob {
+            dir: RepoPath::from_internal_string(dir),
+            pattern: glob::Pattern::new(pattern).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_parse_fs_pattern() {
+        let root = Path::new("/ws");
+        let cwd = Path::new("/ws/sub");
+        let parse = |input| SparsePattern::parse_fs_pattern(cwd, root, input).unwrap();
+
+        assert_eq!(
+            parse("dir"),
+            SparsePattern::include_prefix(RepoPath::from_internal_string("sub/dir"))
+        );
+        assert_eq!(parse(".."), SparsePattern::root());
+        assert_eq!(
+            parse("!testdata"),
+            SparsePattern::Exclude(SparsePath::Prefix(RepoPath::from_internal_string(
+                "sub/testdata"
+            )))
+        );
+        assert_eq!(
+            parse("../services/*/src"),
+            SparsePattern::Include(glob("services", "*/src"))
+        );
+        assert_eq!(
+            parse("!../**/testdata"),
+            SparsePattern::Exclude(glob("", "**/testdata"))
+        );
+        assert_eq!(parse("*.md"), SparsePattern::Include(glob("sub", "*.md")));
+
+        assert!(matches!(
+            SparsePattern::parse_fs_pattern(cwd, root, "[").unwrap_err(),
+            SparsePatternParseError::GlobPattern(_)
+        ));
+        assert!(matches!(
+            SparsePattern::parse_fs_pattern(cwd, root, "../../outside").unwrap_err(),
+            SparsePatternParseError::FsPath(_)
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_to_fs_string() {
+        let root = Path::new("/ws");
+        for (cwd, input) in [
+            ("/ws", "."),
+            ("/ws", "dir"),
+            ("/ws", "!**/testdata"),
+            ("/ws", "services/*/src"),
+            ("/ws/sub", "../dir"),
+            ("/ws/sub", "!../**/testdata"),
+        ] {
+            let cwd = Path::new(cwd);
+            let pattern = SparsePattern::parse_fs_pattern(cwd, root, input).unwrap();
+            assert_eq!(pattern.to_fs_string(cwd, root), input);
+        }
+    }
+
+    #[test]
+    fn test_build_matcher() {
+        let matcher = build_matcher(&[
+            SparsePattern::include_prefix(RepoPath::from_internal_string("README")),
+            SparsePattern::Include(glob("services", "*/src")),
+            SparsePattern::Exclude(glob("", "**/testdata")),
+        ]);
+        let matches = |path| matcher.matches(&RepoPath::from_internal_string(path));
+        assert!(matches("README"));
+        assert!(matches("services/foo/src/main.rs"));
+        assert!(!matches("services/foo/src/testdata/input"));
+        assert!(!matches("services/foo/Cargo.toml"));
+        assert!(!matches("lib/src/lib.rs"));
+
+        // Exclusions without inclusions match nothing
+        let matcher = build_matcher(&[SparsePattern::Exclude(glob("", "**/testdata"))]);
+        assert!(!matcher.matches(&RepoPath::from_internal_string("README")));
+    }
+}
@@ -0,0 +1,229 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Signing backend which shells out to `ssh-keygen -Y`.
+
+use std::ffi::{OsStr, OsString};
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+use crate::signing::{SigStatus, SignError, SignResult, SigningBackend, Verification};
+
+const SIGNATURE_PREFIX: &[u8] = b"-----BEGIN SSH SIGNATURE-----";
+
+/// The namespace git uses for commit signatures.
+const NAMESPACE: &str = "git";
+
+/// Signs and verifies data using `ssh-keygen`.
+#[derive(Debug)]
+pub struct SshBackend {
+    program: OsString,
+    allowed_signers: Option<PathBuf>,
+}
+
+impl SshBackend {
+    /// Creates a backend running the given `ssh-keygen` program. Signatures
+    /// are only considered good if their key is listed in the
+    /// `allowed_signers` file.
+    pub fn new(program: OsString, allowed_signers: Option<PathBuf>) -> Self {
+        SshBackend {
+            program,
+            allowed_signers,
+        }
+    }
+
+    /// Creates a backend configured by `signing.backends.ssh.program` and
+    /// `signing.backends.ssh.allowed-signers`.
+    pub fn from_config(config: &config::Config) -> Self {
+        let program = config
+            .get_string("signing.backends.ssh.program")
+            .unwrap_or_else(|_| "ssh-keygen".to_string());
+        let allowed_signers = config
+            .get_string("signing.backends.ssh.allowed-signers")
+            .ok()
+            .map(PathBuf::from);
+        SshBackend::new(program.into(), allowed_signers)
+    }
+
+    fn run(&self, args: &[&OsStr], input: &[u8]) -> SignResult<Output> {
+        let mut command = Command::new(&self.program);
+        command
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        tracing::info!(?command, "running ssh-keygen command");
+        let mut child = command.spawn().map_err(backend_error)?;
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(input)
+            .map_err(backend_error)?;
+        child.wait_with_output().map_err(backend_error)
+    }
+
+    fn find_principal(
+        &self,
+        allowed_signers: &OsStr,
+        signature: &OsStr,
+    ) -> SignResult<Option<String>> {
+        let output = self.run(
+            &[
+                "-Y".as_ref(),
+                "find-principals".as_ref(),
+                "-f".as_ref(),
+                allowed_signers,
+                "-s".as_ref(),
+                signature,
+            ],
+            b"",
+        )?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(stdout.lines().next().map(|line| line.trim().to_owned()))
+    }
+}
+
+impl SigningBackend for SshBackend {
+    fn name(&self) -> &str {
+        "ssh"
+    }
+
+    fn can_read(&self, signature: &[u8]) -> bool {
+        signature.starts_with(SIGNATURE_PREFIX)
+    }
+
+    fn sign(&self, data: &[u8], key: Option<&str>) -> SignResult<Vec<u8>> {
+        let key = key.ok_or_else(|| {
+            SignError::Backend("signing.key must be set to the path of an SSH key".into())
+        })?;
+        let output = self.run(
+            &[
+                "-Y".as_ref(),
+                "sign".as_ref(),
+                "-f".as_ref(),
+                key.as_ref(),
+                "-n".as_ref(),
+                NAMESPACE.as_ref(),
+            ],
+            data,
+        )?;
+        if !output.status.success() {
+            return Err(SignError::Backend(
+                format!(
+                    "ssh-keygen failed to sign the data: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )
+                .into(),
+            ));
+        }
+        Ok(output.stdout)
+    }
+
+    fn verify(&self, data: &[u8], signature: &[u8]) -> SignResult<Verification> {
+        let mut signature_file = tempfile::NamedTempFile::new().map_err(backend_error)?;
+        signature_file.write_all(signature).map_err(backend_error)?;
+        signature_file.flush().map_err(backend_error)?;
+        let signature_path = signature_file.path().as_os_str();
+
+        if let Some(allowed_signers) = &self.allowed_signers {
+            let allowed_signers = allowed_signers.as_os_str();
+            if let Some(principal) = self.find_principal(allowed_signers, signature_path)? {
+                let output = self.run(
+                    &[
+                        "-Y".as_ref(),
+                        "verify".as_ref(),
+                        "-f".as_ref(),
+                        allowed_signers,
+                        "-I".as_ref(),
+                        principal.as_ref(),
+                        "-n".as_ref(),
+                        NAMESPACE.as_ref(),
+                        "-s".as_ref(),
+                        signature_path,
+                    ],
+                    data,
+                )?;
+                let status = if output.status.success() {
+                    SigStatus::Good
+                } else {
+                    SigStatus::Bad
+                };
+                return Ok(Verification {
+                    status,
+                    key: parse_fingerprint(&output.stdout),
+                    display: Some(principal),
+                });
+            }
+        }
+
+        // The key isn't trusted, but we can still check that the signature
+        // matches the data.
+        let output = self.run(
+            &[
+                "-Y".as_ref(),
+                "check-novalidate".as_ref(),
+                "-n".as_ref(),
+                NAMESPACE.as_ref(),
+                "-s".as_ref(),
+                signature_path,
+            ],
+            data,
+        )?;
+        let status = if output.status.success() {
+            SigStatus::Unknown
+        } else {
+            SigStatus::Bad
+        };
+        Ok(Verification {
+            status,
+            key: parse_fingerprint(&output.stdout),
+            display: None,
+        })
+    }
+}
+
+fn backend_error(err: std::io::Error) -> SignError {
+    SignError::Backend(Box::new(err))
+}
+
+/// Extracts the key fingerprint from a message like `Good "git" signature
+/// with ED25519 key SHA256:...`.
+fn parse_fingerprint(output: &[u8]) -> Option<String> {
+    let output = String::from_utf8_lossy(output);
+    output.lines().find_map(|line| {
+        let (_, fingerprint) = line.rsplit_once(" key ")?;
+        Some(fingerprint.trim().to_owned())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fingerprint() {
+        assert_eq!(
+            parse_fingerprint(
+                b"Good \"git\" signature for test@example.com with ED25519 key SHA256:abc+def\n"
+            ),
+            Some("SHA256:abc+def".to_owned())
+        );
+        assert_eq!(parse_fingerprint(b"Could not verify signature.\n"), None);
+    }
+}
@@ -19,16 +19,18 @@ use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
 use std::io::Read;
 use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
 
 use crate::backend;
 use crate::backend::{
-    Backend, BackendResult, ChangeId, CommitId, ConflictId, FileId, MergedTreeId, SymlinkId,
-    TreeId, TreeValue,
+    Backend, BackendResult, ChangeId, CommitId, ConflictId, FileId, MergedTreeId, SigningFn,
+    SymlinkId, TreeId, TreeValue,
 };
 use crate::commit::Commit;
 use crate::merge::Merge;
 use crate::merged_tree::MergedTree;
 use crate::repo_path::RepoPath;
+use crate::signing::Signer;
 use crate::tree::Tree;
 use crate::tree_builder::TreeBuilder;
 
@@ -36,6 +38,7 @@ use crate::tree_builder::TreeBuilder;
 /// adds caching.
 pub struct Store {
     backend: Box<dyn Backend>,
+    signer: Signer,
     commit_cache: RwLock<HashMap<CommitId, Arc<backend::Commit>>>,
     tree_cache: RwLock<HashMap<(RepoPath, TreeId), Arc<backend::Tree>>>,
     use_tree_conflict_format: bool,
@@ -50,9 +53,14 @@ impl Debug for Store {
 }
 
 impl Store {
-    pub fn new(backend: Box<dyn Backend>, use_tree_conflict_format: bool) -> Arc<Self> {
+    pub fn new(
+        backend: Box<dyn Backend>,
+        signer: Signer,
+        use_tree_conflict_format: bool,
+    ) -> Arc<Self> {
         Arc::new(Store {
             backend,
+            signer,
             commit_cache: Default::default(),
             tree_cache: Default::default(),
             use_tree_conflict_format,
@@ -63,6 +71,10 @@ impl Store {
         self.backend.as_any()
     }
 
+    pub fn signer(&self) -> &Signer {
+        &self.signer
+    }
+
     /// Whether new tree should be written using the tree-level format.
     pub fn use_tree_conflict_format(&self) -> bool {
         self.use_tree_conflict_format
@@ -115,9 +127,13 @@ impl Store {
         Ok(data)
     }
 
-    pub fn write_commit(self: &Arc<Self>, commit: backend::Commit) -> BackendResult<Commit> {
+    pub fn write_commit(
+        self: &Arc<Self>,
+        commit: backend::Commit,
+        sign_with: Option<&mut SigningFn>,
+    ) -> BackendResult<Commit> {
         assert!(!commit.parents.is_empty());
-        let (commit_id, commit) = self.backend.write_commit(commit)?;
+        let (commit_id, commit) = self.backend.write_commit(commit, sign_with)?;
         let data = Arc::new(commit);
         {
             let mut write_locked_cache = self.commit_cache.write().unwrap();
@@ -127,6 +143,12 @@ impl Store {
         Ok(Commit::new(self.clone(), commit_id, data))
     }
 
+    /// Removes objects which are unreachable from the `heads` and older than
+    /// `keep_newer`. See `Backend::gc()`.
+    pub fn gc(&self, heads: &[CommitId], keep_newer: SystemTime) -> BackendResult<()> {
+        self.backend.gc(heads, keep_newer)
+    }
+
     pub fn get_tree(self: &Arc<Self>, dir: &RepoPath, id: &TreeId) -> BackendResult<Tree> {
         let data = self.get_backend_tree(dir, id)?;
         Ok(Tree::new(self.clone(), dir.clone(), id.clone(), data))
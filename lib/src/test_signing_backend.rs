@@ -0,0 +1,69 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A fake signing backend for tests. The "signatures" it produces are just a
+//! hash of the data and provide no security.
+
+use blake2::{Blake2b512, Digest};
+
+use crate::signing::{SigStatus, SignError, SignResult, SigningBackend, Verification};
+
+const PREFIX: &str = "--- JJ-TEST-SIGNATURE ---\nKEY: ";
+
+/// A signing backend that "signs" data by hashing it together with the key.
+#[derive(Debug)]
+pub struct TestSigningBackend;
+
+impl SigningBackend for TestSigningBackend {
+    fn name(&self) -> &str {
+        "test"
+    }
+
+    fn can_read(&self, signature: &[u8]) -> bool {
+        signature.starts_with(PREFIX.as_bytes())
+    }
+
+    fn sign(&self, data: &[u8], key: Option<&str>) -> SignResult<Vec<u8>> {
+        let key = key.unwrap_or_default();
+        let hash = hash_data(data, key);
+        Ok(format!("{PREFIX}{key}\n{hash}\n").into_bytes())
+    }
+
+    fn verify(&self, data: &[u8], signature: &[u8]) -> SignResult<Verification> {
+        let signature = std::str::from_utf8(signature)
+            .ok()
+            .and_then(|signature| signature.strip_prefix(PREFIX))
+            .ok_or(SignError::InvalidSignatureFormat)?;
+        let (key, hash) = signature
+            .split_once('\n')
+            .ok_or(SignError::InvalidSignatureFormat)?;
+        let status = if hash.trim_end() == hash_data(data, key) {
+            SigStatus::Good
+        } else {
+            SigStatus::Bad
+        };
+        Ok(Verification {
+            status,
+            key: Some(key.to_owned()),
+            display: None,
+        })
+    }
+}
+
+fn hash_data(data: &[u8], key: &str) -> String {
+    let mut hasher = Blake2b512::new();
+    hasher.update(key.as_bytes());
+    hasher.update(data);
+    hex::encode(&hasher.finalize()[..16])
+}
@@ -0,0 +1,271 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing of absolute and relative dates used in date patterns.
+
+use chrono::{
+    DateTime, Datelike as _, Duration, FixedOffset, Months, NaiveDate, NaiveDateTime, TimeZone,
+    Weekday,
+};
+use thiserror::Error;
+
+use crate::backend::{MillisSinceEpoch, Timestamp};
+
+/// Error occurred during parsing of a [`DatePattern`].
+#[derive(Debug, Error)]
+pub enum DatePatternParseError {
+    /// Unknown pattern kind is specified.
+    #[error(r#"Invalid date pattern kind "{0}""#)]
+    InvalidKind(String),
+    /// The date expression couldn't be understood.
+    #[error(r#"Invalid date "{0}""#)]
+    InvalidDate(String),
+}
+
+/// Pattern to be tested against a timestamp like the commit author date.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DatePattern {
+    /// Matches timestamps at or after the given instant.
+    AtOrAfter(MillisSinceEpoch),
+    /// Matches timestamps strictly before the given instant.
+    Before(MillisSinceEpoch),
+}
+
+impl DatePattern {
+    /// Creates a pattern of the given `kind` ("after" or "before") from the
+    /// date expression `src`. Relative dates are resolved against `context`.
+    pub fn from_str_kind(
+        src: &str,
+        kind: &str,
+        context: &DatePatternContext,
+    ) -> Result<Self, DatePatternParseError> {
+        let make_pattern = match kind {
+            "after" => DatePattern::AtOrAfter,
+            "before" => DatePattern::Before,
+            _ => return Err(DatePatternParseError::InvalidKind(kind.to_owned())),
+        };
+        let datetime = context.parse_datetime(src)?;
+        Ok(make_pattern(MillisSinceEpoch(datetime.timestamp_millis())))
+    }
+
+    /// Returns true if this pattern matches the `timestamp`.
+    pub fn matches(&self, timestamp: &Timestamp) -> bool {
+        match self {
+            DatePattern::AtOrAfter(earliest) => timestamp.timestamp >= *earliest,
+            DatePattern::Before(latest) => timestamp.timestamp < *latest,
+        }
+    }
+}
+
+/// Reference point for resolving relative dates such as "2 weeks ago".
+///
+/// The time zone of the reference point is used for dates which don't
+/// specify their own time zone.
+#[derive(Clone, Copy, Debug)]
+pub struct DatePatternContext {
+    now: DateTime<FixedOffset>,
+}
+
+impl DatePatternContext {
+    /// Creates a context which resolves relative dates against the current
+    /// local time.
+    pub fn from_current_time() -> Self {
+        Self::from_timestamp(&Timestamp::now())
+    }
+
+    /// Creates a context which resolves relative dates against `timestamp`.
+    pub fn from_timestamp(timestamp: &Timestamp) -> Self {
+        let offset = FixedOffset::east_opt(timestamp.tz_offset * 60)
+            .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+        let now = offset
+            .timestamp_millis_opt(timestamp.timestamp.0)
+            .single()
+            .unwrap_or_else(|| offset.timestamp_nanos(0));
+        DatePatternContext { now }
+    }
+
+    /// Parses the date expression `src`.
+    ///
+    /// Absolute dates such as "2023-01-01", "2023-01-01 12:30" or RFC 3339
+    /// timestamps are accepted, as well as relative expressions such as "now",
+    /// "today", "yesterday", "3 days ago" and "last tuesday". An expression
+    /// can be followed by a time zone ("UTC", "+09:00", "-0500") to
+    /// override the time zone of this context.
+    pub fn parse_datetime(
+        &self,
+        src: &str,
+    ) -> Result<DateTime<FixedOffset>, DatePatternParseError> {
+        let make_error = || DatePatternParseError::InvalidDate(src.to_owned());
+        let src = src.trim();
+        if let Ok(datetime) = DateTime::parse_from_rfc3339(src) {
+            return Ok(datetime);
+        }
+        let lowercase = src.to_ascii_lowercase();
+        let mut words = lowercase.split_whitespace().collect::<Vec<_>>();
+        let offset = match words.last().and_then(|word| parse_offset(word)) {
+            Some(offset) if words.len() > 1 => {
+                words.pop();
+                offset
+            }
+            _ => *self.now.offset(),
+        };
+        let now = self.now.with_timezone(&offset);
+        let today = now.date_naive();
+        let datetime = match words.as_slice() {
+            ["now"] => Some(now),
+            ["today"] => start_of_day(today, offset),
+            ["yesterday"] => start_of_day(today - Duration::days(1), offset),
+            ["tomorrow"] => start_of_day(today + Duration::days(1), offset),
+            [count, unit, "ago"] => {
+                let count: u32 = count.parse().map_err(|_| make_error())?;
+                subtract_units(now, count, unit)
+            }
+            ["last", unit_or_weekday] => {
+                if let Ok(weekday) = unit_or_weekday.parse::<Weekday>() {
+                    let days_back = (today.weekday().num_days_from_monday() + 6
+                        - weekday.num_days_from_monday())
+                        % 7
+                        + 1;
+                    start_of_day(today - Duration::days(days_back.into()), offset)
+                } else {
+                    subtract_units(now, 1, unit_or_weekday)
+                }
+            }
+            [date] => parse_naive_date(date).and_then(|date| start_of_day(date, offset)),
+            [date, time] => {
+                let datetime = NaiveDateTime::parse_from_str(
+                    &format!("{date} {time}"),
+                    if time.matches(':').count() == 2 {
+                        "%Y-%m-%d %H:%M:%S"
+                    } else {
+                        "%Y-%m-%d %H:%M"
+                    },
+                )
+                .ok();
+                datetime.and_then(|datetime| offset.from_local_datetime(&datetime).single())
+            }
+            _ => None,
+        };
+        datetime.ok_or_else(make_error)
+    }
+}
+
+fn parse_offset(word: &str) -> Option<FixedOffset> {
+    if matches!(word, "utc" | "gmt" | "z") {
+        return FixedOffset::east_opt(0);
+    }
+    let (sign, digits) = match word.as_bytes().first()? {
+        b'+' => (1, &word[1..]),
+        b'-' => (-1, &word[1..]),
+        _ => return None,
+    };
+    let digits = digits.replace(':', "");
+    if !(digits.len() == 2 || digits.len() == 4) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits[2..].parse().unwrap_or(0);
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+fn parse_naive_date(word: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(word, "%Y-%m-%d").ok()
+}
+
+fn start_of_day(date: NaiveDate, offset: FixedOffset) -> Option<DateTime<FixedOffset>> {
+    offset
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+        .single()
+}
+
+fn subtract_units(
+    now: DateTime<FixedOffset>,
+    count: u32,
+    unit: &str,
+) -> Option<DateTime<FixedOffset>> {
+    let unit = unit.strip_suffix('s').unwrap_or(unit);
+    let count_i64 = i64::from(count);
+    match unit {
+        "second" | "sec" => now.checked_sub_signed(Duration::seconds(count_i64)),
+        "minute" | "min" => now.checked_sub_signed(Duration::minutes(count_i64)),
+        "hour" => now.checked_sub_signed(Duration::hours(count_i64)),
+        "day" => now.checked_sub_signed(Duration::days(count_i64)),
+        "week" => now.checked_sub_signed(Duration::weeks(count_i64)),
+        "month" => now.checked_sub_months(Months::new(count)),
+        "year" => now.checked_sub_months(Months::new(count.checked_mul(12)?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> DatePatternContext {
+        // Wednesday
+        let now = DateTime::parse_from_rfc3339("2023-06-14T15:30:00+02:00").unwrap();
+        DatePatternContext::from_timestamp(&Timestamp::from_datetime(now))
+    }
+
+    fn parse(src: &str) -> String {
+        context().parse_datetime(src).unwrap().to_rfc3339()
+    }
+
+    #[test]
+    fn test_parse_absolute() {
+        assert_eq!(parse("2023-01-02"), "2023-01-02T00:00:00+02:00");
+        assert_eq!(parse("2023-01-02 10:20"), "2023-01-02T10:20:00+02:00");
+        assert_eq!(parse("2023-01-02 10:20:30"), "2023-01-02T10:20:30+02:00");
+        assert_eq!(parse("2023-01-02T10:20:30Z"), "2023-01-02T10:20:30+00:00");
+        assert_eq!(parse("2023-01-02 UTC"), "2023-01-02T00:00:00+00:00");
+        assert_eq!(parse("2023-01-02 10:20 -0500"), "2023-01-02T10:20:00-05:00");
+        assert!(context().parse_datetime("2023-13-01").is_err());
+        assert!(context().parse_datetime("UTC").is_err());
+    }
+
+    #[test]
+    fn test_parse_relative() {
+        assert_eq!(parse("now"), "2023-06-14T15:30:00+02:00");
+        assert_eq!(parse("today"), "2023-06-14T00:00:00+02:00");
+        assert_eq!(parse("Yesterday"), "2023-06-13T00:00:00+02:00");
+        assert_eq!(parse("yesterday +09:00"), "2023-06-13T00:00:00+09:00");
+        assert_eq!(parse("today utc"), "2023-06-14T00:00:00+00:00");
+        assert_eq!(parse("1 hour ago"), "2023-06-14T14:30:00+02:00");
+        assert_eq!(parse("2 weeks ago"), "2023-05-31T15:30:00+02:00");
+        assert_eq!(parse("3 months ago"), "2023-03-14T15:30:00+02:00");
+        assert_eq!(parse("last year"), "2022-06-14T15:30:00+02:00");
+        assert_eq!(parse("last tuesday"), "2023-06-13T00:00:00+02:00");
+        assert_eq!(parse("last wed"), "2023-06-07T00:00:00+02:00");
+        assert_eq!(parse("last Thursday"), "2023-06-08T00:00:00+02:00");
+        assert!(context().parse_datetime("2 fortnights ago").is_err());
+        assert!(context().parse_datetime("ago").is_err());
+    }
+
+    #[test]
+    fn test_date_pattern() {
+        let context = context();
+        let pattern = DatePattern::from_str_kind("2023-06-01", "after", &context).unwrap();
+        let timestamp =
+            |src: &str| Timestamp::from_datetime(DateTime::parse_from_rfc3339(src).unwrap());
+        assert!(pattern.matches(&timestamp("2023-06-01T00:00:00+02:00")));
+        assert!(!pattern.matches(&timestamp("2023-05-31T23:59:59+02:00")));
+        let pattern = DatePattern::from_str_kind("2023-06-01", "before", &context).unwrap();
+        assert!(!pattern.matches(&timestamp("2023-06-01T00:00:00+02:00")));
+        assert!(pattern.matches(&timestamp("2023-05-31T22:59:59+01:00")));
+        assert!(matches!(
+            DatePattern::from_str_kind("today", "at", &context),
+            Err(DatePatternParseError::InvalidKind(_))
+        ));
+    }
+}
@@ -224,18 +224,45 @@ impl View {
     fn remove_remote_branch(&mut self, name: &str, remote_name: &str) {
         if let Some(branch) = self.data.branches.get_mut(name) {
             branch.remote_targets.remove(remote_name);
+            branch.untracked_remotes.remove(remote_name);
             if branch.remote_targets.is_empty() && branch.local_target.is_absent() {
                 self.remove_branch(name);
             }
         }
     }
 
+    /// Returns true if the local branch tracks the remote branch of the same
+    /// name.
+    ///
+    /// Remote branches are tracked unless they've been explicitly untracked.
+    pub fn is_remote_branch_tracked(&self, name: &str, remote_name: &str) -> bool {
+        self.data.branches.get(name).map_or(true, |branch| {
+            !branch.untracked_remotes.contains(remote_name)
+        })
+    }
+
+    /// Sets whether the local branch tracks the remote branch of the same name.
+    /// Does nothing if the remote branch doesn't exist.
+    pub fn set_remote_branch_tracked(&mut self, name: &str, remote_name: &str, tracked: bool) {
+        let Some(branch) = self.data.branches.get_mut(name) else {
+            return;
+        };
+        if tracked {
+            branch.untracked_remotes.remove(remote_name);
+        } else if branch.remote_targets.contains_key(remote_name) {
+            branch.untracked_remotes.insert(remote_name.to_owned());
+        }
+    }
+
     pub fn rename_remote(&mut self, old: &str, new: &str) {
         for branch in self.data.branches.values_mut() {
             let target = branch.remote_targets.remove(old).flatten();
             if target.is_present() {
                 branch.remote_targets.insert(new.to_owned(), target);
             }
+            if branch.untracked_remotes.remove(old) {
+                branch.untracked_remotes.insert(new.to_owned());
+            }
         }
     }
 
@@ -33,7 +33,7 @@ use std::time::UNIX_EPOCH;
 use itertools::Itertools;
 use once_cell::unsync::OnceCell;
 use prost::Message;
-use rayon::iter::IntoParallelIterator;
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator};
 use rayon::prelude::ParallelIterator;
 use tempfile::NamedTempFile;
 use thiserror::Error;
@@ -42,7 +42,9 @@ use tracing::{instrument, trace_span};
 use crate::backend::{
     BackendError, FileId, MergedTreeId, MillisSinceEpoch, ObjectId, SymlinkId, TreeId, TreeValue,
 };
-use crate::conflicts;
+use crate::conflicts::{self, ConflictMarkerStyle};
+#[cfg(target_os = "linux")]
+use crate::fsmonitor::inotify;
 #[cfg(feature = "watchman")]
 use crate::fsmonitor::watchman;
 use crate::fsmonitor::FsmonitorKind;
@@ -56,6 +58,7 @@ use crate::merged_tree::{MergedTree, MergedTreeBuilder};
 use crate::op_store::{OperationId, WorkspaceId};
 use crate::repo_path::{FsPathParseError, RepoPath, RepoPathComponent, RepoPathJoin};
 use crate::settings::HumanByteSize;
+use crate::sparse::{self, SparsePath, SparsePattern};
 use crate::store::Store;
 use crate::tree::Tree;
 
@@ -123,13 +126,17 @@ pub struct TreeState {
     tree_id: MergedTreeId,
     file_states: BTreeMap<RepoPath, FileState>,
     // Currently only path prefixes
-    sparse_patterns: Vec<RepoPath>,
+    sparse_patterns: Vec<SparsePattern>,
     own_mtime: MillisSinceEpoch,
 
     /// The most recent clock value returned by Watchman. Will only be set if
     /// the repo is configured to use the Watchman filesystem monitor and
     /// Watchman has been queried at least once.
     watchman_clock: Option<crate::protos::working_copy::WatchmanClock>,
+
+    /// The most recent clock value returned by the built-in inotify filesystem
+    /// monitor.
+    inotify_clock: Option<String>,
 }
 
 fn file_state_from_proto(proto: crate::protos::working_copy::FileState) -> FileState {
@@ -184,18 +191,79 @@ fn file_states_from_proto(
     file_states
 }
 
-fn sparse_patterns_from_proto(proto: &crate::protos::working_copy::TreeState) -> Vec<RepoPath> {
+fn sparse_patterns_from_proto(
+    proto: &crate::protos::working_copy::TreeState,
+) -> Result<Vec<SparsePattern>, glob::PatternError> {
     let mut sparse_patterns = vec![];
     if let Some(proto_sparse_patterns) = proto.sparse_patterns.as_ref() {
-        for prefix in &proto_sparse_patterns.prefixes {
-            sparse_patterns.push(RepoPath::from_internal_string(prefix.as_str()));
+        // If there are any patterns, the prefixes only approximate them for older
+        // versions.
+        if proto_sparse_patterns.patterns.is_empty() {
+            for prefix in &proto_sparse_patterns.prefixes {
+                sparse_patterns.push(SparsePattern::include_prefix(
+                    RepoPath::from_internal_string(prefix.as_str()),
+                ));
+            }
+        }
+        for proto_pattern in &proto_sparse_patterns.patterns {
+            let dir = RepoPath::from_internal_string(proto_pattern.dir.as_str());
+            let path = if proto_pattern.glob.is_empty() {
+                SparsePath::Prefix(dir)
+            } else {
+                let pattern = glob::Pattern::new(&proto_pattern.glob)?;
+                SparsePath::Glob { dir, pattern }
+            };
+            if proto_pattern.exclude {
+                sparse_patterns.push(SparsePattern::Exclude(path));
+            } else {
+                sparse_patterns.push(SparsePattern::Include(path));
+            }
         }
     } else {
         // For compatibility with old working copies.
         // TODO: Delete this is late 2022 or so.
-        sparse_patterns.push(RepoPath::root());
+        sparse_patterns.push(SparsePattern::root());
+    }
+    Ok(sparse_patterns)
+}
+
+fn sparse_patterns_to_proto(
+    sparse_patterns: &[SparsePattern],
+) -> crate::protos::working_copy::SparsePatterns {
+    let mut proto = crate::protos::working_copy::SparsePatterns::default();
+    let is_plain_prefix =
+        |pattern: &SparsePattern| matches!(pattern, SparsePattern::Include(SparsePath::Prefix(_)));
+    if sparse_patterns.iter().all(is_plain_prefix) {
+        // Same format as older versions write
+        for pattern in sparse_patterns {
+            if let SparsePath::Prefix(path) = pattern.path() {
+                proto.prefixes.push(path.to_internal_file_string());
+            }
+        }
+        return proto;
     }
-    sparse_patterns
+    for pattern in sparse_patterns {
+        let (dir, glob) = match pattern.path() {
+            SparsePath::Prefix(path) => (path, ""),
+            SparsePath::Glob { dir, pattern } => (dir, pattern.as_str()),
+        };
+        if !pattern.is_exclude() {
+            // Older versions ignore the patterns and only read the prefixes.
+            // Include the whole directory of each glob so they still check out
+            // at least the files that are matched instead of none.
+            proto.prefixes.push(dir.to_internal_file_string());
+        }
+        proto
+            .patterns
+            .push(crate::protos::working_copy::SparsePattern {
+                exclude: pattern.is_exclude(),
+                dir: dir.to_internal_file_string(),
+                glob: glob.to_owned(),
+            });
+    }
+    proto.prefixes.sort();
+    proto.prefixes.dedup();
+    proto
 }
 
 /// Creates intermediate directories from the `working_copy_path` to the
@@ -359,6 +427,7 @@ pub struct SnapshotOptions<'a> {
     pub fsmonitor_kind: Option<FsmonitorKind>,
     pub progress: Option<&'a SnapshotProgress<'a>>,
     pub max_new_file_size: u64,
+    pub conflict_marker_style: ConflictMarkerStyle,
 }
 
 impl SnapshotOptions<'_> {
@@ -368,6 +437,19 @@ impl SnapshotOptions<'_> {
             fsmonitor_kind: None,
             progress: None,
             max_new_file_size: u64::MAX,
+            conflict_marker_style: ConflictMarkerStyle::default(),
+        }
+    }
+}
+
+pub struct CheckoutOptions {
+    pub conflict_marker_style: ConflictMarkerStyle,
+}
+
+impl CheckoutOptions {
+    pub fn empty_for_test() -> Self {
+        CheckoutOptions {
+            conflict_marker_style: ConflictMarkerStyle::default(),
         }
     }
 }
@@ -375,6 +457,7 @@ impl SnapshotOptions<'_> {
 struct FsmonitorMatcher {
     matcher: Option<Box<dyn Matcher>>,
     watchman_clock: Option<crate::protos::working_copy::WatchmanClock>,
+    inotify_clock: Option<String>,
 }
 
 #[derive(Debug, Error)]
@@ -419,6 +502,11 @@ pub enum TreeStateError {
         path: PathBuf,
         source: tempfile::PersistError,
     },
+    #[error("Decoding sparse patterns from {path}: {source}")]
+    DecodeSparsePatterns {
+        path: PathBuf,
+        source: glob::PatternError,
+    },
     #[error("Filesystem monitor error: {0}")]
     Fsmonitor(Box<dyn Error + Send + Sync>),
 }
@@ -436,12 +524,12 @@ impl TreeState {
         &self.file_states
     }
 
-    pub fn sparse_patterns(&self) -> &Vec<RepoPath> {
+    pub fn sparse_patterns(&self) -> &Vec<SparsePattern> {
         &self.sparse_patterns
     }
 
     fn sparse_matcher(&self) -> Box<dyn Matcher> {
-        Box::new(PrefixMatcher::new(&self.sparse_patterns))
+        sparse::build_matcher(&self.sparse_patterns)
     }
 
     pub fn init(
@@ -464,9 +552,10 @@ impl TreeState {
             state_path,
             tree_id,
             file_states: BTreeMap::new(),
-            sparse_patterns: vec![RepoPath::root()],
+            sparse_patterns: vec![SparsePattern::root()],
             own_mtime: MillisSinceEpoch(0),
             watchman_clock: None,
+            inotify_clock: None,
         }
     }
 
@@ -527,8 +616,14 @@ impl TreeState {
             self.tree_id = MergedTreeId::Merge(tree_ids_builder.build());
         }
         self.file_states = file_states_from_proto(&proto);
-        self.sparse_patterns = sparse_patterns_from_proto(&proto);
+        self.sparse_patterns = sparse_patterns_from_proto(&proto).map_err(|err| {
+            TreeStateError::DecodeSparsePatterns {
+                path: tree_state_path.to_owned(),
+                source: err,
+            }
+        })?;
         self.watchman_clock = proto.watchman_clock;
+        self.inotify_clock = Some(proto.inotify_clock).filter(|clock| !clock.is_empty());
         Ok(())
     }
 
@@ -549,14 +644,9 @@ impl TreeState {
                 file_state_to_proto(file_state),
             );
         }
-        let mut sparse_patterns = crate::protos::working_copy::SparsePatterns::default();
-        for path in &self.sparse_patterns {
-            sparse_patterns
-                .prefixes
-                .push(path.to_internal_file_string());
-        }
-        proto.sparse_patterns = Some(sparse_patterns);
+        proto.sparse_patterns = Some(sparse_patterns_to_proto(&self.sparse_patterns));
         proto.watchman_clock = self.watchman_clock.clone();
+        proto.inotify_clock = self.inotify_clock.clone().unwrap_or_default();
 
         let mut temp_file = NamedTempFile::new_in(&self.state_path).unwrap();
         temp_file
@@ -639,6 +729,14 @@ impl TreeState {
         Ok(changed_files)
     }
 
+    #[cfg(target_os = "linux")]
+    #[instrument(skip(self))]
+    pub fn query_inotify(&self) -> Result<(String, Option<Vec<PathBuf>>), TreeStateError> {
+        let socket_path = self.state_path.join(inotify::SOCKET_FILE_NAME);
+        inotify::query_changed_files(&socket_path, self.inotify_clock.as_deref())
+            .map_err(|err| TreeStateError::Fsmonitor(Box::new(err)))
+    }
+
     /// Look for changes to the working copy. If there are any changes, create
     /// a new tree from it and return it, and also update the dirstate on disk.
     #[instrument(skip_all)]
@@ -648,6 +746,7 @@ impl TreeState {
             fsmonitor_kind,
             progress,
             max_new_file_size,
+            conflict_marker_style,
         } = options;
 
         let sparse_matcher = self.sparse_matcher();
@@ -657,6 +756,7 @@ impl TreeState {
         let FsmonitorMatcher {
             matcher: fsmonitor_matcher,
             watchman_clock,
+            inotify_clock,
         } = self.make_fsmonitor_matcher(fsmonitor_kind)?;
         let fsmonitor_matcher = match fsmonitor_matcher.as_ref() {
             None => &EverythingMatcher,
@@ -684,6 +784,7 @@ impl TreeState {
                 directory_to_visit,
                 progress,
                 max_new_file_size,
+                conflict_marker_style,
             )
         })?;
 
@@ -737,6 +838,7 @@ impl TreeState {
             assert_eq!(state_paths, tree_paths);
         }
         self.watchman_clock = watchman_clock;
+        self.inotify_clock = inotify_clock;
         Ok(is_dirty)
     }
 
@@ -751,6 +853,7 @@ impl TreeState {
         directory_to_visit: DirectoryToVisit,
         progress: Option<&SnapshotProgress>,
         max_new_file_size: u64,
+        conflict_marker_style: ConflictMarkerStyle,
     ) -> Result<(), SnapshotError> {
         let DirectoryToVisit {
             dir,
@@ -833,6 +936,7 @@ impl TreeState {
                                     Some(&current_file_state),
                                     current_tree,
                                     &new_file_state,
+                                    conflict_marker_style,
                                 )?;
                                 if let Some(tree_value) = update {
                                     tree_entries_tx
@@ -859,6 +963,7 @@ impl TreeState {
                             directory_to_visit,
                             progress,
                             max_new_file_size,
+                            conflict_marker_style,
                         )?;
                     }
                 } else if matcher.matches(&path) {
@@ -893,6 +998,7 @@ impl TreeState {
                                 maybe_current_file_state,
                                 current_tree,
                                 &new_file_state,
+                                conflict_marker_style,
                             )?;
                             if let Some(tree_value) = update {
                                 tree_entries_tx.send((path.clone(), tree_value)).ok();
@@ -914,15 +1020,17 @@ impl TreeState {
         &mut self,
         fsmonitor_kind: Option<FsmonitorKind>,
     ) -> Result<FsmonitorMatcher, SnapshotError> {
-        let (watchman_clock, changed_files) = match fsmonitor_kind {
-            None => (None, None),
-            Some(FsmonitorKind::Test { changed_files }) => (None, Some(changed_files)),
+        let (watchman_clock, inotify_clock, changed_files) = match fsmonitor_kind {
+            None => (None, None, None),
+            Some(FsmonitorKind::Test { changed_files }) => (None, None, Some(changed_files)),
             #[cfg(feature = "watchman")]
             Some(FsmonitorKind::Watchman) => match self.query_watchman() {
-                Ok((watchman_clock, changed_files)) => (Some(watchman_clock.into()), changed_files),
+                Ok((watchman_clock, changed_files)) => {
+                    (Some(watchman_clock.into()), None, changed_files)
+                }
                 Err(err) => {
                     tracing::warn!(?err, "Failed to query filesystem monitor");
-                    (None, None)
+                    (None, None, None)
                 }
             },
             #[cfg(not(feature = "watchman"))]
@@ -933,6 +1041,22 @@ impl TreeState {
                         .to_string(),
                 ));
             }
+            #[cfg(target_os = "linux")]
+            Some(FsmonitorKind::Inotify) => match self.query_inotify() {
+                Ok((inotify_clock, changed_files)) => (None, Some(inotify_clock), changed_files),
+                Err(err) => {
+                    tracing::warn!(?err, "Failed to query filesystem monitor");
+                    (None, None, None)
+                }
+            },
+            #[cfg(not(target_os = "linux"))]
+            Some(FsmonitorKind::Inotify) => {
+                return Err(SnapshotError::FsmonitorError(
+                    "The inotify filesystem monitor is only supported on Linux (consider \
+                     disabling `core.fsmonitor`)"
+                        .to_string(),
+                ));
+            }
         };
         let matcher: Option<Box<dyn Matcher>> = match changed_files {
             None => None,
@@ -959,6 +1083,7 @@ impl TreeState {
         Ok(FsmonitorMatcher {
             matcher,
             watchman_clock,
+            inotify_clock,
         })
     }
 
@@ -969,6 +1094,7 @@ impl TreeState {
         maybe_current_file_state: Option<&FileState>,
         current_tree: &MergedTree,
         new_file_state: &FileState,
+        conflict_marker_style: ConflictMarkerStyle,
     ) -> Result<Option<Merge<Option<TreeValue>>>, SnapshotError> {
         let clean = match maybe_current_file_state {
             None => {
@@ -991,6 +1117,7 @@ impl TreeState {
                 &disk_path,
                 &current_tree_values,
                 new_file_type,
+                conflict_marker_style,
             )?;
             if new_tree_values != current_tree_values {
                 Ok(Some(new_tree_values))
@@ -1006,6 +1133,7 @@ impl TreeState {
         disk_path: &Path,
         current_tree_values: &Merge<Option<TreeValue>>,
         file_type: FileType,
+        conflict_marker_style: ConflictMarkerStyle,
     ) -> Result<Merge<Option<TreeValue>>, SnapshotError> {
         let executable = match file_type {
             FileType::Normal { executable } => executable,
@@ -1043,6 +1171,7 @@ impl TreeState {
                 self.store.as_ref(),
                 repo_path,
                 &content,
+                conflict_marker_style,
             )?;
             match new_file_ids.into_resolved() {
                 Ok(file_id) => {
@@ -1138,6 +1267,7 @@ impl TreeState {
         disk_path: &Path,
         path: &RepoPath,
         conflict: &Merge<Option<TreeValue>>,
+        conflict_marker_style: ConflictMarkerStyle,
     ) -> Result<FileState, CheckoutError> {
         create_parent_dirs(&self.working_copy_path, path)?;
         let mut file = OpenOptions::new()
@@ -1149,8 +1279,14 @@ impl TreeState {
                 err,
             })?;
         let mut conflict_data = vec![];
-        conflicts::materialize(conflict, self.store.as_ref(), path, &mut conflict_data)
-            .expect("Failed to materialize conflict to in-memory buffer");
+        conflicts::materialize(
+            conflict,
+            self.store.as_ref(),
+            path,
+            conflict_marker_style,
+            &mut conflict_data,
+        )
+        .expect("Failed to materialize conflict to in-memory buffer");
         file.write_all(&conflict_data)
             .map_err(|err| CheckoutError::IoError {
                 message: format!("Failed to write conflict to file {}", disk_path.display()),
@@ -1176,21 +1312,32 @@ impl TreeState {
         Ok(())
     }
 
-    pub fn check_out(&mut self, new_tree: &MergedTree) -> Result<CheckoutStats, CheckoutError> {
+    pub fn check_out(
+        &mut self,
+        new_tree: &MergedTree,
+        options: &CheckoutOptions,
+    ) -> Result<CheckoutStats, CheckoutError> {
         let old_tree = self.current_tree().map_err(|err| match err {
             err @ BackendError::ObjectNotFound { .. } => CheckoutError::SourceNotFound {
                 source: Box::new(err),
             },
             other => CheckoutError::InternalBackendError(other),
         })?;
-        let stats = self.update(&old_tree, new_tree, self.sparse_matcher().as_ref(), Err)?;
+        let stats = self.update(
+            &old_tree,
+            new_tree,
+            self.sparse_matcher().as_ref(),
+            options,
+            Err,
+        )?;
         self.tree_id = new_tree.id();
         Ok(stats)
     }
 
     pub fn set_sparse_patterns(
         &mut self,
-        sparse_patterns: Vec<RepoPath>,
+        sparse_patterns: Vec<SparsePattern>,
+        options: &CheckoutOptions,
     ) -> Result<CheckoutStats, CheckoutError> {
         let tree = self.current_tree().map_err(|err| match err {
             err @ BackendError::ObjectNotFound { .. } => CheckoutError::SourceNotFound {
@@ -1198,8 +1345,8 @@ impl TreeState {
             },
             other => CheckoutError::InternalBackendError(other),
         })?;
-        let old_matcher = PrefixMatcher::new(&self.sparse_patterns);
-        let new_matcher = PrefixMatcher::new(&sparse_patterns);
+        let old_matcher = sparse::build_matcher(&self.sparse_patterns);
+        let new_matcher = sparse::build_matcher(&sparse_patterns);
         let added_matcher = DifferenceMatcher::new(&new_matcher, &old_matcher);
         let removed_matcher = DifferenceMatcher::new(&old_matcher, &new_matcher);
         let empty_tree = MergedTree::resolved(Tree::null(self.store.clone(), RepoPath::root()));
@@ -1207,9 +1354,10 @@ impl TreeState {
             &empty_tree,
             &tree,
             &added_matcher,
+            options,
             suppress_file_exists_error, // Keep un-ignored file and mark it as modified
         )?;
-        let removed_stats = self.update(&tree, &empty_tree, &removed_matcher, Err)?;
+        let removed_stats = self.update(&tree, &empty_tree, &removed_matcher, options, Err)?;
         self.sparse_patterns = sparse_patterns;
         assert_eq!(added_stats.updated_files, 0);
         assert_eq!(added_stats.removed_files, 0);
@@ -1222,67 +1370,26 @@ impl TreeState {
         })
     }
 
+    /// Updates the working copy from `old_tree` to `new_tree`.
+    ///
+    /// Files are removed and written in parallel on the global thread pool.
+    /// The file states are recorded in diff order afterwards, so the result
+    /// doesn't depend on the order in which the files were written.
     fn update(
         &mut self,
         old_tree: &MergedTree,
         new_tree: &MergedTree,
         matcher: &dyn Matcher,
+        options: &CheckoutOptions,
         mut handle_error: impl FnMut(CheckoutError) -> Result<(), CheckoutError>,
     ) -> Result<CheckoutStats, CheckoutError> {
-        let mut apply_diff = |path: RepoPath,
-                              before: Merge<Option<TreeValue>>,
-                              after: Merge<Option<TreeValue>>|
-         -> Result<(), CheckoutError> {
-            let disk_path = path.to_fs_path(&self.working_copy_path);
-
-            if before.is_present() {
-                fs::remove_file(&disk_path).ok();
-            }
-            // TODO: Check that the file has not changed before overwriting/removing it.
-            match after.into_resolved() {
-                Ok(None) => {
-                    let mut parent_dir = disk_path.parent().unwrap();
-                    loop {
-                        if fs::remove_dir(parent_dir).is_err() {
-                            break;
-                        }
-                        parent_dir = parent_dir.parent().unwrap();
-                    }
-                    self.file_states.remove(&path);
-                }
-                Ok(Some(after)) => {
-                    let file_state = match after {
-                        TreeValue::File { id, executable } => {
-                            self.write_file(&disk_path, &path, &id, executable)?
-                        }
-                        TreeValue::Symlink(id) => self.write_symlink(&disk_path, &path, &id)?,
-                        TreeValue::Conflict(_) => {
-                            panic!("unexpected conflict entry in diff at {path:?}");
-                        }
-                        TreeValue::GitSubmodule(_id) => {
-                            println!("ignoring git submodule at {path:?}");
-                            FileState::for_gitsubmodule()
-                        }
-                        TreeValue::Tree(_id) => {
-                            panic!("unexpected tree entry in diff at {path:?}");
-                        }
-                    };
-                    self.file_states.insert(path, file_state);
-                }
-                Err(after_conflict) => {
-                    let file_state = self.write_conflict(&disk_path, &path, &after_conflict)?;
-                    self.file_states.insert(path, file_state);
-                }
-            }
-            Ok(())
-        };
-
         let mut stats = CheckoutStats {
             updated_files: 0,
             added_files: 0,
             removed_files: 0,
         };
-        for (path, before, after) in old_tree.diff(new_tree, matcher) {
+        let diff = old_tree.diff(new_tree, matcher).collect_vec();
+        for (_path, before, after) in &diff {
             if after.is_absent() {
                 stats.removed_files += 1;
             } else if before.is_absent() {
@@ -1290,11 +1397,83 @@ impl TreeState {
             } else {
                 stats.updated_files += 1;
             }
-            apply_diff(path, before, after).or_else(&mut handle_error)?;
+        }
+
+        // Remove all old files first so a new file can replace a directory and
+        // vice versa regardless of the order the files are written in.
+        trace_span!("remove old files").in_scope(|| {
+            diff.par_iter()
+                .filter(|(_path, before, _after)| before.is_present())
+                .for_each(|(path, _before, _after)| {
+                    // TODO: Check that the file has not changed before overwriting/removing it.
+                    fs::remove_file(path.to_fs_path(&self.working_copy_path)).ok();
+                });
+        });
+        // Sibling files share parent directories, so empty directories are
+        // pruned on a single thread.
+        trace_span!("remove empty directories").in_scope(|| {
+            for (path, _before, _after) in diff.iter().filter(|(_, _, after)| after.is_absent()) {
+                let disk_path = path.to_fs_path(&self.working_copy_path);
+                let mut parent_dir = disk_path.parent().unwrap();
+                loop {
+                    if fs::remove_dir(parent_dir).is_err() {
+                        break;
+                    }
+                    parent_dir = parent_dir.parent().unwrap();
+                }
+            }
+        });
+        let new_file_states = trace_span!("write new files").in_scope(|| {
+            diff.par_iter()
+                .map(|(path, _before, after)| self.write_tree_value(path, after, options))
+                .collect::<Vec<_>>()
+        });
+
+        for ((path, _before, _after), new_file_state) in diff.into_iter().zip(new_file_states) {
+            match new_file_state {
+                Ok(None) => {
+                    self.file_states.remove(&path);
+                }
+                Ok(Some(file_state)) => {
+                    if file_state.file_type == FileType::GitSubmodule {
+                        println!("ignoring git submodule at {path:?}");
+                    }
+                    self.file_states.insert(path, file_state);
+                }
+                Err(err) => handle_error(err)?,
+            }
         }
         Ok(stats)
     }
 
+    /// Writes the new `value` of the file at `path`, whose old version has
+    /// already been removed. Returns the state of the written file, or `None`
+    /// if the file was deleted.
+    fn write_tree_value(
+        &self,
+        path: &RepoPath,
+        value: &Merge<Option<TreeValue>>,
+        options: &CheckoutOptions,
+    ) -> Result<Option<FileState>, CheckoutError> {
+        let disk_path = path.to_fs_path(&self.working_copy_path);
+        let file_state = match value.as_resolved() {
+            Some(None) => return Ok(None),
+            Some(Some(TreeValue::File { id, executable })) => {
+                self.write_file(&disk_path, path, id, *executable)?
+            }
+            Some(Some(TreeValue::Symlink(id))) => self.write_symlink(&disk_path, path, id)?,
+            Some(Some(TreeValue::Conflict(_))) => {
+                panic!("unexpected conflict entry in diff at {path:?}");
+            }
+            Some(Some(TreeValue::GitSubmodule(_id))) => FileState::for_gitsubmodule(),
+            Some(Some(TreeValue::Tree(_id))) => {
+                panic!("unexpected tree entry in diff at {path:?}");
+            }
+            None => self.write_conflict(&disk_path, path, value, options.conflict_marker_style)?,
+        };
+        Ok(Some(file_state))
+    }
+
     pub fn reset(&mut self, new_tree: &MergedTree) -> Result<(), ResetError> {
         let old_tree = self.current_tree().map_err(|err| match err {
             err @ BackendError::ObjectNotFound { .. } => ResetError::SourceNotFound {
@@ -1476,7 +1655,7 @@ impl WorkingCopy {
         Ok(self.tree_state()?.file_states())
     }
 
-    pub fn sparse_patterns(&self) -> Result<&[RepoPath], TreeStateError> {
+    pub fn sparse_patterns(&self) -> Result<&[SparsePattern], TreeStateError> {
         Ok(self.tree_state()?.sparse_patterns())
     }
 
@@ -1516,6 +1695,7 @@ impl WorkingCopy {
         operation_id: OperationId,
         old_tree_id: Option<&MergedTreeId>,
         new_tree: &MergedTree,
+        options: &CheckoutOptions,
     ) -> Result<CheckoutStats, CheckoutError> {
         let mut locked_wc = self.start_mutation()?;
         // Check if the current working-copy commit has changed on disk compared to what
@@ -1527,7 +1707,7 @@ impl WorkingCopy {
                 return Err(CheckoutError::ConcurrentCheckout);
             }
         }
-        let stats = locked_wc.check_out(new_tree)?;
+        let stats = locked_wc.check_out(new_tree, options)?;
         locked_wc.finish(operation_id)?;
         Ok(stats)
     }
@@ -1578,10 +1758,14 @@ impl LockedWorkingCopy<'_> {
         Ok(tree_state.current_tree_id().clone())
     }
 
-    pub fn check_out(&mut self, new_tree: &MergedTree) -> Result<CheckoutStats, CheckoutError> {
+    pub fn check_out(
+        &mut self,
+        new_tree: &MergedTree,
+        options: &CheckoutOptions,
+    ) -> Result<CheckoutStats, CheckoutError> {
         // TODO: Write a "pending_checkout" file with the new TreeId so we can
         // continue an interrupted update if we find such a file.
-        let stats = self.wc.tree_state_mut()?.check_out(new_tree)?;
+        let stats = self.wc.tree_state_mut()?.check_out(new_tree, options)?;
         self.tree_state_dirty = true;
         Ok(stats)
     }
@@ -1592,20 +1776,21 @@ impl LockedWorkingCopy<'_> {
         Ok(())
     }
 
-    pub fn sparse_patterns(&self) -> Result<&[RepoPath], TreeStateError> {
+    pub fn sparse_patterns(&self) -> Result<&[SparsePattern], TreeStateError> {
         self.wc.sparse_patterns()
     }
 
     pub fn set_sparse_patterns(
         &mut self,
-        new_sparse_patterns: Vec<RepoPath>,
+        new_sparse_patterns: Vec<SparsePattern>,
+        options: &CheckoutOptions,
     ) -> Result<CheckoutStats, CheckoutError> {
         // TODO: Write a "pending_checkout" file with new sparse patterns so we can
         // continue an interrupted update if we find such a file.
         let stats = self
             .wc
             .tree_state_mut()?
-            .set_sparse_patterns(new_sparse_patterns)?;
+            .set_sparse_patterns(new_sparse_patterns, options)?;
         self.tree_state_dirty = true;
         Ok(stats)
     }
@@ -0,0 +1,214 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+
+use jj_lib::absorb::{absorb_hunks, split_hunks_to_trees};
+use jj_lib::backend::TreeValue;
+use jj_lib::commit::Commit;
+use jj_lib::matchers::{EverythingMatcher, FilesMatcher};
+use jj_lib::repo::{MutableRepo, Repo};
+use jj_lib::repo_path::RepoPath;
+use jj_lib::settings::UserSettings;
+use testutils::{create_tree, TestRepo};
+
+fn write_commit(
+    mut_repo: &mut MutableRepo,
+    settings: &UserSettings,
+    parent: &Commit,
+    path_contents: &[(&RepoPath, &str)],
+) -> Commit {
+    let tree = create_tree(mut_repo.base_repo(), path_contents);
+    mut_repo
+        .new_commit(settings, vec![parent.id().clone()], tree.id())
+        .write()
+        .unwrap()
+}
+
+fn file_contents(commit: &Commit, path: &RepoPath) -> String {
+    let value = commit.tree().unwrap().path_value(path);
+    match value.into_resolved() {
+        Ok(Some(TreeValue::File { id, .. })) => {
+            let content = testutils::read_file(commit.store(), path, &id);
+            String::from_utf8(content).unwrap()
+        }
+        value => panic!("unexpected value: {value:?}"),
+    }
+}
+
+#[test]
+fn test_absorb_into_commits_that_modified_lines() {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+    let root_commit = repo.store().root_commit();
+    let file = RepoPath::from_internal_string("file");
+    let other_file = RepoPath::from_internal_string("other");
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let commit_a = write_commit(mut_repo, &settings, &root_commit, &[(&file, "a\nb\nc\n")]);
+    let commit_b = write_commit(
+        mut_repo,
+        &settings,
+        &commit_a,
+        &[(&file, "a\nb\nc\nd\ne\nf\n")],
+    );
+    let commit_c = write_commit(
+        mut_repo,
+        &settings,
+        &commit_b,
+        &[(&file, "a\nb\nc\nd\ne\nf\n"), (&other_file, "other\n")],
+    );
+    // Modifies a line from A, inserts a line inside B's lines, modifies lines
+    // from both A and B, and adds a new file.
+    let source = write_commit(
+        mut_repo,
+        &settings,
+        &commit_c,
+        &[
+            (&file, "A\nb\nC\nD\ne\ne2\nf\n"),
+            (&other_file, "other\n"),
+            (&RepoPath::from_internal_string("new"), "new\n"),
+        ],
+    );
+    let destinations: HashSet<_> = [&commit_a, &commit_b, &commit_c]
+        .iter()
+        .map(|commit| commit.id().clone())
+        .collect();
+
+    let selected_trees =
+        split_hunks_to_trees(mut_repo, &source, &destinations, &EverythingMatcher).unwrap();
+    assert_eq!(
+        selected_trees
+            .iter()
+            .map(|selected| selected.commit.id())
+            .collect::<Vec<_>>(),
+        vec![commit_a.id(), commit_b.id()]
+    );
+    let stats = absorb_hunks(mut_repo, &settings, &source, selected_trees).unwrap();
+    assert_eq!(stats.rewritten_destinations.len(), 2);
+    assert_eq!(stats.num_rebased, 2);
+    assert!(!stats.source_abandoned);
+
+    let new_a = &stats.rewritten_destinations[0];
+    assert_eq!(new_a.change_id(), commit_a.change_id());
+    assert_eq!(file_contents(new_a, &file), "A\nb\nc\n");
+    let new_b = &stats.rewritten_destinations[1];
+    assert_eq!(new_b.change_id(), commit_b.change_id());
+    assert_eq!(new_b.parent_ids(), [new_a.id().clone()]);
+    assert_eq!(file_contents(new_b, &file), "A\nb\nc\nd\ne\ne2\nf\n");
+
+    // The hunk spanning lines from both A and B, and the added file stay in the
+    // source.
+    let heads = mut_repo.view().heads().iter().cloned().collect::<Vec<_>>();
+    assert_eq!(heads.len(), 1);
+    let new_source = repo.store().get_commit(&heads[0]).unwrap();
+    assert_eq!(new_source.change_id(), source.change_id());
+    assert_eq!(new_source.tree_id(), source.tree_id());
+    let new_c = &new_source.parents()[0];
+    assert_eq!(new_c.change_id(), commit_c.change_id());
+    assert_eq!(new_c.parent_ids(), [new_b.id().clone()]);
+    assert_eq!(file_contents(new_c, &file), "A\nb\nc\nd\ne\ne2\nf\n");
+}
+
+#[test]
+fn test_absorb_skips_immutable_and_unmatched() {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+    let root_commit = repo.store().root_commit();
+    let file1 = RepoPath::from_internal_string("file1");
+    let file2 = RepoPath::from_internal_string("file2");
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let commit_a = write_commit(
+        mut_repo,
+        &settings,
+        &root_commit,
+        &[(&file1, "a\n"), (&file2, "a\n")],
+    );
+    let commit_b = write_commit(
+        mut_repo,
+        &settings,
+        &commit_a,
+        &[(&file1, "a\nb\n"), (&file2, "a\nb\n")],
+    );
+    let source = write_commit(
+        mut_repo,
+        &settings,
+        &commit_b,
+        &[(&file1, "A\nB\n"), (&file2, "A\nB\n")],
+    );
+    let file1_matcher = FilesMatcher::new(&[RepoPath::from_internal_string("file1")]);
+    // Lines from A aren't absorbed since A isn't a destination
+    let destinations = HashSet::from([commit_b.id().clone()]);
+    let selected_trees =
+        split_hunks_to_trees(mut_repo, &source, &destinations, &file1_matcher).unwrap();
+    assert!(selected_trees.is_empty());
+
+    let destinations = HashSet::from([commit_a.id().clone(), commit_b.id().clone()]);
+    let selected_trees =
+        split_hunks_to_trees(mut_repo, &source, &destinations, &file1_matcher).unwrap();
+    assert!(selected_trees.is_empty());
+
+    // Once each line is changed separately, they go to different commits, and
+    // paths not matching are left alone.
+    let source = write_commit(
+        mut_repo,
+        &settings,
+        &commit_b,
+        &[(&file1, "A\nb\n"), (&file2, "A\nB\n")],
+    );
+    let selected_trees =
+        split_hunks_to_trees(mut_repo, &source, &destinations, &file1_matcher).unwrap();
+    assert_eq!(selected_trees.len(), 1);
+    let stats = absorb_hunks(mut_repo, &settings, &source, selected_trees).unwrap();
+    let new_a = &stats.rewritten_destinations[0];
+    assert_eq!(file_contents(new_a, &file1), "A\n");
+    assert_eq!(file_contents(new_a, &file2), "a\n");
+    assert!(!stats.source_abandoned);
+}
+
+#[test]
+fn test_absorb_abandons_empty_source() {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+    let root_commit = repo.store().root_commit();
+    let file = RepoPath::from_internal_string("file");
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let commit_a = write_commit(mut_repo, &settings, &root_commit, &[(&file, "a\nb\n")]);
+    let source = write_commit(mut_repo, &settings, &commit_a, &[(&file, "a\nB\n")]);
+    let child = write_commit(mut_repo, &settings, &source, &[(&file, "a\nB\nc\n")]);
+
+    let destinations = HashSet::from([commit_a.id().clone()]);
+    let selected_trees =
+        split_hunks_to_trees(mut_repo, &source, &destinations, &EverythingMatcher).unwrap();
+    let stats = absorb_hunks(mut_repo, &settings, &source, selected_trees).unwrap();
+    assert!(stats.source_abandoned);
+    assert_eq!(stats.num_rebased, 1);
+    let new_a = &stats.rewritten_destinations[0];
+    assert_eq!(file_contents(new_a, &file), "a\nB\n");
+
+    let heads = mut_repo.view().heads().iter().cloned().collect::<Vec<_>>();
+    assert_eq!(heads.len(), 1);
+    let new_child = repo.store().get_commit(&heads[0]).unwrap();
+    assert_eq!(new_child.change_id(), child.change_id());
+    assert_eq!(new_child.parent_ids(), [new_a.id().clone()]);
+    assert_eq!(new_child.tree_id(), child.tree_id());
+}
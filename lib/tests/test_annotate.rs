@@ -0,0 +1,147 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jj_lib::annotate::annotate_file;
+use jj_lib::backend::{CommitId, MergedTreeId};
+use jj_lib::commit::Commit;
+use jj_lib::conflicts::ConflictMarkerStyle;
+use jj_lib::repo::{MutableRepo, Repo};
+use jj_lib::repo_path::RepoPath;
+use jj_lib::settings::UserSettings;
+use testutils::{create_tree, TestRepo};
+
+fn write_commit(
+    mut_repo: &mut MutableRepo,
+    settings: &UserSettings,
+    parents: &[&Commit],
+    tree_id: MergedTreeId,
+) -> Commit {
+    let parent_ids = parents.iter().map(|commit| commit.id().clone()).collect();
+    mut_repo
+        .new_commit(settings, parent_ids, tree_id)
+        .write()
+        .unwrap()
+}
+
+fn annotate(repo: &dyn Repo, commit: &Commit, path: &RepoPath) -> Vec<(CommitId, usize, String)> {
+    annotate_file(repo, commit, path, ConflictMarkerStyle::Merge)
+        .unwrap()
+        .into_iter()
+        .map(|line| {
+            (
+                line.commit.id().clone(),
+                line.original_line,
+                String::from_utf8(line.content).unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_annotate_linear() {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+    let root_commit = repo.store().root_commit();
+    let file = RepoPath::from_internal_string("file");
+    let other_file = RepoPath::from_internal_string("other");
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let tree_a = create_tree(repo, &[(&file, "a\nb\nc\n")]);
+    let commit_a = write_commit(mut_repo, &settings, &[&root_commit], tree_a.id());
+    let tree_b = create_tree(repo, &[(&file, "0\na\nB\nc\n")]);
+    let commit_b = write_commit(mut_repo, &settings, &[&commit_a], tree_b.id());
+    let tree_c = create_tree(repo, &[(&file, "0\na\nB\nc\n"), (&other_file, "other\n")]);
+    let commit_c = write_commit(mut_repo, &settings, &[&commit_b], tree_c.id());
+    let tree_d = create_tree(repo, &[(&file, "0\na\nB\nc\nd")]);
+    let commit_d = write_commit(mut_repo, &settings, &[&commit_c], tree_d.id());
+
+    assert_eq!(
+        annotate(mut_repo, &commit_d, &file),
+        vec![
+            (commit_b.id().clone(), 0, "0\n".to_owned()),
+            (commit_a.id().clone(), 0, "a\n".to_owned()),
+            (commit_b.id().clone(), 2, "B\n".to_owned()),
+            (commit_a.id().clone(), 2, "c\n".to_owned()),
+            (commit_d.id().clone(), 4, "d".to_owned()),
+        ]
+    );
+    assert_eq!(annotate(mut_repo, &commit_a, &other_file), vec![]);
+}
+
+#[test]
+fn test_annotate_merge() {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+    let root_commit = repo.store().root_commit();
+    let file = RepoPath::from_internal_string("file");
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let tree_a = create_tree(repo, &[(&file, "a\n")]);
+    let commit_a = write_commit(mut_repo, &settings, &[&root_commit], tree_a.id());
+    let tree_b = create_tree(repo, &[(&file, "a\nb\n")]);
+    let commit_b = write_commit(mut_repo, &settings, &[&commit_a], tree_b.id());
+    let tree_c = create_tree(repo, &[(&file, "a\nc\n")]);
+    let commit_c = write_commit(mut_repo, &settings, &[&commit_a], tree_c.id());
+
+    // Lines from either side are attributed to that side
+    let tree_merge = create_tree(repo, &[(&file, "a\nb\nm\nc\n")]);
+    let commit_merge = write_commit(
+        mut_repo,
+        &settings,
+        &[&commit_b, &commit_c],
+        tree_merge.id(),
+    );
+    assert_eq!(
+        annotate(mut_repo, &commit_merge, &file),
+        vec![
+            (commit_a.id().clone(), 0, "a\n".to_owned()),
+            (commit_b.id().clone(), 1, "b\n".to_owned()),
+            (commit_merge.id().clone(), 2, "m\n".to_owned()),
+            (commit_c.id().clone(), 1, "c\n".to_owned()),
+        ]
+    );
+
+    // Conflict markers are attributed to the commit that introduced the
+    // conflict
+    let tree_conflict = tree_b.merge(&tree_a, &tree_c).unwrap();
+    let commit_conflict = write_commit(
+        mut_repo,
+        &settings,
+        &[&commit_b, &commit_c],
+        tree_conflict.id(),
+    );
+    assert_eq!(
+        annotate(mut_repo, &commit_conflict, &file),
+        vec![
+            (commit_a.id().clone(), 0, "a\n".to_owned()),
+            (
+                commit_conflict.id().clone(),
+                1,
+                "<<<<<<< Side #1\n".to_owned()
+            ),
+            (commit_b.id().clone(), 1, "b\n".to_owned()),
+            (commit_conflict.id().clone(), 3, "=======\n".to_owned()),
+            (commit_c.id().clone(), 1, "c\n".to_owned()),
+            (
+                commit_conflict.id().clone(),
+                5,
+                ">>>>>>> Side #2\n".to_owned()
+            ),
+        ]
+    );
+}
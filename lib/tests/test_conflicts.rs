@@ -15,6 +15,7 @@
 use jj_lib::backend::FileId;
 use jj_lib::conflicts::{
     extract_as_single_hunk, materialize_merge_result, parse_conflict, update_from_content,
+    ConflictMarkerStyle, MIN_CONFLICT_MARKER_LEN,
 };
 use jj_lib::merge::Merge;
 use jj_lib::repo::Repo;
@@ -299,7 +300,7 @@ line 5 right
 
     // The first add should always be from the left side
     insta::assert_debug_snapshot!(
-        parse_conflict(materialized.as_bytes(), conflict.num_sides()),
+        parse_conflict(materialized.as_bytes(), conflict.num_sides(), MIN_CONFLICT_MARKER_LEN),
         @r###"
     Some(
         [
@@ -436,7 +437,8 @@ line 3
 line 4
 line 5
 ",
-            2
+            2,
+            MIN_CONFLICT_MARKER_LEN,
         ),
         None
     )
@@ -458,7 +460,8 @@ right
 >>>>>>>
 line 5
 ",
-            2
+            2,
+            MIN_CONFLICT_MARKER_LEN,
         ),
         @r###"
     Some(
@@ -511,7 +514,8 @@ right
 >>>>>>>
 line 5
 ",
-            3
+            3,
+            MIN_CONFLICT_MARKER_LEN,
         ),
         @r###"
     Some(
@@ -561,7 +565,8 @@ right
 >>>>>>>
 line 5
 ",
-            3
+            3,
+            MIN_CONFLICT_MARKER_LEN,
         ),
         None
     )
@@ -583,7 +588,8 @@ right
 >>>>>>>
 line 5
 ",
-            2
+            2,
+            MIN_CONFLICT_MARKER_LEN,
         ),
         None
     )
@@ -606,7 +612,8 @@ right
 >>>>>>>
 line 5
 ",
-            2
+            2,
+            MIN_CONFLICT_MARKER_LEN,
         ),
         None
     )
@@ -629,12 +636,25 @@ fn test_update_conflict_from_content() {
     // If the content is unchanged compared to the materialized value, we get the
     // old conflict id back.
     let materialized = materialize_conflict_string(store, &path, &conflict);
-    let result = update_from_content(&conflict, store, &path, materialized.as_bytes()).unwrap();
+    let result = update_from_content(
+        &conflict,
+        store,
+        &path,
+        materialized.as_bytes(),
+        ConflictMarkerStyle::Diff,
+    )
+    .unwrap();
     assert_eq!(result, conflict);
 
     // If the conflict is resolved, we get None back to indicate that.
-    let result =
-        update_from_content(&conflict, store, &path, b"resolved 1\nline 2\nresolved 3\n").unwrap();
+    let result = update_from_content(
+        &conflict,
+        store,
+        &path,
+        b"resolved 1\nline 2\nresolved 3\n",
+        ConflictMarkerStyle::Diff,
+    )
+    .unwrap();
     let expected_file_id = testutils::write_file(store, &path, "resolved 1\nline 2\nresolved 3\n");
     assert_eq!(result, Merge::normal(expected_file_id));
 
@@ -644,6 +664,7 @@ fn test_update_conflict_from_content() {
         store,
         &path,
         b"resolved 1\nline 2\n<<<<<<<\n%%%%%%%\n-line 3\n+left 3\n+++++++\nright 3\n>>>>>>>\n",
+        ConflictMarkerStyle::Diff,
     )
     .unwrap();
     assert_ne!(new_conflict, conflict);
@@ -676,20 +697,36 @@ fn test_update_conflict_from_content_modify_delete() {
     // If the content is unchanged compared to the materialized value, we get the
     // old conflict id back.
     let materialized = materialize_conflict_string(store, &path, &conflict);
-    let result = update_from_content(&conflict, store, &path, materialized.as_bytes()).unwrap();
+    let result = update_from_content(
+        &conflict,
+        store,
+        &path,
+        materialized.as_bytes(),
+        ConflictMarkerStyle::Diff,
+    )
+    .unwrap();
     assert_eq!(result, conflict);
 
     // If the conflict is resolved, we get None back to indicate that.
-    let result = update_from_content(&conflict, store, &path, b"resolved\n").unwrap();
+    let result = update_from_content(
+        &conflict,
+        store,
+        &path,
+        b"resolved\n",
+        ConflictMarkerStyle::Diff,
+    )
+    .unwrap();
     let expected_file_id = testutils::write_file(store, &path, "resolved\n");
     assert_eq!(result, Merge::normal(expected_file_id));
 
     // If the conflict is modified, we get a new conflict back.
-    let new_conflict = update_from_content(&conflict,
-        store,
-        &path,
-        b"<<<<<<<\n%%%%%%%\n line 1\n-line 2 before\n+line 2 modified after\n line 3\n+++++++\n>>>>>>>\n",
-    )
+    let new_conflict = update_from_content(
+&conflict,
+store,
+&path,
+b"<<<<<<<\n%%%%%%%\n line 1\n-line 2 before\n+line 2 modified after\n line 3\n+++++++\n>>>>>>>\n",
+ConflictMarkerStyle::Diff,
+)
     .unwrap();
     // Calculate expected new FileIds
     let new_base_file_id = testutils::write_file(store, &path, "line 1\nline 2 before\nline 3\n");
@@ -705,13 +742,351 @@ fn test_update_conflict_from_content_modify_delete() {
     );
 }
 
+#[test]
+fn test_materialize_conflict_git_styles() {
+    let test_repo = TestRepo::init();
+    let store = test_repo.repo.store();
+
+    let path = RepoPath::from_internal_string("file");
+    let base_id = testutils::write_file(store, &path, "line 1\nline 2\nline 3\nline 4\n");
+    let left_id = testutils::write_file(store, &path, "line 1\nleft 2\ncommon 3\nline 4\n");
+    let right_id = testutils::write_file(store, &path, "line 1\nright 2\ncommon 3\nline 4\n");
+    let conflict = Merge::new(
+        vec![Some(base_id.clone())],
+        vec![Some(left_id.clone()), Some(right_id.clone())],
+    );
+    insta::assert_snapshot!(
+        materialize_conflict_string_with_style(store, &path, &conflict, ConflictMarkerStyle::Merge),
+        @r###"
+    line 1
+    <<<<<<< Side #1
+    left 2
+    common 3
+    =======
+    right 2
+    common 3
+    >>>>>>> Side #2
+    line 4
+    "###
+    );
+    insta::assert_snapshot!(
+        materialize_conflict_string_with_style(store, &path, &conflict, ConflictMarkerStyle::Diff3),
+        @r###"
+    line 1
+    <<<<<<< Side #1
+    left 2
+    common 3
+    ||||||| Base
+    line 2
+    line 3
+    =======
+    right 2
+    common 3
+    >>>>>>> Side #2
+    line 4
+    "###
+    );
+    // The line that both sides have in common is moved out of the conflict
+    insta::assert_snapshot!(
+        materialize_conflict_string_with_style(store, &path, &conflict, ConflictMarkerStyle::Zdiff3),
+        @r###"
+    line 1
+    <<<<<<< Side #1
+    left 2
+    ||||||| Base
+    line 2
+    line 3
+    =======
+    right 2
+    >>>>>>> Side #2
+    common 3
+    line 4
+    "###
+    );
+
+    // Conflicts with more than two sides use the diff style
+    let right2_id = testutils::write_file(store, &path, "line 1\nright 2.2\nline 3\nline 4\n");
+    let conflict = Merge::new(
+        vec![Some(base_id.clone()), Some(base_id.clone())],
+        vec![
+            Some(left_id.clone()),
+            Some(right_id.clone()),
+            Some(right2_id),
+        ],
+    );
+    insta::assert_snapshot!(
+        materialize_conflict_string_with_style(store, &path, &conflict, ConflictMarkerStyle::Diff3),
+        @"
+    line 1
+    <<<<<<<
+    %%%%%%%
+    -line 2
+    -line 3
+    +left 2
+    +common 3
+    +++++++
+    right 2
+    common 3
+    %%%%%%%
+    -line 2
+    +right 2.2
+     line 3
+    >>>>>>>
+    line 4
+    "
+    );
+}
+
+#[test]
+fn test_materialize_conflict_marker_len() {
+    let test_repo = TestRepo::init();
+    let store = test_repo.repo.store();
+
+    // Lines that look like conflict markers make the markers longer
+    let path = RepoPath::from_internal_string("file");
+    let base_id = testutils::write_file(store, &path, "title\n=======\nline\n");
+    let left_id = testutils::write_file(store, &path, "title\n=======\nleft\n");
+    let right_id = testutils::write_file(store, &path, "title\n-------\nright\n");
+    let conflict = Merge::new(
+        vec![Some(base_id.clone())],
+        vec![Some(left_id.clone()), Some(right_id.clone())],
+    );
+    let materialized = materialize_conflict_string(store, &path, &conflict);
+    insta::assert_snapshot!(materialized, @r###"
+    title
+    <<<<<<<<<
+    %%%%%%%%%
+     =======
+    -line
+    +left
+    +++++++++
+    -------
+    right
+    >>>>>>>>>
+    "###
+    );
+    let result = update_from_content(
+        &conflict,
+        store,
+        &path,
+        materialized.as_bytes(),
+        ConflictMarkerStyle::Diff,
+    )
+    .unwrap();
+    assert_eq!(result, conflict);
+
+    // Editing the conflict with the longer markers works
+    let new_conflict = update_from_content(
+        &conflict,
+        store,
+        &path,
+        materialized.replace("left", "edited").as_bytes(),
+        ConflictMarkerStyle::Diff,
+    )
+    .unwrap();
+    let new_left_id = testutils::write_file(store, &path, "title\n=======\nedited\n");
+    assert_eq!(
+        new_conflict,
+        Merge::new(
+            vec![Some(base_id.clone())],
+            vec![Some(new_left_id.clone()), Some(right_id.clone())],
+        )
+    );
+
+    // Markers of the usual length are now part of the contents
+    let materialized =
+        materialize_conflict_string_with_style(store, &path, &conflict, ConflictMarkerStyle::Diff3);
+    insta::assert_snapshot!(materialized, @r###"
+    title
+    <<<<<<<<< Side #1
+    =======
+    left
+    ||||||||| Base
+    =======
+    line
+    =========
+    -------
+    right
+    >>>>>>>>> Side #2
+    "###
+    );
+    let new_conflict = update_from_content(
+        &conflict,
+        store,
+        &path,
+        materialized.replace("left", "edited").as_bytes(),
+        ConflictMarkerStyle::Diff3,
+    )
+    .unwrap();
+    assert_eq!(
+        new_conflict,
+        Merge::new(
+            vec![Some(base_id.clone())],
+            vec![Some(new_left_id), Some(right_id.clone())],
+        )
+    );
+}
+
+#[test]
+fn test_update_conflict_from_content_git_styles() {
+    let test_repo = TestRepo::init();
+    let store = test_repo.repo.store();
+
+    let path = RepoPath::from_internal_string("dir/file");
+    let base_file_id = testutils::write_file(store, &path, "line 1\nline 2\nline 3\n");
+    let left_file_id = testutils::write_file(store, &path, "left 1\nline 2\nleft 3\n");
+    let right_file_id = testutils::write_file(store, &path, "right 1\nline 2\nright 3\n");
+    let conflict = Merge::new(
+        vec![Some(base_file_id.clone())],
+        vec![Some(left_file_id.clone()), Some(right_file_id.clone())],
+    );
+    let new_base_file_id = testutils::write_file(store, &path, "resolved 1\nline 2\nline 3\n");
+    let new_left_file_id = testutils::write_file(store, &path, "resolved 1\nline 2\nleft 3\n");
+    let new_right_file_id = testutils::write_file(store, &path, "resolved 1\nline 2\nright 3\n");
+    let new_conflict = Merge::new(
+        vec![Some(new_base_file_id)],
+        vec![Some(new_left_file_id), Some(new_right_file_id)],
+    );
+
+    for style in [
+        ConflictMarkerStyle::Merge,
+        ConflictMarkerStyle::Diff3,
+        ConflictMarkerStyle::Zdiff3,
+    ] {
+        // If the content is unchanged compared to the materialized value, we get
+        // the old conflict id back.
+        let materialized = materialize_conflict_string_with_style(store, &path, &conflict, style);
+        let result =
+            update_from_content(&conflict, store, &path, materialized.as_bytes(), style).unwrap();
+        assert_eq!(result, conflict, "{style:?}");
+
+        // If the first conflict is resolved, we get a new conflict back. The
+        // base of the remaining conflict is kept even if it wasn't
+        // materialized.
+        let (_, remaining) = materialized.split_once("line 2\n").unwrap();
+        let edited = format!("resolved 1\nline 2\n{remaining}");
+        let result =
+            update_from_content(&conflict, store, &path, edited.as_bytes(), style).unwrap();
+        assert_eq!(result, new_conflict, "{style:?}");
+    }
+
+    // The remaining conflict in the `merge` style is matched by its sides
+    let result = update_from_content(
+        &conflict,
+        store,
+        &path,
+        b"resolved 1\nline 2\n<<<<<<<\nleft 3\n=======\nright 3\n>>>>>>>\n",
+        ConflictMarkerStyle::Merge,
+    )
+    .unwrap();
+    assert_eq!(result, new_conflict);
+    // Conflicts in any style are recognized
+    let result = update_from_content(
+        &conflict,
+        store,
+        &path,
+        b"resolved 1\nline 2\n<<<<<<< ours\nleft 3\n||||||| base\nline 3\n=======\nright 3\n>>>>>>> theirs\n",
+        ConflictMarkerStyle::Diff,
+    )
+    .unwrap();
+    assert_eq!(result, new_conflict);
+}
+
+#[test]
+fn test_update_conflict_from_content_zdiff3_affixes() {
+    let test_repo = TestRepo::init();
+    let store = test_repo.repo.store();
+
+    let path = RepoPath::from_internal_string("dir/file");
+    let file_id = |content: &str| testutils::write_file(store, &path, content);
+    // The sides share lines at the start and the end of the conflict, which
+    // aren't in the base
+    let conflict = Merge::new(
+        vec![Some(file_id("line 1\nbase\nline 3\n"))],
+        vec![
+            Some(file_id("line 1\ncommon a\nleft\ncommon b\nline 3\n")),
+            Some(file_id("line 1\ncommon a\nright\ncommon b\nline 3\n")),
+        ],
+    );
+    let materialized = materialize_conflict_string_with_style(
+        store,
+        &path,
+        &conflict,
+        ConflictMarkerStyle::Zdiff3,
+    );
+    insta::assert_snapshot!(materialized, @r###"
+    line 1
+    common a
+    <<<<<<< Side #1
+    left
+    ||||||| Base
+    base
+    =======
+    right
+    >>>>>>> Side #2
+    common b
+    line 3
+    "###);
+
+    // Editing the file outside the conflict keeps the base intact
+    let edited = materialized.replace("line 1\n", "edited 1\n");
+    let result = update_from_content(
+        &conflict,
+        store,
+        &path,
+        edited.as_bytes(),
+        ConflictMarkerStyle::Zdiff3,
+    )
+    .unwrap();
+    assert_eq!(
+        result,
+        Merge::new(
+            vec![Some(file_id("edited 1\nbase\nline 3\n"))],
+            vec![
+                Some(file_id("edited 1\ncommon a\nleft\ncommon b\nline 3\n")),
+                Some(file_id("edited 1\ncommon a\nright\ncommon b\nline 3\n")),
+            ],
+        )
+    );
+
+    // So does editing a side of the conflict
+    let edited = materialized.replace("left\n", "LEFT\n");
+    let result = update_from_content(
+        &conflict,
+        store,
+        &path,
+        edited.as_bytes(),
+        ConflictMarkerStyle::Zdiff3,
+    )
+    .unwrap();
+    assert_eq!(
+        result,
+        Merge::new(
+            vec![Some(file_id("line 1\nbase\nline 3\n"))],
+            vec![
+                Some(file_id("line 1\ncommon a\nLEFT\ncommon b\nline 3\n")),
+                Some(file_id("line 1\ncommon a\nright\ncommon b\nline 3\n")),
+            ],
+        )
+    );
+}
+
 fn materialize_conflict_string(
     store: &Store,
     path: &RepoPath,
     conflict: &Merge<Option<FileId>>,
+) -> String {
+    materialize_conflict_string_with_style(store, path, conflict, ConflictMarkerStyle::Diff)
+}
+
+fn materialize_conflict_string_with_style(
+    store: &Store,
+    path: &RepoPath,
+    conflict: &Merge<Option<FileId>>,
+    conflict_marker_style: ConflictMarkerStyle,
 ) -> String {
     let mut result: Vec<u8> = vec![];
     let contents = extract_as_single_hunk(conflict, store, path);
-    materialize_merge_result(&contents, &mut result).unwrap();
+    materialize_merge_result(&contents, conflict_marker_style, &mut result).unwrap();
     String::from_utf8(result).unwrap()
 }
@@ -0,0 +1,148 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jj_lib::copies::{diff_with_copies, CopyOperation};
+use jj_lib::matchers::{EverythingMatcher, FilesMatcher, Matcher};
+use jj_lib::merged_tree::MergedTree;
+use jj_lib::repo_path::RepoPath;
+use testutils::{create_tree, TestRepo};
+
+fn summarize(
+    tree1: &MergedTree,
+    tree2: &MergedTree,
+    matcher: &dyn Matcher,
+) -> Vec<(String, String, Option<CopyOperation>)> {
+    diff_with_copies(tree1, tree2, matcher)
+        .unwrap()
+        .into_iter()
+        .map(|entry| {
+            (
+                entry.source.to_internal_file_string(),
+                entry.target.to_internal_file_string(),
+                entry.copy_operation,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_diff_with_copies_rename() {
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+
+    let exact_before = RepoPath::from_internal_string("exact");
+    let exact_after = RepoPath::from_internal_string("dir/exact");
+    let similar_before = RepoPath::from_internal_string("similar");
+    let similar_after = RepoPath::from_internal_string("similar2");
+    let removed = RepoPath::from_internal_string("removed");
+    let added = RepoPath::from_internal_string("added");
+    let tree1 = create_tree(
+        repo,
+        &[
+            (&exact_before, "a\nb\nc\n"),
+            (&similar_before, "1\n2\n3\n4\n"),
+            (&removed, "removed\n"),
+        ],
+    );
+    let tree2 = create_tree(
+        repo,
+        &[
+            (&exact_after, "a\nb\nc\n"),
+            (&similar_after, "1\n2\n3\nfour\n"),
+            (&added, "added\n"),
+        ],
+    );
+
+    assert_eq!(
+        summarize(&tree1, &tree2, &EverythingMatcher),
+        vec![
+            ("added".to_owned(), "added".to_owned(), None),
+            (
+                "exact".to_owned(),
+                "dir/exact".to_owned(),
+                Some(CopyOperation::Rename)
+            ),
+            ("removed".to_owned(), "removed".to_owned(), None),
+            (
+                "similar".to_owned(),
+                "similar2".to_owned(),
+                Some(CopyOperation::Rename)
+            ),
+        ]
+    );
+
+    // The source of the rename doesn't have to match
+    let matcher = FilesMatcher::new(&[similar_after]);
+    assert_eq!(
+        summarize(&tree1, &tree2, &matcher),
+        vec![(
+            "similar".to_owned(),
+            "similar2".to_owned(),
+            Some(CopyOperation::Rename)
+        )]
+    );
+}
+
+#[test]
+fn test_diff_with_copies_copy() {
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+
+    let source = RepoPath::from_internal_string("source");
+    let target1 = RepoPath::from_internal_string("target1");
+    let target2 = RepoPath::from_internal_string("target2");
+    let modified = RepoPath::from_internal_string("modified");
+    let unrelated = RepoPath::from_internal_string("unrelated");
+    let empty_before = RepoPath::from_internal_string("empty1");
+    let empty_after = RepoPath::from_internal_string("empty2");
+    let tree1 = create_tree(
+        repo,
+        &[
+            (&source, "source\n"),
+            (&modified, "modified\n"),
+            (&empty_before, ""),
+        ],
+    );
+    let tree2 = create_tree(
+        repo,
+        &[
+            (&target1, "source\n"),
+            (&target2, "source\n"),
+            (&modified, "modified\nmore\n"),
+            (&unrelated, "modified\n"),
+            (&empty_after, ""),
+        ],
+    );
+
+    // Modified files and empty files aren't considered as sources
+    assert_eq!(
+        summarize(&tree1, &tree2, &EverythingMatcher),
+        vec![
+            ("empty1".to_owned(), "empty1".to_owned(), None),
+            ("empty2".to_owned(), "empty2".to_owned(), None),
+            ("modified".to_owned(), "modified".to_owned(), None),
+            (
+                "source".to_owned(),
+                "target1".to_owned(),
+                Some(CopyOperation::Rename)
+            ),
+            (
+                "source".to_owned(),
+                "target2".to_owned(),
+                Some(CopyOperation::Copy)
+            ),
+            ("unrelated".to_owned(), "unrelated".to_owned(), None),
+        ]
+    );
+}
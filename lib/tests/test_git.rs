@@ -32,10 +32,12 @@ use jj_lib::git::{
 };
 use jj_lib::git_backend::GitBackend;
 use jj_lib::op_store::{BranchTarget, RefTarget};
+use jj_lib::protos::op_store::View;
 use jj_lib::repo::{MutableRepo, ReadonlyRepo, Repo};
 use jj_lib::settings::{GitSettings, UserSettings};
 use jj_lib::view::RefName;
-use maplit::{btreemap, hashset};
+use maplit::{btreemap, btreeset, hashset};
+use prost::Message as _;
 use tempfile::TempDir;
 use testutils::{
     commit_transactions, create_random_commit, load_repo_at_head, write_random_commit, TestRepo,
@@ -123,11 +125,13 @@ fn test_import_refs() {
         remote_targets: btreemap! {
           "origin".to_string() => RefTarget::normal(jj_id(&commit1)),
         },
+        untracked_remotes: Default::default(),
     };
     assert_eq!(view.get_branch("main"), Some(expected_main_branch).as_ref());
     let expected_feature1_branch = BranchTarget {
         local_target: RefTarget::normal(jj_id(&commit3)),
         remote_targets: btreemap! {},
+        untracked_remotes: Default::default(),
     };
     assert_eq!(
         view.get_branch("feature1"),
@@ -136,6 +140,7 @@ fn test_import_refs() {
     let expected_feature2_branch = BranchTarget {
         local_target: RefTarget::normal(jj_id(&commit4)),
         remote_targets: btreemap! {},
+        untracked_remotes: Default::default(),
     };
     assert_eq!(
         view.get_branch("feature2"),
@@ -146,6 +151,7 @@ fn test_import_refs() {
         remote_targets: btreemap! {
           "origin".to_string() => RefTarget::normal(jj_id(&commit6)),
         },
+        untracked_remotes: Default::default(),
     };
     assert_eq!(
         view.get_branch("feature3"),
@@ -247,6 +253,7 @@ fn test_import_refs_reimport() {
         remote_targets: btreemap! {
           "origin".to_string() => commit1_target.clone(),
         },
+        untracked_remotes: Default::default(),
     };
     assert_eq!(view.get_branch("main"), Some(expected_main_branch).as_ref());
     let expected_feature2_branch = BranchTarget {
@@ -255,6 +262,7 @@ fn test_import_refs_reimport() {
             [commit6.id().clone(), jj_id(&commit5)],
         ),
         remote_targets: btreemap! {},
+        untracked_remotes: Default::default(),
     };
     assert_eq!(
         view.get_branch("feature2"),
@@ -445,6 +453,7 @@ fn test_import_refs_reimport_with_deleted_remote_ref() {
             remote_targets: btreemap! {
                 "origin".to_string() => RefTarget::normal(jj_id(&commit_remote_only)),
             },
+            untracked_remotes: Default::default(),
         }),
     );
     assert_eq!(
@@ -454,6 +463,7 @@ fn test_import_refs_reimport_with_deleted_remote_ref() {
             remote_targets: btreemap! {
                 "origin".to_string() => RefTarget::normal(jj_id(&commit_remote_and_local)),
             },
+            untracked_remotes: Default::default(),
         }),
     );
     view.get_branch("main").unwrap(); // branch #3 of 3
@@ -534,6 +544,7 @@ fn test_import_refs_reimport_with_moved_remote_ref() {
             remote_targets: btreemap! {
                 "origin".to_string() => RefTarget::normal(jj_id(&commit_remote_only)),
             },
+            untracked_remotes: Default::default(),
         }),
     );
     assert_eq!(
@@ -543,6 +554,7 @@ fn test_import_refs_reimport_with_moved_remote_ref() {
             remote_targets: btreemap! {
                 "origin".to_string() => RefTarget::normal(jj_id(&commit_remote_and_local)),
             },
+            untracked_remotes: Default::default(),
         }),
     );
     view.get_branch("main").unwrap(); // branch #3 of 3
@@ -578,6 +590,7 @@ fn test_import_refs_reimport_with_moved_remote_ref() {
             remote_targets: btreemap! {
                 "origin".to_string() => RefTarget::normal(jj_id(&new_commit_remote_only)),
             },
+            untracked_remotes: Default::default(),
         }),
     );
     assert_eq!(
@@ -587,6 +600,7 @@ fn test_import_refs_reimport_with_moved_remote_ref() {
             remote_targets: btreemap! {
                 "origin".to_string() => RefTarget::normal(jj_id(&new_commit_remote_and_local)),
             },
+            untracked_remotes: Default::default(),
         }),
     );
     view.get_branch("main").unwrap(); // branch #3 of 3
@@ -779,6 +793,7 @@ fn test_import_some_refs() {
         remote_targets: btreemap! {
             "origin".to_string() => commit_feat1_target,
         },
+        untracked_remotes: Default::default(),
     };
     assert_eq!(
         view.get_branch("feature1"),
@@ -789,6 +804,7 @@ fn test_import_some_refs() {
         remote_targets: btreemap! {
             "origin".to_string() => commit_feat2_target,
         },
+        untracked_remotes: Default::default(),
     };
     assert_eq!(
         view.get_branch("feature2"),
@@ -799,6 +815,7 @@ fn test_import_some_refs() {
         remote_targets: btreemap! {
             "origin".to_string() => commit_feat3_target,
         },
+        untracked_remotes: Default::default(),
     };
     assert_eq!(
         view.get_branch("feature3"),
@@ -809,6 +826,7 @@ fn test_import_some_refs() {
         remote_targets: btreemap! {
             "origin".to_string() => commit_feat4_target,
         },
+        untracked_remotes: Default::default(),
     };
     assert_eq!(
         view.get_branch("feature4"),
@@ -1276,6 +1294,7 @@ fn test_import_export_no_auto_local_branch() {
         remote_targets: btreemap! {
             "origin".to_string() => RefTarget::normal(jj_id(&git_commit)),
         },
+        untracked_remotes: btreeset! {"origin".to_string()},
     };
     assert_eq!(
         mut_repo.view().get_branch("main"),
@@ -1291,6 +1310,97 @@ fn test_import_export_no_auto_local_branch() {
     assert_eq!(mut_repo.get_git_ref("refs/heads/main"), RefTarget::absent());
 }
 
+#[test]
+fn test_import_refs_legacy_view_no_auto_local_branch() {
+    // Remote branches in a view written before branches could be untracked
+    // don't start to be merged into local branches
+    let test_data = GitRepoData::create();
+    let git_settings = GitSettings {
+        auto_local_branch: false,
+    };
+    let git_repo = test_data.git_repo;
+    let commit1 = empty_git_commit(&git_repo, "refs/remotes/origin/main", &[]);
+    let mut tx = test_data
+        .repo
+        .start_transaction(&test_data.settings, "test");
+    git::import_refs(tx.mut_repo(), &git_repo, &git_settings).unwrap();
+    let repo = tx.commit();
+
+    // Remove the tracking state from all views to simulate an older version
+    let views_dir = repo.repo_path().join("op_store").join("views");
+    for entry in fs::read_dir(views_dir).unwrap() {
+        let path = entry.unwrap().path();
+        let mut proto = View::decode(fs::read(&path).unwrap().as_slice()).unwrap();
+        for branch in &mut proto.branches {
+            for remote_branch in &mut branch.remote_branches {
+                remote_branch.untracked = None;
+            }
+        }
+        fs::write(&path, proto.encode_to_vec()).unwrap();
+    }
+
+    let repo = load_repo_at_head(&test_data.settings, repo.repo_path());
+    assert!(!repo.view().is_remote_branch_tracked("main", "origin"));
+    let commit2 = empty_git_commit(&git_repo, "refs/remotes/origin/main", &[&commit1]);
+    let mut tx = repo.start_transaction(&test_data.settings, "test");
+    let mut_repo = tx.mut_repo();
+    git::import_refs(mut_repo, &git_repo, &git_settings).unwrap();
+    assert_eq!(mut_repo.get_local_branch("main"), RefTarget::absent());
+    assert_eq!(
+        mut_repo.get_remote_branch("main", "origin"),
+        RefTarget::normal(jj_id(&commit2))
+    );
+}
+
+#[test]
+fn test_import_refs_untracked_remote_branch() {
+    // Changes to an untracked remote branch aren't merged into the local branch
+    let test_data = GitRepoData::create();
+    let git_settings = GitSettings::default();
+    let git_repo = test_data.git_repo;
+    let commit1 = empty_git_commit(&git_repo, "refs/remotes/origin/main", &[]);
+
+    let mut tx = test_data
+        .repo
+        .start_transaction(&test_data.settings, "test");
+    let mut_repo = tx.mut_repo();
+    git::import_refs(mut_repo, &git_repo, &git_settings).unwrap();
+    assert!(mut_repo.is_remote_branch_tracked("main", "origin"));
+    assert_eq!(
+        mut_repo.get_local_branch("main"),
+        RefTarget::normal(jj_id(&commit1))
+    );
+
+    mut_repo.set_remote_branch_tracked("main", "origin", false);
+    let commit2 = empty_git_commit(&git_repo, "refs/remotes/origin/main", &[&commit1]);
+    git::import_refs(mut_repo, &git_repo, &git_settings).unwrap();
+    assert!(!mut_repo.is_remote_branch_tracked("main", "origin"));
+    assert_eq!(
+        mut_repo.get_local_branch("main"),
+        RefTarget::normal(jj_id(&commit1))
+    );
+    assert_eq!(
+        mut_repo.get_remote_branch("main", "origin"),
+        RefTarget::normal(jj_id(&commit2))
+    );
+
+    // Deleting the untracked remote branch doesn't delete the local branch
+    git_repo
+        .find_reference("refs/remotes/origin/main")
+        .unwrap()
+        .delete()
+        .unwrap();
+    git::import_refs(mut_repo, &git_repo, &git_settings).unwrap();
+    assert_eq!(
+        mut_repo.get_local_branch("main"),
+        RefTarget::normal(jj_id(&commit1))
+    );
+    assert_eq!(
+        mut_repo.get_remote_branch("main", "origin"),
+        RefTarget::absent()
+    );
+}
+
 #[test]
 fn test_export_conflicts() {
     // We skip export of conflicted branches
@@ -1358,6 +1468,116 @@ fn test_export_branch_on_root_commit() {
     );
 }
 
+#[test]
+fn test_export_tags() {
+    // Tags are exported like local branches
+    let test_data = GitRepoData::create();
+    let git_repo = test_data.git_repo;
+    let mut tx = test_data
+        .repo
+        .start_transaction(&test_data.settings, "test");
+    let mut_repo = tx.mut_repo();
+    let commit_a = write_random_commit(mut_repo, &test_data.settings);
+    let commit_b = write_random_commit(mut_repo, &test_data.settings);
+    mut_repo.set_tag_target("v1", RefTarget::normal(commit_a.id().clone()));
+    assert_eq!(git::export_refs(mut_repo, &git_repo), Ok(vec![]));
+    assert_eq!(
+        mut_repo.get_git_ref("refs/tags/v1"),
+        RefTarget::normal(commit_a.id().clone())
+    );
+    assert_eq!(
+        git_repo.find_reference("refs/tags/v1").unwrap().target(),
+        Some(git_id(&commit_a))
+    );
+
+    mut_repo.set_tag_target("v1", RefTarget::normal(commit_b.id().clone()));
+    assert_eq!(git::export_refs(mut_repo, &git_repo), Ok(vec![]));
+    assert_eq!(
+        git_repo.find_reference("refs/tags/v1").unwrap().target(),
+        Some(git_id(&commit_b))
+    );
+
+    mut_repo.set_tag_target("v1", RefTarget::absent());
+    assert_eq!(git::export_refs(mut_repo, &git_repo), Ok(vec![]));
+    assert_eq!(mut_repo.get_git_ref("refs/tags/v1"), RefTarget::absent());
+    assert!(git_repo.find_reference("refs/tags/v1").is_err());
+}
+
+#[test]
+fn test_export_annotated_tag() {
+    // An annotated tag can be moved and deleted even though its Git ref points
+    // to the tag object rather than the commit
+    let test_data = GitRepoData::create();
+    let git_repo = test_data.git_repo;
+    let mut tx = test_data
+        .repo
+        .start_transaction(&test_data.settings, "test");
+    let mut_repo = tx.mut_repo();
+    let commit_a = write_random_commit(mut_repo, &test_data.settings);
+    let commit_b = write_random_commit(mut_repo, &test_data.settings);
+    let tagger = test_data.settings.signature();
+    let get_git_tag = |name: &str| {
+        git_repo
+            .find_reference(name)
+            .unwrap()
+            .peel_to_tag()
+            .unwrap()
+    };
+    git::set_annotated_tag(
+        mut_repo,
+        &git_repo,
+        "v1",
+        commit_a.id(),
+        "Release 1",
+        &tagger,
+    )
+    .unwrap();
+    assert_eq!(
+        mut_repo.get_tag("v1"),
+        RefTarget::normal(commit_a.id().clone())
+    );
+    // The tag ref isn't written until it's exported
+    assert!(git_repo.find_reference("refs/tags/v1").is_err());
+    assert!(git::is_annotated_tag(&git_repo, "v1"));
+    assert_eq!(git::export_refs(mut_repo, &git_repo), Ok(vec![]));
+    let git_tag = get_git_tag("refs/tags/v1");
+    assert_eq!(git_tag.message(), Some("Release 1"));
+    assert_eq!(git_tag.target_id(), git_id(&commit_a));
+    assert!(git_repo.find_reference("refs/jj/tags/v1").is_err());
+    assert_eq!(
+        mut_repo.get_git_ref("refs/tags/v1"),
+        RefTarget::normal(commit_a.id().clone())
+    );
+    // Nothing left to export, and importing doesn't change anything
+    assert_eq!(git::export_refs(mut_repo, &git_repo), Ok(vec![]));
+    git::import_refs(mut_repo, &git_repo, &GitSettings::default()).unwrap();
+    assert_eq!(
+        mut_repo.get_tag("v1"),
+        RefTarget::normal(commit_a.id().clone())
+    );
+
+    // Changing the message without moving the tag
+    git::set_annotated_tag(mut_repo, &git_repo, "v1", commit_a.id(), "Fixed", &tagger).unwrap();
+    assert_eq!(get_git_tag("refs/tags/v1").message(), Some("Release 1"));
+    assert_eq!(git::export_refs(mut_repo, &git_repo), Ok(vec![]));
+    assert_eq!(get_git_tag("refs/tags/v1").message(), Some("Fixed"));
+
+    mut_repo.set_tag_target("v1", RefTarget::normal(commit_b.id().clone()));
+    assert_eq!(git::export_refs(mut_repo, &git_repo), Ok(vec![]));
+    assert_eq!(
+        git_repo.find_reference("refs/tags/v1").unwrap().target(),
+        Some(git_id(&commit_b))
+    );
+    assert!(!git::is_annotated_tag(&git_repo, "v1"));
+
+    // The tag object is forgotten if the tag doesn't point to its target anymore
+    git::set_annotated_tag(mut_repo, &git_repo, "v1", commit_a.id(), "Again", &tagger).unwrap();
+    mut_repo.set_tag_target("v1", RefTarget::absent());
+    assert_eq!(git::export_refs(mut_repo, &git_repo), Ok(vec![]));
+    assert!(git_repo.find_reference("refs/tags/v1").is_err());
+    assert!(git_repo.find_reference("refs/jj/tags/v1").is_err());
+}
+
 #[test]
 fn test_export_partial_failure() {
     // Check that we skip branches that fail to export
@@ -1653,6 +1873,7 @@ fn test_fetch_initial_commit() {
                 remote_targets: btreemap! {
                     "origin".to_string() => initial_commit_target,
                 },
+                untracked_remotes: Default::default(),
             },
         }
     );
@@ -1718,6 +1939,7 @@ fn test_fetch_success() {
                 remote_targets: btreemap! {
                     "origin".to_string() => new_commit_target,
                 },
+                untracked_remotes: Default::default(),
             },
         }
     );
@@ -1945,6 +2167,42 @@ fn test_push_updates_deletion() {
         .is_err());
 }
 
+#[test]
+fn test_push_tags() {
+    let settings = testutils::user_settings();
+    let temp_dir = testutils::new_temp_dir();
+    let setup = set_up_push_repos(&settings, &temp_dir);
+    let clone_repo = get_git_repo(&setup.jj_repo);
+    let mut tx = setup.jj_repo.start_transaction(&settings, "test");
+    git::set_annotated_tag(
+        tx.mut_repo(),
+        &clone_repo,
+        "v1",
+        setup.new_commit.id(),
+        "Release 1",
+        &settings.signature(),
+    )
+    .unwrap();
+    assert_eq!(git::export_refs(tx.mut_repo(), &clone_repo), Ok(vec![]));
+    let result = git::push_tags(
+        &clone_repo,
+        "origin",
+        &["v1"],
+        git::RemoteCallbacks::default(),
+    );
+    assert_eq!(result, Ok(()));
+
+    // The tag object is pushed along with the ref
+    let source_repo = git2::Repository::open(&setup.source_repo_dir).unwrap();
+    let git_tag = source_repo
+        .find_reference("refs/tags/v1")
+        .unwrap()
+        .peel_to_tag()
+        .unwrap();
+    assert_eq!(git_tag.message(), Some("Release 1"));
+    assert_eq!(git_tag.target_id(), git_id(&setup.new_commit));
+}
+
 #[test]
 fn test_push_updates_mixed_deletion_and_addition() {
     let settings = testutils::user_settings();
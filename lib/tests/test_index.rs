@@ -15,18 +15,20 @@
 use std::sync::Arc;
 
 use jj_lib::backend::CommitId;
+use jj_lib::changed_path_filter::ChangedPathKey;
 use jj_lib::commit::Commit;
 use jj_lib::commit_builder::CommitBuilder;
 use jj_lib::default_index_store::{
-    CompositeIndex, IndexPosition, MutableIndexImpl, ReadonlyIndexWrapper,
+    CompositeIndex, DefaultIndexStore, IndexPosition, MutableIndexImpl, ReadonlyIndexWrapper,
 };
 use jj_lib::index::Index as _;
 use jj_lib::repo::{MutableRepo, ReadonlyRepo, Repo};
+use jj_lib::repo_path::RepoPath;
 use jj_lib::settings::UserSettings;
 use test_case::test_case;
 use testutils::{
-    create_random_commit, load_repo_at_head, write_random_commit, CommitGraphBuilder, TestRepo,
-    TestRepoBackend,
+    create_random_commit, create_tree, load_repo_at_head, write_random_commit, CommitGraphBuilder,
+    TestRepo, TestRepoBackend,
 };
 
 fn child_commit<'repo>(
@@ -572,3 +574,113 @@ fn test_index_store_type(backend: TestRepoBackend) {
     );
     assert_eq!(as_readonly_composite(&repo).num_commits(), 1);
 }
+
+/// Test that index files written in an older format are rebuilt instead of
+/// being reported as corrupt.
+#[test_case(TestRepoBackend::Local ; "local backend")]
+#[test_case(TestRepoBackend::Git ; "git backend")]
+fn test_index_old_format_version(backend: TestRepoBackend) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init_with_backend(backend);
+    let repo = create_n_commits(&settings, &test_repo.repo, 3);
+    let repo = create_n_commits(&settings, &repo, 2);
+    assert_eq!(as_readonly_composite(&repo).num_commits(), 6);
+
+    // Strip the version header from all segment files to simulate an index
+    // written before the format was versioned.
+    let index_dir = repo.repo_path().join("index");
+    for entry in std::fs::read_dir(&index_dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_file() && path.file_name().unwrap() != "type" {
+            let data = std::fs::read(&path).unwrap();
+            std::fs::write(&path, &data[4..]).unwrap();
+        }
+    }
+
+    let repo = load_repo_at_head(&settings, repo.repo_path());
+    assert_eq!(as_readonly_composite(&repo).num_commits(), 6);
+    let repo = create_n_commits(&settings, &repo, 1);
+    assert_eq!(as_readonly_composite(&repo).num_commits(), 7);
+}
+
+#[test_case(TestRepoBackend::Local ; "local backend")]
+#[test_case(TestRepoBackend::Git ; "git backend")]
+fn test_index_changed_path_filters(backend: TestRepoBackend) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init_with_backend(backend);
+    let repo = &test_repo.repo;
+
+    // Filters aren't built by default
+    assert_eq!(
+        as_readonly_composite(repo).stats().num_changed_path_filters,
+        0
+    );
+    let index_store: &DefaultIndexStore = repo.index_store().as_any().downcast_ref().unwrap();
+    assert!(!index_store.changed_paths_enabled());
+    index_store.set_changed_paths_enabled(true).unwrap();
+    index_store.reinit();
+    let repo = repo.reload_at(repo.operation()).unwrap();
+    assert_eq!(
+        as_readonly_composite(&repo)
+            .stats()
+            .num_changed_path_filters,
+        1
+    );
+
+    let dir_file_path = RepoPath::from_internal_string("dir/file");
+    let other_file_path = RepoPath::from_internal_string("other");
+    let tree1 = create_tree(&repo, &[(&dir_file_path, "1")]);
+    let tree2 = create_tree(&repo, &[(&dir_file_path, "1"), (&other_file_path, "2")]);
+    let mut tx = repo.start_transaction(&settings, "test");
+    let commit1 = create_random_commit(tx.mut_repo(), &settings)
+        .set_tree_id(tree1.id())
+        .write()
+        .unwrap();
+    let commit2 = child_commit(tx.mut_repo(), &settings, &commit1)
+        .set_tree_id(tree2.id())
+        .write()
+        .unwrap();
+    let commit3 = child_commit(tx.mut_repo(), &settings, &commit2)
+        .set_tree_id(tree2.id())
+        .write()
+        .unwrap();
+    let repo = tx.commit();
+
+    // New commits get filters in the incremental index file
+    let index = as_readonly_composite(&repo);
+    assert_eq!(index.stats().num_changed_path_filters, 4);
+    let might_contain = |commit: &Commit, path: &str| {
+        let entry = index.entry_by_id(commit.id()).unwrap();
+        let key = ChangedPathKey::new(&RepoPath::from_internal_string(path));
+        entry.changed_path_filter().unwrap().might_contain(&key)
+    };
+    assert!(might_contain(&commit1, "dir/file"));
+    assert!(might_contain(&commit1, "dir"));
+    assert!(!might_contain(&commit1, "other"));
+    assert!(might_contain(&commit2, "other"));
+    assert!(!might_contain(&commit2, "dir"));
+    assert!(!might_contain(&commit3, "dir"));
+    assert!(!might_contain(&commit3, "other"));
+
+    // Filters are preserved when index files are squashed
+    let repo = create_n_commits(&settings, &repo, 10);
+    assert_eq!(commits_by_level(&repo), vec![14]);
+    assert_eq!(
+        as_readonly_composite(&repo)
+            .stats()
+            .num_changed_path_filters,
+        14
+    );
+
+    // Disabling the filters takes effect on reindexing
+    let index_store: &DefaultIndexStore = repo.index_store().as_any().downcast_ref().unwrap();
+    index_store.set_changed_paths_enabled(false).unwrap();
+    index_store.reinit();
+    let repo = repo.reload_at(repo.operation()).unwrap();
+    assert_eq!(
+        as_readonly_composite(&repo)
+            .stats()
+            .num_changed_path_filters,
+        0
+    );
+}
@@ -1066,6 +1066,75 @@ fn test_merge_simple() {
     assert_eq!(merged, expected_merged);
 }
 
+/// Merge 3 resolved trees where a file was renamed on one side and modified on
+/// the other
+#[test]
+fn test_merge_follows_rename() {
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+
+    let old_path = RepoPath::from_internal_string("dir1/file");
+    let new_path = RepoPath::from_internal_string("dir2/file");
+    let other_path = RepoPath::from_internal_string("other");
+    let base = create_single_tree(repo, &[(&old_path, "a\nb\nc\n")]);
+    let side1 = create_single_tree(repo, &[(&new_path, "a\nb\nc\nd\n")]);
+    let side2 = create_single_tree(repo, &[(&old_path, "A\nb\nc\n"), (&other_path, "other\n")]);
+    let expected = create_single_tree(
+        repo,
+        &[(&new_path, "A\nb\nc\nd\n"), (&other_path, "other\n")],
+    );
+    let base_merged = MergedTree::new(Merge::resolved(base));
+    let side1_merged = MergedTree::new(Merge::resolved(side1));
+    let side2_merged = MergedTree::new(Merge::resolved(side2));
+    let expected_merged = MergedTree::new(Merge::resolved(expected));
+
+    let merged = side1_merged
+        .merge_following_renames(&base_merged, &side2_merged)
+        .unwrap();
+    assert_eq!(merged, expected_merged);
+    let merged = side2_merged
+        .merge_following_renames(&base_merged, &side1_merged)
+        .unwrap();
+    assert_eq!(merged, expected_merged);
+
+    // A plain merge doesn't follow the rename
+    let merged = side1_merged.merge(&base_merged, &side2_merged).unwrap();
+    assert!(!merged.path_value(&old_path).is_resolved());
+    assert!(merged.path_value(&new_path).is_present());
+}
+
+/// Merge 3 resolved trees where a file was removed on one side and modified on
+/// the other, while an unrelated file was added on the first side
+#[test]
+fn test_merge_following_renames_unrelated_add() {
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+
+    let removed_path = RepoPath::from_internal_string("removed");
+    let added_path = RepoPath::from_internal_string("added");
+    let base = create_single_tree(repo, &[(&removed_path, "a\nb\nc\n")]);
+    let side1 = create_single_tree(repo, &[(&added_path, "x\ny\nz\n")]);
+    let side2 = create_single_tree(repo, &[(&removed_path, "A\nb\nc\n")]);
+    let base_merged = MergedTree::new(Merge::resolved(base));
+    let side1_merged = MergedTree::new(Merge::resolved(side1));
+    let side2_merged = MergedTree::new(Merge::resolved(side2));
+
+    // The removal and the addition aren't paired as a rename, so the
+    // modification conflicts with the removal
+    let merged = side1_merged
+        .merge_following_renames(&base_merged, &side2_merged)
+        .unwrap();
+    assert_eq!(
+        merged,
+        side1_merged.merge(&base_merged, &side2_merged).unwrap()
+    );
+    assert!(!merged.path_value(&removed_path).is_resolved());
+    assert_eq!(
+        merged.path_value(&added_path),
+        side1_merged.path_value(&added_path)
+    );
+}
+
 /// Merge 3 resolved trees that can be partially resolved
 #[test]
 fn test_merge_partial_resolution() {
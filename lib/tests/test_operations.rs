@@ -13,10 +13,17 @@
 // limitations under the License.
 
 use std::path::Path;
+use std::slice;
+use std::time::{Duration, SystemTime};
 
+use assert_matches::assert_matches;
+use itertools::Itertools as _;
 use jj_lib::backend::{CommitId, ObjectId};
+use jj_lib::git_backend::GitBackend;
+use jj_lib::op_store::OpStoreError;
+use jj_lib::operation::{self, Operation};
 use jj_lib::repo::Repo;
-use testutils::{create_random_commit, write_random_commit, TestRepo};
+use testutils::{create_random_commit, write_random_commit, TestRepo, TestRepoBackend};
 
 fn list_dir(dir: &Path) -> Vec<String> {
     std::fs::read_dir(dir)
@@ -177,3 +184,188 @@ fn test_isolation() {
     let repo = repo.reload_at_head(&settings).unwrap();
     assert_heads(repo.as_ref(), vec![rewrite1.id(), rewrite2.id()]);
 }
+
+#[test]
+fn test_reparent_range() {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init();
+    let repo_0 = &test_repo.repo;
+    let op_store = repo_0.op_store();
+    let read_op = |id| {
+        let data = op_store.read_operation(id).unwrap();
+        Operation::new(op_store.clone(), id.clone(), data)
+    };
+
+    // Linear history: 0 -> A -> B -> C -> D
+    let repo_a = repo_0.start_transaction(&settings, "a").commit();
+    let repo_b = repo_a.start_transaction(&settings, "b").commit();
+    let repo_c = repo_b.start_transaction(&settings, "c").commit();
+    let repo_d = repo_c.start_transaction(&settings, "d").commit();
+    let op_a = repo_a.operation();
+    let op_b = repo_b.operation();
+    let op_c = repo_c.operation();
+    let op_d = repo_d.operation();
+
+    // Abandon B and C
+    let stats = operation::reparent_range(
+        op_store.as_ref(),
+        slice::from_ref(op_c),
+        slice::from_ref(op_d),
+        slice::from_ref(op_a),
+    )
+    .unwrap();
+    assert_eq!(stats.new_head_ids.len(), 1);
+    assert_eq!(stats.rewritten_ids.len(), 1);
+    assert_eq!(stats.unreachable_count, 2);
+    let new_op_d = read_op(&stats.new_head_ids[0]);
+    assert_eq!(stats.rewritten_ids[op_d.id()], *new_op_d.id());
+    assert_eq!(new_op_d.parent_ids(), &[op_a.id().clone()]);
+    assert_eq!(
+        new_op_d.store_operation().view_id,
+        op_d.store_operation().view_id
+    );
+    assert_eq!(
+        new_op_d.store_operation().metadata,
+        op_d.store_operation().metadata
+    );
+
+    // Abandon A and its ancestors
+    let stats = operation::reparent_range(
+        op_store.as_ref(),
+        slice::from_ref(op_a),
+        slice::from_ref(op_d),
+        &[],
+    )
+    .unwrap();
+    assert_eq!(stats.rewritten_ids.len(), 3);
+    assert_eq!(
+        stats.unreachable_count,
+        operation::walk_ancestors(op_a).count()
+    );
+    let new_op_b = read_op(&stats.rewritten_ids[op_b.id()]);
+    let new_op_c = read_op(&stats.rewritten_ids[op_c.id()]);
+    let new_op_d = read_op(&stats.new_head_ids[0]);
+    assert!(new_op_b.parent_ids().is_empty());
+    assert_eq!(new_op_c.parent_ids(), &[new_op_b.id().clone()]);
+    assert_eq!(new_op_d.parent_ids(), &[new_op_c.id().clone()]);
+
+    // Nothing to reparent if the head is in the abandoned range
+    let stats = operation::reparent_range(
+        op_store.as_ref(),
+        slice::from_ref(op_d),
+        slice::from_ref(op_d),
+        &[],
+    )
+    .unwrap();
+    assert!(stats.rewritten_ids.is_empty());
+    assert_eq!(stats.new_head_ids, vec![op_d.id().clone()]);
+}
+
+#[test]
+fn test_gc() {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+    let op_store = repo.op_store();
+
+    let op_id0 = repo.op_id().clone();
+    let mut tx1 = repo.start_transaction(&settings, "transaction 1");
+    write_random_commit(tx1.mut_repo(), &settings);
+    let repo1 = tx1.commit();
+    let op_id1 = repo1.op_id().clone();
+    // Operation which isn't reachable from the op heads
+    let mut tx2 = repo1.start_transaction(&settings, "transaction 2");
+    write_random_commit(tx2.mut_repo(), &settings);
+    let unpublished_op = tx2.write();
+    let op2 = unpublished_op.operation().store_operation().clone();
+    let op_id2 = unpublished_op.operation().id().clone();
+    drop(unpublished_op);
+
+    // Recent objects are kept
+    op_store
+        .gc(slice::from_ref(&op_id1), SystemTime::UNIX_EPOCH)
+        .unwrap();
+    assert!(op_store.read_operation(&op_id2).is_ok());
+    assert!(op_store.read_view(&op2.view_id).is_ok());
+
+    let keep_newer = SystemTime::now() + Duration::from_secs(1);
+    op_store.gc(slice::from_ref(&op_id1), keep_newer).unwrap();
+    assert!(op_store.read_operation(&op_id0).is_ok());
+    assert!(op_store.read_operation(&op_id1).is_ok());
+    assert!(op_store
+        .read_view(&op_store.read_operation(&op_id1).unwrap().view_id)
+        .is_ok());
+    assert_matches!(
+        op_store.read_operation(&op_id2),
+        Err(OpStoreError::NotFound)
+    );
+    assert_matches!(
+        op_store.read_view(&op2.view_id),
+        Err(OpStoreError::NotFound)
+    );
+}
+
+#[test]
+fn test_gc_expired_operations() {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init_with_backend(TestRepoBackend::Git);
+    let repo = &test_repo.repo;
+    let op_store = repo.op_store();
+    let git_backend = repo
+        .store()
+        .backend_impl()
+        .downcast_ref::<GitBackend>()
+        .unwrap();
+    let has_keep_ref = |id: &CommitId| {
+        let git_repo = git_backend.git_repo();
+        let has_ref = git_repo
+            .references_glob("refs/jj/keep/*")
+            .unwrap()
+            .map(|git_ref| git_ref.unwrap().target().unwrap())
+            .any(|oid| oid.as_bytes() == id.as_bytes());
+        has_ref
+    };
+
+    let mut tx1 = repo.start_transaction(&settings, "transaction 1");
+    let commit_a = write_random_commit(tx1.mut_repo(), &settings);
+    let repo1 = tx1.commit();
+    let op_id1 = repo1.op_id().clone();
+    // Commit A is only referenced by the expired operation after this
+    let mut tx2 = repo1.start_transaction(&settings, "transaction 2");
+    tx2.mut_repo()
+        .record_abandoned_commit(commit_a.id().clone());
+    tx2.mut_repo().rebase_descendants(&settings).unwrap();
+    let repo2 = tx2.commit();
+    assert!(has_keep_ref(commit_a.id()));
+
+    // Everything but the head operation is expired
+    let keep_newer = SystemTime::now() + Duration::from_secs(1);
+    let stats =
+        operation::truncate_ancestors(op_store.as_ref(), &[repo2.operation().clone()], keep_newer)
+            .unwrap();
+    let [new_head_id] = stats.new_head_ids.as_slice() else {
+        panic!("unexpected heads: {:?}", stats.new_head_ids);
+    };
+    assert_ne!(new_head_id, repo2.op_id());
+    assert_eq!(stats.unreachable_count, 2);
+    let new_head_op = op_store.read_operation(new_head_id).unwrap();
+    assert!(new_head_op.parents.is_empty());
+    assert_eq!(new_head_op.view_id, *repo2.operation().view().unwrap().id());
+
+    op_store.gc(&stats.new_head_ids, keep_newer).unwrap();
+    assert_matches!(
+        op_store.read_operation(&op_id1),
+        Err(OpStoreError::NotFound)
+    );
+    assert_matches!(
+        op_store.read_operation(repo2.op_id()),
+        Err(OpStoreError::NotFound)
+    );
+    assert!(op_store.read_view(&new_head_op.view_id).is_ok());
+
+    // Only the commits in the remaining view are protected
+    let view = op_store.read_view(&new_head_op.view_id).unwrap();
+    let heads = view.head_ids.into_iter().collect_vec();
+    repo.store().gc(&heads, keep_newer).unwrap();
+    assert!(!has_keep_ref(commit_a.id()));
+}
@@ -23,6 +23,8 @@ use assert_matches::assert_matches;
 use itertools::Itertools;
 use jj_lib::backend::{ChangeId, CommitId, MillisSinceEpoch, ObjectId, Signature, Timestamp};
 use jj_lib::commit::Commit;
+use jj_lib::default_index_store::DefaultIndexStore;
+use jj_lib::fileset::FilesetExpression;
 use jj_lib::git;
 use jj_lib::git_backend::GitBackend;
 use jj_lib::index::{HexPrefix, PrefixResolution};
@@ -31,11 +33,12 @@ use jj_lib::repo::Repo;
 use jj_lib::repo_path::RepoPath;
 use jj_lib::revset::{
     optimize, parse, DefaultSymbolResolver, ResolvedExpression, Revset, RevsetAliasesMap,
-    RevsetExpression, RevsetFilterPredicate, RevsetParseContext, RevsetResolutionError,
-    RevsetWorkspaceContext,
+    RevsetEvaluationError, RevsetExpression, RevsetFilterPredicate, RevsetParseContext,
+    RevsetResolutionError, RevsetWorkspaceContext,
 };
 use jj_lib::revset_graph::{ReverseRevsetGraphIterator, RevsetGraphEdge};
 use jj_lib::settings::GitSettings;
+use jj_lib::time_util::DatePatternContext;
 use jj_lib::workspace::Workspace;
 use test_case::test_case;
 use testutils::{
@@ -47,6 +50,7 @@ fn resolve_symbol(repo: &dyn Repo, symbol: &str) -> Result<Vec<CommitId>, Revset
     let context = RevsetParseContext {
         aliases_map: &RevsetAliasesMap::new(),
         user_email: String::new(),
+        date_pattern_context: DatePatternContext::from_current_time(),
         workspace: None,
     };
     let expression = parse(symbol, &context).unwrap();
@@ -175,6 +179,7 @@ fn test_resolve_symbol_commit_id() {
     let context = RevsetParseContext {
         aliases_map: &RevsetAliasesMap::new(),
         user_email: settings.user_email(),
+        date_pattern_context: DatePatternContext::from_current_time(),
         workspace: None,
     };
     assert_matches!(
@@ -781,6 +786,7 @@ fn resolve_commit_ids(repo: &dyn Repo, revset_str: &str) -> Vec<CommitId> {
     let context = RevsetParseContext {
         aliases_map: &RevsetAliasesMap::new(),
         user_email: settings.user_email(),
+        date_pattern_context: DatePatternContext::from_current_time(),
         workspace: None,
     };
     let expression = optimize(parse(revset_str, &context).unwrap());
@@ -802,10 +808,12 @@ fn resolve_commit_ids_in_workspace(
         cwd: cwd.unwrap_or_else(|| workspace.workspace_root()),
         workspace_id: workspace.workspace_id(),
         workspace_root: workspace.workspace_root(),
+        allow_filesets: true,
     };
     let context = RevsetParseContext {
         aliases_map: &RevsetAliasesMap::new(),
         user_email: settings.user_email(),
+        date_pattern_context: DatePatternContext::from_current_time(),
         workspace: Some(workspace_ctx),
     };
     let expression = optimize(parse(revset_str, &context).unwrap());
@@ -1773,6 +1781,18 @@ fn test_evaluate_expression_branches(backend: TestRepoBackend) {
         resolve_commit_ids(mut_repo, "branches(exact:branch1)"),
         vec![commit1.id().clone()]
     );
+    assert_eq!(
+        resolve_commit_ids(mut_repo, r#"branches(glob:"Branch?")"#),
+        vec![]
+    );
+    assert_eq!(
+        resolve_commit_ids(mut_repo, r#"branches(glob:"bran*1")"#),
+        vec![commit1.id().clone()]
+    );
+    assert_eq!(
+        resolve_commit_ids(mut_repo, r#"branches(regex:"[^1]$")"#),
+        vec![commit2.id().clone()]
+    );
     // Can silently resolve to an empty set if there's no matches
     assert_eq!(resolve_commit_ids(mut_repo, "branches(branch3)"), vec![]);
     assert_eq!(
@@ -1941,6 +1961,184 @@ fn test_evaluate_expression_remote_branches(backend: TestRepoBackend) {
     );
 }
 
+#[test_case(TestRepoBackend::Local ; "local backend")]
+#[test_case(TestRepoBackend::Git ; "git backend")]
+fn test_evaluate_expression_untracked_remote_branches(backend: TestRepoBackend) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init_with_backend(backend);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+
+    let commit1 = write_random_commit(mut_repo, &settings);
+    let commit2 = write_random_commit(mut_repo, &settings);
+    let commit3 = write_random_commit(mut_repo, &settings);
+
+    mut_repo.set_remote_branch_target("branch1", "origin", RefTarget::normal(commit1.id().clone()));
+    mut_repo.set_remote_branch_target("branch2", "origin", RefTarget::normal(commit2.id().clone()));
+    mut_repo.set_remote_branch_target(
+        "branch2",
+        "private",
+        RefTarget::normal(commit3.id().clone()),
+    );
+    assert_eq!(
+        resolve_commit_ids(mut_repo, "untracked_remote_branches()"),
+        vec![
+            commit3.id().clone(),
+            commit2.id().clone(),
+            commit1.id().clone()
+        ]
+    );
+    // Remote branches with a local branch of the same name are tracked
+    mut_repo.set_local_branch_target("branch2", RefTarget::normal(commit1.id().clone()));
+    assert_eq!(
+        resolve_commit_ids(mut_repo, "untracked_remote_branches()"),
+        vec![commit1.id().clone()]
+    );
+    assert_eq!(
+        resolve_commit_ids(mut_repo, "untracked_remote_branches(branch2)"),
+        vec![]
+    );
+    assert_eq!(
+        resolve_commit_ids(mut_repo, r#"untracked_remote_branches("", private)"#),
+        vec![]
+    );
+}
+
+#[test]
+fn test_evaluate_expression_at_operation() {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let commit1 = write_random_commit(tx.mut_repo(), &settings);
+    let commit2 = write_random_commit(tx.mut_repo(), &settings);
+    tx.mut_repo()
+        .set_local_branch_target("main", RefTarget::normal(commit1.id().clone()));
+    let repo1 = tx.commit();
+
+    let mut tx = repo1.start_transaction(&settings, "test");
+    let commit3 = create_random_commit(tx.mut_repo(), &settings)
+        .set_parents(vec![commit1.id().clone()])
+        .write()
+        .unwrap();
+    tx.mut_repo()
+        .set_local_branch_target("main", RefTarget::normal(commit3.id().clone()));
+    tx.mut_repo()
+        .set_local_branch_target("feature", RefTarget::normal(commit3.id().clone()));
+    tx.mut_repo().remove_head(commit2.id());
+    let repo2 = tx.commit();
+
+    let op1_hex = repo1.op_id().hex();
+    let resolve = |revset_str: &str| -> Result<Vec<CommitId>, RevsetResolutionError> {
+        let context = RevsetParseContext {
+            aliases_map: &RevsetAliasesMap::new(),
+            user_email: settings.user_email(),
+            date_pattern_context: DatePatternContext::from_current_time(),
+            workspace: None,
+        };
+        let expression = optimize(parse(revset_str, &context).unwrap());
+        let symbol_resolver = DefaultSymbolResolver::new(repo2.as_ref()).with_operation_resolver(
+            Box::new(|op_str| {
+                if op_str == op1_hex {
+                    Ok(repo1.clone())
+                } else {
+                    Err(RevsetResolutionError::InvalidOperation {
+                        name: op_str.to_owned(),
+                        message: "No such operation".to_owned(),
+                    })
+                }
+            }),
+        );
+        let expression = expression.resolve_user_expression(repo2.as_ref(), &symbol_resolver)?;
+        Ok(expression
+            .evaluate(repo2.as_ref())
+            .unwrap()
+            .iter()
+            .collect())
+    };
+
+    // Symbols are resolved at the operation
+    assert_eq!(resolve("main").unwrap(), vec![commit3.id().clone()]);
+    assert_eq!(
+        resolve(&format!("at_operation({op1_hex}, main)")).unwrap(),
+        vec![commit1.id().clone()]
+    );
+    assert_eq!(
+        resolve(&format!("at_operation({op1_hex}, main)..main")).unwrap(),
+        vec![commit3.id().clone()]
+    );
+    assert_eq!(
+        resolve(&format!("at_operation({op1_hex}, present(feature))")).unwrap(),
+        vec![]
+    );
+    assert_matches!(
+        resolve(&format!("at_operation({op1_hex}, feature)")),
+        Err(RevsetResolutionError::NoSuchRevision { .. })
+    );
+
+    // all() and visible_heads() are resolved at the operation
+    assert_eq!(
+        resolve("visible_heads()").unwrap(),
+        vec![commit3.id().clone()]
+    );
+    assert_eq!(
+        resolve(&format!("at_operation({op1_hex}, visible_heads())")).unwrap(),
+        vec![commit2.id().clone(), commit1.id().clone()]
+    );
+    assert_eq!(
+        resolve(&format!("at_operation({op1_hex}, all()) ~ all()")).unwrap(),
+        vec![commit2.id().clone()]
+    );
+
+    // Unknown operation
+    assert_matches!(
+        resolve("at_operation(000000, main)"),
+        Err(RevsetResolutionError::InvalidOperation { name, .. }) if name == "000000"
+    );
+    // Operations can't be resolved without an operation resolver
+    let expression = optimize(
+        parse(
+            &format!("at_operation({op1_hex}, main)"),
+            &RevsetParseContext {
+                aliases_map: &RevsetAliasesMap::new(),
+                user_email: settings.user_email(),
+                date_pattern_context: DatePatternContext::from_current_time(),
+                workspace: None,
+            },
+        )
+        .unwrap(),
+    );
+    assert_matches!(
+        expression
+            .resolve_user_expression(repo2.as_ref(), &DefaultSymbolResolver::new(repo2.as_ref())),
+        Err(RevsetResolutionError::InvalidOperation { .. })
+    );
+}
+
+#[test]
+fn test_evaluate_expression_commit_not_in_index() {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+
+    // The commit is written to the store, but the transaction is discarded, so
+    // it isn't indexed.
+    let mut tx = repo.start_transaction(&settings, "test");
+    let commit = write_random_commit(tx.mut_repo(), &settings);
+    drop(tx);
+
+    let expression = RevsetExpression::commits(vec![commit.id().clone()])
+        .resolve(repo.as_ref())
+        .unwrap();
+    assert_matches!(
+        expression.evaluate(repo.as_ref()).map(|_| ()),
+        Err(RevsetEvaluationError::Other(message)) if message.contains(&commit.id().hex())
+    );
+}
+
 #[test_case(TestRepoBackend::Local ; "local backend")]
 #[test_case(TestRepoBackend::Git ; "git backend")]
 fn test_evaluate_expression_latest(backend: TestRepoBackend) {
@@ -2092,6 +2290,15 @@ fn test_evaluate_expression_description(backend: TestRepoBackend) {
         resolve_commit_ids(mut_repo, "description(\"commit 2\")"),
         vec![commit2.id().clone()]
     );
+    // Can find matches by glob or regex
+    assert_eq!(
+        resolve_commit_ids(mut_repo, r#"description(glob:"commit [23]")"#),
+        vec![commit3.id().clone(), commit2.id().clone()]
+    );
+    assert_eq!(
+        resolve_commit_ids(mut_repo, r#"description(regex:"^commit [^3]")"#),
+        vec![commit2.id().clone(), commit1.id().clone()]
+    );
     // Searches only among candidates if specified
     assert_eq!(
         resolve_commit_ids(mut_repo, "visible_heads() & description(\"commit 2\")"),
@@ -2308,6 +2515,77 @@ fn test_evaluate_expression_committer(backend: TestRepoBackend) {
     );
 }
 
+#[test_case(TestRepoBackend::Local ; "local backend")]
+#[test_case(TestRepoBackend::Git ; "git backend")]
+fn test_evaluate_expression_date(backend: TestRepoBackend) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init_with_backend(backend);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+
+    let timestamp =
+        |src: &str| Timestamp::from_datetime(chrono::DateTime::parse_from_rfc3339(src).unwrap());
+    let mut write_commit_with_dates = |author_date: &str, committer_date: &str| {
+        let builder = create_random_commit(mut_repo, &settings);
+        let mut author = builder.author().clone();
+        author.timestamp = timestamp(author_date);
+        let mut committer = builder.committer().clone();
+        committer.timestamp = timestamp(committer_date);
+        builder
+            .set_author(author)
+            .set_committer(committer)
+            .write()
+            .unwrap()
+    };
+    let commit1 = write_commit_with_dates("2023-01-01T10:00:00+00:00", "2023-03-01T10:00:00+00:00");
+    let commit2 = write_commit_with_dates("2023-02-01T10:00:00+00:00", "2023-02-01T10:00:00+00:00");
+    let commit3 = write_commit_with_dates("2023-03-01T10:00:00+00:00", "2023-03-02T10:00:00+00:00");
+
+    assert_eq!(
+        resolve_commit_ids(mut_repo, r#"author_date(after:"2023-02-01 UTC")"#),
+        vec![commit3.id().clone(), commit2.id().clone()]
+    );
+    assert_eq!(
+        resolve_commit_ids(mut_repo, r#"author_date(before:"2023-02-01 UTC") ~ root()"#),
+        vec![commit1.id().clone()]
+    );
+    assert_eq!(
+        resolve_commit_ids(mut_repo, r#"committer_date(after:"2023-03-01T00:00:00Z")"#),
+        vec![commit3.id().clone(), commit1.id().clone()]
+    );
+    // Time zones are taken into account
+    assert_eq!(
+        resolve_commit_ids(
+            mut_repo,
+            r#"committer_date(after:"2023-03-02 11:00 +02:00")"#
+        ),
+        vec![commit3.id().clone()]
+    );
+    // Two patterns select a range
+    assert_eq!(
+        resolve_commit_ids(
+            mut_repo,
+            r#"author_date(after:"2023-01-15", before:"2023-02-15")"#
+        ),
+        vec![commit2.id().clone()]
+    );
+    // Relative dates are resolved against the current time
+    assert_eq!(
+        resolve_commit_ids(mut_repo, r#"committer_date(after:"2 weeks ago")"#),
+        vec![]
+    );
+    assert_eq!(
+        resolve_commit_ids(mut_repo, r#"author_date(before:"yesterday") ~ root()"#),
+        vec![
+            commit3.id().clone(),
+            commit2.id().clone(),
+            commit1.id().clone()
+        ]
+    );
+}
+
 #[test_case(TestRepoBackend::Local ; "local backend")]
 #[test_case(TestRepoBackend::Git ; "git backend")]
 fn test_evaluate_expression_union(backend: TestRepoBackend) {
@@ -2632,8 +2910,9 @@ fn test_evaluate_expression_file(backend: TestRepoBackend) {
 
     let resolve = |file_path: &RepoPath| -> Vec<CommitId> {
         let mut_repo = &*mut_repo;
-        let expression =
-            RevsetExpression::filter(RevsetFilterPredicate::File(Some(vec![file_path.clone()])));
+        let expression = RevsetExpression::filter(RevsetFilterPredicate::File(
+            FilesetExpression::prefix_path(file_path.clone()),
+        ));
         let revset = expression
             .resolve(mut_repo)
             .unwrap()
@@ -2675,6 +2954,28 @@ fn test_evaluate_expression_file(backend: TestRepoBackend) {
         ),
         vec![commit2.id().clone()]
     );
+    assert_eq!(
+        resolve_commit_ids_in_workspace(
+            mut_repo,
+            r#"file("glob:added_*_removed | root:added_clean_clean")"#,
+            &test_workspace.workspace,
+            Some(test_workspace.workspace.workspace_root()),
+        ),
+        vec![
+            commit3.id().clone(),
+            commit2.id().clone(),
+            commit1.id().clone()
+        ]
+    );
+    assert_eq!(
+        resolve_commit_ids_in_workspace(
+            mut_repo,
+            r#"file("~glob:added_modified_*")"#,
+            &test_workspace.workspace,
+            Some(test_workspace.workspace.workspace_root()),
+        ),
+        vec![commit1.id().clone()]
+    );
 
     // empty() revset, which is identical to ~file(".")
     assert_eq!(
@@ -2683,6 +2984,198 @@ fn test_evaluate_expression_file(backend: TestRepoBackend) {
     );
 }
 
+#[test_case(TestRepoBackend::Local ; "local backend")]
+#[test_case(TestRepoBackend::Git ; "git backend")]
+fn test_evaluate_expression_file_with_changed_path_filters(backend: TestRepoBackend) {
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init_with_backend(backend);
+    let index_store: &DefaultIndexStore = test_repo
+        .repo
+        .index_store()
+        .as_any()
+        .downcast_ref()
+        .unwrap();
+    index_store.set_changed_paths_enabled(true).unwrap();
+    index_store.reinit();
+    let repo = test_repo
+        .repo
+        .reload_at(test_repo.repo.operation())
+        .unwrap();
+
+    let dir_file1 = RepoPath::from_internal_string("dir/file1");
+    let dir_file2 = RepoPath::from_internal_string("dir/file2");
+    let other_file = RepoPath::from_internal_string("other");
+    let tree1 = create_tree(&repo, &[(&dir_file1, "1")]);
+    let tree2 = create_tree(&repo, &[(&dir_file1, "1"), (&other_file, "1")]);
+    let tree3 = create_tree(
+        &repo,
+        &[(&dir_file1, "1"), (&dir_file2, "1"), (&other_file, "1")],
+    );
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let commit1 = mut_repo
+        .new_commit(
+            &settings,
+            vec![repo.store().root_commit_id().clone()],
+            tree1.id(),
+        )
+        .write()
+        .unwrap();
+    let commit2 = mut_repo
+        .new_commit(&settings, vec![commit1.id().clone()], tree2.id())
+        .write()
+        .unwrap();
+    let commit3 = mut_repo
+        .new_commit(&settings, vec![commit2.id().clone()], tree3.id())
+        .write()
+        .unwrap();
+    let repo = tx.commit();
+
+    let resolve = |expression: FilesetExpression| -> Vec<CommitId> {
+        let expression = RevsetExpression::filter(RevsetFilterPredicate::File(expression));
+        let revset = expression
+            .resolve(repo.as_ref())
+            .unwrap()
+            .evaluate(repo.as_ref())
+            .unwrap();
+        revset.iter().collect()
+    };
+    let prefix_path =
+        |value: &str| FilesetExpression::prefix_path(RepoPath::from_internal_string(value));
+
+    assert_eq!(
+        resolve(prefix_path("dir")),
+        vec![commit3.id().clone(), commit1.id().clone()]
+    );
+    assert_eq!(
+        resolve(prefix_path("dir/file1")),
+        vec![commit1.id().clone()]
+    );
+    assert_eq!(resolve(prefix_path("other")), vec![commit2.id().clone()]);
+    assert_eq!(resolve(prefix_path("nonexistent")), vec![]);
+    assert_eq!(
+        resolve(FilesetExpression::union_all(vec![
+            prefix_path("dir/file2"),
+            prefix_path("other"),
+        ])),
+        vec![commit3.id().clone(), commit2.id().clone()]
+    );
+    assert_eq!(
+        resolve(prefix_path("dir").difference(prefix_path("dir/file1"))),
+        vec![commit3.id().clone()]
+    );
+    // The root directory can't be looked up in the filters
+    assert_eq!(
+        resolve(prefix_path("")),
+        vec![
+            commit3.id().clone(),
+            commit2.id().clone(),
+            commit1.id().clone()
+        ]
+    );
+}
+
+#[test_case(TestRepoBackend::Local ; "local backend")]
+#[test_case(TestRepoBackend::Git ; "git backend")]
+fn test_evaluate_expression_diff_contains(backend: TestRepoBackend) {
+    let settings = testutils::user_settings();
+    let test_workspace = TestWorkspace::init_with_backend(&settings, backend);
+    let repo = &test_workspace.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+
+    let empty_clean_inserted_deleted =
+        RepoPath::from_internal_string("empty_clean_inserted_deleted");
+    let blank_clean_inserted_clean = RepoPath::from_internal_string("blank_clean_inserted_clean");
+    let noeol_modified_modified_clean =
+        RepoPath::from_internal_string("noeol_modified_modified_clean");
+    let tree1 = create_tree(
+        repo,
+        &[
+            (&empty_clean_inserted_deleted, ""),
+            (&blank_clean_inserted_clean, "\n"),
+            (&noeol_modified_modified_clean, "1"),
+        ],
+    );
+    let tree2 = create_tree(
+        repo,
+        &[
+            (&empty_clean_inserted_deleted, ""),
+            (&blank_clean_inserted_clean, "\n"),
+            (&noeol_modified_modified_clean, "2"),
+        ],
+    );
+    let tree3 = create_tree(
+        repo,
+        &[
+            (&empty_clean_inserted_deleted, "3"),
+            (&blank_clean_inserted_clean, "\n3\n"),
+            (&noeol_modified_modified_clean, "2 3"),
+        ],
+    );
+    let tree4 = create_tree(
+        repo,
+        &[
+            (&blank_clean_inserted_clean, "\n3\n"),
+            (&noeol_modified_modified_clean, "2 3"),
+        ],
+    );
+    let commit1 = mut_repo
+        .new_commit(
+            &settings,
+            vec![repo.store().root_commit_id().clone()],
+            tree1.id(),
+        )
+        .write()
+        .unwrap();
+    let commit2 = mut_repo
+        .new_commit(&settings, vec![commit1.id().clone()], tree2.id())
+        .write()
+        .unwrap();
+    let commit3 = mut_repo
+        .new_commit(&settings, vec![commit2.id().clone()], tree3.id())
+        .write()
+        .unwrap();
+    let commit4 = mut_repo
+        .new_commit(&settings, vec![commit3.id().clone()], tree4.id())
+        .write()
+        .unwrap();
+
+    let query = |revset_str: &str| {
+        resolve_commit_ids_in_workspace(
+            mut_repo,
+            revset_str,
+            &test_workspace.workspace,
+            Some(test_workspace.workspace.workspace_root()),
+        )
+    };
+
+    // should match both inserted and deleted lines
+    assert_eq!(
+        query(r#"diff_contains("2")"#),
+        vec![commit3.id().clone(), commit2.id().clone()]
+    );
+    assert_eq!(
+        query(r#"diff_contains("3")"#),
+        vec![commit4.id().clone(), commit3.id().clone()]
+    );
+    assert_eq!(query(r#"diff_contains("2 3")"#), vec![commit3.id().clone()]);
+    assert_eq!(
+        query(r#"diff_contains("3", "noeol_modified_modified_clean")"#),
+        vec![commit3.id().clone()]
+    );
+    assert_eq!(
+        query(r#"diff_contains(regex:"^3$", "blank_clean_inserted_clean")"#),
+        vec![commit3.id().clone()]
+    );
+    // unchanged lines don't match
+    assert_eq!(
+        query(r#"diff_contains(exact:"", "blank_clean_inserted_clean")"#),
+        vec![commit1.id().clone()]
+    );
+}
+
 #[test_case(TestRepoBackend::Local ; "local backend")]
 #[test_case(TestRepoBackend::Git ; "git backend")]
 fn test_evaluate_expression_conflict(backend: TestRepoBackend) {
@@ -2906,6 +3399,7 @@ fn test_no_such_revision_suggestion() {
             BranchTarget {
                 local_target: RefTarget::normal(commit.id().clone()),
                 remote_targets: Default::default(),
+                untracked_remotes: Default::default(),
             },
         );
     }
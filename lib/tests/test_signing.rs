@@ -0,0 +1,233 @@
+// Copyright 2023 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jj_lib::commit::Commit;
+use jj_lib::repo::Repo;
+use jj_lib::settings::UserSettings;
+use jj_lib::signing::{SigStatus, SigningBackend, Verification};
+use jj_lib::ssh_signing::SshBackend;
+use test_case::test_case;
+use testutils::{create_random_commit, write_random_commit, TestRepo, TestRepoBackend};
+
+fn user_settings(sign_all: bool) -> UserSettings {
+    user_settings_with_backend("test", sign_all)
+}
+
+fn user_settings_with_backend(backend: &str, sign_all: bool) -> UserSettings {
+    let config = testutils::base_config()
+        .add_source(config::File::from_str(
+            &format!(
+                r#"
+                    signing.key = "impeccable"
+                    signing.backend = "{backend}"
+                    signing.sign-all = {sign_all}
+                "#
+            ),
+            config::FileFormat::Toml,
+        ))
+        .build()
+        .unwrap();
+    UserSettings::from_config(config)
+}
+
+fn good_verification() -> Option<Verification> {
+    Some(Verification {
+        status: SigStatus::Good,
+        key: Some("impeccable".to_owned()),
+        display: None,
+    })
+}
+
+fn reload(settings: &UserSettings, test_repo: &TestRepo, commit: &Commit) -> Commit {
+    // Load the commit from a new store to make sure that the signature was
+    // actually written.
+    let repo = testutils::load_repo_at_head(settings, test_repo.repo.repo_path());
+    repo.store().get_commit(commit.id()).unwrap()
+}
+
+#[test]
+fn test_sign_all() {
+    let settings = user_settings(true);
+    let test_repo = TestRepo::init_with_backend_and_settings(TestRepoBackend::Git, &settings);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let commit = write_random_commit(tx.mut_repo(), &settings);
+    tx.commit();
+
+    assert!(commit.is_signed());
+    assert_eq!(commit.verification().unwrap(), good_verification());
+    let commit = reload(&settings, &test_repo, &commit);
+    assert_eq!(commit.verification().unwrap(), good_verification());
+}
+
+#[test]
+fn test_no_sign_by_default() {
+    let settings = user_settings(false);
+    let test_repo = TestRepo::init_with_backend_and_settings(TestRepoBackend::Git, &settings);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let commit = write_random_commit(tx.mut_repo(), &settings);
+    tx.commit();
+
+    assert!(!commit.is_signed());
+    assert_eq!(commit.verification().unwrap(), None);
+}
+
+#[test]
+fn test_rewrite_keeps_signature_only_if_unchanged() {
+    let sign_settings = user_settings(true);
+    let settings = user_settings(false);
+    let test_repo = TestRepo::init_with_backend_and_settings(TestRepoBackend::Git, &settings);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&sign_settings, "test");
+    let commit = create_random_commit(tx.mut_repo(), &sign_settings)
+        .set_description("initial")
+        .write()
+        .unwrap();
+    let repo = tx.commit();
+    assert!(commit.is_signed());
+
+    // Rewriting without changing anything that's signed keeps the signature,
+    // even if signing is disabled.
+    let mut tx = repo.start_transaction(&settings, "test");
+    let unchanged = tx
+        .mut_repo()
+        .rewrite_commit(&settings, &commit)
+        .set_committer(commit.committer().clone())
+        .set_predecessors(commit.predecessor_ids().to_vec())
+        .write()
+        .unwrap();
+    assert_eq!(unchanged.id(), commit.id());
+    let unchanged = reload(&settings, &test_repo, &unchanged);
+    assert_eq!(unchanged.verification().unwrap(), good_verification());
+
+    // Changing the content drops the signature.
+    let changed = tx
+        .mut_repo()
+        .rewrite_commit(&settings, &commit)
+        .set_description("changed")
+        .write()
+        .unwrap();
+    assert!(!changed.is_signed());
+    let changed = reload(&settings, &test_repo, &changed);
+    assert!(!changed.is_signed());
+
+    // Unless signing is enabled, in which case the commit is signed again.
+    let resigned = tx
+        .mut_repo()
+        .rewrite_commit(&sign_settings, &commit)
+        .set_description("changed again")
+        .write()
+        .unwrap();
+    let resigned = reload(&settings, &test_repo, &resigned);
+    assert_eq!(resigned.verification().unwrap(), good_verification());
+}
+
+#[test]
+fn test_sign_all_without_usable_backend() {
+    let settings = user_settings(true);
+    let test_repo = TestRepo::init_with_backend_and_settings(TestRepoBackend::Git, &settings);
+    let mut tx = test_repo.repo.start_transaction(&settings, "test");
+    let signed_commit = write_random_commit(tx.mut_repo(), &settings);
+    tx.commit();
+
+    for backend in ["none", "bogus"] {
+        // An invalid backend doesn't prevent loading the repo and verifying
+        // signatures
+        let settings = user_settings_with_backend(backend, true);
+        let repo = testutils::load_repo_at_head(&settings, test_repo.repo.repo_path());
+        let commit = repo.store().get_commit(signed_commit.id()).unwrap();
+        assert_eq!(commit.verification().unwrap(), good_verification());
+
+        // Commits aren't silently left unsigned
+        let mut tx = repo.start_transaction(&settings, "test");
+        let result = create_random_commit(tx.mut_repo(), &settings).write();
+        assert!(result.is_err(), "backend {backend} should fail to sign");
+    }
+}
+
+#[test]
+fn test_ssh_backend() {
+    // ssh-keygen isn't necessarily available, e.g. on Windows CI.
+    if std::process::Command::new("ssh-keygen")
+        .arg("-V")
+        .output()
+        .is_err()
+    {
+        eprintln!("Skipping because ssh-keygen is not installed");
+        return;
+    }
+    let temp_dir = testutils::new_temp_dir();
+    let key_path = temp_dir.path().join("key");
+    let status = std::process::Command::new("ssh-keygen")
+        .args([
+            "-q",
+            "-t",
+            "ed25519",
+            "-N",
+            "",
+            "-C",
+            "test.user@example.com",
+            "-f",
+        ])
+        .arg(&key_path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let public_key = std::fs::read_to_string(key_path.with_extension("pub")).unwrap();
+    let allowed_signers_path = temp_dir.path().join("allowed_signers");
+    std::fs::write(
+        &allowed_signers_path,
+        format!("test.user@example.com {public_key}"),
+    )
+    .unwrap();
+
+    let untrusted_backend = SshBackend::new("ssh-keygen".into(), None);
+    let trusted_backend = SshBackend::new("ssh-keygen".into(), Some(allowed_signers_path));
+    let signature = untrusted_backend
+        .sign(b"hello", Some(key_path.to_str().unwrap()))
+        .unwrap();
+    assert!(untrusted_backend.can_read(&signature));
+
+    let verification = trusted_backend.verify(b"hello", &signature).unwrap();
+    assert_eq!(verification.status, SigStatus::Good);
+    assert_eq!(
+        verification.display.as_deref(),
+        Some("test.user@example.com")
+    );
+    assert!(verification.key.unwrap().starts_with("SHA256:"));
+    let verification = untrusted_backend.verify(b"hello", &signature).unwrap();
+    assert_eq!(verification.status, SigStatus::Unknown);
+    let verification = trusted_backend.verify(b"goodbye", &signature).unwrap();
+    assert_eq!(verification.status, SigStatus::Bad);
+    let verification = untrusted_backend.verify(b"goodbye", &signature).unwrap();
+    assert_eq!(verification.status, SigStatus::Bad);
+}
+
+#[test_case(TestRepoBackend::Local ; "local backend")]
+#[test_case(TestRepoBackend::Test ; "test backend")]
+fn test_sign_unsupported_backend(backend: TestRepoBackend) {
+    let settings = user_settings(true);
+    let test_repo = TestRepo::init_with_backend_and_settings(backend, &settings);
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let commit = write_random_commit(tx.mut_repo(), &settings);
+    tx.commit();
+
+    assert!(!commit.is_signed());
+}
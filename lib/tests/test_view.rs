@@ -299,10 +299,12 @@ fn test_merge_views_branches() {
             "origin".to_string() => RefTarget::normal(main_branch_origin_tx1.id().clone()),
             "alternate".to_string() => RefTarget::normal(main_branch_alternate_tx0.id().clone()),
         },
+        untracked_remotes: Default::default(),
     };
     let expected_feature_branch = BranchTarget {
         local_target: RefTarget::normal(feature_branch_tx1.id().clone()),
         remote_targets: btreemap! {},
+        untracked_remotes: Default::default(),
     };
     assert_eq!(
         repo.view().branches(),
@@ -313,6 +315,45 @@ fn test_merge_views_branches() {
     );
 }
 
+#[test]
+fn test_merge_views_remote_branch_tracking() {
+    // Tests merging of the tracking state of remote branches
+    let settings = testutils::user_settings();
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction(&settings, "test");
+    let mut_repo = tx.mut_repo();
+    let commit = write_random_commit(mut_repo, &settings);
+    for branch_name in ["main", "feature", "stale"] {
+        mut_repo.set_remote_branch_target(
+            branch_name,
+            "origin",
+            RefTarget::normal(commit.id().clone()),
+        );
+    }
+    mut_repo.set_remote_branch_tracked("stale", "origin", false);
+    let repo = tx.commit();
+
+    // Untrack on one side, track on the other side, and an unrelated change on
+    // both sides.
+    let mut tx1 = repo.start_transaction(&settings, "test");
+    tx1.mut_repo()
+        .set_remote_branch_tracked("main", "origin", false);
+    tx1.mut_repo()
+        .set_local_branch_target("main", RefTarget::normal(commit.id().clone()));
+    let mut tx2 = repo.start_transaction(&settings, "test");
+    tx2.mut_repo()
+        .set_remote_branch_tracked("stale", "origin", true);
+    tx2.mut_repo()
+        .set_local_branch_target("feature", RefTarget::normal(commit.id().clone()));
+
+    let repo = commit_transactions(&settings, vec![tx1, tx2]);
+    assert!(!repo.view().is_remote_branch_tracked("main", "origin"));
+    assert!(repo.view().is_remote_branch_tracked("feature", "origin"));
+    assert!(repo.view().is_remote_branch_tracked("stale", "origin"));
+}
+
 #[test]
 fn test_merge_views_tags() {
     // Tests merging of tags (by performing concurrent operations). See
@@ -33,7 +33,10 @@ use jj_lib::op_store::{OperationId, WorkspaceId};
 use jj_lib::repo::{ReadonlyRepo, Repo};
 use jj_lib::repo_path::{RepoPath, RepoPathComponent, RepoPathJoin};
 use jj_lib::settings::UserSettings;
-use jj_lib::working_copy::{LockedWorkingCopy, SnapshotError, SnapshotOptions, WorkingCopy};
+use jj_lib::sparse::SparsePattern;
+use jj_lib::working_copy::{
+    CheckoutOptions, LockedWorkingCopy, SnapshotError, SnapshotOptions, WorkingCopy,
+};
 use test_case::test_case;
 use testutils::{create_tree, write_random_commit, TestRepoBackend, TestWorkspace};
 
@@ -44,7 +47,7 @@ fn test_root() {
     let mut test_workspace = TestWorkspace::init(&settings);
 
     let wc = test_workspace.workspace.working_copy();
-    assert_eq!(wc.sparse_patterns().unwrap(), vec![RepoPath::root()]);
+    assert_eq!(wc.sparse_patterns().unwrap(), vec![SparsePattern::root()]);
     let new_tree = test_workspace.snapshot().unwrap();
     let repo = &test_workspace.repo;
     let wc_commit_id = repo
@@ -189,10 +192,20 @@ fn test_checkout_file_transitions(backend: TestRepoBackend) {
     let right_tree = store.get_root_tree(&right_tree_id).unwrap();
 
     let wc = test_workspace.workspace.working_copy_mut();
-    wc.check_out(repo.op_id().clone(), None, &left_tree)
-        .unwrap();
-    wc.check_out(repo.op_id().clone(), None, &right_tree)
-        .unwrap();
+    wc.check_out(
+        repo.op_id().clone(),
+        None,
+        &left_tree,
+        &CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
+    wc.check_out(
+        repo.op_id().clone(),
+        None,
+        &right_tree,
+        &CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
 
     // Check that the working copy is clean.
     let new_tree = test_workspace.snapshot().unwrap();
@@ -273,9 +286,20 @@ fn test_conflict_subdirectory() {
     let merged_tree = tree1.merge(&empty_tree, &tree2).unwrap();
     let repo = &test_workspace.repo;
     let wc = test_workspace.workspace.working_copy_mut();
-    wc.check_out(repo.op_id().clone(), None, &tree1).unwrap();
-    wc.check_out(repo.op_id().clone(), None, &merged_tree)
-        .unwrap();
+    wc.check_out(
+        repo.op_id().clone(),
+        None,
+        &tree1,
+        &CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
+    wc.check_out(
+        repo.op_id().clone(),
+        None,
+        &merged_tree,
+        &CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
 }
 
 #[test]
@@ -289,8 +313,13 @@ fn test_tree_builder_file_directory_transition() {
     let mut check_out_tree = |tree_id: &TreeId| {
         let tree = repo.store().get_tree(&RepoPath::root(), tree_id).unwrap();
         let wc = workspace.working_copy_mut();
-        wc.check_out(repo.op_id().clone(), None, &MergedTree::legacy(tree))
-            .unwrap();
+        wc.check_out(
+            repo.op_id().clone(),
+            None,
+            &MergedTree::legacy(tree),
+            &CheckoutOptions::empty_for_test(),
+        )
+        .unwrap();
     };
 
     let parent_path = RepoPath::from_internal_string("foo/bar");
@@ -323,6 +352,78 @@ fn test_tree_builder_file_directory_transition() {
     assert!(!child_path.to_fs_path(&workspace_root).exists());
 }
 
+#[test]
+fn test_checkout_many_files() {
+    // Files are written in parallel. Test that the result is still the same as
+    // when writing them one by one.
+    let settings = testutils::user_settings();
+    let mut test_workspace = TestWorkspace::init(&settings);
+    let repo = &test_workspace.repo;
+    let workspace_root = test_workspace.workspace.workspace_root().clone();
+
+    let paths = (0..10)
+        .flat_map(|i| (0..20).map(move |j| format!("dir{i}/sub{}/file{j}", j % 3)))
+        .map(|path| RepoPath::from_internal_string(&path))
+        .collect_vec();
+    let tree1 = create_tree(
+        repo,
+        &paths.iter().map(|path| (path, "contents")).collect_vec(),
+    );
+    // Replace the "dir0" directory by a file and modify the other files
+    let dir0_path = RepoPath::from_internal_string("dir0");
+    let tree2 = create_tree(
+        repo,
+        &paths
+            .iter()
+            .filter(|path| !dir0_path.contains(path))
+            .map(|path| (path, "modified"))
+            .chain([(&dir0_path, "file")])
+            .collect_vec(),
+    );
+
+    let wc = test_workspace.workspace.working_copy_mut();
+    let stats = wc
+        .check_out(
+            repo.op_id().clone(),
+            None,
+            &tree1,
+            &CheckoutOptions::empty_for_test(),
+        )
+        .unwrap();
+    assert_eq!(stats.added_files, 200);
+    assert_eq!(
+        wc.file_states().unwrap().keys().collect_vec(),
+        paths.iter().sorted().collect_vec()
+    );
+    for path in &paths {
+        assert_eq!(
+            std::fs::read_to_string(path.to_fs_path(&workspace_root)).unwrap(),
+            "contents"
+        );
+    }
+
+    let stats = wc
+        .check_out(
+            repo.op_id().clone(),
+            Some(&tree1.id()),
+            &tree2,
+            &CheckoutOptions::empty_for_test(),
+        )
+        .unwrap();
+    assert_eq!(stats.added_files, 1);
+    assert_eq!(stats.updated_files, 180);
+    assert_eq!(stats.removed_files, 20);
+    assert!(dir0_path.to_fs_path(&workspace_root).is_file());
+    for path in paths.iter().filter(|path| !dir0_path.contains(path)) {
+        assert_eq!(
+            std::fs::read_to_string(path.to_fs_path(&workspace_root)).unwrap(),
+            "modified"
+        );
+    }
+    let new_tree = test_workspace.snapshot().unwrap();
+    assert_eq!(new_tree.id(), tree2.id());
+}
+
 #[test]
 fn test_reset() {
     let settings = testutils::user_settings();
@@ -341,8 +442,13 @@ fn test_reset() {
     );
 
     let wc = test_workspace.workspace.working_copy_mut();
-    wc.check_out(repo.op_id().clone(), None, &tree_with_file)
-        .unwrap();
+    wc.check_out(
+        repo.op_id().clone(),
+        None,
+        &tree_with_file,
+        &CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
 
     // Test the setup: the file should exist on disk and in the tree state.
     assert!(ignored_path.to_fs_path(&workspace_root).is_file());
@@ -390,7 +496,13 @@ fn test_checkout_discard() {
 
     let wc = test_workspace.workspace.working_copy_mut();
     let state_path = wc.state_path().to_path_buf();
-    wc.check_out(repo.op_id().clone(), None, &tree1).unwrap();
+    wc.check_out(
+        repo.op_id().clone(),
+        None,
+        &tree1,
+        &CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
 
     // Test the setup: the file should exist on disk and in the tree state.
     assert!(file1_path.to_fs_path(&workspace_root).is_file());
@@ -398,7 +510,9 @@ fn test_checkout_discard() {
 
     // Start a checkout
     let mut locked_wc = wc.start_mutation().unwrap();
-    locked_wc.check_out(&tree2).unwrap();
+    locked_wc
+        .check_out(&tree2, &CheckoutOptions::empty_for_test())
+        .unwrap();
     // The change should be reflected in the working copy but not saved
     assert!(!file1_path.to_fs_path(&workspace_root).is_file());
     assert!(file2_path.to_fs_path(&workspace_root).is_file());
@@ -580,7 +694,13 @@ fn test_gitignores_in_ignored_dir() {
 
     let tree1 = create_tree(&test_workspace.repo, &[(&gitignore_path, "ignored\n")]);
     let wc = test_workspace.workspace.working_copy_mut();
-    wc.check_out(op_id.clone(), None, &tree1).unwrap();
+    wc.check_out(
+        op_id.clone(),
+        None,
+        &tree1,
+        &CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
 
     testutils::write_working_copy_file(&workspace_root, &nested_gitignore_path, "!file\n");
     testutils::write_working_copy_file(&workspace_root, &ignored_path, "contents");
@@ -634,7 +754,14 @@ fn test_gitignores_checkout_never_overwrites_ignored() {
     // "contents". The exiting contents ("garbage") shouldn't be replaced in the
     // working copy.
     let wc = test_workspace.workspace.working_copy_mut();
-    assert!(wc.check_out(repo.op_id().clone(), None, &tree).is_err());
+    assert!(wc
+        .check_out(
+            repo.op_id().clone(),
+            None,
+            &tree,
+            &CheckoutOptions::empty_for_test()
+        )
+        .is_err());
 
     // Check that the old contents are in the working copy
     let path = workspace_root.join("modified");
@@ -668,7 +795,13 @@ fn test_gitignores_ignored_directory_already_tracked() {
 
     // Check out the tree with the files in `ignored/`
     let wc = test_workspace.workspace.working_copy_mut();
-    wc.check_out(repo.op_id().clone(), None, &tree).unwrap();
+    wc.check_out(
+        repo.op_id().clone(),
+        None,
+        &tree,
+        &CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
 
     // Make some changes inside the ignored directory and check that they are
     // detected when we snapshot. The files that are still there should not be
@@ -760,7 +893,13 @@ fn test_gitsubmodule() {
     let tree_id = MergedTreeId::Legacy(tree_builder.write_tree());
     let tree = store.get_root_tree(&tree_id).unwrap();
     let wc = test_workspace.workspace.working_copy_mut();
-    wc.check_out(repo.op_id().clone(), None, &tree).unwrap();
+    wc.check_out(
+        repo.op_id().clone(),
+        None,
+        &tree,
+        &CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
 
     std::fs::create_dir(submodule_path.to_fs_path(&workspace_root)).unwrap();
 
@@ -799,7 +938,14 @@ fn test_existing_directory_symlink() {
 
     // Checkout should fail because "parent" already exists and is a symlink.
     let wc = test_workspace.workspace.working_copy_mut();
-    assert!(wc.check_out(repo.op_id().clone(), None, &tree).is_err());
+    assert!(wc
+        .check_out(
+            repo.op_id().clone(),
+            None,
+            &tree,
+            &CheckoutOptions::empty_for_test()
+        )
+        .is_err());
 
     // Therefore, "../escaped" shouldn't be created.
     assert!(!workspace_root.parent().unwrap().join("escaped").exists());
@@ -813,7 +959,7 @@ fn test_fsmonitor() {
     let workspace_root = test_workspace.workspace.workspace_root().clone();
 
     let wc = test_workspace.workspace.working_copy_mut();
-    assert_eq!(wc.sparse_patterns().unwrap(), vec![RepoPath::root()]);
+    assert_eq!(wc.sparse_patterns().unwrap(), vec![SparsePattern::root()]);
 
     let foo_path = RepoPath::from_internal_string("foo");
     let bar_path = RepoPath::from_internal_string("bar");
@@ -898,6 +1044,116 @@ fn test_fsmonitor() {
     }
 }
 
+#[cfg(target_os = "linux")]
+#[test]
+fn test_fsmonitor_inotify() {
+    use std::path::PathBuf;
+
+    use jj_lib::fsmonitor::inotify;
+
+    let settings = testutils::user_settings();
+    let mut test_workspace = TestWorkspace::init(&settings);
+    let repo = &test_workspace.repo;
+    let workspace_root = test_workspace.workspace.workspace_root().clone();
+    let wc = test_workspace.workspace.working_copy_mut();
+    let socket_path = wc.state_path().join(inotify::SOCKET_FILE_NAME);
+
+    let foo_path = RepoPath::from_internal_string("foo");
+    let bar_path = RepoPath::from_internal_string("dir/bar");
+    testutils::write_working_copy_file(&workspace_root, &foo_path, "foo\n");
+
+    assert!(matches!(
+        inotify::query_status(&socket_path),
+        Err(inotify::Error::NotRunning)
+    ));
+    let daemon = inotify::Daemon::start(&workspace_root, &socket_path).unwrap();
+    assert!(matches!(
+        inotify::Daemon::start(&workspace_root, &socket_path),
+        Err(inotify::Error::AlreadyRunning)
+    ));
+    let daemon_thread = std::thread::spawn(move || daemon.run().unwrap());
+    assert_eq!(inotify::query_status(&socket_path).unwrap().num_watches, 1);
+
+    let query = |clock: Option<&str>| {
+        let (clock, paths) = inotify::query_changed_files(&socket_path, clock).unwrap();
+        (
+            clock,
+            paths.map(|paths| paths.into_iter().sorted().collect_vec()),
+        )
+    };
+    // Clients without a clock have to crawl the working copy
+    let (clock, paths) = query(None);
+    assert_eq!(paths, None);
+    let (_, paths) = query(Some(&clock));
+    assert_eq!(paths, Some(vec![]));
+    // Clocks from another instance aren't valid
+    let (_, paths) = query(Some("1-2:0"));
+    assert_eq!(paths, None);
+
+    testutils::write_working_copy_file(&workspace_root, &foo_path, "updated foo\n");
+    testutils::write_working_copy_file(&workspace_root, &bar_path, "bar\n");
+    let (clock, paths) = query(Some(&clock));
+    assert_eq!(
+        paths,
+        Some(vec![
+            PathBuf::from("dir"),
+            PathBuf::from("dir/bar"),
+            PathBuf::from("foo"),
+        ])
+    );
+    assert_eq!(inotify::query_status(&socket_path).unwrap().num_watches, 2);
+
+    let snapshot = |locked_wc: &mut LockedWorkingCopy| {
+        locked_wc
+            .snapshot(SnapshotOptions {
+                fsmonitor_kind: Some(FsmonitorKind::Inotify),
+                ..SnapshotOptions::empty_for_test()
+            })
+            .unwrap()
+    };
+    {
+        let mut locked_wc = wc.start_mutation().unwrap();
+        let tree_id = snapshot(&mut locked_wc);
+        insta::assert_snapshot!(testutils::dump_tree(repo.store(), &tree_id), @r###"
+        tree 00e5119af4f555a63bc3
+          file "dir/bar" (94cc973e7e1aefb7eff6): "bar\n"
+          file "foo" (e0fbd106147cc04ccd05): "updated foo\n"
+        "###);
+        locked_wc.finish(repo.op_id().clone()).unwrap();
+    }
+
+    // Changes to the repo's own state aren't reported
+    let (_, paths) = query(Some(&clock));
+    assert_eq!(paths, Some(vec![]));
+
+    std::fs::rename(workspace_root.join("dir"), workspace_root.join("renamed")).unwrap();
+    {
+        let mut locked_wc = wc.start_mutation().unwrap();
+        let tree_id = snapshot(&mut locked_wc);
+        insta::assert_snapshot!(testutils::dump_tree(repo.store(), &tree_id), @r###"
+        tree 4f1cf78a4d0f0f2393ae
+          file "foo" (e0fbd106147cc04ccd05): "updated foo\n"
+          file "renamed/bar" (94cc973e7e1aefb7eff6): "bar\n"
+        "###);
+        locked_wc.finish(repo.op_id().clone()).unwrap();
+    }
+
+    inotify::stop(&socket_path).unwrap();
+    daemon_thread.join().unwrap();
+    assert!(!socket_path.exists());
+
+    // Socket paths that don't fit in `sockaddr_un` are rejected up front
+    let long_socket_path = workspace_root.join("x".repeat(200));
+    assert!(matches!(
+        inotify::query_status(&long_socket_path),
+        Err(inotify::Error::SocketPathTooLong(_))
+    ));
+    assert!(matches!(
+        inotify::Daemon::start(&workspace_root, &long_socket_path),
+        Err(inotify::Error::SocketPathTooLong(_))
+    ));
+}
+
 #[test]
 fn test_snapshot_max_new_file_size() {
     let settings = UserSettings::from_config(
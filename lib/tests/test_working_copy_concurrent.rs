@@ -18,7 +18,7 @@ use std::thread;
 use assert_matches::assert_matches;
 use jj_lib::repo::Repo;
 use jj_lib::repo_path::RepoPath;
-use jj_lib::working_copy::{CheckoutError, SnapshotOptions};
+use jj_lib::working_copy::{CheckoutError, CheckoutOptions, SnapshotOptions};
 use jj_lib::workspace::Workspace;
 use testutils::{create_tree, write_working_copy_file, TestRepo, TestWorkspace};
 
@@ -41,7 +41,13 @@ fn test_concurrent_checkout() {
     // Check out tree1
     let wc1 = test_workspace1.workspace.working_copy_mut();
     // The operation ID is not correct, but that doesn't matter for this test
-    wc1.check_out(repo1.op_id().clone(), None, &tree1).unwrap();
+    wc1.check_out(
+        repo1.op_id().clone(),
+        None,
+        &tree1,
+        &CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
 
     // Check out tree2 from another process (simulated by another workspace
     // instance)
@@ -53,12 +59,22 @@ fn test_concurrent_checkout() {
     .unwrap();
     workspace2
         .working_copy_mut()
-        .check_out(repo1.op_id().clone(), Some(&tree_id1), &tree2)
+        .check_out(
+            repo1.op_id().clone(),
+            Some(&tree_id1),
+            &tree2,
+            &CheckoutOptions::empty_for_test(),
+        )
         .unwrap();
 
     // Checking out another tree (via the first repo instance) should now fail.
     assert_matches!(
-        wc1.check_out(repo1.op_id().clone(), Some(&tree_id1), &tree3),
+        wc1.check_out(
+            repo1.op_id().clone(),
+            Some(&tree_id1),
+            &tree3,
+            &CheckoutOptions::empty_for_test()
+        ),
         Err(CheckoutError::ConcurrentCheckout)
     );
 
@@ -101,7 +117,12 @@ fn test_checkout_parallel() {
     test_workspace
         .workspace
         .working_copy_mut()
-        .check_out(repo.op_id().clone(), None, &tree)
+        .check_out(
+            repo.op_id().clone(),
+            None,
+            &tree,
+            &CheckoutOptions::empty_for_test(),
+        )
         .unwrap();
 
     thread::scope(|s| {
@@ -126,14 +147,14 @@ fn test_checkout_parallel() {
                 // The operation ID is not correct, but that doesn't matter for this test
                 let stats = workspace
                     .working_copy_mut()
-                    .check_out(op_id, None, &tree)
+                    .check_out(op_id, None, &tree, &CheckoutOptions::empty_for_test())
                     .unwrap();
                 assert_eq!(stats.updated_files, 0);
                 assert_eq!(stats.added_files, 1);
                 assert_eq!(stats.removed_files, 1);
                 // Check that the working copy contains one of the trees. We may see a
                 // different tree than the one we just checked out, but since
-                // write_tree() should take the same lock as check_out(), write_tree()
+                // write_tree() should take the same lock as check_out(&CheckoutOptions::empty_for_test()), write_tree()
                 // should never produce a different tree.
                 let mut locked_wc = workspace.working_copy_mut().start_mutation().unwrap();
                 let new_tree_id = locked_wc
@@ -159,7 +180,13 @@ fn test_racy_checkout() {
     let mut num_matches = 0;
     for _ in 0..100 {
         let wc = test_workspace.workspace.working_copy_mut();
-        wc.check_out(op_id.clone(), None, &tree).unwrap();
+        wc.check_out(
+            op_id.clone(),
+            None,
+            &tree,
+            &CheckoutOptions::empty_for_test(),
+        )
+        .unwrap();
         assert_eq!(
             std::fs::read(path.to_fs_path(&workspace_root)).unwrap(),
             b"1".to_vec()
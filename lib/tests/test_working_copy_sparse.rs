@@ -14,9 +14,12 @@
 
 use itertools::Itertools;
 use jj_lib::matchers::EverythingMatcher;
+use jj_lib::protos::working_copy::TreeState;
 use jj_lib::repo::Repo;
 use jj_lib::repo_path::RepoPath;
-use jj_lib::working_copy::{CheckoutStats, WorkingCopy};
+use jj_lib::sparse::{SparsePath, SparsePattern};
+use jj_lib::working_copy::{CheckoutOptions, CheckoutStats, WorkingCopy};
+use prost::Message as _;
 use testutils::{create_tree, TestWorkspace};
 
 #[test]
@@ -49,13 +52,19 @@ fn test_sparse_checkout() {
     );
 
     let wc = test_workspace.workspace.working_copy_mut();
-    wc.check_out(repo.op_id().clone(), None, &tree).unwrap();
+    wc.check_out(
+        repo.op_id().clone(),
+        None,
+        &tree,
+        &CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
 
     // Set sparse patterns to only dir1/
     let mut locked_wc = wc.start_mutation().unwrap();
-    let sparse_patterns = vec![dir1_path];
+    let sparse_patterns = vec![SparsePattern::include_prefix(dir1_path)];
     let stats = locked_wc
-        .set_sparse_patterns(sparse_patterns.clone())
+        .set_sparse_patterns(sparse_patterns.clone(), &CheckoutOptions::empty_for_test())
         .unwrap();
     assert_eq!(
         stats,
@@ -97,9 +106,13 @@ fn test_sparse_checkout() {
 
     // Set sparse patterns to file2, dir1/subdir1/ and dir2/
     let mut locked_wc = wc.start_mutation().unwrap();
-    let sparse_patterns = vec![root_file1_path.clone(), dir1_subdir1_path, dir2_path];
+    let sparse_patterns = vec![
+        SparsePattern::include_prefix(root_file1_path.clone()),
+        SparsePattern::include_prefix(dir1_subdir1_path),
+        SparsePattern::include_prefix(dir2_path),
+    ];
     let stats = locked_wc
-        .set_sparse_patterns(sparse_patterns.clone())
+        .set_sparse_patterns(sparse_patterns.clone(), &CheckoutOptions::empty_for_test())
         .unwrap();
     assert_eq!(
         stats,
@@ -150,12 +163,20 @@ fn test_sparse_commit() {
     );
 
     let wc = test_workspace.workspace.working_copy_mut();
-    wc.check_out(repo.op_id().clone(), None, &tree).unwrap();
+    wc.check_out(
+        repo.op_id().clone(),
+        None,
+        &tree,
+        &CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
 
     // Set sparse patterns to only dir1/
     let mut locked_wc = wc.start_mutation().unwrap();
-    let sparse_patterns = vec![dir1_path.clone()];
-    locked_wc.set_sparse_patterns(sparse_patterns).unwrap();
+    let sparse_patterns = vec![SparsePattern::include_prefix(dir1_path.clone())];
+    locked_wc
+        .set_sparse_patterns(sparse_patterns, &CheckoutOptions::empty_for_test())
+        .unwrap();
     locked_wc.finish(repo.op_id().clone()).unwrap();
 
     // Write modified version of all files, including files that are not in the
@@ -175,8 +196,13 @@ fn test_sparse_commit() {
     // Set sparse patterns to also include dir2/
     let wc = test_workspace.workspace.working_copy_mut();
     let mut locked_wc = wc.start_mutation().unwrap();
-    let sparse_patterns = vec![dir1_path, dir2_path];
-    locked_wc.set_sparse_patterns(sparse_patterns).unwrap();
+    let sparse_patterns = vec![
+        SparsePattern::include_prefix(dir1_path),
+        SparsePattern::include_prefix(dir2_path),
+    ];
+    locked_wc
+        .set_sparse_patterns(sparse_patterns, &CheckoutOptions::empty_for_test())
+        .unwrap();
     locked_wc.finish(op_id).unwrap();
 
     // Create a tree from the working copy. Only dir1/file1 and dir2/file1 should be
@@ -204,8 +230,10 @@ fn test_sparse_commit_gitignore() {
 
     // Set sparse patterns to only dir1/
     let mut locked_wc = wc.start_mutation().unwrap();
-    let sparse_patterns = vec![dir1_path.clone()];
-    locked_wc.set_sparse_patterns(sparse_patterns).unwrap();
+    let sparse_patterns = vec![SparsePattern::include_prefix(dir1_path.clone())];
+    locked_wc
+        .set_sparse_patterns(sparse_patterns, &CheckoutOptions::empty_for_test())
+        .unwrap();
     locked_wc.finish(repo.op_id().clone()).unwrap();
 
     // Write dir1/file1 and dir1/file2 and a .gitignore saying to ignore dir1/file1
@@ -221,3 +249,103 @@ fn test_sparse_commit_gitignore() {
     assert_eq!(entries.len(), 1);
     assert_eq!(entries[0].0, dir1_file2_path);
 }
+
+#[test]
+fn test_sparse_checkout_globs() {
+    let settings = testutils::user_settings();
+    let mut test_workspace = TestWorkspace::init(&settings);
+    let repo = &test_workspace.repo;
+    let working_copy_path = test_workspace.workspace.workspace_root().clone();
+
+    let readme_path = RepoPath::from_internal_string("README");
+    let foo_src_path = RepoPath::from_internal_string("services/foo/src/main.rs");
+    let foo_testdata_path = RepoPath::from_internal_string("services/foo/src/testdata/input");
+    let foo_cargo_path = RepoPath::from_internal_string("services/foo/Cargo.toml");
+    let bar_src_path = RepoPath::from_internal_string("services/bar/src/main.rs");
+    let lib_src_path = RepoPath::from_internal_string("lib/src/lib.rs");
+
+    let tree = create_tree(
+        repo,
+        &[
+            (&readme_path, "contents"),
+            (&foo_src_path, "contents"),
+            (&foo_testdata_path, "contents"),
+            (&foo_cargo_path, "contents"),
+            (&bar_src_path, "contents"),
+            (&lib_src_path, "contents"),
+        ],
+    );
+
+    let wc = test_workspace.workspace.working_copy_mut();
+    wc.check_out(
+        repo.op_id().clone(),
+        None,
+        &tree,
+        &CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
+
+    // Include the src directories of all services, but not their test data
+    let mut locked_wc = wc.start_mutation().unwrap();
+    let sparse_patterns = vec![
+        SparsePattern::include_prefix(readme_path.clone()),
+        SparsePattern::Include(SparsePath::Glob {
+            dir: RepoPath::from_internal_string("services"),
+            pattern: glob::Pattern::new("*/src").unwrap(),
+        }),
+        SparsePattern::Exclude(SparsePath::Glob {
+            dir: RepoPath::root(),
+            pattern: glob::Pattern::new("**/testdata").unwrap(),
+        }),
+    ];
+    let stats = locked_wc
+        .set_sparse_patterns(sparse_patterns.clone(), &CheckoutOptions::empty_for_test())
+        .unwrap();
+    assert_eq!(
+        stats,
+        CheckoutStats {
+            updated_files: 0,
+            added_files: 0,
+            removed_files: 3
+        }
+    );
+    locked_wc.finish(repo.op_id().clone()).unwrap();
+    assert!(readme_path.to_fs_path(&working_copy_path).exists());
+    assert!(foo_src_path.to_fs_path(&working_copy_path).exists());
+    assert!(!foo_testdata_path.to_fs_path(&working_copy_path).exists());
+    assert!(!foo_cargo_path.to_fs_path(&working_copy_path).exists());
+    assert!(bar_src_path.to_fs_path(&working_copy_path).exists());
+    assert!(!lib_src_path.to_fs_path(&working_copy_path).exists());
+
+    // Reload the state to check that it was persisted
+    let wc = WorkingCopy::load(
+        repo.store().clone(),
+        wc.working_copy_path().to_path_buf(),
+        wc.state_path().to_path_buf(),
+    );
+    assert_eq!(
+        wc.sparse_patterns().unwrap().iter().sorted().collect_vec(),
+        sparse_patterns.iter().sorted().collect_vec()
+    );
+    assert_eq!(
+        wc.file_states().unwrap().keys().collect_vec(),
+        vec![&readme_path, &bar_src_path, &foo_src_path]
+    );
+
+    // Older versions only read the prefixes, which include the directories of
+    // the globs
+    let tree_state_bytes = std::fs::read(wc.state_path().join("tree_state")).unwrap();
+    let tree_state = TreeState::decode(tree_state_bytes.as_slice()).unwrap();
+    let proto_sparse_patterns = tree_state.sparse_patterns.unwrap();
+    assert_eq!(proto_sparse_patterns.prefixes, vec!["README", "services"]);
+    assert_eq!(proto_sparse_patterns.patterns.len(), 3);
+
+    // Files outside the sparse patterns aren't snapshotted
+    std::fs::write(foo_src_path.to_fs_path(&working_copy_path), "modified").unwrap();
+    testutils::write_working_copy_file(&working_copy_path, &foo_testdata_path, "modified");
+    testutils::write_working_copy_file(&working_copy_path, &lib_src_path, "modified");
+    let modified_tree = test_workspace.snapshot().unwrap();
+    let diff = tree.diff(&modified_tree, &EverythingMatcher).collect_vec();
+    assert_eq!(diff.len(), 1);
+    assert_eq!(diff[0].0, foo_src_path);
+}
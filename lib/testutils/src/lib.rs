@@ -111,14 +111,20 @@ impl TestRepo {
     }
 
     pub fn init_with_backend(backend: TestRepoBackend) -> Self {
-        let settings = user_settings();
+        Self::init_with_backend_and_settings(backend, &user_settings())
+    }
+
+    pub fn init_with_backend_and_settings(
+        backend: TestRepoBackend,
+        settings: &UserSettings,
+    ) -> Self {
         let temp_dir = new_temp_dir();
 
         let repo_dir = temp_dir.path().join("repo");
         fs::create_dir(&repo_dir).unwrap();
 
         let repo = ReadonlyRepo::init(
-            &settings,
+            settings,
             &repo_dir,
             |store_path| -> Result<Box<dyn Backend>, BackendInitError> {
                 backend.init_backend(store_path)
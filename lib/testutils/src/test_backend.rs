@@ -21,7 +21,7 @@ use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
 
 use jj_lib::backend::{
     make_root_commit, Backend, BackendError, BackendResult, ChangeId, Commit, CommitId, Conflict,
-    ConflictId, FileId, ObjectId, SymlinkId, Tree, TreeId,
+    ConflictId, FileId, ObjectId, SigningFn, SymlinkId, Tree, TreeId,
 };
 use jj_lib::repo_path::RepoPath;
 
@@ -266,7 +266,12 @@ impl Backend for TestBackend {
         }
     }
 
-    fn write_commit(&self, contents: Commit) -> BackendResult<(CommitId, Commit)> {
+    fn write_commit(
+        &self,
+        mut contents: Commit,
+        _sign_with: Option<&mut SigningFn>,
+    ) -> BackendResult<(CommitId, Commit)> {
+        contents.secure_sig = None;
         let id = CommitId::new(get_hash(&contents));
         self.locked_data()
             .commits